@@ -374,6 +374,18 @@ impl IngressPoolSelect for IngressPoolImpl {
             });
         collected
     }
+
+    fn select_validated_from_refs<'a>(
+        &self,
+        range: std::ops::RangeInclusive<Time>,
+        f: Box<dyn FnOnce(Vec<&IngressPoolObject>) -> Vec<SignedIngress> + 'a>,
+    ) -> Vec<SignedIngress> {
+        f(self
+            .validated()
+            .get_all_by_expiry_range(range)
+            .map(|x| &x.msg)
+            .collect())
+    }
 }
 
 impl IngressPoolThrottler for IngressPoolImpl {
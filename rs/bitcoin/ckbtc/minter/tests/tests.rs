@@ -3,7 +3,7 @@ use ic_base_types::CanisterId;
 use ic_btc_types::Network;
 use ic_ckbtc_minter::lifecycle::init::InitArgs as CkbtcMinterInitArgs;
 use ic_icrc1::Account;
-use ic_icrc1_ledger::InitArgs as LedgerInitArgs;
+use ic_icrc1_ledger::{InitArgs as LedgerInitArgs, LedgerArgument};
 use ic_state_machine_tests::StateMachine;
 use ic_test_utilities_load_wasm::load_wasm;
 use icp_ledger::ArchiveOptions;
@@ -51,9 +51,14 @@ fn install_ledger(env: &StateMachine) -> CanisterId {
             cycles_for_archive_creation: None,
             max_transactions_per_response: None,
         },
+        fee_collector_account: None,
     };
-    env.install_canister(ledger_wasm(), Encode!(&args).unwrap(), None)
-        .unwrap()
+    env.install_canister(
+        ledger_wasm(),
+        Encode!(&LedgerArgument::Init(args)).unwrap(),
+        None,
+    )
+    .unwrap()
 }
 
 fn install_minter(env: &StateMachine, ledger_id: CanisterId) -> CanisterId {
@@ -22,6 +22,7 @@ use ic_test_utilities::types::{
 use ic_types::{
     crypto::CryptoHash,
     messages::{CallbackId, Payload, RejectContext, Request, RequestOrResponse, Response},
+    time::CoarseTime,
     xnet::StreamHeader,
     CryptoHashOfPartialState, Cycles, Funds,
 };
@@ -177,6 +178,119 @@ fn canonical_encoding_request() {
     );
 }
 
+/// Canonical CBOR encoding (with certification versions 12 and above) of:
+///
+/// ```no_run
+/// RequestOrResponse::Request(
+///     Request {
+///         receiver: canister_test_id(1),
+///         sender: canister_test_id(2),
+///         sender_reply_callback: CallbackId::from(3),
+///         payment: Cycles::new(4),
+///         method_name: "test".to_string(),
+///         method_payload: vec![6],
+///         deadline: CoarseTime::from_secs_since_unix_epoch(1000),
+///     }
+/// )
+/// ```
+///
+/// Expected:
+///
+/// ```text
+/// A1                            # map(1)
+///    00                         # field_index(RequestOrResponse::request)
+///    A7                         # map(7)
+///       00                      # field_index(Request::receiver)
+///       4A                      # bytes(10)
+///          00000000000000010101 # "\x00\x00\x00\x00\x00\x00\x00\x01\x01\x01"
+///       01                      # field_index(Request::sender)
+///       4A                      # bytes(10)
+///          00000000000000020101 # "\x00\x00\x00\x00\x00\x00\x00\x02\x01\x01"
+///       02                      # field_index(Request::sender_reply_callback)
+///       03                      # unsigned(3)
+///       03                      # field_index(Request::payment)
+///       A1                      # map(1)
+///          00                   # field_index(Funds::cycles)
+///          A1                   # map(1)
+///             00                # field_index(Cycles::raw)
+///             04                # unsigned(4)
+///       04                      # field_index(Request::method_name)
+///       64                      # text(4)
+///          74657374             # "test"
+///       05                      # field_index(Request::method_payload)
+///       41                      # bytes(1)
+///          06                   # "\x06"
+///       07                      # field_index(Request::deadline)
+///       19 03E8                 # unsigned(1000)
+/// Used http://cbor.me/ for printing the human friendly output.
+/// ```
+#[test]
+fn canonical_encoding_best_effort_request_v12_plus() {
+    for certification_version in
+        all_supported_versions().filter(|v| v >= &CertificationVersion::V12)
+    {
+        assert_eq!(
+            "A1 00 A7 00 4A 00 00 00 00 00 00 00 01 01 01 01 4A 00 00 00 00 00 00 00 02 01 01 02 03 03 A1 00 A1 00 04 04 64 74 65 73 74 05 41 06 07 19 03 E8",
+            as_hex(&encode_message(&best_effort_request(), certification_version))
+        );
+    }
+}
+
+/// Canonical CBOR encoding (with certification versions below 12) of the same
+/// best-effort request as above: the deadline is not encoded.
+///
+/// Expected:
+///
+/// ```text
+/// A1                            # map(1)
+///    00                         # field_index(RequestOrResponse::request)
+///    A6                         # map(6)
+///       00                      # field_index(Request::receiver)
+///       4A                      # bytes(10)
+///          00000000000000010101 # "\x00\x00\x00\x00\x00\x00\x00\x01\x01\x01"
+///       01                      # field_index(Request::sender)
+///       4A                      # bytes(10)
+///          00000000000000020101 # "\x00\x00\x00\x00\x00\x00\x00\x02\x01\x01"
+///       02                      # field_index(Request::sender_reply_callback)
+///       03                      # unsigned(3)
+///       03                      # field_index(Request::payment)
+///       A1                      # map(1)
+///          00                   # field_index(Funds::cycles)
+///          A1                   # map(1)
+///             00                # field_index(Cycles::raw)
+///             04                # unsigned(4)
+///       04                      # field_index(Request::method_name)
+///       64                      # text(4)
+///          74657374             # "test"
+///       05                      # field_index(Request::method_payload)
+///       41                      # bytes(1)
+///          06                   # "\x06"
+/// Used http://cbor.me/ for printing the human friendly output.
+/// ```
+#[test]
+fn canonical_encoding_best_effort_request_before_v12() {
+    for certification_version in all_supported_versions().filter(|v| v < &CertificationVersion::V12)
+    {
+        assert_eq!(
+            "A1 00 A6 00 4A 00 00 00 00 00 00 00 01 01 01 01 4A 00 00 00 00 00 00 00 02 01 01 02 03 03 A1 00 A1 00 04 04 64 74 65 73 74 05 41 06",
+            as_hex(&encode_message(&best_effort_request(), certification_version))
+        );
+    }
+}
+
+fn best_effort_request() -> RequestOrResponse {
+    RequestBuilder::new()
+        .receiver(canister_test_id(1))
+        .sender(canister_test_id(2))
+        .sender_reply_callback(CallbackId::from(3))
+        .payment(Cycles::new(4))
+        .method_name("test".to_string())
+        .method_payload(vec![6])
+        .deadline(CoarseTime::from_secs_since_unix_epoch(1000))
+        .build()
+        .into()
+}
+
 /// Canonical CBOR encoding of:
 ///
 /// ```no_run
@@ -13,7 +13,7 @@
 use crate::CertificationVersion;
 use ic_error_types::TryFromError;
 use ic_protobuf::proxy::ProxyDecodeError;
-use ic_types::xnet::StreamIndex;
+use ic_types::{messages::NO_DEADLINE, time::CoarseTime, xnet::StreamIndex};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::VecDeque,
@@ -65,6 +65,11 @@ pub struct Request {
     pub method_payload: Bytes,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cycles_payment: Option<Cycles>,
+    /// Only encoded for best-effort requests and certification versions
+    /// `>= V12`, so that the encoding of guaranteed response requests is
+    /// unchanged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<u32>,
 }
 
 /// Canonical representation of `ic_types::messages::Response`.
@@ -80,6 +85,10 @@ pub struct Response {
     pub response_payload: Payload,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cycles_refund: Option<Cycles>,
+    /// Only encoded for best-effort responses and certification versions
+    /// `>= V12`, so that the encoding of guaranteed responses is unchanged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<u32>,
 }
 
 /// Canonical representation of `ic_types::funds::Cycles`.
@@ -247,6 +256,9 @@ impl From<(&ic_types::messages::Request, CertificationVersion)> for Request {
             method_name: request.method_name.clone(),
             method_payload: request.method_payload.clone(),
             cycles_payment: None,
+            deadline: (request.is_best_effort()
+                && certification_version >= CertificationVersion::V12)
+                .then(|| request.deadline.as_secs_since_unix_epoch()),
         }
     }
 }
@@ -268,6 +280,9 @@ impl TryFrom<Request> for ic_types::messages::Request {
             payment,
             method_name: request.method_name,
             method_payload: request.method_payload,
+            deadline: request
+                .deadline
+                .map_or(NO_DEADLINE, CoarseTime::from_secs_since_unix_epoch),
         })
     }
 }
@@ -287,6 +302,9 @@ impl From<(&ic_types::messages::Response, CertificationVersion)> for Response {
             refund: funds,
             response_payload: (&response.response_payload, certification_version).into(),
             cycles_refund: None,
+            deadline: (response.is_best_effort()
+                && certification_version >= CertificationVersion::V12)
+                .then(|| response.deadline.as_secs_since_unix_epoch()),
         }
     }
 }
@@ -307,6 +325,9 @@ impl TryFrom<Response> for ic_types::messages::Response {
             originator_reply_callback: response.originator_reply_callback.into(),
             refund,
             response_payload: response.response_payload.try_into()?,
+            deadline: response
+                .deadline
+                .map_or(NO_DEADLINE, CoarseTime::from_secs_since_unix_epoch),
         })
     }
 }
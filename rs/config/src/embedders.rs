@@ -45,6 +45,13 @@ pub struct FeatureFlags {
     pub new_wasm_transform_lib: FlagStatus,
     /// Track dirty pages with a write barrier instead of the signal handler.
     pub write_barrier: FlagStatus,
+    /// Count the instructions executed by every Wasm function in a dedicated
+    /// global. Only supported by the new instrumentation and only meant for
+    /// profiling canisters in tests.
+    pub instruction_profiling: FlagStatus,
+    /// Allow canisters to make best-effort calls via
+    /// `ic0.call_with_best_effort_response`.
+    pub best_effort_responses: FlagStatus,
 }
 
 impl Default for FeatureFlags {
@@ -53,6 +60,8 @@ impl Default for FeatureFlags {
             rate_limiting_of_debug_prints: FlagStatus::Enabled,
             new_wasm_transform_lib: FlagStatus::Enabled,
             write_barrier: FlagStatus::Disabled,
+            instruction_profiling: FlagStatus::Disabled,
+            best_effort_responses: FlagStatus::Disabled,
         }
     }
 }
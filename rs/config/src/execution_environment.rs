@@ -24,6 +24,12 @@ const GB: u64 = 1024 * 1024 * 1024;
 /// canister's data and the deltas.
 const SUBNET_MEMORY_CAPACITY: NumBytes = NumBytes::new(450 * GB);
 
+/// Once the memory usage of the subnet exceeds this threshold, canisters that
+/// allocate more memory have to reserve cycles for the newly allocated
+/// storage. The amount of reserved cycles grows linearly from zero at the
+/// threshold to the full reservation at `SUBNET_MEMORY_CAPACITY`.
+const SUBNET_MEMORY_THRESHOLD: NumBytes = NumBytes::new(300 * GB);
+
 /// This is the upper limit on how much memory can be used by all canister
 /// messages on a given subnet.
 ///
@@ -31,6 +37,14 @@ const SUBNET_MEMORY_CAPACITY: NumBytes = NumBytes::new(450 * GB);
 /// responses; plus the maximum allowed response size per queue reservation.
 const SUBNET_MESSAGE_MEMORY_CAPACITY: NumBytes = NumBytes::new(25 * GB);
 
+/// This is the upper limit on how much memory can be used by best-effort
+/// canister messages on a given subnet. Above it, the largest best-effort
+/// messages are dropped.
+///
+/// Best-effort message memory usage is calculated as the total size of enqueued
+/// best-effort requests and responses.
+const BEST_EFFORT_MESSAGE_MEMORY_CAPACITY: NumBytes = NumBytes::new(5 * GB);
+
 /// This is the upper limit on how much memory can be used by the ingress
 /// history on a given subnet. It is lower than the subnet messsage memory
 /// capacity because here we count actual memory consumption as opposed to
@@ -59,6 +73,11 @@ pub(crate) const MAX_INSTRUCTIONS_PER_COMPOSITE_QUERY_CALL: u64 = 5_000_000_000;
 /// This would allow 100 calls with the current MAX_INSTRUCTIONS_PER_COMPOSITE_QUERY_CALL
 pub const INSTRUCTION_OVERHEAD_PER_QUERY_CALL: u64 = 50_000_000;
 
+/// The number of certified heights over which each replica collects query
+/// statistics before including them in a block. With one block per second this
+/// amounts to roughly ten minutes.
+pub const QUERY_STATS_EPOCH_LENGTH: u64 = 600;
+
 // The ID of the Bitcoin testnet canister.
 const BITCOIN_TESTNET_CANISTER_ID: &str = "g4xu7-jiaaa-aaaan-aaaaq-cai";
 
@@ -87,10 +106,18 @@ pub struct Config {
     /// the subnet.
     pub subnet_memory_capacity: NumBytes,
 
+    /// The subnet memory usage above which allocating memory requires
+    /// reserving cycles.
+    pub subnet_memory_threshold: NumBytes,
+
     /// The maximum amount of logical storage available to canister messages
     /// across the whole subnet.
     pub subnet_message_memory_capacity: NumBytes,
 
+    /// The maximum amount of logical storage available to best-effort canister
+    /// messages across the whole subnet.
+    pub best_effort_message_memory_capacity: NumBytes,
+
     /// The maximum amount of logical storage available to the ingress history
     /// across the whole subnet.
     pub ingress_history_memory_capacity: NumBytes,
@@ -155,6 +182,22 @@ pub struct Config {
 
     /// Indicates whether composite queries are available or not.
     pub composite_queries: FlagStatus,
+
+    /// The length of a query stats epoch in certified heights. Must be the
+    /// same on all replicas of a subnet, as it is used to validate blocks.
+    pub query_stats_epoch_length: u64,
+
+    /// If this flag is enabled, then canister modules are instrumented to count
+    /// the instructions executed by every Wasm function. Only meant for
+    /// profiling canisters in tests.
+    pub instruction_profiling: FlagStatus,
+
+    /// If this flag is enabled, then canisters can make best-effort calls via
+    /// `ic0.call_with_best_effort_response`. Must stay disabled until the
+    /// current certification version encodes the deadlines of best-effort
+    /// messages in streams, or else best-effort requests would reach other
+    /// subnets as guaranteed-response requests.
+    pub best_effort_responses: FlagStatus,
 }
 
 impl Default for Config {
@@ -173,7 +216,9 @@ impl Default for Config {
             create_funds_whitelist: String::default(),
             max_instructions_for_message_acceptance_calls: MAX_INSTRUCTIONS_PER_MESSAGE_WITHOUT_DTS,
             subnet_memory_capacity: SUBNET_MEMORY_CAPACITY,
+            subnet_memory_threshold: SUBNET_MEMORY_THRESHOLD,
             subnet_message_memory_capacity: SUBNET_MESSAGE_MEMORY_CAPACITY,
+            best_effort_message_memory_capacity: BEST_EFFORT_MESSAGE_MEMORY_CAPACITY,
             ingress_history_memory_capacity: INGRESS_HISTORY_MEMORY_CAPACITY,
             max_canister_memory_size: NumBytes::new(
                 MAX_STABLE_MEMORY_IN_BYTES + MAX_WASM_MEMORY_IN_BYTES,
@@ -211,6 +256,9 @@ impl Default for Config {
                 mainnet_canister_id: Some(bitcoin_mainnet_canister_id),
             },
             composite_queries: FlagStatus::Disabled,
+            query_stats_epoch_length: QUERY_STATS_EPOCH_LENGTH,
+            instruction_profiling: FlagStatus::Disabled,
+            best_effort_responses: FlagStatus::Disabled,
         }
     }
 }
@@ -121,6 +121,10 @@ pub const ECDSA_SIGNATURE_FEE: Cycles = Cycles::new(10 * B as u128);
 /// IMPORTANT: never set this value to zero.
 const DEFAULT_REFERENCE_SUBNET_SIZE: usize = 13;
 
+/// Storage allocated while the subnet memory usage is above the reservation
+/// threshold is prepaid for 30 days.
+const STORAGE_RESERVATION_DURATION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
 /// Costs for each newly created dirty page in stable memory.
 const DEFAULT_DIRTY_PAGE_OVERHEAD: NumInstructions = NumInstructions::new(1_000);
 const SYSTEM_SUBNET_DIRTY_PAGE_OVERHEAD: NumInstructions = NumInstructions::new(0);
@@ -341,6 +345,12 @@ pub struct CyclesAccountManagerConfig {
     /// How often to charge canisters for memory and compute allocations.
     pub duration_between_allocation_charges: Duration,
 
+    /// For how long the storage of newly allocated memory is prepaid when the
+    /// memory is allocated while the subnet memory usage is above the
+    /// reservation threshold. The prepaid cycles are moved to the reserved
+    /// balance of the canister.
+    pub storage_reservation_duration: Duration,
+
     /// Amount to charge for an ECDSA signature.
     pub ecdsa_signature_fee: Cycles,
 
@@ -374,6 +384,7 @@ impl CyclesAccountManagerConfig {
             // 4 SDR per GiB per year => 4e12 Cycles per year
             gib_storage_per_second_fee: Cycles::new(127_000),
             duration_between_allocation_charges: Duration::from_secs(10),
+            storage_reservation_duration: STORAGE_RESERVATION_DURATION,
             ecdsa_signature_fee: ECDSA_SIGNATURE_FEE,
             http_request_baseline_fee: Cycles::new(400_000_000),
             http_request_per_byte_fee: Cycles::new(100_000),
@@ -394,6 +405,7 @@ impl CyclesAccountManagerConfig {
             ingress_byte_reception_fee: Cycles::new(0),
             gib_storage_per_second_fee: Cycles::new(0),
             duration_between_allocation_charges: Duration::from_secs(10),
+            storage_reservation_duration: STORAGE_RESERVATION_DURATION,
             /// The ECDSA signature fee is the fee charged when creating a
             /// signature on this subnet. The request likely came from a
             /// different subnet which is not a system subnet. There is an
@@ -29,7 +29,7 @@ mod proptests;
 pub use block_maker::SubnetRecords;
 pub use crypto::ConsensusCrypto;
 pub use membership::Membership;
-pub use metrics::ValidatorMetrics;
+pub use metrics::{ConsensusTimeline, ValidatorMetrics, CONSENSUS_TIMELINE_CAPACITY};
 
 #[cfg(test)]
 pub(crate) mod mocks;
@@ -61,6 +61,7 @@ use ic_interfaces::{
     ecdsa::EcdsaPool,
     ingress_manager::IngressSelector,
     messaging::{MessageRouting, XNetPayloadBuilder},
+    query_stats::QueryStatsPayloadBuilder,
     self_validating_payload::SelfValidatingPayloadBuilder,
     time_source::TimeSource,
 };
@@ -114,9 +115,11 @@ pub struct ConsensusImpl {
     aggregator: ShareAggregator,
     purger: Purger,
     metrics: ConsensusMetrics,
+    timeline: Arc<ConsensusTimeline>,
     time_source: Arc<dyn TimeSource>,
     registry_client: Arc<dyn RegistryClient>,
     state_manager: Arc<dyn StateManager<State = ReplicatedState>>,
+    message_routing: Arc<dyn MessageRouting>,
     dkg_key_manager: Arc<Mutex<DkgKeyManager>>,
     last_invoked: RefCell<BTreeMap<ConsensusSubcomponent, Time>>,
     schedule: RoundRobin,
@@ -141,6 +144,7 @@ impl ConsensusImpl {
         xnet_payload_builder: Arc<dyn XNetPayloadBuilder>,
         self_validating_payload_builder: Arc<dyn SelfValidatingPayloadBuilder>,
         canister_http_payload_builder: Arc<dyn CanisterHttpPayloadBuilder>,
+        query_stats_payload_builder: Arc<dyn QueryStatsPayloadBuilder>,
         dkg_pool: Arc<RwLock<dyn DkgPool>>,
         ecdsa_pool: Arc<RwLock<dyn EcdsaPool>>,
         dkg_key_manager: Arc<Mutex<DkgKeyManager>>,
@@ -152,6 +156,7 @@ impl ConsensusImpl {
         metrics_registry: MetricsRegistry,
         logger: ReplicaLogger,
         local_store_time_reader: Option<Arc<dyn LocalStoreCertifiedTimeReader>>,
+        timeline: Arc<ConsensusTimeline>,
     ) -> Self {
         let payload_builder = Arc::new(PayloadBuilderImpl::new(
             replica_config.subnet_id,
@@ -160,6 +165,7 @@ impl ConsensusImpl {
             xnet_payload_builder,
             self_validating_payload_builder,
             canister_http_payload_builder,
+            query_stats_payload_builder,
             metrics_registry.clone(),
             logger.clone(),
         ));
@@ -253,15 +259,17 @@ impl ConsensusImpl {
             ),
             purger: Purger::new(
                 state_manager.clone(),
-                message_routing,
+                message_routing.clone(),
                 logger.clone(),
                 metrics_registry.clone(),
             ),
             metrics: ConsensusMetrics::new(metrics_registry),
+            timeline,
             log: logger,
             time_source,
             registry_client,
             state_manager,
+            message_routing,
             malicious_flags,
             replica_config,
             last_invoked: RefCell::new(last_invoked),
@@ -501,6 +509,18 @@ impl Consensus for ConsensusImpl {
 
         let changeset = self.schedule.call_next(&calls);
 
+        self.timeline.observe_progress(
+            &pool_reader,
+            self.state_manager.latest_certified_height(),
+            Height::from(
+                self.message_routing
+                    .expected_batch_height()
+                    .get()
+                    .saturating_sub(1),
+            ),
+            self.time_source.get_relative_time(),
+        );
+
         if let Some(settings) = get_notarization_delay_settings(
             &self.log,
             &*self.registry_client,
@@ -626,6 +646,7 @@ pub fn setup(
     xnet_payload_builder: Arc<dyn XNetPayloadBuilder>,
     self_validating_payload_builder: Arc<dyn SelfValidatingPayloadBuilder>,
     canister_http_payload_builder: Arc<dyn CanisterHttpPayloadBuilder>,
+    query_stats_payload_builder: Arc<dyn QueryStatsPayloadBuilder>,
     dkg_pool: Arc<RwLock<dyn DkgPool>>,
     ecdsa_pool: Arc<RwLock<dyn EcdsaPool>>,
     dkg_key_manager: Arc<Mutex<DkgKeyManager>>,
@@ -637,6 +658,7 @@ pub fn setup(
     logger: ReplicaLogger,
     local_store_time_reader: Option<Arc<dyn LocalStoreCertifiedTimeReader>>,
     registry_poll_delay_duration_ms: u64,
+    timeline: Arc<ConsensusTimeline>,
 ) -> (ConsensusImpl, ConsensusGossipImpl) {
     // Currently, the orchestrator polls the registry every
     // `registry_poll_delay_duration_ms` and writes new updates into the
@@ -660,6 +682,7 @@ pub fn setup(
             xnet_payload_builder,
             self_validating_payload_builder,
             canister_http_payload_builder,
+            query_stats_payload_builder,
             dkg_pool,
             ecdsa_pool,
             dkg_key_manager,
@@ -671,6 +694,7 @@ pub fn setup(
             metrics_registry.clone(),
             logger,
             local_store_time_reader,
+            timeline,
         ),
         ConsensusGossipImpl::new(message_routing, metrics_registry),
     )
@@ -689,6 +713,7 @@ mod tests {
         canister_http::FakeCanisterHttpPayloadBuilder,
         ingress_selector::FakeIngressSelector,
         message_routing::FakeMessageRouting,
+        query_stats::FakeQueryStatsPayloadBuilder,
         self_validating_payload_builder::FakeSelfValidatingPayloadBuilder,
         types::ids::{node_test_id, subnet_test_id},
         xnet_payload_builder::FakeXNetPayloadBuilder,
@@ -752,6 +777,7 @@ mod tests {
             Arc::new(FakeXNetPayloadBuilder::new()),
             Arc::new(FakeSelfValidatingPayloadBuilder::new()),
             Arc::new(FakeCanisterHttpPayloadBuilder::new()),
+            Arc::new(FakeQueryStatsPayloadBuilder::new()),
             dkg_pool,
             ecdsa_pool,
             Arc::new(Mutex::new(DkgKeyManager::new(
@@ -770,6 +796,7 @@ mod tests {
             Some(Arc::new(FakeLocalStoreCertifiedTimeReader::new(
                 time_source.clone(),
             ))),
+            Arc::new(ConsensusTimeline::new(CONSENSUS_TIMELINE_CAPACITY)),
         );
         (consensus_impl, Box::new(pool), time_source)
     }
@@ -22,7 +22,7 @@ use ic_types::{
     canister_http::*,
     consensus::ecdsa::{CompletedSignature, EcdsaBlockReader},
     crypto::threshold_sig::ni_dkg::{NiDkgId, NiDkgTag, NiDkgTranscript},
-    messages::{CallbackId, Response},
+    messages::{CallbackId, Response, NO_DEADLINE},
     ReplicaVersion,
 };
 use std::collections::BTreeMap;
@@ -247,6 +247,7 @@ pub fn generate_execution_responses_for_canister_http_responses(
                         ic_types::messages::Payload::Reject((canister_http_reject).into())
                     }
                 },
+                deadline: NO_DEADLINE,
             }
         })
         // Deliver timeout responses
@@ -265,6 +266,7 @@ pub fn generate_execution_responses_for_canister_http_responses(
                             message: "Canister http request timed out".to_string(),
                         },
                     ),
+                    deadline: NO_DEADLINE,
                 }),
         )
         .chain(
@@ -283,6 +285,7 @@ pub fn generate_execution_responses_for_canister_http_responses(
                                 message: "Canister http responses were different across replicas, and no consensus was reached".to_string(),
                             },
                         ),
+                        deadline: NO_DEADLINE,
                     })
                 }),
         )
@@ -354,6 +357,7 @@ pub fn generate_responses_to_setup_initial_dkg_calls(
                 originator_reply_callback: callback_id,
                 refund: Cycles::zero(),
                 response_payload,
+                deadline: NO_DEADLINE,
             });
         }
     }
@@ -137,15 +137,7 @@ impl BlockMaker {
             Ok(Some(rank)) => {
                 if !already_proposed(pool, height, my_node_id)
                     && !self.is_better_block_proposal_available(pool, height, rank)
-                    && is_time_to_make_block(
-                        &self.log,
-                        self.registry_client.as_ref(),
-                        self.replica_config.subnet_id,
-                        pool,
-                        height,
-                        rank,
-                        self.time_source.as_ref(),
-                    )
+                    && self.is_time_to_make_block(pool, height, rank)
                 {
                     self.propose_block(pool, rank, parent).map(|proposal| {
                         debug!(
@@ -176,6 +168,34 @@ impl BlockMaker {
         }
     }
 
+    /// Return true if the time since round start is greater than the block
+    /// maker delay for the given rank, adapted to the recently observed
+    /// finalization latency if the subnet enables the adaptive notary delay.
+    fn is_time_to_make_block(&self, pool: &PoolReader<'_>, height: Height, rank: Rank) -> bool {
+        let settings = match pool.registry_version(height).and_then(|registry_version| {
+            get_notarization_delay_settings(
+                &self.log,
+                self.registry_client.as_ref(),
+                self.replica_config.subnet_id,
+                registry_version,
+            )
+        }) {
+            Some(settings) => settings,
+            None => return false,
+        };
+        let finalization_latency = settings
+            .adaptive_notary_delay_bounds
+            .and_then(|_| get_recent_finalization_latency(pool, FINALIZATION_LATENCY_WINDOW));
+        let block_maker_delay =
+            get_adaptive_block_maker_delay(&settings, finalization_latency, rank);
+        match pool.get_round_start_time(height) {
+            Some(start_time) => {
+                self.time_source.get_relative_time() >= start_time + block_maker_delay
+            }
+            None => false,
+        }
+    }
+
     /// Return true if the validated pool contains a better (lower ranked) block
     /// proposal than the given rank, for the given height.
     fn is_better_block_proposal_available(
@@ -1,4 +1,5 @@
 use crate::consensus::{pool_reader::PoolReader, utils::get_block_hash_string};
+use ic_interfaces::consensus::{ConsensusTimelineReader, HeightTimeline};
 use ic_metrics::{
     buckets::{decimal_buckets, decimal_buckets_with_zero, linear_buckets},
     MetricsRegistry,
@@ -9,11 +10,12 @@ use ic_types::{
         ecdsa::{CompletedReshareRequest, CompletedSignature, EcdsaPayload, KeyTranscriptCreation},
         Block, BlockProposal, ConsensusMessageHashable, HasHeight, HasRank,
     },
-    CountBytes,
+    CountBytes, Height, Time,
 };
 use prometheus::{
-    GaugeVec, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+    Gauge, GaugeVec, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
 };
+use std::collections::BTreeMap;
 use std::sync::RwLock;
 
 // For certain metrics, we record metrics based on block's rank.
@@ -357,6 +359,7 @@ impl FinalizerMetrics {
 
 pub struct NotaryMetrics {
     pub time_to_notary_sign: HistogramVec,
+    pub adaptive_initial_notary_delay: Gauge,
 }
 
 impl NotaryMetrics {
@@ -368,6 +371,10 @@ impl NotaryMetrics {
                 vec![0.0, 0.2, 0.4, 0.6, 0.8, 1.0, 1.2, 1.4, 1.6, 1.8, 2.0, 2.2, 2.4, 2.6, 2.8, 3.0, 3.5, 4.0, 4.5, 5.0, 6.0, 8.0, 10.0, 15.0, 20.0],
                 &["rank"],
             ),
+            adaptive_initial_notary_delay: metrics_registry.gauge(
+                "consensus_adaptive_initial_notary_delay_seconds",
+                "The initial notary delay derived from the observed finalization latency",
+            ),
         }
     }
 
@@ -913,3 +920,186 @@ impl EcdsaSignatureMetrics {
         }
     }
 }
+
+/// The number of heights for which the consensus timeline is kept by default.
+pub const CONSENSUS_TIMELINE_CAPACITY: usize = 1000;
+
+/// A consensus phase whose completion is recorded in the [`ConsensusTimeline`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimelinePhase {
+    BlockProposal,
+    Notarization,
+    Finalization,
+    Certification,
+    BatchDelivery,
+}
+
+const TIMELINE_PHASES: usize = 5;
+
+impl TimelinePhase {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn field(self, timeline: &mut HeightTimeline) -> &mut Option<Time> {
+        match self {
+            TimelinePhase::BlockProposal => &mut timeline.block_proposal,
+            TimelinePhase::Notarization => &mut timeline.notarization,
+            TimelinePhase::Finalization => &mut timeline.finalization,
+            TimelinePhase::Certification => &mut timeline.certification,
+            TimelinePhase::BatchDelivery => &mut timeline.batch_delivery,
+        }
+    }
+}
+
+#[derive(Default)]
+struct TimelineState {
+    /// The highest height observed so far for each phase.
+    last_observed: [Height; TIMELINE_PHASES],
+    timelines: BTreeMap<Height, HeightTimeline>,
+}
+
+/// Records the time at which this replica observed the completion of each
+/// consensus phase, for the last `capacity` heights.
+pub struct ConsensusTimeline {
+    capacity: usize,
+    state: RwLock<TimelineState>,
+}
+
+impl ConsensusTimeline {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: RwLock::new(TimelineState::default()),
+        }
+    }
+
+    /// Record that `phase` has been completed up to and including `height` at
+    /// the given time. The time is only recorded for `height` itself, and only
+    /// if the phase has not been observed at a height at least as high before.
+    /// Heights that were skipped, e.g. on the first observation after a restart
+    /// or after catching up, are not back-filled, as `time` says nothing about
+    /// when they were completed.
+    pub fn observe(&self, phase: TimelinePhase, height: Height, time: Time) {
+        let mut state = self.state.write().unwrap();
+        if height <= state.last_observed[phase.index()] {
+            return;
+        }
+        state.last_observed[phase.index()] = height;
+        let timeline = state
+            .timelines
+            .entry(height)
+            .or_insert_with(|| HeightTimeline {
+                height,
+                ..HeightTimeline::default()
+            });
+        phase.field(timeline).get_or_insert(time);
+        while state.timelines.len() > self.capacity {
+            let lowest = *state.timelines.keys().next().unwrap();
+            state.timelines.remove(&lowest);
+        }
+    }
+
+    /// Record all phases that progressed according to the given pool and the
+    /// given certified and delivered heights.
+    pub fn observe_progress(
+        &self,
+        pool: &PoolReader<'_>,
+        certified_height: Height,
+        delivered_batch_height: Height,
+        time: Time,
+    ) {
+        if let Some(height) = pool.pool().validated().block_proposal().max_height() {
+            self.observe(TimelinePhase::BlockProposal, height, time);
+        }
+        self.observe(
+            TimelinePhase::Notarization,
+            pool.get_notarized_height(),
+            time,
+        );
+        self.observe(
+            TimelinePhase::Finalization,
+            pool.get_finalized_height(),
+            time,
+        );
+        self.observe(TimelinePhase::Certification, certified_height, time);
+        self.observe(TimelinePhase::BatchDelivery, delivered_batch_height, time);
+    }
+}
+
+impl ConsensusTimelineReader for ConsensusTimeline {
+    fn get_latest_timelines(&self, n: usize) -> Vec<HeightTimeline> {
+        let state = self.state.read().unwrap();
+        let mut timelines: Vec<_> = state.timelines.values().rev().take(n).cloned().collect();
+        timelines.reverse();
+        timelines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_test_utilities::mock_time;
+    use std::time::Duration;
+
+    #[test]
+    fn test_consensus_timeline_records_first_observation() {
+        let timeline = ConsensusTimeline::new(10);
+        let t0 = mock_time();
+        let t1 = t0 + Duration::from_secs(1);
+
+        timeline.observe(TimelinePhase::Finalization, Height::from(2), t0);
+        timeline.observe(TimelinePhase::Finalization, Height::from(2), t1);
+        timeline.observe(TimelinePhase::Finalization, Height::from(3), t1);
+        timeline.observe(TimelinePhase::Certification, Height::from(1), t1);
+        timeline.observe(TimelinePhase::Certification, Height::from(2), t1);
+
+        let timelines = timeline.get_latest_timelines(10);
+        assert_eq!(timelines.len(), 3);
+        assert_eq!(timelines[0].height, Height::from(1));
+        assert_eq!(timelines[0].finalization, None);
+        assert_eq!(timelines[0].certification, Some(t1));
+        assert_eq!(timelines[1].finalization, Some(t0));
+        assert_eq!(timelines[1].certification, Some(t1));
+        assert_eq!(timelines[2].finalization, Some(t1));
+        assert_eq!(timelines[2].certification, None);
+    }
+
+    /// After a restart or catch-up, the heights below the first observed one
+    /// must not be back-filled with the time of the observation.
+    #[test]
+    fn test_consensus_timeline_does_not_back_fill_skipped_heights() {
+        let timeline = ConsensusTimeline::new(1000);
+        let t0 = mock_time();
+        let t1 = t0 + Duration::from_secs(1);
+
+        timeline.observe(TimelinePhase::Notarization, Height::from(500), t0);
+        timeline.observe(TimelinePhase::Notarization, Height::from(510), t1);
+
+        let timelines = timeline.get_latest_timelines(1000);
+        let heights: Vec<_> = timelines.iter().map(|t| t.height.get()).collect();
+        assert_eq!(heights, vec![500, 510]);
+        assert_eq!(timelines[0].notarization, Some(t0));
+        assert_eq!(timelines[1].notarization, Some(t1));
+    }
+
+    #[test]
+    fn test_consensus_timeline_is_bounded() {
+        let timeline = ConsensusTimeline::new(5);
+        let time = mock_time();
+
+        for h in 1..=10 {
+            timeline.observe(TimelinePhase::Notarization, Height::from(h), time);
+        }
+        timeline.observe(TimelinePhase::BatchDelivery, Height::from(3), time);
+
+        let timelines = timeline.get_latest_timelines(3);
+        let heights: Vec<_> = timelines.iter().map(|t| t.height.get()).collect();
+        assert_eq!(heights, vec![8, 9, 10]);
+        assert_eq!(timeline.get_latest_timelines(100).len(), 5);
+        assert!(timeline
+            .get_latest_timelines(100)
+            .iter()
+            .all(|t| t.batch_delivery.is_none()));
+    }
+}
@@ -28,7 +28,11 @@ use crate::consensus::{
     metrics::NotaryMetrics,
     pool_reader::PoolReader,
     prelude::*,
-    utils::{find_lowest_ranked_proposals, get_adjusted_notary_delay},
+    utils::{
+        find_lowest_ranked_proposals, get_adaptive_initial_notary_delay,
+        get_adjusted_notary_delay_from_settings, get_notarization_delay_settings,
+        get_recent_finalization_latency, FINALIZATION_LATENCY_WINDOW,
+    },
     ConsensusCrypto,
 };
 use ic_interfaces::time_source::TimeSource;
@@ -105,14 +109,32 @@ impl Notary {
         height: Height,
         rank: Rank,
     ) -> Option<std::time::Duration> {
-        let adjusted_notary_delay = get_adjusted_notary_delay(
-            self.membership.as_ref(),
+        let mut settings = get_notarization_delay_settings(
+            &self.log,
+            &*self.membership.registry_client,
+            self.membership.subnet_id,
+            pool.registry_version(height)?,
+        )?;
+        // The notary delay only affects when this node issues its own shares, so
+        // adapting it does not change which blocks other nodes consider valid.
+        if let Some(bounds) = settings.adaptive_notary_delay_bounds {
+            // Same estimate as the block maker uses, see
+            // `BlockMaker::is_time_to_make_block`.
+            if let Some(latency) =
+                get_recent_finalization_latency(pool, FINALIZATION_LATENCY_WINDOW)
+            {
+                settings.initial_notary_delay = get_adaptive_initial_notary_delay(latency, &bounds);
+                self.metrics
+                    .adaptive_initial_notary_delay
+                    .set(settings.initial_notary_delay.as_secs_f64());
+            }
+        }
+        let adjusted_notary_delay = get_adjusted_notary_delay_from_settings(
+            settings,
             pool,
             self.state_manager.as_ref(),
-            &self.log,
-            height,
             rank,
-        )?;
+        );
         if let Some(start_time) = pool.get_round_start_time(height) {
             let now = self.time_source.get_relative_time();
             if now >= start_time + adjusted_notary_delay {
@@ -230,6 +252,7 @@ mod tests {
     //! Notary unit tests
     use super::*;
     use crate::consensus::mocks::{dependencies_with_subnet_params, Dependencies};
+    use crate::consensus::utils::get_adjusted_notary_delay;
     use ic_interfaces::consensus_pool::ConsensusPool;
     use ic_logger::replica_logger::no_op_logger;
     use ic_metrics::MetricsRegistry;
@@ -442,4 +465,87 @@ mod tests {
             });
         })
     }
+
+    /// On a subnet with the adaptive notary delay enabled, a fast finalization
+    /// lets the notary sign the rank-0 block before the static initial notary
+    /// delay has passed, but not before the lower bound.
+    #[test]
+    fn test_notary_adaptive_initial_notary_delay() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let committee = vec![node_test_id(0)];
+            let min_initial_notary_delay = Duration::from_millis(100);
+            let Dependencies {
+                mut pool,
+                membership,
+                replica_config,
+                time_source,
+                crypto,
+                state_manager,
+                ..
+            } = dependencies_with_subnet_params(
+                pool_config,
+                subnet_test_id(0),
+                vec![(
+                    1,
+                    SubnetRecordBuilder::from(&committee)
+                        .with_dkg_interval_length(30)
+                        .with_adaptive_notary_delay(
+                            min_initial_notary_delay.as_millis() as u64,
+                            1500,
+                        )
+                        .build(),
+                )],
+            );
+            state_manager
+                .get_mut()
+                .expect_latest_certified_height()
+                .return_const(Height::new(1));
+
+            // Height 1 is finalized without any time passing, so the observed
+            // finalization latency is zero and the delay drops to the lower bound.
+            pool.advance_round_normal_operation();
+            let block = pool.make_next_block();
+            pool.insert_validated(block.clone());
+
+            let notary = Notary::new(
+                Arc::clone(&time_source) as Arc<_>,
+                replica_config,
+                membership.clone(),
+                crypto,
+                state_manager.clone(),
+                MetricsRegistry::new(),
+                no_op_logger(),
+            );
+            let run_notary = |pool: &dyn ConsensusPool| {
+                let reader = PoolReader::new(pool);
+                notary.on_state_change(&reader)
+            };
+            assert!(run_notary(&pool).is_empty());
+
+            let static_notary_delay = get_adjusted_notary_delay(
+                membership.as_ref(),
+                &PoolReader::new(&pool),
+                state_manager.as_ref(),
+                &no_op_logger(),
+                Height::from(2),
+                Rank(0),
+            )
+            .unwrap();
+            assert!(static_notary_delay > min_initial_notary_delay);
+
+            let start_time = time_source.get_relative_time();
+            time_source
+                .set_time(start_time + min_initial_notary_delay - Duration::from_millis(1))
+                .unwrap();
+            assert!(run_notary(&pool).is_empty());
+
+            time_source
+                .set_time(start_time + min_initial_notary_delay)
+                .unwrap();
+            match run_notary(&pool).as_slice() {
+                [share] => assert_eq!(&share.content.block, block.content.get_hash()),
+                shares => panic!("Expected exactly one share, got {:?}", shares),
+            }
+        })
+    }
 }
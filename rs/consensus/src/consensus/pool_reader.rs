@@ -387,6 +387,17 @@ impl<'a> PoolReader<'a> {
         }
     }
 
+    /// Get the time at which the first finalization of the given height was
+    /// added to the validated pool. Return None if no finalization is found.
+    pub fn get_finalization_time(&self, height: Height) -> Option<Time> {
+        let validated = self.pool.validated();
+        validated
+            .finalization()
+            .get_by_height(height)
+            .flat_map(|x| validated.get_timestamp(&x.get_id()))
+            .min()
+    }
+
     /// Get all valid random beacon shares at the given height.
     pub fn get_random_beacon_shares(
         &self,
@@ -1,8 +1,14 @@
 use crate::consensus::{
-    mocks::{dependencies_with_subnet_params, Dependencies},
+    mocks::{dependencies, dependencies_with_subnet_params, Dependencies},
     payload_builder::{test::make_test_payload_impl, PayloadBuilder},
+    pool_reader::PoolReader,
+    utils::{
+        get_adaptive_block_maker_delay, get_adaptive_initial_notary_delay,
+        get_adjusted_notary_delay_from_settings,
+    },
     SubnetRecords,
 };
+use ic_registry_client_helpers::subnet::{AdaptiveNotaryDelayBounds, NotarizationDelaySettings};
 use ic_test_utilities::{
     consensus::fake::Fake,
     mock_time,
@@ -17,7 +23,7 @@ use ic_types::{
     consensus::{
         certification::{Certification, CertificationContent},
         dkg::Dealings,
-        BlockPayload, DataPayload, Payload,
+        BlockPayload, DataPayload, Payload, Rank,
     },
     crypto::{CryptoHash, Signed},
     messages::SignedIngress,
@@ -27,6 +33,7 @@ use ic_types::{
 };
 use proptest::prelude::*;
 use std::collections::BTreeMap;
+use std::time::Duration;
 
 const MAX_MESSAGES: usize = 10;
 const MAX_SIZE: usize = 5 * 1024 * 1024;
@@ -84,7 +91,13 @@ fn proptest_round(
 
         let wrapped_payload = wrap_batch_payload(0, payload);
         payload_builder
-            .validate_payload(Height::from(0), &wrapped_payload, &[], &context)
+            .validate_payload(
+                Height::from(0),
+                node_test_id(0),
+                &wrapped_payload,
+                &[],
+                &context,
+            )
             .unwrap();
 
         // Check that no critical errors occured during the run.
@@ -92,6 +105,97 @@ fn proptest_round(
     });
 }
 
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 64,
+        ..ProptestConfig::default()
+    })]
+
+    /// The adaptive initial notary delay never leaves the configured bounds.
+    #[test]
+    fn proptest_adaptive_notary_delay_within_bounds(
+        latency_millis in 0..60_000u64,
+        min_millis in 0..5_000u64,
+        max_millis in 0..10_000u64,
+    ) {
+        let bounds = AdaptiveNotaryDelayBounds {
+            min_initial_notary_delay: Duration::from_millis(min_millis),
+            max_initial_notary_delay: Duration::from_millis(max_millis),
+        };
+        let delay =
+            get_adaptive_initial_notary_delay(Duration::from_millis(latency_millis), &bounds);
+        prop_assert!(delay >= bounds.min_initial_notary_delay);
+        prop_assert!(delay <= bounds.max_initial_notary_delay.max(bounds.min_initial_notary_delay));
+    }
+
+    /// Adapting the initial notary delay preserves the ordering of notary
+    /// delays by rank, never lets a notary sign a block before the lower bound
+    /// of the initial notary delay has passed on top of the ranked delay, and
+    /// always leaves the block of the same rank, proposed with the adaptive
+    /// block maker delay, at least the initial notary delay to propagate.
+    #[test]
+    fn proptest_adaptive_notary_delay_preserves_rank_order(
+        latency_millis in 0..60_000u64,
+        min_millis in 0..5_000u64,
+        max_millis in 0..10_000u64,
+        unit_delay_millis in 1..5_000u64,
+        rank in 0..10u64,
+    ) {
+        proptest_notary_delay_rank_order(
+            Duration::from_millis(latency_millis),
+            AdaptiveNotaryDelayBounds {
+                min_initial_notary_delay: Duration::from_millis(min_millis),
+                max_initial_notary_delay: Duration::from_millis(max_millis),
+            },
+            Duration::from_millis(unit_delay_millis),
+            rank,
+        );
+    }
+}
+
+fn proptest_notary_delay_rank_order(
+    latency: Duration,
+    bounds: AdaptiveNotaryDelayBounds,
+    unit_delay: Duration,
+    rank: u64,
+) {
+    ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+        let Dependencies {
+            mut pool,
+            state_manager,
+            ..
+        } = dependencies(pool_config, 4);
+        pool.advance_round_normal_operation_n(3);
+        state_manager
+            .get_mut()
+            .expect_latest_certified_height()
+            .return_const(PoolReader::new(&pool).get_finalized_height());
+
+        let settings = NotarizationDelaySettings {
+            unit_delay,
+            initial_notary_delay: get_adaptive_initial_notary_delay(latency, &bounds),
+            adaptive_notary_delay_bounds: Some(bounds),
+        };
+        let pool_reader = PoolReader::new(&pool);
+        let delay = |rank| {
+            get_adjusted_notary_delay_from_settings(
+                settings.clone(),
+                &pool_reader,
+                state_manager.as_ref(),
+                Rank(rank),
+            )
+        };
+        let block_maker_delay =
+            get_adaptive_block_maker_delay(&settings, Some(latency), Rank(rank));
+        assert!(delay(rank) < delay(rank + 1));
+        assert!(delay(rank) >= bounds.min_initial_notary_delay + unit_delay * rank as u32);
+        assert!(delay(rank) >= block_maker_delay + settings.initial_notary_delay);
+        // The validator accepts the block, as no honest block maker may
+        // propose earlier than `get_block_maker_delay` allows.
+        assert!(block_maker_delay >= unit_delay.min(bounds.min_initial_notary_delay) * rank as u32);
+    });
+}
+
 /// Build a number of ingress messages
 fn prop_ingress_vec(
     max_messages: usize,
@@ -7,7 +7,9 @@ use ic_interfaces_registry::RegistryClient;
 use ic_interfaces_state_manager::StateManager;
 use ic_logger::{error, warn, ReplicaLogger};
 use ic_protobuf::registry::subnet::v1::SubnetRecord;
-use ic_registry_client_helpers::subnet::{NotarizationDelaySettings, SubnetRegistry};
+use ic_registry_client_helpers::subnet::{
+    AdaptiveNotaryDelayBounds, NotarizationDelaySettings, SubnetRegistry,
+};
 use ic_replicated_state::ReplicatedState;
 use ic_types::replica_config::ReplicaConfig;
 use ic_types::{
@@ -32,6 +34,16 @@ pub const ACCEPTABLE_FINALIZATION_CERTIFICATION_GAP: u64 = 3;
 /// cup for the last summary block.
 pub const ACCEPTABLE_CUP_GAP_RATIO: f64 = 0.5;
 
+/// The fraction of the observed finalization latency that is used as the
+/// initial notary delay when the adaptive notary delay is enabled. The initial
+/// notary delay exists to give the rank-0 block time to propagate, which on a
+/// healthy subnet takes a fraction of the time needed to finalize a round.
+pub const ADAPTIVE_NOTARY_DELAY_LATENCY_FRACTION: f64 = 0.5;
+
+/// The number of most recent finalized heights over which the finalization
+/// latency is averaged by `get_recent_finalization_latency`.
+pub const FINALIZATION_LATENCY_WINDOW: u64 = 10;
+
 /// Rotate on_state_change calls with a round robin schedule to ensure fairness.
 #[derive(Default)]
 pub struct RoundRobin {
@@ -70,7 +82,10 @@ pub fn crypto_hashable_to_seed<T: CryptoHashable>(hashable: &T) -> [u8; 32] {
 }
 
 /// Calculate the required delay for block making based on the block maker's
-/// rank.
+/// rank. When the adaptive notary delay is enabled, honest block makers may
+/// shorten their delay down to the lower bound of the initial notary delay
+/// (see `get_adaptive_block_maker_delay`), so this returns the earliest delay
+/// any of them may use.
 pub fn get_block_maker_delay(
     log: &ReplicaLogger,
     registry_client: &dyn RegistryClient,
@@ -78,8 +93,57 @@ pub fn get_block_maker_delay(
     registry_version: RegistryVersion,
     rank: Rank,
 ) -> Option<Duration> {
-    get_notarization_delay_settings(log, registry_client, subnet_id, registry_version)
-        .map(|settings| settings.unit_delay * rank.0 as u32)
+    get_notarization_delay_settings(log, registry_client, subnet_id, registry_version).map(
+        |settings| {
+            let unit_delay = match settings.adaptive_notary_delay_bounds {
+                Some(bounds) => settings.unit_delay.min(bounds.min_initial_notary_delay),
+                None => settings.unit_delay,
+            };
+            unit_delay * rank.0 as u32
+        },
+    )
+}
+
+/// Calculate the delay a block maker of the given rank waits before proposing
+/// a block. When the adaptive notary delay is enabled and a finalization
+/// latency has been observed, the unit delay shrinks to the adaptive initial
+/// notary delay, so that on a fast subnet a block maker of higher rank steps
+/// in as soon as the rank-0 block had its chance to propagate. The result is
+/// never below the delay returned by `get_block_maker_delay`.
+pub fn get_adaptive_block_maker_delay(
+    settings: &NotarizationDelaySettings,
+    finalization_latency: Option<Duration>,
+    rank: Rank,
+) -> Duration {
+    let unit_delay = match (settings.adaptive_notary_delay_bounds, finalization_latency) {
+        (Some(bounds), Some(latency)) => settings
+            .unit_delay
+            .min(get_adaptive_initial_notary_delay(latency, &bounds)),
+        _ => settings.unit_delay,
+    };
+    unit_delay * rank.0 as u32
+}
+
+/// Return the average time between the start of a round and the arrival of its
+/// finalization over the last `window` finalized heights, or `None` if no such
+/// height is found in the pool.
+pub fn get_recent_finalization_latency(pool: &PoolReader<'_>, window: u64) -> Option<Duration> {
+    let finalized_height = pool.get_finalized_height();
+    let start = finalized_height
+        .get()
+        .saturating_sub(window.saturating_sub(1));
+    let latencies: Vec<Duration> = (start..=finalized_height.get())
+        .map(Height::from)
+        .filter_map(|height| {
+            let start_time = pool.get_round_start_time(height)?;
+            let finalization_time = pool.get_finalization_time(height)?;
+            (finalization_time >= start_time).then(|| finalization_time - start_time)
+        })
+        .collect();
+    if latencies.is_empty() {
+        return None;
+    }
+    Some(latencies.iter().sum::<Duration>() / latencies.len() as u32)
 }
 
 /// Return true if the given subnet id is the root subnet
@@ -213,6 +277,20 @@ pub fn get_adjusted_notary_delay_from_settings(
     Duration::from_millis(adjusted_delay)
 }
 
+/// Calculate the initial notary delay from the observed finalization latency,
+/// clamped to the bounds configured in the registry. A misconfigured upper
+/// bound that is lower than the lower bound is ignored.
+pub fn get_adaptive_initial_notary_delay(
+    finalization_latency: Duration,
+    bounds: &AdaptiveNotaryDelayBounds,
+) -> Duration {
+    let min = bounds.min_initial_notary_delay;
+    let max = bounds.max_initial_notary_delay.max(min);
+    finalization_latency
+        .mul_f64(ADAPTIVE_NOTARY_DELAY_LATENCY_FRACTION)
+        .clamp(min, max)
+}
+
 /// Return the validated block proposals with the lowest rank at height `h`, if
 /// there are any. Else return `None`.
 pub fn find_lowest_ranked_proposals(pool: &PoolReader<'_>, h: Height) -> Vec<BlockProposal> {
@@ -582,6 +660,7 @@ mod tests {
             let settings = NotarizationDelaySettings {
                 unit_delay: Duration::from_secs(1),
                 initial_notary_delay: Duration::from_secs(0),
+                adaptive_notary_delay_bounds: None,
             };
             let crate::consensus::mocks::Dependencies {
                 mut pool,
@@ -685,4 +764,47 @@ mod tests {
         assert_eq!(round_robin.call_next(&calls), vec![1]);
         assert_eq!(round_robin.call_next(&calls), vec![1]);
     }
+
+    #[test]
+    fn test_get_adaptive_block_maker_delay() {
+        let bounds = AdaptiveNotaryDelayBounds {
+            min_initial_notary_delay: Duration::from_millis(100),
+            max_initial_notary_delay: Duration::from_millis(2000),
+        };
+        let settings = NotarizationDelaySettings {
+            unit_delay: Duration::from_millis(1000),
+            initial_notary_delay: Duration::from_millis(1500),
+            adaptive_notary_delay_bounds: Some(bounds),
+        };
+        let rank = Rank(3);
+
+        // Without an observed latency the unit delay is used.
+        assert_eq!(
+            get_adaptive_block_maker_delay(&settings, None, rank),
+            Duration::from_millis(3000)
+        );
+        // A fast subnet shrinks the unit delay to the adaptive notary delay...
+        assert_eq!(
+            get_adaptive_block_maker_delay(&settings, Some(Duration::from_millis(400)), rank),
+            Duration::from_millis(600)
+        );
+        // ...but never below the lower bound, nor above the unit delay.
+        assert_eq!(
+            get_adaptive_block_maker_delay(&settings, Some(Duration::ZERO), rank),
+            Duration::from_millis(300)
+        );
+        assert_eq!(
+            get_adaptive_block_maker_delay(&settings, Some(Duration::from_secs(10)), rank),
+            Duration::from_millis(3000)
+        );
+        // Without bounds the latency is ignored.
+        let static_settings = NotarizationDelaySettings {
+            adaptive_notary_delay_bounds: None,
+            ..settings
+        };
+        assert_eq!(
+            get_adaptive_block_maker_delay(&static_settings, Some(Duration::ZERO), rank),
+            Duration::from_millis(3000)
+        );
+    }
 }
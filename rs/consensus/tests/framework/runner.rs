@@ -6,7 +6,7 @@ use ic_consensus::consensus::dkg_key_manager::DkgKeyManager;
 use ic_consensus::consensus::pool_reader::PoolReader;
 use ic_consensus::{
     certification::CertifierImpl,
-    consensus::{ConsensusImpl, Membership},
+    consensus::{ConsensusImpl, ConsensusTimeline, Membership, CONSENSUS_TIMELINE_CAPACITY},
     dkg,
 };
 use ic_interfaces::time_source::TimeSource;
@@ -145,6 +145,7 @@ impl<'a> ConsensusRunner<'a> {
             deps.xnet_payload_builder.clone(),
             deps.self_validating_payload_builder.clone(),
             deps.canister_http_payload_builder.clone(),
+            deps.query_stats_payload_builder.clone(),
             deps.dkg_pool.clone(),
             deps.ecdsa_pool.clone(),
             dkg_key_manager.clone(),
@@ -156,6 +157,7 @@ impl<'a> ConsensusRunner<'a> {
             deps.metrics_registry.clone(),
             replica_logger.clone(),
             None,
+            Arc::new(ConsensusTimeline::new(CONSENSUS_TIMELINE_CAPACITY)),
         );
         let dkg = dkg::DkgImpl::new(
             deps.replica_config.node_id,
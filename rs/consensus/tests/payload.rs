@@ -4,7 +4,11 @@ use crate::framework::ConsensusDriver;
 use ic_artifact_pool::{consensus_pool, dkg_pool, ecdsa_pool};
 use ic_consensus::consensus::dkg_key_manager::DkgKeyManager;
 use ic_consensus::consensus::pool_reader::PoolReader;
-use ic_consensus::{certification::CertifierImpl, consensus::ConsensusImpl, dkg};
+use ic_consensus::{
+    certification::CertifierImpl,
+    consensus::{ConsensusImpl, ConsensusTimeline, CONSENSUS_TIMELINE_CAPACITY},
+    dkg,
+};
 use ic_interfaces::time_source::TimeSource;
 use ic_interfaces_state_manager::Labeled;
 use ic_interfaces_state_manager_mocks::MockStateManager;
@@ -16,6 +20,7 @@ use ic_test_utilities::{
     crypto::CryptoReturningOk,
     ingress_selector::FakeIngressSelector,
     message_routing::FakeMessageRouting,
+    query_stats::FakeQueryStatsPayloadBuilder,
     self_validating_payload_builder::FakeSelfValidatingPayloadBuilder,
     state::get_initial_state,
     types::ids::{node_test_id, subnet_test_id},
@@ -53,6 +58,9 @@ fn consensus_produces_expected_batches() {
         let canister_http_payload_builder = FakeCanisterHttpPayloadBuilder::new();
         let canister_http_payload_builder = Arc::new(canister_http_payload_builder);
 
+        let query_stats_payload_builder = FakeQueryStatsPayloadBuilder::new();
+        let query_stats_payload_builder = Arc::new(query_stats_payload_builder);
+
         let mut state_manager = MockStateManager::new();
         state_manager.expect_remove_states_below().return_const(());
         state_manager
@@ -139,6 +147,7 @@ fn consensus_produces_expected_batches() {
             Arc::clone(&xnet_payload_builder) as Arc<_>,
             Arc::clone(&self_validating_payload_builder) as Arc<_>,
             Arc::clone(&canister_http_payload_builder) as Arc<_>,
+            Arc::clone(&query_stats_payload_builder) as Arc<_>,
             Arc::clone(&dkg_pool) as Arc<_>,
             Arc::clone(&ecdsa_pool) as Arc<_>,
             dkg_key_manager.clone(),
@@ -150,6 +159,7 @@ fn consensus_produces_expected_batches() {
             metrics_registry.clone(),
             no_op_logger(),
             None,
+            Arc::new(ConsensusTimeline::new(CONSENSUS_TIMELINE_CAPACITY)),
         );
         let dkg = dkg::DkgImpl::new(
             replica_config.node_id,
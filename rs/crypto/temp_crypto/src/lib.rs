@@ -548,6 +548,9 @@ impl EcdsaSubnetConfig {
                     idkg_key_rotation_period_ms: key_rotation_period
                         .map(|key_rotation_period| key_rotation_period.as_millis() as u64),
                 }),
+                adaptive_notary_delay: None,
+                max_ingress_messages_per_canister_per_block: 0,
+                max_ingress_messages_per_sender_per_block: 0,
             },
         }
     }
@@ -60,6 +60,62 @@ impl std::fmt::Display for CyclesAccountManagerError {
     }
 }
 
+/// Describes how saturated a subnet resource such as memory is.
+///
+/// Allocating the resource while its `usage` is above `threshold` requires
+/// reserving cycles. The amount grows linearly from zero at the threshold to
+/// the full amount at `capacity`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResourceSaturation {
+    usage: u64,
+    threshold: u64,
+    capacity: u64,
+}
+
+impl ResourceSaturation {
+    /// Creates a new saturation. The threshold is capped at the capacity.
+    pub fn new(usage: u64, threshold: u64, capacity: u64) -> Self {
+        Self {
+            usage,
+            threshold: threshold.min(capacity),
+            capacity,
+        }
+    }
+
+    /// Creates a new saturation from the amount of the resource that is still
+    /// available rather than from the usage.
+    pub fn new_from_available(available: i64, threshold: u64, capacity: u64) -> Self {
+        let usage = capacity.saturating_sub(available.max(0) as u64);
+        Self::new(usage, threshold, capacity)
+    }
+
+    pub fn usage(&self) -> u64 {
+        self.usage
+    }
+
+    pub fn threshold(&self) -> u64 {
+        self.threshold
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Returns the part of the usage that is above the threshold, capped at
+    /// the capacity.
+    pub fn usage_above_threshold(&self) -> u64 {
+        self.usage.min(self.capacity).saturating_sub(self.threshold)
+    }
+
+    /// Returns the saturation after `delta` more of the resource is used.
+    pub fn add(&self, delta: u64) -> Self {
+        Self {
+            usage: self.usage.saturating_add(delta),
+            ..*self
+        }
+    }
+}
+
 /// Handles any operation related to cycles accounting, such as charging (due to
 /// using system resources) or refunding unused cycles.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -251,6 +307,8 @@ impl CyclesAccountManager {
     /// Note that this method reports the cycles withdrawn as consumed (i.e.
     /// burnt).
     ///
+    /// The canister's ingress priority fee is charged on top of `cycles`.
+    ///
     /// # Errors
     ///
     /// Returns a `CanisterOutOfCyclesError` if the
@@ -263,6 +321,7 @@ impl CyclesAccountManager {
         cycles: Cycles,
         subnet_size: usize,
     ) -> Result<(), CanisterOutOfCyclesError> {
+        let cycles = cycles + self.ingress_priority_fee(&canister.system_state);
         let threshold = self.freeze_threshold_cycles(
             canister.system_state.freeze_threshold,
             canister.system_state.memory_allocation,
@@ -468,6 +527,15 @@ impl CyclesAccountManager {
         }
     }
 
+    /// Returns the priority fee that the canister with the given system state
+    /// pays on top of the induction cost of every ingress message it pays for.
+    ///
+    /// The fee is declared by the canister via its settings and is not scaled
+    /// by the subnet size.
+    pub fn ingress_priority_fee(&self, system_state: &SystemState) -> Cycles {
+        system_state.ingress_priority_fee
+    }
+
     /// Returns the cost of an ingress message based on the message size.
     pub fn ingress_induction_cost_from_bytes(&self, bytes: NumBytes, subnet_size: usize) -> Cycles {
         self.scale_cost(
@@ -513,8 +581,53 @@ impl CyclesAccountManager {
     ) -> Result<(), CanisterOutOfCyclesError> {
         let cycles_amount = self.memory_cost(bytes, duration, subnet_size);
 
+        // Storage is paid from the reserved balance first and only the
+        // remainder is charged from the main balance.
+        let from_reserved_balance = std::cmp::min(cycles_amount, system_state.reserved_balance());
+
         // Can charge all the way to the empty account (zero cycles)
-        self.consume_with_threshold(system_state, cycles_amount, Cycles::zero())
+        self.consume_with_threshold(
+            system_state,
+            cycles_amount - from_reserved_balance,
+            Cycles::zero(),
+        )?;
+        system_state.remove_cycles_from_reserved_balance(from_reserved_balance);
+        self.observe_consumed_cycles(system_state, from_reserved_balance);
+        Ok(())
+    }
+
+    /// Returns the amount of cycles to reserve for allocating
+    /// `allocated_bytes` of memory on a subnet with the given memory
+    /// saturation.
+    ///
+    /// Each newly allocated byte above the threshold reserves the cost of
+    /// storing it for `storage_reservation_duration`, scaled by how close the
+    /// subnet memory usage is to the capacity: from zero at the threshold to
+    /// the full cost at the capacity. No cycles are reserved below the
+    /// threshold.
+    pub fn storage_reservation_cycles(
+        &self,
+        allocated_bytes: NumBytes,
+        subnet_memory_saturation: &ResourceSaturation,
+        subnet_size: usize,
+    ) -> Cycles {
+        let range =
+            (subnet_memory_saturation.capacity() - subnet_memory_saturation.threshold()) as u128;
+        if range == 0 {
+            return Cycles::zero();
+        }
+        let before = subnet_memory_saturation.usage_above_threshold() as u128;
+        let after = subnet_memory_saturation
+            .add(allocated_bytes.get())
+            .usage_above_threshold() as u128;
+        // The integral of the linear scaling factor `x / range` over the
+        // allocated bytes that are above the threshold.
+        let scaled_bytes = (after * after - before * before) / (2 * range);
+        self.memory_cost(
+            NumBytes::new(scaled_bytes as u64),
+            self.config.storage_reservation_duration,
+            subnet_size,
+        )
     }
 
     /// The cost of using `bytes` worth of memory.
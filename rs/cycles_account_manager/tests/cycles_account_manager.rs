@@ -1,12 +1,12 @@
 use ic_base_types::NumSeconds;
 use ic_config::subnet_config::SubnetConfigs;
 use ic_constants::SMALL_APP_SUBNET_MAX_SIZE;
-use ic_cycles_account_manager::IngressInductionCost;
+use ic_cycles_account_manager::{IngressInductionCost, ResourceSaturation};
 use ic_ic00_types::{CanisterIdRecord, Payload, IC_00};
 use ic_interfaces::execution_environment::CanisterOutOfCyclesError;
 use ic_logger::replica_logger::no_op_logger;
 use ic_registry_subnet_type::SubnetType;
-use ic_replicated_state::SystemState;
+use ic_replicated_state::{ReservationError, SystemState};
 use ic_test_utilities::{
     cycles_account_manager::CyclesAccountManagerBuilder,
     state::{new_canister_state, SystemStateBuilder},
@@ -344,6 +344,102 @@ fn canister_charge_for_memory_until_zero_works() {
         .is_err());
 }
 
+#[test]
+fn storage_reservation_cycles_grow_with_subnet_memory_usage() {
+    let subnet_size = SMALL_APP_SUBNET_MAX_SIZE;
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let allocated_bytes = NumBytes::from(1 << 20);
+    let capacity = 1 << 30;
+    let threshold = capacity / 2;
+
+    let below_threshold = ResourceSaturation::new(0, threshold, capacity);
+    assert_eq!(
+        cycles_account_manager.storage_reservation_cycles(
+            allocated_bytes,
+            &below_threshold,
+            subnet_size
+        ),
+        Cycles::zero()
+    );
+
+    let above_threshold = ResourceSaturation::new(threshold + (1 << 20), threshold, capacity);
+    let almost_full = ResourceSaturation::new(capacity - (1 << 20), threshold, capacity);
+    let low = cycles_account_manager.storage_reservation_cycles(
+        allocated_bytes,
+        &above_threshold,
+        subnet_size,
+    );
+    let high = cycles_account_manager.storage_reservation_cycles(
+        allocated_bytes,
+        &almost_full,
+        subnet_size,
+    );
+    assert!(low > Cycles::zero());
+    assert!(high > low);
+}
+
+#[test]
+fn charge_for_memory_uses_reserved_balance_first() {
+    let subnet_size = SMALL_APP_SUBNET_MAX_SIZE;
+    let mut system_state = SystemStateBuilder::new().build();
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let bytes = NumBytes::from(1 << 30);
+    let duration = Duration::from_secs(1);
+    let fee = cycles_account_manager.memory_cost(bytes, duration, subnet_size);
+
+    system_state
+        .reserve_cycles(fee * 2_u64, Cycles::zero())
+        .unwrap();
+    let balance = system_state.balance();
+    cycles_account_manager
+        .charge_for_memory(&mut system_state, bytes, duration, subnet_size)
+        .unwrap();
+    assert_eq!(system_state.balance(), balance);
+    assert_eq!(system_state.reserved_balance(), fee);
+
+    cycles_account_manager
+        .charge_for_memory(&mut system_state, bytes, duration, subnet_size)
+        .unwrap();
+    cycles_account_manager
+        .charge_for_memory(&mut system_state, bytes, duration, subnet_size)
+        .unwrap();
+    assert_eq!(system_state.reserved_balance(), Cycles::zero());
+    assert_eq!(system_state.balance(), balance - fee);
+}
+
+#[test]
+fn reserve_cycles_respects_freezing_threshold() {
+    let mut system_state = SystemStateBuilder::new()
+        .initial_cycles(Cycles::new(1_000))
+        .build();
+    let freezing_threshold = Cycles::new(300);
+
+    assert_eq!(
+        system_state.reserve_cycles(Cycles::new(701), freezing_threshold),
+        Err(ReservationError::InsufficientCycles {
+            requested: Cycles::new(701),
+            available: Cycles::new(700),
+        })
+    );
+    assert_eq!(system_state.balance(), Cycles::new(1_000));
+    assert_eq!(system_state.reserved_balance(), Cycles::zero());
+
+    system_state
+        .reserve_cycles(Cycles::new(700), freezing_threshold)
+        .unwrap();
+    assert_eq!(system_state.balance(), Cycles::new(300));
+    assert_eq!(system_state.reserved_balance(), Cycles::new(700));
+
+    // A frozen canister cannot reserve anything.
+    assert_eq!(
+        system_state.reserve_cycles(Cycles::new(1), freezing_threshold),
+        Err(ReservationError::InsufficientCycles {
+            requested: Cycles::new(1),
+            available: Cycles::zero(),
+        })
+    );
+}
+
 #[test]
 fn ingress_induction_cost_valid_subnet_message() {
     let subnet_id = subnet_test_id(0);
@@ -376,6 +472,36 @@ fn ingress_induction_cost_valid_subnet_message() {
     }
 }
 
+#[test]
+fn charge_ingress_induction_cost_includes_priority_fee() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let mut canister = new_canister_state(
+        canister_test_id(0),
+        canister_test_id(1).get(),
+        INITIAL_CYCLES,
+        NumSeconds::from(0),
+    );
+    canister.system_state.ingress_priority_fee = Cycles::new(1_000);
+    assert_eq!(
+        cycles_account_manager.ingress_priority_fee(&canister.system_state),
+        Cycles::new(1_000)
+    );
+
+    cycles_account_manager
+        .charge_ingress_induction_cost(
+            &mut canister,
+            NumBytes::from(0),
+            ComputeAllocation::default(),
+            Cycles::new(1_000_000),
+            SMALL_APP_SUBNET_MAX_SIZE,
+        )
+        .unwrap();
+    assert_eq!(
+        canister.system_state.balance(),
+        INITIAL_CYCLES - Cycles::new(1_001_000)
+    );
+}
+
 #[test]
 fn charging_removes_canisters_with_insufficient_balance() {
     with_test_replica_logger(|log| {
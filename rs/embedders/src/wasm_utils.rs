@@ -186,6 +186,7 @@ fn validate_and_instrument(
                     module,
                     config.cost_to_compile_wasm_instruction,
                     config.feature_flags.write_barrier,
+                    config.feature_flags.instruction_profiling,
                 )?,
             )
         } else {
@@ -93,6 +93,23 @@
 //! blocks to optimize for performance. The maximal overflow in that case is
 //! bound by the length of the longest execution path consisting of
 //! non-reentrant basic blocks.
+//!
+//! If instruction profiling is enabled, then every locally defined function
+//! `i` additionally gets a mutable `i64` global exported as
+//! `canister profile_instructions <i>`. Every decrementation of the
+//! instructions counter inside the function body also increments this global
+//! by the same amount:
+//!
+//! ```wasm
+//! global.get 1
+//! i64.const 2
+//! i64.add
+//! global.set 1
+//! ```
+//!
+//! Use [`exported_global_names`] on the instrumented module to find the
+//! profiling globals among the exported globals of the instance and
+//! [`profiled_function_names`] to map them back to function names.
 
 use super::{InstrumentationOutput, Segments};
 use ic_config::flag_status::FlagStatus;
@@ -107,9 +124,10 @@ use crate::wasm_utils::wasm_transform::{self, Module};
 use crate::wasmtime_embedder::{WASM_HEAP_BYTEMAP_MEMORY_NAME, WASM_HEAP_MEMORY_NAME};
 use wasmparser::{
     BlockType, ConstExpr, Export, ExternalKind, FuncType, Global, GlobalType, Import, MemoryType,
-    Operator, Type, TypeRef, ValType,
+    Name, NameSectionReader, Operator, Type, TypeRef, ValType,
 };
 
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 
 // The indicies of injected function imports.
@@ -139,7 +157,8 @@ fn instruction_to_cost(i: &Operator) -> u64 {
 //     out of instructions.
 //   * `update_available_memory` which is called after a native `memory.grow` to
 //     check whether the canister has enough available memory according to its
-//     memory allocation.
+//     memory allocation and whether the new Wasm memory size respects the
+//     canister's `wasm_memory_limit`.
 //
 // Note that these functions are injected as the first two imports, so that we
 // can increment all function indices unconditionally by two. (If they would be
@@ -153,6 +172,11 @@ const UPDATE_MEMORY_FUN_NAME: &str = "update_available_memory";
 const TABLE_STR: &str = "table";
 const CANISTER_COUNTER_INSTRUCTIONS_STR: &str = "canister counter_instructions";
 const CANISTER_START_STR: &str = "canister_start";
+const NAME_SECTION_STR: &str = "name";
+
+/// Prefix of the exported globals that count the instructions executed by
+/// every locally defined function when instruction profiling is enabled.
+pub const PROFILE_INSTRUCTIONS_PREFIX: &str = "canister profile_instructions ";
 
 /// There is one byte for each OS page in the wasm heap.
 const BYTEMAP_SIZE_IN_WASM_PAGES: u64 =
@@ -239,6 +263,14 @@ pub struct ExportModuleData {
     pub start_fn_ix: Option<u32>,
 }
 
+// The profiling global of a function together with a scratch local that
+// holds the dynamic cost of bulk memory instructions.
+#[derive(Copy, Clone, Debug)]
+struct ProfilingCounter {
+    global_ix: u32,
+    scratch_local_ix: u32,
+}
+
 /// Takes a Wasm binary and inserts the instructions metering and memory grow
 /// instrumentation.
 ///
@@ -248,6 +280,7 @@ pub(super) fn instrument(
     module: Module<'_>,
     cost_to_compile_wasm_instruction: NumInstructions,
     write_barrier: FlagStatus,
+    instruction_profiling: FlagStatus,
 ) -> Result<InstrumentationOutput, WasmInstrumentationError> {
     let mut module = inject_helper_functions(module);
     module = export_table(module);
@@ -283,11 +316,6 @@ pub(super) fn instrument(
         module.start = None;
     }
 
-    // inject instructions counter decrementation
-    for func_body in &mut module.code_sections {
-        inject_metering(&mut func_body.instructions, &export_module_data);
-    }
-
     // Collect all the function types of the locally defined functions inside the
     // module.
     //
@@ -300,6 +328,32 @@ pub(super) fn instrument(
         func_types.push(t.clone());
     }
 
+    // The profiling globals follow the instructions counter, which is added
+    // by `export_additional_symbols`.
+    let num_profiled_functions = module.code_sections.len();
+    let first_profiling_global_ix = export_module_data.instructions_counter_ix + 1;
+
+    // inject instructions counter decrementation
+    for (func_ix, func_body) in module.code_sections.iter_mut().enumerate() {
+        let profiling_counter = match instruction_profiling {
+            FlagStatus::Enabled => {
+                let n_locals: u32 = func_body.locals.iter().map(|x| x.0).sum();
+                let scratch_local_ix = func_types[func_ix].params().len() as u32 + n_locals;
+                func_body.locals.push((1, ValType::I32));
+                Some(ProfilingCounter {
+                    global_ix: first_profiling_global_ix + func_ix as u32,
+                    scratch_local_ix,
+                })
+            }
+            FlagStatus::Disabled => None,
+        };
+        inject_metering(
+            &mut func_body.instructions,
+            &export_module_data,
+            profiling_counter,
+        );
+    }
+
     // Inject `update_available_memory` to functions with `memory.grow`
     // instructions.
     if !func_types.is_empty() {
@@ -312,6 +366,22 @@ pub(super) fn instrument(
     let mut extra_data: Option<Vec<u8>> = None;
     module = export_additional_symbols(module, &export_module_data, &mut extra_data);
 
+    let mut profiling_strs: Vec<String> = Vec::new();
+    let mut profiling_init_data: Vec<u8> = Vec::new();
+    if instruction_profiling == FlagStatus::Enabled {
+        debug_assert_eq!(
+            (num_imported_globals + module.globals.len()) as u32,
+            first_profiling_global_ix
+        );
+        module = export_profiling_counters(
+            module,
+            num_profiled_functions,
+            first_profiling_global_ix,
+            &mut profiling_strs,
+            &mut profiling_init_data,
+        );
+    }
+
     let exported_functions = module
         .exports
         .iter()
@@ -514,7 +584,15 @@ impl InjectionPoint {
 // - we insert a function call before each dynamic cost instruction which
 //   performs an overflow check and then decrements the counter by the value at
 //   the top of the stack.
-fn inject_metering(code: &mut Vec<Operator>, export_data_module: &ExportModuleData) {
+//
+// If a profiling counter is given, then every decrementation of the
+// instructions counter is mirrored by an incrementation of the function's
+// profiling global.
+fn inject_metering(
+    code: &mut Vec<Operator>,
+    export_data_module: &ExportModuleData,
+    profiling_counter: Option<ProfilingCounter>,
+) {
     let points = injections(code);
     let points = points.iter().filter(|point| match point.cost_detail {
         InjectionPointCostDetail::StaticCost {
@@ -544,6 +622,20 @@ fn inject_metering(code: &mut Vec<Operator>, export_data_module: &ExportModuleDa
                         global_index: export_data_module.instructions_counter_ix,
                     },
                 ]);
+                if let Some(counter) = profiling_counter {
+                    if cost > 0 {
+                        elems.extend_from_slice(&[
+                            GlobalGet {
+                                global_index: counter.global_ix,
+                            },
+                            I64Const { value: cost as i64 },
+                            I64Add,
+                            GlobalSet {
+                                global_index: counter.global_ix,
+                            },
+                        ]);
+                    }
+                }
                 if scope == Scope::ReentrantBlockStart {
                     elems.extend_from_slice(&[
                         GlobalGet {
@@ -562,6 +654,26 @@ fn inject_metering(code: &mut Vec<Operator>, export_data_module: &ExportModuleDa
                 }
             }
             InjectionPointCostDetail::DynamicCost => {
+                if let Some(counter) = profiling_counter {
+                    // The dynamic cost is on top of the stack and must stay
+                    // there for the call below.
+                    elems.extend_from_slice(&[
+                        LocalTee {
+                            local_index: counter.scratch_local_ix,
+                        },
+                        I64ExtendI32U,
+                        GlobalGet {
+                            global_index: counter.global_ix,
+                        },
+                        I64Add,
+                        GlobalSet {
+                            global_index: counter.global_ix,
+                        },
+                        LocalGet {
+                            local_index: counter.scratch_local_ix,
+                        },
+                    ]);
+                }
                 elems.extend_from_slice(&[Call {
                     function_index: export_data_module.decr_instruction_counter_fn,
                 }]);
@@ -810,3 +922,94 @@ fn export_mutable_globals<'a>(
 
     module
 }
+
+// Adds one mutable `i64` profiling global per locally defined function and
+// exports it.
+fn export_profiling_counters<'a>(
+    mut module: Module<'a>,
+    num_functions: usize,
+    first_global_ix: u32,
+    extra_strs: &'a mut Vec<String>,
+    zero_init_data: &'a mut Vec<u8>,
+) -> Module<'a> {
+    use wasm_encoder::Encode;
+    //encode() automatically adds an End instructions
+    wasm_encoder::ConstExpr::i64_const(0).encode(zero_init_data);
+    let zero_init_data: &'a [u8] = zero_init_data;
+
+    extra_strs
+        .extend((0..num_functions).map(|ix| format!("{}{}", PROFILE_INSTRUCTIONS_PREFIX, ix)));
+    let extra_strs: &'a [String] = extra_strs;
+
+    for (ix, name) in extra_strs.iter().enumerate() {
+        module.globals.push(Global {
+            ty: GlobalType {
+                content_type: ValType::I64,
+                mutable: true,
+            },
+            init_expr: ConstExpr::new(zero_init_data, 0),
+        });
+        module.exports.push(Export {
+            name: name.as_str(),
+            kind: ExternalKind::Global,
+            index: first_global_ix + ix as u32,
+        });
+    }
+
+    module
+}
+
+/// Returns the export names of the globals exported by the given Wasm module,
+/// in the order in which the instance reports their values.
+pub fn exported_global_names(wasm: &BinaryEncodedWasm) -> Result<Vec<String>, WasmError> {
+    let module =
+        Module::parse(wasm.as_slice(), false).map_err(|err| WasmError::new(format!("{}", err)))?;
+    Ok(module
+        .exports
+        .iter()
+        .filter(|export| export.kind == ExternalKind::Global)
+        .map(|export| export.name.to_string())
+        .collect())
+}
+
+/// Returns the names of the locally defined functions of the given Wasm module
+/// in the order of their profiling globals, i.e. the `i`-th name belongs to the
+/// global exported as `canister profile_instructions <i>`.
+///
+/// The names are taken from the `name` custom section. Functions without a
+/// name are reported as `func[<index>]`.
+pub fn profiled_function_names(wasm: &BinaryEncodedWasm) -> Result<Vec<String>, WasmError> {
+    let module =
+        Module::parse(wasm.as_slice(), false).map_err(|err| WasmError::new(format!("{}", err)))?;
+    let to_wasm_error = |err: wasmparser::BinaryReaderError| WasmError::new(err.to_string());
+
+    let num_imported_functions = module
+        .imports
+        .iter()
+        .filter(|import| matches!(import.ty, TypeRef::Func(_)))
+        .count() as u32;
+
+    let mut names = BTreeMap::new();
+    for (section_name, data) in &module.custom_sections {
+        if *section_name != NAME_SECTION_STR {
+            continue;
+        }
+        for subsection in NameSectionReader::new(data, 0).map_err(to_wasm_error)? {
+            if let Name::Function(map) = subsection.map_err(to_wasm_error)? {
+                for naming in map.get_map().map_err(to_wasm_error)? {
+                    let naming = naming.map_err(to_wasm_error)?;
+                    names.insert(naming.index, naming.name.to_string());
+                }
+            }
+        }
+    }
+
+    Ok((0..module.code_sections.len() as u32)
+        .map(|ix| {
+            let func_ix = num_imported_functions + ix;
+            names
+                .remove(&func_ix)
+                .unwrap_or_else(|| format!("func[{}]", func_ix))
+        })
+        .collect())
+}
@@ -16,7 +16,10 @@ use std::{
 use wasmtime::Config;
 
 use crate::{
-    wasm_utils::wasm_transform::{DataSegment, DataSegmentKind, Module},
+    wasm_utils::{
+        new_instrumentation::PROFILE_INSTRUCTIONS_PREFIX,
+        wasm_transform::{DataSegment, DataSegmentKind, Module},
+    },
     wasmtime_embedder::WASM_HEAP_MEMORY_NAME,
 };
 use wasmparser::{ExternalKind, Operator, Type, TypeRef, ValType};
@@ -290,6 +293,16 @@ fn get_valid_system_apis() -> HashMap<String, HashMap<String, FunctionSignature>
                 },
             )],
         ),
+        (
+            "call_with_best_effort_response",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![ValType::I32],
+                    return_type: vec![],
+                },
+            )],
+        ),
         (
             "call_cycles_add",
             vec![(
@@ -401,6 +414,26 @@ fn get_valid_system_apis() -> HashMap<String, HashMap<String, FunctionSignature>
                 },
             )],
         ),
+        (
+            "stable64_checkpoint",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![ValType::I64, ValType::I64, ValType::I64],
+                    return_type: vec![ValType::I64],
+                },
+            )],
+        ),
+        (
+            "stable64_restore",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![ValType::I64, ValType::I64, ValType::I64],
+                    return_type: vec![ValType::I64],
+                },
+            )],
+        ),
         (
             "time",
             vec![(
@@ -833,7 +866,9 @@ fn validate_export_section(module: &Module) -> Result<usize, WasmValidationError
         let valid_exported_functions = get_valid_exported_functions();
         for export in &module.exports {
             // Verify that the exported symbol's name isn't reserved.
-            if RESERVED_SYMBOLS.contains(&export.name) {
+            if RESERVED_SYMBOLS.contains(&export.name)
+                || export.name.starts_with(PROFILE_INSTRUCTIONS_PREFIX)
+            {
                 return Err(WasmValidationError::InvalidExportSection(format!(
                     "Exporting reserved symbol {} not allowed.",
                     export.name
@@ -289,6 +289,16 @@ fn get_valid_system_apis() -> HashMap<String, HashMap<String, FunctionSignature>
                 },
             )],
         ),
+        (
+            "call_with_best_effort_response",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![ValueType::I32],
+                    return_type: vec![],
+                },
+            )],
+        ),
         (
             "call_cycles_add",
             vec![(
@@ -400,6 +410,26 @@ fn get_valid_system_apis() -> HashMap<String, HashMap<String, FunctionSignature>
                 },
             )],
         ),
+        (
+            "stable64_checkpoint",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![ValueType::I64, ValueType::I64, ValueType::I64],
+                    return_type: vec![ValueType::I64],
+                },
+            )],
+        ),
+        (
+            "stable64_restore",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![ValueType::I64, ValueType::I64, ValueType::I64],
+                    return_type: vec![ValueType::I64],
+                },
+            )],
+        ),
         (
             "time",
             vec![(
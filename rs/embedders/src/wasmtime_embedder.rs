@@ -228,6 +228,7 @@ impl WasmtimeEmbedder {
             canister_id,
             &store,
             self.config.feature_flags.rate_limiting_of_debug_prints,
+            self.config.feature_flags.best_effort_responses,
             self.config.stable_memory_dirty_page_limit,
         );
 
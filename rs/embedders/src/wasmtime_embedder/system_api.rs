@@ -329,6 +329,7 @@ pub(crate) fn syscalls<S: SystemApi>(
     canister_id: CanisterId,
     store: &Store<StoreData<S>>,
     rate_limiting_of_debug_prints: FlagStatus,
+    best_effort_responses: FlagStatus,
     stable_memory_dirty_page_limit: NumPages,
 ) -> Linker<StoreData<S>> {
     fn with_system_api<S, T>(caller: &mut Caller<'_, StoreData<S>>, f: impl Fn(&mut S) -> T) -> T {
@@ -855,6 +856,25 @@ pub(crate) fn syscalls<S: SystemApi>(
         })
         .unwrap();
 
+    linker
+        .func_wrap("ic0", "call_with_best_effort_response", {
+            move |mut caller: Caller<'_, StoreData<S>>, timeout_seconds: i32| {
+                // The deadline of a best-effort request is only preserved in
+                // streams from certification version 12 onwards.
+                let result = match best_effort_responses {
+                    FlagStatus::Enabled => with_system_api(&mut caller, |s| {
+                        s.ic0_call_with_best_effort_response(timeout_seconds as u32)
+                    }),
+                    FlagStatus::Disabled => Err(HypervisorError::ContractViolation(
+                        "ic0.call_with_best_effort_response is not enabled on this subnet"
+                            .to_string(),
+                    )),
+                };
+                result.map_err(|e| process_err(&mut caller, e))
+            }
+        })
+        .unwrap();
+
     linker
         .func_wrap("ic0", "call_cycles_add", {
             move |mut caller: Caller<'_, StoreData<S>>, amount: i64| {
@@ -1074,6 +1094,95 @@ pub(crate) fn syscalls<S: SystemApi>(
         })
         .unwrap();
 
+    linker
+        .func_wrap("ic0", "stable64_checkpoint", {
+            let log = log.clone();
+            move |mut caller: Caller<'_, StoreData<S>>, offset: i64, src: i64, size: i64| {
+                let offset = offset as u64;
+                let src = src as u64;
+                let size = size as u64;
+                // Every byte of the range is read and compared with stable
+                // memory, so the comparison is charged per byte like
+                // `stable64_write` and before it is done. Comparing is cheaper
+                // than copying, see the `ic0_stable64_checkpoint()` benchmarks.
+                // The dirty pages are tracked per message, so pages modified in
+                // earlier messages can only be found by comparing.
+                charge_for_system_api_call(
+                    &log,
+                    canister_id,
+                    &mut caller,
+                    system_api_complexity::overhead::STABLE64_CHECKPOINT,
+                    size as u32,
+                    &ExecutionComplexity {
+                        cpu: system_api_complexity::cpu::STABLE64_CHECKPOINT,
+                        ..Default::default()
+                    },
+                    NumInstructions::from(0),
+                    stable_memory_dirty_page_limit,
+                )?;
+                let differing_ranges =
+                    with_memory_and_system_api(&mut caller, |system_api, memory| {
+                        system_api.stable64_differing_ranges(offset, src, size, memory)
+                    })?;
+                // Only the differing parts are written, so only they can create
+                // new dirty pages.
+                let mut stable_dirty_pages = NumPages::from(0);
+                let mut dirty_page_cost = NumInstructions::from(0);
+                for range in differing_ranges.iter() {
+                    let (pages, cost) = get_new_stable_dirty_pages(
+                        &mut caller,
+                        offset + (range.start - src),
+                        range.end - range.start,
+                    )?;
+                    stable_dirty_pages += pages;
+                    dirty_page_cost += cost;
+                }
+                charge_for_system_api_call(
+                    &log,
+                    canister_id,
+                    &mut caller,
+                    NumInstructions::from(0),
+                    0,
+                    &ExecutionComplexity {
+                        stable_dirty_pages,
+                        ..Default::default()
+                    },
+                    dirty_page_cost,
+                    stable_memory_dirty_page_limit,
+                )?;
+                with_memory_and_system_api(&mut caller, |system_api, memory| {
+                    system_api.ic0_stable64_checkpoint(offset, src, size, &differing_ranges, memory)
+                })
+                .map(|copied_bytes| copied_bytes as i64)
+            }
+        })
+        .unwrap();
+
+    linker
+        .func_wrap("ic0", "stable64_restore", {
+            let log = log.clone();
+            move |mut caller: Caller<'_, StoreData<S>>, dst: i64, offset: i64, size: i64| {
+                charge_for_system_api_call(
+                    &log,
+                    canister_id,
+                    &mut caller,
+                    system_api_complexity::overhead::STABLE64_RESTORE,
+                    size as u32,
+                    &ExecutionComplexity {
+                        cpu: system_api_complexity::cpu::STABLE64_RESTORE,
+                        ..Default::default()
+                    },
+                    NumInstructions::from(0),
+                    stable_memory_dirty_page_limit,
+                )?;
+                with_memory_and_system_api(&mut caller, |system_api, memory| {
+                    system_api.ic0_stable64_restore(dst as u64, offset as u64, size as u64, memory)
+                })
+                .map(|copied_bytes| copied_bytes as i64)
+            }
+        })
+        .unwrap();
+
     linker
         .func_wrap("ic0", "time", {
             move |mut caller: Caller<'_, StoreData<S>>| {
@@ -34,6 +34,8 @@ pub mod overhead {
     pub const STABLE_WRITE: NumInstructions = NumInstructions::new(20);
     pub const STABLE64_READ: NumInstructions = NumInstructions::new(20);
     pub const STABLE64_WRITE: NumInstructions = NumInstructions::new(20);
+    pub const STABLE64_CHECKPOINT: NumInstructions = NumInstructions::new(20);
+    pub const STABLE64_RESTORE: NumInstructions = NumInstructions::new(20);
     pub const PERFORMANCE_COUNTER: NumInstructions = NumInstructions::new(200);
 }
 
@@ -99,6 +101,8 @@ pub mod cpu {
     pub const STABLE64_GROW: NumInstructions = from_nanos(20); // should be 60
     pub const STABLE64_READ: NumInstructions = from_nanos(20); // should be 100
     pub const STABLE64_WRITE: NumInstructions = from_nanos(20); // should be 110
+    pub const STABLE64_CHECKPOINT: NumInstructions = from_nanos(20);
+    pub const STABLE64_RESTORE: NumInstructions = from_nanos(20);
     pub const CANISTER_CYCLES_BALANCE128: NumInstructions = from_nanos(50);
     pub const MSG_CYCLES_AVAILABLE128: NumInstructions = from_nanos(60);
     pub const MSG_CYCLES_ACCEPT128: NumInstructions = from_nanos(80);
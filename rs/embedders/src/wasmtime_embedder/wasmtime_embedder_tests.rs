@@ -54,6 +54,7 @@ fn test_wasmtime_system_api() {
                 MAX_NUM_INSTRUCTIONS,
             ),
             canister_memory_limit,
+            wasm_memory_limit: None,
             compute_allocation: ComputeAllocation::default(),
             subnet_type: SubnetType::Application,
             execution_mode: ExecutionMode::Replicated,
@@ -100,6 +101,7 @@ fn test_wasmtime_system_api() {
         canister_id,
         &store,
         FlagStatus::Enabled,
+        FlagStatus::Disabled,
         config.stable_memory_dirty_page_limit,
     );
     let instance = linker
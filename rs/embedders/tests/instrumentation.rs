@@ -3,6 +3,7 @@ use ic_config::flag_status::FlagStatus;
 use ic_embedders::{
     wasm_utils::{
         instrumentation::{export_additional_symbols, ExportModuleData},
+        new_instrumentation::{exported_global_names, profiled_function_names},
         validate_and_instrument_for_testing,
         validation::RESERVED_SYMBOLS,
         Segments,
@@ -249,3 +250,46 @@ fn test_exports_only_reserved_symbols() {
         assert!(RESERVED_SYMBOLS.contains(&export.field()))
     }
 }
+
+#[test]
+fn instruction_profiling_exports_one_counter_per_function() {
+    let wasm = wabt::Wat2Wasm::new()
+        .write_debug_names(true)
+        .convert(
+            r#"
+        (module
+            (import "ic0" "msg_reply" (func $msg_reply))
+            (func $reply
+                (call $msg_reply)
+            )
+            (func $go
+                (call $reply)
+            )
+            (export "canister_update go" (func $go))
+        )"#,
+        )
+        .map(|wasm| BinaryEncodedWasm::new(wasm.as_ref().to_vec()))
+        .unwrap();
+
+    let mut config = EmbeddersConfig::default();
+    config.feature_flags.new_wasm_transform_lib = FlagStatus::Enabled;
+    config.feature_flags.instruction_profiling = FlagStatus::Enabled;
+    let output =
+        validate_and_instrument_for_testing(&WasmtimeEmbedder::new(config, no_op_logger()), &wasm)
+            .expect("couldn't instrument Wasm code")
+            .1;
+
+    assert_eq!(
+        exported_global_names(&output.binary).unwrap(),
+        vec![
+            "canister counter_instructions",
+            "canister profile_instructions 0",
+            "canister profile_instructions 1",
+        ]
+    );
+
+    assert_eq!(
+        profiled_function_names(&wasm).unwrap(),
+        vec!["reply".to_string(), "go".to_string()]
+    );
+}
@@ -285,6 +285,26 @@ lazy_static! {
             ),
             8_225_000_007,
         ),
+        // Stable memory is not modified, so these measure the comparison of
+        // the whole range with stable memory, which is charged like a write.
+        common::Benchmark(
+            "ic0_stable64_checkpoint()/1B",
+            Module::StableTest.from_ic0(
+                "stable64_checkpoint",
+                Params3(0_i64, 0_i64, 1_i64),
+                Result::I64,
+            ),
+            35_000_007,
+        ),
+        common::Benchmark(
+            "ic0_stable64_checkpoint()/8K",
+            Module::StableTest.from_ic0(
+                "stable64_checkpoint",
+                Params3(0_i64, 0_i64, 8192_i64),
+                Result::I64,
+            ),
+            8_226_000_007,
+        ),
         common::Benchmark(
             "ic0_time()",
             Module::Test.from_ic0("time", NoParams, Result::I64),
@@ -1,7 +1,10 @@
 use crate::execution::install_code::{
     validate_compute_allocation, validate_controller, validate_memory_allocation, OriginalContext,
 };
-use crate::execution::{install::execute_install, upgrade::execute_upgrade};
+use crate::execution::{
+    common::reserve_cycles_for_memory_allocation, install::execute_install,
+    upgrade::execute_upgrade,
+};
 use crate::execution_environment::{CompilationCostHandling, RoundContext, RoundLimits};
 use crate::{
     canister_settings::CanisterSettings,
@@ -12,10 +15,12 @@ use crate::{
 use ic_base_types::NumSeconds;
 use ic_config::flag_status::FlagStatus;
 use ic_cycles_account_manager::CyclesAccountManager;
+use ic_embedders::wasm_utils::decoding::MAX_WASM_MODULE_SIZE_BYTES;
 use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_ic00_types::{
-    CanisterInstallMode, CanisterStatusResultV2, CanisterStatusType, InstallCodeArgs,
-    Method as Ic00Method,
+    CanisterChangeDetails, CanisterChangeOrigin, CanisterInfoResponse, CanisterInstallMode,
+    CanisterStatusResultV2, CanisterStatusType, InstallChunkedCodeArgs, InstallCodeArgs,
+    Method as Ic00Method, QueryStatsResponse, UploadChunkReply,
 };
 use ic_interfaces::execution_environment::{
     CanisterOutOfCyclesError, HypervisorError, IngressHistoryWriter, SubnetAvailableMemory,
@@ -25,6 +30,9 @@ use ic_logger::{error, fatal, info, ReplicaLogger};
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
+    canister_state::system_state::{
+        ChunkHash, MAX_CANISTER_HISTORY_CHANGES, MAX_CHUNKS_PER_CANISTER,
+    },
     CallOrigin, CanisterState, CanisterStatus, NetworkTopology, ReplicatedState, SchedulerState,
     SystemState,
 };
@@ -39,7 +47,7 @@ use ic_types::{
     InvalidMemoryAllocationError, InvalidQueryAllocationError, MemoryAllocation, NumBytes,
     PrincipalId, QueryAllocation, SubnetId, Time,
 };
-use ic_wasm_types::CanisterModule;
+use ic_wasm_types::{CanisterModule, WasmHash};
 use num_traits::cast::ToPrimitive;
 use prometheus::IntCounter;
 use serde::{Deserialize, Serialize};
@@ -132,7 +140,7 @@ impl CanisterMgrConfig {
 
 #[derive(Clone, Debug)]
 pub struct InstallCodeContext {
-    pub sender: PrincipalId,
+    pub origin: CanisterChangeOrigin,
     pub mode: CanisterInstallMode,
     pub canister_id: CanisterId,
     pub wasm_module: CanisterModule,
@@ -142,7 +150,13 @@ pub struct InstallCodeContext {
     pub query_allocation: QueryAllocation,
 }
 
-/// Errors that can occur when converting from (sender, [`InstallCodeArgs`]) to
+impl InstallCodeContext {
+    pub fn sender(&self) -> PrincipalId {
+        self.origin.origin()
+    }
+}
+
+/// Errors that can occur when converting from (origin, [`InstallCodeArgs`]) to
 /// an [`InstallCodeContext`].
 #[derive(Debug)]
 pub enum InstallCodeContextError {
@@ -207,11 +221,11 @@ impl From<InvalidMemoryAllocationError> for InstallCodeContextError {
     }
 }
 
-impl TryFrom<(PrincipalId, InstallCodeArgs)> for InstallCodeContext {
+impl TryFrom<(CanisterChangeOrigin, InstallCodeArgs)> for InstallCodeContext {
     type Error = InstallCodeContextError;
 
-    fn try_from(input: (PrincipalId, InstallCodeArgs)) -> Result<Self, Self::Error> {
-        let (sender, args) = input;
+    fn try_from(input: (CanisterChangeOrigin, InstallCodeArgs)) -> Result<Self, Self::Error> {
+        let (origin, args) = input;
         let canister_id = CanisterId::new(args.canister_id).map_err(|err| {
             InstallCodeContextError::InvalidCanisterId(format!(
                 "Converting canister id {} failed with {}",
@@ -241,7 +255,7 @@ impl TryFrom<(PrincipalId, InstallCodeArgs)> for InstallCodeContext {
         let query_allocation = QueryAllocation::default();
 
         Ok(InstallCodeContext {
-            sender,
+            origin,
             mode: args.mode,
             canister_id,
             wasm_module: CanisterModule::new(args.wasm_module),
@@ -298,6 +312,7 @@ impl CanisterManager {
             // are not allowed to send.
             Err(_)
             | Ok(Ic00Method::CreateCanister)
+            | Ok(Ic00Method::CanisterInfo)
             | Ok(Ic00Method::ECDSAPublicKey)
             | Ok(Ic00Method::SetupInitialDKG)
             | Ok(Ic00Method::SignWithECDSA)
@@ -329,6 +344,9 @@ impl CanisterManager {
             | Ok(Ic00Method::DeleteCanister) |
             Ok(Ic00Method::UpdateSettings)|
             Ok(Ic00Method::InstallCode) |
+            Ok(Ic00Method::UploadChunk) |
+            Ok(Ic00Method::ClearChunkStore) |
+            Ok(Ic00Method::InstallChunkedCode) |
             Ok(Ic00Method::SetController) => {
                 match effective_canister_id {
                     Some(canister_id) => {
@@ -450,17 +468,35 @@ impl CanisterManager {
         if let Some(freezing_threshold) = settings.freezing_threshold {
             canister.system_state.freeze_threshold = freezing_threshold;
         }
+        if let Some(ingress_priority_fee) = settings.ingress_priority_fee {
+            canister.system_state.ingress_priority_fee = ingress_priority_fee;
+        }
+        if let Some(wasm_memory_limit) = settings.wasm_memory_limit {
+            // A limit of zero removes the limit, like a memory allocation of
+            // zero switches the canister back to best-effort memory.
+            canister.system_state.wasm_memory_limit = if wasm_memory_limit.get() == 0 {
+                None
+            } else {
+                Some(wasm_memory_limit)
+            };
+        }
+        if let Some(reserved_cycles_limit) = settings.reserved_cycles_limit {
+            canister.system_state.reserved_balance_limit = Some(reserved_cycles_limit);
+        }
     }
 
     /// Tries to apply the requested settings on the canister identified by
     /// `canister_id`.
     pub(crate) fn update_settings(
         &self,
-        sender: PrincipalId,
+        timestamp: Time,
+        origin: CanisterChangeOrigin,
         settings: CanisterSettings,
         canister: &mut CanisterState,
         round_limits: &mut RoundLimits,
+        subnet_size: usize,
     ) -> Result<(), CanisterManagerError> {
+        let sender = origin.origin();
         // Verify controller.
         validate_controller(canister, &sender)?;
         validate_compute_allocation(
@@ -479,6 +515,16 @@ impl CanisterManager {
         let validated_settings =
             ValidatedCanisterSettings::try_from((settings, self.config.max_controllers))?;
 
+        if let Some(limit) = validated_settings.reserved_cycles_limit {
+            let reserved_cycles = canister.system_state.reserved_balance();
+            if limit < reserved_cycles {
+                return Err(CanisterManagerError::ReservedCyclesLimitIsTooLow {
+                    cycles: reserved_cycles,
+                    limit,
+                });
+            }
+        }
+
         let old_usage = canister.memory_usage(self.config.own_subnet_type);
         let old_mem = canister
             .system_state
@@ -486,9 +532,53 @@ impl CanisterManager {
             .bytes()
             .max(old_usage);
         let old_compute_allocation = canister.scheduler_state.compute_allocation.as_percent();
+        let controllers_changed =
+            validated_settings.controller.is_some() || validated_settings.controllers.is_some();
+        // Only needed to roll back the new settings if the canister cannot
+        // reserve the cycles for a larger memory allocation.
+        let original_canister = validated_settings
+            .memory_allocation
+            .map_or(false, |allocation| allocation.bytes() > old_mem)
+            .then(|| canister.clone());
 
         self.do_update_settings(validated_settings, canister);
 
+        let new_usage = old_usage;
+        let new_mem = canister
+            .system_state
+            .memory_allocation
+            .bytes()
+            .max(new_usage);
+
+        if let Some(original_canister) = original_canister {
+            // Growing the memory allocation allocates memory on the subnet just
+            // like growing the memory during execution does, so it reserves
+            // cycles under the new settings, including the new reserved cycles
+            // limit.
+            let subnet_memory_saturation = self
+                .hypervisor
+                .subnet_memory_saturation(&round_limits.subnet_available_memory);
+            let freezing_threshold = self.cycles_account_manager.freeze_threshold_cycles(
+                canister.system_state.freeze_threshold,
+                canister.system_state.memory_allocation,
+                new_usage,
+                canister.scheduler_state.compute_allocation,
+                subnet_size,
+            );
+            if let Err(err) = reserve_cycles_for_memory_allocation(
+                &mut canister.system_state,
+                new_mem - old_mem,
+                &subnet_memory_saturation,
+                freezing_threshold,
+                subnet_size,
+                &self.cycles_account_manager,
+            ) {
+                let canister_id = canister.canister_id();
+                *canister = original_canister;
+                return Err(CanisterManagerError::Hypervisor(canister_id, err));
+            }
+        }
+
         let new_compute_allocation = canister.scheduler_state.compute_allocation.as_percent();
         if old_compute_allocation < new_compute_allocation {
             round_limits.compute_allocation_used = round_limits
@@ -500,13 +590,6 @@ impl CanisterManager {
                 .saturating_sub(old_compute_allocation - new_compute_allocation);
         }
 
-        let new_usage = old_usage;
-        let new_mem = canister
-            .system_state
-            .memory_allocation
-            .bytes()
-            .max(new_usage);
-
         if new_mem >= old_mem {
             // settings were validated before so this should always succeed
             round_limits
@@ -520,16 +603,149 @@ impl CanisterManager {
         }
 
         canister.system_state.canister_version += 1;
+        if controllers_changed {
+            let new_controllers = canister.system_state.controllers.iter().copied().collect();
+            canister.system_state.add_canister_change(
+                timestamp,
+                origin,
+                CanisterChangeDetails::controllers_change(new_controllers),
+            );
+        }
 
         Ok(())
     }
 
+    /// Stores a chunk of a Wasm module in the chunk store of the canister and
+    /// returns the hash of the chunk.
+    ///
+    /// The chunk is counted towards the memory usage of the canister, so it
+    /// must fit into the canister's memory allocation, if any, or else into the
+    /// remaining memory of the subnet.
+    pub(crate) fn upload_chunk(
+        &self,
+        sender: PrincipalId,
+        canister: &mut CanisterState,
+        chunk: Vec<u8>,
+        round_limits: &mut RoundLimits,
+        subnet_size: usize,
+    ) -> Result<UploadChunkReply, CanisterManagerError> {
+        validate_controller(canister, &sender)?;
+        canister
+            .system_state
+            .wasm_chunk_store
+            .can_insert_chunk(&chunk)
+            .map_err(|err| CanisterManagerError::WasmChunkStoreError {
+                message: err.to_string(),
+            })?;
+
+        if !canister
+            .system_state
+            .wasm_chunk_store
+            .contains_chunk(&chunk)
+        {
+            let chunk_bytes = NumBytes::from(chunk.len() as u64);
+            // The chunks are paid for like any other memory of the canister,
+            // so the canister must be able to afford storing the new chunk
+            // for the duration of its freezing threshold.
+            let threshold = self.cycles_account_manager.freeze_threshold_cycles(
+                canister.system_state.freeze_threshold,
+                canister.memory_allocation(),
+                canister.memory_usage(self.config.own_subnet_type) + chunk_bytes,
+                canister.compute_allocation(),
+                subnet_size,
+            );
+            let available = canister.system_state.debited_balance();
+            if available < threshold {
+                return Err(CanisterManagerError::InsufficientCyclesInMemoryGrow {
+                    bytes: chunk_bytes,
+                    available,
+                    threshold,
+                });
+            }
+            match canister.memory_allocation() {
+                MemoryAllocation::Reserved(allocation) => {
+                    let memory_usage_needed =
+                        canister.memory_usage(self.config.own_subnet_type) + chunk_bytes;
+                    if memory_usage_needed > allocation {
+                        return Err(CanisterManagerError::NotEnoughMemoryAllocationGiven {
+                            canister_id: canister.canister_id(),
+                            memory_allocation_given: canister.memory_allocation(),
+                            memory_usage_needed,
+                        });
+                    }
+                }
+                MemoryAllocation::BestEffort => {
+                    let subnet_memory_saturation = self
+                        .hypervisor
+                        .subnet_memory_saturation(&round_limits.subnet_available_memory);
+                    let available = round_limits.subnet_available_memory.get_total_memory();
+                    round_limits
+                        .subnet_available_memory
+                        .try_decrement(chunk_bytes, NumBytes::from(0))
+                        .map_err(
+                            |_| CanisterManagerError::SubnetMemoryCapacityOverSubscribed {
+                                requested: chunk_bytes,
+                                available: NumBytes::from(available.max(0) as u64),
+                            },
+                        )?;
+                    // The chunk is newly allocated best-effort memory, so it
+                    // reserves cycles like memory allocated during execution.
+                    if let Err(err) = reserve_cycles_for_memory_allocation(
+                        &mut canister.system_state,
+                        chunk_bytes,
+                        &subnet_memory_saturation,
+                        threshold,
+                        subnet_size,
+                        &self.cycles_account_manager,
+                    ) {
+                        round_limits
+                            .subnet_available_memory
+                            .increment(chunk_bytes, NumBytes::from(0));
+                        return Err(CanisterManagerError::Hypervisor(
+                            canister.canister_id(),
+                            err,
+                        ));
+                    }
+                }
+            }
+        }
+
+        let hash = canister
+            .system_state
+            .wasm_chunk_store
+            .insert_chunk(chunk)
+            .map_err(|err| CanisterManagerError::WasmChunkStoreError {
+                message: err.to_string(),
+            })?;
+        Ok(UploadChunkReply {
+            hash: hash.to_vec(),
+        })
+    }
+
+    /// Removes all chunks from the chunk store of the canister.
+    pub(crate) fn clear_chunk_store(
+        &self,
+        sender: PrincipalId,
+        canister: &mut CanisterState,
+        round_limits: &mut RoundLimits,
+    ) -> Result<(), CanisterManagerError> {
+        validate_controller(canister, &sender)?;
+        let freed = canister.system_state.wasm_chunk_store.memory_usage();
+        canister.system_state.wasm_chunk_store.clear();
+        if canister.memory_allocation() == MemoryAllocation::BestEffort {
+            round_limits
+                .subnet_available_memory
+                .increment(freed, NumBytes::from(0));
+        }
+        Ok(())
+    }
+
     /// Creates a new canister and inserts it into `ReplicatedState`.
     ///
     /// Returns the auto-generated id the new canister that has been created.
     pub(crate) fn create_canister(
         &self,
-        sender: PrincipalId,
+        origin: CanisterChangeOrigin,
         sender_subnet_id: SubnetId,
         cycles: Cycles,
         settings: CanisterSettings,
@@ -573,7 +789,7 @@ impl CanisterManager {
             Err(err) => (Err(err), cycles),
             Ok(validate_settings) => {
                 let canister_id = match self.create_canister_helper(
-                    sender,
+                    origin,
                     cycles,
                     fee,
                     validate_settings,
@@ -697,7 +913,7 @@ impl CanisterManager {
         execution_refund_error_counter: &IntCounter,
         subnet_size: usize,
     ) -> DtsInstallCodeResult {
-        if let Err(err) = validate_controller(&canister, &context.sender) {
+        if let Err(err) = validate_controller(&canister, &context.sender()) {
             return DtsInstallCodeResult::Finished {
                 canister,
                 message,
@@ -742,7 +958,7 @@ impl CanisterManager {
             subnet_size,
             requested_compute_allocation: context.compute_allocation,
             requested_memory_allocation: context.memory_allocation,
-            sender: context.sender,
+            origin: context.origin.clone(),
             canister_id: canister.canister_id(),
         };
 
@@ -770,10 +986,11 @@ impl CanisterManager {
     /// See https://sdk.dfinity.org/docs/interface-spec/index.html#ic-uninstall_code
     pub(crate) fn uninstall_code(
         &self,
+        origin: CanisterChangeOrigin,
         canister_id: CanisterId,
-        sender: PrincipalId,
         state: &mut ReplicatedState,
     ) -> Result<(), CanisterManagerError> {
+        let sender = origin.origin();
         let time = state.time();
         let canister = match state.canister_state_mut(&canister_id) {
             Some(canister) => canister,
@@ -788,6 +1005,11 @@ impl CanisterManager {
         }
 
         let rejects = uninstall_canister(&self.log, canister, time);
+        canister.system_state.add_canister_change(
+            time,
+            origin,
+            CanisterChangeDetails::code_uninstall(),
+        );
         crate::util::process_responses(
             rejects,
             state,
@@ -908,6 +1130,35 @@ impl CanisterManager {
         Ok(stop_contexts)
     }
 
+    /// Returns up to `num_requested_changes` most recent changes to the
+    /// canister, together with its current module hash and controllers.
+    /// No changes are returned if `num_requested_changes` is not specified.
+    pub(crate) fn get_canister_info(
+        &self,
+        num_requested_changes: Option<u64>,
+        canister: &CanisterState,
+    ) -> CanisterInfoResponse {
+        let canister_history = canister.system_state.get_canister_history();
+        let num_requested_changes = num_requested_changes
+            .unwrap_or(0)
+            .min(MAX_CANISTER_HISTORY_CHANGES as u64) as usize;
+        let changes = canister_history
+            .get_changes(num_requested_changes)
+            .cloned()
+            .collect();
+        let module_hash = canister
+            .execution_state
+            .as_ref()
+            .map(|execution_state| execution_state.wasm_binary.binary.module_hash().to_vec());
+        let controllers = canister.controllers().iter().copied().collect();
+        CanisterInfoResponse::new(
+            canister_history.get_total_num_changes(),
+            changes,
+            module_hash,
+            controllers,
+        )
+    }
+
     /// Fetches the current status of the canister.
     pub(crate) fn get_canister_status(
         &self,
@@ -932,6 +1183,7 @@ impl CanisterManager {
         let compute_allocation = canister.scheduler_state.compute_allocation;
         let memory_allocation = canister.memory_allocation();
         let freeze_threshold = canister.system_state.freeze_threshold;
+        let total_query_stats = &canister.system_state.total_query_stats;
 
         Ok(CanisterStatusResultV2::new(
             canister.status(),
@@ -954,6 +1206,25 @@ impl CanisterManager {
                     subnet_size,
                 )
                 .get(),
+            canister
+                .system_state
+                .wasm_memory_limit
+                .map(|limit| limit.get()),
+            canister.system_state.reserved_balance().get(),
+            canister
+                .system_state
+                .reserved_balance_limit
+                .map(|limit| limit.get()),
+            QueryStatsResponse {
+                num_calls_total: candid::Nat::from(total_query_stats.num_calls),
+                num_instructions_total: candid::Nat::from(total_query_stats.num_instructions),
+                request_payload_bytes_total: candid::Nat::from(
+                    total_query_stats.ingress_payload_size,
+                ),
+                response_payload_bytes_total: candid::Nat::from(
+                    total_query_stats.egress_payload_size,
+                ),
+            },
         ))
     }
 
@@ -961,18 +1232,29 @@ impl CanisterManager {
     /// the canister is able to run this, otherwise an error is returned.
     pub(crate) fn set_controller(
         &self,
-        sender: PrincipalId,
+        origin: CanisterChangeOrigin,
         canister_id: CanisterId,
         new_controller: PrincipalId,
         state: &mut ReplicatedState,
         round_limits: &mut RoundLimits,
+        subnet_size: usize,
     ) -> Result<(), CanisterManagerError> {
+        let time = state.time();
         let canister = state
             .canister_state_mut(&canister_id)
             .ok_or(CanisterManagerError::CanisterNotFound(canister_id))?;
 
-        let settings = CanisterSettings::new(Some(new_controller), None, None, None, None);
-        self.update_settings(sender, settings, canister, round_limits)
+        let settings = CanisterSettings::new(
+            Some(new_controller),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        self.update_settings(time, origin, settings, canister, round_limits, subnet_size)
     }
 
     /// Permanently deletes a canister from `ReplicatedState`.
@@ -1045,7 +1327,7 @@ impl CanisterManager {
     /// Returns the auto-generated id the new canister that has been created.
     pub(crate) fn create_canister_with_cycles(
         &self,
-        sender: PrincipalId,
+        origin: CanisterChangeOrigin,
         cycles_amount: Option<u128>,
         settings: CanisterSettings,
         specified_id: Option<PrincipalId>,
@@ -1054,6 +1336,7 @@ impl CanisterManager {
         max_number_of_canisters: u64,
         round_limits: &mut RoundLimits,
     ) -> Result<CanisterId, CanisterManagerError> {
+        let sender = origin.origin();
         if !provisional_whitelist.contains(&sender) {
             return Err(CanisterManagerError::SenderNotInWhitelist(sender));
         }
@@ -1072,7 +1355,7 @@ impl CanisterManager {
         ) {
             Err(err) => Err(err),
             Ok(validated_settings) => self.create_canister_helper(
-                sender,
+                origin,
                 cycles,
                 Cycles::new(0),
                 validated_settings,
@@ -1120,7 +1403,7 @@ impl CanisterManager {
 
     fn create_canister_helper(
         &self,
-        sender: PrincipalId,
+        origin: CanisterChangeOrigin,
         cycles: Cycles,
         creation_fee: Cycles,
         settings: ValidatedCanisterSettings,
@@ -1149,6 +1432,7 @@ impl CanisterManager {
         let cycles = cycles - creation_fee;
 
         // Canister id available. Create the new canister.
+        let sender = origin.origin();
         let mut system_state = SystemState::new_running(
             new_canister_id,
             sender,
@@ -1162,6 +1446,17 @@ impl CanisterManager {
         let mut new_canister = CanisterState::new(system_state, None, scheduler_state);
 
         self.do_update_settings(settings, &mut new_canister);
+        let controllers = new_canister
+            .system_state
+            .controllers
+            .iter()
+            .copied()
+            .collect();
+        new_canister.system_state.add_canister_change(
+            state.time(),
+            origin,
+            CanisterChangeDetails::canister_creation(controllers),
+        );
         let new_usage = new_canister.memory_usage(self.config.own_subnet_type);
         let new_mem = new_canister
             .system_state
@@ -1311,6 +1606,25 @@ pub(crate) enum CanisterManagerError {
     CanisterNotHostedBySubnet {
         message: String,
     },
+    WasmChunkStoreError {
+        message: String,
+    },
+    ReservedCyclesLimitIsTooLow {
+        cycles: Cycles,
+        limit: Cycles,
+    },
+    InsufficientCyclesInMemoryGrow {
+        bytes: NumBytes,
+        available: Cycles,
+        threshold: Cycles,
+    },
+    WasmModuleNotFound {
+        module_hash: Vec<u8>,
+    },
+    WasmModuleHashMismatch {
+        expected: Vec<u8>,
+        actual: Vec<u8>,
+    },
 }
 
 impl From<CanisterManagerError> for UserError {
@@ -1467,6 +1781,49 @@ impl From<CanisterManagerError> for UserError {
                     format!("Unsuccessful validation of specified ID: {}", message),
                 )
             }
+            WasmChunkStoreError { message } => {
+                Self::new(
+                    ErrorCode::CanisterContractViolation,
+                    format!("Error from Wasm chunk store: {}", message),
+                )
+            }
+            InsufficientCyclesInMemoryGrow { bytes, available, threshold } => {
+                Self::new(
+                    ErrorCode::InsufficientCyclesInMemoryGrow,
+                    format!(
+                        "Cannot grow memory by {} bytes due to insufficient cycles. At least {} additional cycles are required to stay above the freezing threshold.",
+                        bytes, threshold - available
+                    ),
+                )
+            }
+            WasmModuleNotFound { module_hash } => {
+                Self::new(
+                    ErrorCode::CanisterContractViolation,
+                    format!(
+                        "No canister on this subnet controlled by the sender has a Wasm module with hash {} installed",
+                        hex::encode(module_hash)
+                    ),
+                )
+            }
+            WasmModuleHashMismatch { expected, actual } => {
+                Self::new(
+                    ErrorCode::CanisterContractViolation,
+                    format!(
+                        "Hash of the Wasm module {} does not match the expected hash {}",
+                        hex::encode(actual),
+                        hex::encode(expected)
+                    ),
+                )
+            }
+            ReservedCyclesLimitIsTooLow { cycles, limit } => {
+                Self::new(
+                    ErrorCode::ReservedCyclesLimitIsTooLow,
+                    format!(
+                        "Cannot set the reserved cycles limit {} below the reserved cycles balance of the canister {}.",
+                        limit, cycles
+                    ),
+                )
+            }
         }
     }
 }
@@ -1484,6 +1841,125 @@ impl From<CanisterManagerError> for RejectContext {
     }
 }
 
+/// Assembles the Wasm module of an `install_chunked_code` request from the
+/// chunk store of the canister and verifies the hash of the module. The
+/// resulting context is installed like that of a regular `install_code`.
+pub(crate) fn install_code_context_from_chunks(
+    origin: CanisterChangeOrigin,
+    canister: &CanisterState,
+    args: InstallChunkedCodeArgs,
+) -> Result<InstallCodeContext, UserError> {
+    validate_controller(canister, &origin.origin())?;
+
+    // A chunk may be listed several times, so the number of listed chunks and
+    // the size of the assembled module must be bounded independently of the
+    // contents of the chunk store.
+    if args.chunk_hashes_list.len() > MAX_CHUNKS_PER_CANISTER {
+        return Err(CanisterManagerError::WasmChunkStoreError {
+            message: format!(
+                "Wasm module cannot be assembled from {} chunks, at most {} chunks are allowed",
+                args.chunk_hashes_list.len(),
+                MAX_CHUNKS_PER_CANISTER
+            ),
+        }
+        .into());
+    }
+
+    let chunk_store = &canister.system_state.wasm_chunk_store;
+    let mut wasm_module = Vec::new();
+    for hash in args.chunk_hashes_list.iter() {
+        let chunk = ChunkHash::try_from(hash.as_slice())
+            .ok()
+            .and_then(|hash| chunk_store.get_chunk(&hash))
+            .ok_or_else(|| CanisterManagerError::WasmChunkStoreError {
+                message: format!(
+                    "Chunk with hash {} not found in the chunk store of canister {}",
+                    hex::encode(hash),
+                    canister.canister_id()
+                ),
+            })?;
+        if wasm_module.len() + chunk.len() > MAX_WASM_MODULE_SIZE_BYTES {
+            return Err(CanisterManagerError::WasmChunkStoreError {
+                message: format!(
+                    "Wasm module assembled from chunks exceeds the maximum module size of {} bytes",
+                    MAX_WASM_MODULE_SIZE_BYTES
+                ),
+            }
+            .into());
+        }
+        wasm_module.extend_from_slice(chunk);
+    }
+
+    let install_context = InstallCodeContext::try_from((
+        origin,
+        InstallCodeArgs::new(
+            args.mode,
+            args.get_canister_id(),
+            wasm_module,
+            args.arg,
+            None,
+            None,
+            None,
+        ),
+    ))?;
+    let module_hash = install_context.wasm_module.module_hash();
+    if module_hash[..] != args.wasm_module_hash[..] {
+        return Err(CanisterManagerError::WasmChunkStoreError {
+            message: format!(
+                "Hash of the assembled Wasm module {} does not match the expected hash {}",
+                hex::encode(module_hash),
+                hex::encode(&args.wasm_module_hash)
+            ),
+        }
+        .into());
+    }
+    Ok(install_context)
+}
+
+/// Converts the arguments of an `install_code` request into an install
+/// context. A request that passes only `wasm_module_hash` reuses the module
+/// with that hash from a canister on this subnet that is controlled by the
+/// sender, which saves sending the module again and, thanks to the compilation
+/// cache, compiling it again.
+pub(crate) fn install_code_context_from_args(
+    origin: CanisterChangeOrigin,
+    state: &ReplicatedState,
+    args: InstallCodeArgs,
+) -> Result<InstallCodeContext, UserError> {
+    let expected_hash = args.wasm_module_hash.clone();
+    let module_by_hash = args.wasm_module.is_empty();
+    let mut install_context = InstallCodeContext::try_from((origin, args))?;
+    let expected_hash = match expected_hash {
+        None => return Ok(install_context),
+        Some(expected_hash) => expected_hash,
+    };
+
+    if module_by_hash {
+        install_context.wasm_module = <[u8; 32]>::try_from(expected_hash.as_slice())
+            .ok()
+            .and_then(|module_hash| {
+                state.find_wasm_module_controlled_by(
+                    &WasmHash::from(module_hash),
+                    &install_context.sender(),
+                )
+            })
+            .cloned()
+            .ok_or(CanisterManagerError::WasmModuleNotFound {
+                module_hash: expected_hash,
+            })?;
+    } else {
+        let module_hash = install_context.wasm_module.module_hash();
+        if module_hash[..] != expected_hash[..] {
+            return Err(CanisterManagerError::WasmModuleHashMismatch {
+                expected: expected_hash,
+                actual: module_hash.to_vec(),
+            }
+            .into());
+        }
+    }
+    Ok(install_context)
+}
+
 /// Uninstalls a canister.
 ///
 /// See https://sdk.dfinity.org/docs/interface-spec/index.html#ic-uninstall_code
@@ -1537,7 +2013,7 @@ pub fn uninstall_canister(
                         },
                     }));
                 }
-                CallOrigin::CanisterUpdate(caller_canister_id, callback_id) => {
+                CallOrigin::CanisterUpdate(caller_canister_id, callback_id, deadline) => {
                     rejects.push(Response::Canister(CanisterResponse {
                         originator: *caller_canister_id,
                         respondent: canister_id,
@@ -1547,6 +2023,7 @@ pub fn uninstall_canister(
                             code: RejectCode::CanisterReject,
                             message: String::from("Canister has been uninstalled."),
                         }),
+                        deadline: *deadline,
                     }));
                 }
                 CallOrigin::CanisterQuery(_, _) | CallOrigin::Query(_) => fatal!(
@@ -1572,6 +2049,9 @@ struct ValidatedCanisterSettings {
     pub compute_allocation: Option<ComputeAllocation>,
     pub memory_allocation: Option<MemoryAllocation>,
     pub freezing_threshold: Option<NumSeconds>,
+    pub ingress_priority_fee: Option<Cycles>,
+    pub wasm_memory_limit: Option<NumBytes>,
+    pub reserved_cycles_limit: Option<Cycles>,
 }
 
 impl TryFrom<(CanisterSettings, usize)> for ValidatedCanisterSettings {
@@ -1607,6 +2087,9 @@ impl TryFrom<(CanisterSettings, usize)> for ValidatedCanisterSettings {
             compute_allocation: settings.compute_allocation(),
             memory_allocation: settings.memory_allocation(),
             freezing_threshold: settings.freezing_threshold(),
+            ingress_priority_fee: settings.ingress_priority_fee(),
+            wasm_memory_limit: settings.wasm_memory_limit(),
+            reserved_cycles_limit: settings.reserved_cycles_limit(),
         })
     }
 }
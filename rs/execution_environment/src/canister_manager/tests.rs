@@ -22,10 +22,14 @@ use ic_config::{
 };
 use ic_constants::SMALL_APP_SUBNET_MAX_SIZE;
 use ic_cycles_account_manager::CyclesAccountManager;
+use ic_embedders::wasm_utils::decoding::MAX_WASM_MODULE_SIZE_BYTES;
 use ic_error_types::{ErrorCode, UserError};
 use ic_ic00_types::{
-    CanisterIdRecord, CanisterInstallMode, CanisterSettingsArgs, CanisterStatusType,
-    CreateCanisterArgs, EmptyBlob, InstallCodeArgs, Method, Payload, UpdateSettingsArgs,
+    CanisterChange, CanisterChangeDetails, CanisterChangeOrigin, CanisterIdRecord,
+    CanisterInfoRequest, CanisterInfoResponse, CanisterInstallMode, CanisterSettingsArgs,
+    CanisterStatusResultV2, CanisterStatusType, CreateCanisterArgs, EmptyBlob,
+    InstallChunkedCodeArgs, InstallCodeArgs, Method, Payload, UpdateSettingsArgs, UploadChunkArgs,
+    UploadChunkReply,
 };
 use ic_interfaces::{
     execution_environment::{ExecutionMode, HypervisorError, SubnetAvailableMemory},
@@ -37,8 +41,11 @@ use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_routing_table::{CanisterIdRange, RoutingTable, CANISTER_IDS_PER_SUBNET};
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    page_map, testing::CanisterQueuesTesting, CallContextManager, CallOrigin, CanisterState,
-    CanisterStatus, NumWasmPages, PageMap, ReplicatedState,
+    canister_state::system_state::{MAX_CHUNKS_PER_CANISTER, MAX_CHUNK_SIZE},
+    page_map,
+    testing::CanisterQueuesTesting,
+    CallContextManager, CallOrigin, CanisterState, CanisterStatus, NumWasmPages, PageMap,
+    ReplicatedState,
 };
 use ic_system_api::{ExecutionParameters, InstructionLimits};
 use ic_test_utilities::{
@@ -58,7 +65,7 @@ use ic_test_utilities::{
 };
 use ic_types::{
     ingress::{IngressState, IngressStatus, WasmResult},
-    messages::{CallbackId, StopCanisterContext},
+    messages::{CallbackId, Payload as ResponsePayload, RequestOrResponse, StopCanisterContext},
     nominal_cycles::NominalCycles,
     CanisterId, CanisterTimer, ComputeAllocation, Cycles, MemoryAllocation, NumBytes,
     NumInstructions, QueryAllocation, SubnetId, UserId,
@@ -97,6 +104,7 @@ lazy_static! {
             MAX_NUM_INSTRUCTIONS
         ),
         canister_memory_limit: NumBytes::new(u64::MAX / 2),
+        wasm_memory_limit: None,
         compute_allocation: ComputeAllocation::default(),
         subnet_type: SubnetType::Application,
         execution_mode: ExecutionMode::Replicated,
@@ -109,7 +117,7 @@ pub struct InstallCodeContextBuilder {
 
 impl InstallCodeContextBuilder {
     pub fn sender(mut self, sender: PrincipalId) -> Self {
-        self.ctx.sender = sender;
+        self.ctx.origin = canister_change_origin_from_principal(&sender);
         self
     }
 
@@ -158,7 +166,7 @@ impl Default for InstallCodeContextBuilder {
     fn default() -> Self {
         Self {
             ctx: InstallCodeContext {
-                sender: PrincipalId::new_user_test_id(0),
+                origin: canister_change_origin_from_principal(&PrincipalId::new_user_test_id(0)),
                 canister_id: canister_test_id(0),
                 wasm_module: CanisterModule::new(wabt::wat2wasm(EMPTY_WAT).unwrap()),
                 arg: vec![],
@@ -171,6 +179,12 @@ impl Default for InstallCodeContextBuilder {
     }
 }
 
+/// The tests calling the canister manager directly only care about the
+/// principal initiating a change, so all changes are attributed to users.
+fn canister_change_origin_from_principal(sender: &PrincipalId) -> CanisterChangeOrigin {
+    CanisterChangeOrigin::from_user(*sender)
+}
+
 struct CanisterManagerBuilder {
     cycles_account_manager: CyclesAccountManager,
     subnet_id: SubnetId,
@@ -345,7 +359,7 @@ fn install_canister_makes_subnet_oversubscribed() {
         };
         let canister_id1 = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -358,7 +372,7 @@ fn install_canister_makes_subnet_oversubscribed() {
             .unwrap();
         let canister_id2 = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -371,7 +385,7 @@ fn install_canister_makes_subnet_oversubscribed() {
             .unwrap();
         let canister_id3 = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -481,7 +495,7 @@ fn upgrade_canister_with_no_wasm_fails() {
         };
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -531,7 +545,7 @@ fn can_update_compute_allocation_during_upgrade() {
         };
         let canister_id1 = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 Cycles::new(2_000_000_000_000_000),
                 CanisterSettings::default(),
@@ -601,7 +615,7 @@ fn upgrading_canister_makes_subnet_oversubscribed() {
         let initial_cycles = Cycles::new(30_000_000_000_000);
         let canister_id1 = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 initial_cycles,
                 CanisterSettings::default(),
@@ -614,7 +628,7 @@ fn upgrading_canister_makes_subnet_oversubscribed() {
             .unwrap();
         let canister_id2 = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 initial_cycles,
                 CanisterSettings::default(),
@@ -627,7 +641,7 @@ fn upgrading_canister_makes_subnet_oversubscribed() {
             .unwrap();
         let canister_id3 = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 initial_cycles,
                 CanisterSettings::default(),
@@ -802,7 +816,7 @@ fn can_update_memory_allocation_during_upgrade() {
         };
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -936,7 +950,7 @@ fn can_create_canister() {
         assert_eq!(
             canister_manager
                 .create_canister(
-                    canister,
+                    canister_change_origin_from_principal(&canister),
                     sender_subnet_id,
                     *INITIAL_CYCLES,
                     CanisterSettings::default(),
@@ -952,7 +966,7 @@ fn can_create_canister() {
         assert_eq!(
             canister_manager
                 .create_canister(
-                    canister,
+                    canister_change_origin_from_principal(&canister),
                     sender_subnet_id,
                     *INITIAL_CYCLES,
                     CanisterSettings::default(),
@@ -982,7 +996,7 @@ fn create_canister_fails_if_not_enough_cycles_are_sent_with_the_request() {
 
         assert_eq!(
             canister_manager.create_canister(
-                canister,
+                canister_change_origin_from_principal(&canister),
                 sender_subnet_id,
                 Cycles::new(100),
                 CanisterSettings::default(),
@@ -1018,7 +1032,7 @@ fn can_create_canister_with_extra_cycles() {
         assert_eq!(
             canister_manager
                 .create_canister(
-                    canister,
+                    canister_change_origin_from_principal(&canister),
                     sender_subnet_id,
                     Cycles::from(cycles),
                     CanisterSettings::default(),
@@ -1047,7 +1061,7 @@ fn cannot_install_non_empty_canister() {
         };
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -1111,7 +1125,7 @@ fn install_code_with_wrong_controller_fails() {
         // Create a canister with canister_test_id 1 as controller.
         let canister_id = canister_manager
             .create_canister(
-                canister_test_id(1).get(),
+                canister_change_origin_from_principal(&canister_test_id(1).get()),
                 subnet_test_id(1),
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -1175,7 +1189,7 @@ fn create_canister_sets_correct_allocations() {
         };
         let canister_id = canister_manager
             .create_canister(
-                canister_test_id(1).get(),
+                canister_change_origin_from_principal(&canister_test_id(1).get()),
                 subnet_test_id(1),
                 *INITIAL_CYCLES,
                 settings,
@@ -1203,7 +1217,7 @@ fn create_canister_updates_consumed_cycles_metric_correctly() {
         };
         let canister_id = canister_manager
             .create_canister(
-                canister_test_id(1).get(),
+                canister_change_origin_from_principal(&canister_test_id(1).get()),
                 subnet_test_id(1),
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -1243,7 +1257,7 @@ fn provisional_create_canister_has_no_creation_fee() {
         };
         let canister_id = canister_manager
             .create_canister_with_cycles(
-                canister_test_id(1).get(),
+                canister_change_origin_from_principal(&canister_test_id(1).get()),
                 Some(INITIAL_CYCLES.get()),
                 CanisterSettings::default(),
                 None,
@@ -1278,7 +1292,7 @@ fn reinstall_on_empty_canister_succeeds() {
         let sender = canister_test_id(42).get();
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 subnet_test_id(1),
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -1385,7 +1399,7 @@ fn install_puts_canister_back_after_invalid_wasm() {
         let sender_subnet_id = subnet_test_id(1);
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -1440,7 +1454,7 @@ fn reinstall_clears_stable_memory() {
         let sender_subnet_id = subnet_test_id(1);
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -1533,7 +1547,7 @@ fn stop_a_running_canister() {
         let sender_subnet_id = subnet_test_id(1);
         let canister_id = canister_manager
             .create_canister(
-                sender.get(),
+                canister_change_origin_from_principal(&sender.get()),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -1665,7 +1679,7 @@ fn stop_a_canister_with_incorrect_controller() {
         let sender_subnet_id = subnet_test_id(1);
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -1738,7 +1752,7 @@ fn start_a_canister_with_incorrect_controller() {
         let sender_subnet_id = subnet_test_id(1);
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -1776,7 +1790,7 @@ fn starting_an_already_running_canister_keeps_it_running() {
         let sender_subnet_id = subnet_test_id(1);
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -1883,7 +1897,7 @@ fn get_canister_status_with_incorrect_controller() {
         let sender_subnet_id = subnet_test_id(1);
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -1921,7 +1935,7 @@ fn get_canister_status_of_running_canister() {
         let sender_subnet_id = subnet_test_id(1);
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -1954,7 +1968,7 @@ fn get_canister_status_of_self() {
         let sender_subnet_id = subnet_test_id(1);
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -2029,11 +2043,12 @@ fn set_controller_with_incorrect_controller() {
         // Set the controller from the wrong controller. Should fail.
         assert_eq!(
             canister_manager.set_controller(
-                wrong_controller,
+                canister_change_origin_from_principal(&wrong_controller),
                 canister_id,
                 new_controller,
                 &mut state,
                 &mut round_limits,
+                SMALL_APP_SUBNET_MAX_SIZE,
             ),
             Err(CanisterManagerError::CanisterInvalidController {
                 canister_id,
@@ -2072,11 +2087,12 @@ fn set_controller_with_correct_controller() {
         // Set the controller from the correct controller. Should succeed.
         assert!(canister_manager
             .set_controller(
-                controller,
+                canister_change_origin_from_principal(&controller),
                 canister_id,
                 new_controller,
                 &mut state,
-                &mut round_limits
+                &mut round_limits,
+                SMALL_APP_SUBNET_MAX_SIZE,
             )
             .is_ok());
 
@@ -2215,7 +2231,7 @@ fn install_canister_with_query_allocation() {
         let sender_subnet_id = subnet_test_id(1);
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -2286,7 +2302,7 @@ fn create_canister_with_cycles_sender_in_whitelist() {
     let sender = canister_test_id(1).get();
     let canister_id = canister_manager
         .create_canister_with_cycles(
-            sender,
+            canister_change_origin_from_principal(&sender),
             Some(123),
             CanisterSettings::default(),
             None,
@@ -2321,7 +2337,7 @@ fn create_canister_with_specified_id(
     let creator = canister_test_id(1).get();
 
     let creation_result = canister_manager.create_canister_with_cycles(
-        creator,
+        canister_change_origin_from_principal(&creator),
         Some(123),
         CanisterSettings::default(),
         Some(specified_id),
@@ -2452,7 +2468,7 @@ fn installing_a_canister_with_not_enough_memory_allocation_fails() {
         let sender_subnet_id = subnet_test_id(1);
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -2545,7 +2561,7 @@ fn upgrading_canister_with_not_enough_memory_allocation_fails() {
         let sender_subnet_id = subnet_test_id(1);
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -2669,7 +2685,7 @@ fn installing_a_canister_with_not_enough_cycles_fails() {
         let sender_subnet_id = subnet_test_id(1);
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 // Give the new canister a relatively small number of cycles so it doesn't have
                 // enough to be installed.
@@ -2778,7 +2794,7 @@ fn failed_upgrade_hooks_consume_instructions() {
         let sender = canister_test_id(100).get();
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -2793,7 +2809,7 @@ fn failed_upgrade_hooks_consume_instructions() {
         let res = install_code(
             &canister_manager,
             InstallCodeContext {
-                sender,
+                origin: canister_change_origin_from_principal(&sender),
                 canister_id,
                 wasm_module: CanisterModule::new(initial_wasm),
                 arg: vec![],
@@ -2818,7 +2834,7 @@ fn failed_upgrade_hooks_consume_instructions() {
         let (instructions_left, result, _) = install_code(
             &canister_manager,
             InstallCodeContext {
-                sender,
+                origin: canister_change_origin_from_principal(&sender),
                 canister_id,
                 wasm_module: CanisterModule::new(upgrade_wasm),
                 arg: vec![],
@@ -2920,7 +2936,7 @@ fn failed_install_hooks_consume_instructions() {
         let sender = canister_test_id(100).get();
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -2936,7 +2952,7 @@ fn failed_install_hooks_consume_instructions() {
         let (instructions_left, result, _) = install_code(
             &canister_manager,
             InstallCodeContext {
-                sender,
+                origin: canister_change_origin_from_principal(&sender),
                 canister_id,
                 wasm_module: CanisterModule::new(wasm),
                 arg: vec![],
@@ -3004,7 +3020,7 @@ fn install_code_respects_instruction_limit() {
     let sender = canister_test_id(100).get();
     let canister_id = canister_manager
         .create_canister(
-            sender,
+            canister_change_origin_from_principal(&sender),
             subnet_id,
             *INITIAL_CYCLES,
             CanisterSettings::default(),
@@ -3052,7 +3068,7 @@ fn install_code_respects_instruction_limit() {
     let (instructions_left, result, canister) = install_code(
         &canister_manager,
         InstallCodeContext {
-            sender,
+            origin: canister_change_origin_from_principal(&sender),
             canister_id,
             wasm_module: CanisterModule::new(wasm.clone()),
             arg: vec![],
@@ -3083,7 +3099,7 @@ fn install_code_respects_instruction_limit() {
     let (instructions_left, result, canister) = install_code(
         &canister_manager,
         InstallCodeContext {
-            sender,
+            origin: canister_change_origin_from_principal(&sender),
             canister_id,
             wasm_module: CanisterModule::new(wasm.clone()),
             arg: vec![],
@@ -3108,7 +3124,7 @@ fn install_code_respects_instruction_limit() {
     let (instructions_left, result, canister) = install_code(
         &canister_manager,
         InstallCodeContext {
-            sender,
+            origin: canister_change_origin_from_principal(&sender),
             canister_id,
             wasm_module: CanisterModule::new(wasm.clone()),
             arg: vec![],
@@ -3139,7 +3155,7 @@ fn install_code_respects_instruction_limit() {
     let (instructions_left, result, _) = install_code(
         &canister_manager,
         InstallCodeContext {
-            sender,
+            origin: canister_change_origin_from_principal(&sender),
             canister_id,
             wasm_module: CanisterModule::new(wasm),
             arg: vec![],
@@ -3348,7 +3364,7 @@ fn lower_memory_allocation_than_usage_fails() {
         let sender = canister_test_id(100).get();
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -3363,7 +3379,7 @@ fn lower_memory_allocation_than_usage_fails() {
         let res = install_code(
             &canister_manager,
             InstallCodeContext {
-                sender,
+                origin: canister_change_origin_from_principal(&sender),
                 canister_id,
                 wasm_module: CanisterModule::new(wasm),
                 arg: vec![],
@@ -3384,12 +3400,22 @@ fn lower_memory_allocation_than_usage_fails() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(2)).unwrap()),
             None,
+            None,
+            None,
+            None,
         );
 
         let canister = state.canister_state_mut(&canister_id).unwrap();
 
         assert_matches!(
-            canister_manager.update_settings(sender, settings, canister, &mut round_limits,),
+            canister_manager.update_settings(
+                mock_time(),
+                canister_change_origin_from_principal(&sender),
+                settings,
+                canister,
+                &mut round_limits,
+                SMALL_APP_SUBNET_MAX_SIZE,
+            ),
             Err(CanisterManagerError::NotEnoughMemoryAllocationGiven { .. })
         );
     })
@@ -3412,10 +3438,13 @@ fn test_install_when_updating_memory_allocation_via_canister_settings() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(2)).unwrap()),
             None,
+            None,
+            None,
+            None,
         );
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 subnet_id,
                 *INITIAL_CYCLES,
                 settings,
@@ -3431,7 +3460,7 @@ fn test_install_when_updating_memory_allocation_via_canister_settings() {
         let res = install_code(
             &canister_manager,
             InstallCodeContext {
-                sender,
+                origin: canister_change_origin_from_principal(&sender),
                 canister_id,
                 wasm_module: CanisterModule::new(wasm.clone()),
                 arg: vec![],
@@ -3457,18 +3486,28 @@ fn test_install_when_updating_memory_allocation_via_canister_settings() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(MEMORY_CAPACITY.get() / 2)).unwrap()),
             None,
+            None,
+            None,
+            None,
         );
 
         let canister = state.canister_state_mut(&canister_id).unwrap();
 
         canister_manager
-            .update_settings(sender, settings, canister, &mut round_limits)
+            .update_settings(
+                mock_time(),
+                canister_change_origin_from_principal(&sender),
+                settings,
+                canister,
+                &mut round_limits,
+                SMALL_APP_SUBNET_MAX_SIZE,
+            )
             .unwrap();
 
         install_code(
             &canister_manager,
             InstallCodeContext {
-                sender,
+                origin: canister_change_origin_from_principal(&sender),
                 canister_id,
                 wasm_module: CanisterModule::new(wasm),
                 arg: vec![],
@@ -3502,6 +3541,9 @@ fn test_upgrade_when_updating_memory_allocation_via_canister_settings() {
                 MemoryAllocation::try_from(NumBytes::from(WASM_PAGE_SIZE_IN_BYTES + 100)).unwrap(),
             ),
             None,
+            None,
+            None,
+            None,
         );
         let wat = r#"
         (module
@@ -3510,7 +3552,7 @@ fn test_upgrade_when_updating_memory_allocation_via_canister_settings() {
         let wasm = wabt::wat2wasm(wat).unwrap();
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 subnet_id,
                 *INITIAL_CYCLES,
                 settings,
@@ -3525,7 +3567,7 @@ fn test_upgrade_when_updating_memory_allocation_via_canister_settings() {
         let res = install_code(
             &canister_manager,
             InstallCodeContext {
-                sender,
+                origin: canister_change_origin_from_principal(&sender),
                 canister_id,
                 wasm_module: CanisterModule::new(wasm),
                 arg: vec![],
@@ -3551,7 +3593,7 @@ fn test_upgrade_when_updating_memory_allocation_via_canister_settings() {
         let res = install_code(
             &canister_manager,
             InstallCodeContext {
-                sender,
+                origin: canister_change_origin_from_principal(&sender),
                 canister_id,
                 wasm_module: CanisterModule::new(wasm.clone()),
                 arg: vec![],
@@ -3580,18 +3622,28 @@ fn test_upgrade_when_updating_memory_allocation_via_canister_settings() {
                     .unwrap(),
             ),
             None,
+            None,
+            None,
+            None,
         );
 
         let canister = state.canister_state_mut(&canister_id).unwrap();
 
         canister_manager
-            .update_settings(sender, settings, canister, &mut round_limits)
+            .update_settings(
+                mock_time(),
+                canister_change_origin_from_principal(&sender),
+                settings,
+                canister,
+                &mut round_limits,
+                SMALL_APP_SUBNET_MAX_SIZE,
+            )
             .unwrap();
 
         install_code(
             &canister_manager,
             InstallCodeContext {
-                sender,
+                origin: canister_change_origin_from_principal(&sender),
                 canister_id,
                 wasm_module: CanisterModule::new(wasm),
                 arg: vec![],
@@ -3632,8 +3684,8 @@ fn uninstall_code_can_be_invoked_by_governance_canister() {
 
     canister_manager
         .uninstall_code(
+            canister_change_origin_from_principal(&GOVERNANCE_CANISTER_ID.get()),
             canister_test_id(0),
-            GOVERNANCE_CANISTER_ID.get(),
             &mut state,
         )
         .unwrap();
@@ -3659,10 +3711,10 @@ fn test_install_when_setting_memory_allocation_to_zero() {
         let wasm = ic_test_utilities::universal_canister::UNIVERSAL_CANISTER_WASM.to_vec();
 
         let sender = canister_test_id(100).get();
-        let settings = CanisterSettings::new(None, None, None, None, None);
+        let settings = CanisterSettings::new(None, None, None, None, None, None, None, None);
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 subnet_id,
                 *INITIAL_CYCLES,
                 settings,
@@ -3681,24 +3733,29 @@ fn test_install_when_setting_memory_allocation_to_zero() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(0)).unwrap()),
             None,
+            None,
+            None,
+            None,
         );
 
         let canister = state.canister_state_mut(&canister_id).unwrap();
 
         canister_manager
             .update_settings(
-                sender,
+                mock_time(),
+                canister_change_origin_from_principal(&sender),
                 settings,
                 canister,
                 //memory_allocation_used,
                 &mut round_limits,
+                SMALL_APP_SUBNET_MAX_SIZE,
             )
             .unwrap();
 
         install_code(
             &canister_manager,
             InstallCodeContext {
-                sender,
+                origin: canister_change_origin_from_principal(&sender),
                 canister_id,
                 wasm_module: CanisterModule::new(wasm),
                 arg: vec![],
@@ -3732,10 +3789,13 @@ fn test_upgrade_when_setting_memory_allocation_to_zero() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(MEMORY_CAPACITY.get() / 2)).unwrap()),
             None,
+            None,
+            None,
+            None,
         );
         let canister_id = canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 subnet_id,
                 *INITIAL_CYCLES,
                 settings,
@@ -3750,7 +3810,7 @@ fn test_upgrade_when_setting_memory_allocation_to_zero() {
         let res = install_code(
             &canister_manager,
             InstallCodeContext {
-                sender,
+                origin: canister_change_origin_from_principal(&sender),
                 canister_id,
                 wasm_module: CanisterModule::new(wasm.clone()),
                 arg: vec![],
@@ -3772,18 +3832,28 @@ fn test_upgrade_when_setting_memory_allocation_to_zero() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(0)).unwrap()),
             None,
+            None,
+            None,
+            None,
         );
 
         let canister = state.canister_state_mut(&canister_id).unwrap();
 
         canister_manager
-            .update_settings(sender, settings, canister, &mut round_limits)
+            .update_settings(
+                mock_time(),
+                canister_change_origin_from_principal(&sender),
+                settings,
+                canister,
+                &mut round_limits,
+                SMALL_APP_SUBNET_MAX_SIZE,
+            )
             .unwrap();
 
         install_code(
             &canister_manager,
             InstallCodeContext {
-                sender,
+                origin: canister_change_origin_from_principal(&sender),
                 canister_id,
                 wasm_module: CanisterModule::new(wasm),
                 arg: vec![],
@@ -3814,7 +3884,7 @@ fn max_number_of_canisters_is_respected_when_creating_canisters() {
         // Create 3 canisters with `max_number_of_canisters = 3`, should succeed.
         canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -3827,7 +3897,7 @@ fn max_number_of_canisters_is_respected_when_creating_canisters() {
             .unwrap();
         canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -3840,7 +3910,7 @@ fn max_number_of_canisters_is_respected_when_creating_canisters() {
             .unwrap();
         canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -3856,7 +3926,7 @@ fn max_number_of_canisters_is_respected_when_creating_canisters() {
         // Creating a fourth canister with 3 already created and
         // `max_number_of_canisters = 3` should fail.
         let (res, _) = canister_manager.create_canister(
-            sender,
+            canister_change_origin_from_principal(&sender),
             sender_subnet_id,
             *INITIAL_CYCLES,
             CanisterSettings::default(),
@@ -3874,7 +3944,7 @@ fn max_number_of_canisters_is_respected_when_creating_canisters() {
         // `max_number_of_canisters = 10` should succeed.
         canister_manager
             .create_canister(
-                sender,
+                canister_change_origin_from_principal(&sender),
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
@@ -4068,6 +4138,7 @@ fn install_code_context_conversion_u128() {
         compute_allocation: Some(candid::Nat::from(u128::MAX)),
         memory_allocation: Some(candid::Nat::from(u128::MAX)),
         query_allocation: Some(candid::Nat::from(u128::MAX)),
+        wasm_module_hash: None,
     };
 
     assert!(InstallCodeContext::try_from((
@@ -4131,6 +4202,115 @@ fn unfreezing_of_frozen_canister() {
     get_reply(result);
 }
 
+#[test]
+fn update_settings_sets_ingress_priority_fee() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    assert_eq!(
+        test.canister_state(canister_id)
+            .system_state
+            .ingress_priority_fee,
+        Cycles::zero()
+    );
+
+    let payload = UpdateSettingsArgs {
+        canister_id: canister_id.get(),
+        settings: CanisterSettingsArgs {
+            ingress_priority_fee: Some(candid::Nat::from(1_000_u64)),
+            ..Default::default()
+        },
+    }
+    .encode();
+    get_reply(test.subnet_message(Method::UpdateSettings, payload));
+    assert_eq!(
+        test.canister_state(canister_id)
+            .system_state
+            .ingress_priority_fee,
+        Cycles::new(1_000)
+    );
+
+    // Settings that do not mention the fee leave it unchanged.
+    let payload = UpdateSettingsArgs {
+        canister_id: canister_id.get(),
+        settings: CanisterSettingsArgs {
+            freezing_threshold: Some(candid::Nat::from(0_u64)),
+            ..Default::default()
+        },
+    }
+    .encode();
+    get_reply(test.subnet_message(Method::UpdateSettings, payload));
+    assert_eq!(
+        test.canister_state(canister_id)
+            .system_state
+            .ingress_priority_fee,
+        Cycles::new(1_000)
+    );
+}
+
+#[test]
+fn update_settings_rejects_out_of_range_ingress_priority_fee() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let payload = UpdateSettingsArgs {
+        canister_id: canister_id.get(),
+        settings: CanisterSettingsArgs {
+            ingress_priority_fee: Some(candid::Nat::from(u128::MAX) + candid::Nat::from(1_u64)),
+            ..Default::default()
+        },
+    }
+    .encode();
+    let err = test
+        .subnet_message(Method::UpdateSettings, payload)
+        .unwrap_err();
+    assert_eq!(ErrorCode::CanisterContractViolation, err.code());
+}
+
+#[test]
+fn canister_status_reports_wasm_memory_limit() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let status = match test.canister_status(canister_id).unwrap() {
+        WasmResult::Reply(bytes) => CanisterStatusResultV2::decode(&bytes).unwrap(),
+        WasmResult::Reject(msg) => panic!("Unexpected reject {}", msg),
+    };
+    assert_eq!(status.wasm_memory_limit(), None);
+
+    test.update_wasm_memory_limit(canister_id, NumBytes::new(1 << 30))
+        .unwrap();
+    assert_eq!(
+        test.canister_state(canister_id)
+            .system_state
+            .wasm_memory_limit,
+        Some(NumBytes::new(1 << 30))
+    );
+    let status = match test.canister_status(canister_id).unwrap() {
+        WasmResult::Reply(bytes) => CanisterStatusResultV2::decode(&bytes).unwrap(),
+        WasmResult::Reject(msg) => panic!("Unexpected reject {}", msg),
+    };
+    assert_eq!(status.wasm_memory_limit(), Some(1 << 30));
+}
+
+#[test]
+fn update_settings_clears_wasm_memory_limit_when_set_to_zero() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    test.update_wasm_memory_limit(canister_id, NumBytes::new(1 << 30))
+        .unwrap();
+    test.update_wasm_memory_limit(canister_id, NumBytes::new(0))
+        .unwrap();
+    assert_eq!(
+        test.canister_state(canister_id)
+            .system_state
+            .wasm_memory_limit,
+        None
+    );
+    let status = match test.canister_status(canister_id).unwrap() {
+        WasmResult::Reply(bytes) => CanisterStatusResultV2::decode(&bytes).unwrap(),
+        WasmResult::Reject(msg) => panic!("Unexpected reject {}", msg),
+    };
+    assert_eq!(status.wasm_memory_limit(), None);
+}
+
 #[test]
 fn create_canister_fails_if_memory_capacity_exceeded() {
     let mut test = ExecutionTestBuilder::new()
@@ -5063,3 +5243,509 @@ fn install_code_cannot_switch_from_reserved_to_best_effort_memory_allocation() {
         NumWasmPages::from(10)
     )
 }
+
+fn upload_chunks(test: &mut ExecutionTest, canister_id: CanisterId, wasm: &[u8]) -> Vec<Vec<u8>> {
+    let (first, second) = wasm.split_at(wasm.len() / 2);
+    [first, second]
+        .iter()
+        .map(|chunk| {
+            let args = UploadChunkArgs::new(canister_id, chunk.to_vec());
+            let reply = get_reply(test.subnet_message(Method::UploadChunk, args.encode()));
+            UploadChunkReply::decode(&reply).unwrap().hash
+        })
+        .collect()
+}
+
+#[test]
+fn install_chunked_code_installs_module_assembled_from_chunks() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    let wasm = wabt::wat2wasm(r#"(module (memory 1))"#).unwrap();
+
+    let chunk_hashes = upload_chunks(&mut test, canister_id, &wasm);
+    let chunk_store = &test
+        .canister_state(canister_id)
+        .system_state
+        .wasm_chunk_store;
+    assert_eq!(chunk_store.len(), 2);
+    assert_eq!(
+        chunk_store.memory_usage(),
+        NumBytes::from(wasm.len() as u64)
+    );
+
+    let args = InstallChunkedCodeArgs::new(
+        CanisterInstallMode::Install,
+        canister_id,
+        chunk_hashes,
+        ic_crypto_sha::Sha256::hash(&wasm).to_vec(),
+        vec![],
+    );
+    test.subnet_message(Method::InstallChunkedCode, args.encode())
+        .unwrap();
+    assert_eq!(
+        test.execution_state(canister_id)
+            .wasm_binary
+            .binary
+            .as_slice(),
+        &wasm[..]
+    );
+}
+
+#[test]
+fn install_chunked_code_rejects_module_hash_mismatch() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    let wasm = wabt::wat2wasm(r#"(module (memory 1))"#).unwrap();
+
+    let mut chunk_hashes = upload_chunks(&mut test, canister_id, &wasm);
+    // Assemble the chunks in the wrong order.
+    chunk_hashes.reverse();
+    let args = InstallChunkedCodeArgs::new(
+        CanisterInstallMode::Install,
+        canister_id,
+        chunk_hashes,
+        ic_crypto_sha::Sha256::hash(&wasm).to_vec(),
+        vec![],
+    );
+    let err = test
+        .subnet_message(Method::InstallChunkedCode, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+    assert!(test.canister_state(canister_id).execution_state.is_none());
+}
+
+#[test]
+fn install_chunked_code_rejects_unknown_chunk() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    let wasm = wabt::wat2wasm(r#"(module (memory 1))"#).unwrap();
+
+    let args = InstallChunkedCodeArgs::new(
+        CanisterInstallMode::Install,
+        canister_id,
+        vec![ic_crypto_sha::Sha256::hash(&wasm).to_vec()],
+        ic_crypto_sha::Sha256::hash(&wasm).to_vec(),
+        vec![],
+    );
+    let err = test
+        .subnet_message(Method::InstallChunkedCode, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+}
+
+#[test]
+fn install_chunked_code_rejects_too_many_chunk_hashes() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    let chunk = vec![0; 1024];
+    let args = UploadChunkArgs::new(canister_id, chunk.clone());
+    let reply = get_reply(test.subnet_message(Method::UploadChunk, args.encode()));
+    let hash = UploadChunkReply::decode(&reply).unwrap().hash;
+
+    // Listing the same chunk repeatedly must not get around the chunk limit.
+    let args = InstallChunkedCodeArgs::new(
+        CanisterInstallMode::Install,
+        canister_id,
+        vec![hash; MAX_CHUNKS_PER_CANISTER + 1],
+        ic_crypto_sha::Sha256::hash(&chunk).to_vec(),
+        vec![],
+    );
+    let err = test
+        .subnet_message(Method::InstallChunkedCode, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+    assert!(err.description().contains("chunks are allowed"));
+    assert!(test.canister_state(canister_id).execution_state.is_none());
+}
+
+#[test]
+fn install_chunked_code_rejects_module_exceeding_max_size() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    let chunk = vec![0; MAX_CHUNK_SIZE];
+    let args = UploadChunkArgs::new(canister_id, chunk.clone());
+    let reply = get_reply(test.subnet_message(Method::UploadChunk, args.encode()));
+    let hash = UploadChunkReply::decode(&reply).unwrap().hash;
+
+    // A single stored chunk listed often enough exceeds the maximum module
+    // size, which must be detected before the module is assembled.
+    let args = InstallChunkedCodeArgs::new(
+        CanisterInstallMode::Install,
+        canister_id,
+        vec![hash; MAX_WASM_MODULE_SIZE_BYTES / MAX_CHUNK_SIZE + 1],
+        ic_crypto_sha::Sha256::hash(&chunk).to_vec(),
+        vec![],
+    );
+    let err = test
+        .subnet_message(Method::InstallChunkedCode, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+    assert!(err
+        .description()
+        .contains("exceeds the maximum module size"));
+    assert!(test.canister_state(canister_id).execution_state.is_none());
+}
+
+#[test]
+fn install_code_by_module_hash_reuses_module_installed_on_subnet() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wasm = wabt::wat2wasm(r#"(module (memory 1))"#).unwrap();
+    let first = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    test.install_canister(first, wasm.clone()).unwrap();
+
+    let second = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    let args = InstallCodeArgs::new_from_module_hash(
+        CanisterInstallMode::Install,
+        second,
+        ic_crypto_sha::Sha256::hash(&wasm).to_vec(),
+        vec![],
+        None,
+        None,
+        None,
+    );
+    test.subnet_message(Method::InstallCode, args.encode())
+        .unwrap();
+    assert_eq!(
+        test.execution_state(second).wasm_binary.binary.as_slice(),
+        &wasm[..]
+    );
+}
+
+#[test]
+fn install_code_by_module_hash_rejects_module_of_canister_not_controlled_by_sender() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wasm = wabt::wat2wasm(r#"(module (memory 1))"#).unwrap();
+    let first = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    test.install_canister(first, wasm.clone()).unwrap();
+    test.set_controller(first, user_test_id(42).get()).unwrap();
+
+    let second = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    let args = InstallCodeArgs::new_from_module_hash(
+        CanisterInstallMode::Install,
+        second,
+        ic_crypto_sha::Sha256::hash(&wasm).to_vec(),
+        vec![],
+        None,
+        None,
+        None,
+    );
+    let err = test
+        .subnet_message(Method::InstallCode, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+    assert!(test.canister_state(second).execution_state.is_none());
+}
+
+#[test]
+fn install_code_by_module_hash_rejects_unknown_module() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wasm = wabt::wat2wasm(r#"(module (memory 1))"#).unwrap();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000_000));
+
+    let args = InstallCodeArgs::new_from_module_hash(
+        CanisterInstallMode::Install,
+        canister_id,
+        ic_crypto_sha::Sha256::hash(&wasm).to_vec(),
+        vec![],
+        None,
+        None,
+        None,
+    );
+    let err = test
+        .subnet_message(Method::InstallCode, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+    assert!(test.canister_state(canister_id).execution_state.is_none());
+}
+
+#[test]
+fn install_code_rejects_module_not_matching_module_hash() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wasm = wabt::wat2wasm(r#"(module (memory 1))"#).unwrap();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000_000));
+
+    let mut args = InstallCodeArgs::new(
+        CanisterInstallMode::Install,
+        canister_id,
+        wasm,
+        vec![],
+        None,
+        None,
+        None,
+    );
+    args.wasm_module_hash = Some(vec![0; 32]);
+    let err = test
+        .subnet_message(Method::InstallCode, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+    assert!(test.canister_state(canister_id).execution_state.is_none());
+}
+
+#[test]
+fn upload_chunk_fails_when_below_freezing_threshold() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+    let memory_usage = test
+        .canister_state(canister_id)
+        .memory_usage(SubnetType::Application);
+    let chunk = vec![1; MAX_CHUNK_SIZE];
+    let threshold = test.cycles_account_manager().freeze_threshold_cycles(
+        NumSeconds::from(1),
+        MemoryAllocation::BestEffort,
+        memory_usage + NumBytes::from(chunk.len() as u64),
+        ComputeAllocation::zero(),
+        test.subnet_size(),
+    );
+    // Pick a freezing threshold so that the canister can afford its current
+    // memory, but not the additional chunk.
+    let balance = test.canister_state(canister_id).system_state.balance();
+    let freeze_threshold = balance.get() / threshold.get() * 2;
+    test.update_freezing_threshold(canister_id, NumSeconds::from(freeze_threshold as u64))
+        .unwrap();
+
+    let args = UploadChunkArgs::new(canister_id, chunk);
+    let err = test
+        .subnet_message(Method::UploadChunk, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InsufficientCyclesInMemoryGrow);
+    assert!(test
+        .canister_state(canister_id)
+        .system_state
+        .wasm_chunk_store
+        .is_empty());
+}
+
+#[test]
+fn upload_chunk_reserves_cycles_above_subnet_memory_threshold() {
+    let mut test = ExecutionTestBuilder::new()
+        .with_subnet_total_memory(1 << 30)
+        .with_subnet_memory_threshold(0)
+        .build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    let balance_before = test.canister_state(canister_id).system_state.balance();
+
+    let args = UploadChunkArgs::new(canister_id, vec![1; MAX_CHUNK_SIZE]);
+    test.subnet_message(Method::UploadChunk, args.encode())
+        .unwrap();
+    let reserved = test
+        .canister_state(canister_id)
+        .system_state
+        .reserved_balance();
+    let balance = test.canister_state(canister_id).system_state.balance();
+    assert!(reserved > Cycles::zero());
+    assert!(balance_before - balance >= reserved);
+}
+
+#[test]
+fn upload_chunk_fails_when_reserved_cycles_limit_is_exceeded() {
+    let mut test = ExecutionTestBuilder::new()
+        .with_subnet_total_memory(1 << 30)
+        .with_subnet_memory_threshold(0)
+        .build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    test.update_reserved_cycles_limit(canister_id, Cycles::zero())
+        .unwrap();
+    let subnet_available_memory_before = test.subnet_available_memory().get_total_memory();
+
+    let args = UploadChunkArgs::new(canister_id, vec![1; MAX_CHUNK_SIZE]);
+    let err = test
+        .subnet_message(Method::UploadChunk, args.encode())
+        .unwrap_err();
+    assert_eq!(
+        err.code(),
+        ErrorCode::ReservedCyclesLimitExceededInMemoryGrow
+    );
+    let system_state = &test.canister_state(canister_id).system_state;
+    assert!(system_state.wasm_chunk_store.is_empty());
+    assert_eq!(system_state.reserved_balance(), Cycles::zero());
+    assert_eq!(
+        test.subnet_available_memory().get_total_memory(),
+        subnet_available_memory_before
+    );
+}
+
+#[test]
+fn update_settings_memory_allocation_reserves_cycles_above_subnet_memory_threshold() {
+    let mut test = ExecutionTestBuilder::new()
+        .with_subnet_total_memory(1 << 30)
+        .with_subnet_memory_threshold(0)
+        .build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    let balance_before = test.canister_state(canister_id).system_state.balance();
+
+    test.canister_update_allocations_settings(canister_id, None, Some(100 << 20))
+        .unwrap();
+    let reserved = test
+        .canister_state(canister_id)
+        .system_state
+        .reserved_balance();
+    let balance = test.canister_state(canister_id).system_state.balance();
+    assert!(reserved > Cycles::zero());
+    assert!(balance_before - balance >= reserved);
+}
+
+#[test]
+fn update_settings_memory_allocation_fails_when_reserved_cycles_limit_is_exceeded() {
+    let mut test = ExecutionTestBuilder::new()
+        .with_subnet_total_memory(1 << 30)
+        .with_subnet_memory_threshold(0)
+        .build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    test.update_reserved_cycles_limit(canister_id, Cycles::zero())
+        .unwrap();
+    let subnet_available_memory_before = test.subnet_available_memory().get_total_memory();
+
+    let err = test
+        .canister_update_allocations_settings(canister_id, None, Some(100 << 20))
+        .unwrap_err();
+    assert_eq!(
+        err.code(),
+        ErrorCode::ReservedCyclesLimitExceededInMemoryGrow
+    );
+    let system_state = &test.canister_state(canister_id).system_state;
+    assert_eq!(system_state.memory_allocation, MemoryAllocation::BestEffort);
+    assert_eq!(system_state.reserved_balance(), Cycles::zero());
+    assert_eq!(
+        test.subnet_available_memory().get_total_memory(),
+        subnet_available_memory_before
+    );
+}
+
+#[test]
+fn clear_chunk_store_removes_all_chunks() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    let wasm = wabt::wat2wasm(r#"(module (memory 1))"#).unwrap();
+    upload_chunks(&mut test, canister_id, &wasm);
+
+    let args = CanisterIdRecord::from(canister_id);
+    test.subnet_message(Method::ClearChunkStore, args.encode())
+        .unwrap();
+    let chunk_store = &test
+        .canister_state(canister_id)
+        .system_state
+        .wasm_chunk_store;
+    assert!(chunk_store.is_empty());
+    assert_eq!(chunk_store.memory_usage(), NumBytes::from(0));
+}
+
+#[test]
+fn canister_history_records_changes() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let user_id = test.user_id().get();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    let wasm = wabt::wat2wasm(EMPTY_WAT).unwrap();
+    let module_hash = ic_crypto_sha::Sha256::hash(&wasm);
+    test.install_canister(canister_id, wasm.clone()).unwrap();
+    test.upgrade_canister(canister_id, wasm).unwrap();
+    test.uninstall_code(canister_id).unwrap();
+    let new_controller = user_test_id(42).get();
+    test.set_controller(canister_id, new_controller).unwrap();
+
+    let history = test
+        .canister_state(canister_id)
+        .system_state
+        .get_canister_history();
+    assert_eq!(history.get_total_num_changes(), 5);
+    let changes: Vec<&CanisterChange> = history.get_changes(usize::MAX).collect();
+    let details: Vec<(u64, CanisterChangeDetails)> = changes
+        .iter()
+        .map(|change| (change.canister_version(), change.details().clone()))
+        .collect();
+    assert_eq!(
+        details,
+        vec![
+            (0, CanisterChangeDetails::canister_creation(vec![user_id])),
+            (
+                1,
+                CanisterChangeDetails::code_deployment(CanisterInstallMode::Install, module_hash)
+            ),
+            (
+                2,
+                CanisterChangeDetails::code_deployment(CanisterInstallMode::Upgrade, module_hash)
+            ),
+            (3, CanisterChangeDetails::code_uninstall()),
+            (
+                4,
+                CanisterChangeDetails::controllers_change(vec![new_controller])
+            ),
+        ]
+    );
+    for change in changes {
+        assert_eq!(change.origin(), &CanisterChangeOrigin::from_user(user_id));
+    }
+}
+
+#[test]
+fn failed_install_is_not_recorded_in_canister_history() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    test.install_canister(canister_id, vec![1, 2, 3])
+        .unwrap_err();
+    let history = test
+        .canister_state(canister_id)
+        .system_state
+        .get_canister_history();
+    assert_eq!(history.get_total_num_changes(), 1);
+}
+
+#[test]
+fn canister_info_returns_requested_changes() {
+    let own_subnet = subnet_test_id(1);
+    let caller_subnet = subnet_test_id(2);
+    let caller = canister_test_id(1);
+    let mut test = ExecutionTestBuilder::new()
+        .with_own_subnet_id(own_subnet)
+        .with_caller(caller_subnet, caller)
+        .build();
+    let user_id = test.user_id().get();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    let wasm = wabt::wat2wasm(EMPTY_WAT).unwrap();
+    let module_hash = ic_crypto_sha::Sha256::hash(&wasm);
+    test.install_canister(canister_id, wasm.clone()).unwrap();
+    test.reinstall_canister(canister_id, wasm).unwrap();
+
+    test.inject_call_to_ic00(
+        Method::CanisterInfo,
+        CanisterInfoRequest::new(canister_id, Some(2)).encode(),
+        Cycles::zero(),
+    );
+    test.execute_all();
+    let response = match test.xnet_messages()[0].clone() {
+        RequestOrResponse::Response(response) => response,
+        RequestOrResponse::Request(request) => panic!("Expected a response, got {:?}", request),
+    };
+    let info = match &response.response_payload {
+        ResponsePayload::Data(data) => CanisterInfoResponse::decode(data).unwrap(),
+        ResponsePayload::Reject(reject) => panic!("Unexpected reject: {:?}", reject),
+    };
+    assert_eq!(info.total_num_changes(), 3);
+    assert_eq!(info.module_hash(), Some(module_hash.to_vec()));
+    assert_eq!(info.controllers(), vec![user_id]);
+    let details: Vec<CanisterChangeDetails> = info
+        .changes()
+        .iter()
+        .map(|change| change.details().clone())
+        .collect();
+    assert_eq!(
+        details,
+        vec![
+            CanisterChangeDetails::code_deployment(CanisterInstallMode::Install, module_hash),
+            CanisterChangeDetails::code_deployment(CanisterInstallMode::Reinstall, module_hash),
+        ]
+    );
+}
+
+#[test]
+fn canister_info_cannot_be_called_via_ingress() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000_000));
+    let err = test
+        .should_accept_ingress_message(
+            CanisterId::ic_00(),
+            Method::CanisterInfo,
+            CanisterInfoRequest::new(canister_id, None).encode(),
+        )
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterRejectedMessage);
+}
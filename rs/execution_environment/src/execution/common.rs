@@ -2,6 +2,7 @@
 // TODO(RUN-60): Move helper functions here.
 
 use ic_base_types::{CanisterId, NumBytes, SubnetId};
+use ic_cycles_account_manager::{CyclesAccountManager, ResourceSaturation};
 use ic_embedders::wasm_executor::{CanisterStateChanges, SliceExecutionOutput};
 use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_ic00_types::CanisterStatusType;
@@ -12,13 +13,13 @@ use ic_interfaces::messages::RequestOrIngress;
 use ic_logger::{error, fatal, warn, ReplicaLogger};
 use ic_replicated_state::{
     CallContext, CallContextAction, CallOrigin, CanisterState, ExecutionState, NetworkTopology,
-    SystemState,
+    ReservationError, SystemState,
 };
 use ic_system_api::sandbox_safe_system_state::SystemStateChanges;
 use ic_types::ingress::{IngressState, IngressStatus, WasmResult};
 use ic_types::messages::{CallContextId, CallbackId, MessageId, Payload, RejectContext, Response};
 use ic_types::methods::{Callback, WasmMethod};
-use ic_types::{Cycles, MemoryAllocation, NumInstructions, Time, UserId};
+use ic_types::{time::CoarseTime, Cycles, MemoryAllocation, NumInstructions, Time, UserId};
 
 use crate::execution_environment::ExecutionResponse;
 use crate::{as_round_instructions, ExecuteMessageResult, RoundLimits};
@@ -53,8 +54,8 @@ pub(crate) fn action_to_response(
             time,
             log,
         ),
-        CallOrigin::CanisterUpdate(caller_canister_id, callback_id) => {
-            action_to_request_response(canister, action, caller_canister_id, callback_id)
+        CallOrigin::CanisterUpdate(caller_canister_id, callback_id, deadline) => {
+            action_to_request_response(canister, action, caller_canister_id, callback_id, deadline)
         }
         CallOrigin::CanisterQuery(_, _) | CallOrigin::Query(_) => fatal!(
             log,
@@ -75,6 +76,7 @@ pub(crate) fn action_to_request_response(
     action: CallContextAction,
     originator: CanisterId,
     reply_callback_id: CallbackId,
+    deadline: CoarseTime,
 ) -> ExecutionResponse {
     let response_payload_and_refund = match action {
         CallContextAction::NotYetResponded | CallContextAction::AlreadyResponded => None,
@@ -115,6 +117,7 @@ pub(crate) fn action_to_request_response(
             originator_reply_callback: reply_callback_id,
             refund,
             response_payload,
+            deadline,
         })
     } else {
         ExecutionResponse::Empty
@@ -224,13 +227,14 @@ pub(crate) fn wasm_result_to_query_response(
         CallOrigin::Ingress(user_id, message_id) => {
             wasm_result_to_ingress_response(result, canister, user_id, message_id, time)
         }
-        CallOrigin::CanisterUpdate(caller_canister_id, callback_id) => {
+        CallOrigin::CanisterUpdate(caller_canister_id, callback_id, deadline) => {
             let response = Response {
                 originator: caller_canister_id,
                 respondent: canister.canister_id(),
                 originator_reply_callback: callback_id,
                 refund: Cycles::zero(),
                 response_payload: Payload::from(result),
+                deadline,
             };
             ExecutionResponse::Request(response)
         }
@@ -380,6 +384,44 @@ pub fn update_round_limits(round_limits: &mut RoundLimits, slice: &SliceExecutio
     round_limits.instructions -= as_round_instructions(slice.executed_instructions);
 }
 
+/// Moves the cycles required for storing `allocated_bytes` of newly allocated
+/// memory from the main balance of the canister to its reserved balance.
+/// Nothing is reserved while the subnet memory usage is below the threshold.
+/// The main balance must stay above `freezing_threshold` after reserving.
+pub(crate) fn reserve_cycles_for_memory_allocation(
+    system_state: &mut SystemState,
+    allocated_bytes: NumBytes,
+    subnet_memory_saturation: &ResourceSaturation,
+    freezing_threshold: Cycles,
+    subnet_size: usize,
+    cycles_account_manager: &CyclesAccountManager,
+) -> HypervisorResult<()> {
+    let reservation_cycles = cycles_account_manager.storage_reservation_cycles(
+        allocated_bytes,
+        subnet_memory_saturation,
+        subnet_size,
+    );
+    system_state
+        .reserve_cycles(reservation_cycles, freezing_threshold)
+        .map_err(|err| match err {
+            ReservationError::InsufficientCycles {
+                requested,
+                available,
+            } => HypervisorError::InsufficientCyclesInMemoryGrow {
+                bytes: allocated_bytes,
+                available,
+                required: requested,
+            },
+            ReservationError::ReservedLimitExceeded { requested, limit } => {
+                HypervisorError::ReservedCyclesLimitExceededInMemoryGrow {
+                    bytes: allocated_bytes,
+                    requested,
+                    limit,
+                }
+            }
+        })
+}
+
 /// Tries to apply the given canister changes to the given system state and
 /// subnet available memory. In case of an error, the partially applied changes
 /// are not undone.
@@ -392,6 +434,10 @@ fn try_apply_canister_state_changes(
     network_topology: &NetworkTopology,
     subnet_id: SubnetId,
     log: &ReplicaLogger,
+    subnet_memory_saturation: &ResourceSaturation,
+    freezing_threshold: Cycles,
+    subnet_size: usize,
+    cycles_account_manager: &CyclesAccountManager,
 ) -> HypervisorResult<()> {
     match &system_state.memory_allocation {
         MemoryAllocation::BestEffort => subnet_available_memory
@@ -400,7 +446,19 @@ fn try_apply_canister_state_changes(
         MemoryAllocation::Reserved(_) => (),
     }
 
-    system_state_changes.apply_changes(time, system_state, network_topology, subnet_id, log)
+    system_state_changes.apply_changes(time, system_state, network_topology, subnet_id, log)?;
+
+    match &system_state.memory_allocation {
+        MemoryAllocation::BestEffort => reserve_cycles_for_memory_allocation(
+            system_state,
+            output.allocated_bytes,
+            subnet_memory_saturation,
+            freezing_threshold,
+            subnet_size,
+            cycles_account_manager,
+        ),
+        MemoryAllocation::Reserved(_) => Ok(()),
+    }
 }
 
 /// Applies canister state change after Wasm execution if possible.
@@ -422,6 +480,10 @@ pub fn apply_canister_state_changes(
     network_topology: &NetworkTopology,
     subnet_id: SubnetId,
     log: &ReplicaLogger,
+    subnet_memory_saturation: &ResourceSaturation,
+    freezing_threshold: Cycles,
+    subnet_size: usize,
+    cycles_account_manager: &CyclesAccountManager,
 ) {
     if let Some(CanisterStateChanges {
         globals,
@@ -443,6 +505,10 @@ pub fn apply_canister_state_changes(
             network_topology,
             subnet_id,
             log,
+            subnet_memory_saturation,
+            freezing_threshold,
+            subnet_size,
+            cycles_account_manager,
         ) {
             Ok(()) => {
                 execution_state.wasm_memory = wasm_memory;
@@ -461,6 +527,11 @@ pub fn apply_canister_state_changes(
                     HypervisorError::OutOfMemory => {
                         warn!(log, "Failed to apply state changes due to DTS: {}", err)
                     }
+                    HypervisorError::InsufficientCyclesInMemoryGrow { .. }
+                    | HypervisorError::ReservedCyclesLimitExceededInMemoryGrow { .. } => {
+                        // The canister cannot afford the memory it allocated.
+                        // This is an expected user error.
+                    }
                     _ => {
                         // TODO(RUN-299): Increment a critical error counter here.
                         error!(
@@ -492,6 +563,7 @@ pub(crate) fn finish_call_with_error(
                 originator_reply_callback: request.sender_reply_callback,
                 refund: request.payment,
                 response_payload: Payload::from(Err(user_error)),
+                deadline: request.deadline,
             };
             ExecutionResponse::Request(response)
         }
@@ -6,7 +6,7 @@ use std::path::{Path, PathBuf};
 use ic_base_types::{CanisterId, NumBytes, PrincipalId};
 use ic_config::flag_status::FlagStatus;
 use ic_embedders::wasm_executor::CanisterStateChanges;
-use ic_ic00_types::CanisterInstallMode;
+use ic_ic00_types::{CanisterChangeDetails, CanisterChangeOrigin, CanisterInstallMode};
 use ic_interfaces::{
     execution_environment::{
         HypervisorError, HypervisorResult, SubnetAvailableMemory, SubnetAvailableMemoryError,
@@ -28,6 +28,7 @@ use crate::{
     canister_manager::{
         CanisterManagerError, CanisterMgrConfig, DtsInstallCodeResult, InstallCodeResult,
     },
+    execution::common::reserve_cycles_for_memory_allocation,
     execution_environment::RoundContext,
     CompilationCostHandling, RoundLimits,
 };
@@ -186,6 +187,9 @@ impl InstallCodeHelper {
             .system_state
             .apply_cycles_debit(self.canister.canister_id(), round.log);
 
+        let subnet_memory_saturation = round
+            .hypervisor
+            .subnet_memory_saturation(&round_limits.subnet_available_memory);
         let mut subnet_available_memory = round_limits.subnet_available_memory;
         subnet_available_memory.increment(self.deallocated_bytes, NumBytes::from(0));
         if let Err(err) = subnet_available_memory
@@ -239,6 +243,32 @@ impl InstallCodeHelper {
             round_limits.compute_allocation_used = others + new_compute_allocation.as_percent();
         }
 
+        let freezing_threshold = round.cycles_account_manager.freeze_threshold_cycles(
+            self.canister.system_state.freeze_threshold,
+            self.canister.system_state.memory_allocation,
+            self.canister
+                .memory_usage(original.execution_parameters.subnet_type),
+            self.canister.scheduler_state.compute_allocation,
+            original.subnet_size,
+        );
+        if let Err(err) = reserve_cycles_for_memory_allocation(
+            &mut self.canister.system_state,
+            self.allocated_bytes,
+            &subnet_memory_saturation,
+            freezing_threshold,
+            original.subnet_size,
+            round.cycles_account_manager,
+        ) {
+            let canister_id = clean_canister.canister_id();
+            return finish_err(
+                clean_canister,
+                self.instructions_left(),
+                original,
+                round,
+                CanisterManagerError::Hypervisor(canister_id, err),
+            );
+        }
+
         // After this point `install_code` is guaranteed to succeed.
         // Commit all the remaining state and round limit changes.
 
@@ -266,6 +296,13 @@ impl InstallCodeHelper {
 
         let old_wasm_hash = get_wasm_hash(&clean_canister);
         let new_wasm_hash = get_wasm_hash(&self.canister);
+        if let Some(module_hash) = new_wasm_hash {
+            self.canister.system_state.add_canister_change(
+                original.time,
+                original.origin.clone(),
+                CanisterChangeDetails::code_deployment(original.mode, module_hash),
+            );
+        }
         DtsInstallCodeResult::Finished {
             canister: self.canister,
             message: original.message,
@@ -303,7 +340,7 @@ impl InstallCodeHelper {
             config,
         )?;
 
-        validate_controller(&self.canister, &original.sender)?;
+        validate_controller(&self.canister, &original.origin.origin())?;
 
         match original.mode {
             CanisterInstallMode::Install => {
@@ -562,7 +599,7 @@ pub(crate) struct OriginalContext {
     pub subnet_size: usize,
     pub requested_compute_allocation: Option<ComputeAllocation>,
     pub requested_memory_allocation: Option<MemoryAllocation>,
-    pub sender: PrincipalId,
+    pub origin: CanisterChangeOrigin,
     pub canister_id: CanisterId,
 }
 
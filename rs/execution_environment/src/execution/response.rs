@@ -344,6 +344,9 @@ impl ResponseHelper {
             }
         }
 
+        let subnet_memory_saturation = round
+            .hypervisor
+            .subnet_memory_saturation(&round_limits.subnet_available_memory);
         apply_canister_state_changes(
             canister_state_changes,
             self.canister.execution_state.as_mut().unwrap(),
@@ -354,6 +357,10 @@ impl ResponseHelper {
             round.network_topology,
             round.hypervisor.subnet_id(),
             round.log,
+            &subnet_memory_saturation,
+            original.freezing_threshold,
+            original.subnet_size,
+            round.cycles_account_manager,
         );
         match output.wasm_result {
             Ok(_) => Ok(self.finish(
@@ -388,6 +395,9 @@ impl ResponseHelper {
             assert_eq!(requested.get(), 0);
         }
 
+        let subnet_memory_saturation = round
+            .hypervisor
+            .subnet_memory_saturation(&round_limits.subnet_available_memory);
         apply_canister_state_changes(
             canister_state_changes,
             self.canister.execution_state.as_mut().unwrap(),
@@ -398,6 +408,10 @@ impl ResponseHelper {
             round.network_topology,
             round.hypervisor.subnet_id(),
             round.log,
+            &subnet_memory_saturation,
+            original.freezing_threshold,
+            original.subnet_size,
+            round.cycles_account_manager,
         );
 
         match output.wasm_result {
@@ -777,9 +791,9 @@ pub fn execute_response(
     };
 
     let func_ref = match original.call_origin {
-        CallOrigin::Ingress(_, _) | CallOrigin::CanisterUpdate(_, _) | CallOrigin::SystemTask => {
-            FuncRef::UpdateClosure(closure)
-        }
+        CallOrigin::Ingress(_, _)
+        | CallOrigin::CanisterUpdate(_, _, _)
+        | CallOrigin::SystemTask => FuncRef::UpdateClosure(closure),
         CallOrigin::CanisterQuery(_, _) | CallOrigin::Query(_) => FuncRef::QueryClosure(closure),
     };
 
@@ -845,9 +859,9 @@ fn execute_response_cleanup(
         .instruction_limits
         .update(instructions_left);
     let func_ref = match original.call_origin {
-        CallOrigin::Ingress(_, _) | CallOrigin::CanisterUpdate(_, _) | CallOrigin::SystemTask => {
-            FuncRef::UpdateClosure(cleanup_closure)
-        }
+        CallOrigin::Ingress(_, _)
+        | CallOrigin::CanisterUpdate(_, _, _)
+        | CallOrigin::SystemTask => FuncRef::UpdateClosure(cleanup_closure),
         CallOrigin::CanisterQuery(_, _) | CallOrigin::Query(_) => {
             FuncRef::QueryClosure(cleanup_closure)
         }
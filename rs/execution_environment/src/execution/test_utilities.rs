@@ -3,7 +3,7 @@ use crate::util::process_stopping_canisters;
 use crate::{
     execute_canister, CompilationCostHandling, ExecuteMessageResult, ExecutionEnvironment,
     ExecutionResponse, Hypervisor, IngressHistoryWriterImpl, InternalHttpQueryHandler,
-    RoundInstructions, RoundLimits,
+    QueryStatsCollector, RoundInstructions, RoundLimits,
 };
 use ic_base_types::{NumBytes, NumSeconds, PrincipalId, SubnetId};
 use ic_config::subnet_config::SchedulerConfig;
@@ -396,6 +396,9 @@ impl ExecutionTest {
             compute_allocation,
             memory_allocation,
             None,
+            None,
+            None,
+            None,
         ));
 
         let result =
@@ -427,6 +430,9 @@ impl ExecutionTest {
                 compute_allocation,
                 memory_allocation,
                 None,
+                None,
+                None,
+                None,
             ),
         }
         .encode();
@@ -498,6 +504,55 @@ impl ExecutionTest {
                 None,
                 None,
                 Some(freezing_threshold.get()),
+                None,
+                None,
+                None,
+            ),
+        }
+        .encode();
+        self.subnet_message(Method::UpdateSettings, payload)
+    }
+
+    /// Updates the Wasm memory limit of the given canister.
+    pub fn update_wasm_memory_limit(
+        &mut self,
+        canister_id: CanisterId,
+        wasm_memory_limit: NumBytes,
+    ) -> Result<WasmResult, UserError> {
+        let payload = UpdateSettingsArgs {
+            canister_id: canister_id.into(),
+            settings: CanisterSettingsArgs::new(
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(wasm_memory_limit.get()),
+                None,
+            ),
+        }
+        .encode();
+        self.subnet_message(Method::UpdateSettings, payload)
+    }
+
+    /// Updates the reserved cycles limit of the given canister.
+    pub fn update_reserved_cycles_limit(
+        &mut self,
+        canister_id: CanisterId,
+        reserved_cycles_limit: Cycles,
+    ) -> Result<WasmResult, UserError> {
+        let payload = UpdateSettingsArgs {
+            canister_id: canister_id.into(),
+            settings: CanisterSettingsArgs::new(
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(reserved_cycles_limit.get()),
             ),
         }
         .encode();
@@ -512,7 +567,16 @@ impl ExecutionTest {
     ) -> Result<WasmResult, UserError> {
         let payload = UpdateSettingsArgs {
             canister_id: canister_id.into(),
-            settings: CanisterSettingsArgs::new(None, Some(vec![controller]), None, None, None),
+            settings: CanisterSettingsArgs::new(
+                None,
+                Some(vec![controller]),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ),
         }
         .encode();
         self.subnet_message(Method::UpdateSettings, payload)
@@ -1270,6 +1334,7 @@ pub struct ExecutionTestBuilder {
     initial_canister_cycles: Cycles,
     subnet_total_memory: i64,
     subnet_message_memory: i64,
+    subnet_memory_threshold: i64,
     registry_settings: RegistryExecutionSettings,
     manual_execution: bool,
     rate_limiting_of_instructions: bool,
@@ -1295,6 +1360,9 @@ impl Default for ExecutionTestBuilder {
         let subnet_message_memory = ic_config::execution_environment::Config::default()
             .subnet_message_memory_capacity
             .get() as i64;
+        let subnet_memory_threshold = ic_config::execution_environment::Config::default()
+            .subnet_memory_threshold
+            .get() as i64;
         let max_instructions_per_composite_query_call =
             ic_config::execution_environment::Config::default()
                 .max_instructions_per_composite_query_call;
@@ -1317,6 +1385,7 @@ impl Default for ExecutionTestBuilder {
             initial_canister_cycles: INITIAL_CANISTER_CYCLES,
             subnet_total_memory,
             subnet_message_memory,
+            subnet_memory_threshold,
             registry_settings: test_registry_settings(),
             manual_execution: false,
             rate_limiting_of_instructions: false,
@@ -1454,6 +1523,13 @@ impl ExecutionTestBuilder {
         }
     }
 
+    pub fn with_subnet_memory_threshold(self, subnet_memory_threshold: i64) -> Self {
+        Self {
+            subnet_memory_threshold,
+            ..self
+        }
+    }
+
     pub fn with_subnet_features(self, subnet_features: &str) -> Self {
         Self {
             subnet_features: String::from(subnet_features),
@@ -1647,6 +1723,7 @@ impl ExecutionTestBuilder {
             allocatable_compute_capacity_in_percent: self.allocatable_compute_capacity_in_percent,
             subnet_memory_capacity: NumBytes::from(self.subnet_total_memory as u64),
             subnet_message_memory_capacity: NumBytes::from(self.subnet_message_memory as u64),
+            subnet_memory_threshold: NumBytes::from(self.subnet_memory_threshold as u64),
             bitcoin: BitcoinConfig {
                 privileged_access: self.bitcoin_privileged_access,
                 ..Default::default()
@@ -1691,6 +1768,8 @@ impl ExecutionTestBuilder {
             config.clone(),
             Arc::clone(&cycles_account_manager),
         );
+        let query_stats_collector =
+            Arc::new(QueryStatsCollector::new(config.query_stats_epoch_length));
         let query_handler = InternalHttpQueryHandler::new(
             self.log.clone(),
             hypervisor,
@@ -1700,6 +1779,7 @@ impl ExecutionTestBuilder {
             self.instruction_limit_without_dts,
             Arc::clone(&cycles_account_manager),
             composite_queries,
+            query_stats_collector,
         );
         ExecutionTest {
             state: Some(state),
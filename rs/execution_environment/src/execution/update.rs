@@ -335,6 +335,9 @@ impl UpdateHelper {
             }
         }
 
+        let subnet_memory_saturation = round
+            .hypervisor
+            .subnet_memory_saturation(&round_limits.subnet_available_memory);
         apply_canister_state_changes(
             canister_state_changes,
             self.canister.execution_state.as_mut().unwrap(),
@@ -345,6 +348,10 @@ impl UpdateHelper {
             round.network_topology,
             round.hypervisor.subnet_id(),
             round.log,
+            &subnet_memory_saturation,
+            original.freezing_threshold,
+            original.subnet_size,
+            round.cycles_account_manager,
         );
         let heap_delta = if output.wasm_result.is_ok() {
             NumBytes::from((output.instance_stats.dirty_pages * ic_sys::PAGE_SIZE) as u64)
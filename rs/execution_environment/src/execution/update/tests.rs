@@ -4,9 +4,12 @@ use ic_base_types::NumSeconds;
 use ic_config::subnet_config::SchedulerConfig;
 use ic_error_types::ErrorCode;
 use ic_registry_subnet_type::SubnetType;
-use ic_replicated_state::{canister_state::NextExecution, CallOrigin};
+use ic_replicated_state::{
+    canister_state::{NextExecution, WASM_PAGE_SIZE_IN_BYTES},
+    CallOrigin, NumWasmPages,
+};
 use ic_state_machine_tests::{Cycles, WasmResult};
-use ic_types::NumInstructions;
+use ic_types::{NumBytes, NumInstructions};
 use ic_universal_canister::{call_args, wasm};
 
 use crate::execution::test_utilities::{check_ingress_status, ExecutionTest, ExecutionTestBuilder};
@@ -310,6 +313,144 @@ fn hitting_page_delta_limit_fails_message() {
     modified: 8388612 KB.");
 }
 
+const WAT_GROWING_WASM_MEMORY: &str = r#"
+    (module
+        (import "ic0" "msg_reply" (func $msg_reply))
+        (func (export "canister_update grow")
+            (if (i32.eq (memory.grow (i32.const 10)) (i32.const -1))
+                (then (unreachable))
+            )
+            (call $msg_reply)
+        )
+        (memory (export "memory") 1)
+    )"#;
+
+#[test]
+fn memory_grow_above_wasm_memory_limit_traps() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(WAT_GROWING_WASM_MEMORY).unwrap();
+    test.update_wasm_memory_limit(
+        canister_id,
+        NumBytes::new(10 * WASM_PAGE_SIZE_IN_BYTES as u64),
+    )
+    .unwrap();
+    let err = test.ingress(canister_id, "grow", vec![]).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterWasmMemoryLimitExceeded);
+    assert_eq!(
+        test.execution_state(canister_id).wasm_memory.size,
+        NumWasmPages::new(1)
+    );
+}
+
+#[test]
+fn memory_grow_within_wasm_memory_limit_succeeds() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(WAT_GROWING_WASM_MEMORY).unwrap();
+    test.update_wasm_memory_limit(
+        canister_id,
+        NumBytes::new(11 * WASM_PAGE_SIZE_IN_BYTES as u64),
+    )
+    .unwrap();
+    let result = test.ingress(canister_id, "grow", vec![]).unwrap();
+    assert_eq!(result, WasmResult::Reply(vec![]));
+    assert_eq!(
+        test.execution_state(canister_id).wasm_memory.size,
+        NumWasmPages::new(11)
+    );
+}
+
+#[test]
+fn memory_grow_reserves_cycles_above_subnet_memory_threshold() {
+    let mut test = ExecutionTestBuilder::new()
+        .with_subnet_total_memory(GB as i64)
+        .with_subnet_memory_threshold(0)
+        .build();
+    let canister_id = test.canister_from_wat(WAT_GROWING_WASM_MEMORY).unwrap();
+    let reserved_before = test
+        .canister_state(canister_id)
+        .system_state
+        .reserved_balance();
+    let balance_before = test.canister_state(canister_id).system_state.balance();
+    let result = test.ingress(canister_id, "grow", vec![]).unwrap();
+    assert_eq!(result, WasmResult::Reply(vec![]));
+    let reserved = test
+        .canister_state(canister_id)
+        .system_state
+        .reserved_balance()
+        - reserved_before;
+    let balance = test.canister_state(canister_id).system_state.balance();
+    assert!(reserved > Cycles::zero());
+    assert!(balance_before - balance >= reserved);
+}
+
+#[test]
+fn memory_grow_does_not_reserve_cycles_below_subnet_memory_threshold() {
+    let mut test = ExecutionTestBuilder::new()
+        .with_subnet_total_memory(GB as i64)
+        .with_subnet_memory_threshold(GB as i64)
+        .build();
+    let canister_id = test.canister_from_wat(WAT_GROWING_WASM_MEMORY).unwrap();
+    let result = test.ingress(canister_id, "grow", vec![]).unwrap();
+    assert_eq!(result, WasmResult::Reply(vec![]));
+    assert_eq!(
+        test.canister_state(canister_id)
+            .system_state
+            .reserved_balance(),
+        Cycles::zero()
+    );
+}
+
+#[test]
+fn memory_grow_fails_when_reserved_cycles_limit_is_exceeded() {
+    let mut test = ExecutionTestBuilder::new()
+        .with_subnet_total_memory(GB as i64)
+        .with_subnet_memory_threshold(0)
+        .build();
+    let canister_id = test.canister_from_wat(WAT_GROWING_WASM_MEMORY).unwrap();
+    let reserved = test
+        .canister_state(canister_id)
+        .system_state
+        .reserved_balance();
+    test.update_reserved_cycles_limit(canister_id, reserved)
+        .unwrap();
+    let err = test.ingress(canister_id, "grow", vec![]).unwrap_err();
+    assert_eq!(
+        err.code(),
+        ErrorCode::ReservedCyclesLimitExceededInMemoryGrow
+    );
+    assert_eq!(
+        test.execution_state(canister_id).wasm_memory.size,
+        NumWasmPages::new(1)
+    );
+    assert_eq!(
+        test.canister_state(canister_id)
+            .system_state
+            .reserved_balance(),
+        reserved
+    );
+}
+
+#[test]
+fn reserved_cycles_limit_cannot_be_set_below_reserved_balance() {
+    let mut test = ExecutionTestBuilder::new()
+        .with_subnet_total_memory(GB as i64)
+        .with_subnet_memory_threshold(0)
+        .build();
+    let canister_id = test.canister_from_wat(WAT_GROWING_WASM_MEMORY).unwrap();
+    test.ingress(canister_id, "grow", vec![]).unwrap();
+    let reserved = test
+        .canister_state(canister_id)
+        .system_state
+        .reserved_balance();
+    assert!(reserved > Cycles::zero());
+    let err = test
+        .update_reserved_cycles_limit(canister_id, reserved - Cycles::new(1))
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::ReservedCyclesLimitIsTooLow);
+    test.update_reserved_cycles_limit(canister_id, reserved)
+        .unwrap();
+}
+
 #[test]
 fn dts_update_resume_fails_due_to_cycles_change() {
     // Test steps:
@@ -4,11 +4,14 @@ use crate::execution::test_utilities::{check_ingress_status, ExecutionTest, Exec
 use ic_error_types::ErrorCode;
 use ic_ic00_types::{EmptyBlob, Payload};
 use ic_logger::replica_logger::LogEntryLogger;
-use ic_replicated_state::{canister_state::NextExecution, CanisterState};
+use ic_replicated_state::{
+    canister_state::{NextExecution, WASM_PAGE_SIZE_IN_BYTES},
+    CanisterState, NumWasmPages,
+};
 use ic_state_machine_tests::{IngressState, WasmResult};
 use ic_test_utilities::types::ids::user_test_id;
 use ic_test_utilities_metrics::fetch_int_counter;
-use ic_types::Cycles;
+use ic_types::{Cycles, NumBytes};
 use maplit::btreeset;
 
 ////////////////////////////////////////////////////////////////////////
@@ -878,3 +881,35 @@ fn dts_uninstall_with_aborted_upgrade() {
         )
     );
 }
+
+#[test]
+fn upgrade_hooks_ignore_wasm_memory_limit() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wat = r#"
+        (module
+            (func (export "canister_pre_upgrade")
+                (if (i32.eq (memory.grow (i32.const 10)) (i32.const -1))
+                    (then (unreachable))
+                )
+            )
+            (func (export "canister_post_upgrade")
+                (if (i32.eq (memory.grow (i32.const 10)) (i32.const -1))
+                    (then (unreachable))
+                )
+            )
+            (memory (export "memory") 1)
+        )"#;
+    let canister_id = test.canister_from_wat(wat).unwrap();
+    test.update_wasm_memory_limit(
+        canister_id,
+        NumBytes::new(2 * WASM_PAGE_SIZE_IN_BYTES as u64),
+    )
+    .unwrap();
+
+    test.upgrade_canister(canister_id, wabt::wat2wasm(wat).unwrap())
+        .unwrap();
+    assert_eq!(
+        test.execution_state(canister_id).wasm_memory.size,
+        NumWasmPages::new(11)
+    );
+}
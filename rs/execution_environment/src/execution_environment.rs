@@ -1,7 +1,8 @@
 use crate::{
     canister_manager::{
-        CanisterManager, CanisterManagerError, CanisterMgrConfig, DtsInstallCodeResult,
-        InstallCodeContext, PausedInstallCodeExecution, StopCanisterResult,
+        install_code_context_from_args, install_code_context_from_chunks, CanisterManager,
+        CanisterManagerError, CanisterMgrConfig, DtsInstallCodeResult, InstallCodeContext,
+        PausedInstallCodeExecution, StopCanisterResult,
     },
     canister_settings::CanisterSettings,
     execution::{
@@ -28,11 +29,12 @@ use ic_crypto_tecdsa::derive_tecdsa_public_key;
 use ic_cycles_account_manager::{CyclesAccountManager, IngressInductionCost};
 use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_ic00_types::{
-    CanisterHttpRequestArgs, CanisterIdRecord, CanisterSettingsArgs,
-    ComputeInitialEcdsaDealingsArgs, CreateCanisterArgs, ECDSAPublicKeyArgs,
-    ECDSAPublicKeyResponse, EcdsaKeyId, EmptyBlob, InstallCodeArgs, Method as Ic00Method,
-    Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs,
-    SetControllerArgs, SetupInitialDKGArgs, SignWithECDSAArgs, UpdateSettingsArgs, IC_00,
+    CanisterChangeOrigin, CanisterHttpRequestArgs, CanisterIdRecord, CanisterInfoRequest,
+    CanisterSettingsArgs, ComputeInitialEcdsaDealingsArgs, CreateCanisterArgs, ECDSAPublicKeyArgs,
+    ECDSAPublicKeyResponse, EcdsaKeyId, EmptyBlob, InstallChunkedCodeArgs, InstallCodeArgs,
+    Method as Ic00Method, Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs,
+    ProvisionalTopUpCanisterArgs, SetControllerArgs, SetupInitialDKGArgs, SignWithECDSAArgs,
+    UpdateSettingsArgs, UploadChunkArgs, IC_00,
 };
 use ic_interfaces::{
     execution_environment::{
@@ -61,7 +63,7 @@ use ic_types::{
     ingress::{IngressState, IngressStatus, WasmResult},
     messages::{
         extract_effective_canister_id, AnonymousQuery, Payload, RejectContext, Request, Response,
-        SignedIngressContent, StopCanisterContext,
+        SignedIngressContent, StopCanisterContext, NO_DEADLINE,
     },
     CanisterId, CanisterTimer, Cycles, LongExecutionMode, NumBytes, NumInstructions, SubnetId,
     Time,
@@ -396,6 +398,7 @@ impl ExecutionEnvironment {
                                 originator_reply_callback: request.sender_reply_callback,
                                 refund: request.payment,
                                 response_payload: response.response_payload.clone(),
+                                deadline: request.deadline,
                             }
                             .into(),
                         );
@@ -412,7 +415,7 @@ impl ExecutionEnvironment {
         let method = Ic00Method::from_str(msg.method_name());
         let payload = msg.method_payload();
         let result = match method {
-            Ok(Ic00Method::InstallCode) => {
+            Ok(Ic00Method::InstallCode) | Ok(Ic00Method::InstallChunkedCode) => {
                 // Tail call is needed for deterministic time slicing here to
                 // properly handle the case of a paused execution.
                 return self.execute_install_code(
@@ -448,6 +451,7 @@ impl ExecutionEnvironment {
                                         message: reject_message,
                                     },
                                 ),
+                                deadline: request.deadline,
                             }
                             .into(),
                         );
@@ -513,7 +517,7 @@ impl ExecutionEnvironment {
                                         let result = match CanisterSettings::try_from(settings) {
                                             Err(err) => Some((Err(err.into()), cycles)),
                                             Ok(settings) =>
-                                                Some(self.create_canister(*msg.sender(), cycles, settings, registry_settings.max_number_of_canisters, &mut state, registry_settings.subnet_size, round_limits))
+                                                Some(self.create_canister(canister_change_origin(&msg), cycles, settings, registry_settings.max_number_of_canisters, &mut state, registry_settings.subnet_size, round_limits))
                                         };
                                         info!(
                                             self.log,
@@ -536,13 +540,45 @@ impl ExecutionEnvironment {
                     Err(err) => Err(candid_error_to_user_error(err)),
                     Ok(args) => self
                         .canister_manager
-                        .uninstall_code(args.get_canister_id(), *msg.sender(), &mut state)
+                        .uninstall_code(
+                            canister_change_origin(&msg),
+                            args.get_canister_id(),
+                            &mut state,
+                        )
                         .map(|()| EmptyBlob.encode())
                         .map_err(|err| err.into()),
                 };
                 Some((res, msg.take_cycles()))
             }
 
+            Ok(Ic00Method::UploadChunk) => {
+                let res = match UploadChunkArgs::decode(payload) {
+                    Err(err) => Err(candid_error_to_user_error(err)),
+                    Ok(args) => self.upload_chunk(
+                        *msg.sender(),
+                        args.get_canister_id(),
+                        args.chunk,
+                        &mut state,
+                        round_limits,
+                        registry_settings.subnet_size,
+                    ),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::ClearChunkStore) => {
+                let res = match CanisterIdRecord::decode(payload) {
+                    Err(err) => Err(candid_error_to_user_error(err)),
+                    Ok(args) => self.clear_chunk_store(
+                        *msg.sender(),
+                        args.get_canister_id(),
+                        &mut state,
+                        round_limits,
+                    ),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
             Ok(Ic00Method::UpdateSettings) => {
                 let res = match UpdateSettingsArgs::decode(payload) {
                     Err(err) => Err(candid_error_to_user_error(err)),
@@ -554,11 +590,12 @@ impl ExecutionEnvironment {
                         let result = match CanisterSettings::try_from(args.settings) {
                             Err(err) => Err(err.into()),
                             Ok(settings) => self.update_settings(
-                                *msg.sender(),
+                                canister_change_origin(&msg),
                                 settings,
                                 canister_id,
                                 &mut state,
                                 round_limits,
+                                registry_settings.subnet_size,
                             ),
                         };
                         // The induction cost of `UpdateSettings` is charged
@@ -606,11 +643,12 @@ impl ExecutionEnvironment {
                     Ok(args) => self
                         .canister_manager
                         .set_controller(
-                            *msg.sender(),
+                            canister_change_origin(&msg),
                             args.get_canister_id(),
                             args.get_new_controller(),
                             &mut state,
                             round_limits,
+                            registry_settings.subnet_size,
                         )
                         .map(|()| EmptyBlob.encode())
                         .map_err(|err| err.into()),
@@ -618,6 +656,23 @@ impl ExecutionEnvironment {
                 Some((res, msg.take_cycles()))
             }
 
+            Ok(Ic00Method::CanisterInfo) => match &msg {
+                RequestOrIngress::Request(_) => {
+                    let res = match CanisterInfoRequest::decode(payload) {
+                        Err(err) => Err(candid_error_to_user_error(err)),
+                        Ok(record) => self.get_canister_info(
+                            record.canister_id(),
+                            record.num_requested_changes(),
+                            &state,
+                        ),
+                    };
+                    Some((res, msg.take_cycles()))
+                }
+                RequestOrIngress::Ingress(_) => {
+                    self.reject_unexpected_ingress(Ic00Method::CanisterInfo)
+                }
+            },
+
             Ok(Ic00Method::CanisterStatus) => {
                 let res = match CanisterIdRecord::decode(payload) {
                     Err(err) => Err(candid_error_to_user_error(err)),
@@ -853,7 +908,7 @@ impl ExecutionEnvironment {
                             Ok(settings) => self
                                 .canister_manager
                                 .create_canister_with_cycles(
-                                    *msg.sender(),
+                                    canister_change_origin(&msg),
                                     cycles_amount,
                                     settings,
                                     args.specified_id,
@@ -1200,6 +1255,7 @@ impl ExecutionEnvironment {
         ExecutionParameters {
             instruction_limits,
             canister_memory_limit: canister.memory_limit(self.config.max_canister_memory_size),
+            wasm_memory_limit: canister.system_state.wasm_memory_limit,
             compute_allocation: canister.scheduler_state.compute_allocation,
             subnet_type: self.own_subnet_type,
             execution_mode,
@@ -1208,7 +1264,7 @@ impl ExecutionEnvironment {
 
     fn create_canister(
         &self,
-        sender: PrincipalId,
+        origin: CanisterChangeOrigin,
         cycles: Cycles,
         settings: CanisterSettings,
         max_number_of_canisters: u64,
@@ -1216,10 +1272,10 @@ impl ExecutionEnvironment {
         subnet_size: usize,
         round_limits: &mut RoundLimits,
     ) -> (Result<Vec<u8>, UserError>, Cycles) {
-        match state.find_subnet_id(sender) {
+        match state.find_subnet_id(origin.origin()) {
             Ok(sender_subnet_id) => {
                 let (res, cycles) = self.canister_manager.create_canister(
-                    sender,
+                    origin,
                     sender_subnet_id,
                     cycles,
                     settings,
@@ -1240,15 +1296,47 @@ impl ExecutionEnvironment {
 
     fn update_settings(
         &self,
-        sender: PrincipalId,
+        origin: CanisterChangeOrigin,
         settings: CanisterSettings,
         canister_id: CanisterId,
         state: &mut ReplicatedState,
         round_limits: &mut RoundLimits,
+        subnet_size: usize,
+    ) -> Result<Vec<u8>, UserError> {
+        let time = state.time();
+        let canister = get_canister_mut(canister_id, state)?;
+        self.canister_manager
+            .update_settings(time, origin, settings, canister, round_limits, subnet_size)
+            .map(|()| EmptyBlob.encode())
+            .map_err(|err| err.into())
+    }
+
+    fn upload_chunk(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        chunk: Vec<u8>,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+        subnet_size: usize,
+    ) -> Result<Vec<u8>, UserError> {
+        let canister = get_canister_mut(canister_id, state)?;
+        self.canister_manager
+            .upload_chunk(sender, canister, chunk, round_limits, subnet_size)
+            .map(|reply| reply.encode())
+            .map_err(|err| err.into())
+    }
+
+    fn clear_chunk_store(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
     ) -> Result<Vec<u8>, UserError> {
         let canister = get_canister_mut(canister_id, state)?;
         self.canister_manager
-            .update_settings(sender, settings, canister, round_limits)
+            .clear_chunk_store(sender, canister, round_limits)
             .map(|()| EmptyBlob.encode())
             .map_err(|err| err.into())
     }
@@ -1306,6 +1394,24 @@ impl ExecutionEnvironment {
         }
     }
 
+    fn get_canister_info(
+        &self,
+        canister_id: CanisterId,
+        num_requested_changes: Option<u64>,
+        state: &ReplicatedState,
+    ) -> Result<Vec<u8>, UserError> {
+        let canister = state.canister_state(&canister_id).ok_or_else(|| {
+            UserError::new(
+                ErrorCode::CanisterNotFound,
+                format!("Canister {} not found.", &canister_id),
+            )
+        })?;
+        Ok(self
+            .canister_manager
+            .get_canister_info(num_requested_changes, canister)
+            .encode())
+    }
+
     fn get_canister_status(
         &self,
         sender: PrincipalId,
@@ -1434,6 +1540,10 @@ impl ExecutionEnvironment {
 
             if let IngressInductionCost::Fee { payer, cost } = induction_cost {
                 let paying_canister = canister(payer)?;
+                let cost = cost
+                    + self
+                        .cycles_account_manager
+                        .ingress_priority_fee(&paying_canister.system_state);
                 if let Err(err) = self.cycles_account_manager.can_withdraw_cycles(
                     &paying_canister.system_state,
                     cost,
@@ -1569,6 +1679,7 @@ impl ExecutionEnvironment {
                     originator_reply_callback: req.sender_reply_callback,
                     refund,
                     response_payload: payload,
+                    deadline: req.deadline,
                 };
 
                 state.push_subnet_output_response(response.into());
@@ -1650,6 +1761,7 @@ impl ExecutionEnvironment {
                             code: RejectCode::CanisterReject,
                             message: format!("Canister {}'s stop request cancelled", canister_id),
                         }),
+                        deadline: NO_DEADLINE,
                     };
                     state.push_subnet_output_response(response.into());
                 }
@@ -1855,8 +1967,22 @@ impl ExecutionEnvironment {
             state: &mut ReplicatedState,
         ) -> Result<(InstallCodeContext, CanisterState), UserError> {
             let payload = msg.method_payload();
-            let args = InstallCodeArgs::decode(payload).map_err(candid_error_to_user_error)?;
-            let install_context = InstallCodeContext::try_from((*msg.sender(), args))?;
+            let install_context = match Ic00Method::from_str(msg.method_name()) {
+                Ok(Ic00Method::InstallChunkedCode) => {
+                    let args = InstallChunkedCodeArgs::decode(payload)
+                        .map_err(candid_error_to_user_error)?;
+                    let canister_id = args.get_canister_id();
+                    let canister = state
+                        .canister_state(&canister_id)
+                        .ok_or(CanisterManagerError::CanisterNotFound(canister_id))?;
+                    install_code_context_from_chunks(canister_change_origin(msg), canister, args)?
+                }
+                _ => {
+                    let args =
+                        InstallCodeArgs::decode(payload).map_err(candid_error_to_user_error)?;
+                    install_code_context_from_args(canister_change_origin(msg), state, args)?
+                }
+            };
             let canister = state
                 .take_canister_state(&install_context.canister_id)
                 .ok_or(CanisterManagerError::CanisterNotFound(
@@ -2286,6 +2412,17 @@ pub(crate) fn subnet_memory_capacity(config: &ExecutionConfig) -> SubnetAvailabl
     )
 }
 
+/// Returns the origin of a canister change requested by `msg`: the calling
+/// canister for inter-canister requests, or the user for ingress messages.
+fn canister_change_origin(msg: &RequestOrIngress) -> CanisterChangeOrigin {
+    match msg {
+        RequestOrIngress::Request(request) => {
+            CanisterChangeOrigin::from_canister(request.sender.get(), None)
+        }
+        RequestOrIngress::Ingress(ingress) => CanisterChangeOrigin::from_user(ingress.source.get()),
+    }
+}
+
 fn get_canister_mut(
     canister_id: CanisterId,
     state: &mut ReplicatedState,
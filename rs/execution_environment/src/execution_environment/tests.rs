@@ -10,7 +10,7 @@ use ic_ic00_types::{
     self as ic00, CanisterHttpRequestArgs, CanisterIdRecord, CanisterStatusResultV2,
     CanisterStatusType, EcdsaCurve, EcdsaKeyId, EmptyBlob, HttpMethod, Method,
     Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs,
-    TransformContext, TransformFunc, IC_00,
+    QueryStatsResponse, TransformContext, TransformFunc, IC_00,
 };
 use ic_registry_routing_table::canister_id_into_u64;
 use ic_registry_routing_table::CanisterIdRange;
@@ -24,10 +24,12 @@ use ic_test_utilities::{assert_utils::assert_balance_equals, mock_time};
 use ic_test_utilities_metrics::{fetch_histogram_vec_count, metric_vec};
 use ic_types::canister_http::Transform;
 use ic_types::{
+    batch::QueryStats,
     canister_http::CanisterHttpMethod,
     ingress::{IngressState, IngressStatus, WasmResult},
     messages::{
         CallbackId, Payload, RejectContext, RequestOrResponse, Response, MAX_RESPONSE_COUNT_BYTES,
+        NO_DEADLINE,
     },
     CanisterId, Cycles, PrincipalId, RegistryVersion,
 };
@@ -692,6 +694,62 @@ fn get_running_canister_status_from_another_canister() {
     );
 }
 
+#[test]
+fn canister_status_reports_reserved_cycles() {
+    let mut test = ExecutionTestBuilder::new()
+        .with_subnet_total_memory(ONE_GIB)
+        .with_subnet_memory_threshold(0)
+        .build();
+    let canister = test.universal_canister().unwrap();
+    let reserved = test
+        .canister_state(canister)
+        .system_state
+        .reserved_balance();
+    assert!(reserved > Cycles::zero());
+
+    let reply = get_reply(test.canister_status(canister));
+    let csr = CanisterStatusResultV2::decode(&reply).unwrap();
+    assert_eq!(Cycles::new(csr.reserved_cycles()), reserved);
+    assert_eq!(csr.reserved_cycles_limit(), None);
+
+    let limit = reserved + Cycles::new(1_000_000);
+    test.update_reserved_cycles_limit(canister, limit).unwrap();
+    let reply = get_reply(test.canister_status(canister));
+    let csr = CanisterStatusResultV2::decode(&reply).unwrap();
+    assert_eq!(csr.reserved_cycles_limit(), Some(limit.get()));
+}
+
+#[test]
+fn canister_status_reports_total_query_stats() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister = test.universal_canister().unwrap();
+    let reply = get_reply(test.canister_status(canister));
+    let csr = CanisterStatusResultV2::decode(&reply).unwrap();
+    assert_eq!(csr.query_stats(), QueryStatsResponse::default());
+
+    let stats = QueryStats {
+        num_calls: 3,
+        num_instructions: 1_000,
+        ingress_payload_size: 10,
+        egress_payload_size: 20,
+    };
+    test.canister_state_mut(canister)
+        .system_state
+        .total_query_stats
+        .saturating_accumulate(&stats);
+    let reply = get_reply(test.canister_status(canister));
+    let csr = CanisterStatusResultV2::decode(&reply).unwrap();
+    assert_eq!(
+        csr.query_stats(),
+        QueryStatsResponse {
+            num_calls_total: candid::Nat::from(3_u64),
+            num_instructions_total: candid::Nat::from(1_000_u64),
+            request_payload_bytes_total: candid::Nat::from(10_u64),
+            response_payload_bytes_total: candid::Nat::from(20_u64),
+        }
+    );
+}
+
 #[test]
 fn get_canister_status_from_another_canister_when_memory_low() {
     let mut test = ExecutionTestBuilder::new().build();
@@ -1088,7 +1146,8 @@ fn setup_initial_dkg_sender_not_on_nns() {
                     ic00::Method::SetupInitialDKG,
                     other_canister,
                 )
-            })
+            }),
+            deadline: NO_DEADLINE,
         }
         .into()
     );
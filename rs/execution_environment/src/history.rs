@@ -293,6 +293,12 @@ fn dashboard_label_value_from(code: ErrorCode) -> &'static str {
         QueryCallGraphTooDeep => "Query call graph contains too many nested calls",
         QueryCallGraphTotalInstructionLimitExceeded => "Total instructions limit exceeded for query call graph",
         CompositeQueryCalledInReplicatedMode => "Composite query cannot be called in replicated mode",
+        CanisterWasmMemoryLimitExceeded => "Canister exceeded its Wasm memory limit",
+        InsufficientCyclesInMemoryGrow => "Canister does not have enough cycles to grow memory",
+        ReservedCyclesLimitExceededInMemoryGrow => {
+            "Canister cannot grow memory due to its reserved cycles limit"
+        }
+        ReservedCyclesLimitIsTooLow => "Canister reserved cycles limit is too low",
         CanisterNotHostedBySubnet => "Canister is not hosted by subnet",
     }
 }
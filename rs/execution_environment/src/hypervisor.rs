@@ -1,12 +1,15 @@
 use ic_canister_sandbox_replica_controller::sandboxed_execution_controller::SandboxedExecutionController;
 use ic_config::flag_status::FlagStatus;
 use ic_config::{embedders::Config as EmbeddersConfig, execution_environment::Config};
-use ic_cycles_account_manager::CyclesAccountManager;
+use ic_constants::SMALL_APP_SUBNET_MAX_SIZE;
+use ic_cycles_account_manager::{CyclesAccountManager, ResourceSaturation};
 use ic_embedders::wasm_executor::{WasmExecutionResult, WasmExecutor};
 use ic_embedders::wasm_utils::decoding::decoded_wasm_size;
 use ic_embedders::{wasm_executor::WasmExecutorImpl, WasmExecutionInput, WasmtimeEmbedder};
 use ic_embedders::{CompilationCache, CompilationResult};
-use ic_interfaces::execution_environment::{HypervisorResult, WasmExecutionOutput};
+use ic_interfaces::execution_environment::{
+    HypervisorResult, SubnetAvailableMemory, WasmExecutionOutput,
+};
 use ic_logger::{fatal, ReplicaLogger};
 use ic_metrics::buckets::decimal_buckets_with_zero;
 use ic_metrics::{buckets::exponential_buckets, MetricsRegistry};
@@ -124,6 +127,8 @@ pub struct Hypervisor {
     deterministic_time_slicing: FlagStatus,
     cost_to_compile_wasm_instruction: NumInstructions,
     dirty_page_overhead: NumInstructions,
+    subnet_memory_threshold: NumBytes,
+    subnet_memory_capacity: NumBytes,
 }
 
 impl Hypervisor {
@@ -230,6 +235,8 @@ impl Hypervisor {
         embedder_config.feature_flags.rate_limiting_of_debug_prints =
             config.rate_limiting_of_debug_prints;
         embedder_config.cost_to_compile_wasm_instruction = config.cost_to_compile_wasm_instruction;
+        embedder_config.feature_flags.instruction_profiling = config.instruction_profiling;
+        embedder_config.feature_flags.best_effort_responses = config.best_effort_responses;
 
         let wasm_executor: Arc<dyn WasmExecutor> = match config.canister_sandboxing_flag {
             FlagStatus::Enabled => {
@@ -262,6 +269,8 @@ impl Hypervisor {
             deterministic_time_slicing: config.deterministic_time_slicing,
             cost_to_compile_wasm_instruction: config.cost_to_compile_wasm_instruction,
             dirty_page_overhead,
+            subnet_memory_threshold: config.subnet_memory_threshold,
+            subnet_memory_capacity: config.subnet_memory_capacity,
         }
     }
 
@@ -276,6 +285,8 @@ impl Hypervisor {
         deterministic_time_slicing: FlagStatus,
         cost_to_compile_wasm_instruction: NumInstructions,
         dirty_page_overhead: NumInstructions,
+        subnet_memory_threshold: NumBytes,
+        subnet_memory_capacity: NumBytes,
     ) -> Self {
         Self {
             wasm_executor,
@@ -288,9 +299,25 @@ impl Hypervisor {
             deterministic_time_slicing,
             cost_to_compile_wasm_instruction,
             dirty_page_overhead,
+            subnet_memory_threshold,
+            subnet_memory_capacity,
         }
     }
 
+    /// Returns the saturation of the subnet memory given the memory that is
+    /// still available. It determines how many cycles a canister has to
+    /// reserve when allocating memory.
+    pub(crate) fn subnet_memory_saturation(
+        &self,
+        subnet_available_memory: &SubnetAvailableMemory,
+    ) -> ResourceSaturation {
+        ResourceSaturation::new_from_available(
+            subnet_available_memory.get_total_memory(),
+            self.subnet_memory_threshold.get(),
+            self.subnet_memory_capacity.get(),
+        )
+    }
+
     #[cfg(test)]
     pub fn compile_count(&self) -> u64 {
         self.metrics.compile.get_sample_count()
@@ -316,6 +343,16 @@ impl Hypervisor {
             execution_parameters.instruction_limits.message(),
             execution_parameters.instruction_limits.slice()
         );
+        let subnet_size = network_topology
+            .get_subnet_size(&self.own_subnet_id)
+            .unwrap_or(SMALL_APP_SUBNET_MAX_SIZE);
+        let freezing_threshold = self.cycles_account_manager.freeze_threshold_cycles(
+            system_state.freeze_threshold,
+            system_state.memory_allocation,
+            canister_current_memory_usage,
+            execution_parameters.compute_allocation,
+            subnet_size,
+        );
         let execution_result = self.execute_dts(
             api_type,
             &execution_state,
@@ -335,6 +372,8 @@ impl Hypervisor {
             }
         };
         update_round_limits(round_limits, &slice);
+        let subnet_memory_saturation =
+            self.subnet_memory_saturation(&round_limits.subnet_available_memory);
         apply_canister_state_changes(
             canister_state_changes,
             &mut execution_state,
@@ -345,6 +384,10 @@ impl Hypervisor {
             network_topology,
             self.own_subnet_id,
             &self.log,
+            &subnet_memory_saturation,
+            freezing_threshold,
+            subnet_size,
+            &self.cycles_account_manager,
         );
         (output, execution_state, system_state)
     }
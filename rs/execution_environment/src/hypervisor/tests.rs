@@ -749,6 +749,162 @@ fn ic0_stable64_read_does_not_trap_if_in_bounds() {
     assert_empty_reply(result);
 }
 
+#[test]
+fn ic0_stable64_checkpoint_copies_only_modified_pages() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wat = r#"
+        (module
+            (import "ic0" "stable64_grow" (func $stable64_grow (param i64) (result i64)))
+            (import "ic0" "stable64_read"
+                (func $stable64_read (param $dst i64) (param $offset i64) (param $size i64))
+            )
+            (import "ic0" "stable64_checkpoint"
+                (func $stable64_checkpoint
+                    (param $offset i64) (param $src i64) (param $size i64) (result i64))
+            )
+            (func (export "canister_update modify")
+                (drop (call $stable64_grow (i64.const 1)))
+                ;; Modify the first and the third OS page of the heap.
+                (i32.store (i32.const 100) (i32.const 42))
+                (i32.store (i32.const 8292) (i32.const 43))
+                ;; Only the two modified pages should be copied.
+                (if (i64.ne
+                        (call $stable64_checkpoint (i64.const 0) (i64.const 0) (i64.const 65536))
+                        (i64.const 8192))
+                    (then (unreachable))
+                )
+            )
+            (func (export "canister_update check")
+                ;; Nothing has been modified yet in this message.
+                (if (i64.ne
+                        (call $stable64_checkpoint (i64.const 0) (i64.const 0) (i64.const 65536))
+                        (i64.const 0))
+                    (then (unreachable))
+                )
+                ;; The values written in the previous message are in stable memory.
+                (call $stable64_read (i64.const 16384) (i64.const 100) (i64.const 4))
+                (call $stable64_read (i64.const 16388) (i64.const 8292) (i64.const 4))
+                (if (i32.ne (i32.load (i32.const 16384)) (i32.const 42))
+                    (then (unreachable))
+                )
+                (if (i32.ne (i32.load (i32.const 16388)) (i32.const 43))
+                    (then (unreachable))
+                )
+            )
+            (memory 1 1)
+        )"#;
+    let canister_id = test.canister_from_wat(wat).unwrap();
+    let result = test.ingress(canister_id, "modify", vec![]);
+    assert_empty_reply(result);
+    let result = test.ingress(canister_id, "check", vec![]);
+    assert_empty_reply(result);
+}
+
+#[test]
+fn ic0_stable64_checkpoint_copies_pages_modified_in_earlier_messages() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wat = r#"
+        (module
+            (import "ic0" "stable64_grow" (func $stable64_grow (param i64) (result i64)))
+            (import "ic0" "stable64_checkpoint"
+                (func $stable64_checkpoint
+                    (param $offset i64) (param $src i64) (param $size i64) (result i64))
+            )
+            (func (export "canister_update modify")
+                (drop (call $stable64_grow (i64.const 1)))
+                (i32.store (i32.const 100) (i32.const 42))
+            )
+            (func (export "canister_update checkpoint")
+                ;; The page modified in the previous message is copied once.
+                (if (i64.ne
+                        (call $stable64_checkpoint (i64.const 0) (i64.const 0) (i64.const 65536))
+                        (i64.const 4096))
+                    (then (unreachable))
+                )
+                (if (i64.ne
+                        (call $stable64_checkpoint (i64.const 0) (i64.const 0) (i64.const 65536))
+                        (i64.const 0))
+                    (then (unreachable))
+                )
+            )
+            (memory 1 1)
+        )"#;
+    let canister_id = test.canister_from_wat(wat).unwrap();
+    let result = test.ingress(canister_id, "modify", vec![]);
+    assert_empty_reply(result);
+    let result = test.ingress(canister_id, "checkpoint", vec![]);
+    assert_empty_reply(result);
+}
+
+#[test]
+fn ic0_stable64_restore_copies_only_differing_pages() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wat = r#"
+        (module
+            (import "ic0" "stable64_grow" (func $stable64_grow (param i64) (result i64)))
+            (import "ic0" "stable64_checkpoint"
+                (func $stable64_checkpoint
+                    (param $offset i64) (param $src i64) (param $size i64) (result i64))
+            )
+            (import "ic0" "stable64_restore"
+                (func $stable64_restore
+                    (param $dst i64) (param $offset i64) (param $size i64) (result i64))
+            )
+            (func (export "canister_update checkpoint")
+                (drop (call $stable64_grow (i64.const 1)))
+                (i32.store (i32.const 100) (i32.const 42))
+                (drop (call $stable64_checkpoint (i64.const 0) (i64.const 0) (i64.const 65536)))
+            )
+            (func (export "canister_update restore")
+                (i32.store (i32.const 100) (i32.const 43))
+                ;; Only the overwritten page is copied back.
+                (if (i64.ne
+                        (call $stable64_restore (i64.const 0) (i64.const 0) (i64.const 65536))
+                        (i64.const 4096))
+                    (then (unreachable))
+                )
+                (if (i32.ne (i32.load (i32.const 100)) (i32.const 42))
+                    (then (unreachable))
+                )
+                (if (i64.ne
+                        (call $stable64_restore (i64.const 0) (i64.const 0) (i64.const 65536))
+                        (i64.const 0))
+                    (then (unreachable))
+                )
+            )
+            (memory 1 1)
+        )"#;
+    let canister_id = test.canister_from_wat(wat).unwrap();
+    let result = test.ingress(canister_id, "checkpoint", vec![]);
+    assert_empty_reply(result);
+    let result = test.ingress(canister_id, "restore", vec![]);
+    assert_empty_reply(result);
+}
+
+#[test]
+fn ic0_stable64_checkpoint_traps_if_out_of_bounds() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wat = r#"
+        (module
+            (import "ic0" "stable64_grow" (func $stable64_grow (param i64) (result i64)))
+            (import "ic0" "stable64_checkpoint"
+                (func $stable64_checkpoint
+                    (param $offset i64) (param $src i64) (param $size i64) (result i64))
+            )
+            (func (export "canister_update test")
+                ;; Grow stable memory by 1 page (64kb).
+                (drop (call $stable64_grow (i64.const 1)))
+                ;; The range is checked even if the heap matches stable memory.
+                (drop (call $stable64_checkpoint (i64.const 1) (i64.const 0) (i64.const 65536)))
+            )
+            (memory 1 1)
+        )"#;
+    let canister_id = test.canister_from_wat(wat).unwrap();
+    let err = test.ingress(canister_id, "test", vec![]).unwrap_err();
+    assert_eq!(ErrorCode::CanisterTrapped, err.code());
+    assert!(err.description().contains("stable memory out of bounds"));
+}
+
 #[test]
 fn time_with_5_nanoseconds() {
     let mut test = ExecutionTestBuilder::new().build();
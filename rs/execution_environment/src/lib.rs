@@ -38,7 +38,9 @@ use ic_replicated_state::{CallOrigin, NetworkTopology, ReplicatedState};
 use ic_types::{messages::CallContextId, SubnetId};
 use ingress_filter::IngressFilter;
 use query_handler::HttpQueryHandler;
-pub use query_handler::InternalHttpQueryHandler;
+pub use query_handler::{
+    InternalHttpQueryHandler, QueryStatsCollector, QueryStatsPayloadBuilderImpl,
+};
 pub use scheduler::RoundSchedule;
 use scheduler::SchedulerImpl;
 use std::sync::{Arc, Mutex};
@@ -86,6 +88,7 @@ pub struct ExecutionServices {
     pub async_query_handler: QueryExecutionService,
     pub anonymous_query_handler: AnonymousQueryService,
     pub scheduler: Box<dyn Scheduler<State = ReplicatedState>>,
+    pub query_stats_collector: Arc<QueryStatsCollector>,
 }
 
 impl ExecutionServices {
@@ -131,6 +134,8 @@ impl ExecutionServices {
             config.clone(),
             Arc::clone(&cycles_account_manager),
         ));
+        let query_stats_collector =
+            Arc::new(QueryStatsCollector::new(config.query_stats_epoch_length));
         let sync_query_handler = Arc::new(InternalHttpQueryHandler::new(
             logger.clone(),
             hypervisor,
@@ -140,6 +145,7 @@ impl ExecutionServices {
             scheduler_config.max_instructions_per_message_without_dts,
             Arc::clone(&cycles_account_manager),
             config.composite_queries,
+            Arc::clone(&query_stats_collector),
         ));
         let threadpool = threadpool::Builder::new()
             .num_threads(config.query_execution_threads)
@@ -159,6 +165,7 @@ impl ExecutionServices {
             Arc::clone(&sync_query_handler) as Arc<_>,
             Arc::clone(&threadpool),
             Arc::clone(&state_reader),
+            Arc::clone(&query_stats_collector),
         );
         let ingress_filter = IngressFilter::new_service(
             concurrency_buffer.clone(),
@@ -198,6 +205,7 @@ impl ExecutionServices {
             async_query_handler,
             anonymous_query_handler,
             scheduler,
+            query_stats_collector,
         }
     }
 
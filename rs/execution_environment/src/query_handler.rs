@@ -2,9 +2,12 @@
 //! query methods via query calls.
 
 mod query_context;
+mod query_stats;
 #[cfg(test)]
 mod tests;
 
+pub use query_stats::{QueryStatsCollector, QueryStatsPayloadBuilderImpl};
+
 use crate::execution_environment::subnet_memory_capacity;
 use crate::{
     hypervisor::Hypervisor,
@@ -22,12 +25,13 @@ use ic_metrics::MetricsRegistry;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::ReplicatedState;
 use ic_types::{
+    batch::QueryStats,
     ingress::WasmResult,
     messages::{
         Blob, Certificate, CertificateDelegation, HttpQueryResponse, HttpQueryResponseReply,
         UserQuery,
     },
-    CanisterId, NumInstructions,
+    CanisterId, Height, NumInstructions,
 };
 use serde::Serialize;
 use std::{
@@ -52,7 +56,7 @@ fn get_latest_certified_state_and_data_certificate(
     state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
     certificate_delegation: Option<CertificateDelegation>,
     canister_id: CanisterId,
-) -> Option<(Arc<ReplicatedState>, Vec<u8>)> {
+) -> Option<(Arc<ReplicatedState>, Vec<u8>, Height)> {
     // The path to fetch the data certificate for the canister.
     let path = SubTree(flatmap! {
         label("canister") => SubTree(
@@ -77,6 +81,7 @@ fn get_latest_certified_state_and_data_certificate(
                     signature: Blob(cert.signed.signature.signature.get().0),
                     delegation: certificate_delegation,
                 }),
+                cert.height,
             )
         })
 }
@@ -94,6 +99,7 @@ pub struct InternalHttpQueryHandler {
     max_instructions_per_query: NumInstructions,
     cycles_account_manager: Arc<CyclesAccountManager>,
     composite_queries: FlagStatus,
+    query_stats_collector: Arc<QueryStatsCollector>,
 }
 
 #[derive(Clone)]
@@ -102,6 +108,7 @@ pub(crate) struct HttpQueryHandler {
     internal: Arc<dyn QueryHandler<State = ReplicatedState>>,
     state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
     threadpool: Arc<Mutex<threadpool::ThreadPool>>,
+    query_stats_collector: Arc<QueryStatsCollector>,
 }
 
 impl InternalHttpQueryHandler {
@@ -114,6 +121,7 @@ impl InternalHttpQueryHandler {
         max_instructions_per_query: NumInstructions,
         cycles_account_manager: Arc<CyclesAccountManager>,
         composite_queries: FlagStatus,
+        query_stats_collector: Arc<QueryStatsCollector>,
     ) -> Self {
         Self {
             log,
@@ -124,6 +132,7 @@ impl InternalHttpQueryHandler {
             max_instructions_per_query,
             cycles_account_manager,
             composite_queries,
+            query_stats_collector,
         }
     }
 }
@@ -158,12 +167,30 @@ impl QueryHandler for InternalHttpQueryHandler {
             self.config.instruction_overhead_per_query_call,
             self.composite_queries,
         );
-        context.run(
+        let canister_id = query.receiver;
+        let ingress_payload_size = query.method_payload.len() as u64;
+        let result = context.run(
             query,
             &self.metrics,
             Arc::clone(&self.cycles_account_manager),
             &measurement_scope,
-        )
+        );
+
+        let egress_payload_size = match &result {
+            Ok(WasmResult::Reply(reply)) => reply.len(),
+            Ok(WasmResult::Reject(message)) => message.len(),
+            Err(err) => err.description().len(),
+        } as u64;
+        self.query_stats_collector.register_query_statistics(
+            canister_id,
+            &QueryStats {
+                num_calls: 1,
+                num_instructions: context.total_instructions_executed().get(),
+                ingress_payload_size,
+                egress_payload_size,
+            },
+        );
+        result
     }
 }
 
@@ -173,11 +200,13 @@ impl HttpQueryHandler {
         internal: Arc<dyn QueryHandler<State = ReplicatedState>>,
         threadpool: Arc<Mutex<threadpool::ThreadPool>>,
         state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+        query_stats_collector: Arc<QueryStatsCollector>,
     ) -> QueryExecutionService {
         let base_service = BoxCloneService::new(Self {
             internal,
             state_reader,
             threadpool,
+            query_stats_collector,
         });
         ServiceBuilder::new()
             .layer(concurrency_buffer)
@@ -214,6 +243,7 @@ impl Service<(UserQuery, Option<CertificateDelegation>)> for HttpQueryHandler {
     ) -> Self::Future {
         let internal = Arc::clone(&self.internal);
         let state_reader = Arc::clone(&self.state_reader);
+        let query_stats_collector = Arc::clone(&self.query_stats_collector);
         let (tx, rx) = oneshot::channel();
         let threadpool = self.threadpool.lock().unwrap().clone();
         threadpool.execute(move || {
@@ -226,7 +256,10 @@ impl Service<(UserQuery, Option<CertificateDelegation>)> for HttpQueryHandler {
                     certificate_delegation,
                     query.receiver,
                 ) {
-                    Some((state, cert)) => internal.query(query, state, cert),
+                    Some((state, cert, certified_height)) => {
+                        query_stats_collector.set_epoch_from_height(certified_height);
+                        internal.query(query, state, cert)
+                    }
                     None => Err(UserError::new(
                         ErrorCode::CertifiedStateUnavailable,
                         "Certified state is not available yet. Please try again...",
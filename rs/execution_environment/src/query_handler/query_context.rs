@@ -59,6 +59,7 @@ use ic_types::{
     ingress::WasmResult,
     messages::{
         CallbackId, Payload, RejectContext, Request, RequestOrResponse, Response, UserQuery,
+        NO_DEADLINE,
     },
     methods::WasmMethod,
     CanisterId, Cycles, NumInstructions, NumMessages, Time,
@@ -104,6 +105,7 @@ fn generate_response(request: Arc<Request>, payload: Payload) -> Response {
         originator_reply_callback: request.sender_reply_callback,
         response_payload: payload,
         refund: Cycles::zero(),
+        deadline: request.deadline,
     }
 }
 
@@ -153,6 +155,9 @@ pub(super) struct QueryContext<'a> {
     instructions_per_composite_query_call: NumInstructions,
     round_limits: RoundLimits,
     composite_queries: FlagStatus,
+    // Number of instructions executed so far, across all canisters in the
+    // call graph. Reported in the query stats of the queried canister.
+    total_instructions_executed: NumInstructions,
 }
 
 impl<'a> QueryContext<'a> {
@@ -195,9 +200,15 @@ impl<'a> QueryContext<'a> {
             instructions_per_composite_query_call,
             round_limits,
             composite_queries,
+            total_instructions_executed: NumInstructions::from(0),
         }
     }
 
+    /// Returns the number of instructions executed so far.
+    pub(super) fn total_instructions_executed(&self) -> NumInstructions {
+        self.total_instructions_executed
+    }
+
     /// Executes the given Query sent by an end user.
     ///
     /// - If it produces a response return the response.
@@ -427,7 +438,7 @@ impl<'a> QueryContext<'a> {
                         // Messages of these types are not produced by this
                         // module so must have existed on the canister's output
                         // queue from before.
-                        CallOrigin::CanisterUpdate(_, _)
+                        CallOrigin::CanisterUpdate(_, _, _)
                         | CallOrigin::SystemTask
                         | CallOrigin::Ingress(_, _) => continue,
 
@@ -513,6 +524,11 @@ impl<'a> QueryContext<'a> {
                 .get()
                 .saturating_sub(instructions_executed.get()),
         );
+        self.total_instructions_executed = NumInstructions::from(
+            self.total_instructions_executed
+                .get()
+                .saturating_add(instructions_executed.get()),
+        );
         measurement_scope.add(
             instructions_executed,
             NumSlices::from(1),
@@ -562,7 +578,7 @@ impl<'a> QueryContext<'a> {
         };
         let func_ref = match call_origin {
             CallOrigin::Ingress(_, _)
-            | CallOrigin::CanisterUpdate(_, _)
+            | CallOrigin::CanisterUpdate(_, _, _)
             | CallOrigin::SystemTask => unreachable!("Unreachable in the QueryContext."),
             CallOrigin::CanisterQuery(_, _) | CallOrigin::Query(_) => {
                 FuncRef::QueryClosure(closure)
@@ -655,6 +671,11 @@ impl<'a> QueryContext<'a> {
                 .get()
                 .saturating_sub(instructions_executed.get()),
         );
+        self.total_instructions_executed = NumInstructions::from(
+            self.total_instructions_executed
+                .get()
+                .saturating_add(instructions_executed.get()),
+        );
 
         measurement_scope.add(
             instructions_executed,
@@ -682,7 +703,7 @@ impl<'a> QueryContext<'a> {
     ) -> (NumInstructions, Result<Option<WasmResult>, HypervisorError>) {
         let func_ref = match call_origin {
             CallOrigin::Ingress(_, _)
-            | CallOrigin::CanisterUpdate(_, _)
+            | CallOrigin::CanisterUpdate(_, _, _)
             | CallOrigin::SystemTask => unreachable!("Unreachable in the QueryContext."),
             CallOrigin::CanisterQuery(_, _) | CallOrigin::Query(_) => {
                 FuncRef::QueryClosure(cleanup_closure)
@@ -928,6 +949,7 @@ impl<'a> QueryContext<'a> {
                 originator_reply_callback: callback_id,
                 response_payload: payload,
                 refund: Cycles::zero(),
+                deadline: NO_DEADLINE,
             };
             self.outstanding_response = Some(response);
         };
@@ -1056,7 +1078,7 @@ impl<'a> QueryContext<'a> {
         match call_origin {
             CallOrigin::Query(_) => self.handle_response_with_query_origin(canister, action),
 
-            CallOrigin::CanisterUpdate(_, _)
+            CallOrigin::CanisterUpdate(_, _, _)
             | CallOrigin::Ingress(_, _)
             | CallOrigin::SystemTask => fatal!(
                 self.log,
@@ -1078,6 +1100,7 @@ impl<'a> QueryContext<'a> {
         ExecutionParameters {
             instruction_limits,
             canister_memory_limit: canister.memory_limit(self.max_canister_memory_size),
+            wasm_memory_limit: None,
             compute_allocation: canister.scheduler_state.compute_allocation,
             subnet_type: self.own_subnet_type,
             execution_mode: ExecutionMode::NonReplicated,
@@ -0,0 +1,566 @@
+//! Collection of query call statistics and their delivery through consensus.
+//!
+//! Query calls are executed by a single replica, so no other replica learns
+//! about them. Each replica therefore records per-canister statistics of the
+//! queries it executed, grouped into epochs of certified heights. Once an
+//! epoch has ended, the replica includes its statistics for that epoch in the
+//! next block it makes, even if it did not execute any query. Message Routing
+//! then aggregates the statistics delivered by the nodes of the subnet and
+//! adds them to the totals in the canister's `SystemState`.
+
+use ic_interfaces::{
+    query_stats::{
+        InvalidQueryStatsPayload, QueryStatsPayloadBuilder, QueryStatsPayloadValidationError,
+        QueryStatsTransientValidationError,
+    },
+    validation::ValidationError,
+};
+use ic_interfaces_state_manager::StateReader;
+use ic_logger::{warn, ReplicaLogger};
+use ic_replicated_state::ReplicatedState;
+use ic_types::{
+    batch::{
+        epoch_from_height, CanisterQueryStats, QueryStats, QueryStatsEpoch, QueryStatsPayload,
+        ValidationContext,
+    },
+    CanisterId, CountBytes, Height, NodeId, NumBytes,
+};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Default)]
+struct CollectorState {
+    /// The epoch of the latest certified height seen so far, if any.
+    current_epoch: Option<QueryStatsEpoch>,
+    /// The statistics collected so far, by epoch and canister.
+    stats: BTreeMap<QueryStatsEpoch, BTreeMap<CanisterId, QueryStats>>,
+}
+
+/// Collects the statistics of the query calls executed by this replica.
+pub struct QueryStatsCollector {
+    epoch_length: u64,
+    state: Mutex<CollectorState>,
+}
+
+impl QueryStatsCollector {
+    pub fn new(epoch_length: u64) -> Self {
+        Self {
+            epoch_length,
+            state: Mutex::new(CollectorState::default()),
+        }
+    }
+
+    /// Advances the current epoch to the one containing the given certified
+    /// height. Statistics registered afterwards are attributed to that epoch.
+    pub fn set_epoch_from_height(&self, height: Height) {
+        let epoch = epoch_from_height(height, self.epoch_length);
+        let mut state = self.state.lock().unwrap();
+        if state.current_epoch.map_or(true, |current| current < epoch) {
+            state.current_epoch = Some(epoch);
+        }
+    }
+
+    /// Adds the statistics of a query call executed on `canister_id` to the
+    /// current epoch. Calls executed before any certified height is known are
+    /// not recorded.
+    pub fn register_query_statistics(&self, canister_id: CanisterId, stats: &QueryStats) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(epoch) = state.current_epoch {
+            state
+                .stats
+                .entry(epoch)
+                .or_default()
+                .entry(canister_id)
+                .or_default()
+                .saturating_accumulate(stats);
+        }
+    }
+
+    /// Returns the statistics collected during `epoch`, sorted by canister
+    /// id. Statistics of older epochs are dropped, since they are no longer
+    /// going to be delivered.
+    fn epoch_stats(&self, epoch: QueryStatsEpoch) -> Vec<CanisterQueryStats> {
+        let mut state = self.state.lock().unwrap();
+        state.stats = state.stats.split_off(&epoch);
+        state
+            .stats
+            .get(&epoch)
+            .map(|stats| {
+                stats
+                    .iter()
+                    .map(|(canister_id, stats)| CanisterQueryStats {
+                        canister_id: *canister_id,
+                        stats: *stats,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Builds and validates the query stats section of block payloads.
+pub struct QueryStatsPayloadBuilderImpl {
+    collector: Arc<QueryStatsCollector>,
+    state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+    node_id: NodeId,
+    epoch_length: u64,
+    log: ReplicaLogger,
+}
+
+impl QueryStatsPayloadBuilderImpl {
+    pub fn new(
+        collector: Arc<QueryStatsCollector>,
+        state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+        node_id: NodeId,
+        epoch_length: u64,
+        log: ReplicaLogger,
+    ) -> Self {
+        Self {
+            collector,
+            state_reader,
+            node_id,
+            epoch_length,
+            log,
+        }
+    }
+
+    /// Returns the last epoch delivered for `proposer`, either in the state at
+    /// the certified height or in one of the past payloads, together with the
+    /// last epoch aggregated in the state at the certified height.
+    fn last_delivered_epoch(
+        &self,
+        proposer: &NodeId,
+        certified_height: Height,
+        past_payloads: &[&QueryStatsPayload],
+    ) -> Result<
+        (Option<QueryStatsEpoch>, Option<QueryStatsEpoch>),
+        QueryStatsTransientValidationError,
+    > {
+        let state = self
+            .state_reader
+            .get_state_at(certified_height)
+            .map_err(QueryStatsTransientValidationError::StateUnavailable)?;
+        let query_stats = &state.get_ref().metadata.query_stats;
+        let in_state = query_stats.last_delivered(proposer);
+        let in_past_payloads = past_payloads
+            .iter()
+            .filter(|payload| payload.proposer == *proposer)
+            .map(|payload| payload.epoch)
+            .max();
+        Ok((
+            in_state.max(in_past_payloads),
+            query_stats.last_aggregated(),
+        ))
+    }
+}
+
+impl QueryStatsPayloadBuilder for QueryStatsPayloadBuilderImpl {
+    fn get_query_stats_payload(
+        &self,
+        height: Height,
+        validation_context: &ValidationContext,
+        past_payloads: &[&QueryStatsPayload],
+        byte_limit: NumBytes,
+    ) -> Option<QueryStatsPayload> {
+        let current_epoch =
+            epoch_from_height(validation_context.certified_height, self.epoch_length);
+        let (delivered, aggregated) = match self.last_delivered_epoch(
+            &self.node_id,
+            validation_context.certified_height,
+            past_payloads,
+        ) {
+            Ok(epochs) => epochs,
+            Err(err) => {
+                warn!(
+                    self.log,
+                    "Failed to build query stats payload at height {}: {:?}", height, err
+                );
+                return None;
+            }
+        };
+        // Epochs are reported in order, so that enough reports of the same
+        // epoch are delivered for it to be aggregated. Epochs that were already
+        // aggregated are skipped, and a node without any delivered epoch starts
+        // with the last completed one.
+        let epoch = match delivered.max(aggregated) {
+            Some(epoch) => epoch.increment(),
+            None => QueryStatsEpoch::from(current_epoch.get().checked_sub(1)?),
+        };
+        if epoch >= current_epoch {
+            return None;
+        }
+
+        let mut payload = QueryStatsPayload {
+            proposer: self.node_id,
+            epoch,
+            stats: vec![],
+        };
+        // Statistics that do not fit into the block are dropped, so that the
+        // epoch can still be delivered.
+        let mut remaining = byte_limit.get().checked_sub(payload.count_bytes() as u64)?;
+        payload.stats = self
+            .collector
+            .epoch_stats(epoch)
+            .into_iter()
+            .take_while(|stats| {
+                let size = stats.count_bytes() as u64;
+                remaining = match remaining.checked_sub(size) {
+                    Some(remaining) => remaining,
+                    None => return false,
+                };
+                true
+            })
+            .collect();
+        Some(payload)
+    }
+
+    fn validate_query_stats_payload(
+        &self,
+        _height: Height,
+        proposer: NodeId,
+        payload: &QueryStatsPayload,
+        validation_context: &ValidationContext,
+        past_payloads: &[&QueryStatsPayload],
+    ) -> Result<NumBytes, QueryStatsPayloadValidationError> {
+        if payload.proposer != proposer {
+            return Err(ValidationError::Permanent(
+                InvalidQueryStatsPayload::InvalidProposer {
+                    expected: proposer,
+                    proposer: payload.proposer,
+                },
+            ));
+        }
+
+        let current_epoch =
+            epoch_from_height(validation_context.certified_height, self.epoch_length);
+        if payload.epoch >= current_epoch {
+            return Err(ValidationError::Permanent(
+                InvalidQueryStatsPayload::EpochNotCompleted {
+                    epoch: payload.epoch,
+                    current_epoch,
+                },
+            ));
+        }
+
+        let (delivered, _) = self
+            .last_delivered_epoch(
+                &payload.proposer,
+                validation_context.certified_height,
+                past_payloads,
+            )
+            .map_err(ValidationError::Transient)?;
+        if delivered.map_or(false, |delivered| delivered >= payload.epoch) {
+            return Err(ValidationError::Permanent(
+                InvalidQueryStatsPayload::AlreadyDelivered {
+                    proposer: payload.proposer,
+                    epoch: payload.epoch,
+                },
+            ));
+        }
+
+        for pair in payload.stats.windows(2) {
+            if pair[0].canister_id >= pair[1].canister_id {
+                return Err(ValidationError::Permanent(
+                    InvalidQueryStatsPayload::DuplicateOrUnsortedCanisterId(pair[1].canister_id),
+                ));
+            }
+        }
+
+        Ok(NumBytes::new(payload.count_bytes() as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_interfaces_state_manager::Labeled;
+    use ic_interfaces_state_manager_mocks::MockStateManager;
+    use ic_logger::replica_logger::no_op_logger;
+    use ic_registry_subnet_type::SubnetType;
+    use ic_replicated_state::{NodeTopology, SubnetTopology};
+    use ic_test_utilities::{
+        mock_time,
+        types::ids::{canister_test_id, node_test_id, subnet_test_id},
+    };
+    use ic_types::RegistryVersion;
+
+    const EPOCH_LENGTH: u64 = 10;
+
+    fn stats(num_calls: u32) -> QueryStats {
+        QueryStats {
+            num_calls,
+            num_instructions: 1_000 * num_calls as u64,
+            ingress_payload_size: 10 * num_calls as u64,
+            egress_payload_size: 20 * num_calls as u64,
+        }
+    }
+
+    fn context(certified_height: u64) -> ValidationContext {
+        ValidationContext {
+            registry_version: RegistryVersion::from(1),
+            certified_height: Height::from(certified_height),
+            time: mock_time(),
+        }
+    }
+
+    fn payload(proposer: u64, epoch: u64) -> QueryStatsPayload {
+        QueryStatsPayload {
+            proposer: node_test_id(proposer),
+            epoch: epoch.into(),
+            stats: vec![],
+        }
+    }
+
+    /// Returns a payload builder for node 1 of a subnet of 4 nodes, where
+    /// `delivered` were delivered in the certified state.
+    fn payload_builder(
+        collector: Arc<QueryStatsCollector>,
+        delivered: &[QueryStatsPayload],
+    ) -> QueryStatsPayloadBuilderImpl {
+        let mut state = ReplicatedState::new(subnet_test_id(1), SubnetType::Application);
+        state.metadata.network_topology.subnets.insert(
+            subnet_test_id(1),
+            SubnetTopology {
+                nodes: (1..=4)
+                    .map(|node| (node_test_id(node), NodeTopology::default()))
+                    .collect(),
+                ..Default::default()
+            },
+        );
+        for payload in delivered {
+            state.deliver_query_stats(payload);
+        }
+        let state = Arc::new(state);
+        let mut state_manager = MockStateManager::new();
+        state_manager
+            .expect_get_state_at()
+            .returning(move |height| Ok(Labeled::new(height, Arc::clone(&state))));
+        QueryStatsPayloadBuilderImpl::new(
+            collector,
+            Arc::new(state_manager),
+            node_test_id(1),
+            EPOCH_LENGTH,
+            no_op_logger(),
+        )
+    }
+
+    #[test]
+    fn collector_groups_stats_by_epoch_and_canister() {
+        let collector = QueryStatsCollector::new(EPOCH_LENGTH);
+        // No certified height known yet, not recorded.
+        collector.register_query_statistics(canister_test_id(1), &stats(100));
+
+        collector.set_epoch_from_height(Height::from(5));
+        collector.register_query_statistics(canister_test_id(2), &stats(1));
+        collector.register_query_statistics(canister_test_id(1), &stats(1));
+        collector.register_query_statistics(canister_test_id(1), &stats(2));
+        // Heights never move the epoch backwards.
+        collector.set_epoch_from_height(Height::from(15));
+        collector.set_epoch_from_height(Height::from(3));
+        collector.register_query_statistics(canister_test_id(1), &stats(7));
+
+        assert_eq!(
+            collector.epoch_stats(QueryStatsEpoch::from(0)),
+            vec![
+                CanisterQueryStats {
+                    canister_id: canister_test_id(1),
+                    stats: stats(3),
+                },
+                CanisterQueryStats {
+                    canister_id: canister_test_id(2),
+                    stats: stats(1),
+                },
+            ]
+        );
+
+        // Once epoch 1 is requested, the statistics of epoch 0 are dropped.
+        assert_eq!(collector.epoch_stats(QueryStatsEpoch::from(1)).len(), 1);
+        assert_eq!(collector.epoch_stats(QueryStatsEpoch::from(0)), vec![]);
+    }
+
+    #[test]
+    fn payload_builder_reports_completed_epochs_in_order() {
+        let collector = Arc::new(QueryStatsCollector::new(EPOCH_LENGTH));
+        collector.set_epoch_from_height(Height::from(12));
+        collector.register_query_statistics(canister_test_id(1), &stats(4));
+        let builder = payload_builder(Arc::clone(&collector), &[]);
+
+        // Epoch 1 has not ended yet, so the last completed epoch is reported,
+        // even though no query was executed during it.
+        let empty = builder
+            .get_query_stats_payload(Height::from(20), &context(19), &[], NumBytes::new(1 << 20))
+            .unwrap();
+        assert_eq!(empty, payload(1, 0));
+
+        let payload = builder
+            .get_query_stats_payload(
+                Height::from(21),
+                &context(20),
+                &[&empty],
+                NumBytes::new(1 << 20),
+            )
+            .unwrap();
+        assert_eq!(payload.proposer, node_test_id(1));
+        assert_eq!(payload.epoch, QueryStatsEpoch::from(1));
+        assert_eq!(payload.stats.len(), 1);
+        assert_eq!(
+            builder
+                .validate_query_stats_payload(
+                    Height::from(21),
+                    node_test_id(1),
+                    &payload,
+                    &context(20),
+                    &[&empty]
+                )
+                .unwrap(),
+            NumBytes::new(payload.count_bytes() as u64)
+        );
+
+        // Not included again while it is in a past payload.
+        assert_eq!(
+            builder.get_query_stats_payload(
+                Height::from(22),
+                &context(20),
+                &[&payload, &empty],
+                NumBytes::new(1 << 20)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn payload_builder_skips_aggregated_epochs() {
+        let collector = Arc::new(QueryStatsCollector::new(EPOCH_LENGTH));
+        // Nodes 2, 3 and 4 delivered epoch 3, so it was aggregated.
+        let builder = payload_builder(collector, &[payload(2, 3), payload(3, 3), payload(4, 3)]);
+
+        assert_eq!(
+            builder.get_query_stats_payload(
+                Height::from(1),
+                &context(60),
+                &[],
+                NumBytes::new(1 << 20)
+            ),
+            Some(payload(1, 4))
+        );
+    }
+
+    #[test]
+    fn payload_builder_drops_stats_that_do_not_fit() {
+        let collector = Arc::new(QueryStatsCollector::new(EPOCH_LENGTH));
+        collector.set_epoch_from_height(Height::from(12));
+        for canister in 0..10 {
+            collector.register_query_statistics(canister_test_id(canister), &stats(1));
+        }
+        let builder = payload_builder(Arc::clone(&collector), &[payload(1, 0)]);
+
+        let stats_size = CanisterQueryStats {
+            canister_id: canister_test_id(0),
+            stats: stats(1),
+        }
+        .count_bytes() as u64;
+        let byte_limit = payload(1, 1).count_bytes() as u64 + 3 * stats_size + 1;
+        let payload = builder
+            .get_query_stats_payload(
+                Height::from(21),
+                &context(20),
+                &[],
+                NumBytes::new(byte_limit),
+            )
+            .unwrap();
+        assert_eq!(payload.stats.len(), 3);
+        assert!(payload.count_bytes() as u64 <= byte_limit);
+    }
+
+    #[test]
+    fn payload_validation_rejects_invalid_payloads() {
+        let collector = Arc::new(QueryStatsCollector::new(EPOCH_LENGTH));
+        let builder = payload_builder(collector, &[payload(2, 3)]);
+
+        assert!(matches!(
+            builder.validate_query_stats_payload(
+                Height::from(1),
+                node_test_id(2),
+                &payload(1, 4),
+                &context(60),
+                &[]
+            ),
+            Err(ValidationError::Permanent(
+                InvalidQueryStatsPayload::InvalidProposer { .. }
+            ))
+        ));
+        assert!(matches!(
+            builder.validate_query_stats_payload(
+                Height::from(1),
+                node_test_id(1),
+                &payload(1, 5),
+                &context(50),
+                &[]
+            ),
+            Err(ValidationError::Permanent(
+                InvalidQueryStatsPayload::EpochNotCompleted { .. }
+            ))
+        ));
+        assert!(matches!(
+            builder.validate_query_stats_payload(
+                Height::from(1),
+                node_test_id(2),
+                &payload(2, 3),
+                &context(60),
+                &[]
+            ),
+            Err(ValidationError::Permanent(
+                InvalidQueryStatsPayload::AlreadyDelivered { .. }
+            ))
+        ));
+        assert!(matches!(
+            builder.validate_query_stats_payload(
+                Height::from(1),
+                node_test_id(1),
+                &payload(1, 4),
+                &context(60),
+                &[&payload(1, 4)]
+            ),
+            Err(ValidationError::Permanent(
+                InvalidQueryStatsPayload::AlreadyDelivered { .. }
+            ))
+        ));
+        assert!(builder
+            .validate_query_stats_payload(
+                Height::from(1),
+                node_test_id(2),
+                &payload(2, 4),
+                &context(60),
+                &[]
+            )
+            .is_ok());
+
+        let unsorted = QueryStatsPayload {
+            stats: vec![
+                CanisterQueryStats {
+                    canister_id: canister_test_id(2),
+                    stats: stats(1),
+                },
+                CanisterQueryStats {
+                    canister_id: canister_test_id(1),
+                    stats: stats(1),
+                },
+            ],
+            ..payload(1, 4)
+        };
+        assert!(matches!(
+            builder.validate_query_stats_payload(
+                Height::from(1),
+                node_test_id(1),
+                &unsorted,
+                &context(60),
+                &[]
+            ),
+            Err(ValidationError::Permanent(
+                InvalidQueryStatsPayload::DuplicateOrUnsortedCanisterId(_)
+            ))
+        ));
+    }
+}
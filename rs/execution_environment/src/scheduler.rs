@@ -1936,7 +1936,9 @@ fn can_execute_msg(
         };
 
         // Only one install code message allowed at a time.
-        if let Some(Ic00Method::InstallCode) = maybe_instal_code_method {
+        if let Some(Ic00Method::InstallCode | Ic00Method::InstallChunkedCode) =
+            maybe_instal_code_method
+        {
             return false;
         }
     }
@@ -1992,8 +1994,11 @@ fn get_instructions_limits_for_subnet_message(
             | BitcoinGetCurrentFeePercentiles
             | BitcoinGetSuccessors
             | ProvisionalCreateCanisterWithCycles
+            | UploadChunk
+            | ClearChunkStore
+            | CanisterInfo
             | ProvisionalTopUpCanister => default_limits,
-            InstallCode => InstructionLimits::new(
+            InstallCode | InstallChunkedCode => InstructionLimits::new(
                 dts,
                 config.max_instructions_per_install_code,
                 config.max_instructions_per_install_code_slice,
@@ -2033,6 +2038,10 @@ fn is_bitcoin_request(msg: &CanisterInputMessage) -> bool {
                 | UpdateSettings
                 | ProvisionalCreateCanisterWithCycles
                 | ProvisionalTopUpCanister
+                | UploadChunk
+                | ClearChunkStore
+                | CanisterInfo
+                | InstallChunkedCode
                 | InstallCode => false,
             },
             Err(_) => false,
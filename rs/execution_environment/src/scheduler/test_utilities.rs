@@ -48,7 +48,9 @@ use ic_test_utilities::{
 };
 use ic_types::{
     ingress::{IngressState, IngressStatus},
-    messages::{CallContextId, Ingress, MessageId, Request, RequestOrResponse, Response},
+    messages::{
+        CallContextId, Ingress, MessageId, Request, RequestOrResponse, Response, NO_DEADLINE,
+    },
     methods::{Callback, FuncRef, SystemMethod, WasmClosure, WasmMethod},
     CanisterTimer, ComputeAllocation, Cycles, ExecutionRound, MemoryAllocation, NumInstructions,
     Randomness, Time, UserId,
@@ -334,6 +336,7 @@ impl SchedulerTest {
             compute_allocation: None,
             memory_allocation: None,
             query_allocation: None,
+            wasm_module_hash: None,
         };
 
         let caller = self.xnet_canister_id();
@@ -679,6 +682,8 @@ impl SchedulerTestBuilder {
             deterministic_time_slicing,
             config.cost_to_compile_wasm_instruction,
             SchedulerConfig::application_subnet().dirty_page_overhead,
+            config.subnet_memory_threshold,
+            config.subnet_memory_capacity,
         );
         let hypervisor = Arc::new(hypervisor);
         let ingress_history_writer =
@@ -1092,6 +1097,7 @@ impl TestWasmExecutorCore {
                 on_reply: closure.clone(),
                 on_reject: closure,
                 on_cleanup: None,
+                deadline: NO_DEADLINE,
             })
             .map_err(|err| err.to_string())?;
         let request = Request {
@@ -1101,6 +1107,7 @@ impl TestWasmExecutorCore {
             payment: Cycles::zero(),
             method_name: "update".into(),
             method_payload: encode_message_id_as_payload(call_message_id),
+            deadline: NO_DEADLINE,
         };
         if let Err(req) = system_state.push_output_request(
             canister_current_memory_usage,
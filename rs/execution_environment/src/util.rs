@@ -98,6 +98,7 @@ pub fn process_stopping_canisters(
                             originator_reply_callback: reply_callback,
                             refund: cycles,
                             response_payload: Payload::Data(EmptyBlob.encode()),
+                            deadline: ic_types::messages::NO_DEADLINE,
                         };
                         state.push_subnet_output_response(response.into());
                     }
@@ -1,5 +1,6 @@
 use ic_config::{
     execution_environment::Config as HypervisorConfig,
+    flag_status::FlagStatus,
     subnet_config::{CyclesAccountManagerConfig, SubnetConfigs},
 };
 use ic_registry_subnet_type::SubnetType;
@@ -321,6 +322,9 @@ fn test_canister_out_of_cycles() {
             compute_allocation: Some(candid::Nat::from(1)),
             memory_allocation: None,
             freezing_threshold: None,
+            ingress_priority_fee: None,
+            wasm_memory_limit: None,
+            reserved_cycles_limit: None,
         }),
     );
 
@@ -384,6 +388,9 @@ fn canister_has_zero_balance_when_uninstalled_due_to_low_cycles() {
                 compute_allocation: Some(candid::Nat::from(1)),
                 memory_allocation: None,
                 freezing_threshold: None,
+                ingress_priority_fee: None,
+                wasm_memory_limit: None,
+                reserved_cycles_limit: None,
             }),
             INITIAL_CYCLES_BALANCE,
         )
@@ -574,6 +581,66 @@ fn test_state_machine_consumes_instructions() {
     );
 }
 
+#[test]
+fn profile_ingress_reports_instructions_per_function() {
+    let subnet_config = SubnetConfigs::default().own_subnet_config(SubnetType::Application);
+    let env = StateMachine::new_with_config(StateMachineConfig::new(
+        subnet_config,
+        HypervisorConfig {
+            instruction_profiling: FlagStatus::Enabled,
+            ..Default::default()
+        },
+    ));
+
+    let wasm = wabt::Wat2Wasm::new()
+        .write_debug_names(true)
+        .convert(
+            r#"
+            (module
+              (import "ic0" "msg_reply" (func $msg_reply))
+              (func $busy_loop (param $n i32)
+                (loop $loop
+                  (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                  (br_if $loop (local.get $n))))
+              (func $run
+                (call $busy_loop (i32.const 1000))
+                (call $msg_reply))
+              (memory 1)
+              (export "canister_update run" (func $run)))"#,
+        )
+        .expect("invalid WAT");
+    let canister_id = env
+        .install_canister(wasm.as_ref().to_vec(), vec![], None)
+        .unwrap();
+
+    let (result, profile) = env
+        .profile_ingress(PrincipalId::new_anonymous(), canister_id, "run", vec![])
+        .unwrap();
+    assert_eq!(result, WasmResult::Reply(vec![]));
+    assert_eq!(profile.method, "run");
+    assert_eq!(
+        profile.instructions_by_function.keys().collect::<Vec<_>>(),
+        vec!["busy_loop", "run"]
+    );
+    assert!(
+        profile.instructions_by_function["busy_loop"] > profile.instructions_by_function["run"]
+    );
+    assert!(env.instructions_consumed() >= profile.total_instructions() as f64);
+
+    let flat_profile = profile.to_flat_profile();
+    let lines: Vec<_> = flat_profile.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            format!(
+                "busy_loop {}",
+                profile.instructions_by_function["busy_loop"]
+            ),
+            format!("run {}", profile.instructions_by_function["run"]),
+        ]
+    );
+}
+
 #[test]
 fn test_set_stable_memory() {
     let env = StateMachine::new();
@@ -679,6 +746,9 @@ fn exceeding_memory_capacity_fails_when_memory_allocation_changes() {
                 compute_allocation: None,
                 memory_allocation: None,
                 freezing_threshold: None,
+                ingress_priority_fee: None,
+                wasm_memory_limit: None,
+                reserved_cycles_limit: None,
             }),
             INITIAL_CYCLES_BALANCE,
         )
@@ -694,6 +764,9 @@ fn exceeding_memory_capacity_fails_when_memory_allocation_changes() {
                 compute_allocation: None,
                 memory_allocation: Some(candid::Nat::from(20u64 * 1024 * 1024 + 1)),
                 freezing_threshold: None,
+                ingress_priority_fee: None,
+                wasm_memory_limit: None,
+                reserved_cycles_limit: None,
             },
         )
         .unwrap_err();
@@ -708,6 +781,9 @@ fn exceeding_memory_capacity_fails_when_memory_allocation_changes() {
             compute_allocation: None,
             memory_allocation: Some(candid::Nat::from(20u64 * 1024 * 1024)),
             freezing_threshold: None,
+            ingress_priority_fee: None,
+            wasm_memory_limit: None,
+            reserved_cycles_limit: None,
         },
     )
     .unwrap();
@@ -756,6 +832,9 @@ fn exceeding_memory_capacity_fails_during_message_execution() {
                 compute_allocation: None,
                 memory_allocation: None,
                 freezing_threshold: None,
+                ingress_priority_fee: None,
+                wasm_memory_limit: None,
+                reserved_cycles_limit: None,
             }),
             INITIAL_CYCLES_BALANCE,
         )
@@ -821,6 +900,9 @@ fn max_canister_memory_respected_even_when_no_memory_allocation_is_set() {
                 compute_allocation: None,
                 memory_allocation: None,
                 freezing_threshold: None,
+                ingress_priority_fee: None,
+                wasm_memory_limit: None,
+                reserved_cycles_limit: None,
             }),
             INITIAL_CYCLES_BALANCE,
         )
@@ -1,17 +1,20 @@
-//! Module that deals with requests to /api/v2/canister/.../call
+//! Module that deals with requests to /api/v2/canister/.../call and
+//! /api/v3/canister/.../call
 
 use crate::{
     body::BodyReceiverLayer,
     common::{
-        get_cors_headers, make_plaintext_response, make_response, map_box_error_to_response,
-        remove_effective_canister_id,
+        cbor_response, get_cors_headers, into_cbor, make_plaintext_response, make_response,
+        map_box_error_to_response, remove_effective_canister_id,
     },
+    state_reader_executor::StateReaderExecutor,
     types::ApiReqType,
     validator_executor::ValidatorExecutor,
     EndpointService, HttpError, HttpHandlerMetrics, IngressFilterService, UNKNOWN_LABEL,
 };
 use http::Request;
 use hyper::{Body, Response, StatusCode};
+use ic_crypto_tree_hash::{sparse_labeled_tree_from_paths, Label, Path};
 use ic_interfaces_p2p::{IngressError, IngressIngestionService};
 use ic_interfaces_registry::RegistryClient;
 use ic_logger::{error, info_sample, warn, ReplicaLogger};
@@ -21,17 +24,161 @@ use ic_registry_client_helpers::{
 };
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_types::{
+    ingress::{IngressState, IngressStatus, WasmResult},
     malicious_flags::MaliciousFlags,
-    messages::{SignedIngress, SignedRequestBytes},
-    CanisterId, CountBytes, RegistryVersion, SubnetId,
+    messages::{
+        Blob, Certificate, CertificateDelegation, HttpCallResponse, MessageId, SignedIngress,
+        SignedRequestBytes,
+    },
+    CanisterId, CountBytes, Height, RegistryVersion, SubnetId,
 };
 use std::convert::{Infallible, TryInto};
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::{
+    sync::{watch, Semaphore},
+    time::{interval, timeout, MissedTickBehavior},
+};
 use tower::{load_shed::LoadShed, util::BoxCloneService, Service, ServiceBuilder, ServiceExt};
 
+/// How long a synchronous call waits for its message to reach a terminal
+/// status before falling back to a `202 Accepted` response.
+const SYNC_CALL_TIMEOUT: Duration = Duration::from_secs(10);
+/// The maximum number of synchronous calls that wait for their message at the
+/// same time. Further calls get a `202 Accepted` response right away.
+const MAX_SYNC_CALL_WAITERS: usize = 1_000;
+/// How often the latest certified height is checked for changes. This is a
+/// single atomic load shared by all waiting calls, which are only woken up
+/// once the certified height changes.
+const CERTIFIED_HEIGHT_CHECK_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Watches the certified state for submitted messages, so that synchronous
+/// calls can return the certified status of a message instead of requiring
+/// the client to poll `read_state`.
+#[derive(Clone)]
+pub(crate) struct IngressWatcher {
+    state_reader_executor: StateReaderExecutor,
+    delegation_from_nns: Arc<RwLock<Option<CertificateDelegation>>>,
+    certified_height: watch::Receiver<Height>,
+    waiters: Arc<Semaphore>,
+}
+
+impl IngressWatcher {
+    /// Creates a new watcher and spawns the task that notifies it about new
+    /// certified heights. Must be called from within a tokio runtime.
+    pub(crate) fn new(
+        state_reader_executor: StateReaderExecutor,
+        delegation_from_nns: Arc<RwLock<Option<CertificateDelegation>>>,
+    ) -> Self {
+        let (certified_height_sender, certified_height) =
+            watch::channel(state_reader_executor.latest_certified_height());
+        let executor = state_reader_executor.clone();
+        tokio::spawn(async move {
+            let mut interval = interval(CERTIFIED_HEIGHT_CHECK_INTERVAL);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            loop {
+                interval.tick().await;
+                let height = executor.latest_certified_height();
+                if *certified_height_sender.borrow() != height
+                    && certified_height_sender.send(height).is_err()
+                {
+                    // All watchers are gone.
+                    break;
+                }
+            }
+        });
+        Self {
+            state_reader_executor,
+            delegation_from_nns,
+            certified_height,
+            waiters: Arc::new(Semaphore::new(MAX_SYNC_CALL_WAITERS)),
+        }
+    }
+
+    /// Waits up to `SYNC_CALL_TIMEOUT` for the message to reach a terminal
+    /// status and returns a response carrying the certified `request_status`
+    /// of the message. The certified state is only read, on the state reader
+    /// executor, when a new height gets certified. Returns `None` if too many
+    /// calls are already waiting, if the message did not reach a terminal
+    /// status in time, or if its reply was already pruned.
+    async fn wait_for_certified_reply(&self, message_id: &MessageId) -> Option<Response<Body>> {
+        let _permit = Arc::clone(&self.waiters).try_acquire_owned().ok()?;
+        let mut certified_height = self.certified_height.clone();
+        let mut paths = vec![
+            Path::new(vec![
+                Label::from("request_status"),
+                Label::from(message_id.as_bytes()),
+            ]),
+            Path::from(Label::from("time")),
+        ];
+        let labeled_tree = sparse_labeled_tree_from_paths(&mut paths);
+
+        let (state_tag, tree, certification) = timeout(SYNC_CALL_TIMEOUT, async {
+            loop {
+                // A message is never certified right when it is submitted, so
+                // wait for the next certified height before reading.
+                certified_height.changed().await.ok()?;
+                if let Ok(Some((state, tree, certification))) = self
+                    .state_reader_executor
+                    .read_certified_state(&labeled_tree)
+                    .await
+                {
+                    if let Some(state_tag) = terminal_state(&state.get_ingress_status(message_id)) {
+                        return Some((state_tag, tree, certification));
+                    }
+                }
+            }
+        })
+        .await
+        .ok()??;
+
+        let delegation_from_nns = self.delegation_from_nns.read().unwrap().clone();
+        let signature = certification.signed.signature.signature.get().0;
+        let certificate = Blob(into_cbor(&Certificate {
+            tree,
+            signature: Blob(signature),
+            delegation: delegation_from_nns,
+        }));
+        match state_tag {
+            TerminalState::Replied => {
+                Some(cbor_response(&HttpCallResponse::Replied { certificate }))
+            }
+            TerminalState::Rejected => {
+                Some(cbor_response(&HttpCallResponse::Rejected { certificate }))
+            }
+            // The reply is no longer available, the client has to learn the
+            // status through `read_state`.
+            TerminalState::Done => None,
+        }
+    }
+}
+
+/// The terminal states of an ingress message, as far as the response to a
+/// synchronous call is concerned.
+#[derive(Debug, PartialEq, Eq)]
+enum TerminalState {
+    Replied,
+    Rejected,
+    Done,
+}
+
+fn terminal_state(status: &IngressStatus) -> Option<TerminalState> {
+    match status {
+        IngressStatus::Known { state, .. } => match state {
+            IngressState::Completed(WasmResult::Reply(_)) => Some(TerminalState::Replied),
+            IngressState::Completed(WasmResult::Reject(_)) | IngressState::Failed(_) => {
+                Some(TerminalState::Rejected)
+            }
+            IngressState::Done => Some(TerminalState::Done),
+            IngressState::Received | IngressState::Processing => None,
+        },
+        IngressStatus::Unknown => None,
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct CallService {
     log: ReplicaLogger,
@@ -42,6 +189,7 @@ pub(crate) struct CallService {
     ingress_sender: IngressIngestionService,
     ingress_filter: LoadShed<IngressFilterService>,
     malicious_flags: MaliciousFlags,
+    ingress_watcher: Option<IngressWatcher>,
 }
 
 impl CallService {
@@ -55,6 +203,7 @@ impl CallService {
         ingress_sender: IngressIngestionService,
         ingress_filter: IngressFilterService,
         malicious_flags: MaliciousFlags,
+        ingress_watcher: Option<IngressWatcher>,
     ) -> EndpointService {
         let base_service = BoxCloneService::new(ServiceBuilder::new().service(Self {
             log,
@@ -65,6 +214,7 @@ impl CallService {
             ingress_sender,
             ingress_filter: ServiceBuilder::new().load_shed().service(ingress_filter),
             malicious_flags,
+            ingress_watcher,
         }));
         BoxCloneService::new(
             ServiceBuilder::new()
@@ -122,7 +272,8 @@ fn get_registry_data(
     Ok((settings, provisional_whitelist))
 }
 
-/// Handles a call to /api/v2/canister/../call
+/// Handles a call to /api/v2/canister/../call, and to /api/v3/canister/../call
+/// if the service was created with an `IngressWatcher`.
 impl Service<Request<Vec<u8>>> for CallService {
     type Response = Response<Body>;
     type Error = Infallible;
@@ -231,6 +382,7 @@ impl Service<Request<Vec<u8>>> for CallService {
         let log = self.log.clone();
         let validator_executor = self.validator_executor.clone();
         let malicious_flags = self.malicious_flags.clone();
+        let ingress_watcher = self.ingress_watcher.clone();
 
         Box::pin(async move {
             if let Err(http_err) = validator_executor
@@ -273,7 +425,13 @@ impl Service<Request<Vec<u8>>> for CallService {
                         "ingress_message_submit";
                         ingress_message => ingress_log_entry
                     );
-                    make_accepted_response()
+                    match ingress_watcher {
+                        Some(ingress_watcher) => ingress_watcher
+                            .wait_for_certified_reply(&message_id)
+                            .await
+                            .unwrap_or_else(make_accepted_response),
+                        None => make_accepted_response(),
+                    }
                 }
             };
             Ok(response)
@@ -291,8 +449,13 @@ fn make_accepted_response() -> Response<Body> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use ic_error_types::{ErrorCode, UserError};
+    use ic_test_utilities::{
+        mock_time,
+        types::ids::{canister_test_id, user_test_id},
+    };
     use ic_types::{
-        messages::{Blob, HttpCallContent, HttpCanisterUpdate, HttpRequestEnvelope},
+        messages::{HttpCallContent, HttpCanisterUpdate, HttpRequestEnvelope},
         time::current_time_and_expiry_time,
     };
     use std::convert::TryFrom;
@@ -338,4 +501,38 @@ mod test {
         let message_id_2 = SignedIngress::try_from(request2).unwrap().id();
         assert_eq!(message_id_2, message_id);
     }
+
+    #[test]
+    fn check_terminal_statuses() {
+        let status = |state| IngressStatus::Known {
+            receiver: canister_test_id(1).get(),
+            user_id: user_test_id(1),
+            time: mock_time(),
+            state,
+        };
+        assert_eq!(terminal_state(&IngressStatus::Unknown), None);
+        assert_eq!(terminal_state(&status(IngressState::Received)), None);
+        assert_eq!(terminal_state(&status(IngressState::Processing)), None);
+        assert_eq!(
+            terminal_state(&status(IngressState::Completed(WasmResult::Reply(vec![])))),
+            Some(TerminalState::Replied)
+        );
+        assert_eq!(
+            terminal_state(&status(IngressState::Completed(WasmResult::Reject(
+                String::new()
+            )))),
+            Some(TerminalState::Rejected)
+        );
+        assert_eq!(
+            terminal_state(&status(IngressState::Failed(UserError::new(
+                ErrorCode::CanisterNotFound,
+                ""
+            )))),
+            Some(TerminalState::Rejected)
+        );
+        assert_eq!(
+            terminal_state(&status(IngressState::Done)),
+            Some(TerminalState::Done)
+        );
+    }
 }
@@ -23,6 +23,7 @@ use tower::{load_shed::error::Overloaded, BoxError};
 pub const CONTENT_TYPE_HTML: &str = "text/html";
 pub const CONTENT_TYPE_CBOR: &str = "application/cbor";
 pub const CONTENT_TYPE_PROTOBUF: &str = "application/x-protobuf";
+pub const CONTENT_TYPE_JSON: &str = "application/json";
 
 pub(crate) fn poll_ready(r: Poll<Result<(), Infallible>>) -> Poll<Result<(), BoxError>> {
     match r {
@@ -117,6 +118,10 @@ pub(crate) fn make_response(user_error: UserError) -> Response<Body> {
         C::QueryCallGraphTooDeep => StatusCode::INTERNAL_SERVER_ERROR,
         C::QueryCallGraphTotalInstructionLimitExceeded => StatusCode::INTERNAL_SERVER_ERROR,
         C::CompositeQueryCalledInReplicatedMode => StatusCode::INTERNAL_SERVER_ERROR,
+        C::CanisterWasmMemoryLimitExceeded => StatusCode::INTERNAL_SERVER_ERROR,
+        C::InsufficientCyclesInMemoryGrow => StatusCode::SERVICE_UNAVAILABLE,
+        C::ReservedCyclesLimitExceededInMemoryGrow => StatusCode::SERVICE_UNAVAILABLE,
+        C::ReservedCyclesLimitIsTooLow => StatusCode::BAD_REQUEST,
         C::CanisterNotHostedBySubnet => StatusCode::NOT_FOUND,
     };
     make_plaintext_response(status, user_error.description().to_string())
@@ -0,0 +1,89 @@
+//! Module that serves the per-height consensus timeline at
+//! `/_/consensus_timeline`, which shows when this replica observed the
+//! completion of each consensus phase for the most recent heights.
+
+use crate::common::{get_cors_headers, make_plaintext_response, CONTENT_TYPE_JSON};
+use http::{header, request::Parts};
+use hyper::{Body, Response, StatusCode};
+use ic_interfaces::consensus::ConsensusTimelineReader;
+use std::collections::HashMap;
+
+/// Number of heights returned if the `heights` query parameter is not given.
+const DEFAULT_HEIGHTS: usize = 100;
+
+/// Returns the timelines of the last `heights` heights as a JSON array, in
+/// ascending order of height. All timestamps are in nanoseconds since the
+/// UNIX epoch.
+pub(crate) fn timeline(parts: Parts, reader: &dyn ConsensusTimelineReader) -> Response<Body> {
+    let heights = match query(parts) {
+        Ok(heights) => heights,
+        Err(err) => return make_plaintext_response(StatusCode::BAD_REQUEST, err),
+    };
+    match serde_json::to_vec(&reader.get_latest_timelines(heights)) {
+        Ok(body) => {
+            let mut response = Response::new(Body::from(body));
+            *response.status_mut() = StatusCode::OK;
+            *response.headers_mut() = get_cors_headers();
+            response.headers_mut().insert(
+                header::CONTENT_TYPE,
+                header::HeaderValue::from_static(CONTENT_TYPE_JSON),
+            );
+            response
+        }
+        Err(err) => make_plaintext_response(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
+    }
+}
+
+fn query(parts: Parts) -> Result<usize, String> {
+    let query_pairs: HashMap<_, _> = match parts.uri.query() {
+        Some(query) => url::form_urlencoded::parse(query.as_bytes()).collect(),
+        None => Default::default(),
+    };
+    match query_pairs.get("heights") {
+        Some(val) => val.parse().map_err(|err| format!("{}", err)),
+        None => Ok(DEFAULT_HEIGHTS),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::Request;
+    use ic_interfaces::consensus::HeightTimeline;
+    use ic_types::Height;
+
+    struct FakeTimelineReader;
+
+    impl ConsensusTimelineReader for FakeTimelineReader {
+        fn get_latest_timelines(&self, n: usize) -> Vec<HeightTimeline> {
+            (1..=n as u64)
+                .map(|h| HeightTimeline {
+                    height: Height::from(h),
+                    ..HeightTimeline::default()
+                })
+                .collect()
+        }
+    }
+
+    fn get(uri: &str) -> Response<Body> {
+        let (parts, _) = Request::get(uri).body(()).unwrap().into_parts();
+        timeline(parts, &FakeTimelineReader)
+    }
+
+    #[tokio::test]
+    async fn test_timeline_returns_requested_number_of_heights() {
+        let response = get("/_/consensus_timeline?heights=3");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let timelines: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(timelines.len(), 3);
+        assert_eq!(timelines[2]["height"], 3);
+        assert!(timelines[2]["finalization"].is_null());
+    }
+
+    #[test]
+    fn test_timeline_rejects_invalid_heights() {
+        let response = get("/_/consensus_timeline?heights=abc");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
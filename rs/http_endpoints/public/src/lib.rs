@@ -9,6 +9,7 @@ mod body;
 mod call;
 mod catch_up_package;
 mod common;
+mod consensus_timeline;
 mod dashboard;
 mod health_status_refresher;
 mod metrics;
@@ -21,7 +22,7 @@ mod types;
 mod validator_executor;
 
 use crate::{
-    call::CallService,
+    call::{CallService, IngressWatcher},
     catch_up_package::CatchUpPackageService,
     common::{
         get_cors_headers, get_root_threshold_public_key, make_plaintext_response,
@@ -51,6 +52,7 @@ use ic_crypto_tls_interfaces::TlsHandshake;
 use ic_crypto_tree_hash::{lookup_path, LabeledTree, Path};
 use ic_crypto_utils_threshold_sig_der::parse_threshold_sig_key_from_der;
 use ic_interfaces::{
+    consensus::ConsensusTimelineReader,
     consensus_pool::ConsensusPoolCache,
     crypto::IngressSigVerifier,
     execution_environment::{IngressFilterService, QueryExecutionService},
@@ -134,6 +136,7 @@ pub(crate) const MAX_REQUEST_SIZE_BYTES: Byte = Byte::from_bytes(5 * 1024 * 1024
 pub(crate) const MAX_REQUEST_RECEIVE_DURATION: Duration = Duration::from_secs(300); // 5 min
 
 const HTTP_DASHBOARD_URL_PATH: &str = "/_/dashboard";
+const HTTP_CONSENSUS_TIMELINE_URL_PATH: &str = "/_/consensus_timeline";
 const CONTENT_TYPE_CBOR: &str = "application/cbor";
 
 // Placeholder used when we can't determine the approriate prometheus label.
@@ -152,12 +155,14 @@ pub(crate) type EndpointService = BoxCloneService<Request<Body>, Response<Body>,
 #[derive(Clone)]
 struct HttpHandler {
     call_service: EndpointService,
+    sync_call_service: EndpointService,
     query_service: EndpointService,
     catchup_service: EndpointService,
     dashboard_service: EndpointService,
     status_service: EndpointService,
     read_state_service: EndpointService,
     health_status_refresher: HealthStatusRefreshLayer,
+    consensus_timeline_reader: Arc<dyn ConsensusTimelineReader>,
 }
 
 // Crates a detached tokio blocking task that initializes the server (reading
@@ -275,6 +280,7 @@ pub fn start_server(
     nns_subnet_id: SubnetId,
     log: ReplicaLogger,
     consensus_pool_cache: Arc<dyn ConsensusPoolCache>,
+    consensus_timeline_reader: Arc<dyn ConsensusTimelineReader>,
     subnet_type: SubnetType,
     malicious_flags: MaliciousFlags,
 ) {
@@ -299,6 +305,17 @@ pub fn start_server(
     let state_reader_executor = StateReaderExecutor::new(state_reader);
     let validator_executor = ValidatorExecutor::new(ingress_verifier, log.clone());
     let call_service = CallService::new_service(
+        log.clone(),
+        metrics.clone(),
+        subnet_id,
+        Arc::clone(&registry_client),
+        validator_executor.clone(),
+        ingress_sender.clone(),
+        ingress_filter.clone(),
+        malicious_flags.clone(),
+        None,
+    );
+    let sync_call_service = CallService::new_service(
         log.clone(),
         metrics.clone(),
         subnet_id,
@@ -307,6 +324,10 @@ pub fn start_server(
         ingress_sender,
         ingress_filter,
         malicious_flags.clone(),
+        Some(IngressWatcher::new(
+            state_reader_executor.clone(),
+            Arc::clone(&delegation_from_nns),
+        )),
     );
     let query_service = QueryService::new_service(
         log.clone(),
@@ -361,12 +382,14 @@ pub fn start_server(
 
     let http_handler = HttpHandler {
         call_service,
+        sync_call_service,
         query_service,
         status_service,
         catchup_service,
         dashboard_service,
         read_state_service,
         health_status_refresher,
+        consensus_timeline_reader,
     };
     let main_service = create_main_service(metrics.clone(), http_handler);
 
@@ -565,11 +588,13 @@ async fn make_router(
     (mut req, mut timer): RequestWithTimer,
 ) -> ResponseWithTimer {
     let call_service = http_handler.call_service.clone();
+    let sync_call_service = http_handler.sync_call_service.clone();
     let query_service = http_handler.query_service.clone();
     let status_service = http_handler.status_service.clone();
     let catch_up_package_service = http_handler.catchup_service.clone();
     let dashboard_service = http_handler.dashboard_service.clone();
     let read_state_service = http_handler.read_state_service.clone();
+    let consensus_timeline_reader = http_handler.consensus_timeline_reader.clone();
 
     let svc = match req.method().clone() {
         Method::POST => {
@@ -603,6 +628,10 @@ async fn make_router(
                         timer.set_label(LABEL_REQUEST_TYPE, ApiReqType::Call.into());
                         (call_service, Some(effective_canister_id))
                     }
+                    ["", "api", "v3", "canister", effective_canister_id, "call"] => {
+                        timer.set_label(LABEL_REQUEST_TYPE, ApiReqType::SyncCall.into());
+                        (sync_call_service, Some(effective_canister_id))
+                    }
                     ["", "api", "v2", "canister", effective_canister_id, "query"] => {
                         timer.set_label(LABEL_REQUEST_TYPE, ApiReqType::Query.into());
                         (query_service, Some(effective_canister_id))
@@ -662,6 +691,16 @@ async fn make_router(
                 timer.set_label(LABEL_REQUEST_TYPE, ApiReqType::Dashboard.into());
                 dashboard_service
             }
+            HTTP_CONSENSUS_TIMELINE_URL_PATH => {
+                timer.set_label(LABEL_REQUEST_TYPE, ApiReqType::ConsensusTimeline.into());
+                return (
+                    consensus_timeline::timeline(
+                        req.into_parts().0,
+                        consensus_timeline_reader.as_ref(),
+                    ),
+                    timer,
+                );
+            }
             "/_/pprof" => {
                 timer.set_label(LABEL_REQUEST_TYPE, ApiReqType::PprofHome.into());
                 return (pprof::home(), timer);
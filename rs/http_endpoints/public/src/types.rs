@@ -10,6 +10,8 @@ use strum::IntoStaticStr;
 pub(crate) enum ApiReqType {
     /// `call`
     Call,
+    /// `call` that waits for the certified reply
+    SyncCall,
     /// `query`
     Query,
     /// `read_state`
@@ -19,6 +21,7 @@ pub(crate) enum ApiReqType {
     Status,
     Dashboard,
     RedirectToDashboard,
+    ConsensusTimeline,
     Options,
     PprofHome,
     PprofProfile,
@@ -49,6 +52,7 @@ mod tests {
     fn test_label_values_do_not_change() {
         type StaticStr = &'static str;
         assert_eq!(StaticStr::from(ApiReqType::Call), "call");
+        assert_eq!(StaticStr::from(ApiReqType::SyncCall), "sync_call");
         assert_eq!(StaticStr::from(ApiReqType::Query), "query");
         assert_eq!(StaticStr::from(ApiReqType::ReadState), "read_state");
         assert_eq!(StaticStr::from(ApiReqType::Status), "status");
@@ -58,6 +62,10 @@ mod tests {
         );
         assert_eq!(StaticStr::from(ApiReqType::Options), "options");
         assert_eq!(StaticStr::from(ApiReqType::Dashboard), "dashboard");
+        assert_eq!(
+            StaticStr::from(ApiReqType::ConsensusTimeline),
+            "consensus_timeline"
+        );
         assert_eq!(
             StaticStr::from(ApiReqType::RedirectToDashboard),
             "redirect_to_dashboard"
@@ -1,6 +1,9 @@
 use ic_crypto_tree_hash::MixedHashTree;
 use ic_error_types::UserError;
-use ic_interfaces::execution_environment::{IngressFilterService, QueryExecutionService};
+use ic_interfaces::{
+    consensus::{ConsensusTimelineReader, HeightTimeline},
+    execution_environment::{IngressFilterService, QueryExecutionService},
+};
 use ic_interfaces_p2p::{IngressError, IngressIngestionService};
 use ic_interfaces_registry_mocks::MockRegistryClient;
 use ic_interfaces_state_manager::Labeled;
@@ -225,6 +228,15 @@ pub(crate) fn basic_consensus_pool_cache() -> MockConsensusCache {
     mock_consensus_cache
 }
 
+// Consensus timeline reader that has not recorded any heights.
+pub(crate) struct EmptyConsensusTimelineReader;
+
+impl ConsensusTimelineReader for EmptyConsensusTimelineReader {
+    fn get_latest_timelines(&self, _n: usize) -> Vec<HeightTimeline> {
+        vec![]
+    }
+}
+
 // Basic registry client mock at version 1
 pub(crate) fn basic_registry_client() -> MockRegistryClient {
     let mut mock_registry_client = MockRegistryClient::new();
@@ -1,7 +1,8 @@
 use crate::common::{
     basic_consensus_pool_cache, basic_registry_client, basic_state_manager_mock,
     setup_ingress_filter_mock, setup_ingress_ingestion_mock, setup_query_execution_mock,
-    IngressFilterHandle, IngressIngestionHandle, QueryExecutionHandle,
+    EmptyConsensusTimelineReader, IngressFilterHandle, IngressIngestionHandle,
+    QueryExecutionHandle,
 };
 use hyper::{
     client::conn::{handshake, SendRequest},
@@ -40,7 +41,7 @@ use ic_test_utilities::{
     crypto::temp_crypto_component_with_fake_registry,
     mock_time,
     state::ReplicatedStateBuilder,
-    types::ids::{node_test_id, subnet_test_id},
+    types::ids::{canister_test_id, node_test_id, subnet_test_id, user_test_id},
 };
 use ic_types::{
     batch::{BatchPayload, ValidationContext},
@@ -56,17 +57,21 @@ use ic_types::{
         },
         CombinedThresholdSig, CombinedThresholdSigOf, CryptoHash, CryptoHashOf, Signed,
     },
+    ingress::{IngressState, IngressStatus, WasmResult},
     malicious_flags::MaliciousFlags,
-    messages::{Blob, HttpQueryResponse, HttpQueryResponseReply},
+    messages::{
+        Blob, HttpCallResponse, HttpQueryResponse, HttpQueryResponseReply, SignedIngress,
+        SignedRequestBytes,
+    },
     signature::ThresholdSignature,
-    CryptoHashOfPartialState, Height, RegistryVersion,
+    CryptoHashOfPartialState, Height, NumBytes, RegistryVersion,
 };
 use prost::Message;
 use std::{
     collections::BTreeMap,
     net::SocketAddr,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
         Arc,
     },
 };
@@ -150,6 +155,7 @@ fn start_http_endpoint(
         nns_subnet_id,
         no_op_logger(),
         consensus_cache,
+        Arc::new(EmptyConsensusTimelineReader),
         SubnetType::Application,
         MaliciousFlags::default(),
     );
@@ -583,6 +589,126 @@ fn test_unathorized_call() {
     });
 }
 
+/// A synchronous call to the `/api/v3` route returns the certified reply as
+/// soon as a certified state contains a terminal status for the message.
+#[test]
+fn test_sync_call_returns_certified_reply() {
+    let rt = Runtime::new().unwrap();
+    let addr = get_free_localhost_socket_addr();
+    let config = Config {
+        listen_addr: addr,
+        ..Default::default()
+    };
+
+    let agent = Agent::builder()
+        .with_identity(AnonymousIdentity)
+        .with_transport(ReqwestHttpReplicaV2Transport::create(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+    let canister = Principal::from_text("223xb-saaaa-aaaaf-arlqa-cai").unwrap();
+    let update = UpdateBuilder::new(&agent, canister, "test".to_string())
+        .with_effective_canister_id(canister)
+        .with_arg(Vec::new())
+        .sign()
+        .unwrap();
+    let message_id =
+        SignedIngress::try_from(SignedRequestBytes::from(update.signed_update.clone()))
+            .unwrap()
+            .id();
+
+    let mut mock_state_manager = MockStateManager::new();
+    mock_state_manager.expect_get_latest_state().returning(|| {
+        Labeled::new(
+            Height::from(1),
+            Arc::new(ReplicatedStateBuilder::new().build()),
+        )
+    });
+    // Every call observes a new certified height, so that waiting calls are
+    // woken up.
+    let certified_height = Arc::new(AtomicU64::new(1));
+    mock_state_manager
+        .expect_latest_certified_height()
+        .returning(move || Height::from(certified_height.fetch_add(1, Ordering::SeqCst)));
+    mock_state_manager
+        .expect_read_certified_state()
+        .returning(move |_labeled_tree| {
+            let mut state = ReplicatedStateBuilder::new().build();
+            state.set_ingress_status(
+                message_id.clone(),
+                IngressStatus::Known {
+                    receiver: canister_test_id(0).get(),
+                    user_id: user_test_id(0),
+                    time: mock_time(),
+                    state: IngressState::Completed(WasmResult::Reply(b"reply".to_vec())),
+                },
+                NumBytes::from(u64::MAX),
+            );
+            let cert = Certification {
+                height: Height::from(1),
+                signed: Signed {
+                    signature: ThresholdSignature {
+                        signer: NiDkgId {
+                            start_block_height: Height::from(0),
+                            dealer_subnet: subnet_test_id(0),
+                            dkg_tag: NiDkgTag::HighThreshold,
+                            target_subnet: NiDkgTargetSubnet::Local,
+                        },
+                        signature: CombinedThresholdSigOf::new(CombinedThresholdSig(vec![])),
+                    },
+                    content: CertificationContent::new(CryptoHashOfPartialState::from(CryptoHash(
+                        vec![],
+                    ))),
+                },
+            };
+            Some((Arc::new(state), MixedHashTree::Leaf(Vec::new()), cert))
+        });
+
+    let (mut ingress_filter, mut ingress_sender, _) = start_http_endpoint(
+        rt.handle().clone(),
+        config,
+        Arc::new(mock_state_manager),
+        Arc::new(basic_consensus_pool_cache()),
+        Arc::new(basic_registry_client()),
+    );
+
+    // Ingress sender mock that returns empty Ok(()) response.
+    rt.spawn(async move {
+        loop {
+            let (_, resp) = ingress_sender.next_request().await.unwrap();
+            resp.send_response(Ok(()))
+        }
+    });
+
+    // Ingress filter mock that returns empty Ok(()) response.
+    rt.spawn(async move {
+        loop {
+            let (_, resp) = ingress_filter.next_request().await.unwrap();
+            resp.send_response(Ok(()))
+        }
+    });
+
+    rt.block_on(async {
+        let client = hyper::Client::new();
+        loop {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri(format!("http://{}/api/v3/canister/{}/call", addr, canister))
+                .header("Content-Type", "application/cbor")
+                .body(Body::from(update.signed_update.clone()))
+                .unwrap();
+            let response = client.request(request).await.unwrap();
+            if response.status() == StatusCode::OK {
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                let response: HttpCallResponse = serde_cbor::from_slice(&body).unwrap();
+                assert!(matches!(response, HttpCallResponse::Replied { .. }));
+                break;
+            }
+            println!("Received unexpeceted response: {:?}", response);
+            sleep(Duration::from_millis(250)).await
+        }
+    });
+}
+
 /// Once we have reached the number of outstanding connection, new connections should be refused.
 #[tokio::test]
 async fn test_max_outstanding_connections() {
@@ -7,6 +7,10 @@
 //!
 //! We vary the pool size count between 15,000 and 105,000, with 10,000
 //! increments.
+//!
+//! In addition, we measure the round-robin selection across destination
+//! canisters by spreading a fixed number of messages over a varying number of
+//! canisters, with per-canister and per-sender caps in place.
 
 use criterion::{criterion_group, criterion_main, Criterion};
 use ic_artifact_pool::ingress_pool::IngressPoolImpl;
@@ -23,6 +27,7 @@ use ic_interfaces_registry::RegistryClient;
 use ic_interfaces_state_manager_mocks::MockStateManager;
 use ic_logger::replica_logger::no_op_logger;
 use ic_metrics::MetricsRegistry;
+use ic_protobuf::registry::subnet::v1::SubnetRecord;
 use ic_registry_client::client::RegistryClientImpl;
 use ic_registry_client_helpers::subnet::SubnetRegistry;
 use ic_registry_keys::make_subnet_record_key;
@@ -33,8 +38,8 @@ use ic_test_utilities::{
     crypto::temp_crypto_component_with_fake_registry,
     cycles_account_manager::CyclesAccountManagerBuilder,
     history::MockIngressHistory,
-    state::ReplicatedStateBuilder,
-    types::ids::{node_test_id, subnet_test_id},
+    state::{CanisterStateBuilder, ReplicatedStateBuilder},
+    types::ids::{canister_test_id, node_test_id, subnet_test_id},
     types::messages::SignedIngressBuilder,
     FastForwardTimeSource,
 };
@@ -44,7 +49,7 @@ use ic_types::{
     batch::ValidationContext,
     ingress::IngressStatus,
     malicious_flags::MaliciousFlags,
-    Height, NumBytes, RegistryVersion, SubnetId, Time,
+    CanisterId, Height, NumBytes, RegistryVersion, SubnetId, Time,
 };
 use rand::Rng;
 use std::{
@@ -52,8 +57,9 @@ use std::{
     sync::{Arc, RwLock},
 };
 
-/// Helper to run a single test with dependency setup.
-fn run_test<T>(_test_name: &str, test: T)
+/// Helper to run a single test with dependency setup. The replicated state
+/// contains canisters `0..num_canisters`.
+fn run_test<T>(_test_name: &str, subnet_record: SubnetRecord, num_canisters: u64, test: T)
 where
     T: FnOnce(
         Arc<FastForwardTimeSource>,
@@ -68,14 +74,19 @@ where
         .returning(|_| Ok(Box::new(|_| IngressStatus::Unknown)));
     let subnet_id = subnet_test_id(0);
     let runtime = tokio::runtime::Runtime::new().unwrap();
-    let registry = setup_registry(subnet_id, runtime.handle().clone());
+    let registry = setup_registry(subnet_id, subnet_record, runtime.handle().clone());
     let consensus_pool_cache = Arc::new(MockConsensusCache::new());
+    let mut state_builder = ReplicatedStateBuilder::default();
+    for i in 0..num_canisters {
+        state_builder = state_builder.with_canister(
+            CanisterStateBuilder::default()
+                .with_canister_id(canister_test_id(i))
+                .build(),
+        );
+    }
     let mut state_manager = MockStateManager::new();
     state_manager.expect_get_state_at().return_const(Ok(
-        ic_interfaces_state_manager::Labeled::new(
-            Height::new(0),
-            Arc::new(ReplicatedStateBuilder::default().build()),
-        ),
+        ic_interfaces_state_manager::Labeled::new(Height::new(0), Arc::new(state_builder.build())),
     ));
 
     with_test_pool_config(|pool_config| {
@@ -116,12 +127,16 @@ where
 /// be considered as valid according to expiry restrictions (not expired & not
 /// too far in the future).
 ///
+/// Messages are addressed to the canister returned by `receiver` for their
+/// index.
+///
 /// Return the mean of all expiry time.
 fn prepare(
     time_source: &dyn TimeSource,
     pool: Arc<RwLock<IngressPoolImpl>>,
     now: Time,
     num: usize,
+    receiver: impl Fn(usize) -> (CanisterId, &'static str),
 ) -> Time {
     let mut changeset = ChangeSet::new();
     let ingress_size = 1024;
@@ -132,12 +147,13 @@ fn prepare(
         let expiry = std::time::Duration::from_millis(
             rng.gen::<u64>() % (10 * (MAX_INGRESS_TTL.as_millis() as u64)),
         );
+        let (canister_id, method_name) = receiver(i);
         let ingress = SignedIngressBuilder::new()
-            .method_name("provisional_create_canister_with_cycles")
+            .method_name(method_name)
             .method_payload(vec![0; ingress_size])
             .nonce(i as u64)
             .expiry_time(now + expiry)
-            .canister_id(IC_00)
+            .canister_id(canister_id)
             .build();
         let message_id = IngressMessageId::from(&ingress);
         let attribute = IngressMessageAttribute::new(&ingress);
@@ -170,7 +186,12 @@ fn get_ingress_payload(now: Time, manager: &IngressManager, byte_limit: NumBytes
         certified_height: Height::from(0),
     };
     let past_payload = HashSet::new();
-    let payload = manager.get_ingress_payload(&past_payload, &validation_context, byte_limit);
+    let payload = manager.get_ingress_payload(
+        Height::from(1),
+        &past_payload,
+        &validation_context,
+        byte_limit,
+    );
     payload.message_count()
 }
 
@@ -183,12 +204,16 @@ fn build_payload(criterion: &mut Criterion) {
         let size = 5000 + 10000 * i;
         run_test(
             "get_ingress_payload",
+            test_subnet_record(),
+            0,
             |time_source: Arc<FastForwardTimeSource>,
              pool,
              manager: &mut IngressManager,
              registry| {
                 let now = time_source.get_relative_time();
-                let then = prepare(time_source.as_ref(), pool, now, size);
+                let then = prepare(time_source.as_ref(), pool, now, size, |_| {
+                    (IC_00, "provisional_create_canister_with_cycles")
+                });
                 time_source.set_time(then).unwrap();
                 let name = format!("get_ingress_payload({})", size);
                 let byte_limit = registry
@@ -210,10 +235,57 @@ fn build_payload(criterion: &mut Criterion) {
     group.finish()
 }
 
+/// Speed test for building ingress payloads with round-robin selection across
+/// a varying number of destination canisters.
+fn build_payload_round_robin(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("build_payload_round_robin");
+    group.sample_size(30);
+    group.measurement_time(std::time::Duration::from_secs(10));
+    let size = 25000;
+    for num_canisters in [1, 10, 100, 1000] {
+        let mut subnet_record = test_subnet_record();
+        subnet_record.max_ingress_messages_per_canister_per_block = 100;
+        subnet_record.max_ingress_messages_per_sender_per_block = 500;
+        run_test(
+            "get_ingress_payload_round_robin",
+            subnet_record,
+            num_canisters,
+            |time_source: Arc<FastForwardTimeSource>,
+             pool,
+             manager: &mut IngressManager,
+             registry| {
+                let now = time_source.get_relative_time();
+                let then = prepare(time_source.as_ref(), pool, now, size, |i| {
+                    (canister_test_id(i as u64 % num_canisters), "write")
+                });
+                time_source.set_time(then).unwrap();
+                let name = format!("get_ingress_payload_round_robin({})", num_canisters);
+                let byte_limit = registry
+                    .get_subnet_record(subnet_test_id(0), RegistryVersion::new(1))
+                    .unwrap()
+                    .unwrap()
+                    .max_block_payload_size;
+
+                group.bench_function(&name, |bench| {
+                    bench.iter(|| {
+                        let n = get_ingress_payload(then, manager, NumBytes::new(byte_limit));
+                        // All messages are sent by the anonymous user.
+                        assert!(n <= 500, "Too many ingress in payload: {}", n);
+                    })
+                });
+            },
+        );
+    }
+    group.finish()
+}
+
 /// Sets up a registry client.
-fn setup_registry(subnet_id: SubnetId, runtime: tokio::runtime::Handle) -> Arc<dyn RegistryClient> {
+fn setup_registry(
+    subnet_id: SubnetId,
+    subnet_record: SubnetRecord,
+    runtime: tokio::runtime::Handle,
+) -> Arc<dyn RegistryClient> {
     let registry_data_provider = Arc::new(ProtoRegistryDataProvider::new());
-    let subnet_record = test_subnet_record();
     registry_data_provider
         .add(
             &make_subnet_record_key(subnet_id),
@@ -229,6 +301,6 @@ fn setup_registry(subnet_id: SubnetId, runtime: tokio::runtime::Handle) -> Arc<d
     registry
 }
 
-criterion_group!(benches, build_payload);
+criterion_group!(benches, build_payload, build_payload_round_robin);
 
 criterion_main!(benches);
@@ -11,7 +11,7 @@ use ic_interfaces::{
         IngressPayloadValidationError, IngressPermanentError, IngressSelector, IngressSetQuery,
         IngressTransientError,
     },
-    ingress_pool::{IngressPoolSelect, SelectResult},
+    ingress_pool::{IngressPoolObject, IngressPoolSelect},
     validation::{ValidationError, ValidationResult},
 };
 use ic_interfaces_state_manager::StateManagerError;
@@ -24,14 +24,19 @@ use ic_types::{
     consensus::Payload,
     ingress::{IngressSets, IngressStatus},
     messages::{extract_effective_canister_id, MessageId, SignedIngress},
-    CanisterId, CountBytes, Cycles, Height, NumBytes, Time,
+    CanisterId, CountBytes, Cycles, Height, NumBytes, Time, UserId,
 };
 use ic_validator::{validate_request, RequestValidationError};
-use std::{collections::BTreeMap, sync::Arc};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+};
 
 impl IngressSelector for IngressManager {
     fn get_ingress_payload(
         &self,
+        height: Height,
         past_ingress: &dyn IngressSetQuery,
         context: &ValidationContext,
         byte_limit: NumBytes,
@@ -75,44 +80,125 @@ impl IngressSelector for IngressManager {
             .get_ingress_message_settings(context.registry_version)
             .expect("Couldn't fetch ingress message parameters from the registry.");
 
-        // Select valid ingress messages and stop once the total size
-        // becomes greater than byte_limit.
-        let mut accumulated_size = 0;
-        let mut cycles_needed: BTreeMap<CanisterId, Cycles> = BTreeMap::new();
-        let mut num_messages = 0;
-
-        let mut messages_in_payload = self.ingress_pool.select_validated(
+        let byte_limit_bytes = byte_limit.get() as usize;
+        let mut messages_in_payload = self.ingress_pool.select_validated_from_refs(
             expiry_range,
-            Box::new(move |ingress_obj| {
-                let result = self.validate_ingress(
-                    IngressMessageId::from(ingress_obj),
-                    &ingress_obj.signed_ingress,
-                    &state,
-                    context,
-                    &settings,
-                    &past_ingress_set,
-                    num_messages,
-                    &mut cycles_needed,
-                );
-                match result {
-                    Ok(()) => {
-                        num_messages += 1;
-                        // Calculate the size and abort once we have hit the limit
-                        accumulated_size += ingress_obj.signed_ingress.count_bytes();
-                        if accumulated_size > byte_limit.get() as usize {
-                            return SelectResult::Abort;
-                        }
+            Box::new(|ingress_objs: Vec<&IngressPoolObject>| {
+                // Group the candidate messages by destination canister,
+                // preserving the pool order within each group. Messages already
+                // included in past blocks are dropped right away. A canister
+                // cannot get more messages into the block than the per-canister
+                // cap, the per-block cap or the byte limit allow, so there is no
+                // need to keep more candidates than that.
+                let max_candidates_per_canister = settings
+                    .max_ingress_messages_per_canister_per_block
+                    .min(settings.max_ingress_messages_per_block);
+                let mut candidates: BTreeMap<CanisterId, (usize, VecDeque<&IngressPoolObject>)> =
+                    BTreeMap::new();
+                for ingress_obj in ingress_objs {
+                    let (candidate_bytes, queue) = candidates
+                        .entry(ingress_obj.signed_ingress.canister_id())
+                        .or_default();
+                    if queue.len() >= max_candidates_per_canister
+                        || *candidate_bytes >= byte_limit_bytes
+                        || past_ingress_set.contains(&IngressMessageId::from(ingress_obj))
+                    {
+                        continue;
+                    }
+                    *candidate_bytes += ingress_obj.signed_ingress.count_bytes();
+                    queue.push_back(ingress_obj);
+                }
+
+                // Canisters that pay a higher ingress priority fee are served
+                // first in every round. Among canisters paying the same fee, the
+                // canister that is served first rotates with the height of the
+                // block being built, so
+                // that no canister is favored by its id.
+                let mut queues: Vec<_> = candidates
+                    .into_iter()
+                    .map(|(canister_id, (_, queue))| {
+                        let priority_fee = state
+                            .canister_state(&canister_id)
+                            .map(|canister| {
+                                self.cycles_account_manager
+                                    .ingress_priority_fee(&canister.system_state)
+                            })
+                            .unwrap_or_else(Cycles::zero);
+                        (priority_fee, queue)
+                    })
+                    .collect();
+                queues.sort_by_key(|(priority_fee, _)| Reverse(*priority_fee));
+                let rotation = height.get() as usize;
+                let mut tier_start = 0;
+                while tier_start < queues.len() {
+                    let tier_fee = queues[tier_start].0;
+                    let tier_len = queues[tier_start..]
+                        .iter()
+                        .take_while(|(priority_fee, _)| *priority_fee == tier_fee)
+                        .count();
+                    queues[tier_start..tier_start + tier_len].rotate_left(rotation % tier_len);
+                    tier_start += tier_len;
+                }
 
-                        SelectResult::Selected(ingress_obj.signed_ingress.clone())
+                // Select valid ingress messages in a round-robin fashion across
+                // destination canisters, so that a single busy canister cannot
+                // crowd out the others, and stop once the payload reaches its
+                // message count limit or its total size becomes greater than
+                // byte_limit. Only the selected messages are copied out of the
+                // pool.
+                let mut accumulated_size = 0;
+                let mut cycles_needed: BTreeMap<CanisterId, Cycles> = BTreeMap::new();
+                let mut messages_per_sender: BTreeMap<UserId, usize> = BTreeMap::new();
+                let mut selected = Vec::new();
+
+                'selection: while !queues.is_empty() {
+                    // Each round selects at most one message per canister.
+                    for (_, queue) in queues.iter_mut() {
+                        if selected.len() >= settings.max_ingress_messages_per_block {
+                            break 'selection;
+                        }
+                        while let Some(ingress_obj) = queue.pop_front() {
+                            let signed_ingress = &ingress_obj.signed_ingress;
+                            let sender_count = messages_per_sender
+                                .entry(signed_ingress.sender())
+                                .or_insert(0);
+                            if *sender_count >= settings.max_ingress_messages_per_sender_per_block {
+                                continue;
+                            }
+                            let result = self.validate_ingress(
+                                IngressMessageId::from(ingress_obj),
+                                signed_ingress,
+                                &state,
+                                context,
+                                &settings,
+                                &past_ingress_set,
+                                selected.len(),
+                                &mut cycles_needed,
+                            );
+                            match result {
+                                Ok(()) => {
+                                    // Calculate the size and abort once we have hit the limit
+                                    accumulated_size += signed_ingress.count_bytes();
+                                    if accumulated_size > byte_limit_bytes {
+                                        break 'selection;
+                                    }
+                                    *sender_count += 1;
+                                    selected.push(signed_ingress.clone());
+                                    break;
+                                }
+                                Err(ValidationError::Permanent(
+                                    IngressPermanentError::IngressPayloadTooBig(_, _),
+                                )) => break 'selection,
+                                Err(ValidationError::Permanent(
+                                    IngressPermanentError::IngressPayloadTooManyMessages(_, _),
+                                )) => break 'selection,
+                                _ => continue,
+                            }
+                        }
                     }
-                    Err(ValidationError::Permanent(
-                        IngressPermanentError::IngressPayloadTooBig(_, _),
-                    )) => SelectResult::Abort,
-                    Err(ValidationError::Permanent(
-                        IngressPermanentError::IngressPayloadTooManyMessages(_, _),
-                    )) => SelectResult::Abort,
-                    _ => SelectResult::Skip,
+                    queues.retain(|(_, queue)| !queue.is_empty());
                 }
+                selected
             }),
         );
 
@@ -206,11 +292,40 @@ impl IngressSelector for IngressManager {
 
         // Tracks the sum of cycles needed per canister.
         let mut cycles_needed: BTreeMap<CanisterId, Cycles> = BTreeMap::new();
+        // Track the number of messages per destination canister and per sender.
+        let mut messages_per_canister: BTreeMap<CanisterId, usize> = BTreeMap::new();
+        let mut messages_per_sender: BTreeMap<UserId, usize> = BTreeMap::new();
         for i in 0..payload.message_count() {
             let (ingress_id, ingress) = payload
                 .get(i)
                 .map_err(IngressPermanentError::IngressPayloadError)?;
 
+            let canister_id = ingress.canister_id();
+            let canister_count = messages_per_canister.entry(canister_id).or_insert(0);
+            *canister_count += 1;
+            if *canister_count > settings.max_ingress_messages_per_canister_per_block {
+                return Err(ValidationError::Permanent(
+                    IngressPermanentError::IngressPayloadTooManyMessagesForCanister(
+                        canister_id,
+                        *canister_count,
+                        settings.max_ingress_messages_per_canister_per_block,
+                    ),
+                ));
+            }
+
+            let sender = ingress.sender();
+            let sender_count = messages_per_sender.entry(sender).or_insert(0);
+            *sender_count += 1;
+            if *sender_count > settings.max_ingress_messages_per_sender_per_block {
+                return Err(ValidationError::Permanent(
+                    IngressPermanentError::IngressPayloadTooManyMessagesFromSender(
+                        sender,
+                        *sender_count,
+                        settings.max_ingress_messages_per_sender_per_block,
+                    ),
+                ));
+            }
+
             self.validate_ingress(
                 ingress_id.clone(),
                 &ingress,
@@ -349,6 +464,10 @@ impl IngressManager {
                 cost: ingress_cost,
             } => match state.canister_state(&payer) {
                 Some(canister) => {
+                    let ingress_cost = ingress_cost
+                        + self
+                            .cycles_account_manager
+                            .ingress_priority_fee(&canister.system_state);
                     let cumulative_ingress_cost =
                         cycles_needed.entry(payer).or_insert_with(Cycles::zero);
                     if let Err(err) = self.cycles_account_manager.can_withdraw_cycles(
@@ -486,8 +605,12 @@ mod tests {
     // use the `RegistryClient` which spawns tokio tasks. Without tokio, the tests
     // would compile but panic at runtime.
     use super::*;
-    use crate::tests::{access_ingress_pool, setup, setup_registry, setup_with_params};
+    use crate::tests::{
+        access_ingress_pool, setup, setup_registry, setup_registry_with_subnet_record,
+        setup_with_params,
+    };
     use assert_matches::assert_matches;
+    use ic_artifact_pool::ingress_pool::IngressPoolImpl;
     use ic_ic00_types::{CanisterIdRecord, Payload, IC_00};
     use ic_interfaces::{
         artifact_pool::UnvalidatedArtifact,
@@ -507,6 +630,7 @@ mod tests {
         },
         FastForwardTimeSource,
     };
+    use ic_test_utilities_registry::test_subnet_record;
     use ic_types::crypto::crypto_hash;
     use ic_types::{
         artifact::{IngressMessageAttribute, IngressMessageId},
@@ -516,7 +640,7 @@ mod tests {
         time::current_time_and_expiry_time,
         Height, RegistryVersion,
     };
-    use std::{collections::HashSet, convert::TryInto, time::Duration};
+    use std::{collections::HashSet, convert::TryInto, sync::RwLock, time::Duration};
 
     const MAX_SIZE: usize = 1000;
     const MAX_SIZE_AS_NUM_BYTES: NumBytes = NumBytes::new(MAX_SIZE as u64);
@@ -525,6 +649,7 @@ mod tests {
     async fn test_get_empty_ingress_payload() {
         setup(|ingress_manager, _| {
             let ingress_msgs = ingress_manager.get_ingress_payload(
+                Height::from(0),
                 &HashSet::new(),
                 &ValidationContext {
                     time: mock_time(),
@@ -655,6 +780,7 @@ mod tests {
                 }
 
                 let payload = ingress_manager.get_ingress_payload(
+                    Height::from(0),
                     &HashSet::new(),
                     &validation_context,
                     MAX_SIZE_AS_NUM_BYTES,
@@ -840,6 +966,7 @@ mod tests {
 
                 // get ingress message in payload
                 let first_ingress_payload = ingress_manager.get_ingress_payload(
+                    Height::from(0),
                     &HashSet::new(),
                     &validation_context,
                     MAX_SIZE_AS_NUM_BYTES,
@@ -897,6 +1024,7 @@ mod tests {
 
                 // get ingress message in payload
                 let first_ingress_payload = ingress_manager.get_ingress_payload(
+                    Height::from(0),
                     &HashSet::new(),
                     &validation_context,
                     MAX_SIZE_AS_NUM_BYTES,
@@ -910,6 +1038,7 @@ mod tests {
                     hash_set.insert(id);
                 }
                 let second_ingress_payload = ingress_manager.get_ingress_payload(
+                    Height::from(0),
                     &hash_set,
                     &validation_context,
                     MAX_SIZE_AS_NUM_BYTES,
@@ -990,6 +1119,7 @@ mod tests {
                 };
 
                 let ingress_payload = ingress_manager.get_ingress_payload(
+                    Height::from(0),
                     &HashSet::new(),
                     &validation_context,
                     MAX_SIZE_AS_NUM_BYTES,
@@ -1074,6 +1204,7 @@ mod tests {
                 };
 
                 let ingress_payload = ingress_manager.get_ingress_payload(
+                    Height::from(0),
                     &HashSet::new(),
                     &validation_context,
                     NumBytes::new(MAX_SIZE as u64),
@@ -1254,6 +1385,7 @@ mod tests {
                 };
 
                 let ingress_payload = ingress_manager.get_ingress_payload(
+                    Height::from(0),
                     &HashSet::new(),
                     &validation_context,
                     MAX_SIZE_AS_NUM_BYTES,
@@ -1431,6 +1563,7 @@ mod tests {
                 }
 
                 let payload = ingress_manager.get_ingress_payload(
+                    Height::from(0),
                     &HashSet::new(),
                     &validation_context,
                     MAX_SIZE_AS_NUM_BYTES,
@@ -1815,6 +1948,7 @@ mod tests {
                 };
 
                 let _payload = ingress_manager.get_ingress_payload(
+                    Height::from(0),
                     &HashSet::new(),
                     &validation_context,
                     NumBytes::new(MAX_SIZE as u64),
@@ -1822,4 +1956,308 @@ mod tests {
             },
         );
     }
+
+    /// Sets up an ingress manager whose subnet record has the given ingress
+    /// message caps and whose state contains one canister per given ingress
+    /// priority fee.
+    fn setup_with_ingress_caps(
+        max_ingress_messages_per_block: u64,
+        max_ingress_messages_per_canister_per_block: u64,
+        max_ingress_messages_per_sender_per_block: u64,
+        priority_fees: &[u128],
+        run: impl FnOnce(IngressManager, Arc<RwLock<IngressPoolImpl>>),
+    ) {
+        let subnet_id = subnet_test_id(0);
+        let mut subnet_record = test_subnet_record();
+        subnet_record.max_ingress_messages_per_block = max_ingress_messages_per_block;
+        subnet_record.max_ingress_messages_per_canister_per_block =
+            max_ingress_messages_per_canister_per_block;
+        subnet_record.max_ingress_messages_per_sender_per_block =
+            max_ingress_messages_per_sender_per_block;
+        let registry = setup_registry_with_subnet_record(subnet_id, subnet_record);
+        let mut state_builder = ReplicatedStateBuilder::default();
+        for (i, priority_fee) in priority_fees.iter().enumerate() {
+            let mut canister = CanisterStateBuilder::default()
+                .with_canister_id(canister_test_id(i as u64))
+                .build();
+            canister.system_state.ingress_priority_fee = Cycles::new(*priority_fee);
+            state_builder = state_builder.with_canister(canister);
+        }
+        setup_with_params(
+            None,
+            Some((registry, subnet_id)),
+            None,
+            Some(state_builder.build()),
+            run,
+        )
+    }
+
+    /// Inserts the given messages into the validated section of the pool.
+    fn insert_validated(ingress_pool: &Arc<RwLock<IngressPoolImpl>>, msgs: &[SignedIngress]) {
+        access_ingress_pool(ingress_pool, |mut ingress_pool| {
+            for m in msgs {
+                let message_id = IngressMessageId::from(m);
+                let attribute = IngressMessageAttribute::new(m);
+                ingress_pool.insert(UnvalidatedArtifact {
+                    message: m.clone(),
+                    peer_id: node_test_id(0),
+                    timestamp: mock_time(),
+                });
+                ingress_pool.apply_changeset(vec![ChangeAction::MoveToValidated((
+                    message_id,
+                    node_test_id(0),
+                    m.count_bytes(),
+                    attribute,
+                    crypto_hash(m.binary()).get(),
+                ))]);
+            }
+        });
+    }
+
+    fn count_per_canister(payload: IngressPayload) -> BTreeMap<CanisterId, usize> {
+        let msgs: Vec<SignedIngress> = payload.try_into().unwrap();
+        let mut counts = BTreeMap::new();
+        for m in msgs {
+            *counts.entry(m.canister_id()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[tokio::test]
+    // A canister with many messages in the pool must not crowd out others.
+    async fn test_get_payload_round_robin_across_canisters() {
+        setup_with_ingress_caps(4, 0, 0, &[0, 0, 0], |ingress_manager, ingress_pool| {
+            // Canister 0 has many messages which all expire before the others.
+            let mut msgs: Vec<_> = (0..10)
+                .map(|i| {
+                    SignedIngressBuilder::new()
+                        .canister_id(canister_test_id(0))
+                        .nonce(i)
+                        .expiry_time(mock_time() + Duration::from_secs(i + 1))
+                        .build()
+                })
+                .collect();
+            msgs.push(
+                SignedIngressBuilder::new()
+                    .canister_id(canister_test_id(1))
+                    .expiry_time(mock_time() + MAX_INGRESS_TTL)
+                    .build(),
+            );
+            msgs.push(
+                SignedIngressBuilder::new()
+                    .canister_id(canister_test_id(2))
+                    .expiry_time(mock_time() + MAX_INGRESS_TTL)
+                    .build(),
+            );
+            insert_validated(&ingress_pool, &msgs);
+
+            let payload = ingress_manager.get_ingress_payload(
+                Height::from(0),
+                &HashSet::new(),
+                &ValidationContext {
+                    time: mock_time(),
+                    registry_version: RegistryVersion::from(1),
+                    certified_height: Height::from(0),
+                },
+                NumBytes::new(1024 * 1024),
+            );
+            assert_eq!(payload.message_count(), 4);
+            let counts = count_per_canister(payload);
+            assert_eq!(counts.get(&canister_test_id(0)), Some(&2));
+            assert_eq!(counts.get(&canister_test_id(1)), Some(&1));
+            assert_eq!(counts.get(&canister_test_id(2)), Some(&1));
+        })
+    }
+
+    #[tokio::test]
+    // Canisters paying an ingress priority fee are served first.
+    async fn test_get_payload_prefers_canisters_with_priority_fee() {
+        setup_with_ingress_caps(2, 0, 0, &[0, 10, 1_000], |ingress_manager, ingress_pool| {
+            let msgs: Vec<_> = (0..3)
+                .map(|i| {
+                    SignedIngressBuilder::new()
+                        .canister_id(canister_test_id(i))
+                        .expiry_time(mock_time() + MAX_INGRESS_TTL)
+                        .build()
+                })
+                .collect();
+            insert_validated(&ingress_pool, &msgs);
+
+            let payload = ingress_manager.get_ingress_payload(
+                Height::from(0),
+                &HashSet::new(),
+                &ValidationContext {
+                    time: mock_time(),
+                    registry_version: RegistryVersion::from(1),
+                    certified_height: Height::from(0),
+                },
+                NumBytes::new(1024 * 1024),
+            );
+            let msgs: Vec<SignedIngress> = payload.try_into().unwrap();
+            let receivers: Vec<_> = msgs.iter().map(|m| m.canister_id()).collect();
+            assert_eq!(receivers, vec![canister_test_id(2), canister_test_id(1)]);
+        })
+    }
+
+    #[tokio::test]
+    // Among canisters paying the same fee, the one served first rotates with
+    // the height of the block being built.
+    async fn test_get_payload_rotates_round_robin_start_per_height() {
+        setup_with_ingress_caps(1, 0, 0, &[0, 0, 0], |ingress_manager, ingress_pool| {
+            let msgs: Vec<_> = (0..3)
+                .map(|i| {
+                    SignedIngressBuilder::new()
+                        .canister_id(canister_test_id(i))
+                        .expiry_time(mock_time() + MAX_INGRESS_TTL)
+                        .build()
+                })
+                .collect();
+            insert_validated(&ingress_pool, &msgs);
+
+            // The certified height does not advance, e.g. because certification
+            // lags behind, but the block height does.
+            let first_receiver = |height: u64| {
+                let payload = ingress_manager.get_ingress_payload(
+                    Height::from(height),
+                    &HashSet::new(),
+                    &ValidationContext {
+                        time: mock_time(),
+                        registry_version: RegistryVersion::from(1),
+                        certified_height: Height::from(0),
+                    },
+                    NumBytes::new(1024 * 1024),
+                );
+                let msgs: Vec<SignedIngress> = payload.try_into().unwrap();
+                msgs[0].canister_id()
+            };
+            assert_eq!(first_receiver(0), canister_test_id(0));
+            assert_eq!(first_receiver(1), canister_test_id(1));
+            assert_eq!(first_receiver(2), canister_test_id(2));
+            assert_eq!(first_receiver(3), canister_test_id(0));
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get_payload_respects_per_canister_cap() {
+        setup_with_ingress_caps(100, 2, 0, &[0, 0], |ingress_manager, ingress_pool| {
+            let msgs: Vec<_> = (0..10)
+                .map(|i| {
+                    SignedIngressBuilder::new()
+                        .canister_id(canister_test_id(i % 2))
+                        .nonce(i)
+                        .expiry_time(mock_time() + MAX_INGRESS_TTL)
+                        .build()
+                })
+                .collect();
+            insert_validated(&ingress_pool, &msgs);
+
+            let payload = ingress_manager.get_ingress_payload(
+                Height::from(0),
+                &HashSet::new(),
+                &ValidationContext {
+                    time: mock_time(),
+                    registry_version: RegistryVersion::from(1),
+                    certified_height: Height::from(0),
+                },
+                NumBytes::new(1024 * 1024),
+            );
+            assert_eq!(payload.message_count(), 4);
+            let counts = count_per_canister(payload);
+            assert_eq!(counts.get(&canister_test_id(0)), Some(&2));
+            assert_eq!(counts.get(&canister_test_id(1)), Some(&2));
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get_payload_respects_per_sender_cap() {
+        setup_with_ingress_caps(100, 0, 2, &[0, 0, 0], |ingress_manager, ingress_pool| {
+            // All messages are sent by the anonymous user.
+            let msgs: Vec<_> = (0..6)
+                .map(|i| {
+                    SignedIngressBuilder::new()
+                        .canister_id(canister_test_id(i % 3))
+                        .nonce(i)
+                        .expiry_time(mock_time() + MAX_INGRESS_TTL)
+                        .build()
+                })
+                .collect();
+            insert_validated(&ingress_pool, &msgs);
+
+            let payload = ingress_manager.get_ingress_payload(
+                Height::from(0),
+                &HashSet::new(),
+                &ValidationContext {
+                    time: mock_time(),
+                    registry_version: RegistryVersion::from(1),
+                    certified_height: Height::from(0),
+                },
+                NumBytes::new(1024 * 1024),
+            );
+            assert_eq!(payload.message_count(), 2);
+        })
+    }
+
+    #[tokio::test]
+    async fn test_validate_ingress_payload_per_canister_cap() {
+        setup_with_ingress_caps(100, 2, 0, &[0], |ingress_manager, _| {
+            let payload: Vec<_> = (0..3)
+                .map(|i| {
+                    SignedIngressBuilder::new()
+                        .canister_id(canister_test_id(0))
+                        .nonce(i)
+                        .expiry_time(mock_time() + MAX_INGRESS_TTL)
+                        .build()
+                })
+                .collect();
+
+            let ingress_validation = ingress_manager.validate_ingress_payload(
+                &IngressPayload::from(payload),
+                &HashSet::new(),
+                &ValidationContext {
+                    time: mock_time(),
+                    registry_version: RegistryVersion::from(1),
+                    certified_height: Height::from(0),
+                },
+            );
+
+            assert_matches!(
+                ingress_validation,
+                Err(ValidationError::Permanent(
+                    IngressPermanentError::IngressPayloadTooManyMessagesForCanister(id, 3, 2),
+                )) if id == canister_test_id(0)
+            );
+        })
+    }
+
+    #[tokio::test]
+    async fn test_validate_ingress_payload_per_sender_cap() {
+        setup_with_ingress_caps(100, 0, 1, &[0, 0], |ingress_manager, _| {
+            let payload: Vec<_> = (0..2)
+                .map(|i| {
+                    SignedIngressBuilder::new()
+                        .canister_id(canister_test_id(i))
+                        .nonce(i)
+                        .expiry_time(mock_time() + MAX_INGRESS_TTL)
+                        .build()
+                })
+                .collect();
+
+            let ingress_validation = ingress_manager.validate_ingress_payload(
+                &IngressPayload::from(payload),
+                &HashSet::new(),
+                &ValidationContext {
+                    time: mock_time(),
+                    registry_version: RegistryVersion::from(1),
+                    certified_height: Height::from(0),
+                },
+            );
+
+            assert_matches!(
+                ingress_validation,
+                Err(ValidationError::Permanent(
+                    IngressPermanentError::IngressPayloadTooManyMessagesFromSender(_, 2, 1),
+                ))
+            );
+        })
+    }
 }
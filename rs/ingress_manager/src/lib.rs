@@ -67,6 +67,15 @@ impl IngressPoolSelect for IngressPoolSelectWrapper {
         let pool = self.pool.read().unwrap();
         pool.select_validated(range, f)
     }
+
+    fn select_validated_from_refs<'a>(
+        &self,
+        range: RangeInclusive<Time>,
+        f: Box<dyn FnOnce(Vec<&IngressPoolObject>) -> Vec<SignedIngress> + 'a>,
+    ) -> Vec<SignedIngress> {
+        let pool = self.pool.read().unwrap();
+        pool.select_validated_from_refs(range, f)
+    }
 }
 /// Keeps the metrics to be exported by the IngressManager
 struct IngressManagerMetrics {
@@ -193,6 +202,13 @@ impl IngressManager {
                     );
                     settings.max_ingress_messages_per_block = 1;
                 }
+                // A per-canister or per-sender cap of 0 means no cap at all
+                if settings.max_ingress_messages_per_canister_per_block == 0 {
+                    settings.max_ingress_messages_per_canister_per_block = usize::MAX;
+                }
+                if settings.max_ingress_messages_per_sender_per_block == 0 {
+                    settings.max_ingress_messages_per_sender_per_block = usize::MAX;
+                }
                 settings
             }),
         }
@@ -213,6 +229,7 @@ pub(crate) mod tests {
     };
     use ic_interfaces_state_manager_mocks::MockStateManager;
     use ic_metrics::MetricsRegistry;
+    use ic_protobuf::registry::subnet::v1::SubnetRecord;
     use ic_registry_client::client::RegistryClientImpl;
     use ic_registry_keys::make_subnet_record_key;
     use ic_registry_proto_data_provider::ProtoRegistryDataProvider;
@@ -234,10 +251,16 @@ pub(crate) mod tests {
         subnet_id: SubnetId,
         max_ingress_bytes_per_message: usize,
     ) -> Arc<dyn RegistryClient> {
-        let registry_data_provider = Arc::new(ProtoRegistryDataProvider::new());
         let mut subnet_record = test_subnet_record();
         subnet_record.max_ingress_bytes_per_message = max_ingress_bytes_per_message as u64;
+        setup_registry_with_subnet_record(subnet_id, subnet_record)
+    }
 
+    pub(crate) fn setup_registry_with_subnet_record(
+        subnet_id: SubnetId,
+        subnet_record: SubnetRecord,
+    ) -> Arc<dyn RegistryClient> {
+        let registry_data_provider = Arc::new(ProtoRegistryDataProvider::new());
         registry_data_provider
             .add(
                 &make_subnet_record_key(subnet_id),
@@ -98,6 +98,7 @@ proptest! {
 
                 // Generate a payload out of the propped up ingress
                 let payload = ingress_manager.get_ingress_payload(
+                    Height::from(0),
                     &HashSet::new(),
                     &validation_context,
                     NumBytes::new(MAX_BLOCK_SIZE),
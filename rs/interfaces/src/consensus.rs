@@ -9,6 +9,10 @@ use crate::{
         IngressPayloadValidationError, IngressPermanentError, IngressTransientError,
     },
     messaging::{InvalidXNetPayload, XNetPayloadValidationError, XNetTransientValidationError},
+    query_stats::{
+        InvalidQueryStatsPayload, QueryStatsPayloadValidationError,
+        QueryStatsTransientValidationError,
+    },
     self_validating_payload::{
         InvalidSelfValidatingPayload, SelfValidatingPayloadValidationError,
         SelfValidatingTransientValidationError,
@@ -19,7 +23,9 @@ use ic_base_types::{NumBytes, SubnetId};
 use ic_types::{
     artifact::{ConsensusMessageAttribute, ConsensusMessageFilter, ConsensusMessageId, PriorityFn},
     registry::RegistryClientError,
+    Height, Time,
 };
+use serde::Serialize;
 
 /// Consensus artifact processing interface.
 pub trait Consensus: Send {
@@ -52,6 +58,26 @@ pub trait ConsensusGossip: Send + Sync {
     fn get_filter(&self) -> ConsensusMessageFilter;
 }
 
+/// The times at which this replica observed the completion of each consensus
+/// phase at a given height. A phase that has not been observed (yet) is `None`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct HeightTimeline {
+    pub height: Height,
+    pub block_proposal: Option<Time>,
+    pub notarization: Option<Time>,
+    pub finalization: Option<Time>,
+    pub certification: Option<Time>,
+    pub batch_delivery: Option<Time>,
+}
+
+/// Read access to the per-height timeline recorded by consensus, which helps
+/// to find out which phase is slow when a subnet slows down.
+pub trait ConsensusTimelineReader: Send + Sync {
+    /// Return the timelines of at most the `n` highest recorded heights, in
+    /// ascending order of height.
+    fn get_latest_timelines(&self, n: usize) -> Vec<HeightTimeline>;
+}
+
 #[derive(Debug)]
 pub enum PayloadPermanentError {
     XNetPayloadValidationError(InvalidXNetPayload),
@@ -62,6 +88,7 @@ pub enum PayloadPermanentError {
     },
     SelfValidatingPayloadValidationError(InvalidSelfValidatingPayload),
     CanisterHttpPayloadValidationError(CanisterHttpPermanentValidationError),
+    QueryStatsPayloadValidationError(InvalidQueryStatsPayload),
 }
 
 #[derive(Debug)]
@@ -72,6 +99,7 @@ pub enum PayloadTransientError {
     SubnetNotFound(SubnetId),
     SelfValidatingPayloadValidationError(SelfValidatingTransientValidationError),
     CanisterHttpPayloadValidationError(CanisterHttpTransientValidationError),
+    QueryStatsPayloadValidationError(QueryStatsTransientValidationError),
 }
 
 /// Payload validation error
@@ -112,3 +140,12 @@ impl From<CanisterHttpPayloadValidationError> for PayloadValidationError {
         )
     }
 }
+
+impl From<QueryStatsPayloadValidationError> for PayloadValidationError {
+    fn from(err: QueryStatsPayloadValidationError) -> Self {
+        err.map(
+            PayloadPermanentError::QueryStatsPayloadValidationError,
+            PayloadTransientError::QueryStatsPayloadValidationError,
+        )
+    }
+}
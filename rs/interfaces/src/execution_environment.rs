@@ -518,6 +518,17 @@ pub trait SystemApi {
     /// See https://sdk.dfinity.org/docs/interface-spec/index.html#system-api-call
     fn ic0_call_on_cleanup(&mut self, fun: u32, env: u32) -> HypervisorResult<()>;
 
+    /// Turns the call under construction into a best-effort call, with a
+    /// deadline of `timeout_seconds` (capped at 300) from the current time.
+    /// Can be called at most once between `ic0.call_new` and
+    /// `ic0.call_perform`.
+    ///
+    /// No memory is reserved for the response of a best-effort call; and its
+    /// messages may be dropped by the system under memory pressure. If no
+    /// response is delivered by the deadline, the call is rejected with
+    /// `SYS_UNKNOWN`.
+    fn ic0_call_with_best_effort_response(&mut self, timeout_seconds: u32) -> HypervisorResult<()>;
+
     /// (deprecated) Please use `ic0_call_cycles_add128` instead, as this API
     /// can only add a 64-bit value.
     ///
@@ -634,6 +645,44 @@ pub trait SystemApi {
         heap: &[u8],
     ) -> HypervisorResult<()>;
 
+    /// Copies the parts of [src, src+size) of the canister memory given by
+    /// `modified_heap_ranges` to the corresponding locations in
+    /// [offset, offset+size) of the stable memory. The embedder obtains the
+    /// ranges from `stable64_differing_ranges`, so only the heap pages that
+    /// differ from their copy in stable memory are written, no matter in which
+    /// message they were modified. `ic0_stable64_restore` copies the range
+    /// back.
+    ///
+    /// The call is charged per byte of the whole range like
+    /// `ic0_stable64_write`, since every byte is compared, plus the cost of
+    /// the new dirty pages created by the copied parts.
+    ///
+    /// This system call traps under the same conditions as
+    /// `ic0_stable64_write`. Returns the number of bytes copied.
+    fn ic0_stable64_checkpoint(
+        &mut self,
+        offset: u64,
+        src: u64,
+        size: u64,
+        modified_heap_ranges: &[ops::Range<u64>],
+        heap: &[u8],
+    ) -> HypervisorResult<u64>;
+
+    /// Copies the parts of [offset, offset+size) of the stable memory that
+    /// differ from [dst, dst+size) of the canister memory back to the
+    /// canister memory. Canister memory pages that already hold the same
+    /// contents are not written.
+    ///
+    /// This system call traps under the same conditions as
+    /// `ic0_stable64_read`. Returns the number of bytes copied.
+    fn ic0_stable64_restore(
+        &mut self,
+        dst: u64,
+        offset: u64,
+        size: u64,
+        heap: &mut [u8],
+    ) -> HypervisorResult<u64>;
+
     /// Determines the number of dirty pages that a stable write would create
     /// and the cost for those dirty pages (without actually doing the write).
     fn dirty_pages_from_stable_write(
@@ -642,6 +691,17 @@ pub trait SystemApi {
         size: u64,
     ) -> HypervisorResult<(NumPages, NumInstructions)>;
 
+    /// Returns the ranges of [src, src+size) of the canister memory whose
+    /// contents differ from [offset, offset+size) of the stable memory, at OS
+    /// page granularity (without copying anything).
+    fn stable64_differing_ranges(
+        &self,
+        offset: u64,
+        src: u64,
+        size: u64,
+        heap: &[u8],
+    ) -> HypervisorResult<Vec<ops::Range<u64>>>;
+
     /// The canister can query the IC for the current time.
     fn ic0_time(&self) -> HypervisorResult<Time>;
 
@@ -679,7 +739,8 @@ pub trait SystemApi {
 
     /// This system call is not part of the public spec. It's called after a
     /// native `memory.grow` has been called to check whether there's enough
-    /// available memory left.
+    /// available memory left and whether the new size of the Wasm memory is
+    /// within the canister's Wasm memory limit.
     fn update_available_memory(
         &mut self,
         native_memory_grow_res: i32,
@@ -1,6 +1,6 @@
 use ic_base_types::{CanisterIdError, PrincipalIdBlobParseError};
 use ic_error_types::UserError;
-use ic_types::{methods::WasmMethod, CanisterId, Cycles, NumInstructions};
+use ic_types::{methods::WasmMethod, CanisterId, Cycles, NumBytes, NumInstructions};
 use ic_wasm_types::{WasmEngineError, WasmInstrumentationError, WasmValidationError};
 use serde::{Deserialize, Serialize};
 
@@ -133,6 +133,26 @@ pub enum HypervisorError {
     },
     /// A canister has written too much new data in a single message.
     MemoryAccessLimitExceeded(String),
+    /// An attempt was made to grow the Wasm heap above the `wasm_memory_limit`
+    /// canister setting.
+    WasmMemoryLimitExceeded {
+        bytes: NumBytes,
+        limit: NumBytes,
+    },
+    /// The canister does not have enough cycles to reserve for the memory it
+    /// allocated while the subnet memory usage was above the threshold.
+    InsufficientCyclesInMemoryGrow {
+        bytes: NumBytes,
+        available: Cycles,
+        required: Cycles,
+    },
+    /// Reserving cycles for the memory allocated by the canister would exceed
+    /// its `reserved_cycles_limit` canister setting.
+    ReservedCyclesLimitExceededInMemoryGrow {
+        bytes: NumBytes,
+        requested: Cycles,
+        limit: Cycles,
+    },
 }
 
 impl From<WasmInstrumentationError> for HypervisorError {
@@ -308,6 +328,42 @@ impl HypervisorError {
                 format!("Canister exceeded memory access limits: {}", s)
 
             ),
+            Self::WasmMemoryLimitExceeded { bytes, limit } => UserError::new(
+                E::CanisterWasmMemoryLimitExceeded,
+                format!(
+                    "Canister {} exceeded its Wasm memory limit: attempted to grow the Wasm memory to {} bytes, but the limit is {} bytes",
+                    canister_id, bytes, limit
+                ),
+            ),
+            Self::InsufficientCyclesInMemoryGrow {
+                bytes,
+                available,
+                required,
+            } => UserError::new(
+                E::InsufficientCyclesInMemoryGrow,
+                format!(
+                    "Canister {} cannot grow memory by {} bytes due to insufficient cycles. \
+                    At least {} additional cycles are required.",
+                    canister_id,
+                    bytes,
+                    required - available
+                ),
+            ),
+            Self::ReservedCyclesLimitExceededInMemoryGrow {
+                bytes,
+                requested,
+                limit,
+            } => UserError::new(
+                E::ReservedCyclesLimitExceededInMemoryGrow,
+                format!(
+                    "Canister {} cannot grow memory by {} bytes due to its reserved cycles limit. \
+                    The current limit ({}) would be exceeded by {}.",
+                    canister_id,
+                    bytes,
+                    limit,
+                    requested - limit
+                ),
+            ),
         }
     }
 
@@ -337,6 +393,13 @@ impl HypervisorError {
             HypervisorError::Aborted => "Aborted",
             HypervisorError::SliceOverrun { .. } => "SliceOverrun",
             HypervisorError::MemoryAccessLimitExceeded(_) => "MemoryAccessLimitExceeded",
+            HypervisorError::WasmMemoryLimitExceeded { .. } => "WasmMemoryLimitExceeded",
+            HypervisorError::InsufficientCyclesInMemoryGrow { .. } => {
+                "InsufficientCyclesInMemoryGrow"
+            }
+            HypervisorError::ReservedCyclesLimitExceededInMemoryGrow { .. } => {
+                "ReservedCyclesLimitExceededInMemoryGrow"
+            }
         }
     }
 
@@ -368,7 +431,10 @@ impl HypervisorError {
             | HypervisorError::MessageRejected
             | HypervisorError::InsufficientCyclesBalance(_)
             | HypervisorError::WasmReservedPages
-            | HypervisorError::MemoryAccessLimitExceeded(_) => false,
+            | HypervisorError::MemoryAccessLimitExceeded(_)
+            | HypervisorError::WasmMemoryLimitExceeded { .. }
+            | HypervisorError::InsufficientCyclesInMemoryGrow { .. }
+            | HypervisorError::ReservedCyclesLimitExceededInMemoryGrow { .. } => false,
         }
     }
 }
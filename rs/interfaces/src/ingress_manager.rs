@@ -12,7 +12,7 @@ use ic_types::{
     ingress::IngressSets,
     messages::MessageId,
     time::{Time, UNIX_EPOCH},
-    CanisterId, Height, NumBytes,
+    CanisterId, Height, NumBytes, UserId,
 };
 use std::collections::HashSet;
 
@@ -63,6 +63,8 @@ pub enum IngressPermanentError {
     IngressMessageTooBig(usize, usize),
     IngressPayloadTooBig(usize, usize),
     IngressPayloadTooManyMessages(usize, usize),
+    IngressPayloadTooManyMessagesForCanister(CanisterId, usize, usize),
+    IngressPayloadTooManyMessagesFromSender(UserId, usize, usize),
     DuplicatedIngressMessage(MessageId),
     InsufficientCycles(CanisterOutOfCyclesError),
     CanisterNotFound(CanisterId),
@@ -131,6 +133,7 @@ pub trait IngressSelector: Send + Sync {
     /// to Consensus.
     ///
     /// #Input
+    /// [height] is the height of the block the payload is built for.
     /// [past_ingress] allows querying if an ingress message exists in past
     /// blocks. It is used for deduplication purpose.
     /// [ValidationContext] contains registry_version that allows to validate
@@ -145,6 +148,7 @@ pub trait IngressSelector: Send + Sync {
     /// [IngressPayload] which is a collection of valid ingress messages
     fn get_ingress_payload(
         &self,
+        height: Height,
         past_ingress: &dyn IngressSetQuery,
         context: &ValidationContext,
         byte_limit: NumBytes,
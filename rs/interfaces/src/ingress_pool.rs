@@ -145,6 +145,16 @@ pub trait IngressPoolSelect: Send + Sync {
         range: std::ops::RangeInclusive<Time>,
         f: Box<dyn FnMut(&IngressPoolObject) -> SelectResult<SignedIngress> + 'a>,
     ) -> Vec<SignedIngress>;
+
+    /// Select qualifying objects from the validated pool, given references to
+    /// all objects in the expiry range at once. Unlike `select_validated`, this
+    /// lets the caller pick objects in any order without copying them out of
+    /// the pool first.
+    fn select_validated_from_refs<'a>(
+        &self,
+        range: std::ops::RangeInclusive<Time>,
+        f: Box<dyn FnOnce(Vec<&IngressPoolObject>) -> Vec<SignedIngress> + 'a>,
+    ) -> Vec<SignedIngress>;
 }
 
 /// Interface to throttle user ingress messages
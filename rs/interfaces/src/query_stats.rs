@@ -0,0 +1,97 @@
+//! The query stats public interface.
+use crate::validation::ValidationError;
+use ic_interfaces_state_manager::StateManagerError;
+use ic_types::{
+    batch::{QueryStatsEpoch, QueryStatsPayload, ValidationContext},
+    consensus::Payload,
+    CanisterId, Height, NodeId, NumBytes, Time,
+};
+
+/// A QueryStatsPayload error from which it is not possible to recover.
+#[derive(Debug)]
+pub enum InvalidQueryStatsPayload {
+    /// The payload contains statistics of a node other than the block maker.
+    InvalidProposer {
+        expected: NodeId,
+        proposer: NodeId,
+    },
+    /// The payload contains statistics of an epoch that has not ended yet at
+    /// the certified height of the validation context.
+    EpochNotCompleted {
+        epoch: QueryStatsEpoch,
+        current_epoch: QueryStatsEpoch,
+    },
+    /// The statistics of this proposer and epoch have already been delivered,
+    /// either in the certified state or in one of the past payloads.
+    AlreadyDelivered {
+        proposer: NodeId,
+        epoch: QueryStatsEpoch,
+    },
+    /// The same canister appears more than once, or the canisters are not
+    /// sorted by canister id.
+    DuplicateOrUnsortedCanisterId(CanisterId),
+    PayloadTooBig,
+}
+
+/// A QueryStatsPayload error from which it may be possible to recover.
+#[derive(Debug)]
+pub enum QueryStatsTransientValidationError {
+    StateUnavailable(StateManagerError),
+}
+
+/// A QueryStatsPayload error that results from payload validation.
+pub type QueryStatsPayloadValidationError =
+    ValidationError<InvalidQueryStatsPayload, QueryStatsTransientValidationError>;
+
+pub trait QueryStatsPayloadBuilder: Send + Sync {
+    /// Produces the `QueryStatsPayload` of this replica for the oldest
+    /// completed epoch whose statistics have not yet been delivered or
+    /// aggregated, given a `ValidationContext` and `past_payloads` (the
+    /// `QueryStatsPayloads` from all blocks above the certified height, in
+    /// descending block height order).
+    ///
+    /// Returns `None` if there is nothing to include or the payload would
+    /// exceed `byte_limit`.
+    fn get_query_stats_payload(
+        &self,
+        height: Height,
+        validation_context: &ValidationContext,
+        past_payloads: &[&QueryStatsPayload],
+        byte_limit: NumBytes,
+    ) -> Option<QueryStatsPayload>;
+
+    /// Checks whether the provided `QueryStatsPayload`, included in a block
+    /// made by `proposer`, is valid given a `ValidationContext` and
+    /// `past_payloads`.
+    ///
+    /// A block maker may only include its own statistics, and the statistics
+    /// of a proposer and epoch may not be delivered twice.
+    ///
+    /// If valid, returns the payload's `CountBytes` size; else returns a
+    /// permanent or transient `ValidationError`.
+    fn validate_query_stats_payload(
+        &self,
+        height: Height,
+        proposer: NodeId,
+        payload: &QueryStatsPayload,
+        validation_context: &ValidationContext,
+        past_payloads: &[&QueryStatsPayload],
+    ) -> Result<NumBytes, QueryStatsPayloadValidationError>;
+
+    /// Extracts the sequence of past `QueryStatsPayloads` from `past_payloads`.
+    fn filter_past_payloads<'a>(
+        &self,
+        past_payloads: &'a [(Height, Time, Payload)],
+    ) -> Vec<&'a QueryStatsPayload> {
+        past_payloads
+            .iter()
+            .filter_map(|(_, _, payload)| {
+                if payload.is_summary() {
+                    None
+                } else {
+                    payload.as_ref().as_data().batch.query_stats.as_ref()
+                }
+            })
+            .collect()
+    }
+}
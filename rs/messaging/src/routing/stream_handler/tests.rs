@@ -1276,6 +1276,7 @@ fn generate_reject_response_queue_full() {
                 RejectCode::SysTransient,
                 err.to_string(),
             )),
+            deadline: msg.deadline,
         }
         .into(),
     );
@@ -1316,6 +1317,7 @@ fn generate_reject_response_canister_not_found() {
                 RejectCode::DestinationInvalid,
                 err.to_string(),
             )),
+            deadline: msg.deadline,
         }
         .into(),
     );
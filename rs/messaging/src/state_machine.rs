@@ -8,7 +8,7 @@ use ic_logger::{fatal, warn, ReplicaLogger};
 use ic_metrics::Timer;
 use ic_registry_subnet_features::SubnetFeatures;
 use ic_replicated_state::{NetworkTopology, ReplicatedState};
-use ic_types::{batch::Batch, ExecutionRound};
+use ic_types::{batch::Batch, ExecutionRound, NumBytes};
 use std::sync::Arc;
 
 #[cfg(test)]
@@ -19,6 +19,8 @@ const PHASE_EXECUTION: &str = "execution";
 const PHASE_MESSAGE_ROUTING: &str = "message_routing";
 const PHASE_REMOVE_CANISTERS: &str = "remove_canisters_not_in_rt";
 const PHASE_TIME_OUT_REQUESTS: &str = "time_out_requests";
+const PHASE_TIME_OUT_CALLBACKS: &str = "time_out_callbacks";
+const PHASE_SHED_MESSAGES: &str = "shed_messages";
 
 pub(crate) trait StateMachine: Send {
     fn execute_round(
@@ -34,6 +36,7 @@ pub(crate) struct StateMachineImpl {
     scheduler: Box<dyn Scheduler<State = ReplicatedState>>,
     demux: Box<dyn Demux>,
     stream_builder: Box<dyn StreamBuilder>,
+    best_effort_message_memory_capacity: NumBytes,
     log: ReplicaLogger,
     metrics: Arc<MessageRoutingMetrics>,
 }
@@ -43,6 +46,7 @@ impl StateMachineImpl {
         scheduler: Box<dyn Scheduler<State = ReplicatedState>>,
         demux: Box<dyn Demux>,
         stream_builder: Box<dyn StreamBuilder>,
+        best_effort_message_memory_capacity: NumBytes,
         log: ReplicaLogger,
         metrics: Arc<MessageRoutingMetrics>,
     ) -> Self {
@@ -50,6 +54,7 @@ impl StateMachineImpl {
             scheduler,
             demux,
             stream_builder,
+            best_effort_message_memory_capacity,
             log,
             metrics,
         }
@@ -142,8 +147,26 @@ impl StateMachine for StateMachineImpl {
             .inc_by(timed_out_requests);
         self.observe_phase_duration(PHASE_TIME_OUT_REQUESTS, &phase_timer);
 
+        // Time out expired best-effort callbacks.
+        let phase_timer = Timer::start();
+        let timed_out_callbacks = state.time_out_callbacks(batch.time);
+        self.metrics
+            .timed_out_callbacks_total
+            .inc_by(timed_out_callbacks);
+        self.observe_phase_duration(PHASE_TIME_OUT_CALLBACKS, &phase_timer);
+
+        // Shed best-effort messages above the memory capacity.
+        let phase_timer = Timer::start();
+        let shed_messages =
+            state.shed_best_effort_messages(self.best_effort_message_memory_capacity);
+        self.metrics.shed_messages_total.inc_by(shed_messages);
+        self.observe_phase_duration(PHASE_SHED_MESSAGES, &phase_timer);
+
         // Preprocess messages and add messages to the induction pool through the Demux.
         let phase_timer = Timer::start();
+        if let Some(query_stats) = batch.payload.query_stats.take() {
+            state.deliver_query_stats(&query_stats);
+        }
         let mut state_with_messages = self.demux.process_payload(state, batch.payload);
 
         // Append additional responses to the consensus queue.
@@ -3,6 +3,7 @@ use crate::{
     routing::demux::MockDemux, routing::stream_builder::MockStreamBuilder,
     state_machine::StateMachineImpl,
 };
+use ic_config::execution_environment::Config as HypervisorConfig;
 use ic_ic00_types::EcdsaKeyId;
 use ic_interfaces::execution_environment::Scheduler;
 use ic_interfaces_state_manager::StateManager;
@@ -148,6 +149,7 @@ fn state_machine_populates_network_topology() {
             fixture.scheduler,
             fixture.demux,
             fixture.stream_builder,
+            HypervisorConfig::default().best_effort_message_memory_capacity,
             log,
             fixture.metrics,
         ));
@@ -180,6 +182,7 @@ fn test_delivered_batch(provided_batch: Batch) {
             fixture.scheduler,
             fixture.demux,
             fixture.stream_builder,
+            HypervisorConfig::default().best_effort_message_memory_capacity,
             log,
             fixture.metrics,
         ));
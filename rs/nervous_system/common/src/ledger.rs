@@ -1,10 +1,13 @@
 use crate::NervousSystemError;
 use async_trait::async_trait;
+use candid::Nat;
+use dfn_candid::candid_one;
 use dfn_core::api::PrincipalId;
 use dfn_core::{call, CanisterId};
 use dfn_protobuf::protobuf;
 use ic_crypto_sha::Sha256;
-use ic_icrc1::{Account, Subaccount};
+use ic_icrc1::endpoints::{TransferArg, TransferError};
+use ic_icrc1::{Account, Memo as Icrc1Memo, Subaccount};
 use ic_ledger_core::block::BlockIndex;
 use icp_ledger::{
     tokens_from_proto, AccountBalanceArgs, AccountIdentifier, Memo, SendArgs,
@@ -39,6 +42,29 @@ pub trait ICRC1Ledger: Send + Sync {
         memo: u64,
     ) -> Result<BlockIndex, NervousSystemError>;
 
+    /// Like `transfer_funds`, but sets `created_at_time_nanos` on the transfer so
+    /// that the ledger deduplicates retries of the same transfer (within its
+    /// deduplication window). Retrying with the same arguments after an
+    /// `Unknown` error returns the index of the original block instead of
+    /// transferring the funds a second time.
+    ///
+    /// The default implementation is for ledgers that do not deduplicate
+    /// transfers: it cannot tell whether a failed transfer was executed, so all
+    /// errors are reported as `Unknown`.
+    async fn transfer_funds_idempotently(
+        &self,
+        amount_e8s: u64,
+        fee_e8s: u64,
+        from_subaccount: Option<Subaccount>,
+        to: Account,
+        memo: u64,
+        _created_at_time_nanos: u64,
+    ) -> Result<BlockIndex, TransferFundsError> {
+        self.transfer_funds(amount_e8s, fee_e8s, from_subaccount, to, memo)
+            .await
+            .map_err(TransferFundsError::Unknown)
+    }
+
     /// Gets the total supply of tokens from the sum of all accounts except for the
     /// minting canister's.
     async fn total_supply(&self) -> Result<Tokens, NervousSystemError>;
@@ -50,6 +76,81 @@ pub trait ICRC1Ledger: Send + Sync {
     fn canister_id(&self) -> CanisterId;
 }
 
+/// The ways in which `ICRC1Ledger::transfer_funds_idempotently` can fail.
+#[derive(Debug)]
+pub enum TransferFundsError {
+    /// The ledger definitely did not execute the transfer.
+    Rejected(NervousSystemError),
+    /// The transfer may or may not have been executed, e.g. because the call to
+    /// the ledger failed without a definite answer.
+    Unknown(NervousSystemError),
+}
+
+impl TransferFundsError {
+    pub fn error_message(&self) -> &str {
+        match self {
+            TransferFundsError::Rejected(err) | TransferFundsError::Unknown(err) => {
+                &err.error_message
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for TransferFundsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TransferFundsError::Rejected(err) => write!(f, "Transfer rejected: {}", err),
+            TransferFundsError::Unknown(err) => write!(f, "Transfer outcome unknown: {}", err),
+        }
+    }
+}
+
+/// Interprets the result of calling `icrc1_transfer` on a ledger.
+///
+/// A duplicate of an earlier transfer counts as a success, ledger errors and
+/// rejects of the call that guarantee that the ledger did not change its state
+/// (DESTINATION_INVALID, CANISTER_REJECT and CANISTER_ERROR) count as definite
+/// rejections, and everything else as an unknown outcome. `TooOld` is unknown
+/// too: the ledger no longer deduplicates the transfer, so it cannot tell
+/// whether an earlier attempt with the same arguments was executed.
+pub fn icrc1_transfer_result_to_block_index(
+    result: Result<Result<Nat, TransferError>, (Option<i32>, String)>,
+) -> Result<BlockIndex, TransferFundsError> {
+    let nat_to_block_index = |n: Nat| {
+        u64::try_from(&n.0).map_err(|err| {
+            TransferFundsError::Unknown(NervousSystemError::new_with_message(format!(
+                "Block index {} returned by 'icrc1_transfer' does not fit into u64: {}",
+                n, err
+            )))
+        })
+    };
+    match result {
+        Ok(Ok(block_index)) => nat_to_block_index(block_index),
+        Ok(Err(TransferError::Duplicate { duplicate_of })) => nat_to_block_index(duplicate_of),
+        Ok(Err(TransferError::TooOld)) => Err(TransferFundsError::Unknown(
+            NervousSystemError::new_with_message(
+                "'icrc1_transfer' of the ledger canister failed. Error: TooOld",
+            ),
+        )),
+        Ok(Err(err)) => Err(TransferFundsError::Rejected(
+            NervousSystemError::new_with_message(format!(
+                "'icrc1_transfer' of the ledger canister failed. Error: {:?}",
+                err
+            )),
+        )),
+        Err((code, msg)) => {
+            let err = NervousSystemError::new_with_message(format!(
+                "Error calling method 'icrc1_transfer' of the ledger canister. Code: {:?}. Message: {}",
+                code, msg
+            ));
+            match code {
+                Some(3) | Some(4) | Some(5) => Err(TransferFundsError::Rejected(err)),
+                _ => Err(TransferFundsError::Unknown(err)),
+            }
+        }
+    }
+}
+
 /// A trait defining common patterns for accessing the Ledger canister.
 #[async_trait]
 pub trait IcpLedger: Send + Sync {
@@ -105,6 +206,35 @@ impl ICRC1Ledger for IcpLedgerCanister {
         .await
     }
 
+    async fn transfer_funds_idempotently(
+        &self,
+        amount_e8s: u64,
+        fee_e8s: u64,
+        from_subaccount: Option<Subaccount>,
+        to: Account,
+        memo: u64,
+        created_at_time_nanos: u64,
+    ) -> Result<BlockIndex, TransferFundsError> {
+        // Unlike 'send_pb', 'icrc1_transfer' reports duplicates with the index
+        // of the original block.
+        let result: Result<Result<Nat, TransferError>, (Option<i32>, String)> = call(
+            self.id,
+            "icrc1_transfer",
+            candid_one,
+            TransferArg {
+                from_subaccount,
+                to,
+                fee: Some(Nat::from(fee_e8s)),
+                created_at_time: Some(created_at_time_nanos),
+                memo: Some(Icrc1Memo::from(memo)),
+                amount: Nat::from(amount_e8s),
+            },
+        )
+        .await;
+
+        icrc1_transfer_result_to_block_index(result)
+    }
+
     async fn total_supply(&self) -> Result<Tokens, NervousSystemError> {
         <IcpLedgerCanister as IcpLedger>::total_supply(self).await
     }
@@ -7940,6 +7940,10 @@ async fn fetch_swap_background_information(
         icp_ledger_canister_id: _,
         transaction_fee_e8s: _,
         neuron_minimum_stake_e8s: _,
+        restricted_countries: _,
+        confirmation_text_sha256: _,
+        allowed_participant_principal_ids: _,
+        follow_up_sale_round: _,
     } = swap_init;
 
     // Extract fields from list_sns_canisters_response.
@@ -7953,6 +7957,7 @@ async fn fetch_swap_background_information(
         index: sns_ledger_index_canister_id,
 
         swap: _,
+        sale_round_swaps: _,
     } = list_sns_canisters_response;
     let root_sns_root_canister_id =
         root_sns_root_canister_id.expect("`root` field in ListSnsCanistersResponse is empty.");
@@ -377,6 +377,11 @@ impl Environment for FakeDriver {
                         // Similar to NNS, but different.
                         transaction_fee_e8s: Some(12_345),
                         neuron_minimum_stake_e8s: Some(123_456_789),
+
+                        restricted_countries: None,
+                        confirmation_text_sha256: None,
+                        allowed_participant_principal_ids: vec![],
+                        follow_up_sale_round: None,
                     }),
                     ..Default::default() // Not realistic, but sufficient for tests.
                 }),
@@ -399,6 +404,7 @@ impl Environment for FakeDriver {
                 dapps: vec![*DAPP_CANISTER_ID],
                 archives: vec![*SNS_LEDGER_ARCHIVE_CANISTER_ID],
                 index: Some(*SNS_LEDGER_INDEX_CANISTER_ID),
+                sale_round_swaps: vec![],
             })
             .unwrap());
         }
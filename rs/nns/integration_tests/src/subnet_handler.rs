@@ -64,6 +64,9 @@ fn test_submit_and_accept_update_subnet_proposal() {
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
+                adaptive_notary_delay: None,
+                max_ingress_messages_per_canister_per_block: 0,
+                max_ingress_messages_per_sender_per_block: 0,
             };
 
             let key = make_subnet_record_key(subnet_id);
@@ -89,6 +92,8 @@ fn test_submit_and_accept_update_subnet_proposal() {
                 subnet_id,
                 max_ingress_bytes_per_message: Some(10 * 1024 * 1024),
                 max_ingress_messages_per_block: None,
+                max_ingress_messages_per_canister_per_block: None,
+                max_ingress_messages_per_sender_per_block: None,
                 max_block_payload_size: None,
                 unit_delay_millis: None,
                 initial_notary_delay_millis: None,
@@ -116,6 +121,8 @@ fn test_submit_and_accept_update_subnet_proposal() {
                 max_number_of_canisters: Some(200),
                 ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
                 ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
+                adaptive_notary_delay: None,
+                disable_adaptive_notary_delay: None,
             };
 
             let proposal_id: ProposalId = submit_external_update_proposal(
@@ -185,6 +192,9 @@ fn test_submit_and_accept_update_subnet_proposal() {
                     ssh_readonly_access: vec!["pub_key_0".to_string()],
                     ssh_backup_access: vec!["pub_key_1".to_string()],
                     ecdsa_config: None,
+                    adaptive_notary_delay: None,
+                    max_ingress_messages_per_canister_per_block: 0,
+                    max_ingress_messages_per_sender_per_block: 0,
                 }
             );
             Ok(())
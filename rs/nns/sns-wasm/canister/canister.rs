@@ -114,6 +114,7 @@ impl CanisterApi for CanisterApiImpl {
             compute_allocation: None,
             memory_allocation: None,
             query_allocation: None,
+            wasm_module_hash: None,
         };
         let install_res: Result<(), (Option<i32>, String)> = dfn_core::call(
             CanisterId::ic_00(),
@@ -143,6 +144,9 @@ impl CanisterApi for CanisterApiImpl {
                 compute_allocation: None,
                 memory_allocation: None,
                 freezing_threshold: None,
+                ingress_priority_fee: None,
+                wasm_memory_limit: None,
+                reserved_cycles_limit: None,
             },
         };
 
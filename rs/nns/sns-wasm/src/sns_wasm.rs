@@ -16,6 +16,7 @@ use candid::Encode;
 use dfn_core::println;
 use ic_base_types::{CanisterId, PrincipalId};
 use ic_cdk::api::stable::StableMemory;
+use ic_icrc1_ledger::LedgerArgument;
 use ic_nns_constants::{GOVERNANCE_CANISTER_ID, ROOT_CANISTER_ID};
 use ic_sns_governance::pb::v1::governance::Version;
 use ic_sns_init::SnsCanisterInitPayloads;
@@ -635,7 +636,7 @@ where
                 canister_api.install_wasm(
                     CanisterId::new(canisters.ledger.unwrap()).unwrap(),
                     latest_wasms.ledger,
-                    Encode!(&init_payloads.ledger).unwrap(),
+                    Encode!(&LedgerArgument::Init(init_payloads.ledger)).unwrap(),
                 ),
                 canister_api.install_wasm(
                     CanisterId::new(canisters.index.unwrap()).unwrap(),
@@ -1098,7 +1099,7 @@ mod test {
     use candid::{Decode, Encode};
     use ic_base_types::PrincipalId;
     use ic_crypto_sha::Sha256;
-    use ic_icrc1_ledger::InitArgs as LedgerInitArgs;
+    use ic_icrc1_ledger::{InitArgs as LedgerInitArgs, LedgerArgument};
     use ic_nns_constants::{GOVERNANCE_CANISTER_ID, ROOT_CANISTER_ID};
     use ic_sns_init::pb::v1::SnsInitPayload;
     use ic_test_utilities::types::ids::{canister_test_id, subnet_test_id};
@@ -2314,7 +2315,10 @@ mod test {
             canister_api.install_wasm_calls.lock().unwrap().remove(0);
         assert_eq!(ledger_canister, ledger_id);
         assert_eq!(ledger_wasm, vec![0, 97, 115, 109, 1, 0, 0, 2]);
-        assert_ledger_init_args_eq(Decode!(&ledger_init_args, LedgerInitArgs).unwrap(), ledger);
+        match Decode!(&ledger_init_args, LedgerArgument).unwrap() {
+            LedgerArgument::Init(args) => assert_ledger_init_args_eq(args, ledger),
+            LedgerArgument::Upgrade(_) => panic!("the ledger was installed with upgrade args"),
+        }
 
         let set_controllers_calls = &*canister_api.set_controllers_calls.lock().unwrap();
 
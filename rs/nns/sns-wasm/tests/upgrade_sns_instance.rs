@@ -16,6 +16,7 @@ use crate::common::EXPECTED_SNS_CREATION_FEE;
 use ic_ic00_types::CanisterInstallMode;
 use ic_icrc1::endpoints::{NumTokens, TransferArg, TransferError};
 use ic_icrc1::Account;
+use ic_icrc1_ledger::LedgerArgument;
 use ic_sns_governance::pb::v1::governance::{Mode, Version};
 use ic_sns_governance::pb::v1::proposal::Action;
 use ic_sns_governance::pb::v1::ProposalDecisionStatus;
@@ -379,7 +380,7 @@ fn upgrade_archive_sns_canister_via_sns_wasms() {
     install_code(
         ledger,
         wasm_for_type(&SnsCanisterType::Ledger),
-        Encode!(&init_payloads.ledger).unwrap(),
+        Encode!(&LedgerArgument::Init(init_payloads.ledger)).unwrap(),
     );
     install_code(
         swap,
@@ -196,7 +196,16 @@ pub fn set_controllers(
         candid_one,
         UpdateSettingsArgs {
             canister_id: target.into(),
-            settings: CanisterSettingsArgs::new(None, Some(controllers), None, None, None),
+            settings: CanisterSettingsArgs::new(
+                None,
+                Some(controllers),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ),
         },
         sender,
     )
@@ -305,6 +305,9 @@ impl SubnetConfig {
             ssh_readonly_access: self.ssh_readonly_access,
             ssh_backup_access: self.ssh_backup_access,
             ecdsa_config: self.ecdsa_config,
+            adaptive_notary_delay: None,
+            max_ingress_messages_per_canister_per_block: 0,
+            max_ingress_messages_per_sender_per_block: 0,
         };
 
         let dkg_dealing_encryption_pubkeys: BTreeMap<_, _> = initialized_nodes
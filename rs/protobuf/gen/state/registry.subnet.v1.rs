@@ -76,6 +76,21 @@ pub struct SubnetRecord {
     /// If a removed key is not held by another subnet, it will be lost.
     #[prost(message, optional, tag = "27")]
     pub ecdsa_config: ::core::option::Option<EcdsaConfig>,
+    /// If set, the notary tunes its initial delay based on the recently observed
+    /// finalization latency, within the given bounds. If unset, the fixed
+    /// `initial_notary_delay_millis` is used.
+    #[prost(message, optional, tag = "28")]
+    pub adaptive_notary_delay: ::core::option::Option<AdaptiveNotaryDelayConfig>,
+    /// Max number of ingress messages per block addressed to a single canister.
+    ///
+    /// A value of 0 is equivalent to setting no limit.
+    #[prost(uint64, tag = "29")]
+    pub max_ingress_messages_per_canister_per_block: u64,
+    /// Max number of ingress messages per block sent by a single user.
+    ///
+    /// A value of 0 is equivalent to setting no limit.
+    #[prost(uint64, tag = "30")]
+    pub max_ingress_messages_per_sender_per_block: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -333,6 +348,17 @@ pub struct SubnetFeatures {
     #[prost(enumeration = "SevFeatureStatus", optional, tag = "7")]
     pub sev_status: ::core::option::Option<i32>,
 }
+/// Bounds for the adaptive initial notary delay.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AdaptiveNotaryDelayConfig {
+    /// Lower bound for the initial notary delay (in milliseconds).
+    #[prost(uint64, tag = "1")]
+    pub min_initial_notary_delay_millis: u64,
+    /// Upper bound for the initial notary delay (in milliseconds).
+    #[prost(uint64, tag = "2")]
+    pub max_initial_notary_delay_millis: u64,
+}
 /// Per subnet ECDSA configuration
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
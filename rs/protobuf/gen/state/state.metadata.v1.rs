@@ -261,6 +261,27 @@ pub struct BitcoinGetSuccessorsFollowUpResponses {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QueryStatsDeliveredEntry {
+    #[prost(message, optional, tag = "1")]
+    pub proposer: ::core::option::Option<super::super::super::types::v1::NodeId>,
+    #[prost(uint64, tag = "2")]
+    pub epoch: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QueryStatsAggregator {
+    /// The last query stats epoch delivered by each node.
+    #[prost(message, repeated, tag = "1")]
+    pub delivered: ::prost::alloc::vec::Vec<QueryStatsDeliveredEntry>,
+    /// The reports of epochs that have not been aggregated yet.
+    #[prost(message, repeated, tag = "2")]
+    pub received: ::prost::alloc::vec::Vec<super::super::super::types::v1::QueryStatsPayload>,
+    /// The latest epoch whose reports have been aggregated.
+    #[prost(uint64, optional, tag = "3")]
+    pub aggregated_epoch: ::core::option::Option<u64>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SystemMetadata {
     #[prost(message, optional, tag = "2")]
     pub prev_state_hash: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
@@ -307,6 +328,9 @@ pub struct SystemMetadata {
     #[prost(message, repeated, tag = "18")]
     pub bitcoin_get_successors_follow_up_responses:
         ::prost::alloc::vec::Vec<BitcoinGetSuccessorsFollowUpResponses>,
+    /// The query stats delivered through consensus that are being aggregated.
+    #[prost(message, optional, tag = "19")]
+    pub query_stats: ::core::option::Option<QueryStatsAggregator>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -107,7 +107,9 @@ use ic_types::{
 };
 use prost::Message;
 use registry_canister::mutations::common::decode_registry_value;
-use registry_canister::mutations::do_create_subnet::{EcdsaInitialConfig, EcdsaKeyRequest};
+use registry_canister::mutations::do_create_subnet::{
+    AdaptiveNotaryDelay, EcdsaInitialConfig, EcdsaKeyRequest,
+};
 use registry_canister::mutations::do_set_firewall_config::SetFirewallConfigPayload;
 use registry_canister::mutations::do_update_unassigned_nodes_config::UpdateUnassignedNodesConfigPayload;
 use registry_canister::mutations::firewall::{
@@ -1010,6 +1012,16 @@ struct ProposeToCreateSubnetCmd {
     /// Maximum number of ingress messages per block. This is a hard cap.
     pub max_ingress_messages_per_block: Option<u64>,
 
+    #[clap(long)]
+    /// Maximum number of ingress messages per block addressed to the same
+    /// canister. If not set, or set to 0, there is no limit.
+    pub max_ingress_messages_per_canister_per_block: Option<u64>,
+
+    #[clap(long)]
+    /// Maximum number of ingress messages per block sent by the same user. If
+    /// not set, or set to 0, there is no limit.
+    pub max_ingress_messages_per_sender_per_block: Option<u64>,
+
     #[clap(long)]
     /// Maximum size in bytes ingress and xnet messages can occupy in a block.
     pub max_block_payload_size: Option<u64>,
@@ -1158,6 +1170,38 @@ struct ProposeToCreateSubnetCmd {
     /// subnet.
     #[clap(long)]
     pub max_number_of_canisters: Option<u64>,
+
+    /// The lower bound (in milliseconds) of the adaptive initial notary delay.
+    /// Must be set together with `--max-initial-notary-delay-millis`.
+    #[clap(long)]
+    pub min_initial_notary_delay_millis: Option<u64>,
+
+    /// The upper bound (in milliseconds) of the adaptive initial notary delay.
+    /// Must be set together with `--min-initial-notary-delay-millis`.
+    #[clap(long)]
+    pub max_initial_notary_delay_millis: Option<u64>,
+}
+
+/// Parse the bounds of the adaptive initial notary delay. Returns `None` if
+/// neither bound is set and panics if only one of them is.
+fn parse_adaptive_notary_delay_options(
+    min_initial_notary_delay_millis: &Option<u64>,
+    max_initial_notary_delay_millis: &Option<u64>,
+) -> Option<AdaptiveNotaryDelay> {
+    match (
+        min_initial_notary_delay_millis,
+        max_initial_notary_delay_millis,
+    ) {
+        (None, None) => None,
+        (Some(min), Some(max)) => Some(AdaptiveNotaryDelay {
+            min_initial_notary_delay_millis: *min,
+            max_initial_notary_delay_millis: *max,
+        }),
+        _ => panic!(
+            "--min-initial-notary-delay-millis and --max-initial-notary-delay-millis \
+            must be set together"
+        ),
+    }
 }
 
 /// Parse the options that are used to create EcdsaInitialConfig option
@@ -1247,6 +1291,12 @@ impl ProposalTitleAndPayload<CreateSubnetPayload> for ProposeToCreateSubnetCmd {
             ingress_bytes_per_block_soft_cap: self.ingress_bytes_per_block_soft_cap.unwrap(),
             max_ingress_bytes_per_message: self.max_ingress_bytes_per_message.unwrap(),
             max_ingress_messages_per_block: self.max_ingress_messages_per_block.unwrap(),
+            max_ingress_messages_per_canister_per_block: self
+                .max_ingress_messages_per_canister_per_block
+                .unwrap_or(0),
+            max_ingress_messages_per_sender_per_block: self
+                .max_ingress_messages_per_sender_per_block
+                .unwrap_or(0),
             max_block_payload_size: self.max_block_payload_size.unwrap(),
             replica_version_id: self
                 .replica_version_id
@@ -1282,6 +1332,10 @@ impl ProposalTitleAndPayload<CreateSubnetPayload> for ProposeToCreateSubnetCmd {
             features: SubnetFeatures::default(),
             ssh_readonly_access: self.ssh_readonly_access.clone(),
             ssh_backup_access: self.ssh_backup_access.clone(),
+            adaptive_notary_delay: parse_adaptive_notary_delay_options(
+                &self.min_initial_notary_delay_millis,
+                &self.max_initial_notary_delay_millis,
+            ),
             max_number_of_canisters: self.max_number_of_canisters.unwrap_or(0),
             ecdsa_config,
         }
@@ -1487,6 +1541,18 @@ struct ProposeToUpdateSubnetCmd {
     /// of this field.
     pub max_ingress_messages_per_block: Option<u64>,
 
+    #[clap(long)]
+    /// If set, the created proposal will contain a desired override of that
+    /// field to the value set. See `ProposeToCreateSubnetCmd` for the semantic
+    /// of this field.
+    pub max_ingress_messages_per_canister_per_block: Option<u64>,
+
+    #[clap(long)]
+    /// If set, the created proposal will contain a desired override of that
+    /// field to the value set. See `ProposeToCreateSubnetCmd` for the semantic
+    /// of this field.
+    pub max_ingress_messages_per_sender_per_block: Option<u64>,
+
     #[clap(long)]
     /// If set, the created proposal will contain a desired override of that
     /// field to the value set. See `ProposeToCreateSubnetCmd` for the semantic
@@ -1675,6 +1741,20 @@ struct ProposeToUpdateSubnetCmd {
     /// of this field.
     #[clap(long)]
     pub max_number_of_canisters: Option<u64>,
+
+    /// If set together with `--max-initial-notary-delay-millis`, the created
+    /// proposal enables the adaptive initial notary delay with these bounds.
+    #[clap(long)]
+    pub min_initial_notary_delay_millis: Option<u64>,
+
+    /// See `--min-initial-notary-delay-millis`.
+    #[clap(long)]
+    pub max_initial_notary_delay_millis: Option<u64>,
+
+    /// If set, the created proposal disables the adaptive initial notary
+    /// delay. Cannot be combined with `--min-initial-notary-delay-millis`.
+    #[clap(long)]
+    pub disable_adaptive_notary_delay: bool,
 }
 
 fn parse_ecdsa_keys_option(maybe_value: &Option<Vec<String>>) -> Vec<EcdsaKeyId> {
@@ -1790,6 +1870,10 @@ impl ProposalTitleAndPayload<UpdateSubnetPayload> for ProposeToUpdateSubnetCmd {
             subnet_id,
             max_ingress_bytes_per_message: self.max_ingress_bytes_per_message,
             max_ingress_messages_per_block: self.max_ingress_messages_per_block,
+            max_ingress_messages_per_canister_per_block: self
+                .max_ingress_messages_per_canister_per_block,
+            max_ingress_messages_per_sender_per_block: self
+                .max_ingress_messages_per_sender_per_block,
             max_block_payload_size: self.max_block_payload_size,
             unit_delay_millis: self.unit_delay_millis,
             initial_notary_delay_millis: self.initial_notary_delay_millis,
@@ -1819,6 +1903,11 @@ impl ProposalTitleAndPayload<UpdateSubnetPayload> for ProposeToUpdateSubnetCmd {
             ecdsa_key_signing_disable,
             ssh_readonly_access: self.ssh_readonly_access.clone(),
             ssh_backup_access: self.ssh_backup_access.clone(),
+            adaptive_notary_delay: parse_adaptive_notary_delay_options(
+                &self.min_initial_notary_delay_millis,
+                &self.max_initial_notary_delay_millis,
+            ),
+            disable_adaptive_notary_delay: self.disable_adaptive_notary_delay.then_some(true),
             max_number_of_canisters: self.max_number_of_canisters,
         }
     }
@@ -54,6 +54,21 @@ pub(crate) fn check_subnet_invariants(
             });
         }
 
+        if let Some(config) = &subnet_record.adaptive_notary_delay {
+            if config.min_initial_notary_delay_millis > config.max_initial_notary_delay_millis {
+                return Err(InvariantCheckError {
+                    msg: format!(
+                        "Subnet {:} has an adaptive notary delay lower bound of {}ms that \
+                        exceeds its upper bound of {}ms",
+                        subnet_id,
+                        config.min_initial_notary_delay_millis,
+                        config.max_initial_notary_delay_millis
+                    ),
+                    source: None,
+                });
+            }
+        }
+
         let num_nodes = subnet_record.membership.len();
         let mut subnet_members: HashSet<NodeId> = subnet_record
             .membership
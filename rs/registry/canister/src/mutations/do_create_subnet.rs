@@ -14,7 +14,7 @@ use serde::Serialize;
 
 use ic_base_types::{NodeId, PrincipalId, RegistryVersion, SubnetId};
 use ic_ic00_types::{EcdsaKeyId, SetupInitialDKGArgs, SetupInitialDKGResponse};
-use ic_protobuf::registry::subnet::v1::EcdsaConfig;
+use ic_protobuf::registry::subnet::v1::{AdaptiveNotaryDelayConfig, EcdsaConfig};
 use ic_protobuf::registry::{
     node::v1::NodeRecord,
     subnet::v1::{CatchUpPackageContents, GossipConfig, SubnetRecord},
@@ -231,6 +231,12 @@ pub struct CreateSubnetPayload {
     pub ingress_bytes_per_block_soft_cap: u64,
     pub max_ingress_bytes_per_message: u64,
     pub max_ingress_messages_per_block: u64,
+    /// The maximum number of messages per block addressed to the same
+    /// canister, or 0 for no limit.
+    pub max_ingress_messages_per_canister_per_block: u64,
+    /// The maximum number of messages per block sent by the same user, or 0
+    /// for no limit.
+    pub max_ingress_messages_per_sender_per_block: u64,
     pub max_block_payload_size: u64,
     pub unit_delay_millis: u64,
     pub initial_notary_delay_millis: u64,
@@ -264,6 +270,28 @@ pub struct CreateSubnetPayload {
     pub ssh_backup_access: Vec<String>,
 
     pub ecdsa_config: Option<EcdsaInitialConfig>,
+
+    /// If set, the initial notary delay of the subnet adapts to the observed
+    /// finalization latency within these bounds.
+    pub adaptive_notary_delay: Option<AdaptiveNotaryDelay>,
+}
+
+/// The bounds of the adaptive initial notary delay of a subnet.
+///
+/// See `AdaptiveNotaryDelayConfig` in /rs/protobuf/def/registry/subnet/v1/subnet.proto.
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct AdaptiveNotaryDelay {
+    pub min_initial_notary_delay_millis: u64,
+    pub max_initial_notary_delay_millis: u64,
+}
+
+impl From<AdaptiveNotaryDelay> for AdaptiveNotaryDelayConfig {
+    fn from(val: AdaptiveNotaryDelay) -> Self {
+        Self {
+            min_initial_notary_delay_millis: val.min_initial_notary_delay_millis,
+            max_initial_notary_delay_millis: val.max_initial_notary_delay_millis,
+        }
+    }
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
@@ -340,6 +368,11 @@ impl From<CreateSubnetPayload> for SubnetRecord {
             ssh_readonly_access: val.ssh_readonly_access,
             ssh_backup_access: val.ssh_backup_access,
             ecdsa_config: val.ecdsa_config.map(|x| x.into()),
+            adaptive_notary_delay: val.adaptive_notary_delay.map(|x| x.into()),
+            max_ingress_messages_per_canister_per_block: val
+                .max_ingress_messages_per_canister_per_block,
+            max_ingress_messages_per_sender_per_block: val
+                .max_ingress_messages_per_sender_per_block,
         }
     }
 }
@@ -1,4 +1,8 @@
-use crate::{common::LOG_PREFIX, mutations::common::encode_or_panic, registry::Registry};
+use crate::{
+    common::LOG_PREFIX,
+    mutations::{common::encode_or_panic, do_create_subnet::AdaptiveNotaryDelay},
+    registry::Registry,
+};
 use std::collections::HashSet;
 
 use candid::{CandidType, Deserialize};
@@ -161,6 +165,8 @@ pub struct UpdateSubnetPayload {
 
     pub max_ingress_bytes_per_message: Option<u64>,
     pub max_ingress_messages_per_block: Option<u64>,
+    pub max_ingress_messages_per_canister_per_block: Option<u64>,
+    pub max_ingress_messages_per_sender_per_block: Option<u64>,
     pub max_block_payload_size: Option<u64>,
     pub unit_delay_millis: Option<u64>,
     pub initial_notary_delay_millis: Option<u64>,
@@ -200,6 +206,13 @@ pub struct UpdateSubnetPayload {
 
     pub ssh_readonly_access: Option<Vec<String>>,
     pub ssh_backup_access: Option<Vec<String>>,
+
+    /// Enables the adaptive initial notary delay with the given bounds.
+    pub adaptive_notary_delay: Option<AdaptiveNotaryDelay>,
+    /// If `Some(true)`, disables the adaptive initial notary delay, so that
+    /// `initial_notary_delay_millis` is used again. Cannot be combined with
+    /// `adaptive_notary_delay`.
+    pub disable_adaptive_notary_delay: Option<bool>,
 }
 
 // Sets the value of a field in record `a` if the provided value `b` is not
@@ -259,6 +272,8 @@ fn merge_subnet_record(
         subnet_id: _subnet_id,
         max_ingress_bytes_per_message,
         max_ingress_messages_per_block,
+        max_ingress_messages_per_canister_per_block,
+        max_ingress_messages_per_sender_per_block,
         max_block_payload_size,
         unit_delay_millis,
         initial_notary_delay_millis,
@@ -286,10 +301,14 @@ fn merge_subnet_record(
         max_number_of_canisters,
         ssh_readonly_access,
         ssh_backup_access,
+        adaptive_notary_delay,
+        disable_adaptive_notary_delay,
     } = payload;
 
     maybe_set!(subnet_record, max_ingress_bytes_per_message);
     maybe_set!(subnet_record, max_ingress_messages_per_block);
+    maybe_set!(subnet_record, max_ingress_messages_per_canister_per_block);
+    maybe_set!(subnet_record, max_ingress_messages_per_sender_per_block);
     maybe_set!(subnet_record, max_block_payload_size);
     maybe_set!(subnet_record, unit_delay_millis);
     maybe_set!(subnet_record, initial_notary_delay_millis);
@@ -334,6 +353,15 @@ fn merge_subnet_record(
     maybe_set!(subnet_record, ssh_readonly_access);
     maybe_set!(subnet_record, ssh_backup_access);
 
+    if disable_adaptive_notary_delay == Some(true) {
+        assert!(
+            adaptive_notary_delay.is_none(),
+            "Cannot both set and disable the adaptive notary delay."
+        );
+        subnet_record.adaptive_notary_delay = None;
+    }
+    maybe_set_option!(subnet_record, adaptive_notary_delay);
+
     subnet_record
 }
 
@@ -346,7 +374,9 @@ mod tests {
     };
     use ic_ic00_types::{EcdsaCurve, EcdsaKeyId};
     use ic_nervous_system_common_test_keys::{TEST_USER1_PRINCIPAL, TEST_USER2_PRINCIPAL};
-    use ic_protobuf::registry::subnet::v1::{GossipConfig, SubnetRecord};
+    use ic_protobuf::registry::subnet::v1::{
+        AdaptiveNotaryDelayConfig, GossipConfig, SubnetRecord,
+    };
     use ic_registry_subnet_features::DEFAULT_ECDSA_MAX_QUEUE_SIZE;
     use ic_registry_subnet_type::SubnetType;
     use ic_test_utilities::types::ids::subnet_test_id;
@@ -374,6 +404,8 @@ mod tests {
             ),
             max_ingress_bytes_per_message: Some(256),
             max_ingress_messages_per_block: Some(256),
+            max_ingress_messages_per_canister_per_block: None,
+            max_ingress_messages_per_sender_per_block: None,
             max_block_payload_size: Some(200),
             unit_delay_millis: Some(300),
             initial_notary_delay_millis: Some(200),
@@ -412,6 +444,8 @@ mod tests {
             max_number_of_canisters: Some(10),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
+            adaptive_notary_delay: None,
+            disable_adaptive_notary_delay: None,
         }
     }
 
@@ -420,6 +454,8 @@ mod tests {
             subnet_id,
             max_ingress_bytes_per_message: None,
             max_ingress_messages_per_block: None,
+            max_ingress_messages_per_canister_per_block: None,
+            max_ingress_messages_per_sender_per_block: None,
             max_block_payload_size: None,
             unit_delay_millis: None,
             initial_notary_delay_millis: None,
@@ -447,6 +483,8 @@ mod tests {
             max_number_of_canisters: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
+            adaptive_notary_delay: None,
+            disable_adaptive_notary_delay: None,
         }
     }
 
@@ -483,6 +521,9 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            adaptive_notary_delay: None,
+            max_ingress_messages_per_canister_per_block: 0,
+            max_ingress_messages_per_sender_per_block: 0,
         };
 
         let payload = UpdateSubnetPayload {
@@ -494,6 +535,8 @@ mod tests {
             ),
             max_ingress_bytes_per_message: Some(256),
             max_ingress_messages_per_block: Some(256),
+            max_ingress_messages_per_canister_per_block: Some(16),
+            max_ingress_messages_per_sender_per_block: Some(8),
             max_block_payload_size: Some(200),
             unit_delay_millis: Some(300),
             initial_notary_delay_millis: Some(200),
@@ -532,6 +575,11 @@ mod tests {
             max_number_of_canisters: Some(10),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
+            adaptive_notary_delay: Some(AdaptiveNotaryDelay {
+                min_initial_notary_delay_millis: 100,
+                max_initial_notary_delay_millis: 300,
+            }),
+            disable_adaptive_notary_delay: None,
         };
 
         assert_eq!(
@@ -584,6 +632,12 @@ mod tests {
                 max_number_of_canisters: 10,
                 ssh_readonly_access: vec!["pub_key_0".to_string()],
                 ssh_backup_access: vec!["pub_key_1".to_string()],
+                adaptive_notary_delay: Some(AdaptiveNotaryDelayConfig {
+                    min_initial_notary_delay_millis: 100,
+                    max_initial_notary_delay_millis: 300,
+                }),
+                max_ingress_messages_per_canister_per_block: 16,
+                max_ingress_messages_per_sender_per_block: 8,
             }
         );
     }
@@ -621,6 +675,9 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            adaptive_notary_delay: None,
+            max_ingress_messages_per_canister_per_block: 0,
+            max_ingress_messages_per_sender_per_block: 0,
         };
 
         let payload = UpdateSubnetPayload {
@@ -632,6 +689,8 @@ mod tests {
             ),
             max_ingress_bytes_per_message: None,
             max_ingress_messages_per_block: None,
+            max_ingress_messages_per_canister_per_block: None,
+            max_ingress_messages_per_sender_per_block: None,
             max_block_payload_size: None,
             unit_delay_millis: Some(100),
             initial_notary_delay_millis: None,
@@ -659,6 +718,8 @@ mod tests {
             max_number_of_canisters: Some(50),
             ssh_readonly_access: None,
             ssh_backup_access: None,
+            adaptive_notary_delay: None,
+            disable_adaptive_notary_delay: None,
         };
 
         assert_eq!(
@@ -694,6 +755,9 @@ mod tests {
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
+                adaptive_notary_delay: None,
+                max_ingress_messages_per_canister_per_block: 0,
+                max_ingress_messages_per_sender_per_block: 0,
             }
         );
     }
@@ -718,6 +782,44 @@ mod tests {
         merge_subnet_record(subnet_record, payload);
     }
 
+    #[test]
+    fn can_disable_adaptive_notary_delay() {
+        let subnet_record = SubnetRecord {
+            gossip_config: Some(build_default_gossip_config()),
+            adaptive_notary_delay: Some(AdaptiveNotaryDelayConfig {
+                min_initial_notary_delay_millis: 100,
+                max_initial_notary_delay_millis: 300,
+            }),
+            ..Default::default()
+        };
+
+        let mut payload = make_empty_update_payload(subnet_test_id(1000));
+        payload.disable_adaptive_notary_delay = Some(true);
+
+        assert_eq!(
+            merge_subnet_record(subnet_record, payload).adaptive_notary_delay,
+            None
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot both set and disable the adaptive notary delay.")]
+    fn panic_on_setting_and_disabling_adaptive_notary_delay() {
+        let subnet_record = SubnetRecord {
+            gossip_config: Some(build_default_gossip_config()),
+            ..Default::default()
+        };
+
+        let mut payload = make_empty_update_payload(subnet_test_id(1000));
+        payload.adaptive_notary_delay = Some(AdaptiveNotaryDelay {
+            min_initial_notary_delay_millis: 100,
+            max_initial_notary_delay_millis: 300,
+        });
+        payload.disable_adaptive_notary_delay = Some(true);
+
+        merge_subnet_record(subnet_record, payload);
+    }
+
     #[test]
     #[should_panic]
     // This test confirms that if `set_gossip_config_to_default` = false and the
@@ -746,6 +848,9 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            adaptive_notary_delay: None,
+            max_ingress_messages_per_canister_per_block: 0,
+            max_ingress_messages_per_sender_per_block: 0,
         };
 
         let payload = UpdateSubnetPayload {
@@ -757,6 +862,8 @@ mod tests {
             ),
             max_ingress_bytes_per_message: None,
             max_ingress_messages_per_block: None,
+            max_ingress_messages_per_canister_per_block: None,
+            max_ingress_messages_per_sender_per_block: None,
             max_block_payload_size: None,
             unit_delay_millis: Some(100),
             initial_notary_delay_millis: None,
@@ -784,6 +891,8 @@ mod tests {
             max_number_of_canisters: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
+            adaptive_notary_delay: None,
+            disable_adaptive_notary_delay: None,
         };
 
         merge_subnet_record(subnet_record, payload);
@@ -813,6 +922,9 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            adaptive_notary_delay: None,
+            max_ingress_messages_per_canister_per_block: 0,
+            max_ingress_messages_per_sender_per_block: 0,
         };
 
         let payload = UpdateSubnetPayload {
@@ -824,6 +936,8 @@ mod tests {
             ),
             max_ingress_bytes_per_message: None,
             max_ingress_messages_per_block: None,
+            max_ingress_messages_per_canister_per_block: None,
+            max_ingress_messages_per_sender_per_block: None,
             max_block_payload_size: None,
             unit_delay_millis: None,
             initial_notary_delay_millis: None,
@@ -851,6 +965,8 @@ mod tests {
             max_number_of_canisters: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
+            adaptive_notary_delay: None,
+            disable_adaptive_notary_delay: None,
         };
 
         assert_eq!(
@@ -886,6 +1002,9 @@ mod tests {
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
+                adaptive_notary_delay: None,
+                max_ingress_messages_per_canister_per_block: 0,
+                max_ingress_messages_per_sender_per_block: 0,
             }
         );
     }
@@ -923,6 +1042,9 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            adaptive_notary_delay: None,
+            max_ingress_messages_per_canister_per_block: 0,
+            max_ingress_messages_per_sender_per_block: 0,
         };
 
         let payload = UpdateSubnetPayload {
@@ -934,6 +1056,8 @@ mod tests {
             ),
             max_ingress_bytes_per_message: None,
             max_ingress_messages_per_block: None,
+            max_ingress_messages_per_canister_per_block: None,
+            max_ingress_messages_per_sender_per_block: None,
             max_block_payload_size: None,
             unit_delay_millis: Some(100),
             initial_notary_delay_millis: None,
@@ -961,6 +1085,8 @@ mod tests {
             max_number_of_canisters: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
+            adaptive_notary_delay: None,
+            disable_adaptive_notary_delay: None,
         };
 
         assert_eq!(
@@ -996,6 +1122,9 @@ mod tests {
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
+                adaptive_notary_delay: None,
+                max_ingress_messages_per_canister_per_block: 0,
+                max_ingress_messages_per_sender_per_block: 0,
             }
         );
     }
@@ -368,6 +368,8 @@ fn make_create_subnet_payload(node_ids: Vec<NodeId>) -> CreateSubnetPayload {
         ingress_bytes_per_block_soft_cap: 2 * 1024 * 1024,
         max_ingress_bytes_per_message: 60 * 1024 * 1024,
         max_ingress_messages_per_block: 1000,
+        max_ingress_messages_per_canister_per_block: 0,
+        max_ingress_messages_per_sender_per_block: 0,
         max_block_payload_size: 4 * 1024 * 1024,
         unit_delay_millis: 500,
         initial_notary_delay_millis: 1500,
@@ -392,6 +394,7 @@ fn make_create_subnet_payload(node_ids: Vec<NodeId>) -> CreateSubnetPayload {
         max_number_of_canisters: 0,
         ssh_readonly_access: vec![],
         ssh_backup_access: vec![],
+        adaptive_notary_delay: None,
         ecdsa_config: None,
     }
 }
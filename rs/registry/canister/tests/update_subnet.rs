@@ -65,6 +65,8 @@ fn test_the_anonymous_user_cannot_update_a_subnets_configuration() {
             subnet_id,
             max_ingress_bytes_per_message: None,
             max_ingress_messages_per_block: None,
+            max_ingress_messages_per_canister_per_block: None,
+            max_ingress_messages_per_sender_per_block: None,
             max_block_payload_size: None,
             unit_delay_millis: None,
             initial_notary_delay_millis: None,
@@ -92,6 +94,8 @@ fn test_the_anonymous_user_cannot_update_a_subnets_configuration() {
             max_number_of_canisters: Some(10),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
+            adaptive_notary_delay: None,
+            disable_adaptive_notary_delay: None,
         };
 
         // The anonymous end-user tries to update a subnet's configuration, bypassing
@@ -161,6 +165,9 @@ fn test_a_canister_other_than_the_governance_canister_cannot_update_a_subnets_co
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            adaptive_notary_delay: None,
+            max_ingress_messages_per_canister_per_block: 0,
+            max_ingress_messages_per_sender_per_block: 0,
         };
 
         // An attacker got a canister that is trying to pass for the governance
@@ -192,6 +199,8 @@ fn test_a_canister_other_than_the_governance_canister_cannot_update_a_subnets_co
             subnet_id,
             max_ingress_bytes_per_message: None,
             max_ingress_messages_per_block: None,
+            max_ingress_messages_per_canister_per_block: None,
+            max_ingress_messages_per_sender_per_block: None,
             max_block_payload_size: None,
             unit_delay_millis: None,
             initial_notary_delay_millis: None,
@@ -219,6 +228,8 @@ fn test_a_canister_other_than_the_governance_canister_cannot_update_a_subnets_co
             max_number_of_canisters: Some(100),
             ssh_readonly_access: None,
             ssh_backup_access: None,
+            adaptive_notary_delay: None,
+            disable_adaptive_notary_delay: None,
         };
 
         // The attacker canister tries to update the subnet's configuration, pretending
@@ -287,6 +298,9 @@ fn test_the_governance_canister_can_update_a_subnets_configuration() {
                             ssh_readonly_access: vec![],
                             ssh_backup_access: vec![],
                             ecdsa_config: None,
+                            adaptive_notary_delay: None,
+                            max_ingress_messages_per_canister_per_block: 0,
+                            max_ingress_messages_per_sender_per_block: 0,
                         }),
                     )],
                     preconditions: vec![],
@@ -309,6 +323,8 @@ fn test_the_governance_canister_can_update_a_subnets_configuration() {
             subnet_id,
             max_ingress_bytes_per_message: None,
             max_ingress_messages_per_block: None,
+            max_ingress_messages_per_canister_per_block: None,
+            max_ingress_messages_per_sender_per_block: None,
             max_block_payload_size: None,
             unit_delay_millis: Some(100),
             initial_notary_delay_millis: None,
@@ -336,6 +352,8 @@ fn test_the_governance_canister_can_update_a_subnets_configuration() {
             max_number_of_canisters: Some(42),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
+            adaptive_notary_delay: None,
+            disable_adaptive_notary_delay: None,
         };
 
         // Attempt to update the subnet's configuration. Since the update happens from
@@ -389,6 +407,9 @@ fn test_the_governance_canister_can_update_a_subnets_configuration() {
                 ssh_readonly_access: vec!["pub_key_0".to_string()],
                 ssh_backup_access: vec!["pub_key_1".to_string()],
                 ecdsa_config: None,
+                adaptive_notary_delay: None,
+                max_ingress_messages_per_canister_per_block: 0,
+                max_ingress_messages_per_sender_per_block: 0,
             }
         );
 
@@ -442,6 +463,9 @@ fn test_subnets_configuration_ecdsa_fields_are_updated_correctly() {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            adaptive_notary_delay: None,
+            max_ingress_messages_per_canister_per_block: 0,
+            max_ingress_messages_per_sender_per_block: 0,
         };
 
         // Just create the registry canister and wait until the subnet_handler ID is
@@ -627,6 +651,8 @@ fn empty_update_subnet_payload(subnet_id: SubnetId) -> UpdateSubnetPayload {
         subnet_id,
         max_ingress_bytes_per_message: None,
         max_ingress_messages_per_block: None,
+        max_ingress_messages_per_canister_per_block: None,
+        max_ingress_messages_per_sender_per_block: None,
         max_block_payload_size: None,
         unit_delay_millis: None,
         initial_notary_delay_millis: None,
@@ -651,6 +677,8 @@ fn empty_update_subnet_payload(subnet_id: SubnetId) -> UpdateSubnetPayload {
         max_number_of_canisters: None,
         ssh_readonly_access: None,
         ssh_backup_access: None,
+        adaptive_notary_delay: None,
+        disable_adaptive_notary_delay: None,
         ecdsa_config: None,
         ecdsa_key_signing_enable: None,
         ecdsa_key_signing_disable: None,
@@ -21,6 +21,16 @@ use std::time::Duration;
 pub struct NotarizationDelaySettings {
     pub unit_delay: Duration,
     pub initial_notary_delay: Duration,
+    /// If set, the initial notary delay may be adapted to the observed
+    /// finalization latency within these bounds.
+    pub adaptive_notary_delay_bounds: Option<AdaptiveNotaryDelayBounds>,
+}
+
+/// Lower and upper bound for the adaptive initial notary delay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AdaptiveNotaryDelayBounds {
+    pub min_initial_notary_delay: Duration,
+    pub max_initial_notary_delay: Duration,
 }
 
 pub struct IngressMessageSettings {
@@ -30,6 +40,12 @@ pub struct IngressMessageSettings {
     /// Maximum number of messages per block. This is a hard cap, which means
     /// blocks will never have more than this number of messages.
     pub max_ingress_messages_per_block: usize,
+    /// Maximum number of messages per block addressed to the same canister.
+    /// A value of 0 means no limit.
+    pub max_ingress_messages_per_canister_per_block: usize,
+    /// Maximum number of messages per block sent by the same user. A value of
+    /// 0 means no limit.
+    pub max_ingress_messages_per_sender_per_block: usize,
 }
 
 /// A helper trait that wraps a RegistryClient and provides utility methods for
@@ -225,6 +241,12 @@ impl<T: RegistryClient + ?Sized> SubnetRegistry for T {
                 IngressMessageSettings {
                     max_ingress_bytes_per_message: subnet.max_ingress_bytes_per_message as usize,
                     max_ingress_messages_per_block: subnet.max_ingress_messages_per_block as usize,
+                    max_ingress_messages_per_canister_per_block: subnet
+                        .max_ingress_messages_per_canister_per_block
+                        as usize,
+                    max_ingress_messages_per_sender_per_block: subnet
+                        .max_ingress_messages_per_sender_per_block
+                        as usize,
                 }
             }),
         )
@@ -273,6 +295,16 @@ impl<T: RegistryClient + ?Sized> SubnetRegistry for T {
                 NotarizationDelaySettings {
                     unit_delay: Duration::from_millis(subnet.unit_delay_millis),
                     initial_notary_delay: Duration::from_millis(subnet.initial_notary_delay_millis),
+                    adaptive_notary_delay_bounds: subnet.adaptive_notary_delay.map(|config| {
+                        AdaptiveNotaryDelayBounds {
+                            min_initial_notary_delay: Duration::from_millis(
+                                config.min_initial_notary_delay_millis,
+                            ),
+                            max_initial_notary_delay: Duration::from_millis(
+                                config.max_initial_notary_delay_millis,
+                            ),
+                        }
+                    }),
                 }
             }),
         )
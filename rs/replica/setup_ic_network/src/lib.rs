@@ -16,7 +16,10 @@ use ic_config::{
 };
 use ic_consensus::{
     canister_http, certification,
-    consensus::{pool_reader::PoolReader, ConsensusCrypto, Membership},
+    consensus::{
+        pool_reader::PoolReader, ConsensusCrypto, ConsensusTimeline, Membership,
+        CONSENSUS_TIMELINE_CAPACITY,
+    },
     dkg, ecdsa,
 };
 use ic_crypto_tls_interfaces::TlsHandshake;
@@ -28,6 +31,7 @@ use ic_interfaces::{
     crypto::{Crypto, IngressSigVerifier},
     execution_environment::IngressHistoryReader,
     messaging::{MessageRouting, XNetPayloadBuilder},
+    query_stats::QueryStatsPayloadBuilder,
     self_validating_payload::SelfValidatingPayloadBuilder,
     time_source::SysTimeSource,
 };
@@ -77,6 +81,8 @@ pub struct ArtifactPools {
     pub dkg_pool: Arc<RwLock<DkgPoolImpl>>,
     pub ecdsa_pool: Arc<RwLock<EcdsaPoolImpl>>,
     pub canister_http_pool: Arc<RwLock<CanisterHttpPoolImpl>>,
+    /// Per-height timestamps of the consensus phases, recorded by consensus.
+    pub consensus_timeline: Arc<ConsensusTimeline>,
 }
 
 /// The function constructs a P2P instance. Currently, it constructs all the
@@ -104,6 +110,7 @@ pub fn create_networking_stack(
     state_sync_client: P2PStateSyncClient,
     xnet_payload_builder: Arc<dyn XNetPayloadBuilder>,
     self_validating_payload_builder: Arc<dyn SelfValidatingPayloadBuilder>,
+    query_stats_payload_builder: Arc<dyn QueryStatsPayloadBuilder>,
     message_router: Arc<dyn MessageRouting>,
     crypto: Arc<dyn Crypto + Send + Sync>,
     consensus_crypto: Arc<dyn ConsensusCrypto + Send + Sync>,
@@ -137,6 +144,7 @@ pub fn create_networking_stack(
         state_sync_client,
         xnet_payload_builder,
         self_validating_payload_builder,
+        query_stats_payload_builder,
         message_router,
         ingress_history_reader,
         artifact_pools,
@@ -209,6 +217,7 @@ fn setup_artifact_manager(
     state_sync_client: P2PStateSyncClient,
     xnet_payload_builder: Arc<dyn XNetPayloadBuilder>,
     self_validating_payload_builder: Arc<dyn SelfValidatingPayloadBuilder>,
+    query_stats_payload_builder: Arc<dyn QueryStatsPayloadBuilder>,
     message_router: Arc<dyn MessageRouting>,
     ingress_history_reader: Box<dyn IngressHistoryReader>,
     artifact_pools: &ArtifactPools,
@@ -316,6 +325,7 @@ fn setup_artifact_manager(
                     Arc::clone(&xnet_payload_builder) as Arc<_>,
                     Arc::clone(&self_validating_payload_builder) as Arc<_>,
                     Arc::clone(&canister_http_payload_builder) as Arc<_>,
+                    Arc::clone(&query_stats_payload_builder),
                     Arc::clone(&artifact_pools.dkg_pool) as Arc<_>,
                     Arc::clone(&artifact_pools.ecdsa_pool) as Arc<_>,
                     Arc::clone(&dkg_key_manager) as Arc<_>,
@@ -327,6 +337,7 @@ fn setup_artifact_manager(
                     replica_logger.clone(),
                     local_store_time_reader,
                     registry_poll_delay_duration_ms,
+                    Arc::clone(&artifact_pools.consensus_timeline),
                 )
             },
             Arc::clone(&time_source) as Arc<_>,
@@ -529,6 +540,7 @@ pub fn init_artifact_pools(
         dkg_pool,
         ecdsa_pool,
         canister_http_pool,
+        consensus_timeline: Arc::new(ConsensusTimeline::new(CONSENSUS_TIMELINE_CAPACITY)),
     }
 }
 
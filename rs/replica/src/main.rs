@@ -283,6 +283,7 @@ fn main() -> io::Result<()> {
         _p2p_thread_joiner,
         ingress_ingestion_service,
         consensus_pool_cache,
+        consensus_timeline,
         ingress_message_filter,
         _xnet_endpoint,
     ) = ic_replica::setup_p2p::construct_ic_stack(
@@ -319,6 +320,7 @@ fn main() -> io::Result<()> {
         root_subnet_id,
         logger.clone(),
         consensus_pool_cache,
+        consensus_timeline,
         subnet_type,
         malicious_behaviour.malicious_flags.clone(),
     );
@@ -4,8 +4,9 @@ use ic_config::{artifact_pool::ArtifactPoolConfig, subnet_config::SubnetConfig,
 use ic_consensus::certification::VerifierImpl;
 use ic_crypto::CryptoComponent;
 use ic_cycles_account_manager::CyclesAccountManager;
-use ic_execution_environment::ExecutionServices;
+use ic_execution_environment::{ExecutionServices, QueryStatsPayloadBuilderImpl};
 use ic_interfaces::{
+    consensus::ConsensusTimelineReader,
     consensus_pool::ConsensusPoolCache,
     execution_environment::{
         AnonymousQueryService, IngressFilterService, QueryExecutionService, QueryHandler,
@@ -54,6 +55,7 @@ pub fn construct_ic_stack(
     P2PThreadJoiner,
     IngressIngestionService,
     Arc<dyn ConsensusPoolCache>,
+    Arc<dyn ConsensusTimelineReader>,
     IngressFilterService,
     XNetEndpoint,
 )> {
@@ -145,6 +147,13 @@ pub fn construct_ic_stack(
         Arc::clone(&cycles_account_manager),
         Arc::clone(&state_manager) as Arc<_>,
     );
+    let query_stats_payload_builder = Arc::new(QueryStatsPayloadBuilderImpl::new(
+        Arc::clone(&execution_services.query_stats_collector),
+        Arc::clone(&state_manager) as Arc<_>,
+        node_id,
+        config.hypervisor.query_stats_epoch_length,
+        replica_logger.clone(),
+    ));
 
     let certified_stream_store: Arc<dyn CertifiedStreamStore> =
         Arc::clone(&state_manager) as Arc<_>;
@@ -247,6 +256,7 @@ pub fn construct_ic_stack(
         P2PStateSyncClient::Client(Arc::clone(&state_manager) as Arc<_>),
         xnet_payload_builder as Arc<_>,
         self_validating_payload_builder as Arc<_>,
+        query_stats_payload_builder as Arc<_>,
         message_router as Arc<_>,
         // TODO(SCL-213)
         Arc::clone(&crypto) as Arc<_>,
@@ -270,6 +280,7 @@ pub fn construct_ic_stack(
         p2p_runner,
         ingress_ingestion_service,
         artifact_pools.consensus_pool_cache,
+        artifact_pools.consensus_timeline as Arc<_>,
         execution_services.ingress_filter,
         xnet_endpoint,
     ))
@@ -403,6 +403,7 @@ where
             _,
             _,
             _,
+            _,
         ) = ic_replica::setup_p2p::construct_ic_stack(
             logger,
             tokio::runtime::Handle::current(),
@@ -64,6 +64,7 @@ pub fn push_response(
                 originator_reply_callback: callback_id,
                 refund: context.request.take_cycles(),
                 response_payload,
+                deadline: context.request.deadline,
             });
 
             Ok(())
@@ -106,6 +107,7 @@ pub fn push_response(
                 originator_reply_callback: callback_id,
                 refund: context.request.take_cycles(),
                 response_payload,
+                deadline: context.request.deadline,
             });
 
             Ok(())
@@ -437,11 +437,13 @@ impl CanisterState {
 
     /// Returns the amount of raw memory currently used by the canister in bytes.
     ///
-    /// This only includes execution memory (heap, stable, globals, Wasm).
+    /// This only includes execution memory (heap, stable, globals, Wasm) and
+    /// the Wasm chunk store.
     pub(crate) fn raw_memory_usage(&self) -> NumBytes {
         self.execution_state
             .as_ref()
             .map_or(NumBytes::from(0), |es| es.memory_usage())
+            + self.system_state.wasm_chunk_store.memory_usage()
     }
 
     /// Returns the amount of system state memory used by the canister in bytes
@@ -35,6 +35,14 @@ pub const DEFAULT_QUEUE_CAPACITY: usize = 500;
 /// is computed as time + REQUEST_LIFETIME.
 pub const REQUEST_LIFETIME: Duration = Duration::from_secs(300);
 
+/// Identifies an input or output queue of `CanisterQueues` by the canister at
+/// the other end of the queue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum CanisterQueueId {
+    Input(CanisterId),
+    Output(CanisterId),
+}
+
 /// Encapsulates information about `CanisterQueues`,
 /// used in detecting a loop when consuming the input messages.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
@@ -332,15 +340,27 @@ impl CanisterQueues {
         input_queue_type: InputQueueType,
     ) -> Result<(), (StateError, RequestOrResponse)> {
         let sender = msg.sender();
-        let input_queue = match msg {
-            RequestOrResponse::Request(_) => {
+        let input_queue = match &msg {
+            RequestOrResponse::Request(request) => {
                 let (input_queue, output_queue) = self.get_or_insert_queues(&sender);
                 if let Err(e) = input_queue.check_has_slot() {
                     return Err((e, msg));
                 }
                 // Safe to already (attempt to) reserve an output slot here, as the `push()`
-                // below is guaranteed to succeed due to the check above.
-                if let Err(e) = output_queue.reserve_slot() {
+                // below is guaranteed to succeed due to the check above. No slot is reserved
+                // for the response to a best-effort request.
+                if !request.is_best_effort() {
+                    if let Err(e) = output_queue.reserve_slot() {
+                        return Err((e, msg));
+                    }
+                }
+                input_queue
+            }
+            // Best-effort responses have no reserved slot (and the queues may have been
+            // garbage collected since the request was sent), so they need a free slot.
+            RequestOrResponse::Response(response) if response.is_best_effort() => {
+                let (input_queue, _) = self.get_or_insert_queues(&sender);
+                if let Err(e) = input_queue.check_has_slot() {
                     return Err((e, msg));
                 }
                 input_queue
@@ -548,7 +568,8 @@ impl CanisterQueues {
     }
 
     /// Pushes a `Request` type message into the relevant output queue. Also
-    /// reserves a slot for the eventual response on the matching input queue.
+    /// reserves a slot for the eventual response on the matching input queue,
+    /// unless this is a best-effort request.
     ///
     /// # Errors
     ///
@@ -564,8 +585,11 @@ impl CanisterQueues {
         if let Err(e) = output_queue.check_has_slot() {
             return Err((e, msg));
         }
-        if let Err(e) = input_queue.reserve_slot() {
-            return Err((e, msg));
+        let is_best_effort = msg.is_best_effort();
+        if !is_best_effort {
+            if let Err(e) = input_queue.reserve_slot() {
+                return Err((e, msg));
+            }
         }
 
         let mu_stats_delta = MemoryUsageStats::request_stats_delta(QueueOp::Push, &msg);
@@ -576,7 +600,9 @@ impl CanisterQueues {
             .push_request(msg, time + REQUEST_LIFETIME)
             .expect("cannot fail due to checks above");
 
-        self.input_queues_stats.reserved_slots += 1;
+        if !is_best_effort {
+            self.input_queues_stats.reserved_slots += 1;
+        }
         self.output_queues_stats += oq_stats_delta;
         self.memory_usage_stats += mu_stats_delta;
         debug_assert!(self.stats_ok());
@@ -603,11 +629,14 @@ impl CanisterQueues {
             "reject_ic00_output_request can only be used to reject management canister requests"
         );
 
-        let (input_queue, _output_queue) = self.get_or_insert_queues(&request.receiver);
-        input_queue.reserve_slot()?;
-        self.input_queues_stats.reserved_slots += 1;
-        self.memory_usage_stats += MemoryUsageStats::response_slot_delta();
-        debug_assert!(self.stats_ok());
+        // Best-effort responses are pushed into a free slot instead.
+        if !request.is_best_effort() {
+            let (input_queue, _output_queue) = self.get_or_insert_queues(&request.receiver);
+            input_queue.reserve_slot()?;
+            self.input_queues_stats.reserved_slots += 1;
+            self.memory_usage_stats += MemoryUsageStats::response_slot_delta();
+            debug_assert!(self.stats_ok());
+        }
 
         let response = RequestOrResponse::Response(Arc::new(Response {
             originator: request.sender,
@@ -615,6 +644,7 @@ impl CanisterQueues {
             originator_reply_callback: request.sender_reply_callback,
             refund: request.payment,
             response_payload: Payload::Reject(reject_context),
+            deadline: request.deadline,
         }));
         self.push_input(response, InputQueueType::LocalSubnet)
             .map_err(|(e, _msg)| e)
@@ -642,11 +672,21 @@ impl CanisterQueues {
     /// Pushes a `Response` type message into the relevant output queue. The
     /// protocol should have already reserved a slot, so this cannot fail.
     ///
+    /// Best-effort responses have no reserved slot and are silently dropped if
+    /// the output queue is full.
+    ///
     /// # Panics
     ///
     /// Panics if the queue does not already exist or there is no reserved slot
     /// to push the `Response` into.
     pub fn push_output_response(&mut self, msg: Arc<Response>) {
+        if msg.is_best_effort() {
+            let (_, output_queue) = self.get_or_insert_queues(&msg.originator);
+            if output_queue.check_has_slot().is_err() {
+                return;
+            }
+        }
+
         let mu_stats_delta = MemoryUsageStats::response_stats_delta(QueueOp::Push, &msg);
         let oq_stats_delta =
             OutputQueuesStats::stats_delta(&RequestOrResponse::Response(msg.clone()));
@@ -773,6 +813,103 @@ impl CanisterQueues {
         self.memory_usage_stats.oversized_requests_extra_bytes as usize
     }
 
+    /// Returns the total byte size of best-effort messages across input and
+    /// output queues.
+    pub fn best_effort_message_memory_usage(&self) -> usize {
+        self.memory_usage_stats.best_effort_message_bytes
+    }
+
+    /// Drops the largest best-effort message across all input and output
+    /// queues, in order to free up memory. Returns `false` if there was no
+    /// best-effort message to drop.
+    ///
+    /// See `shed_largest_best_effort_message_from()` for details.
+    ///
+    /// Time complexity: O(num_messages).
+    pub fn shed_largest_best_effort_message(&mut self) -> bool {
+        match self.largest_best_effort_messages().into_iter().max() {
+            Some((_, queue_id)) => self
+                .shed_largest_best_effort_message_from(queue_id)
+                .is_some(),
+            None => false,
+        }
+    }
+
+    /// Returns the byte size of the largest best-effort message that may be
+    /// shed from each input and output queue holding any, together with the
+    /// respective queue.
+    ///
+    /// Time complexity: O(num_messages).
+    pub(crate) fn largest_best_effort_messages(&self) -> Vec<(usize, CanisterQueueId)> {
+        let mut largest = Vec::new();
+        for (canister_id, (input_queue, output_queue)) in self.canister_queues.iter() {
+            if let Some((_, size_bytes)) = input_queue.largest_best_effort_request() {
+                largest.push((size_bytes, CanisterQueueId::Input(*canister_id)));
+            }
+            if let Some((_, size_bytes)) = output_queue.largest_best_effort_message() {
+                largest.push((size_bytes, CanisterQueueId::Output(*canister_id)));
+            }
+        }
+        largest
+    }
+
+    /// Drops the largest best-effort message from the given queue, in order to
+    /// free up memory. Returns the byte size of the dropped message and that of
+    /// the next largest best-effort message in the queue, if any; or `None` if
+    /// there was no best-effort message to drop.
+    ///
+    /// Only best-effort requests are dropped from input queues, as the
+    /// respective callbacks can no longer expire once a response was enqueued.
+    /// The callbacks of calls whose requests or responses were dropped will
+    /// eventually expire, producing a `SYS_UNKNOWN` reject response.
+    ///
+    /// Time complexity: O(queue_len).
+    pub(crate) fn shed_largest_best_effort_message_from(
+        &mut self,
+        queue_id: CanisterQueueId,
+    ) -> Option<(usize, Option<usize>)> {
+        let shed = match queue_id {
+            CanisterQueueId::Input(canister_id) => {
+                let (input_queue, _) = self.canister_queues.get_mut(&canister_id)?;
+                let (index, size_bytes) = input_queue.largest_best_effort_request()?;
+                let msg = input_queue
+                    .remove(index)
+                    .expect("Message found above so remove should not fail.");
+                let next_size_bytes = input_queue
+                    .largest_best_effort_request()
+                    .map(|(_, size_bytes)| size_bytes);
+
+                // Only senders with non-empty input queues are scheduled.
+                if input_queue.num_messages() == 0 {
+                    self.local_subnet_input_schedule
+                        .retain(|sender| *sender != canister_id);
+                    self.remote_subnet_input_schedule
+                        .retain(|sender| *sender != canister_id);
+                }
+                self.input_queues_stats -= InputQueuesStats::stats_delta(QueueOp::Pop, &msg);
+                self.memory_usage_stats -= MemoryUsageStats::stats_delta(QueueOp::Pop, &msg);
+                (size_bytes, next_size_bytes)
+            }
+            CanisterQueueId::Output(canister_id) => {
+                let (_, output_queue) = self.canister_queues.get_mut(&canister_id)?;
+                let (index, size_bytes) = output_queue.largest_best_effort_message()?;
+                let msg = output_queue
+                    .take(index)
+                    .expect("Message found above so take should not fail.");
+                let next_size_bytes = output_queue
+                    .largest_best_effort_message()
+                    .map(|(_, size_bytes)| size_bytes);
+
+                self.output_queues_stats -= OutputQueuesStats::stats_delta(&msg);
+                self.memory_usage_stats -= MemoryUsageStats::stats_delta(QueueOp::Pop, &msg);
+                (size_bytes, next_size_bytes)
+            }
+        };
+        debug_assert!(self.stats_ok());
+
+        Some(shed)
+    }
+
     /// Sets the (transient) size in bytes of responses routed from
     /// `output_queues` into streams and not yet garbage collected.
     pub(super) fn set_stream_responses_size_bytes(&mut self, size_bytes: usize) {
@@ -956,29 +1093,41 @@ impl CanisterQueues {
     fn calculate_memory_usage_stats(
         canister_queues: &BTreeMap<CanisterId, (InputQueue, OutputQueue)>,
     ) -> MemoryUsageStats {
-        // Actual byte size for responses, 0 for requests.
+        // Actual byte size for guaranteed responses, 0 for requests.
         let response_size_bytes = |msg: &RequestOrResponse| match *msg {
             RequestOrResponse::Request(_) => 0,
+            _ if msg.is_best_effort() => 0,
             RequestOrResponse::Response(_) => msg.count_bytes(),
         };
-        // `max(0, msg.count_bytes() - MAX_RESPONSE_COUNT_BYTES)` for requests, 0 for
-        // responses.
+        // `max(0, msg.count_bytes() - MAX_RESPONSE_COUNT_BYTES)` for guaranteed
+        // requests, 0 for responses.
         let request_overhead_bytes = |msg: &RequestOrResponse| match *msg {
+            _ if msg.is_best_effort() => 0,
             RequestOrResponse::Request(_) => {
                 msg.count_bytes().saturating_sub(MAX_RESPONSE_COUNT_BYTES)
             }
             RequestOrResponse::Response(_) => 0,
         };
+        // Actual byte size for best-effort messages, 0 for guaranteed ones.
+        let best_effort_message_bytes = |msg: &RequestOrResponse| {
+            if msg.is_best_effort() {
+                msg.count_bytes()
+            } else {
+                0
+            }
+        };
 
         let mut stats = MemoryUsageStats::default();
         for (iq, oq) in canister_queues.values() {
             stats.responses_size_bytes += iq.calculate_stat_sum(response_size_bytes);
             stats.reserved_slots += iq.reserved_slots() as i64;
             stats.oversized_requests_extra_bytes += iq.calculate_stat_sum(request_overhead_bytes);
+            stats.best_effort_message_bytes += iq.calculate_stat_sum(best_effort_message_bytes);
 
             stats.responses_size_bytes += oq.calculate_stat_sum(response_size_bytes);
             stats.reserved_slots += oq.reserved_slots() as i64;
-            stats.oversized_requests_extra_bytes += oq.calculate_stat_sum(request_overhead_bytes)
+            stats.oversized_requests_extra_bytes += oq.calculate_stat_sum(request_overhead_bytes);
+            stats.best_effort_message_bytes += oq.calculate_stat_sum(best_effort_message_bytes);
         }
         stats
     }
@@ -994,6 +1143,9 @@ impl CanisterQueues {
     /// Times out requests in `OutputQueues` given a current time, enqueuing a reject response
     /// for each into the matching `InputQueue`.
     ///
+    /// Best-effort requests are dropped without enqueuing a reject response, as
+    /// the respective callbacks will expire and produce one.
+    ///
     /// Updating the correct input queues schedule after enqueuing a reject response into a
     /// previously empty queue also requires the full set of local canisters to decide whether
     /// the destination canister was local or remote.
@@ -1014,6 +1166,12 @@ impl CanisterQueues {
                 let request = RequestOrResponse::Request(request);
                 self.memory_usage_stats -= MemoryUsageStats::stats_delta(QueueOp::Pop, &request);
                 self.output_queues_stats -= OutputQueuesStats::stats_delta(&request);
+                timed_out_requests_count += 1;
+
+                // No reject response for best-effort requests, their callbacks will expire.
+                if request.is_best_effort() {
+                    continue;
+                }
 
                 // Push response, update stats.
                 let iq_stats_delta = InputQueuesStats::stats_delta(QueueOp::Push, &response);
@@ -1030,8 +1188,6 @@ impl CanisterQueues {
                         self.remote_subnet_input_schedule.push_back(*canister_id);
                     }
                 }
-
-                timed_out_requests_count += 1;
             }
         }
 
@@ -1054,6 +1210,7 @@ fn generate_timeout_response(request: &Arc<Request>) -> RequestOrResponse {
             "Request timed out.".to_string(),
             MR_SYNTHETIC_REJECT_MESSAGE_MAX_LEN,
         )),
+        deadline: request.deadline,
     }))
 }
 
@@ -1203,9 +1360,13 @@ impl InputQueuesStats {
             RequestOrResponse::Response(_) => 1,
             RequestOrResponse::Request(_) => 0,
         };
-        // Consume one reservation iff pushing a response.
+        // Consume one reservation iff pushing a guaranteed response.
         let reserved_slots = match (op, msg) {
-            (QueueOp::Push, RequestOrResponse::Response(_)) => -1,
+            (QueueOp::Push, RequestOrResponse::Response(response))
+                if !response.is_best_effort() =>
+            {
+                -1
+            }
             _ => 0,
         };
 
@@ -1316,6 +1477,11 @@ struct MemoryUsageStats {
     /// This is populated by `ReplicatedState::put_streams()`, called by MR
     /// after every streams mutation (induction, routing, GC).
     transient_stream_responses_size_bytes: usize,
+
+    /// Sum total of the byte size of every best-effort message (request or
+    /// response) across input and output queues. Best-effort messages do not
+    /// reserve slots, so they are accounted for by actual size only.
+    best_effort_message_bytes: usize,
 }
 
 impl MemoryUsageStats {
@@ -1325,6 +1491,7 @@ impl MemoryUsageStats {
             + self.reserved_slots as usize * MAX_RESPONSE_COUNT_BYTES
             + self.oversized_requests_extra_bytes
             + self.transient_stream_responses_size_bytes
+            + self.best_effort_message_bytes
     }
 
     /// Calculates the change in stats caused by pushing (+) or popping (-) the
@@ -1339,6 +1506,9 @@ impl MemoryUsageStats {
     /// Calculates the change in stats caused by pushing (+) or popping (-) a
     /// request.
     fn request_stats_delta(op: QueueOp, req: &Request) -> MemoryUsageStats {
+        if req.is_best_effort() {
+            return Self::best_effort_stats_delta(req.count_bytes());
+        }
         MemoryUsageStats {
             // No change in responses byte size (as this is a request).
             responses_size_bytes: 0,
@@ -1351,12 +1521,16 @@ impl MemoryUsageStats {
                 .count_bytes()
                 .saturating_sub(MAX_RESPONSE_COUNT_BYTES),
             transient_stream_responses_size_bytes: 0,
+            best_effort_message_bytes: 0,
         }
     }
 
     /// Calculates the change in stats caused by pushing (+) or popping (-) the
     /// given response.
     fn response_stats_delta(op: QueueOp, rep: &Response) -> MemoryUsageStats {
+        if rep.is_best_effort() {
+            return Self::best_effort_stats_delta(rep.count_bytes());
+        }
         MemoryUsageStats {
             // Adjust responses byte size by this response's byte size.
             responses_size_bytes: rep.count_bytes(),
@@ -1368,6 +1542,19 @@ impl MemoryUsageStats {
             // No change in requests overhead (as this is a response).
             oversized_requests_extra_bytes: 0,
             transient_stream_responses_size_bytes: 0,
+            best_effort_message_bytes: 0,
+        }
+    }
+
+    /// Calculates the change in stats caused by pushing (+) or popping (-) a
+    /// best-effort message of the given byte size.
+    fn best_effort_stats_delta(size_bytes: usize) -> MemoryUsageStats {
+        MemoryUsageStats {
+            responses_size_bytes: 0,
+            reserved_slots: 0,
+            oversized_requests_extra_bytes: 0,
+            transient_stream_responses_size_bytes: 0,
+            best_effort_message_bytes: size_bytes,
         }
     }
 
@@ -1379,6 +1566,7 @@ impl MemoryUsageStats {
             reserved_slots: 1,
             oversized_requests_extra_bytes: 0,
             transient_stream_responses_size_bytes: 0,
+            best_effort_message_bytes: 0,
         }
     }
 }
@@ -1388,6 +1576,7 @@ impl AddAssign<MemoryUsageStats> for MemoryUsageStats {
         self.responses_size_bytes += rhs.responses_size_bytes;
         self.reserved_slots += rhs.reserved_slots;
         self.oversized_requests_extra_bytes += rhs.oversized_requests_extra_bytes;
+        self.best_effort_message_bytes += rhs.best_effort_message_bytes;
         debug_assert!(self.reserved_slots >= 0);
     }
 }
@@ -1397,6 +1586,7 @@ impl SubAssign<MemoryUsageStats> for MemoryUsageStats {
         self.responses_size_bytes -= rhs.responses_size_bytes;
         self.reserved_slots -= rhs.reserved_slots;
         self.oversized_requests_extra_bytes -= rhs.oversized_requests_extra_bytes;
+        self.best_effort_message_bytes -= rhs.best_effort_message_bytes;
         debug_assert!(self.reserved_slots >= 0);
     }
 }
@@ -1407,6 +1597,7 @@ impl PartialEq for MemoryUsageStats {
         self.responses_size_bytes == rhs.responses_size_bytes
             && self.reserved_slots == rhs.reserved_slots
             && self.oversized_requests_extra_bytes == rhs.oversized_requests_extra_bytes
+            && self.best_effort_message_bytes == rhs.best_effort_message_bytes
     }
 }
 
@@ -1434,8 +1625,12 @@ pub fn can_push(msg: &RequestOrResponse, available_memory: i64) -> Result<(), us
 
 /// Returns the memory required to push `req` onto an input or output queue.
 /// This is the maximum of `MAX_RESPONSE_COUNT_BYTES` (to be reserved for a
-/// response) and `req.count_bytes()` (if larger).
+/// response) and `req.count_bytes()` (if larger). Best-effort requests reserve
+/// no memory for a response, so they only require `req.count_bytes()`.
 pub fn memory_required_to_push_request(req: &Request) -> usize {
+    if req.is_best_effort() {
+        return req.count_bytes();
+    }
     req.count_bytes().max(MAX_RESPONSE_COUNT_BYTES)
 }
 
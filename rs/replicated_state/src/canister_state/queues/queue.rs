@@ -105,6 +105,22 @@ impl<T: std::clone::Clone> QueueWithReservation<T> {
         !self.queue.is_empty() || self.reserved_slots() > 0
     }
 
+    /// Returns the index and byte size of the largest item for which `size`
+    /// returns `Some`; or `None` if there is no such item.
+    ///
+    /// Time complexity: O(num_messages).
+    fn find_largest(&self, size: impl Fn(&T) -> Option<usize>) -> Option<(usize, usize)> {
+        let mut largest: Option<(usize, usize)> = None;
+        for (index, item) in self.queue.iter().enumerate() {
+            if let Some(size_bytes) = size(item) {
+                if largest.map_or(true, |(_, largest_size)| size_bytes > largest_size) {
+                    largest = Some((index, size_bytes));
+                }
+            }
+        }
+        largest
+    }
+
     /// Calculates the sum of the given stat across all enqueued messages.
     ///
     /// Time complexity: O(num_messages).
@@ -215,8 +231,12 @@ impl InputQueue {
         &mut self,
         msg: RequestOrResponse,
     ) -> Result<(), (StateError, RequestOrResponse)> {
-        match msg {
+        match &msg {
             RequestOrResponse::Request(_) => self.queue.push(msg),
+            // Best-effort responses have no reserved slot.
+            RequestOrResponse::Response(response) if response.is_best_effort() => {
+                self.queue.push(msg)
+            }
             RequestOrResponse::Response(_) => self.queue.push_into_reserved_slot(msg),
         }
     }
@@ -233,6 +253,25 @@ impl InputQueue {
         self.queue.pop()
     }
 
+    /// Returns the index and byte size of the largest best-effort request in
+    /// the queue, if any.
+    ///
+    /// Responses are never considered, as the respective callbacks are no
+    /// longer able to expire once a response was enqueued.
+    pub(super) fn largest_best_effort_request(&self) -> Option<(usize, usize)> {
+        self.queue.find_largest(|msg| match msg {
+            RequestOrResponse::Request(request) if request.is_best_effort() => {
+                Some(msg.count_bytes())
+            }
+            _ => None,
+        })
+    }
+
+    /// Removes and returns the message at the given index, if any.
+    pub(super) fn remove(&mut self, index: usize) -> Option<RequestOrResponse> {
+        self.queue.queue.remove(index)
+    }
+
     /// Returns the number of messages in the queue.
     pub(super) fn num_messages(&self) -> usize {
         self.queue.queue.len()
@@ -388,10 +427,22 @@ impl OutputQueue {
         Ok(())
     }
 
+    /// Pushes a response into a reserved slot; or, for best-effort responses
+    /// (which have no reserved slot), into an available slot.
+    ///
+    /// # Panics
+    ///
+    /// If no reserved (respectively, available) slot exists.
     pub(super) fn push_response(&mut self, msg: Arc<Response>) {
-        self.queue
-            .push_into_reserved_slot(Some(RequestOrResponse::Response(msg)))
-            .unwrap();
+        if msg.is_best_effort() {
+            self.queue
+                .push(Some(RequestOrResponse::Response(msg)))
+                .unwrap();
+        } else {
+            self.queue
+                .push_into_reserved_slot(Some(RequestOrResponse::Response(msg)))
+                .unwrap();
+        }
 
         self.num_messages += 1;
         debug_assert!(self.check_invariants());
@@ -505,6 +556,27 @@ impl OutputQueue {
         Ok(())
     }
 
+    /// Returns the index and byte size of the largest best-effort message in
+    /// the queue, if any.
+    pub(super) fn largest_best_effort_message(&self) -> Option<(usize, usize)> {
+        self.queue.find_largest(|item| match item {
+            Some(msg) if msg.is_best_effort() => Some(msg.count_bytes()),
+            _ => None,
+        })
+    }
+
+    /// Removes the message at the given index, leaving a `None` in its place,
+    /// and returns it.
+    pub(super) fn take(&mut self, index: usize) -> Option<RequestOrResponse> {
+        let msg = self.queue.queue.get_mut(index)?.take()?;
+
+        self.num_messages -= 1;
+        self.advance_to_next_message();
+        debug_assert!(self.check_invariants());
+
+        Some(msg)
+    }
+
     /// Returns the message that `pop` would have returned, without removing it
     /// from the queue.
     pub(crate) fn peek(&self) -> Option<&RequestOrResponse> {
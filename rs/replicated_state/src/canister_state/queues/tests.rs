@@ -14,7 +14,10 @@ use ic_test_utilities::{
         messages::{IngressBuilder, RequestBuilder, ResponseBuilder},
     },
 };
-use ic_types::{messages::CallbackId, time::current_time_and_expiry_time};
+use ic_types::{
+    messages::{CallbackId, NO_DEADLINE},
+    time::{current_time_and_expiry_time, CoarseTime},
+};
 use proptest::prelude::*;
 use std::convert::TryInto;
 
@@ -1626,6 +1629,7 @@ fn time_out_requests_pushes_correct_reject_responses() {
                     payment: Cycles::from(cycles as u64),
                     method_name: "No-Op".to_string(),
                     method_payload: vec![],
+                    deadline: NO_DEADLINE,
                 }),
                 deadline,
             )
@@ -1681,7 +1685,8 @@ fn time_out_requests_pushes_correct_reject_responses() {
                     RejectCode::SysTransient,
                     "Request timed out.".to_string(),
                     MR_SYNTHETIC_REJECT_MESSAGE_MAX_LEN
-                ))
+                )),
+                deadline: NO_DEADLINE,
             }),
             *reject_response,
         );
@@ -1715,3 +1720,144 @@ fn time_out_requests_pushes_correct_reject_responses() {
         VecDeque::from(vec![remote_canister_id]),
     );
 }
+
+#[test]
+fn best_effort_requests_do_not_reserve_slots_or_memory() {
+    let this = canister_test_id(13);
+    let other = canister_test_id(14);
+    let mut queues = CanisterQueues::default();
+
+    let request = RequestBuilder::default()
+        .sender(this)
+        .receiver(other)
+        .deadline(CoarseTime::from_secs_since_unix_epoch(10))
+        .build();
+    let request_size_bytes = request.count_bytes();
+    assert!(request_size_bytes < MAX_RESPONSE_COUNT_BYTES);
+    assert_eq!(
+        request_size_bytes,
+        memory_required_to_push_request(&request)
+    );
+    queues
+        .push_output_request(request.into(), mock_time())
+        .unwrap();
+
+    // No slot reserved for the response; only the actual request size accounted for.
+    assert_eq!(0, queues.input_queues_reservation_count());
+    assert_eq!(0, queues.reserved_slots());
+    assert_eq!(request_size_bytes, queues.memory_usage());
+    assert_eq!(
+        request_size_bytes,
+        queues.best_effort_message_memory_usage()
+    );
+
+    // A best-effort response is accepted into a free input queue slot.
+    let response = ResponseBuilder::default()
+        .originator(this)
+        .respondent(other)
+        .deadline(CoarseTime::from_secs_since_unix_epoch(10))
+        .build();
+    let response_size_bytes = response.count_bytes();
+    queues
+        .push_input(response.into(), InputQueueType::RemoteSubnet)
+        .unwrap();
+    assert_eq!(0, queues.input_queues_reservation_count());
+    assert_eq!(
+        request_size_bytes + response_size_bytes,
+        queues.best_effort_message_memory_usage()
+    );
+    assert_eq!(
+        queues.memory_usage_stats,
+        CanisterQueues::calculate_memory_usage_stats(&queues.canister_queues)
+    );
+}
+
+#[test]
+fn shed_largest_best_effort_message_drops_largest_message_first() {
+    let this = canister_test_id(13);
+    let other = canister_test_id(14);
+    let deadline = CoarseTime::from_secs_since_unix_epoch(10);
+    let mut queues = CanisterQueues::default();
+
+    // A small best-effort input request.
+    queues
+        .push_input(
+            RequestBuilder::default()
+                .sender(other)
+                .receiver(this)
+                .deadline(deadline)
+                .build()
+                .into(),
+            InputQueueType::RemoteSubnet,
+        )
+        .unwrap();
+    // A large best-effort output request.
+    queues
+        .push_output_request(
+            RequestBuilder::default()
+                .sender(this)
+                .receiver(other)
+                .method_payload(vec![13; 1000])
+                .deadline(deadline)
+                .build()
+                .into(),
+            mock_time(),
+        )
+        .unwrap();
+    // A guaranteed response output request.
+    queues
+        .push_output_request(
+            RequestBuilder::default()
+                .sender(this)
+                .receiver(other)
+                .method_payload(vec![13; 2000])
+                .build()
+                .into(),
+            mock_time(),
+        )
+        .unwrap();
+    let guaranteed_memory_usage = MAX_RESPONSE_COUNT_BYTES;
+
+    // The large output request is shed first.
+    assert!(queues.shed_largest_best_effort_message());
+    assert_eq!(1, queues.output_queues_message_count());
+    assert_eq!(1, queues.input_queues_message_count());
+
+    // Then the input request, also removing the sender from the input schedule.
+    assert!(queues.shed_largest_best_effort_message());
+    assert_eq!(1, queues.output_queues_message_count());
+    assert_eq!(0, queues.input_queues_message_count());
+    assert!(queues.remote_subnet_input_schedule.is_empty());
+    assert_eq!(0, queues.best_effort_message_memory_usage());
+    assert_eq!(guaranteed_memory_usage, queues.memory_usage());
+
+    // Guaranteed response messages are never shed.
+    assert!(!queues.shed_largest_best_effort_message());
+    assert_eq!(1, queues.output_queues_message_count());
+}
+
+#[test]
+fn time_out_requests_drops_best_effort_requests_without_reject_response() {
+    let this = canister_test_id(13);
+    let other = canister_test_id(14);
+    let mut queues = CanisterQueues::default();
+    queues
+        .push_output_request(
+            RequestBuilder::default()
+                .sender(this)
+                .receiver(other)
+                .deadline(CoarseTime::from_secs_since_unix_epoch(10))
+                .build()
+                .into(),
+            mock_time(),
+        )
+        .unwrap();
+
+    assert_eq!(
+        1,
+        queues.time_out_requests(mock_time() + REQUEST_LIFETIME, &this, &BTreeMap::new())
+    );
+    assert_eq!(0, queues.output_queues_message_count());
+    assert!(!queues.has_input());
+    assert_eq!(0, queues.memory_usage());
+}
@@ -1,11 +1,17 @@
 mod call_context_manager;
+mod canister_history;
+mod query_stats;
+mod wasm_chunk_store;
 
-use super::queues::can_push;
 pub use super::queues::memory_required_to_push_request;
+use super::queues::{can_push, CanisterQueueId};
 pub use crate::canister_state::queues::CanisterOutputQueuesIterator;
 use crate::{CanisterQueues, CanisterState, InputQueueType, StateError};
 pub use call_context_manager::{CallContext, CallContextAction, CallContextManager, CallOrigin};
+pub use canister_history::{CanisterHistory, MAX_CANISTER_HISTORY_CHANGES};
 use ic_base_types::NumSeconds;
+use ic_error_types::RejectCode;
+use ic_ic00_types::{CanisterChange, CanisterChangeDetails, CanisterChangeOrigin};
 use ic_interfaces::messages::{CanisterInputMessage, RequestOrIngress};
 use ic_logger::{error, ReplicaLogger};
 use ic_protobuf::{
@@ -14,12 +20,16 @@ use ic_protobuf::{
 };
 use ic_registry_subnet_type::SubnetType;
 use ic_types::{
-    messages::{Ingress, RejectContext, Request, RequestOrResponse, Response, StopCanisterContext},
+    messages::{
+        Ingress, Payload, RejectContext, Request, RequestOrResponse, Response, StopCanisterContext,
+    },
     nominal_cycles::NominalCycles,
+    time::CoarseTime,
     CanisterId, CanisterTimer, Cycles, MemoryAllocation, NumBytes, PrincipalId, Time,
 };
 use lazy_static::lazy_static;
 use maplit::btreeset;
+pub use query_stats::TotalQueryStats;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeMap,
@@ -27,6 +37,9 @@ use std::{
 };
 use std::{collections::BTreeSet, sync::Arc};
 use std::{collections::VecDeque, str::FromStr};
+pub use wasm_chunk_store::{
+    ChunkHash, WasmChunkStore, WasmChunkStoreError, MAX_CHUNKS_PER_CANISTER, MAX_CHUNK_SIZE,
+};
 
 lazy_static! {
     static ref DEFAULT_PRINCIPAL_MULTIPLE_CONTROLLERS: PrincipalId =
@@ -112,6 +125,51 @@ pub struct SystemState {
 
     /// Canister version.
     pub canister_version: u64,
+
+    /// Cycles charged on top of the induction cost of each ingress message
+    /// paid for by this canister. Canisters with a higher fee have their
+    /// ingress messages considered first when building blocks.
+    pub ingress_priority_fee: Cycles,
+
+    /// Chunks of a Wasm module uploaded via `upload_chunk` that have not been
+    /// cleared yet. Counted towards the memory usage of the canister.
+    pub wasm_chunk_store: WasmChunkStore,
+
+    /// Log of the most recent changes to the canister (creation, code
+    /// deployments, controller changes), exposed via `canister_info`.
+    pub canister_history: CanisterHistory,
+
+    /// Upper bound on the size of the Wasm heap. `memory.grow` traps if it
+    /// would exceed the limit, except in the upgrade hooks, so that a canister
+    /// can still be upgraded after reaching it.
+    pub wasm_memory_limit: Option<NumBytes>,
+
+    /// Cycles set aside to pay for storage allocated while the subnet memory
+    /// usage was above the reservation threshold. Storage fees are paid from
+    /// this balance before the main balance. The cycles cannot be withdrawn.
+    reserved_balance: Cycles,
+
+    /// Upper bound on `reserved_balance`. An allocation that would need to
+    /// reserve cycles beyond the limit fails. `None` means there is no limit.
+    pub reserved_balance_limit: Option<Cycles>,
+
+    /// Statistics about the query calls executed on the canister, as
+    /// aggregated from the per-replica statistics delivered by consensus.
+    pub total_query_stats: TotalQueryStats,
+}
+
+/// Errors that can occur when moving cycles from the main balance to the
+/// reserved balance.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReservationError {
+    InsufficientCycles {
+        requested: Cycles,
+        available: Cycles,
+    },
+    ReservedLimitExceeded {
+        requested: Cycles,
+        limit: Cycles,
+    },
 }
 
 /// A wrapper around the different canister statuses.
@@ -416,6 +474,13 @@ impl SystemState {
             task_queue: Default::default(),
             global_timer: CanisterTimer::Inactive,
             canister_version: 0,
+            ingress_priority_fee: Cycles::zero(),
+            wasm_chunk_store: WasmChunkStore::default(),
+            canister_history: CanisterHistory::default(),
+            wasm_memory_limit: None,
+            reserved_balance: Cycles::zero(),
+            reserved_balance_limit: None,
+            total_query_stats: TotalQueryStats::default(),
         }
     }
 
@@ -450,6 +515,13 @@ impl SystemState {
         task_queue: VecDeque<ExecutionTask>,
         global_timer: CanisterTimer,
         canister_version: u64,
+        ingress_priority_fee: Cycles,
+        wasm_chunk_store: WasmChunkStore,
+        canister_history: CanisterHistory,
+        wasm_memory_limit: Option<NumBytes>,
+        reserved_balance: Cycles,
+        reserved_balance_limit: Option<Cycles>,
+        total_query_stats: TotalQueryStats,
     ) -> Self {
         Self {
             controllers,
@@ -465,6 +537,13 @@ impl SystemState {
             task_queue,
             global_timer,
             canister_version,
+            ingress_priority_fee,
+            wasm_chunk_store,
+            canister_history,
+            wasm_memory_limit,
+            reserved_balance,
+            reserved_balance_limit,
+            total_query_stats,
         }
     }
 
@@ -472,6 +551,29 @@ impl SystemState {
         self.canister_id
     }
 
+    /// Records a change to the canister in its history. The change is tagged
+    /// with the current canister version, so it must be recorded after the
+    /// version has been bumped.
+    pub fn add_canister_change(
+        &mut self,
+        timestamp: Time,
+        change_origin: CanisterChangeOrigin,
+        change_details: CanisterChangeDetails,
+    ) {
+        let change = CanisterChange::new(
+            timestamp.as_nanos_since_unix_epoch(),
+            self.canister_version,
+            change_origin,
+            change_details,
+        );
+        self.canister_history.add_canister_change(change);
+    }
+
+    /// Returns the history of changes to the canister.
+    pub fn get_canister_history(&self) -> &CanisterHistory {
+        &self.canister_history
+    }
+
     /// Returns a mutable reference to the balance of the canister.
     pub fn balance_mut(&mut self) -> &mut Cycles {
         &mut self.cycles_balance
@@ -482,6 +584,59 @@ impl SystemState {
         self.cycles_balance
     }
 
+    /// Returns the amount of cycles in the reserved balance.
+    pub fn reserved_balance(&self) -> Cycles {
+        self.reserved_balance
+    }
+
+    /// Moves the given amount of cycles from the main balance to the reserved
+    /// balance.
+    ///
+    /// Returns an error if the main balance (after the pending debit) would
+    /// drop below the given freezing threshold or if the reserved balance would
+    /// exceed its limit.
+    pub fn reserve_cycles(
+        &mut self,
+        amount: Cycles,
+        freezing_threshold: Cycles,
+    ) -> Result<(), ReservationError> {
+        if amount.get() == 0 {
+            return Ok(());
+        }
+        if let Some(limit) = self.reserved_balance_limit {
+            let requested = self.reserved_balance + amount;
+            if requested > limit {
+                return Err(ReservationError::ReservedLimitExceeded { requested, limit });
+            }
+        }
+        // We rely on saturating operations of `Cycles` here.
+        let available = self.debited_balance() - freezing_threshold;
+        if amount > available {
+            return Err(ReservationError::InsufficientCycles {
+                requested: amount,
+                available,
+            });
+        }
+        self.cycles_balance -= amount;
+        self.reserved_balance += amount;
+        Ok(())
+    }
+
+    /// Removes the given amount of cycles from the reserved balance, e.g.
+    /// after using them to pay for storage.
+    ///
+    /// Precondition:
+    /// - `amount <= self.reserved_balance()`.
+    pub fn remove_cycles_from_reserved_balance(&mut self, amount: Cycles) {
+        assert!(
+            amount <= self.reserved_balance,
+            "Insufficient reserved cycles: {} vs {}",
+            amount,
+            self.reserved_balance
+        );
+        self.reserved_balance -= amount;
+    }
+
     /// Returns the balance after applying the pending debit.
     /// Returns 0 if the balance is smaller than the pending debit.
     pub fn debited_balance(&self) -> Cycles {
@@ -611,8 +766,20 @@ impl SystemState {
             "Expected `Request` to have been sent from canister ID {}, but instead got {}",
             self.canister_id, request.sender
         );
+        let best_effort_callback = request
+            .is_best_effort()
+            .then_some(request.sender_reply_callback);
         self.queues
-            .reject_ic00_output_request(request, reject_context)
+            .reject_ic00_output_request(request, reject_context)?;
+
+        // The reject response was enqueued directly, so the callback can no
+        // longer expire.
+        if let (Some(callback_id), Some(call_context_manager)) =
+            (best_effort_callback, self.call_context_manager_mut())
+        {
+            call_context_manager.accept_best_effort_response(callback_id);
+        }
+        Ok(())
     }
 
     /// Returns the number of output requests that can be pushed onto the queue
@@ -628,6 +795,9 @@ impl SystemState {
     /// canister is also refunded the excess cycles that was reserved for
     /// sending this response when the original request was received.
     ///
+    /// Best-effort responses have no reserved slot and are silently dropped if
+    /// the output queue is full.
+    ///
     /// # Panics
     ///
     /// Panics if the queue does not already exist or there is no reserved slot
@@ -658,6 +828,10 @@ impl SystemState {
     /// cycles cost for sending the `Response` back. If it is a `Response`,
     /// the protocol should have already reserved a slot and memory for it.
     ///
+    /// Best-effort responses are silently dropped if their callback has already
+    /// expired (or received a response); or if there is no free slot in the
+    /// input queue, in which case the callback will eventually expire.
+    ///
     /// Updates `subnet_available_memory` to reflect any change in memory usage.
     ///
     /// # Notes
@@ -697,7 +871,7 @@ impl SystemState {
             msg.receiver()
         );
 
-        match (&msg, &self.status) {
+        match (&msg, &mut self.status) {
             // Requests and responses are both rejected when stopped.
             (_, CanisterStatus::Stopped { .. }) => {
                 Err((StateError::CanisterStopped(self.canister_id()), msg))
@@ -722,19 +896,38 @@ impl SystemState {
                     ..
                 },
             ) => {
+                let mut best_effort_callback = None;
                 if let RequestOrResponse::Response(response) = &msg {
+                    if response.is_best_effort() {
+                        if !call_context_manager
+                            .has_unexpired_callback(response.originator_reply_callback)
+                        {
+                            // Late response, the callback has already expired.
+                            return Ok(());
+                        }
+                        best_effort_callback = Some(response.originator_reply_callback);
+                    }
                     call_context_manager
                         .validate_response(response)
                         .map_err(|err| (err, msg.clone()))?;
                 }
-                push_input(
+                let res = push_input(
                     &mut self.queues,
                     msg,
                     canister_available_memory,
                     subnet_available_memory,
                     own_subnet_type,
                     input_queue_type,
-                )
+                );
+                match (res, best_effort_callback) {
+                    (Ok(()), Some(callback_id)) => {
+                        call_context_manager.accept_best_effort_response(callback_id);
+                        Ok(())
+                    }
+                    // Best-effort responses are dropped if they cannot be enqueued.
+                    (Err(_), Some(_)) => Ok(()),
+                    (res, None) => res,
+                }
             }
         }
     }
@@ -843,10 +1036,12 @@ impl SystemState {
         own_subnet_type: SubnetType,
     ) {
         // Bail out if the canister is not running.
-        match self.status {
-            CanisterStatus::Running { .. } => (),
+        let call_context_manager = match &mut self.status {
+            CanisterStatus::Running {
+                call_context_manager,
+            } => call_context_manager,
             CanisterStatus::Stopped | CanisterStatus::Stopping { .. } => return,
-        }
+        };
 
         let mut available_memory = canister_available_memory.min(*subnet_available_memory);
         let mut memory_usage = self.queues.memory_usage() as i64;
@@ -858,6 +1053,20 @@ impl SystemState {
                 return;
             }
 
+            // Leave late best-effort responses to be routed (and dropped) via the
+            // loopback stream.
+            let best_effort_callback = match msg {
+                RequestOrResponse::Response(response) if response.is_best_effort() => {
+                    if !call_context_manager
+                        .has_unexpired_callback(response.originator_reply_callback)
+                    {
+                        return;
+                    }
+                    Some(response.originator_reply_callback)
+                }
+                _ => None,
+            };
+
             // Attempt inducting `msg`. May fail if the input queue is full.
             if self
                 .queues
@@ -866,6 +1075,9 @@ impl SystemState {
             {
                 return;
             }
+            if let Some(callback_id) = best_effort_callback {
+                call_context_manager.accept_best_effort_response(callback_id);
+            }
 
             // Adjust both `available_memory` and `subnet_available_memory` by
             // `memory_usage_before - memory_usage_after`. Defer the accounting
@@ -902,6 +1114,97 @@ impl SystemState {
         self.queues
             .time_out_requests(current_time, own_canister_id, local_canisters)
     }
+
+    /// Queries whether any best-effort callbacks have expired at `current_time`
+    /// without having received a response.
+    pub fn has_expired_callbacks(&self, current_time: CoarseTime) -> bool {
+        self.call_context_manager()
+            .map_or(false, |ccm| ccm.has_expired_callbacks(current_time))
+    }
+
+    /// Enqueues a `SYS_UNKNOWN` reject response for every best-effort callback
+    /// that expired at `current_time` without having received a response.
+    /// Returns the number of callbacks that were timed out.
+    ///
+    /// Reject responses are only enqueued into input queues with free slots;
+    /// the remaining expired callbacks are retried on the next call.
+    ///
+    /// Updating the correct input queues schedule after enqueuing a reject
+    /// response into a previously empty queue also requires the full set of
+    /// local canisters to decide whether the respondent was local or remote.
+    pub fn time_out_callbacks(
+        &mut self,
+        current_time: CoarseTime,
+        own_canister_id: &CanisterId,
+        local_canisters: &BTreeMap<CanisterId, CanisterState>,
+    ) -> u64 {
+        let call_context_manager = match &mut self.status {
+            CanisterStatus::Running {
+                call_context_manager,
+            }
+            | CanisterStatus::Stopping {
+                call_context_manager,
+                ..
+            } => call_context_manager,
+            CanisterStatus::Stopped => return 0,
+        };
+
+        let mut timed_out_callbacks_count = 0;
+        for callback_id in call_context_manager.expired_callbacks(current_time) {
+            let callback = &call_context_manager.callbacks()[&callback_id];
+            let respondent = callback
+                .respondent
+                .expect("Best-effort callbacks always have a respondent");
+            let response = RequestOrResponse::Response(Arc::new(Response {
+                originator: self.canister_id,
+                respondent,
+                originator_reply_callback: callback_id,
+                refund: Cycles::zero(),
+                response_payload: Payload::Reject(RejectContext::new(
+                    RejectCode::SysUnknown,
+                    "Call deadline has expired.".to_string(),
+                )),
+                deadline: callback.deadline,
+            }));
+            let input_queue_type =
+                if respondent == *own_canister_id || local_canisters.contains_key(&respondent) {
+                    InputQueueType::LocalSubnet
+                } else {
+                    InputQueueType::RemoteSubnet
+                };
+
+            if self.queues.push_input(response, input_queue_type).is_ok() {
+                call_context_manager.accept_best_effort_response(callback_id);
+                timed_out_callbacks_count += 1;
+            }
+        }
+
+        timed_out_callbacks_count
+    }
+
+    /// Returns the total byte size of best-effort messages in `self.queues`.
+    pub fn best_effort_message_memory_usage(&self) -> usize {
+        self.queues.best_effort_message_memory_usage()
+    }
+
+    /// Returns the byte size of the largest best-effort message that may be
+    /// shed from each of the queues in `self.queues`.
+    ///
+    /// See `CanisterQueues::largest_best_effort_messages` for details.
+    pub(crate) fn largest_best_effort_messages(&self) -> Vec<(usize, CanisterQueueId)> {
+        self.queues.largest_best_effort_messages()
+    }
+
+    /// Drops the largest best-effort message from the given queue in
+    /// `self.queues`.
+    ///
+    /// See `CanisterQueues::shed_largest_best_effort_message_from` for details.
+    pub(crate) fn shed_largest_best_effort_message_from(
+        &mut self,
+        queue_id: CanisterQueueId,
+    ) -> Option<(usize, Option<usize>)> {
+        self.queues.shed_largest_best_effort_message_from(queue_id)
+    }
 }
 
 /// Implements memory limits verification for pushing a canister-to-canister
@@ -6,16 +6,16 @@ use ic_interfaces::{execution_environment::HypervisorError, messages::RequestOrI
 use ic_protobuf::proxy::{try_from_option_field, ProxyDecodeError};
 use ic_protobuf::state::canister_state_bits::v1 as pb;
 use ic_protobuf::types::v1 as pb_types;
-use ic_types::messages::Response;
-use ic_types::Time;
+use ic_types::messages::{Response, NO_DEADLINE};
 use ic_types::{
     ingress::WasmResult,
     messages::{CallContextId, CallbackId, MessageId},
     methods::Callback,
     user_id_into_protobuf, user_id_try_from_protobuf, CanisterId, Cycles, Funds, UserId,
 };
+use ic_types::{time::CoarseTime, Time};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::{From, TryFrom, TryInto};
 use std::time::Duration;
 
@@ -210,12 +210,17 @@ pub struct CallContextManager {
     // maps call context to its responded status
     call_contexts: BTreeMap<CallContextId, CallContext>,
     callbacks: BTreeMap<CallbackId, Callback>,
+    /// Best-effort callbacks that have neither expired nor received a
+    /// response yet, ordered by deadline.
+    unexpired_callbacks: BTreeSet<(CoarseTime, CallbackId)>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CallOrigin {
     Ingress(UserId, MessageId),
-    CanisterUpdate(CanisterId, CallbackId),
+    /// Inter-canister update call, along with the deadline of the originating
+    /// request (`NO_DEADLINE` for guaranteed response calls).
+    CanisterUpdate(CanisterId, CallbackId, CoarseTime),
     Query(UserId),
     CanisterQuery(CanisterId, CallbackId),
     /// System task is either a Heartbeat or a GlobalTimer.
@@ -229,10 +234,11 @@ impl From<&CallOrigin> for pb::call_context::CallOrigin {
                 user_id: Some(user_id_into_protobuf(*user_id)),
                 message_id: message_id.as_bytes().to_vec(),
             }),
-            CallOrigin::CanisterUpdate(canister_id, callback_id) => {
+            CallOrigin::CanisterUpdate(canister_id, callback_id, deadline) => {
                 Self::CanisterUpdate(pb::call_context::CanisterUpdateOrQuery {
                     canister_id: Some(pb_types::CanisterId::from(*canister_id)),
                     callback_id: callback_id.get(),
+                    deadline_seconds: deadline.as_secs_since_unix_epoch(),
                 })
             }
             CallOrigin::Query(user_id) => Self::Query(user_id_into_protobuf(*user_id)),
@@ -240,6 +246,7 @@ impl From<&CallOrigin> for pb::call_context::CallOrigin {
                 Self::CanisterQuery(pb::call_context::CanisterUpdateOrQuery {
                     canister_id: Some(pb_types::CanisterId::from(*canister_id)),
                     callback_id: callback_id.get(),
+                    deadline_seconds: NO_DEADLINE.as_secs_since_unix_epoch(),
                 })
             }
             CallOrigin::SystemTask => Self::SystemTask(pb::call_context::SystemTask {}),
@@ -265,10 +272,12 @@ impl TryFrom<pb::call_context::CallOrigin> for CallOrigin {
                 pb::call_context::CanisterUpdateOrQuery {
                     canister_id,
                     callback_id,
+                    deadline_seconds,
                 },
             ) => Self::CanisterUpdate(
                 try_from_option_field(canister_id, "CallOrigin::CanisterUpdate::canister_id")?,
                 callback_id.into(),
+                CoarseTime::from_secs_since_unix_epoch(deadline_seconds),
             ),
             pb::call_context::CallOrigin::Query(user_id) => {
                 Self::Query(user_id_try_from_protobuf(user_id)?)
@@ -277,6 +286,7 @@ impl TryFrom<pb::call_context::CallOrigin> for CallOrigin {
                 pb::call_context::CanisterUpdateOrQuery {
                     canister_id,
                     callback_id,
+                    ..
                 },
             ) => Self::CanisterQuery(
                 try_from_option_field(canister_id, "CallOrigin::CanisterQuery::canister_id")?,
@@ -488,6 +498,10 @@ impl CallContextManager {
     pub fn register_callback(&mut self, callback: Callback) -> CallbackId {
         self.next_callback_id += 1;
         let callback_id = CallbackId::from(self.next_callback_id);
+        if callback.deadline != NO_DEADLINE {
+            self.unexpired_callbacks
+                .insert((callback.deadline, callback_id));
+        }
         self.callbacks.insert(callback_id, callback);
         callback_id
     }
@@ -500,7 +514,53 @@ impl CallContextManager {
     /// If we get a response for one of the outstanding calls, we unregister
     /// the callback and return it.
     pub fn unregister_callback(&mut self, callback_id: CallbackId) -> Option<Callback> {
-        self.callbacks.remove(&callback_id)
+        let callback = self.callbacks.remove(&callback_id)?;
+        self.unexpired_callbacks
+            .remove(&(callback.deadline, callback_id));
+        Some(callback)
+    }
+
+    /// Returns `true` if the given best-effort callback has neither expired nor
+    /// received a response yet.
+    pub(crate) fn has_unexpired_callback(&self, callback_id: CallbackId) -> bool {
+        self.callbacks.get(&callback_id).map_or(false, |callback| {
+            self.unexpired_callbacks
+                .contains(&(callback.deadline, callback_id))
+        })
+    }
+
+    /// Records that a response (possibly a `SYS_UNKNOWN` reject response, on
+    /// expiration) to the best-effort call with the given callback was
+    /// enqueued, so the callback can no longer expire.
+    ///
+    /// Returns `false` if the callback had already expired or received a
+    /// response (or is unknown).
+    pub(crate) fn accept_best_effort_response(&mut self, callback_id: CallbackId) -> bool {
+        match self.callbacks.get(&callback_id) {
+            Some(callback) => self
+                .unexpired_callbacks
+                .remove(&(callback.deadline, callback_id)),
+            None => false,
+        }
+    }
+
+    /// Returns `true` if any best-effort callback has a deadline at or before
+    /// `current_time` and has not received a response yet.
+    pub fn has_expired_callbacks(&self, current_time: CoarseTime) -> bool {
+        self.unexpired_callbacks
+            .iter()
+            .next()
+            .map_or(false, |(deadline, _)| *deadline <= current_time)
+    }
+
+    /// Returns the IDs of all best-effort callbacks with deadlines at or before
+    /// `current_time` that have not received a response yet, in deadline order.
+    pub fn expired_callbacks(&self, current_time: CoarseTime) -> Vec<CallbackId> {
+        self.unexpired_callbacks
+            .iter()
+            .take_while(|(deadline, _)| *deadline <= current_time)
+            .map(|(_, callback_id)| *callback_id)
+            .collect()
     }
 
     /// Returns the call origin, which is either the message id of the ingress
@@ -575,9 +635,11 @@ impl CallContextManager {
 impl From<&RequestOrIngress> for CallOrigin {
     fn from(msg: &RequestOrIngress) -> Self {
         match msg {
-            RequestOrIngress::Request(request) => {
-                CallOrigin::CanisterUpdate(request.sender, request.sender_reply_callback)
-            }
+            RequestOrIngress::Request(request) => CallOrigin::CanisterUpdate(
+                request.sender,
+                request.sender_reply_callback,
+                request.deadline,
+            ),
             RequestOrIngress::Ingress(ingress) => {
                 CallOrigin::Ingress(ingress.source, ingress.message_id.clone())
             }
@@ -606,6 +668,11 @@ impl From<&CallContextManager> for pb::CallContextManager {
                     callback: Some(callback.into()),
                 })
                 .collect(),
+            unexpired_callbacks: item
+                .unexpired_callbacks
+                .iter()
+                .map(|(_, id)| id.get())
+                .collect(),
         }
     }
 }
@@ -636,11 +703,23 @@ impl TryFrom<pb::CallContextManager> for CallContextManager {
             );
         }
 
+        let mut unexpired_callbacks = BTreeSet::new();
+        for callback_id in value.unexpired_callbacks.into_iter().map(CallbackId::from) {
+            let callback = callbacks
+                .get(&callback_id)
+                .ok_or(ProxyDecodeError::Other(format!(
+                    "Unexpired callback {} not found",
+                    callback_id
+                )))?;
+            unexpired_callbacks.insert((callback.deadline, callback_id));
+        }
+
         Ok(Self {
             next_call_context_id: value.next_call_context_id,
             next_callback_id: value.next_callback_id,
             call_contexts,
             callbacks,
+            unexpired_callbacks,
         })
     }
 }
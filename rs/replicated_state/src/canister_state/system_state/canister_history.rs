@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests;
+
+use ic_ic00_types::CanisterChange;
+use ic_protobuf::{
+    proxy::ProxyDecodeError, state::canister_state_bits::v1 as pb_canister_state_bits,
+};
+use std::{collections::VecDeque, convert::TryFrom, sync::Arc};
+
+/// The maximum number of changes kept in the history of a single canister.
+/// Older changes are dropped once this limit is reached, but still counted in
+/// `total_num_changes`.
+pub const MAX_CANISTER_HISTORY_CHANGES: usize = 20;
+
+/// Bounded log of the most recent changes to a canister (creation, code
+/// deployments and uninstalls, controller changes), exposed to other canisters
+/// via the `canister_info` management canister method.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CanisterHistory {
+    /// The most recent changes, ordered from oldest to newest.
+    changes: Arc<VecDeque<CanisterChange>>,
+    /// The total number of changes ever recorded for the canister, including
+    /// those that have been dropped from `changes`.
+    total_num_changes: u64,
+}
+
+impl CanisterHistory {
+    /// Records `canister_change`, dropping the oldest change if the history
+    /// already holds `MAX_CANISTER_HISTORY_CHANGES` changes.
+    pub fn add_canister_change(&mut self, canister_change: CanisterChange) {
+        let changes = Arc::make_mut(&mut self.changes);
+        if changes.len() >= MAX_CANISTER_HISTORY_CHANGES {
+            changes.pop_front();
+        }
+        changes.push_back(canister_change);
+        self.total_num_changes += 1;
+    }
+
+    /// Returns up to `num_requested_changes` most recent changes, ordered from
+    /// oldest to newest.
+    pub fn get_changes(
+        &self,
+        num_requested_changes: usize,
+    ) -> impl Iterator<Item = &CanisterChange> {
+        let num_skipped = self.changes.len().saturating_sub(num_requested_changes);
+        self.changes.iter().skip(num_skipped)
+    }
+
+    /// Returns the total number of changes ever recorded for the canister.
+    pub fn get_total_num_changes(&self) -> u64 {
+        self.total_num_changes
+    }
+}
+
+impl From<&CanisterHistory> for pb_canister_state_bits::CanisterHistory {
+    fn from(item: &CanisterHistory) -> Self {
+        Self {
+            changes: item.changes.iter().map(|change| change.into()).collect(),
+            total_num_changes: item.total_num_changes,
+        }
+    }
+}
+
+impl TryFrom<pb_canister_state_bits::CanisterHistory> for CanisterHistory {
+    type Error = ProxyDecodeError;
+
+    fn try_from(value: pb_canister_state_bits::CanisterHistory) -> Result<Self, Self::Error> {
+        let changes = value
+            .changes
+            .into_iter()
+            .map(CanisterChange::try_from)
+            .collect::<Result<VecDeque<_>, _>>()?;
+        Ok(Self {
+            changes: Arc::new(changes),
+            total_num_changes: value.total_num_changes,
+        })
+    }
+}
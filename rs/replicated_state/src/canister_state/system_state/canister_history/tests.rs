@@ -0,0 +1,62 @@
+use super::*;
+use ic_ic00_types::{CanisterChangeDetails, CanisterChangeOrigin, CanisterInstallMode};
+use ic_test_utilities::types::ids::user_test_id;
+
+fn change(timestamp_nanos: u64) -> CanisterChange {
+    CanisterChange::new(
+        timestamp_nanos,
+        timestamp_nanos,
+        CanisterChangeOrigin::from_user(user_test_id(1).get()),
+        CanisterChangeDetails::code_deployment(
+            CanisterInstallMode::Install,
+            [timestamp_nanos as u8; 32],
+        ),
+    )
+}
+
+#[test]
+fn history_is_bounded() {
+    let mut history = CanisterHistory::default();
+    let num_changes = MAX_CANISTER_HISTORY_CHANGES as u64 + 5;
+    for i in 0..num_changes {
+        history.add_canister_change(change(i));
+    }
+    assert_eq!(history.get_total_num_changes(), num_changes);
+    let changes: Vec<_> = history.get_changes(usize::MAX).cloned().collect();
+    assert_eq!(changes.len(), MAX_CANISTER_HISTORY_CHANGES);
+    assert_eq!(changes.first().unwrap(), &change(5));
+    assert_eq!(changes.last().unwrap(), &change(num_changes - 1));
+}
+
+#[test]
+fn get_changes_returns_most_recent() {
+    let mut history = CanisterHistory::default();
+    for i in 0..5 {
+        history.add_canister_change(change(i));
+    }
+    let changes: Vec<_> = history.get_changes(2).cloned().collect();
+    assert_eq!(changes, vec![change(3), change(4)]);
+    assert_eq!(history.get_changes(0).count(), 0);
+}
+
+#[test]
+fn proto_round_trip() {
+    let mut history = CanisterHistory::default();
+    for i in 0..3 {
+        history.add_canister_change(change(i));
+    }
+    history.add_canister_change(CanisterChange::new(
+        3,
+        4,
+        CanisterChangeOrigin::from_canister(user_test_id(2).get(), Some(7)),
+        CanisterChangeDetails::controllers_change(vec![user_test_id(3).get()]),
+    ));
+    history.add_canister_change(CanisterChange::new(
+        5,
+        6,
+        CanisterChangeOrigin::from_user(user_test_id(1).get()),
+        CanisterChangeDetails::code_uninstall(),
+    ));
+    let proto = pb_canister_state_bits::CanisterHistory::from(&history);
+    assert_eq!(CanisterHistory::try_from(proto).unwrap(), history);
+}
@@ -0,0 +1,120 @@
+use ic_protobuf::{proxy::ProxyDecodeError, state::canister_state_bits::v1 as pb};
+use ic_types::batch::QueryStats;
+use std::convert::TryFrom;
+
+/// Statistics about the query calls executed on a canister, summed over all
+/// replicas and epochs whose statistics were delivered through consensus.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TotalQueryStats {
+    pub num_calls: u128,
+    pub num_instructions: u128,
+    pub ingress_payload_size: u128,
+    pub egress_payload_size: u128,
+}
+
+impl TotalQueryStats {
+    /// Adds the statistics one replica collected during one epoch.
+    pub fn saturating_accumulate(&mut self, stats: &QueryStats) {
+        self.num_calls = self.num_calls.saturating_add(stats.num_calls as u128);
+        self.num_instructions = self
+            .num_instructions
+            .saturating_add(stats.num_instructions as u128);
+        self.ingress_payload_size = self
+            .ingress_payload_size
+            .saturating_add(stats.ingress_payload_size as u128);
+        self.egress_payload_size = self
+            .egress_payload_size
+            .saturating_add(stats.egress_payload_size as u128);
+    }
+}
+
+fn u128_into_bytes(value: u128) -> Vec<u8> {
+    if value == 0 {
+        vec![]
+    } else {
+        value.to_le_bytes().to_vec()
+    }
+}
+
+fn u128_try_from_bytes(bytes: &[u8], field: &str) -> Result<u128, ProxyDecodeError> {
+    if bytes.is_empty() {
+        return Ok(0);
+    }
+    let bytes: [u8; 16] = bytes.try_into().map_err(|_| {
+        ProxyDecodeError::Other(format!(
+            "TotalQueryStats::{}: expected 16 bytes, got {}",
+            field,
+            bytes.len()
+        ))
+    })?;
+    Ok(u128::from_le_bytes(bytes))
+}
+
+impl From<&TotalQueryStats> for pb::TotalQueryStats {
+    fn from(item: &TotalQueryStats) -> Self {
+        Self {
+            num_calls: u128_into_bytes(item.num_calls),
+            num_instructions: u128_into_bytes(item.num_instructions),
+            ingress_payload_size: u128_into_bytes(item.ingress_payload_size),
+            egress_payload_size: u128_into_bytes(item.egress_payload_size),
+        }
+    }
+}
+
+impl TryFrom<pb::TotalQueryStats> for TotalQueryStats {
+    type Error = ProxyDecodeError;
+
+    fn try_from(value: pb::TotalQueryStats) -> Result<Self, Self::Error> {
+        Ok(Self {
+            num_calls: u128_try_from_bytes(&value.num_calls, "num_calls")?,
+            num_instructions: u128_try_from_bytes(&value.num_instructions, "num_instructions")?,
+            ingress_payload_size: u128_try_from_bytes(
+                &value.ingress_payload_size,
+                "ingress_payload_size",
+            )?,
+            egress_payload_size: u128_try_from_bytes(
+                &value.egress_payload_size,
+                "egress_payload_size",
+            )?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn total_query_stats_proto_round_trip() {
+        let stats = TotalQueryStats {
+            num_calls: 0,
+            num_instructions: u128::MAX,
+            ingress_payload_size: 17,
+            egress_payload_size: 1 << 70,
+        };
+        let pb_stats = pb::TotalQueryStats::from(&stats);
+        assert_eq!(TotalQueryStats::try_from(pb_stats).unwrap(), stats);
+    }
+
+    #[test]
+    fn total_query_stats_accumulate() {
+        let mut total = TotalQueryStats::default();
+        let stats = QueryStats {
+            num_calls: 2,
+            num_instructions: 1_000,
+            ingress_payload_size: 10,
+            egress_payload_size: 20,
+        };
+        total.saturating_accumulate(&stats);
+        total.saturating_accumulate(&stats);
+        assert_eq!(
+            total,
+            TotalQueryStats {
+                num_calls: 4,
+                num_instructions: 2_000,
+                ingress_payload_size: 20,
+                egress_payload_size: 40,
+            }
+        );
+    }
+}
@@ -0,0 +1,143 @@
+#[cfg(test)]
+mod tests;
+
+use ic_protobuf::state::canister_state_bits::v1 as pb;
+use ic_types::NumBytes;
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+/// The maximum size of a single chunk in bytes.
+pub const MAX_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The maximum number of chunks that can be stored for a single canister.
+pub const MAX_CHUNKS_PER_CANISTER: usize = 100;
+
+/// The SHA-256 hash of a chunk, used to refer to it in `install_chunked_code`.
+pub type ChunkHash = [u8; 32];
+
+/// Errors that can occur when inserting a chunk into a [`WasmChunkStore`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WasmChunkStoreError {
+    ChunkTooLarge { size: usize, max_size: usize },
+    StoreFull { max_chunks: usize },
+}
+
+impl fmt::Display for WasmChunkStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ChunkTooLarge { size, max_size } => write!(
+                f,
+                "Wasm chunk of {} bytes exceeds the maximum chunk size of {} bytes",
+                size, max_size
+            ),
+            Self::StoreFull { max_chunks } => write!(
+                f,
+                "Wasm chunk store already contains the maximum of {} chunks",
+                max_chunks
+            ),
+        }
+    }
+}
+
+/// Chunks of a Wasm module uploaded via `upload_chunk`, indexed by their
+/// hash. The chunks are later assembled into a module by
+/// `install_chunked_code`, which makes it possible to install modules larger
+/// than the maximum message size.
+///
+/// The chunks are counted towards the memory usage of the canister.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WasmChunkStore {
+    chunks: BTreeMap<ChunkHash, Arc<Vec<u8>>>,
+    memory_usage: usize,
+}
+
+impl WasmChunkStore {
+    /// Checks whether `chunk` can be inserted into the store.
+    pub fn can_insert_chunk(&self, chunk: &[u8]) -> Result<(), WasmChunkStoreError> {
+        if chunk.len() > MAX_CHUNK_SIZE {
+            return Err(WasmChunkStoreError::ChunkTooLarge {
+                size: chunk.len(),
+                max_size: MAX_CHUNK_SIZE,
+            });
+        }
+        if self.chunks.len() >= MAX_CHUNKS_PER_CANISTER && !self.contains_chunk(chunk) {
+            return Err(WasmChunkStoreError::StoreFull {
+                max_chunks: MAX_CHUNKS_PER_CANISTER,
+            });
+        }
+        Ok(())
+    }
+
+    /// Inserts `chunk` into the store and returns its hash. Inserting a chunk
+    /// that is already present is a no-op.
+    pub fn insert_chunk(&mut self, chunk: Vec<u8>) -> Result<ChunkHash, WasmChunkStoreError> {
+        self.can_insert_chunk(&chunk)?;
+        let hash = Self::hash(&chunk);
+        if !self.chunks.contains_key(&hash) {
+            self.memory_usage += chunk.len();
+            self.chunks.insert(hash, Arc::new(chunk));
+        }
+        Ok(hash)
+    }
+
+    /// Returns true if `chunk` is already present in the store.
+    pub fn contains_chunk(&self, chunk: &[u8]) -> bool {
+        self.chunks.contains_key(&Self::hash(chunk))
+    }
+
+    /// Returns the chunk with the given hash, if present.
+    pub fn get_chunk(&self, hash: &ChunkHash) -> Option<&[u8]> {
+        self.chunks.get(hash).map(|chunk| chunk.as_slice())
+    }
+
+    /// Returns an iterator over the stored chunks, ordered by hash.
+    pub fn chunks(&self) -> impl Iterator<Item = &[u8]> {
+        self.chunks.values().map(|chunk| chunk.as_slice())
+    }
+
+    /// Returns the number of stored chunks.
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns true if the store holds no chunks.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Removes all chunks from the store.
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.memory_usage = 0;
+    }
+
+    /// Returns the total size of the stored chunks.
+    pub fn memory_usage(&self) -> NumBytes {
+        NumBytes::from(self.memory_usage as u64)
+    }
+
+    fn hash(chunk: &[u8]) -> ChunkHash {
+        ic_crypto_sha::Sha256::hash(chunk)
+    }
+}
+
+impl From<&WasmChunkStore> for pb::WasmChunkStore {
+    fn from(item: &WasmChunkStore) -> Self {
+        Self {
+            chunks: item.chunks().map(|chunk| chunk.to_vec()).collect(),
+        }
+    }
+}
+
+impl From<pb::WasmChunkStore> for WasmChunkStore {
+    fn from(value: pb::WasmChunkStore) -> Self {
+        let mut store = Self::default();
+        for chunk in value.chunks {
+            let hash = Self::hash(&chunk);
+            if !store.chunks.contains_key(&hash) {
+                store.memory_usage += chunk.len();
+                store.chunks.insert(hash, Arc::new(chunk));
+            }
+        }
+        store
+    }
+}
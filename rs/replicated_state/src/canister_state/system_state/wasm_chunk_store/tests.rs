@@ -0,0 +1,71 @@
+use super::*;
+
+#[test]
+fn insert_and_get_chunk() {
+    let mut store = WasmChunkStore::default();
+    let hash = store.insert_chunk(vec![1, 2, 3]).unwrap();
+    assert_eq!(hash, ic_crypto_sha::Sha256::hash(&[1, 2, 3]));
+    assert_eq!(store.get_chunk(&hash), Some(&[1, 2, 3][..]));
+    assert_eq!(store.len(), 1);
+    assert_eq!(store.memory_usage(), NumBytes::from(3));
+}
+
+#[test]
+fn inserting_duplicate_chunk_is_noop() {
+    let mut store = WasmChunkStore::default();
+    let hash1 = store.insert_chunk(vec![7; 10]).unwrap();
+    let hash2 = store.insert_chunk(vec![7; 10]).unwrap();
+    assert_eq!(hash1, hash2);
+    assert_eq!(store.len(), 1);
+    assert_eq!(store.memory_usage(), NumBytes::from(10));
+}
+
+#[test]
+fn rejects_too_large_chunk() {
+    let mut store = WasmChunkStore::default();
+    assert_eq!(
+        store.insert_chunk(vec![0; MAX_CHUNK_SIZE + 1]),
+        Err(WasmChunkStoreError::ChunkTooLarge {
+            size: MAX_CHUNK_SIZE + 1,
+            max_size: MAX_CHUNK_SIZE
+        })
+    );
+    assert!(store.is_empty());
+}
+
+#[test]
+fn rejects_chunks_beyond_limit() {
+    let mut store = WasmChunkStore::default();
+    for i in 0..MAX_CHUNKS_PER_CANISTER {
+        store
+            .insert_chunk((i as u32).to_le_bytes().to_vec())
+            .unwrap();
+    }
+    assert_eq!(
+        store.insert_chunk(vec![0xff; 5]),
+        Err(WasmChunkStoreError::StoreFull {
+            max_chunks: MAX_CHUNKS_PER_CANISTER
+        })
+    );
+    // Re-uploading an existing chunk is still allowed.
+    assert!(store.insert_chunk(0_u32.to_le_bytes().to_vec()).is_ok());
+}
+
+#[test]
+fn clear_removes_all_chunks() {
+    let mut store = WasmChunkStore::default();
+    store.insert_chunk(vec![1]).unwrap();
+    store.insert_chunk(vec![2]).unwrap();
+    store.clear();
+    assert!(store.is_empty());
+    assert_eq!(store.memory_usage(), NumBytes::from(0));
+}
+
+#[test]
+fn roundtrip_through_proto() {
+    let mut store = WasmChunkStore::default();
+    store.insert_chunk(vec![1, 2]).unwrap();
+    store.insert_chunk(vec![3]).unwrap();
+    let proto = pb::WasmChunkStore::from(&store);
+    assert_eq!(WasmChunkStore::from(proto), store);
+}
@@ -14,7 +14,10 @@ use ic_types::{
     methods::{Callback, WasmClosure},
     Time,
 };
-use ic_types::{messages::MAX_RESPONSE_COUNT_BYTES, CountBytes, Cycles};
+use ic_types::{
+    messages::{MAX_RESPONSE_COUNT_BYTES, NO_DEADLINE},
+    CountBytes, Cycles,
+};
 use ic_wasm_types::CanisterModule;
 
 const INITIAL_CYCLES: Cycles = Cycles::new(1 << 36);
@@ -64,7 +67,7 @@ fn canister_state_push_input_response_no_reservation() {
             .call_context_manager_mut()
             .unwrap()
             .new_call_context(
-                CallOrigin::CanisterUpdate(CANISTER_ID, CallbackId::from(1)),
+                CallOrigin::CanisterUpdate(CANISTER_ID, CallbackId::from(1), NO_DEADLINE),
                 Cycles::zero(),
                 Time::from_nanos_since_unix_epoch(0),
             );
@@ -82,6 +85,7 @@ fn canister_state_push_input_response_no_reservation() {
                 WasmClosure::new(0, 2),
                 WasmClosure::new(0, 2),
                 None,
+                NO_DEADLINE,
             ));
 
         let response: RequestOrResponse = ResponseBuilder::default()
@@ -125,7 +129,7 @@ fn canister_state_push_input_response_success() {
             .call_context_manager_mut()
             .unwrap()
             .new_call_context(
-                CallOrigin::CanisterUpdate(CANISTER_ID, CallbackId::from(1)),
+                CallOrigin::CanisterUpdate(CANISTER_ID, CallbackId::from(1), NO_DEADLINE),
                 Cycles::zero(),
                 Time::from_nanos_since_unix_epoch(0),
             );
@@ -143,6 +147,7 @@ fn canister_state_push_input_response_success() {
                 WasmClosure::new(0, 2),
                 WasmClosure::new(0, 2),
                 None,
+                NO_DEADLINE,
             ));
 
         canister_state
@@ -456,7 +461,7 @@ fn canister_state_push_input_response_memory_limit_test_impl(
             .call_context_manager_mut()
             .unwrap()
             .new_call_context(
-                CallOrigin::CanisterUpdate(CANISTER_ID, CallbackId::from(1)),
+                CallOrigin::CanisterUpdate(CANISTER_ID, CallbackId::from(1), NO_DEADLINE),
                 Cycles::zero(),
                 Time::from_nanos_since_unix_epoch(0),
             );
@@ -474,6 +479,7 @@ fn canister_state_push_input_response_memory_limit_test_impl(
                 WasmClosure::new(0, 2),
                 WasmClosure::new(0, 2),
                 None,
+                NO_DEADLINE,
             ));
 
         let response: RequestOrResponse = ResponseBuilder::default()
@@ -589,6 +595,7 @@ fn canister_state_callback_round_trip() {
         WasmClosure::new(0, 2),
         WasmClosure::new(0, 2),
         None,
+        NO_DEADLINE,
     );
 
     let pb_callback = pb::Callback::from(&callback);
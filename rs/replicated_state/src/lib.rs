@@ -16,7 +16,8 @@ pub use canister_state::{
     num_bytes_try_from,
     system_state::{
         memory_required_to_push_request, CallContext, CallContextAction, CallContextManager,
-        CallOrigin, CanisterMetrics, CanisterStatus, ExecutionTask, SystemState,
+        CallOrigin, CanisterHistory, CanisterMetrics, CanisterStatus, ExecutionTask,
+        ReservationError, SystemState, TotalQueryStats, WasmChunkStore,
     },
     CanisterQueues, CanisterState, EmbedderCache, ExecutionState, ExportedFunctions, Global,
     NumWasmPages, SchedulerState,
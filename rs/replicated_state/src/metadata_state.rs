@@ -1,8 +1,11 @@
+pub mod query_stats;
 pub mod subnet_call_context_manager;
 #[cfg(test)]
 mod tests;
 
-use crate::metadata_state::subnet_call_context_manager::SubnetCallContextManager;
+use crate::metadata_state::{
+    query_stats::QueryStatsAggregator, subnet_call_context_manager::SubnetCallContextManager,
+};
 use ic_base_types::CanisterId;
 use ic_btc_types::Network as BitcoinNetwork;
 use ic_btc_types_internal::BlockBlob;
@@ -141,6 +144,11 @@ pub struct SystemMetadata {
     /// response limit. To work around this limitation, large responses are paginated
     /// and are stored here temporarily until they're fetched by the calling canister.
     pub bitcoin_get_successors_follow_up_responses: BTreeMap<CanisterId, Vec<BlockBlob>>,
+
+    /// The query statistics delivered through consensus that have not been
+    /// aggregated yet. Makes sure that the statistics collected by a node
+    /// during an epoch are delivered at most once.
+    pub query_stats: QueryStatsAggregator,
 }
 
 /// Full description of the IC network toplogy.
@@ -480,6 +488,7 @@ impl From<&SystemMetadata> for pb_metadata::SystemMetadata {
                     },
                 )
                 .collect(),
+            query_stats: Some((&item.query_stats).into()),
         }
     }
 }
@@ -533,6 +542,11 @@ impl TryFrom<pb_metadata::SystemMetadata> for SystemMetadata {
             bitcoin_get_successors_follow_up_responses.insert(sender, response.payloads);
         }
 
+        let query_stats = match item.query_stats {
+            Some(query_stats) => QueryStatsAggregator::try_from(query_stats)?,
+            None => QueryStatsAggregator::default(),
+        };
+
         let batch_time = Time::from_nanos_since_unix_epoch(item.batch_time_nanos);
         Ok(Self {
             own_subnet_id: subnet_id_try_from_protobuf(try_from_option_field(
@@ -576,6 +590,7 @@ impl TryFrom<pb_metadata::SystemMetadata> for SystemMetadata {
             },
             expected_compiled_wasms: BTreeSet::new(),
             bitcoin_get_successors_follow_up_responses,
+            query_stats,
         })
     }
 }
@@ -609,6 +624,7 @@ impl SystemMetadata {
             subnet_metrics: Default::default(),
             expected_compiled_wasms: BTreeSet::new(),
             bitcoin_get_successors_follow_up_responses: BTreeMap::default(),
+            query_stats: QueryStatsAggregator::default(),
         }
     }
 
@@ -978,6 +994,17 @@ impl Stream {
         self.reject_signals.push_back(index)
     }
 
+    /// Returns the total byte size of the best-effort messages in the stream.
+    ///
+    /// Time complexity: O(num_messages).
+    pub fn best_effort_messages_size_bytes(&self) -> usize {
+        self.messages
+            .iter()
+            .filter(|(_, msg)| msg.is_best_effort())
+            .map(|(_, msg)| msg.count_bytes())
+            .sum()
+    }
+
     /// Calculates the estimated byte size of the given messages.
     fn size_bytes(messages: &StreamIndexedQueue<RequestOrResponse>) -> usize {
         messages.iter().map(|(_, m)| m.count_bytes()).sum()
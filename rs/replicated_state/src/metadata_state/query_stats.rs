@@ -0,0 +1,317 @@
+//! Aggregation of the query call statistics delivered through consensus.
+//!
+//! Every node reports the statistics of the queries it executed during an
+//! epoch in the blocks it makes. A single node cannot be trusted, so the
+//! reports of an epoch are only aggregated once at least `2f + 1` nodes of the
+//! subnet delivered theirs. Each statistic of a canister is then estimated as
+//! the median of the values reported by these nodes, multiplied by the number
+//! of nodes in the subnet. With at most `f` faulty nodes, the median is always
+//! bounded by values reported by honest nodes.
+
+use ic_base_types::CanisterId;
+use ic_protobuf::{
+    proxy::{try_from_option_field, ProxyDecodeError},
+    state::system_metadata::v1 as pb_metadata,
+    types::v1 as pb_types,
+};
+use ic_types::{
+    batch::{CanisterQueryStats, QueryStats, QueryStatsEpoch, QueryStatsPayload},
+    node_id_into_protobuf, node_id_try_from_protobuf, NodeId,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+};
+
+/// The maximum number of epochs whose reports are kept while waiting for
+/// enough nodes to deliver theirs. Reports of older epochs are dropped.
+pub const MAX_PENDING_QUERY_STATS_EPOCHS: usize = 16;
+
+/// Query statistics received through consensus that have not been aggregated
+/// yet, together with what is needed to deliver each report at most once.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryStatsAggregator {
+    /// The last epoch delivered by each node of the subnet.
+    delivered: BTreeMap<NodeId, QueryStatsEpoch>,
+    /// The reports of epochs that have not been aggregated yet, by epoch and
+    /// node.
+    received: BTreeMap<QueryStatsEpoch, BTreeMap<NodeId, Vec<CanisterQueryStats>>>,
+    /// The latest epoch whose reports have been aggregated.
+    aggregated: Option<QueryStatsEpoch>,
+}
+
+impl QueryStatsAggregator {
+    /// Returns the last epoch delivered by `node`, if any.
+    pub fn last_delivered(&self, node: &NodeId) -> Option<QueryStatsEpoch> {
+        self.delivered.get(node).cloned()
+    }
+
+    /// Returns the latest epoch whose reports have been aggregated, if any.
+    pub fn last_aggregated(&self) -> Option<QueryStatsEpoch> {
+        self.aggregated
+    }
+
+    /// Records the report in `payload`, given the current `nodes` of the
+    /// subnet. Returns the estimated statistics of every reported canister
+    /// once enough nodes delivered their reports for the epoch.
+    ///
+    /// Reports of nodes that are not part of the subnet, reports for epochs
+    /// that were already delivered by the same node and reports for epochs
+    /// that were already aggregated are ignored.
+    pub fn deliver(
+        &mut self,
+        payload: &QueryStatsPayload,
+        nodes: &BTreeSet<NodeId>,
+    ) -> Option<Vec<CanisterQueryStats>> {
+        // Forget about nodes that left the subnet.
+        self.delivered.retain(|node, _| nodes.contains(node));
+        for reports in self.received.values_mut() {
+            reports.retain(|node, _| nodes.contains(node));
+        }
+        self.received.retain(|_, reports| !reports.is_empty());
+
+        if !nodes.contains(&payload.proposer)
+            || self
+                .last_delivered(&payload.proposer)
+                .map_or(false, |delivered| delivered >= payload.epoch)
+        {
+            return None;
+        }
+        self.delivered.insert(payload.proposer, payload.epoch);
+        if self
+            .aggregated
+            .map_or(false, |aggregated| aggregated >= payload.epoch)
+        {
+            return None;
+        }
+
+        self.received
+            .entry(payload.epoch)
+            .or_default()
+            .insert(payload.proposer, payload.stats.clone());
+        while self.received.len() > MAX_PENDING_QUERY_STATS_EPOCHS {
+            let oldest = *self.received.keys().next().unwrap();
+            self.received.remove(&oldest);
+        }
+
+        let reports = self.received.get(&payload.epoch)?;
+        if reports.len() < quorum(nodes.len()) {
+            return None;
+        }
+        let estimates = aggregate(reports, nodes.len());
+        // Reports of older epochs cannot reach a quorum anymore, because
+        // nodes deliver their reports in order.
+        self.received = self
+            .received
+            .split_off(&QueryStatsEpoch::from(payload.epoch.get() + 1));
+        self.aggregated = Some(payload.epoch);
+        Some(estimates)
+    }
+}
+
+/// The number of reports needed to aggregate an epoch on a subnet of
+/// `num_nodes` nodes, i.e. `2f + 1`.
+fn quorum(num_nodes: usize) -> usize {
+    let f = num_nodes.saturating_sub(1) / 3;
+    2 * f + 1
+}
+
+/// Estimates the statistics of every reported canister as the median of the
+/// values reported by the nodes, multiplied by the number of nodes in the
+/// subnet. Nodes that did not report a canister count as zero.
+fn aggregate(
+    reports: &BTreeMap<NodeId, Vec<CanisterQueryStats>>,
+    num_nodes: usize,
+) -> Vec<CanisterQueryStats> {
+    let mut by_canister: BTreeMap<CanisterId, Vec<QueryStats>> = BTreeMap::new();
+    for stats in reports.values() {
+        for canister_stats in stats {
+            by_canister
+                .entry(canister_stats.canister_id)
+                .or_default()
+                .push(canister_stats.stats);
+        }
+    }
+
+    let num_reports = reports.len();
+    by_canister
+        .into_iter()
+        .map(|(canister_id, mut stats)| {
+            stats.resize(num_reports, QueryStats::default());
+            let median = |mut values: Vec<u64>| -> u64 {
+                values.sort_unstable();
+                values[values.len() / 2].saturating_mul(num_nodes as u64)
+            };
+            CanisterQueryStats {
+                canister_id,
+                stats: QueryStats {
+                    num_calls: median(stats.iter().map(|s| s.num_calls as u64).collect())
+                        .min(u32::MAX as u64) as u32,
+                    num_instructions: median(stats.iter().map(|s| s.num_instructions).collect()),
+                    ingress_payload_size: median(
+                        stats.iter().map(|s| s.ingress_payload_size).collect(),
+                    ),
+                    egress_payload_size: median(
+                        stats.iter().map(|s| s.egress_payload_size).collect(),
+                    ),
+                },
+            }
+        })
+        .collect()
+}
+
+impl From<&QueryStatsAggregator> for pb_metadata::QueryStatsAggregator {
+    fn from(item: &QueryStatsAggregator) -> Self {
+        Self {
+            delivered: item
+                .delivered
+                .iter()
+                .map(|(proposer, epoch)| pb_metadata::QueryStatsDeliveredEntry {
+                    proposer: Some(node_id_into_protobuf(*proposer)),
+                    epoch: epoch.get(),
+                })
+                .collect(),
+            received: item
+                .received
+                .iter()
+                .flat_map(|(epoch, reports)| {
+                    reports.iter().map(move |(proposer, stats)| {
+                        pb_types::QueryStatsPayload::from(&QueryStatsPayload {
+                            proposer: *proposer,
+                            epoch: *epoch,
+                            stats: stats.clone(),
+                        })
+                    })
+                })
+                .collect(),
+            aggregated_epoch: item.aggregated.map(|epoch| epoch.get()),
+        }
+    }
+}
+
+impl TryFrom<pb_metadata::QueryStatsAggregator> for QueryStatsAggregator {
+    type Error = ProxyDecodeError;
+
+    fn try_from(item: pb_metadata::QueryStatsAggregator) -> Result<Self, Self::Error> {
+        let mut delivered = BTreeMap::new();
+        for entry in item.delivered {
+            let proposer = node_id_try_from_protobuf(try_from_option_field(
+                entry.proposer,
+                "QueryStatsDeliveredEntry::proposer",
+            )?)?;
+            delivered.insert(proposer, QueryStatsEpoch::from(entry.epoch));
+        }
+
+        let mut received: BTreeMap<_, BTreeMap<_, _>> = BTreeMap::new();
+        for entry in item.received {
+            let report = QueryStatsPayload::try_from(entry).map_err(ProxyDecodeError::Other)?;
+            received
+                .entry(report.epoch)
+                .or_default()
+                .insert(report.proposer, report.stats);
+        }
+
+        Ok(Self {
+            delivered,
+            received,
+            aggregated: item.aggregated_epoch.map(QueryStatsEpoch::from),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_test_utilities::types::ids::{canister_test_id, node_test_id};
+
+    fn stats(num_calls: u32) -> QueryStats {
+        QueryStats {
+            num_calls,
+            num_instructions: 1_000 * num_calls as u64,
+            ingress_payload_size: 10 * num_calls as u64,
+            egress_payload_size: 20 * num_calls as u64,
+        }
+    }
+
+    fn report(proposer: u64, epoch: u64, num_calls: u32) -> QueryStatsPayload {
+        QueryStatsPayload {
+            proposer: node_test_id(proposer),
+            epoch: epoch.into(),
+            stats: vec![CanisterQueryStats {
+                canister_id: canister_test_id(1),
+                stats: stats(num_calls),
+            }],
+        }
+    }
+
+    fn nodes(num_nodes: u64) -> BTreeSet<NodeId> {
+        (0..num_nodes).map(node_test_id).collect()
+    }
+
+    #[test]
+    fn aggregates_median_once_quorum_is_reached() {
+        // 4 nodes tolerate 1 faulty node, so 3 reports are needed.
+        let nodes = nodes(4);
+        let mut aggregator = QueryStatsAggregator::default();
+        assert_eq!(aggregator.deliver(&report(0, 1, 2), &nodes), None);
+        // A faulty node reports a huge number of calls.
+        assert_eq!(aggregator.deliver(&report(1, 1, 1_000_000), &nodes), None);
+        assert_eq!(
+            aggregator.deliver(&report(2, 1, 4), &nodes),
+            Some(vec![CanisterQueryStats {
+                canister_id: canister_test_id(1),
+                stats: QueryStats {
+                    num_calls: 16,
+                    num_instructions: 16_000,
+                    ingress_payload_size: 160,
+                    egress_payload_size: 320,
+                },
+            }])
+        );
+        assert_eq!(aggregator.last_aggregated(), Some(1.into()));
+
+        // Late reports for an aggregated epoch are only recorded as delivered.
+        assert_eq!(aggregator.deliver(&report(3, 1, 4), &nodes), None);
+        assert_eq!(aggregator.last_delivered(&node_test_id(3)), Some(1.into()));
+        assert!(aggregator.received.is_empty());
+    }
+
+    #[test]
+    fn ignores_duplicate_reports_and_unknown_nodes() {
+        let nodes = nodes(4);
+        let mut aggregator = QueryStatsAggregator::default();
+        assert_eq!(aggregator.deliver(&report(0, 1, 2), &nodes), None);
+        assert_eq!(aggregator.deliver(&report(0, 1, 2), &nodes), None);
+        assert_eq!(aggregator.deliver(&report(0, 0, 2), &nodes), None);
+        assert_eq!(aggregator.deliver(&report(7, 1, 2), &nodes), None);
+        assert_eq!(aggregator.received[&1.into()].len(), 1);
+        assert_eq!(aggregator.last_delivered(&node_test_id(7)), None);
+    }
+
+    #[test]
+    fn forgets_nodes_that_left_the_subnet_and_bounds_pending_epochs() {
+        let mut aggregator = QueryStatsAggregator::default();
+        for epoch in 0..2 * MAX_PENDING_QUERY_STATS_EPOCHS as u64 {
+            aggregator.deliver(&report(0, epoch, 1), &nodes(4));
+        }
+        assert_eq!(aggregator.received.len(), MAX_PENDING_QUERY_STATS_EPOCHS);
+
+        let remaining: BTreeSet<_> = [node_test_id(1)].into_iter().collect();
+        aggregator.deliver(&report(1, 0, 1), &remaining);
+        assert_eq!(aggregator.last_delivered(&node_test_id(0)), None);
+        assert_eq!(aggregator.delivered.len(), 1);
+    }
+
+    #[test]
+    fn roundtrip_through_proto() {
+        let nodes = nodes(4);
+        let mut aggregator = QueryStatsAggregator::default();
+        aggregator.deliver(&report(0, 1, 2), &nodes);
+        aggregator.deliver(&report(1, 1, 3), &nodes);
+        aggregator.deliver(&report(2, 1, 4), &nodes);
+        aggregator.deliver(&report(0, 2, 5), &nodes);
+
+        let proto = pb_metadata::QueryStatsAggregator::from(&aggregator);
+        assert_eq!(QueryStatsAggregator::try_from(proto).unwrap(), aggregator);
+    }
+}
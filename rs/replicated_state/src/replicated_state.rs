@@ -20,15 +20,18 @@ use ic_registry_subnet_features::BitcoinFeatureStatus;
 use ic_registry_subnet_type::SubnetType;
 use ic_types::messages::Ingress;
 use ic_types::{
+    batch::QueryStatsPayload,
     ingress::IngressStatus,
     messages::{CallbackId, MessageId, RequestOrResponse, Response},
+    time::CoarseTime,
     xnet::QueueId,
-    CanisterId, MemoryAllocation, NumBytes, SubnetId, Time,
+    CanisterId, MemoryAllocation, NodeId, NumBytes, SubnetId, Time,
 };
+use ic_wasm_types::{CanisterModule, WasmHash};
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaChaRng;
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, VecDeque};
 use std::sync::Arc;
 
 /// Maximum message length of a synthetic reject response produced by message
@@ -311,7 +314,7 @@ impl std::fmt::Display for StateError {
 // our OP layer.
 // * We don't derive `Hash` because `ingress_history` is a Hashmap that doesn't
 // derive `Hash`.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct ReplicatedState {
     /// States of all canisters, indexed by canister ids.
     pub canister_states: BTreeMap<CanisterId, CanisterState>,
@@ -331,6 +334,33 @@ pub struct ReplicatedState {
     pub consensus_queue: Vec<Response>,
 
     bitcoin: BitcoinState,
+
+    /// The IDs of the canisters that have a Wasm module with a given hash
+    /// installed, derived from `canister_states`. It is updated whenever a
+    /// canister state is put into the replicated state, so it may also contain
+    /// canisters that have since been uninstalled or reinstalled with a
+    /// different module. Readers must check the module of the canister.
+    canisters_by_module_hash: BTreeMap<WasmHash, BTreeSet<CanisterId>>,
+}
+
+impl PartialEq for ReplicatedState {
+    fn eq(&self, rhs: &Self) -> bool {
+        // Skip `canisters_by_module_hash`, since it is derived from the
+        // canister states and may contain different stale entries.
+        (
+            &self.canister_states,
+            &self.metadata,
+            &self.subnet_queues,
+            &self.consensus_queue,
+            &self.bitcoin,
+        ) == (
+            &rhs.canister_states,
+            &rhs.metadata,
+            &rhs.subnet_queues,
+            &rhs.consensus_queue,
+            &rhs.bitcoin,
+        )
+    }
 }
 
 impl ReplicatedState {
@@ -342,6 +372,7 @@ impl ReplicatedState {
             subnet_queues: CanisterQueues::default(),
             consensus_queue: Vec::new(),
             bitcoin: BitcoinState::default(),
+            canisters_by_module_hash: BTreeMap::new(),
         }
     }
 
@@ -358,8 +389,13 @@ impl ReplicatedState {
             subnet_queues,
             consensus_queue,
             bitcoin,
+            canisters_by_module_hash: BTreeMap::new(),
         };
         res.update_stream_responses_size_bytes();
+        let canister_ids: Vec<_> = res.canister_states.keys().cloned().collect();
+        for canister_id in canister_ids {
+            res.index_module_hash(&canister_id);
+        }
         res
     }
 
@@ -372,7 +408,17 @@ impl ReplicatedState {
     }
 
     pub fn take_canister_state(&mut self, canister_id: &CanisterId) -> Option<CanisterState> {
-        self.canister_states.remove(canister_id)
+        let canister_state = self.canister_states.remove(canister_id)?;
+        if let Some(execution_state) = canister_state.execution_state.as_ref() {
+            let module_hash = WasmHash::from(&execution_state.wasm_binary.binary);
+            if let Some(canister_ids) = self.canisters_by_module_hash.get_mut(&module_hash) {
+                canister_ids.remove(canister_id);
+                if canister_ids.is_empty() {
+                    self.canisters_by_module_hash.remove(&module_hash);
+                }
+            }
+        }
+        Some(canister_state)
     }
 
     pub fn take_canister_states(&mut self) -> BTreeMap<CanisterId, CanisterState> {
@@ -389,8 +435,41 @@ impl ReplicatedState {
     /// relevant state associated with the older canister state are properly
     /// cleaned up.
     pub fn put_canister_state(&mut self, canister_state: CanisterState) {
-        self.canister_states
-            .insert(canister_state.canister_id(), canister_state);
+        let canister_id = canister_state.canister_id();
+        self.canister_states.insert(canister_id, canister_state);
+        self.index_module_hash(&canister_id);
+    }
+
+    /// Records the hash of the Wasm module installed on the given canister in
+    /// `canisters_by_module_hash`.
+    fn index_module_hash(&mut self, canister_id: &CanisterId) {
+        if let Some(execution_state) = self
+            .canister_states
+            .get(canister_id)
+            .and_then(|canister_state| canister_state.execution_state.as_ref())
+        {
+            self.canisters_by_module_hash
+                .entry(WasmHash::from(&execution_state.wasm_binary.binary))
+                .or_default()
+                .insert(*canister_id);
+        }
+    }
+
+    /// Returns the Wasm module with the given hash that is installed on one of
+    /// the canisters controlled by `controller`, if any.
+    pub fn find_wasm_module_controlled_by(
+        &self,
+        module_hash: &WasmHash,
+        controller: &PrincipalId,
+    ) -> Option<&CanisterModule> {
+        self.canisters_by_module_hash
+            .get(module_hash)?
+            .iter()
+            .filter_map(|canister_id| self.canister_states.get(canister_id))
+            .filter(|canister_state| canister_state.controllers().contains(controller))
+            .filter_map(|canister_state| canister_state.execution_state.as_ref())
+            .map(|execution_state| &execution_state.wasm_binary.binary)
+            .find(|module| WasmHash::from(*module) == *module_hash)
     }
 
     /// Replaces the content of `self.canister_states` with the provided `canisters`.
@@ -739,6 +818,37 @@ impl ReplicatedState {
         self.subnet_queues.garbage_collect();
     }
 
+    /// Records the query statistics in `payload` and, once enough nodes of
+    /// the subnet delivered their statistics for the epoch, adds the
+    /// aggregated statistics to the totals of the respective canisters.
+    ///
+    /// Payloads of nodes that are not part of the subnet and payloads for an
+    /// epoch that is not newer than the last epoch delivered by the same node
+    /// are ignored, as are statistics of canisters that are not hosted by this
+    /// subnet.
+    pub fn deliver_query_stats(&mut self, payload: &QueryStatsPayload) {
+        let nodes: BTreeSet<NodeId> = self
+            .metadata
+            .network_topology
+            .subnets
+            .get(&self.metadata.own_subnet_id)
+            .map(|subnet| subnet.nodes.keys().cloned().collect())
+            .unwrap_or_default();
+        let aggregated = match self.metadata.query_stats.deliver(payload, &nodes) {
+            Some(aggregated) => aggregated,
+            None => return,
+        };
+
+        for canister_stats in aggregated.iter() {
+            if let Some(canister) = self.canister_states.get_mut(&canister_stats.canister_id) {
+                canister
+                    .system_state
+                    .total_query_stats
+                    .saturating_accumulate(&canister_stats.stats);
+            }
+        }
+    }
+
     /// Returns a reference to the `BitcoinState`.
     pub fn bitcoin(&self) -> &BitcoinState {
         &self.bitcoin
@@ -821,6 +931,113 @@ impl ReplicatedState {
 
         timed_out_requests_count
     }
+
+    /// Enqueues `SYS_UNKNOWN` reject responses for all best-effort callbacks
+    /// that expired without having received a response. Returns the number of
+    /// callbacks that were timed out.
+    ///
+    /// See `SystemState::time_out_callbacks` for further details.
+    #[allow(clippy::needless_collect)]
+    pub fn time_out_callbacks(&mut self, current_time: Time) -> u64 {
+        let current_time = CoarseTime::floor(current_time);
+
+        // Same as for `time_out_requests()`, only remove and replace the canisters
+        // that actually have expired callbacks.
+        let canister_ids_with_expired_callbacks = self
+            .canister_states
+            .iter()
+            .filter(|(_, canister_state)| {
+                canister_state
+                    .system_state
+                    .has_expired_callbacks(current_time)
+            })
+            .map(|(canister_id, _)| *canister_id)
+            .collect::<Vec<_>>();
+
+        let mut timed_out_callbacks_count = 0;
+        for canister_id in canister_ids_with_expired_callbacks {
+            let mut canister = self.canister_states.remove(&canister_id).unwrap();
+            timed_out_callbacks_count += canister.system_state.time_out_callbacks(
+                current_time,
+                &canister_id,
+                &self.canister_states,
+            );
+            self.canister_states.insert(canister_id, canister);
+        }
+
+        timed_out_callbacks_count
+    }
+
+    /// Drops best-effort messages from canister and subnet queues, largest
+    /// first, until the total byte size of best-effort messages is at most
+    /// `limit`. Returns the number of messages that were dropped.
+    ///
+    /// Best-effort messages in streams count towards `limit`, but are never
+    /// dropped, as that would require rewriting stream indices.
+    ///
+    /// Time complexity: O(num_messages + num_shed_messages * (log(num_queues) +
+    /// DEFAULT_QUEUE_CAPACITY)).
+    pub fn shed_best_effort_messages(&mut self, limit: NumBytes) -> u64 {
+        let mut memory_usage: usize = self
+            .canister_states
+            .values()
+            .map(|canister| canister.system_state.best_effort_message_memory_usage())
+            .sum::<usize>()
+            + self.subnet_queues.best_effort_message_memory_usage()
+            + self
+                .metadata
+                .streams
+                .iter()
+                .map(|(_, stream)| stream.best_effort_messages_size_bytes())
+                .sum::<usize>();
+        if memory_usage as u64 <= limit.get() {
+            return 0;
+        }
+
+        // The largest best-effort message of every queue, by byte size, owning
+        // canister (or `None` for the subnet queues) and queue.
+        let mut largest_messages = BinaryHeap::new();
+        for (canister_id, canister) in self.canister_states.iter() {
+            for (size_bytes, queue_id) in canister.system_state.largest_best_effort_messages() {
+                largest_messages.push((size_bytes, Some(*canister_id), queue_id));
+            }
+        }
+        for (size_bytes, queue_id) in self.subnet_queues.largest_best_effort_messages() {
+            largest_messages.push((size_bytes, None, queue_id));
+        }
+
+        let mut shed_messages_count = 0;
+        while memory_usage as u64 > limit.get() {
+            let (_, owner, queue_id) = match largest_messages.pop() {
+                Some(largest) => largest,
+                None => break,
+            };
+            let shed = match owner {
+                Some(canister_id) => {
+                    self.canister_states
+                        .get_mut(&canister_id)
+                        .and_then(|canister| {
+                            canister
+                                .system_state
+                                .shed_largest_best_effort_message_from(queue_id)
+                        })
+                }
+                None => self
+                    .subnet_queues
+                    .shed_largest_best_effort_message_from(queue_id),
+            };
+            let (size_bytes, next_size_bytes) =
+                shed.expect("Message found above so shedding should not fail.");
+            memory_usage -= size_bytes;
+            shed_messages_count += 1;
+
+            if let Some(next_size_bytes) = next_size_bytes {
+                largest_messages.push((next_size_bytes, owner, queue_id));
+            }
+        }
+
+        shed_messages_count
+    }
 }
 
 /// A trait exposing `ReplicatedState` functionality for the exclusive use of
@@ -13,20 +13,23 @@ use ic_replicated_state::testing::{CanisterQueuesTesting, SystemStateTesting};
 use ic_replicated_state::{
     metadata_state::subnet_call_context_manager::BitcoinGetSuccessorsContext,
     replicated_state::PeekableOutputIterator, replicated_state::ReplicatedStateMessageRouting,
-    BitcoinStateError, CanisterState, InputQueueType, ReplicatedState, SchedulerState, StateError,
-    SystemState,
+    BitcoinStateError, CanisterState, InputQueueType, NodeTopology, ReplicatedState,
+    SchedulerState, StateError, Stream, SubnetTopology, SystemState,
 };
 use ic_test_utilities::mock_time;
 use ic_test_utilities::state::{
     arb_replicated_state_with_queues, get_running_canister, register_callback,
 };
-use ic_test_utilities::types::ids::canister_test_id;
+use ic_test_utilities::types::ids::{canister_test_id, node_test_id};
 use ic_test_utilities::types::{
     ids::{subnet_test_id, user_test_id},
     messages::{RequestBuilder, ResponseBuilder},
 };
 use ic_types::{
+    batch::{CanisterQueryStats, QueryStats, QueryStatsEpoch, QueryStatsPayload},
     messages::{CallbackId, Payload, RequestOrResponse, MAX_RESPONSE_COUNT_BYTES},
+    time::CoarseTime,
+    xnet::{StreamIndex, StreamIndexedQueue},
     CountBytes, Cycles, Time,
 };
 use proptest::prelude::*;
@@ -891,6 +894,158 @@ fn time_out_requests_updates_subnet_input_schedules_correctly() {
     assert_eq!(remote_schedule, &VecDeque::from(vec![remote_canister_id]));
 }
 
+#[test]
+fn deliver_query_stats_aggregates_median_once_quorum_is_reached() {
+    replicated_state_test(|mut state| {
+        // A subnet of 4 nodes tolerates 1 faulty node, so 3 reports are needed.
+        state.metadata.network_topology.subnets.insert(
+            SUBNET_ID,
+            SubnetTopology {
+                nodes: (1..=4)
+                    .map(|node| (node_test_id(node), NodeTopology::default()))
+                    .collect(),
+                ..Default::default()
+            },
+        );
+        let stats = |num_calls: u32| QueryStats {
+            num_calls,
+            num_instructions: 1_000 * num_calls as u64,
+            ingress_payload_size: 100 * num_calls as u64,
+            egress_payload_size: 200 * num_calls as u64,
+        };
+        let payload = |proposer, epoch, num_calls| QueryStatsPayload {
+            proposer: node_test_id(proposer),
+            epoch: QueryStatsEpoch::from(epoch),
+            stats: vec![
+                CanisterQueryStats {
+                    canister_id: CANISTER_ID,
+                    stats: stats(num_calls),
+                },
+                // Not hosted by this subnet, silently dropped.
+                CanisterQueryStats {
+                    canister_id: OTHER_CANISTER_ID,
+                    stats: stats(num_calls),
+                },
+            ],
+        };
+        let total = |state: &ReplicatedState| {
+            state
+                .canister_state(&CANISTER_ID)
+                .unwrap()
+                .system_state
+                .total_query_stats
+        };
+
+        state.deliver_query_stats(&payload(1, 5, 3));
+        // A faulty node reports a huge number of calls.
+        state.deliver_query_stats(&payload(2, 5, 1_000_000));
+        // Already delivered by node 1.
+        state.deliver_query_stats(&payload(1, 5, 3));
+        state.deliver_query_stats(&payload(1, 4, 3));
+        // Not a node of the subnet.
+        state.deliver_query_stats(&payload(9, 5, 3));
+        assert_eq!(total(&state), QueryStats::default());
+
+        state.deliver_query_stats(&payload(3, 5, 3));
+        // The median of the reports, times the number of nodes.
+        assert_eq!(total(&state), stats(12));
+        assert_eq!(
+            state.metadata.query_stats.last_delivered(&node_test_id(1)),
+            Some(QueryStatsEpoch::from(5))
+        );
+
+        // Late reports for an aggregated epoch are not counted again.
+        state.deliver_query_stats(&payload(4, 5, 3));
+        assert_eq!(total(&state), stats(12));
+    });
+}
+
+#[test]
+fn shed_best_effort_messages_drops_largest_messages_first() {
+    replicated_state_test(|mut state| {
+        let best_effort_request = |receiver: CanisterId, payload_size: usize| {
+            RequestBuilder::default()
+                .sender(CANISTER_ID)
+                .receiver(receiver)
+                .method_payload(vec![13; payload_size])
+                .deadline(CoarseTime::from_secs_since_unix_epoch(1000))
+                .build()
+        };
+
+        // Best-effort requests in the canister output queues.
+        let small = best_effort_request(OTHER_CANISTER_ID, 1000);
+        let large = best_effort_request(OTHER_CANISTER_ID, 3000);
+        for request in [&small, &large] {
+            state
+                .canister_state_mut(&CANISTER_ID)
+                .unwrap()
+                .push_output_request(Arc::new(request.clone()), mock_time())
+                .unwrap();
+        }
+        // A best-effort request in the subnet input queues.
+        let medium = best_effort_request(SUBNET_ID.into(), 2000);
+        let mut subnet_available_memory = SUBNET_AVAILABLE_MEMORY;
+        state
+            .push_input(
+                medium.clone().into(),
+                MAX_CANISTER_MEMORY_SIZE,
+                &mut subnet_available_memory,
+            )
+            .unwrap();
+        // A best-effort request in a stream, which cannot be dropped.
+        let streamed: RequestOrResponse = best_effort_request(OTHER_CANISTER_ID, 500).into();
+        state.modify_streams(|streams| {
+            let mut stream = Stream::new(StreamIndexedQueue::default(), StreamIndex::new(0));
+            stream.push(streamed.clone());
+            streams.insert(subnet_test_id(1), stream);
+        });
+
+        let total_size = small.count_bytes()
+            + medium.count_bytes()
+            + large.count_bytes()
+            + streamed.count_bytes();
+        // Dropping the large request is not enough, the medium one must go too.
+        let limit = total_size - large.count_bytes() - 1;
+        assert_eq!(
+            2,
+            state.shed_best_effort_messages(NumBytes::from(limit as u64))
+        );
+        assert_eq!(
+            1,
+            state
+                .canister_state(&CANISTER_ID)
+                .unwrap()
+                .system_state
+                .queues()
+                .output_queues_message_count()
+        );
+        assert_eq!(
+            small.count_bytes(),
+            state
+                .canister_state(&CANISTER_ID)
+                .unwrap()
+                .system_state
+                .best_effort_message_memory_usage()
+        );
+        assert_eq!(0, state.subnet_queues().input_queues_message_count());
+
+        // Only the small request can still be dropped, the stream is left alone.
+        assert_eq!(1, state.shed_best_effort_messages(NumBytes::from(0)));
+        assert_eq!(
+            0,
+            state
+                .canister_state(&CANISTER_ID)
+                .unwrap()
+                .system_state
+                .best_effort_message_memory_usage()
+        );
+        assert_eq!(
+            streamed.count_bytes(),
+            state.streams()[&subnet_test_id(1)].best_effort_messages_size_bytes()
+        );
+    });
+}
+
 proptest! {
     #[test]
     fn peek_and_next_consistent(
@@ -300,6 +300,8 @@ pub struct Transaction {
     pub created_at: ::core::option::Option<BlockIndex>,
     #[prost(message, optional, tag = "6")]
     pub created_at_time: ::core::option::Option<TimeStamp>,
+    #[prost(message, optional, tag = "7")]
+    pub icrc1_memo: ::core::option::Option<Icrc1Memo>,
     #[prost(oneof = "transaction::Transfer", tags = "1, 2, 3")]
     pub transfer: ::core::option::Option<transaction::Transfer>,
 }
@@ -366,6 +368,11 @@ pub struct Memo {
     #[prost(uint64, tag = "1")]
     pub memo: u64,
 }
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Icrc1Memo {
+    #[prost(bytes = "vec", tag = "1")]
+    pub memo: ::prost::alloc::vec::Vec<u8>,
+}
 #[derive(
     Eq,
     PartialOrd,
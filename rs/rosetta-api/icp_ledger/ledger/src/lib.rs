@@ -4,15 +4,16 @@ use ic_icrc1::Account;
 use ic_ledger_canister_core::archive::ArchiveCanisterWasm;
 use ic_ledger_canister_core::blockchain::Blockchain;
 use ic_ledger_canister_core::ledger::{self as core_ledger, LedgerData, TransactionInfo};
+use ic_ledger_canister_core::stable_balances::StableBalancesStore;
 use ic_ledger_core::{
     balances::Balances,
-    block::{EncodedBlock, HashOf},
+    block::{EncodedBlock, FeeCollector, HashOf},
     timestamp::TimeStamp,
 };
 use ic_ledger_core::{block::BlockIndex, tokens::Tokens};
 use icp_ledger::{
-    AccountIdentifier, Block, LedgerBalances, Memo, Operation, PaymentError, Transaction,
-    TransferError, TransferFee, DEFAULT_TRANSFER_FEE,
+    AccountIdentifier, Block, Memo, Operation, PaymentError, Transaction, TransferError,
+    TransferFee, DEFAULT_TRANSFER_FEE,
 };
 use intmap::IntMap;
 use lazy_static::lazy_static;
@@ -59,15 +60,13 @@ fn unknown_token() -> String {
     "???".to_string()
 }
 
+/// Account balances kept in stable memory.
+pub type LedgerBalances = Balances<AccountIdentifier, StableBalancesStore<AccountIdentifier>>;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Ledger {
     pub balances: LedgerBalances,
     pub blockchain: Blockchain<dfn_runtime::DfnRuntime, IcpLedgerArchiveWasm>,
-    // A cap on the maximum number of accounts
-    pub maximum_number_of_accounts: usize,
-    // When maximum number of accounts is exceeded, a specified number of
-    // accounts with lowest balances are removed
-    accounts_overflow_trim_quantity: usize,
     pub minting_account_id: Option<AccountIdentifier>,
     pub minting_account_icrc1: Option<Account>,
     // This is a set of BlockIndexs that have been notified
@@ -107,6 +106,7 @@ impl LedgerData for Ledger {
     type ArchiveWasm = IcpLedgerArchiveWasm;
     type Transaction = Transaction;
     type Block = Block;
+    type BalancesStore = StableBalancesStore<AccountIdentifier>;
 
     fn transaction_window(&self) -> Duration {
         self.transaction_window
@@ -120,14 +120,6 @@ impl LedgerData for Ledger {
         Self::MAX_TRANSACTIONS_TO_PURGE
     }
 
-    fn max_number_of_accounts(&self) -> usize {
-        self.maximum_number_of_accounts
-    }
-
-    fn accounts_overflow_trim_quantity(&self) -> usize {
-        self.accounts_overflow_trim_quantity
-    }
-
     fn token_name(&self) -> &str {
         &self.token_name
     }
@@ -136,11 +128,11 @@ impl LedgerData for Ledger {
         &self.token_symbol
     }
 
-    fn balances(&self) -> &Balances<Self::AccountId, HashMap<Self::AccountId, Tokens>> {
+    fn balances(&self) -> &Balances<Self::AccountId, Self::BalancesStore> {
         &self.balances
     }
 
-    fn balances_mut(&mut self) -> &mut Balances<Self::AccountId, HashMap<Self::AccountId, Tokens>> {
+    fn balances_mut(&mut self) -> &mut Balances<Self::AccountId, Self::BalancesStore> {
         &mut self.balances
     }
 
@@ -171,6 +163,14 @@ impl LedgerData for Ledger {
     fn on_purged_transaction(&mut self, height: BlockIndex) {
         self.blocks_notified.remove(height);
     }
+
+    fn fee_collector(&self) -> Option<&FeeCollector<Self::AccountId>> {
+        None
+    }
+
+    fn fee_collector_mut(&mut self) -> Option<&mut FeeCollector<Self::AccountId>> {
+        None
+    }
 }
 
 impl Default for Ledger {
@@ -178,8 +178,6 @@ impl Default for Ledger {
         Self {
             balances: LedgerBalances::default(),
             blockchain: Blockchain::default(),
-            maximum_number_of_accounts: 28_000_000,
-            accounts_overflow_trim_quantity: 100_000,
             minting_account_id: None,
             minting_account_icrc1: None,
             blocks_notified: IntMap::new(),
@@ -230,6 +228,7 @@ impl Ledger {
                 memo,
                 // TODO(FI-349): preserve created_at_time and memo the caller specified.
                 created_at_time: created_at_time.or(Some(now)),
+                icrc1_memo: None,
             },
             now,
         )
@@ -1,17 +1,24 @@
 use candid::{candid_method, Nat};
 use dfn_candid::{candid, candid_one, CandidOne};
 use dfn_core::{
-    api::{caller, data_certificate, print, set_certified_data, trap_with},
+    api::{caller, data_certificate, now, print, set_certified_data, trap_with},
     over, over_async, over_init, printer, setup, stable, BytesS,
 };
 use dfn_protobuf::protobuf;
 use ic_base_types::CanisterId;
-use ic_icrc1::{endpoints::Value, Account};
+use ic_icrc1::{
+    endpoints::{TransferArg, TransferError as Icrc1TransferError, Value},
+    Account,
+};
 use ic_ledger_canister_core::{
     archive::{Archive, ArchiveOptions},
-    ledger::{archive_blocks, block_locations, find_block_in_archive, LedgerAccess},
+    ledger::{
+        apply_transaction, archive_blocks, block_locations, find_block_in_archive, LedgerAccess,
+    },
+    memory::{is_legacy_stable_memory, upgrades_reader, upgrades_writer},
     range_utils,
 };
+use ic_ledger_core::balances::InspectableBalancesStore;
 use ic_ledger_core::{
     block::{BlockIndex, BlockType, EncodedBlock},
     timestamp::TimeStamp,
@@ -22,10 +29,11 @@ use icp_ledger::{
     ArchivedBlocksRange, Archives, BinaryAccountBalanceArgs, Block, BlockArg, BlockRes,
     CandidBlock, Decimals, GetBlocksArgs, IterBlocksArgs, LedgerCanisterInitPayload, Memo, Name,
     Operation, PaymentError, QueryArchiveFn, QueryBlocksResponse, SendArgs, Subaccount, Symbol,
-    TipOfChainRes, TotalSupplyArgs, TransferArgs, TransferError, TransferFee, TransferFeeArgs,
-    MAX_BLOCKS_PER_REQUEST,
+    TipOfChainRes, TotalSupplyArgs, Transaction, TransferArgs, TransferError, TransferFee,
+    TransferFeeArgs, MAX_BLOCKS_PER_REQUEST,
 };
 use ledger_canister::{Ledger, LEDGER, MAX_MESSAGE_SIZE_BYTES};
+use serde_bytes::ByteBuf;
 use std::{
     collections::{HashMap, HashSet},
     sync::{Arc, RwLock},
@@ -205,6 +213,103 @@ async fn send(
     Ok(height)
 }
 
+/// Transfers tokens following the ICRC-1 standard. ICRC-1 accounts are mapped
+/// to their ICP account identifiers and the ICRC-1 memo is stored in the block
+/// next to the (zero) ICP memo.
+///
+/// Unlike `send`, the transaction is only deduplicated if the caller sets
+/// `created_at_time`, and a missing `fee` means "whatever the ledger expects".
+async fn icrc1_send(arg: TransferArg) -> Result<BlockIndex, Icrc1TransferError> {
+    let from_account = Account {
+        owner: caller(),
+        subaccount: arg.from_subaccount,
+    };
+    if !LEDGER.read().unwrap().can_send(&from_account.owner) {
+        trap_with(&format!(
+            "Sending from {} is not allowed",
+            from_account.owner
+        ));
+    }
+    let from = AccountIdentifier::from(from_account);
+    let to = AccountIdentifier::from(arg.to);
+
+    let height = {
+        let mut ledger = LEDGER.write().unwrap();
+        let minting_acc = ledger
+            .minting_account_id
+            .expect("Minting canister id not initialized");
+
+        let amount = match u64::try_from(&arg.amount.0) {
+            Ok(e8s) => Tokens::from_e8s(e8s),
+            Err(_) => {
+                // No account can hold more than u64::MAX e8s.
+                let balance = Nat::from(ledger.balances.account_balance(&from).get_e8s());
+                return Err(Icrc1TransferError::InsufficientFunds { balance });
+            }
+        };
+
+        let expected_fee = if from == minting_acc || to == minting_acc {
+            Tokens::ZERO
+        } else {
+            ledger.transfer_fee
+        };
+        if let Some(fee) = arg.fee {
+            if fee != Nat::from(expected_fee.get_e8s()) {
+                return Err(Icrc1TransferError::BadFee {
+                    expected_fee: Nat::from(expected_fee.get_e8s()),
+                });
+            }
+        }
+
+        let operation = if from == minting_acc {
+            if to == minting_acc {
+                trap_with("It is illegal to mint to a minting_account");
+            }
+            Operation::Mint { to, amount }
+        } else if to == minting_acc {
+            // Accounts holding less than the fee can only burn their whole balance.
+            let balance = ledger.balances.account_balance(&from);
+            let min_burn_amount = ledger.transfer_fee.min(balance);
+            if amount < min_burn_amount {
+                return Err(Icrc1TransferError::BadBurn {
+                    min_burn_amount: Nat::from(min_burn_amount.get_e8s()),
+                });
+            }
+            if amount == Tokens::ZERO {
+                return Err(Icrc1TransferError::BadBurn {
+                    min_burn_amount: Nat::from(ledger.transfer_fee.get_e8s()),
+                });
+            }
+            Operation::Burn { from, amount }
+        } else {
+            Operation::Transfer {
+                from,
+                to,
+                amount,
+                fee: expected_fee,
+            }
+        };
+
+        let transaction = Transaction {
+            operation,
+            memo: Memo::default(),
+            created_at_time: arg
+                .created_at_time
+                .map(TimeStamp::from_nanos_since_unix_epoch),
+            icrc1_memo: arg.memo.map(ByteBuf::from),
+        };
+        let (height, hash) = apply_transaction(&mut *ledger, transaction, now().into())
+            .map_err(Icrc1TransferError::from)?;
+        set_certified_data(&hash.into_bytes());
+        height
+    };
+
+    // Don't put anything that could ever trap after this call, see `send`.
+    let max_msg_size = *MAX_MESSAGE_SIZE_BYTES.read().unwrap();
+    archive_blocks::<Access>(max_msg_size).await;
+    Ok(height)
+}
+
 /// You can notify a canister that you have made a payment to it. The
 /// payment must have been made to the account of a canister and from the
 /// callers account. You cannot notify a canister about a transaction it has
@@ -534,10 +639,17 @@ fn main() {
 fn post_upgrade() {
     over_init(|_: BytesS| {
         let mut ledger = LEDGER.write().unwrap();
-        *ledger = ciborium::de::from_reader(stable::StableReader::new())
-            .expect("Decoding stable memory failed");
-
-        ledger.maximum_number_of_accounts = 28_000_000;
+        // Ledgers that kept balances on the heap wrote their whole state
+        // to the stable memory with a length prefix.  Decoding such a state
+        // moves the balances to the stable B-tree, so we must copy the state
+        // out before the memory manager takes over the stable memory.
+        *ledger = if is_legacy_stable_memory() {
+            let state = stable::get();
+            ciborium::de::from_reader(&state[..])
+        } else {
+            ciborium::de::from_reader(upgrades_reader())
+        }
+        .expect("Decoding stable memory failed");
 
         set_certified_data(
             &ledger
@@ -559,7 +671,7 @@ fn pre_upgrade() {
         .read()
         // This should never happen, but it's better to be safe than sorry
         .unwrap_or_else(|poisoned| poisoned.into_inner());
-    ciborium::ser::into_writer(&*ledger, stable::StableWriter::new())
+    ciborium::ser::into_writer(&*ledger, upgrades_writer())
         .expect("failed to write ledger state to stable memory");
 }
 
@@ -668,6 +780,16 @@ fn transfer() {
     over_async(candid_one, transfer_candid)
 }
 
+#[candid_method(update, rename = "icrc1_transfer")]
+async fn icrc1_transfer(arg: TransferArg) -> Result<Nat, Icrc1TransferError> {
+    Ok(Nat::from(icrc1_send(arg).await?))
+}
+
+#[export_name = "canister_update icrc1_transfer"]
+fn icrc1_transfer_candid() {
+    over_async(candid_one, icrc1_transfer)
+}
+
 /// See caveats of use on send_dfx
 #[cfg(feature = "notify-method")]
 #[export_name = "canister_update notify_dfx"]
@@ -994,7 +1116,7 @@ fn encode_metrics(w: &mut ic_metrics_encoder::MetricsEncoder<Vec<u8>>) -> std::i
     )?;
     w.encode_gauge(
         "ledger_balance_store_entries",
-        ledger.balances.store.len() as f64,
+        ledger.balances.store.num_accounts() as f64,
         "Total number of accounts in the balance store.",
     )?;
     w.encode_gauge(
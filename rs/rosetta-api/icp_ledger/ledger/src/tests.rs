@@ -2,39 +2,32 @@ use crate::Ledger;
 use ic_base_types::{CanisterId, PrincipalId};
 use ic_ledger_canister_core::{archive::Archive, ledger as core_ledger, ledger::LedgerTransaction};
 use ic_ledger_core::{
+    balances::InspectableBalancesStore,
     block::{BlockIndex, BlockType},
     timestamp::TimeStamp,
     tokens::Tokens,
 };
 use icp_ledger::{
-    apply_operation, ArchiveOptions, Block, LedgerBalances, Memo, Operation, PaymentError,
-    Transaction, TransferError, DEFAULT_TRANSFER_FEE,
+    apply_operation, AccountIdentifier, ArchiveOptions, Block, LedgerBalances, Memo, Operation,
+    PaymentError, Transaction, TransferError, DEFAULT_TRANSFER_FEE,
 };
-use std::collections::HashSet;
+use serde_bytes::ByteBuf;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, SystemTime};
 
 #[test]
-fn balances_overflow() {
-    let balances = LedgerBalances::new();
+fn balances_are_not_trimmed() {
     let mut state = Ledger {
-        balances,
-        maximum_number_of_accounts: 8,
-        accounts_overflow_trim_quantity: 2,
         minting_account_id: Some(PrincipalId::new_user_test_id(137).into()),
         ..Default::default()
     };
     assert_eq!(state.balances.token_pool, Tokens::MAX);
-    println!(
-        "minting canister initial balance: {}",
-        state.balances.token_pool
-    );
     let mut credited = Tokens::ZERO;
 
-    // 11 accounts. The one with 0 will not be added
-    // The rest will be added and trigger a trim of 2 once
-    // the total number reaches 8 + 2
-    // the number of active accounts won't go below 8 after trimming
+    // 11 accounts. The one with 0 will not be added, all the others are kept
+    // no matter how low their balances are, since finding the accounts with
+    // the lowest balances would take a scan of the balances in stable memory.
     for i in 0..11 {
         let amount = Tokens::new(i, 0).unwrap();
         state
@@ -49,31 +42,16 @@ fn balances_overflow() {
             .unwrap();
         credited += amount
     }
-    println!("amount credited to accounts: {}", credited);
-
-    println!("balances: {:?}", state.balances);
 
-    // The two accounts with lowest balances, 0 and 1 respectively, have been
-    // removed
-    assert_eq!(state.balances.store.len(), 8);
-    assert_eq!(
-        state
-            .balances
-            .account_balance(&PrincipalId::new_user_test_id(0).into()),
-        Tokens::ZERO
-    );
+    assert_eq!(state.balances.store.num_accounts(), 10);
     assert_eq!(
         state
             .balances
             .account_balance(&PrincipalId::new_user_test_id(1).into()),
-        Tokens::ZERO
-    );
-    // We have credited 55 Tokens to various accounts but the three accounts
-    // with lowest balances, 0, 1 and 2, should have been removed and their
-    // balance returned to the minting canister
-    let expected_minting_canister_balance =
-        ((Tokens::MAX - credited).unwrap() + Tokens::new(1 + 2, 0).unwrap()).unwrap();
-    assert_eq!(state.balances.token_pool, expected_minting_canister_balance);
+        Tokens::new(1, 0).unwrap()
+    );
+    assert_eq!(state.balances.token_pool, (Tokens::MAX - credited).unwrap());
+    assert_eq!(state.blockchain.blocks.len(), 11);
 }
 
 #[test]
@@ -275,7 +253,76 @@ fn serialize() {
         state.blockchain.blocks.len(),
         state_decoded.blockchain.blocks.len()
     );
-    assert_eq!(state.balances.store, state_decoded.balances.store);
+    assert_eq!(state.balances.token_pool, state_decoded.balances.token_pool);
+
+    // The balances of both ledgers live in the same stable memory, so compare
+    // the decoded ledger against balances computed from the transactions
+    // rather than against the original ledger.
+    let fee = DEFAULT_TRANSFER_FEE.get_e8s();
+    let sent1 = Tokens::new(10000, 50).unwrap().get_e8s();
+    let sent2 = Tokens::new(30000, 10000).unwrap().get_e8s();
+    let expected_balances: HashMap<AccountIdentifier, Tokens> = vec![
+        (
+            PrincipalId::new_user_test_id(0).into(),
+            Tokens::from_e8s(
+                Tokens::new(2000000, 0).unwrap().get_e8s() - sent1 - fee - sent2 - fee,
+            ),
+        ),
+        (
+            PrincipalId::new_user_test_id(1).into(),
+            Tokens::from_e8s(sent1),
+        ),
+        (
+            PrincipalId::new_user_test_id(200).into(),
+            Tokens::from_e8s(sent2),
+        ),
+    ]
+    .into_iter()
+    .collect();
+
+    assert_eq!(
+        state_decoded.balances.store.num_accounts(),
+        expected_balances.len()
+    );
+    assert_eq!(
+        state_decoded
+            .balances
+            .store
+            .iter_balances()
+            .collect::<HashMap<_, _>>(),
+        expected_balances
+    );
+    for (account, balance) in &expected_balances {
+        assert_eq!(state_decoded.balances.account_balance(account), *balance);
+    }
+}
+
+#[test]
+fn migrate_heap_balances_to_stable_memory() {
+    let mut heap_balances = LedgerBalances::new();
+    for i in 1..=10 {
+        apply_operation(
+            &mut heap_balances,
+            &Operation::Mint {
+                to: PrincipalId::new_user_test_id(i).into(),
+                amount: Tokens::from_e8s(i * 1_000),
+            },
+        )
+        .unwrap();
+    }
+
+    let bytes = serde_cbor::to_vec(&heap_balances).unwrap();
+    let stable_balances: crate::LedgerBalances = serde_cbor::from_slice(&bytes).unwrap();
+
+    assert_eq!(stable_balances.store.num_accounts(), 10);
+    assert_eq!(stable_balances.token_pool, heap_balances.token_pool);
+    for i in 1..=10 {
+        let account = PrincipalId::new_user_test_id(i).into();
+        assert_eq!(
+            stable_balances.account_balance(&account),
+            heap_balances.account_balance(&account)
+        );
+    }
 }
 
 /// Check that 'created_at_time' is not too far in the past or
@@ -781,3 +828,28 @@ fn test_transaction_hash_consistency() {
         "Transaction hash must be stable."
     );
 }
+
+/// Check that the ICRC-1 memo survives the block encoding and that it is part
+/// of the transaction hash used for deduplication.
+#[test]
+fn test_icrc1_memo_encoding() {
+    let mut transaction = Transaction::new(
+        PrincipalId::new_user_test_id(0).into(),
+        PrincipalId::new_user_test_id(1).into(),
+        Tokens::new(1, 0).unwrap(),
+        DEFAULT_TRANSFER_FEE,
+        Memo::default(),
+        TimeStamp::new(1, 0),
+    );
+    let legacy_hash = transaction.hash();
+    transaction.icrc1_memo = Some(ByteBuf::from(b"icrc1 memo".to_vec()));
+    assert_ne!(legacy_hash, transaction.hash());
+
+    let block = Block {
+        parent_hash: None,
+        transaction,
+        timestamp: TimeStamp::new(2, 0),
+    };
+    let block_decoded = Block::decode(block.clone().encode()).unwrap();
+    assert_eq!(block, block_decoded);
+}
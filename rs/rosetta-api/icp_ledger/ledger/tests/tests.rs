@@ -49,3 +49,28 @@ fn test_total_supply() {
 fn test_minting_account() {
     ic_icrc1_ledger_sm_tests::test_minting_account(ledger_wasm(), encode_init_args)
 }
+
+#[test]
+fn test_single_transfer() {
+    ic_icrc1_ledger_sm_tests::test_single_transfer(ledger_wasm(), encode_init_args);
+}
+
+#[test]
+fn test_tx_deduplication() {
+    ic_icrc1_ledger_sm_tests::test_tx_deduplication(ledger_wasm(), encode_init_args);
+}
+
+#[test]
+fn test_mint_burn() {
+    ic_icrc1_ledger_sm_tests::test_mint_burn(ledger_wasm(), encode_init_args);
+}
+
+#[test]
+fn test_tx_time_bounds() {
+    ic_icrc1_ledger_sm_tests::test_tx_time_bounds(ledger_wasm(), encode_init_args);
+}
+
+#[test]
+fn test_upgrade_preserves_balances() {
+    ic_icrc1_ledger_sm_tests::test_upgrade_preserves_balances(ledger_wasm(), encode_init_args);
+}
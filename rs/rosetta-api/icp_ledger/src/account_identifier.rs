@@ -2,6 +2,7 @@ use candid::CandidType;
 use dfn_core::CanisterId;
 use ic_base_types::{CanisterIdError, PrincipalId, PrincipalIdError};
 use ic_crypto_sha::Sha224;
+use ic_ledger_canister_core::stable_balances::StorableAccount;
 use serde::{de, de::Error, Deserialize, Serialize};
 use std::{
     convert::{TryFrom, TryInto},
@@ -50,6 +51,20 @@ impl From<ic_icrc1::Account> for AccountIdentifier {
     }
 }
 
+impl StorableAccount for AccountIdentifier {
+    fn to_storable_bytes(&self) -> Vec<u8> {
+        self.hash.to_vec()
+    }
+
+    fn from_storable_bytes(bytes: &[u8]) -> Self {
+        Self {
+            hash: bytes
+                .try_into()
+                .expect("BUG: stored account identifier must be 28 bytes long"),
+        }
+    }
+}
+
 pub static SUB_ACCOUNT_ZERO: Subaccount = Subaccount([0; 32]);
 static ACCOUNT_DOMAIN_SEPERATOR: &[u8] = b"\x0Aaccount-id";
 
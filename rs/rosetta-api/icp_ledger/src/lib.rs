@@ -7,10 +7,11 @@ pub use ic_ledger_canister_core::archive::ArchiveOptions;
 use ic_ledger_canister_core::ledger::LedgerTransaction;
 use ic_ledger_core::{
     balances::{BalanceError, Balances, BalancesStore},
-    block::{BlockType, EncodedBlock, HashOf, HASH_LENGTH},
+    block::{BlockType, EncodedBlock, FeeCollector, HashOf, HASH_LENGTH},
 };
 use on_wire::{FromWire, IntoWire};
 use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
@@ -103,7 +104,7 @@ where
             to,
             amount,
             fee,
-        } => balances.transfer(from, to, *amount, *fee),
+        } => balances.transfer(from, to, *amount, *fee, None),
         Operation::Burn { from, amount, .. } => balances.burn(from, *amount),
         Operation::Mint { to, amount, .. } => balances.mint(to, *amount),
     }
@@ -119,6 +120,11 @@ pub struct Transaction {
 
     /// The time this transaction was created.
     pub created_at_time: Option<TimeStamp>,
+
+    /// The memo attached to transactions created via the ICRC-1 interface.
+    /// Skipped when absent so that hashes of legacy transactions stay the same.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icrc1_memo: Option<ByteBuf>,
 }
 
 impl LedgerTransaction for Transaction {
@@ -134,6 +140,7 @@ impl LedgerTransaction for Transaction {
             operation: Operation::Burn { from, amount },
             memo: memo.map(Memo).unwrap_or_default(),
             created_at_time,
+            icrc1_memo: None,
         }
     }
 
@@ -147,10 +154,19 @@ impl LedgerTransaction for Transaction {
         HashOf::new(state.finish())
     }
 
-    fn apply<S>(&self, balances: &mut Balances<Self::AccountId, S>) -> Result<(), BalanceError>
+    fn is_transfer(&self) -> bool {
+        matches!(self.operation, Operation::Transfer { .. })
+    }
+
+    fn apply<S>(
+        &self,
+        balances: &mut Balances<Self::AccountId, S>,
+        _fee_collector: Option<&Self::AccountId>,
+    ) -> Result<(), BalanceError>
     where
         S: Default + BalancesStore<Self::AccountId>,
     {
+        // The ICP ledger always burns the fees.
         apply_operation(balances, &self.operation)
     }
 }
@@ -174,6 +190,7 @@ impl Transaction {
             operation,
             memo,
             created_at_time: Some(created_at_time),
+            icrc1_memo: None,
         }
     }
 }
@@ -199,8 +216,14 @@ impl Block {
             operation,
             memo,
             created_at_time: Some(created_at_time),
+            icrc1_memo: None,
         };
-        Ok(Self::from_transaction(parent_hash, transaction, timestamp))
+        Ok(Self::from_transaction(
+            parent_hash,
+            transaction,
+            timestamp,
+            None,
+        ))
     }
 
     #[inline]
@@ -209,7 +232,7 @@ impl Block {
         transaction: Transaction,
         timestamp: TimeStamp,
     ) -> Self {
-        Self::from_transaction(parent_hash, transaction, timestamp)
+        Self::from_transaction(parent_hash, transaction, timestamp, None)
     }
 
     pub fn transaction(&self) -> Cow<Transaction> {
@@ -219,6 +242,7 @@ impl Block {
 
 impl BlockType for Block {
     type Transaction = Transaction;
+    type AccountId = AccountIdentifier;
 
     fn encode(self) -> EncodedBlock {
         EncodedBlock::from_vec(
@@ -250,6 +274,7 @@ impl BlockType for Block {
         parent_hash: Option<HashOf<EncodedBlock>>,
         transaction: Self::Transaction,
         timestamp: TimeStamp,
+        _fee_collector: Option<FeeCollector<Self::AccountId>>,
     ) -> Self {
         Self {
             parent_hash,
@@ -611,6 +636,7 @@ impl From<Operation> for CandidOperation {
 pub struct CandidTransaction {
     pub operation: CandidOperation,
     pub memo: Memo,
+    pub icrc1_memo: Option<ByteBuf>,
     pub created_at_time: TimeStamp,
 }
 
@@ -633,6 +659,7 @@ impl From<Block> for CandidBlock {
             parent_hash: parent_hash.map(|h| h.into_bytes()),
             transaction: CandidTransaction {
                 memo: transaction.memo,
+                icrc1_memo: transaction.icrc1_memo,
                 operation: transaction.operation.into(),
                 created_at_time: transaction.created_at_time.unwrap_or(timestamp),
             },
@@ -10,6 +10,7 @@ use dfn_protobuf::ToProto;
 use ic_base_types::{CanisterId, CanisterIdError};
 use ic_ledger_core::block::HASH_LENGTH;
 use protobuf::cycles_notification_response::Response;
+use serde_bytes::ByteBuf;
 use std::convert::{TryFrom, TryInto};
 
 /// The point of this file is to validate protobufs as they're received and turn
@@ -569,6 +570,7 @@ impl ToProto for Transaction {
             None => Memo(0),
         };
         let created_at_time: Option<TimeStamp> = pb.created_at_time.map(timestamp_from_proto);
+        let icrc1_memo = pb.icrc1_memo.map(|m| ByteBuf::from(m.memo));
         let operation = match pb.transfer.ok_or("This block has no transaction")? {
             PTransfer::Burn(protobuf::Burn {
                 from: Some(from),
@@ -604,6 +606,7 @@ impl ToProto for Transaction {
             operation,
             memo,
             created_at_time,
+            icrc1_memo,
         })
     }
 
@@ -612,6 +615,7 @@ impl ToProto for Transaction {
             memo,
             created_at_time,
             operation,
+            icrc1_memo,
         } = self;
         let transfer = match operation {
             Operation::Burn { from, amount } => PTransfer::Burn(protobuf::Burn {
@@ -640,6 +644,7 @@ impl ToProto for Transaction {
             memo: Some(protobuf::Memo { memo: memo.0 }),
             created_at: None,
             created_at_time: created_at_time.map(timestamp_into_proto),
+            icrc1_memo: icrc1_memo.map(|m| protobuf::Icrc1Memo { memo: m.into_vec() }),
             transfer: Some(transfer),
         }
     }
@@ -1,9 +1,13 @@
 use candid::{candid_method, Principal};
 use ic_cdk_macros::{init, post_upgrade, query, update};
+use ic_crypto_tree_hash::{Label, MixedHashTree};
 use ic_icrc1::{
-    endpoints::{GetTransactionsRequest, Transaction, TransactionRange},
+    endpoints::{
+        BlockRange, GetBlocksRequest, GetTransactionsRequest, Transaction, TransactionRange,
+    },
     Block,
 };
+use ic_ledger_canister_core::mmr::MerkleMountainRange;
 use ic_ledger_core::block::{BlockIndex, BlockType, EncodedBlock};
 use ic_stable_structures::memory_manager::{MemoryId, VirtualMemory};
 use ic_stable_structures::{
@@ -11,8 +15,10 @@ use ic_stable_structures::{
     DefaultMemoryImpl, RestrictedMemory, Storable,
 };
 use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
 use std::borrow::Cow;
 use std::cell::RefCell;
+use std::ops::Range;
 
 const WASM_PAGE_SIZE: u64 = 65536;
 
@@ -24,15 +30,22 @@ const DEFAULT_MEMORY_LIMIT: usize = 3 * GIB;
 /// The maximum number of blocks to return in a single get_transactions request.
 const DEFAULT_MAX_TRANSACTIONS_PER_GET_TRANSACTION_RESPONSE: usize = 2000;
 
+/// The maximum number of blocks to add to the block hash tree in a single
+/// message.  Archives created before the tree was introduced catch up
+/// gradually in the global timer.
+const MAX_BLOCKS_TO_HASH_PER_MESSAGE: usize = 10_000;
+
 /// The maximum number of Wasm pages that we allow to use for the stable storage.
 const NUM_WASM_PAGES: u64 = 4 * (GIB as u64) / WASM_PAGE_SIZE;
 
 const BLOCK_LOG_INDEX_MEMORY_ID: MemoryId = MemoryId::new(0);
 const BLOCK_LOG_DATA_MEMORY_ID: MemoryId = MemoryId::new(1);
+const BLOCK_HASH_TREE_MEMORY_ID: MemoryId = MemoryId::new(2);
 
 type Memory = RestrictedMemory<DefaultMemoryImpl>;
 type BlockLog = StableLog<VirtualMemory<Memory>, VirtualMemory<Memory>>;
 type ConfigCell = StableCell<ArchiveConfig, Memory>;
+type BlockHashTree = MerkleMountainRange<VirtualMemory<Memory>>;
 
 /// Creates a memory region for the configuration stable cell.
 fn config_memory() -> Memory {
@@ -58,6 +71,11 @@ thread_local! {
     static BLOCKS: RefCell<BlockLog> = with_memory_manager(|memory_manager| {
         RefCell::new(BlockLog::init(memory_manager.get(BLOCK_LOG_INDEX_MEMORY_ID), memory_manager.get(BLOCK_LOG_DATA_MEMORY_ID)).expect("failed to initialize stable log"))
     });
+
+    /// Merkle mountain range over the hashes of the blocks in [BLOCKS].
+    static BLOCK_HASH_TREE: RefCell<BlockHashTree> = with_memory_manager(|memory_manager| {
+        RefCell::new(BlockHashTree::init(memory_manager.get(BLOCK_HASH_TREE_MEMORY_ID)))
+    });
 }
 
 /// Configuration of the archive node.
@@ -114,6 +132,55 @@ fn with_blocks<R>(f: impl FnOnce(&BlockLog) -> R) -> R {
     BLOCKS.with(|cell| f(&cell.borrow()))
 }
 
+/// A helper function to access the block hash tree.
+fn with_block_hash_tree<R>(f: impl FnOnce(&mut BlockHashTree) -> R) -> R {
+    BLOCK_HASH_TREE.with(|cell| f(&mut cell.borrow_mut()))
+}
+
+/// Returns true if the block hash tree does not cover all the blocks yet.
+fn block_hash_tree_is_behind() -> bool {
+    let offset = with_archive_opts(|opts| opts.block_index_offset);
+    let num_blocks = with_blocks(|blocks| blocks.len() as u64);
+    with_block_hash_tree(|tree| tree.block_range().end < offset + num_blocks)
+}
+
+/// Adds the hashes of at most [MAX_BLOCKS_TO_HASH_PER_MESSAGE] blocks that
+/// are not in the block hash tree yet and certifies the new root hash.
+fn update_block_hash_tree() {
+    let offset = with_archive_opts(|opts| opts.block_index_offset);
+    with_blocks(|blocks| {
+        with_block_hash_tree(|tree| {
+            let num_hashed = tree.block_range().end.saturating_sub(offset) as usize;
+            let limit = blocks
+                .len()
+                .min(num_hashed.saturating_add(MAX_BLOCKS_TO_HASH_PER_MESSAGE));
+            for i in num_hashed..limit {
+                let block = EncodedBlock::from(blocks.get(i).unwrap());
+                tree.append(offset + i as u64, Block::block_hash(&block).into_bytes());
+            }
+        })
+    });
+    certify_block_hash_tree();
+}
+
+/// Sets the certified data to the root hash of the block hash tree.
+fn certify_block_hash_tree() {
+    ic_cdk::api::set_certified_data(&certified_tree(0..0).digest().0);
+}
+
+/// Returns the tree of the certified archive state revealing the hashes of the
+/// specified blocks.
+fn certified_tree(blocks: Range<BlockIndex>) -> MixedHashTree {
+    with_block_hash_tree(|tree| {
+        let blocks_tree = if blocks.is_empty() {
+            tree.root()
+        } else {
+            tree.witness(blocks)
+        };
+        MixedHashTree::Labeled(Label::from("blocks"), Box::new(blocks_tree))
+    })
+}
+
 fn decode_transaction(txid: u64, bytes: Vec<u8>) -> Transaction {
     Block::decode(EncodedBlock::from(bytes))
         .unwrap_or_else(|e| ic_cdk::api::trap(&format!("failed to decode block {}: {}", txid, e)))
@@ -153,6 +220,9 @@ fn init(
                 memory_manager.get(BLOCK_LOG_DATA_MEMORY_ID),
             )
         });
+        BLOCK_HASH_TREE.with(|cell| {
+            *cell.borrow_mut() = BlockHashTree::init(memory_manager.get(BLOCK_HASH_TREE_MEMORY_ID))
+        });
     })
 }
 
@@ -165,6 +235,39 @@ fn post_upgrade() {
     // the upgrade if the initialization traps.
     let max_memory_size_bytes = with_archive_opts(|opts| opts.max_memory_size_bytes);
     with_blocks(|blocks| assert!(blocks.log_size_bytes() <= max_memory_size_bytes));
+    // Archives created before the block hash tree was introduced hash their
+    // blocks in the global timer, so the upgrade does not depend on the number
+    // of blocks.
+    certify_block_hash_tree();
+    if block_hash_tree_is_behind() {
+        schedule_block_hash_tree_update();
+    }
+}
+
+/// Arms the global timer so that the next round continues hashing the blocks
+/// that are not in the block hash tree yet.
+fn schedule_block_hash_tree_update() {
+    #[cfg(target_arch = "wasm32")]
+    {
+        #[link(wasm_import_module = "ic0")]
+        extern "C" {
+            fn global_timer_set(timestamp: i64) -> i64;
+        }
+        // SAFETY: global_timer_set only replaces the timer deadline.
+        unsafe {
+            global_timer_set(ic_cdk::api::time() as i64);
+        }
+    }
+}
+
+/// Hashes the next batch of blocks and re-arms the timer until the block hash
+/// tree covers all the blocks.
+#[export_name = "canister_global_timer"]
+fn global_timer() {
+    update_block_hash_tree();
+    if block_hash_tree_is_behind() {
+        schedule_block_hash_tree_update();
+    }
 }
 
 #[update]
@@ -190,7 +293,8 @@ fn append_blocks(new_blocks: Vec<EncodedBlock>) {
                 .append(block.as_slice())
                 .unwrap_or_else(|_| ic_cdk::api::trap("no space left"));
         }
-    })
+    });
+    update_block_hash_tree();
 }
 
 #[query]
@@ -241,6 +345,47 @@ fn get_transactions(req: GetTransactionsRequest) -> TransactionRange {
     TransactionRange { transactions }
 }
 
+#[query]
+#[candid_method(query)]
+fn get_blocks(req: GetBlocksRequest) -> BlockRange {
+    let (start, length) = req
+        .as_start_and_length()
+        .unwrap_or_else(|msg| ic_cdk::api::trap(&msg));
+
+    let offset = with_archive_opts(|opts| {
+        if start < opts.block_index_offset {
+            ic_cdk::api::trap(&format!(
+                "requested index {} is less than the minimal index {} this archive serves",
+                start, opts.block_index_offset
+            ));
+        }
+        (start - opts.block_index_offset) as usize
+    });
+
+    let length = length.min(with_archive_opts(|opts| opts.max_transactions_per_response));
+    let blocks: Vec<EncodedBlock> = with_blocks(|blocks| {
+        let limit = blocks.len().min(offset.saturating_add(length));
+        (offset..limit)
+            .map(|i| EncodedBlock::from(blocks.get(i).unwrap()))
+            .collect()
+    });
+
+    // The tree might not cover all the blocks yet if the archive is still
+    // catching up after an upgrade.
+    let certified = with_block_hash_tree(|tree| tree.block_range());
+    let first = start.max(certified.start);
+    let last = (start + blocks.len() as u64).min(certified.end).max(first);
+    let mut hash_tree = vec![];
+    ciborium::ser::into_writer(&certified_tree(first..last), &mut hash_tree)
+        .expect("failed to encode the hash tree");
+
+    BlockRange {
+        blocks,
+        certificate: ic_cdk::api::data_certificate().map(ByteBuf::from),
+        hash_tree: ByteBuf::from(hash_tree),
+    }
+}
+
 #[query]
 fn __get_candid_interface_tmp_hack() -> &'static str {
     include_str!(env!("ARCHIVE_DID_PATH"))
@@ -2,9 +2,11 @@ use async_trait::async_trait;
 use candid::types::number::Nat;
 use candid::utils::{ArgumentDecoder, ArgumentEncoder};
 use candid::Principal;
-use ic_icrc1::endpoints::{TransferArg, TransferError, Value};
+use ic_crypto_tree_hash::{LookupStatus, MixedHashTree};
+use ic_icrc1::endpoints::{GetBlocksRequest, GetBlocksResponse, TransferArg, TransferError, Value};
 pub use ic_icrc1::Account;
-use ic_ledger_core::block::BlockIndex;
+use ic_icrc1::Block;
+use ic_ledger_core::block::{BlockIndex, BlockType, EncodedBlock};
 use num_traits::ToPrimitive;
 
 // Abstraction over the runtime. Implement this in terms of cdk call if you use
@@ -111,6 +113,63 @@ impl<R: Runtime> ICRC1Client<R> {
             .map(untuple)?;
         Ok(result.map(nat_to_u64))
     }
+
+    /// Fetches blocks from the ledger.  Use [verify_blocks] to check that the
+    /// blocks are certified.
+    pub async fn get_blocks(
+        &self,
+        start: BlockIndex,
+        length: usize,
+    ) -> Result<GetBlocksResponse, (i32, String)> {
+        let req = GetBlocksRequest {
+            start: Nat::from(start),
+            length: Nat::from(length),
+        };
+        self.runtime
+            .call(self.ledger_canister_id, "get_blocks", (req,))
+            .await
+            .map(untuple)
+    }
+}
+
+/// An error returned by [verify_blocks].
+#[derive(Debug, PartialEq, Eq)]
+pub enum BlockVerificationError {
+    /// The hash tree is not a valid CBOR-encoded hash tree.
+    MalformedHashTree(String),
+    /// The hash tree does not reveal the hash of the block.
+    MissingBlockHash { index: BlockIndex },
+    /// The hash of the block differs from the hash in the tree.
+    BlockHashMismatch { index: BlockIndex },
+}
+
+/// Checks that the hash tree returned by `get_blocks` reveals the hashes of
+/// the `blocks` starting at `first_index` and returns the root hash of the
+/// tree.
+///
+/// The blocks are authentic if the root hash is equal to the certified data of
+/// the canister that returned the tree, i.e., the ledger or the archive.  The
+/// caller must check the certificate from the same response, for example,
+/// with `ic_certification::verify_certified_data`.
+pub fn verify_blocks(
+    hash_tree: &[u8],
+    first_index: BlockIndex,
+    blocks: &[EncodedBlock],
+) -> Result<[u8; 32], BlockVerificationError> {
+    let tree: MixedHashTree = ciborium::de::from_reader(hash_tree)
+        .map_err(|e| BlockVerificationError::MalformedHashTree(format!("{:?}", e)))?;
+    for (i, block) in blocks.iter().enumerate() {
+        let index = first_index + i as u64;
+        match tree.lookup(&[&b"blocks"[..], &index.to_be_bytes()[..]]) {
+            LookupStatus::Found(MixedHashTree::Leaf(hash)) => {
+                if hash[..] != Block::block_hash(block).as_slice()[..] {
+                    return Err(BlockVerificationError::BlockHashMismatch { index });
+                }
+            }
+            _ => return Err(BlockVerificationError::MissingBlockHash { index }),
+        }
+    }
+    Ok(tree.digest().0)
 }
 
 // extract the element from an unary tuple
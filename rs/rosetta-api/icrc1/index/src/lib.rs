@@ -41,6 +41,11 @@ struct Index {
 
     // The number of unique (principal, subaccount) pairs in the index.
     pub accounts_num: u64,
+
+    // The fee collector accounts by the txid of the transaction that
+    // specifies them. Later transactions refer to these txids.
+    #[serde(default)]
+    pub fee_collectors: BTreeMap<u64, Account>,
 }
 
 impl Index {
@@ -51,6 +56,7 @@ impl Index {
             is_heartbeat_running: false,
             account_index: BTreeMap::new(),
             accounts_num: 0,
+            fee_collectors: BTreeMap::new(),
         }
     }
 }
@@ -244,7 +250,31 @@ async fn build_index() -> Result<(), String> {
     Ok(())
 }
 
+/// Returns the account that received the fee of the transaction.
+fn fee_collector(txid: u64, transaction: &Transaction) -> Result<Option<Account>, String> {
+    if let Some(fee_collector) = &transaction.fee_collector {
+        with_index_mut(|idx| idx.fee_collectors.insert(txid, fee_collector.clone()));
+        return Ok(Some(fee_collector.clone()));
+    }
+    match transaction.fee_collector_block {
+        None => Ok(None),
+        Some(fee_collector_block) => with_index(|idx| {
+            idx.fee_collectors
+                .get(&fee_collector_block)
+                .cloned()
+                .map(Some)
+                .ok_or_else(|| {
+                    format!(
+                        "Transaction {} refers to the fee collector of transaction {} which is not indexed",
+                        txid, fee_collector_block
+                    )
+                })
+        }),
+    }
+}
+
 fn index_transaction(txid: u64, transaction: Transaction) -> Result<(), String> {
+    let fee_collector = fee_collector(txid, &transaction)?;
     match transaction.kind.as_str() {
         "mint" => {
             let mint = transaction
@@ -266,6 +296,11 @@ fn index_transaction(txid: u64, transaction: Transaction) -> Result<(), String>
             let Transfer { from, to, .. } = transaction
                 .transfer
                 .ok_or("Got a transaction with kind 'transfer' but the transfer field was None")?;
+            if let Some(fee_collector) = fee_collector {
+                if fee_collector != from && fee_collector != to {
+                    add_tx(txid, fee_collector);
+                }
+            }
             add_tx(txid, from);
             add_tx(txid, to);
             Ok(())
@@ -483,6 +518,7 @@ mod tests {
                 is_heartbeat_running: false,
                 account_index,
                 accounts_num: 0,
+                fee_collectors: BTreeMap::new(),
             });
         });
     }
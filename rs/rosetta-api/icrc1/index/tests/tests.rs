@@ -10,7 +10,7 @@ use ic_icrc1_index::{
     GetAccountTransactionsArgs, GetTransactions, GetTransactionsResult, InitArgs as IndexInitArgs,
     ListSubaccountsArgs, TransactionWithId,
 };
-use ic_icrc1_ledger::InitArgs as LedgerInitArgs;
+use ic_icrc1_ledger::{InitArgs as LedgerInitArgs, LedgerArgument};
 use ic_ledger_canister_core::archive::ArchiveOptions;
 use ic_ledger_core::{
     block::{BlockIndex, BlockType, EncodedBlock, HashOf},
@@ -72,6 +72,7 @@ fn mint_block() -> EncodedBlock {
             memo: Some(Memo::from([1; 32])),
         },
         TimeStamp::new(3, 4),
+        None,
     )
     .encode()
 }
@@ -92,6 +93,15 @@ fn install_ledger(
     env: &StateMachine,
     initial_balances: Vec<(Account, u64)>,
     archive_options: ArchiveOptions,
+) -> CanisterId {
+    install_ledger_with_fee_collector(env, initial_balances, archive_options, None)
+}
+
+fn install_ledger_with_fee_collector(
+    env: &StateMachine,
+    initial_balances: Vec<(Account, u64)>,
+    archive_options: ArchiveOptions,
+    fee_collector_account: Option<Account>,
 ) -> CanisterId {
     let args = LedgerInitArgs {
         minting_account: MINTER.clone(),
@@ -106,9 +116,14 @@ fn install_ledger(
             Value::entry(BLOB_META_KEY, BLOB_META_VALUE),
         ],
         archive_options,
+        fee_collector_account,
     };
-    env.install_canister(ledger_wasm(), Encode!(&args).unwrap(), None)
-        .unwrap()
+    env.install_canister(
+        ledger_wasm(),
+        Encode!(&LedgerArgument::Init(args)).unwrap(),
+        None,
+    )
+    .unwrap()
 }
 
 fn install_index(env: &StateMachine, ledger_id: CanisterId) -> CanisterId {
@@ -470,3 +485,45 @@ fn test_index_archived_txs_paging() {
     let expected_txids: Vec<u64> = (0..ARCHIVE_TRIGGER_THRESHOLD).rev().collect();
     assert_eq!(expected_txids, actual_txids);
 }
+
+#[test]
+fn test_index_fee_collector() {
+    let env = StateMachine::new();
+    let fee_collector = account(42);
+    let ledger_id = install_ledger_with_fee_collector(
+        &env,
+        vec![],
+        default_archive_options(),
+        Some(fee_collector.clone()),
+    );
+    let index_id = install_index(&env, ledger_id);
+
+    mint(&env, ledger_id, account(1), 100000); // block=0
+    mint(&env, ledger_id, account(2), 100000); // block=1
+    transfer(&env, ledger_id, account(1), account(2), 1); // block=2
+    transfer(&env, ledger_id, account(2), fee_collector.clone(), 1); // block=3
+
+    env.tick(); // trigger index heartbeat
+
+    // The fee collector is related to all transfers but not to the mints.
+    let txs = get_account_transactions(&env, index_id, fee_collector.clone(), None, u64::MAX);
+    let txs = txs.transactions;
+    assert_eq!(2, txs.len());
+    check_transfer(3, account(2), fee_collector.clone(), 1, txs.get(0).unwrap());
+    check_transfer(2, account(1), account(2), 1, txs.get(1).unwrap());
+
+    // The index remembers the fee collector across upgrades.
+    env.upgrade_canister(index_id, index_wasm(), vec![])
+        .expect("Failed to upgrade the Index canister");
+    transfer(&env, ledger_id, account(1), account(3), 1); // block=4
+    env.tick();
+
+    let txs = get_account_transactions(&env, index_id, fee_collector, None, 1);
+    check_transfer(
+        4,
+        account(1),
+        account(3),
+        1,
+        txs.transactions.get(0).unwrap(),
+    );
+}
@@ -1,13 +1,17 @@
 use candid::{CandidType, Decode, Encode, Nat};
 use ic_base_types::PrincipalId;
 use ic_icrc1::{
-    endpoints::{StandardRecord, Value},
-    Account,
+    endpoints::{StandardRecord, TransferArg, TransferError, Value},
+    Account, Memo,
 };
 use ic_ledger_canister_core::archive::ArchiveOptions;
+use ic_ledger_core::block::BlockIndex;
 use ic_state_machine_tests::{CanisterId, StateMachine};
 use num_traits::ToPrimitive;
-use std::{collections::BTreeMap, time::Duration};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime},
+};
 
 pub const FEE: u64 = 10_000;
 pub const ARCHIVE_TRIGGER_THRESHOLD: u64 = 10;
@@ -100,6 +104,55 @@ pub fn metadata(env: &StateMachine, ledger: CanisterId) -> BTreeMap<String, Valu
     .collect()
 }
 
+pub fn send_transfer(
+    env: &StateMachine,
+    ledger: CanisterId,
+    from: PrincipalId,
+    arg: &TransferArg,
+) -> Result<BlockIndex, TransferError> {
+    Decode!(
+        &env.execute_ingress_as(
+            from,
+            ledger,
+            "icrc1_transfer",
+            Encode!(arg)
+            .unwrap()
+        )
+        .expect("failed to transfer funds")
+        .bytes(),
+        Result<Nat, TransferError>
+    )
+    .expect("failed to decode transfer response")
+    .map(|n| n.0.to_u64().unwrap())
+}
+
+pub fn transfer(
+    env: &StateMachine,
+    ledger: CanisterId,
+    from: impl Into<Account>,
+    to: impl Into<Account>,
+    amount: u64,
+) -> Result<BlockIndex, TransferError> {
+    let from = from.into();
+    send_transfer(
+        env,
+        ledger,
+        from.owner,
+        &TransferArg {
+            from_subaccount: from.subaccount,
+            to: to.into(),
+            fee: None,
+            created_at_time: None,
+            amount: Nat::from(amount),
+            memo: None,
+        },
+    )
+}
+
+pub fn system_time_to_nanos(t: SystemTime) -> u64 {
+    t.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_nanos() as u64
+}
+
 fn init_args(initial_balances: Vec<(Account, u64)>) -> InitArgs {
     InitArgs {
         minting_account: MINTER.clone(),
@@ -264,3 +317,308 @@ where
     let (env, canister_id) = setup(ledger_wasm, encode_init_args, vec![]);
     assert_eq!(Some(MINTER), minting_account(&env, canister_id));
 }
+
+pub fn test_tx_deduplication<T>(ledger_wasm: Vec<u8>, encode_init_args: fn(InitArgs) -> T)
+where
+    T: CandidType,
+{
+    let p1 = PrincipalId::new_user_test_id(1);
+    let p2 = PrincipalId::new_user_test_id(2);
+    let (env, canister_id) = setup(
+        ledger_wasm,
+        encode_init_args,
+        vec![(Account::from(p1), 10_000_000)],
+    );
+
+    // No created_at_time => no deduplication
+    let block_id = transfer(&env, canister_id, p1, p2, 10_000).expect("transfer failed");
+    assert!(transfer(&env, canister_id, p1, p2, 10_000).expect("transfer failed") > block_id);
+
+    let now = system_time_to_nanos(env.time());
+
+    let transfer_args = TransferArg {
+        from_subaccount: None,
+        to: p2.into(),
+        fee: None,
+        amount: Nat::from(1_000_000),
+        created_at_time: Some(now),
+        memo: None,
+    };
+
+    let block_idx = send_transfer(&env, canister_id, p1, &transfer_args).expect("transfer failed");
+
+    assert_eq!(
+        send_transfer(&env, canister_id, p1, &transfer_args),
+        Err(TransferError::Duplicate {
+            duplicate_of: Nat::from(block_idx)
+        })
+    );
+
+    env.advance_time(TX_WINDOW + Duration::from_secs(5 * 60));
+    let now = system_time_to_nanos(env.time());
+
+    assert_eq!(
+        send_transfer(&env, canister_id, p1, &transfer_args,),
+        Err(TransferError::TooOld),
+    );
+
+    // Same transaction, but `created_at_time` specified explicitly.
+    // The ledger should not deduplicate this request.
+    let block_idx = send_transfer(
+        &env,
+        canister_id,
+        p1,
+        &TransferArg {
+            from_subaccount: None,
+            to: p2.into(),
+            fee: None,
+            amount: Nat::from(1_000_000),
+            created_at_time: Some(now),
+            memo: None,
+        },
+    )
+    .expect("transfer failed");
+
+    // This time the transaction is a duplicate.
+    assert_eq!(
+        Err(TransferError::Duplicate {
+            duplicate_of: Nat::from(block_idx)
+        }),
+        send_transfer(
+            &env,
+            canister_id,
+            p1,
+            &TransferArg {
+                from_subaccount: None,
+                to: p2.into(),
+                fee: None,
+                amount: Nat::from(1_000_000),
+                created_at_time: Some(now),
+                memo: None,
+            }
+        )
+    );
+
+    // Same transaction, but with "default" `memo`.
+    // The ledger should not deduplicate because we set a new field explicitly.
+    let block_idx = send_transfer(
+        &env,
+        canister_id,
+        p1,
+        &TransferArg {
+            from_subaccount: None,
+            to: p2.into(),
+            fee: None,
+            amount: Nat::from(1_000_000),
+            created_at_time: Some(now),
+            memo: Some(Memo::default()),
+        },
+    )
+    .expect("transfer failed");
+
+    // This time the transaction is a duplicate.
+    assert_eq!(
+        Err(TransferError::Duplicate {
+            duplicate_of: Nat::from(block_idx)
+        }),
+        send_transfer(
+            &env,
+            canister_id,
+            p1,
+            &TransferArg {
+                from_subaccount: None,
+                to: p2.into(),
+                fee: None,
+                amount: Nat::from(1_000_000),
+                created_at_time: Some(now),
+                memo: Some(Memo::default()),
+            }
+        )
+    );
+}
+
+pub fn test_mint_burn<T>(ledger_wasm: Vec<u8>, encode_init_args: fn(InitArgs) -> T)
+where
+    T: CandidType,
+{
+    let p1 = PrincipalId::new_user_test_id(1);
+    let p2 = PrincipalId::new_user_test_id(2);
+    let (env, canister_id) = setup(ledger_wasm, encode_init_args, vec![]);
+
+    assert_eq!(0, total_supply(&env, canister_id));
+    assert_eq!(0, balance_of(&env, canister_id, p1));
+    assert_eq!(0, balance_of(&env, canister_id, MINTER.clone()));
+
+    transfer(&env, canister_id, MINTER.clone(), p1, 10_000_000).expect("mint failed");
+
+    assert_eq!(10_000_000, total_supply(&env, canister_id));
+    assert_eq!(10_000_000, balance_of(&env, canister_id, p1));
+    assert_eq!(0, balance_of(&env, canister_id, MINTER.clone()));
+
+    transfer(&env, canister_id, p1, MINTER.clone(), 1_000_000).expect("burn failed");
+
+    assert_eq!(9_000_000, total_supply(&env, canister_id));
+    assert_eq!(9_000_000, balance_of(&env, canister_id, p1));
+    assert_eq!(0, balance_of(&env, canister_id, MINTER.clone()));
+
+    // You have at least FEE, you can burn at least FEE
+    assert_eq!(
+        Err(TransferError::BadBurn {
+            min_burn_amount: Nat::from(FEE)
+        }),
+        transfer(&env, canister_id, p1, MINTER.clone(), FEE / 2),
+    );
+
+    transfer(&env, canister_id, p1, p2, FEE / 2).expect("transfer failed");
+
+    assert_eq!(FEE / 2, balance_of(&env, canister_id, p2));
+
+    // If you have less than FEE, you can burn only the whole amount.
+    assert_eq!(
+        Err(TransferError::BadBurn {
+            min_burn_amount: Nat::from(FEE / 2)
+        }),
+        transfer(&env, canister_id, p2, MINTER.clone(), FEE / 4),
+    );
+    transfer(&env, canister_id, p2, MINTER.clone(), FEE / 2).expect("burn failed");
+
+    assert_eq!(0, balance_of(&env, canister_id, p2));
+
+    // You cannot burn zero tokens, no matter what your balance is.
+    assert_eq!(
+        Err(TransferError::BadBurn {
+            min_burn_amount: Nat::from(FEE)
+        }),
+        transfer(&env, canister_id, p2, MINTER.clone(), 0),
+    );
+}
+
+pub fn test_single_transfer<T>(ledger_wasm: Vec<u8>, encode_init_args: fn(InitArgs) -> T)
+where
+    T: CandidType,
+{
+    let p1 = PrincipalId::new_user_test_id(1);
+    let p2 = PrincipalId::new_user_test_id(2);
+    let (env, canister_id) = setup(
+        ledger_wasm,
+        encode_init_args,
+        vec![
+            (Account::from(p1), 10_000_000),
+            (Account::from(p2), 5_000_000),
+        ],
+    );
+
+    assert_eq!(15_000_000, total_supply(&env, canister_id));
+    assert_eq!(10_000_000u64, balance_of(&env, canister_id, p1));
+    assert_eq!(5_000_000u64, balance_of(&env, canister_id, p2));
+
+    transfer(&env, canister_id, p1, p2, 1_000_000).expect("transfer failed");
+
+    assert_eq!(15_000_000 - FEE, total_supply(&env, canister_id));
+    assert_eq!(9_000_000u64 - FEE, balance_of(&env, canister_id, p1));
+    assert_eq!(6_000_000u64, balance_of(&env, canister_id, p2));
+}
+
+pub fn test_tx_time_bounds<T>(ledger_wasm: Vec<u8>, encode_init_args: fn(InitArgs) -> T)
+where
+    T: CandidType,
+{
+    let p1 = PrincipalId::new_user_test_id(1);
+    let p2 = PrincipalId::new_user_test_id(2);
+    let (env, canister_id) = setup(
+        ledger_wasm,
+        encode_init_args,
+        vec![(Account::from(p1), 10_000_000)],
+    );
+
+    let now = system_time_to_nanos(env.time());
+    let tx_window = TX_WINDOW.as_nanos() as u64;
+
+    assert_eq!(
+        Err(TransferError::TooOld),
+        send_transfer(
+            &env,
+            canister_id,
+            p1,
+            &TransferArg {
+                from_subaccount: None,
+                to: p2.into(),
+                fee: None,
+                amount: Nat::from(1_000_000),
+                created_at_time: Some(now - tx_window - 1),
+                memo: None,
+            }
+        )
+    );
+
+    assert_eq!(
+        Err(TransferError::CreatedInFuture { ledger_time: now }),
+        send_transfer(
+            &env,
+            canister_id,
+            p1,
+            &TransferArg {
+                from_subaccount: None,
+                to: p2.into(),
+                fee: None,
+                amount: Nat::from(1_000_000),
+                created_at_time: Some(now + Duration::from_secs(5 * 60).as_nanos() as u64),
+                memo: None
+            }
+        )
+    );
+
+    assert_eq!(10_000_000u64, balance_of(&env, canister_id, p1));
+    assert_eq!(0u64, balance_of(&env, canister_id, p2));
+}
+
+pub fn test_upgrade_preserves_balances<T>(ledger_wasm: Vec<u8>, encode_init_args: fn(InitArgs) -> T)
+where
+    T: CandidType,
+{
+    const NUM_ACCOUNTS: u64 = 10_000;
+
+    let account = |i: u64| Account {
+        owner: PrincipalId::new_user_test_id(i % 100),
+        subaccount: if i < 100 {
+            None
+        } else {
+            let mut subaccount = [0u8; 32];
+            subaccount[..8].copy_from_slice(&i.to_be_bytes());
+            Some(subaccount)
+        },
+    };
+    let initial_balances: Vec<_> = (0..NUM_ACCOUNTS)
+        .map(|i| (account(i), (i + 1) * FEE))
+        .collect();
+    let expected_supply: u64 = initial_balances.iter().map(|(_, amount)| amount).sum();
+
+    let (env, canister_id) = setup(ledger_wasm.clone(), encode_init_args, initial_balances);
+
+    let check_balances = |env: &StateMachine| {
+        assert_eq!(expected_supply, total_supply(env, canister_id));
+        for i in (0..NUM_ACCOUNTS).step_by(97) {
+            assert_eq!((i + 1) * FEE, balance_of(env, canister_id, account(i)));
+        }
+    };
+
+    check_balances(&env);
+
+    env.upgrade_canister(canister_id, ledger_wasm.clone(), Encode!().unwrap())
+        .expect("failed to upgrade the ledger");
+    check_balances(&env);
+
+    // The ledger must remain functional after an upgrade and keep the new
+    // balances across subsequent upgrades.
+    let p1 = PrincipalId::new_user_test_id(1);
+    let p2 = PrincipalId::new_user_test_id(NUM_ACCOUNTS + 1);
+    let p1_balance = balance_of(&env, canister_id, p1);
+    transfer(&env, canister_id, p1, p2, 1).expect("transfer failed");
+
+    env.upgrade_canister(canister_id, ledger_wasm, Encode!().unwrap())
+        .expect("failed to upgrade the ledger");
+
+    assert_eq!(p1_balance - 1 - FEE, balance_of(&env, canister_id, p1));
+    assert_eq!(1, balance_of(&env, canister_id, p2));
+    assert_eq!(expected_supply - FEE, total_supply(&env, canister_id));
+}
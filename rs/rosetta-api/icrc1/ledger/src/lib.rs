@@ -5,34 +5,36 @@ use candid::{
     types::number::{Int, Nat},
     CandidType,
 };
+use ic_crypto_tree_hash::{Label, MixedHashTree as T};
 use ic_icrc1::endpoints::{
-    ArchivedTransactionRange, GetTransactionsResponse, QueryArchiveFn, Transaction as Tx, Value,
+    ArchivedBlocksRange, ArchivedTransactionRange, GetBlocksResponse, GetTransactionsResponse,
+    QueryArchiveFn, Transaction as Tx, Value,
 };
-use ic_icrc1::{Account, Block, LedgerBalances, Transaction};
+use ic_icrc1::{Account, Block, Transaction};
 use ic_ledger_canister_core::{
-    archive::{ArchiveCanisterWasm, ArchiveOptions},
+    archive::{Archive, ArchiveCanisterWasm, ArchiveOptions},
     blockchain::Blockchain,
     ledger::{apply_transaction, block_locations, LedgerData, TransactionInfo},
     range_utils,
+    stable_balances::StableBalancesStore,
 };
 use ic_ledger_core::{
     balances::Balances,
-    block::{BlockIndex, BlockType, HashOf},
+    block::{BlockIndex, BlockType, FeeCollector, HashOf},
     timestamp::TimeStamp,
     tokens::Tokens,
 };
 use serde::{Deserialize, Serialize};
 use serde_bytes::ByteBuf;
 use std::borrow::Cow;
-use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::collections::{BTreeMap, VecDeque};
+use std::ops::Range;
 use std::time::Duration;
 
 const TRANSACTION_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
-const MAX_ACCOUNTS: usize = 28_000_000;
 /// The maximum number of transactions the ledger should return for a single
 /// get_transactions request.
 const MAX_TRANSACTIONS_PER_REQUEST: usize = 2_000;
-const ACCOUNTS_OVERFLOW_TRIM_QUANTITY: usize = 100_000;
 const MAX_TRANSACTIONS_IN_WINDOW: usize = 3_000_000;
 const MAX_TRANSACTIONS_TO_PURGE: usize = 100_000;
 
@@ -99,8 +101,77 @@ pub struct InitArgs {
     pub token_symbol: String,
     pub metadata: Vec<(String, Value)>,
     pub archive_options: ArchiveOptions,
+    /// The account receiving the transfer fees.  If not set, the ledger burns
+    /// the fees.
+    pub fee_collector_account: Option<Account>,
 }
 
+#[derive(Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub enum ChangeFeeCollector {
+    /// Burn the fees from now on.
+    Unset,
+    /// Send the fees to the specified account from now on.
+    SetTo(Account),
+}
+
+/// Metadata keys whose values the ledger derives from its own state.
+const COMPUTED_METADATA_KEYS: [&str; 4] =
+    ["icrc1:decimals", "icrc1:name", "icrc1:symbol", "icrc1:fee"];
+
+/// Changes to the ledger configuration applied on upgrade.  `None` fields keep
+/// the current value.
+#[derive(Default, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct UpgradeArgs {
+    /// Replaces all the custom metadata entries.
+    pub metadata: Option<Vec<(String, Value)>>,
+    pub token_name: Option<String>,
+    pub token_symbol: Option<String>,
+    pub transfer_fee: Option<u64>,
+    pub change_fee_collector: Option<ChangeFeeCollector>,
+    pub archive_options: Option<ArchiveOptions>,
+}
+
+impl UpgradeArgs {
+    /// Checks the arguments that do not depend on the ledger state.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(metadata) = &self.metadata {
+            if let Some((key, _)) = metadata
+                .iter()
+                .find(|(k, _)| COMPUTED_METADATA_KEYS.contains(&k.as_str()))
+            {
+                return Err(format!(
+                    "metadata key {} is reserved: the ledger computes it",
+                    key
+                ));
+            }
+        }
+        if self.token_name.as_deref() == Some("") {
+            return Err("token name must not be empty".to_string());
+        }
+        if self.token_symbol.as_deref() == Some("") {
+            return Err("token symbol must not be empty".to_string());
+        }
+        if let Some(archive_options) = &self.archive_options {
+            if archive_options.num_blocks_to_archive == 0 {
+                return Err("num_blocks_to_archive must be positive".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The argument of the ledger canister: [LedgerArgument::Init] on install and
+/// [LedgerArgument::Upgrade] on upgrade.
+#[derive(Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
+pub enum LedgerArgument {
+    Init(InitArgs),
+    Upgrade(Option<UpgradeArgs>),
+}
+
+/// Account balances kept in stable memory.
+pub type LedgerBalances = Balances<Account, StableBalancesStore<Account>>;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Ledger {
     balances: LedgerBalances,
@@ -115,6 +186,9 @@ pub struct Ledger {
     token_symbol: String,
     token_name: String,
     metadata: Vec<(String, StoredValue)>,
+
+    #[serde(default)]
+    fee_collector: Option<FeeCollector<Account>>,
 }
 
 impl Ledger {
@@ -127,6 +201,7 @@ impl Ledger {
             token_symbol,
             metadata,
             archive_options,
+            fee_collector_account,
         }: InitArgs,
         now: TimeStamp,
     ) -> Self {
@@ -143,7 +218,9 @@ impl Ledger {
                 .into_iter()
                 .map(|(k, v)| (k, StoredValue::from(v)))
                 .collect(),
+            fee_collector: fee_collector_account.map(FeeCollector::from),
         };
+        ledger.blockchain.enable_block_hash_tree();
 
         for (account, balance) in initial_balances.into_iter() {
             apply_transaction(
@@ -166,6 +243,7 @@ impl LedgerData for Ledger {
     type ArchiveWasm = Icrc1ArchiveWasm;
     type Transaction = Transaction;
     type Block = Block;
+    type BalancesStore = StableBalancesStore<Account>;
 
     fn transaction_window(&self) -> Duration {
         TRANSACTION_WINDOW
@@ -179,14 +257,6 @@ impl LedgerData for Ledger {
         MAX_TRANSACTIONS_TO_PURGE
     }
 
-    fn max_number_of_accounts(&self) -> usize {
-        MAX_ACCOUNTS
-    }
-
-    fn accounts_overflow_trim_quantity(&self) -> usize {
-        ACCOUNTS_OVERFLOW_TRIM_QUANTITY
-    }
-
     fn token_name(&self) -> &str {
         &self.token_name
     }
@@ -195,11 +265,11 @@ impl LedgerData for Ledger {
         &self.token_symbol
     }
 
-    fn balances(&self) -> &Balances<Self::AccountId, HashMap<Self::AccountId, Tokens>> {
+    fn balances(&self) -> &Balances<Self::AccountId, Self::BalancesStore> {
         &self.balances
     }
 
-    fn balances_mut(&mut self) -> &mut Balances<Self::AccountId, HashMap<Self::AccountId, Tokens>> {
+    fn balances_mut(&mut self) -> &mut Balances<Self::AccountId, Self::BalancesStore> {
         &mut self.balances
     }
 
@@ -228,6 +298,14 @@ impl LedgerData for Ledger {
     }
 
     fn on_purged_transaction(&mut self, _height: BlockIndex) {}
+
+    fn fee_collector(&self) -> Option<&FeeCollector<Self::AccountId>> {
+        self.fee_collector.as_ref()
+    }
+
+    fn fee_collector_mut(&mut self) -> Option<&mut FeeCollector<Self::AccountId>> {
+        self.fee_collector.as_mut()
+    }
 }
 
 impl Ledger {
@@ -239,6 +317,57 @@ impl Ledger {
         self.transfer_fee
     }
 
+    pub fn fee_collector_account(&self) -> Option<&Account> {
+        self.fee_collector.as_ref().map(|fc| &fc.fee_collector)
+    }
+
+    /// Applies the configuration changes.  Does not modify the ledger if the
+    /// arguments are invalid.
+    pub fn upgrade(&mut self, args: UpgradeArgs) -> Result<(), String> {
+        args.validate()?;
+        if let Some(ChangeFeeCollector::SetTo(fee_collector)) = &args.change_fee_collector {
+            if fee_collector == &self.minting_account {
+                return Err("the fee collector must not be the minting account".to_string());
+            }
+        }
+
+        if let Some(metadata) = args.metadata {
+            self.metadata = metadata
+                .into_iter()
+                .map(|(k, v)| (k, StoredValue::from(v)))
+                .collect();
+        }
+        if let Some(token_name) = args.token_name {
+            self.token_name = token_name;
+        }
+        if let Some(token_symbol) = args.token_symbol {
+            self.token_symbol = token_symbol;
+        }
+        if let Some(transfer_fee) = args.transfer_fee {
+            self.transfer_fee = Tokens::from_e8s(transfer_fee);
+        }
+        match args.change_fee_collector {
+            None => {}
+            Some(ChangeFeeCollector::Unset) => self.fee_collector = None,
+            // The next block will specify the new account.
+            Some(ChangeFeeCollector::SetTo(fee_collector)) => {
+                self.fee_collector = Some(FeeCollector::from(fee_collector))
+            }
+        }
+        if let Some(archive_options) = args.archive_options {
+            let mut archive = self
+                .blockchain
+                .archive
+                .write()
+                .expect("failed to obtain archive lock");
+            match archive.as_mut() {
+                Some(archive) => archive.update_options(archive_options),
+                None => *archive = Some(Archive::new(archive_options)),
+            }
+        }
+        Ok(())
+    }
+
     pub fn metadata(&self) -> Vec<(String, Value)> {
         let mut records: Vec<(String, Value)> = self
             .metadata
@@ -258,15 +387,82 @@ impl Ledger {
     /// The canister code must call set_certified_data with the value this function returns after
     /// each successful modification of the ledger.
     pub fn root_hash(&self) -> [u8; 32] {
-        use ic_crypto_tree_hash::{Label, MixedHashTree as T};
-        let tree = match self.blockchain().last_hash {
-            Some(hash) => T::Labeled(
-                Label::from("tip_hash"),
-                Box::new(T::Leaf(hash.as_slice().to_vec())),
-            ),
-            None => T::Empty,
+        self.certified_tree(0..0).digest().0
+    }
+
+    /// Returns the tree of the certified ledger state revealing the hashes of
+    /// the specified blocks:
+    ///
+    /// ```text
+    /// * blocks: the Merkle mountain range over block hashes
+    /// * last_block_index: the index of the tip as a big-endian u64
+    /// * tip_hash: the hash of the tip
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the blocks are not a subrange of the certified block range.
+    fn certified_tree(&self, blocks: Range<BlockIndex>) -> T {
+        let tip_hash = match self.blockchain.last_hash {
+            Some(hash) => hash,
+            None => return T::Empty,
         };
-        tree.digest().0
+        let last_block_index = self.blockchain.chain_length() - 1;
+        T::Fork(Box::new((
+            T::Labeled(
+                Label::from("blocks"),
+                Box::new(self.blockchain.block_hash_tree(blocks)),
+            ),
+            T::Fork(Box::new((
+                T::Labeled(
+                    Label::from("last_block_index"),
+                    Box::new(T::Leaf(last_block_index.to_be_bytes().to_vec())),
+                ),
+                T::Labeled(
+                    Label::from("tip_hash"),
+                    Box::new(T::Leaf(tip_hash.as_slice().to_vec())),
+                ),
+            ))),
+        )))
+    }
+
+    /// Returns encoded blocks in the specified range together with a hash tree
+    /// proving that the blocks are part of the certified ledger state.
+    ///
+    /// The caller is responsible for filling the certificate.
+    pub fn get_blocks(&self, start: BlockIndex, length: usize) -> GetBlocksResponse {
+        let locations = block_locations(self, start, length);
+
+        let local_blocks = range_utils::take(&locations.local_blocks, MAX_TRANSACTIONS_PER_REQUEST);
+        let blocks = self.blockchain.block_slice(local_blocks.clone()).to_vec();
+
+        // Ledgers that existed before the block hash tree was introduced
+        // cannot prove the inclusion of their older blocks.
+        let certified_blocks =
+            range_utils::intersect(&local_blocks, &self.blockchain.certified_block_range())
+                .unwrap_or(0..0);
+        let mut hash_tree = vec![];
+        ciborium::ser::into_writer(&self.certified_tree(certified_blocks), &mut hash_tree)
+            .expect("failed to encode the hash tree");
+
+        let archived_blocks = locations
+            .archived_blocks
+            .into_iter()
+            .map(|(canister_id, slice)| ArchivedBlocksRange {
+                start: Nat::from(slice.start),
+                length: Nat::from(range_utils::range_len(&slice)),
+                callback: QueryArchiveFn::new(canister_id, "get_blocks"),
+            })
+            .collect();
+
+        GetBlocksResponse {
+            first_index: Nat::from(local_blocks.start),
+            chain_length: self.blockchain.chain_length(),
+            certificate: None,
+            hash_tree: ByteBuf::from(hash_tree),
+            blocks,
+            archived_blocks,
+        }
     }
 
     /// Returns transactions in the specified range.
@@ -292,10 +488,7 @@ impl Ledger {
             .map(|(canister_id, slice)| ArchivedTransactionRange {
                 start: Nat::from(slice.start),
                 length: Nat::from(range_utils::range_len(&slice)),
-                callback: QueryArchiveFn {
-                    canister_id,
-                    method: "get_transactions".to_string(),
-                },
+                callback: QueryArchiveFn::new(canister_id, "get_transactions"),
             })
             .collect();
 
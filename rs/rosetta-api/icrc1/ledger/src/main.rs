@@ -1,19 +1,22 @@
-use candid::candid_method;
 use candid::types::number::Nat;
+use candid::{candid_method, Decode};
 use ic_base_types::PrincipalId;
-use ic_cdk::api::stable::{StableReader, StableWriter};
 use ic_cdk_macros::{init, post_upgrade, pre_upgrade, query, update};
 use ic_icrc1::{
     endpoints::{
-        ArchiveInfo, GetTransactionsRequest, GetTransactionsResponse, StandardRecord, TransferArg,
-        TransferError, Value,
+        ArchiveInfo, GetBlocksRequest, GetBlocksResponse, GetTransactionsRequest,
+        GetTransactionsResponse, StandardRecord, TransferArg, TransferError, Value,
     },
     Account, Operation, Transaction,
 };
-use ic_icrc1_ledger::{InitArgs, Ledger};
+use ic_icrc1_ledger::{Ledger, LedgerArgument};
 use ic_ledger_canister_core::ledger::{
     apply_transaction, archive_blocks, LedgerAccess, LedgerData,
 };
+use ic_ledger_canister_core::memory::{
+    is_legacy_stable_memory, read_stable_memory, upgrades_reader, upgrades_writer,
+};
+use ic_ledger_core::balances::InspectableBalancesStore;
 use ic_ledger_core::{timestamp::TimeStamp, tokens::Tokens};
 use num_traits::ToPrimitive;
 use std::cell::RefCell;
@@ -48,25 +51,62 @@ impl LedgerAccess for Access {
 }
 
 #[init]
-fn init(args: InitArgs) {
+fn init(args: LedgerArgument) {
+    let args = match args {
+        LedgerArgument::Init(args) => args,
+        LedgerArgument::Upgrade(_) => {
+            ic_cdk::trap("cannot install the ledger with upgrade arguments")
+        }
+    };
     let now = TimeStamp::from_nanos_since_unix_epoch(ic_cdk::api::time());
-    LEDGER.with(|cell| *cell.borrow_mut() = Some(Ledger::from_init_args(args, now)))
+    LEDGER.with(|cell| *cell.borrow_mut() = Some(Ledger::from_init_args(args, now)));
+    ic_cdk::api::set_certified_data(&Access::with_ledger(Ledger::root_hash));
 }
 
 #[pre_upgrade]
 fn pre_upgrade() {
-    Access::with_ledger(|ledger| ciborium::ser::into_writer(ledger, StableWriter::default()))
+    Access::with_ledger(|ledger| ciborium::ser::into_writer(ledger, upgrades_writer()))
         .expect("failed to encode ledger state");
 }
 
 #[post_upgrade]
 fn post_upgrade() {
-    LEDGER.with(|cell| {
-        *cell.borrow_mut() = Some(
-            ciborium::de::from_reader(StableReader::default())
-                .expect("failed to decode ledger state"),
-        );
-    })
+    // Ledgers that kept balances on the heap wrote their whole state directly
+    // to the stable memory.  Decoding such a state moves the balances to the
+    // stable B-tree, so we must copy the state out before the memory manager
+    // takes over the stable memory.
+    let mut ledger: Ledger = if is_legacy_stable_memory() {
+        let state = read_stable_memory();
+        ciborium::de::from_reader(&state[..])
+    } else {
+        ciborium::de::from_reader(upgrades_reader())
+    }
+    .expect("failed to decode ledger state");
+    // Ledgers that predate the block hash tree start maintaining it with the
+    // first block appended after the upgrade.
+    ledger.blockchain_mut().enable_block_hash_tree();
+    LEDGER.with(|cell| *cell.borrow_mut() = Some(ledger));
+
+    // Upgrades that predate upgrade arguments pass an empty argument.
+    let arg_bytes = ic_cdk::api::call::arg_data_raw();
+    if !arg_bytes.is_empty() {
+        match Decode!(&arg_bytes, Option<LedgerArgument>)
+            .unwrap_or_else(|err| ic_cdk::trap(&format!("failed to decode upgrade args: {}", err)))
+        {
+            None | Some(LedgerArgument::Upgrade(None)) => {}
+            Some(LedgerArgument::Upgrade(Some(args))) => {
+                if let Err(err) = Access::with_ledger_mut(|ledger| ledger.upgrade(args)) {
+                    ic_cdk::trap(&format!("invalid upgrade args: {}", err));
+                }
+            }
+            Some(LedgerArgument::Init(_)) => {
+                ic_cdk::trap("cannot upgrade the ledger with init arguments")
+            }
+        }
+    }
+    // The structure of the certified tree might have changed since the
+    // previous version.
+    ic_cdk::api::set_certified_data(&Access::with_ledger(Ledger::root_hash));
 }
 
 fn encode_metrics(w: &mut ic_metrics_encoder::MetricsEncoder<Vec<u8>>) -> std::io::Result<()> {
@@ -108,7 +148,7 @@ fn encode_metrics(w: &mut ic_metrics_encoder::MetricsEncoder<Vec<u8>>) -> std::i
         )?;
         w.encode_gauge(
             "ledger_balance_store_entries",
-            ledger.balances().store.len() as f64,
+            ledger.balances().store.num_accounts() as f64,
             "Total number of accounts in the balance store.",
         )?;
         w.encode_gauge(
@@ -305,6 +345,17 @@ fn get_transactions(req: GetTransactionsRequest) -> GetTransactionsResponse {
     Access::with_ledger(|ledger| ledger.get_transactions(start, length))
 }
 
+#[query]
+#[candid_method(query)]
+fn get_blocks(req: GetBlocksRequest) -> GetBlocksResponse {
+    let (start, length) = req
+        .as_start_and_length()
+        .unwrap_or_else(|msg| ic_cdk::api::trap(&msg));
+    let mut response = Access::with_ledger(|ledger| ledger.get_blocks(start, length));
+    response.certificate = ic_cdk::api::data_certificate().map(serde_bytes::ByteBuf::from);
+    response
+}
+
 candid::export_service!();
 
 #[query]
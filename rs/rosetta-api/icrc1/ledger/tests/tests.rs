@@ -1,22 +1,29 @@
 use candid::types::number::Nat;
 use candid::{CandidType, Decode, Encode};
+use ciborium::value::Value as CborValue;
 use ic_base_types::PrincipalId;
 use ic_icrc1::{
     endpoints::{
-        ArchiveInfo, GetTransactionsRequest, GetTransactionsResponse, StandardRecord,
-        Transaction as Tx, TransactionRange, Transfer, TransferArg, TransferError, Value,
+        ArchiveInfo, BlockRange, GetBlocksRequest, GetBlocksResponse, GetTransactionsRequest,
+        GetTransactionsResponse, StandardRecord, Transaction as Tx, TransactionRange, Transfer,
+        TransferError, Value,
     },
     Account, Block, Memo, Operation, Transaction,
 };
-use ic_icrc1_ledger::InitArgs;
+use ic_icrc1_client::{verify_blocks, BlockVerificationError};
+use ic_icrc1_ledger::{ChangeFeeCollector, InitArgs, Ledger, LedgerArgument, UpgradeArgs};
 use ic_icrc1_ledger_sm_tests::{
-    balance_of, metadata, setup, supported_standards, total_supply, ARCHIVE_TRIGGER_THRESHOLD,
-    BLOB_META_KEY, BLOB_META_VALUE, FEE, INT_META_KEY, INT_META_VALUE, MINTER, NAT_META_KEY,
-    NAT_META_VALUE, NUM_BLOCKS_TO_ARCHIVE, TEXT_META_KEY, TEXT_META_VALUE, TOKEN_NAME,
-    TOKEN_SYMBOL, TX_WINDOW,
+    balance_of, metadata, setup, supported_standards, total_supply, transfer,
+    ARCHIVE_TRIGGER_THRESHOLD, BLOB_META_KEY, BLOB_META_VALUE, FEE, INT_META_KEY, INT_META_VALUE,
+    MINTER, NAT_META_KEY, NAT_META_VALUE, NUM_BLOCKS_TO_ARCHIVE, TEXT_META_KEY, TEXT_META_VALUE,
+    TOKEN_NAME, TOKEN_SYMBOL,
 };
 use ic_ledger_canister_core::archive::ArchiveOptions;
-use ic_ledger_core::block::{BlockIndex, BlockType, HashOf};
+use ic_ledger_canister_core::ledger::LedgerData;
+use ic_ledger_canister_core::memory::{read_stable_memory, upgrades_writer};
+use ic_ledger_core::block::{BlockType, FeeCollector, HashOf};
+use ic_ledger_core::timestamp::TimeStamp;
+use ic_ledger_core::tokens::Tokens;
 use ic_state_machine_tests::{CanisterId, ErrorCode, StateMachine};
 use num_traits::ToPrimitive;
 use proptest::prelude::*;
@@ -24,7 +31,6 @@ use proptest::test_runner::{Config as TestRunnerConfig, TestCaseResult, TestRunn
 use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
 use std::path::PathBuf;
-use std::time::{Duration, SystemTime};
 
 fn ledger_wasm() -> Vec<u8> {
     ic_test_utilities_load_wasm::load_wasm(
@@ -45,8 +51,8 @@ fn archive_wasm() -> Vec<u8> {
     )
 }
 
-fn install_ledger(env: &StateMachine, initial_balances: Vec<(Account, u64)>) -> CanisterId {
-    let args = InitArgs {
+fn init_args(initial_balances: Vec<(Account, u64)>) -> InitArgs {
+    InitArgs {
         minting_account: MINTER.clone(),
         initial_balances,
         transfer_fee: FEE,
@@ -67,54 +73,17 @@ fn install_ledger(env: &StateMachine, initial_balances: Vec<(Account, u64)>) ->
             cycles_for_archive_creation: None,
             max_transactions_per_response: None,
         },
-    };
-    env.install_canister(ledger_wasm(), Encode!(&args).unwrap(), None)
-        .unwrap()
-}
-
-fn send_transfer(
-    env: &StateMachine,
-    ledger: CanisterId,
-    from: PrincipalId,
-    arg: &TransferArg,
-) -> Result<BlockIndex, TransferError> {
-    Decode!(
-        &env.execute_ingress_as(
-            from,
-            ledger,
-            "icrc1_transfer",
-            Encode!(arg)
-            .unwrap()
-        )
-        .expect("failed to transfer funds")
-        .bytes(),
-        Result<Nat, TransferError>
-    )
-    .expect("failed to decode transfer response")
-    .map(|n| n.0.to_u64().unwrap())
+        fee_collector_account: None,
+    }
 }
 
-fn transfer(
-    env: &StateMachine,
-    ledger: CanisterId,
-    from: impl Into<Account>,
-    to: impl Into<Account>,
-    amount: u64,
-) -> Result<BlockIndex, TransferError> {
-    let from = from.into();
-    send_transfer(
-        env,
-        ledger,
-        from.owner,
-        &TransferArg {
-            from_subaccount: from.subaccount,
-            to: to.into(),
-            fee: None,
-            created_at_time: None,
-            amount: Nat::from(amount),
-            memo: None,
-        },
+fn install_ledger(env: &StateMachine, initial_balances: Vec<(Account, u64)>) -> CanisterId {
+    env.install_canister(
+        ledger_wasm(),
+        Encode!(&LedgerArgument::Init(init_args(initial_balances))).unwrap(),
+        None,
     )
+    .unwrap()
 }
 
 fn list_archives(env: &StateMachine, ledger: CanisterId) -> Vec<ArchiveInfo> {
@@ -187,12 +156,8 @@ fn get_archive_transactions(
     .expect("failed to decode get_transactions archive response")
 }
 
-fn system_time_to_nanos(t: SystemTime) -> u64 {
-    t.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_nanos() as u64
-}
-
-fn encode_init_args(args: ic_icrc1_ledger_sm_tests::InitArgs) -> InitArgs {
-    InitArgs {
+fn encode_init_args(args: ic_icrc1_ledger_sm_tests::InitArgs) -> LedgerArgument {
+    LedgerArgument::Init(InitArgs {
         minting_account: args.minting_account,
         initial_balances: args.initial_balances,
         transfer_fee: args.transfer_fee,
@@ -200,7 +165,8 @@ fn encode_init_args(args: ic_icrc1_ledger_sm_tests::InitArgs) -> InitArgs {
         token_symbol: args.token_symbol,
         metadata: args.metadata,
         archive_options: args.archive_options,
-    }
+        fee_collector_account: None,
+    })
 }
 
 #[test]
@@ -271,195 +237,17 @@ fn test_metadata() {
 
 #[test]
 fn test_tx_deduplication() {
-    let env = StateMachine::new();
-    let p1 = PrincipalId::new_user_test_id(1);
-    let p2 = PrincipalId::new_user_test_id(2);
-    let canister_id = install_ledger(&env, vec![(Account::from(p1), 10_000_000)]);
-
-    // No created_at_time => no deduplication
-    let block_id = transfer(&env, canister_id, p1, p2, 10_000).expect("transfer failed");
-    assert!(transfer(&env, canister_id, p1, p2, 10_000).expect("transfer failed") > block_id);
-
-    let now = system_time_to_nanos(env.time());
-
-    let transfer_args = TransferArg {
-        from_subaccount: None,
-        to: p2.into(),
-        fee: None,
-        amount: Nat::from(1_000_000),
-        created_at_time: Some(now),
-        memo: None,
-    };
-
-    let block_idx = send_transfer(&env, canister_id, p1, &transfer_args).expect("transfer failed");
-
-    assert_eq!(
-        send_transfer(&env, canister_id, p1, &transfer_args),
-        Err(TransferError::Duplicate {
-            duplicate_of: Nat::from(block_idx)
-        })
-    );
-
-    env.advance_time(TX_WINDOW + Duration::from_secs(5 * 60));
-    let now = system_time_to_nanos(env.time());
-
-    assert_eq!(
-        send_transfer(&env, canister_id, p1, &transfer_args,),
-        Err(TransferError::TooOld),
-    );
-
-    // Same transaction, but `created_at_time` specified explicitly.
-    // The ledger should not deduplicate this request.
-    let block_idx = send_transfer(
-        &env,
-        canister_id,
-        p1,
-        &TransferArg {
-            from_subaccount: None,
-            to: p2.into(),
-            fee: None,
-            amount: Nat::from(1_000_000),
-            created_at_time: Some(now),
-            memo: None,
-        },
-    )
-    .expect("transfer failed");
-
-    // This time the transaction is a duplicate.
-    assert_eq!(
-        Err(TransferError::Duplicate {
-            duplicate_of: Nat::from(block_idx)
-        }),
-        send_transfer(
-            &env,
-            canister_id,
-            p1,
-            &TransferArg {
-                from_subaccount: None,
-                to: p2.into(),
-                fee: None,
-                amount: Nat::from(1_000_000),
-                created_at_time: Some(now),
-                memo: None,
-            }
-        )
-    );
-
-    // Same transaction, but with "default" `memo`.
-    // The ledger should not deduplicate because we set a new field explicitly.
-    let block_idx = send_transfer(
-        &env,
-        canister_id,
-        p1,
-        &TransferArg {
-            from_subaccount: None,
-            to: p2.into(),
-            fee: None,
-            amount: Nat::from(1_000_000),
-            created_at_time: Some(now),
-            memo: Some(Memo::default()),
-        },
-    )
-    .expect("transfer failed");
-
-    // This time the transaction is a duplicate.
-    assert_eq!(
-        Err(TransferError::Duplicate {
-            duplicate_of: Nat::from(block_idx)
-        }),
-        send_transfer(
-            &env,
-            canister_id,
-            p1,
-            &TransferArg {
-                from_subaccount: None,
-                to: p2.into(),
-                fee: None,
-                amount: Nat::from(1_000_000),
-                created_at_time: Some(now),
-                memo: Some(Memo::default()),
-            }
-        )
-    );
+    ic_icrc1_ledger_sm_tests::test_tx_deduplication(ledger_wasm(), encode_init_args);
 }
 
 #[test]
 fn test_mint_burn() {
-    let env = StateMachine::new();
-    let p1 = PrincipalId::new_user_test_id(1);
-    let p2 = PrincipalId::new_user_test_id(2);
-    let canister_id = install_ledger(&env, vec![]);
-
-    assert_eq!(0, total_supply(&env, canister_id));
-    assert_eq!(0, balance_of(&env, canister_id, p1));
-    assert_eq!(0, balance_of(&env, canister_id, MINTER.clone()));
-
-    transfer(&env, canister_id, MINTER.clone(), p1, 10_000_000).expect("mint failed");
-
-    assert_eq!(10_000_000, total_supply(&env, canister_id));
-    assert_eq!(10_000_000, balance_of(&env, canister_id, p1));
-    assert_eq!(0, balance_of(&env, canister_id, MINTER.clone()));
-
-    transfer(&env, canister_id, p1, MINTER.clone(), 1_000_000).expect("burn failed");
-
-    assert_eq!(9_000_000, total_supply(&env, canister_id));
-    assert_eq!(9_000_000, balance_of(&env, canister_id, p1));
-    assert_eq!(0, balance_of(&env, canister_id, MINTER.clone()));
-
-    // You have at least FEE, you can burn at least FEE
-    assert_eq!(
-        Err(TransferError::BadBurn {
-            min_burn_amount: Nat::from(FEE)
-        }),
-        transfer(&env, canister_id, p1, MINTER.clone(), FEE / 2),
-    );
-
-    transfer(&env, canister_id, p1, p2, FEE / 2).expect("transfer failed");
-
-    assert_eq!(FEE / 2, balance_of(&env, canister_id, p2));
-
-    // If you have less than FEE, you can burn only the whole amount.
-    assert_eq!(
-        Err(TransferError::BadBurn {
-            min_burn_amount: Nat::from(FEE / 2)
-        }),
-        transfer(&env, canister_id, p2, MINTER.clone(), FEE / 4),
-    );
-    transfer(&env, canister_id, p2, MINTER.clone(), FEE / 2).expect("burn failed");
-
-    assert_eq!(0, balance_of(&env, canister_id, p2));
-
-    // You cannot burn zero tokens, no matter what your balance is.
-    assert_eq!(
-        Err(TransferError::BadBurn {
-            min_burn_amount: Nat::from(FEE)
-        }),
-        transfer(&env, canister_id, p2, MINTER.clone(), 0),
-    );
+    ic_icrc1_ledger_sm_tests::test_mint_burn(ledger_wasm(), encode_init_args);
 }
 
 #[test]
 fn test_single_transfer() {
-    let env = StateMachine::new();
-    let p1 = PrincipalId::new_user_test_id(1);
-    let p2 = PrincipalId::new_user_test_id(2);
-    let canister_id = install_ledger(
-        &env,
-        vec![
-            (Account::from(p1), 10_000_000),
-            (Account::from(p2), 5_000_000),
-        ],
-    );
-
-    assert_eq!(15_000_000, total_supply(&env, canister_id));
-    assert_eq!(10_000_000u64, balance_of(&env, canister_id, p1));
-    assert_eq!(5_000_000u64, balance_of(&env, canister_id, p2));
-
-    transfer(&env, canister_id, p1, p2, 1_000_000).expect("transfer failed");
-
-    assert_eq!(15_000_000 - FEE, total_supply(&env, canister_id));
-    assert_eq!(9_000_000u64 - FEE, balance_of(&env, canister_id, p1));
-    assert_eq!(6_000_000u64, balance_of(&env, canister_id, p2));
+    ic_icrc1_ledger_sm_tests::test_single_transfer(ledger_wasm(), encode_init_args);
 }
 
 #[test]
@@ -607,50 +395,12 @@ fn test_memo_validation() {
 
 #[test]
 fn test_tx_time_bounds() {
-    let env = StateMachine::new();
-    let p1 = PrincipalId::new_user_test_id(1);
-    let p2 = PrincipalId::new_user_test_id(2);
-    let canister_id = install_ledger(&env, vec![(Account::from(p1), 10_000_000)]);
-
-    let now = system_time_to_nanos(env.time());
-    let tx_window = TX_WINDOW.as_nanos() as u64;
-
-    assert_eq!(
-        Err(TransferError::TooOld),
-        send_transfer(
-            &env,
-            canister_id,
-            p1,
-            &TransferArg {
-                from_subaccount: None,
-                to: p2.into(),
-                fee: None,
-                amount: Nat::from(1_000_000),
-                created_at_time: Some(now - tx_window - 1),
-                memo: None,
-            }
-        )
-    );
-
-    assert_eq!(
-        Err(TransferError::CreatedInFuture { ledger_time: now }),
-        send_transfer(
-            &env,
-            canister_id,
-            p1,
-            &TransferArg {
-                from_subaccount: None,
-                to: p2.into(),
-                fee: None,
-                amount: Nat::from(1_000_000),
-                created_at_time: Some(now + Duration::from_secs(5 * 60).as_nanos() as u64),
-                memo: None
-            }
-        )
-    );
+    ic_icrc1_ledger_sm_tests::test_tx_time_bounds(ledger_wasm(), encode_init_args);
+}
 
-    assert_eq!(10_000_000u64, balance_of(&env, canister_id, p1));
-    assert_eq!(0u64, balance_of(&env, canister_id, p2));
+#[test]
+fn test_upgrade_preserves_balances() {
+    ic_icrc1_ledger_sm_tests::test_upgrade_preserves_balances(ledger_wasm(), encode_init_args);
 }
 
 #[test]
@@ -821,14 +571,30 @@ fn arb_transaction() -> impl Strategy<Value = Transaction> {
         })
 }
 
-fn arb_block() -> impl Strategy<Value = Block> {
-    (any::<Option<[u8; 32]>>(), arb_transaction(), any::<u64>()).prop_map(
-        |(parent_hash, transaction, ts)| Block {
-            parent_hash: parent_hash.map(HashOf::new),
-            transaction,
-            timestamp: ts,
+fn arb_fee_collector() -> impl Strategy<Value = Option<FeeCollector<Account>>> {
+    proptest::option::of((arb_account(), any::<Option<u64>>()).prop_map(
+        |(fee_collector, block_index)| FeeCollector {
+            fee_collector,
+            block_index,
         },
+    ))
+}
+
+fn arb_block() -> impl Strategy<Value = Block> {
+    (
+        any::<Option<[u8; 32]>>(),
+        arb_transaction(),
+        any::<u64>(),
+        arb_fee_collector(),
     )
+        .prop_map(|(parent_hash, transaction, ts, fee_collector)| {
+            Block::from_transaction(
+                parent_hash.map(HashOf::new),
+                transaction,
+                TimeStamp::from_nanos_since_unix_epoch(ts),
+                fee_collector,
+            )
+        })
 }
 
 // Generate random blocks and check that their CBOR encoding complies with the CDDL spec.
@@ -906,6 +672,491 @@ fn block_hashes_are_stable() {
         .unwrap();
 }
 
+fn get_blocks(
+    env: &StateMachine,
+    ledger: CanisterId,
+    start: u64,
+    length: u64,
+) -> GetBlocksResponse {
+    Decode!(
+        &env.query(
+            ledger,
+            "get_blocks",
+            Encode!(&GetBlocksRequest {
+                start: Nat::from(start),
+                length: Nat::from(length),
+            })
+            .unwrap()
+        )
+        .expect("failed to query blocks")
+        .bytes(),
+        GetBlocksResponse
+    )
+    .expect("failed to decode get_blocks response")
+}
+
+#[test]
+fn test_get_blocks_certification() {
+    let env = StateMachine::new();
+    let p1 = PrincipalId::new_user_test_id(1);
+    let p2 = PrincipalId::new_user_test_id(2);
+    let ledger = install_ledger(&env, vec![(Account::from(p1), 10_000_000)]);
+
+    for i in 1..=5 {
+        transfer(&env, ledger, p1, p2, i * 1_000).expect("transfer failed");
+    }
+
+    for (start, length) in [(0, 6), (2, 3), (5, 10), (6, 1)] {
+        let resp = get_blocks(&env, ledger, start, length);
+        assert_eq!(resp.chain_length, 6);
+        assert_eq!(
+            resp.blocks.len() as u64,
+            length.min(6u64.saturating_sub(start))
+        );
+
+        let root_hash =
+            verify_blocks(&resp.hash_tree, start, &resp.blocks).expect("failed to verify blocks");
+        let certificate = resp.certificate.expect("missing data certificate");
+        ic_certification::verify_certified_data(&certificate, &ledger, &env.root_key(), &root_hash)
+            .expect("failed to verify the certificate");
+    }
+
+    // Tampering with a block must invalidate the response.
+    let resp = get_blocks(&env, ledger, 0, 6);
+    let mut blocks = resp.blocks.clone();
+    blocks.swap(1, 2);
+    assert_eq!(
+        verify_blocks(&resp.hash_tree, 0, &blocks),
+        Err(BlockVerificationError::BlockHashMismatch { index: 1 })
+    );
+    assert_eq!(
+        verify_blocks(&resp.hash_tree, 1, &resp.blocks),
+        Err(BlockVerificationError::BlockHashMismatch { index: 1 })
+    );
+}
+
+fn get_archive_blocks(
+    env: &StateMachine,
+    archive: CanisterId,
+    start: u64,
+    length: u64,
+) -> BlockRange {
+    Decode!(
+        &env.query(
+            archive,
+            "get_blocks",
+            Encode!(&GetBlocksRequest {
+                start: Nat::from(start),
+                length: Nat::from(length),
+            })
+            .unwrap()
+        )
+        .expect("failed to query archive blocks")
+        .bytes(),
+        BlockRange
+    )
+    .expect("failed to decode get_blocks response")
+}
+
+#[test]
+fn test_archive_get_blocks_certification() {
+    let env = StateMachine::new();
+    let p1 = PrincipalId::new_user_test_id(1);
+    let p2 = PrincipalId::new_user_test_id(2);
+    let ledger = install_ledger(&env, vec![(Account::from(p1), 10_000_000)]);
+
+    for i in 0..ARCHIVE_TRIGGER_THRESHOLD {
+        transfer(&env, ledger, p1, p2, 10_000 + i).expect("transfer failed");
+    }
+    env.run_until_completion(/*max_ticks=*/ 10);
+
+    let resp = get_blocks(&env, ledger, 0, ARCHIVE_TRIGGER_THRESHOLD + 1);
+    assert_eq!(resp.archived_blocks.len(), 1);
+    let archive = resp.archived_blocks[0].callback.canister_id;
+    assert_eq!(resp.archived_blocks[0].callback.method, "get_blocks");
+
+    let check_certified_blocks = |env: &StateMachine| {
+        for (start, length) in [(0, NUM_BLOCKS_TO_ARCHIVE), (1, 3), (4, 10)] {
+            let resp = get_archive_blocks(env, archive, start, length);
+            assert_eq!(
+                resp.blocks.len() as u64,
+                length.min(NUM_BLOCKS_TO_ARCHIVE - start)
+            );
+
+            let root_hash = verify_blocks(&resp.hash_tree, start, &resp.blocks)
+                .expect("failed to verify archived blocks");
+            let certificate = resp.certificate.expect("missing data certificate");
+            ic_certification::verify_certified_data(
+                &certificate,
+                &archive,
+                &env.root_key(),
+                &root_hash,
+            )
+            .expect("failed to verify the certificate");
+        }
+    };
+    check_certified_blocks(&env);
+
+    // The archive returns the same blocks as the ledger did before archiving.
+    let resp = get_archive_blocks(&env, archive, 0, NUM_BLOCKS_TO_ARCHIVE);
+    for (i, block) in resp.blocks.iter().enumerate().skip(1) {
+        let block = Block::decode(block.clone()).expect("failed to decode archived block");
+        assert_eq!(
+            block.transaction.operation,
+            Operation::Transfer {
+                from: p1.into(),
+                to: p2.into(),
+                amount: 10_000 + i as u64 - 1,
+                fee: FEE,
+            }
+        );
+    }
+
+    // Tampering with a block must invalidate the response.
+    let mut blocks = resp.blocks.clone();
+    blocks.swap(1, 2);
+    assert_eq!(
+        verify_blocks(&resp.hash_tree, 0, &blocks),
+        Err(BlockVerificationError::BlockHashMismatch { index: 1 })
+    );
+
+    // The archive keeps certifying its blocks after an upgrade.
+    env.upgrade_canister(archive, archive_wasm(), vec![])
+        .expect("failed to upgrade the archive canister");
+    check_certified_blocks(&env);
+}
+
+#[test]
+fn test_fee_collector() {
+    let env = StateMachine::new();
+    let p1 = PrincipalId::new_user_test_id(1);
+    let p2 = PrincipalId::new_user_test_id(2);
+    let fee_collector = Account {
+        owner: PrincipalId::new_user_test_id(3),
+        subaccount: Some([3; 32]),
+    };
+    let args = InitArgs {
+        fee_collector_account: Some(fee_collector.clone()),
+        ..init_args(vec![(Account::from(p1), 10_000_000)])
+    };
+    let ledger = env
+        .install_canister(
+            ledger_wasm(),
+            Encode!(&LedgerArgument::Init(args)).unwrap(),
+            None,
+        )
+        .unwrap();
+
+    transfer(&env, ledger, p1, p2, 1_000).expect("transfer failed");
+    transfer(&env, ledger, p2, p1, 500).expect("transfer failed");
+
+    // The fees go to the fee collector instead of being burned.
+    assert_eq!(balance_of(&env, ledger, fee_collector.clone()), 2 * FEE);
+    assert_eq!(total_supply(&env, ledger), 10_000_000);
+    assert_eq!(balance_of(&env, ledger, p1), 10_000_000 - 1_000 - FEE + 500);
+    assert_eq!(balance_of(&env, ledger, p2), 1_000 - 500 - FEE);
+
+    env.upgrade_canister(ledger, ledger_wasm(), Encode!().unwrap())
+        .expect("failed to upgrade the ledger");
+    transfer(&env, ledger, p1, p2, 1_000).expect("transfer failed");
+    assert_eq!(balance_of(&env, ledger, fee_collector.clone()), 3 * FEE);
+
+    // Mints and burns do not pay fees, so their blocks do not refer to the
+    // fee collector.
+    transfer(&env, ledger, MINTER.clone(), p2, FEE).expect("mint failed");
+    transfer(&env, ledger, p2, MINTER.clone(), FEE).expect("burn failed");
+    assert_eq!(balance_of(&env, ledger, fee_collector.clone()), 3 * FEE);
+
+    // Only the first transfer block contains the fee collector account, the
+    // other transfer blocks refer to the first one.
+    let blocks: Vec<Block> = get_blocks(&env, ledger, 0, 6)
+        .blocks
+        .into_iter()
+        .map(|b| Block::decode(b).unwrap())
+        .collect();
+    assert_eq!(blocks.len(), 6);
+    for i in [0, 4, 5] {
+        assert_eq!(blocks[i].fee_collector, None, "block {}", i);
+        assert_eq!(blocks[i].fee_collector_block_index, None, "block {}", i);
+    }
+    assert_eq!(blocks[1].fee_collector, Some(fee_collector));
+    assert_eq!(blocks[1].fee_collector_block_index, None);
+    for block in &blocks[2..4] {
+        assert_eq!(block.fee_collector, None);
+        assert_eq!(block.fee_collector_block_index, Some(1));
+    }
+}
+
+#[test]
+fn test_fees_are_burned_without_fee_collector() {
+    let env = StateMachine::new();
+    let p1 = PrincipalId::new_user_test_id(1);
+    let p2 = PrincipalId::new_user_test_id(2);
+    let ledger = install_ledger(&env, vec![(Account::from(p1), 10_000_000)]);
+
+    transfer(&env, ledger, p1, p2, 1_000).expect("transfer failed");
+
+    assert_eq!(total_supply(&env, ledger), 10_000_000 - FEE);
+    let block = Block::decode(get_blocks(&env, ledger, 1, 1).blocks.remove(0)).unwrap();
+    assert_eq!(block.fee_collector, None);
+    assert_eq!(block.fee_collector_block_index, None);
+}
+
+fn upgrade_ledger(
+    env: &StateMachine,
+    ledger: CanisterId,
+    args: UpgradeArgs,
+) -> Result<(), ic_state_machine_tests::UserError> {
+    env.upgrade_canister(
+        ledger,
+        ledger_wasm(),
+        Encode!(&LedgerArgument::Upgrade(Some(args))).unwrap(),
+    )
+}
+
+fn fee(env: &StateMachine, ledger: CanisterId) -> u64 {
+    Decode!(
+        &env.query(ledger, "icrc1_fee", Encode!().unwrap())
+            .expect("failed to query fee")
+            .bytes(),
+        Nat
+    )
+    .expect("failed to decode icrc1_fee response")
+    .0
+    .to_u64()
+    .unwrap()
+}
+
+#[test]
+fn test_upgrade_args() {
+    let env = StateMachine::new();
+    let p1 = PrincipalId::new_user_test_id(1);
+    let p2 = PrincipalId::new_user_test_id(2);
+    let fee_collector = Account::from(PrincipalId::new_user_test_id(3));
+    let ledger = install_ledger(&env, vec![(Account::from(p1), 10_000_000)]);
+
+    upgrade_ledger(
+        &env,
+        ledger,
+        UpgradeArgs {
+            metadata: Some(vec![Value::entry(TEXT_META_KEY, "new value")]),
+            token_name: Some("New Token".to_string()),
+            token_symbol: Some("NEW".to_string()),
+            transfer_fee: Some(2 * FEE),
+            change_fee_collector: Some(ChangeFeeCollector::SetTo(fee_collector.clone())),
+            archive_options: None,
+        },
+    )
+    .expect("failed to upgrade the ledger");
+
+    let metadata = metadata(&env, ledger);
+    assert_eq!(metadata.get("icrc1:name"), Some(&Value::from("New Token")));
+    assert_eq!(metadata.get("icrc1:symbol"), Some(&Value::from("NEW")));
+    assert_eq!(metadata.get("icrc1:fee"), Some(&Value::from(2 * FEE)));
+    assert_eq!(metadata.get(TEXT_META_KEY), Some(&Value::from("new value")));
+    assert_eq!(metadata.get(NAT_META_KEY), None);
+    assert_eq!(fee(&env, ledger), 2 * FEE);
+
+    transfer(&env, ledger, p1, p2, 1_000).expect("transfer failed");
+    assert_eq!(balance_of(&env, ledger, fee_collector.clone()), 2 * FEE);
+    assert_eq!(balance_of(&env, ledger, p1), 10_000_000 - 1_000 - 2 * FEE);
+    let block = Block::decode(get_blocks(&env, ledger, 1, 1).blocks.remove(0)).unwrap();
+    assert_eq!(block.fee_collector, Some(fee_collector.clone()));
+
+    // An upgrade without arguments keeps the configuration.
+    env.upgrade_canister(ledger, ledger_wasm(), Encode!().unwrap())
+        .expect("failed to upgrade the ledger");
+    assert_eq!(fee(&env, ledger), 2 * FEE);
+
+    upgrade_ledger(
+        &env,
+        ledger,
+        UpgradeArgs {
+            change_fee_collector: Some(ChangeFeeCollector::Unset),
+            ..UpgradeArgs::default()
+        },
+    )
+    .expect("failed to upgrade the ledger");
+    transfer(&env, ledger, p1, p2, 1_000).expect("transfer failed");
+    assert_eq!(balance_of(&env, ledger, fee_collector), 2 * FEE);
+    assert_eq!(total_supply(&env, ledger), 10_000_000 - 2 * FEE);
+}
+
+#[test]
+fn test_invalid_upgrade_args() {
+    let env = StateMachine::new();
+    let ledger = install_ledger(&env, vec![]);
+
+    for args in [
+        UpgradeArgs {
+            token_symbol: Some("".to_string()),
+            ..UpgradeArgs::default()
+        },
+        UpgradeArgs {
+            metadata: Some(vec![Value::entry("icrc1:fee", 0u64)]),
+            ..UpgradeArgs::default()
+        },
+        UpgradeArgs {
+            change_fee_collector: Some(ChangeFeeCollector::SetTo(MINTER.clone())),
+            ..UpgradeArgs::default()
+        },
+    ] {
+        assert!(
+            upgrade_ledger(&env, ledger, args.clone()).is_err(),
+            "{:?}",
+            args
+        );
+    }
+
+    // Installing the ledger with upgrade arguments fails.
+    assert!(env
+        .install_canister(
+            ledger_wasm(),
+            Encode!(&LedgerArgument::Upgrade(None)).unwrap(),
+            None
+        )
+        .is_err());
+
+    assert_eq!(fee(&env, ledger), FEE);
+}
+
+/// Installs a canister with an empty module, fills its stable memory with
+/// `stable_memory` and upgrades it to the ledger.
+fn install_ledger_from_stable_memory(env: &StateMachine, stable_memory: &[u8]) -> CanisterId {
+    let ledger = env.install_canister_wat("(module)", vec![], None);
+    env.set_stable_memory(ledger, stable_memory);
+    env.upgrade_canister(ledger, ledger_wasm(), Encode!().unwrap())
+        .expect("failed to upgrade to the ledger");
+    ledger
+}
+
+/// Returns the stable memory of a ledger that kept balances on the heap and
+/// wrote its whole state directly to the stable memory in `pre_upgrade`.
+fn legacy_stable_memory(initial_balances: Vec<(Account, u64)>) -> Vec<u8> {
+    let mut heap_balances = ic_icrc1::LedgerBalances::new();
+    for (account, amount) in initial_balances.iter() {
+        heap_balances
+            .mint(account, Tokens::from_e8s(*amount))
+            .expect("failed to mint tokens");
+    }
+    let ledger = Ledger::from_init_args(init_args(initial_balances), TimeStamp::new(0, 0));
+
+    let state = match CborValue::serialized(&ledger).expect("failed to encode ledger state") {
+        CborValue::Map(fields) => CborValue::Map(
+            fields
+                .into_iter()
+                .map(|(key, value)| {
+                    if key == CborValue::Text("balances".to_string()) {
+                        let balances = CborValue::serialized(&heap_balances)
+                            .expect("failed to encode balances");
+                        (key, balances)
+                    } else {
+                        (key, value)
+                    }
+                })
+                .collect(),
+        ),
+        state => panic!("expected the ledger state to be a map, got {:?}", state),
+    };
+
+    let mut stable_memory = vec![];
+    ciborium::ser::into_writer(&state, &mut stable_memory).expect("failed to encode ledger state");
+    stable_memory
+}
+
+/// Returns the stable memory of a ledger holding `num_accounts` accounts.  The
+/// accounts are credited directly, without recording any blocks, so ledgers
+/// built by this function differ only in the number of accounts.
+fn stable_memory_with_accounts(num_accounts: u64) -> Vec<u8> {
+    // Each thread has its own stable memory, so ledgers built by the same test
+    // do not share balances.
+    std::thread::spawn(move || {
+        let mut ledger = Ledger::from_init_args(init_args(vec![]), TimeStamp::new(0, 0));
+        for i in 0..num_accounts {
+            let mut subaccount = [0u8; 32];
+            subaccount[..8].copy_from_slice(&i.to_be_bytes());
+            let account = Account {
+                owner: PrincipalId::new_user_test_id(1),
+                subaccount: Some(subaccount),
+            };
+            ledger
+                .balances_mut()
+                .mint(&account, Tokens::from_e8s(FEE))
+                .expect("failed to mint tokens");
+        }
+        ciborium::ser::into_writer(&ledger, upgrades_writer())
+            .expect("failed to encode ledger state");
+        read_stable_memory()
+    })
+    .join()
+    .expect("failed to build the ledger stable memory")
+}
+
+#[test]
+fn test_upgrade_from_heap_balances() {
+    let env = StateMachine::new();
+    let p1 = PrincipalId::new_user_test_id(1);
+    let p2 = PrincipalId::new_user_test_id(2);
+    let initial_balances: Vec<_> = (0..100)
+        .map(|i| {
+            let mut subaccount = [0u8; 32];
+            subaccount[..8].copy_from_slice(&i.to_be_bytes());
+            let account = Account {
+                owner: p1,
+                subaccount: Some(subaccount),
+            };
+            (account, (i + 1) * FEE)
+        })
+        .collect();
+    let expected_supply: u64 = initial_balances.iter().map(|(_, amount)| amount).sum();
+
+    let ledger =
+        install_ledger_from_stable_memory(&env, &legacy_stable_memory(initial_balances.clone()));
+
+    let check_balances = |env: &StateMachine| {
+        assert_eq!(total_supply(env, ledger), expected_supply);
+        for (account, amount) in initial_balances.iter() {
+            assert_eq!(balance_of(env, ledger, account.clone()), *amount);
+        }
+    };
+    check_balances(&env);
+
+    // The migrated ledger keeps its balances across subsequent upgrades.
+    env.upgrade_canister(ledger, ledger_wasm(), Encode!().unwrap())
+        .expect("failed to upgrade the ledger");
+    check_balances(&env);
+
+    let (from, amount) = initial_balances[0].clone();
+    transfer(&env, ledger, from.clone(), p2, amount - FEE).expect("transfer failed");
+    assert_eq!(balance_of(&env, ledger, from), 0);
+    assert_eq!(balance_of(&env, ledger, p2), amount - FEE);
+}
+
+#[test]
+fn test_upgrade_instructions_do_not_depend_on_number_of_accounts() {
+    let upgrade_instructions = |num_accounts: u64| {
+        let env = StateMachine::new();
+        let ledger =
+            install_ledger_from_stable_memory(&env, &stable_memory_with_accounts(num_accounts));
+        assert_eq!(total_supply(&env, ledger), num_accounts * FEE);
+
+        let instructions_before = env.subnet_message_instructions();
+        env.upgrade_canister(ledger, ledger_wasm(), Encode!().unwrap())
+            .expect("failed to upgrade the ledger");
+        assert_eq!(total_supply(&env, ledger), num_accounts * FEE);
+        env.subnet_message_instructions() - instructions_before
+    };
+
+    let few_accounts = upgrade_instructions(1_000);
+    let many_accounts = upgrade_instructions(1_000_000);
+    assert!(
+        many_accounts <= few_accounts * 1.1,
+        "upgrading a ledger with 1M accounts took {} instructions, with 1K accounts {}",
+        many_accounts,
+        few_accounts
+    );
+}
+
 #[test]
 fn check_transfer_model() {
     use proptest::collection::vec as pvec;
@@ -3,9 +3,11 @@ use candid::types::number::{Int, Nat};
 use candid::CandidType;
 use ic_base_types::CanisterId;
 use ic_ledger_canister_core::ledger::TransferError as CoreTransferError;
+use ic_ledger_core::block::EncodedBlock;
 use serde::Deserialize;
 use serde_bytes::ByteBuf;
 use std::convert::TryFrom;
+use std::marker::PhantomData;
 
 pub type NumTokens = Nat;
 pub type BlockIndex = Nat;
@@ -199,6 +201,11 @@ pub struct Transaction {
     pub burn: Option<Burn>,
     pub transfer: Option<Transfer>,
     pub timestamp: u64,
+    /// The account receiving the transfer fees, see [Block::fee_collector].
+    pub fee_collector: Option<Account>,
+    /// The index of the block that specifies the fee collector, see
+    /// [Block::fee_collector_block_index].
+    pub fee_collector_block: Option<u64>,
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -221,15 +228,66 @@ pub struct TransactionRange {
     pub transactions: Vec<Transaction>,
 }
 
+pub type GetBlocksRequest = GetTransactionsRequest;
+
+/// A range of blocks served by an archive.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BlockRange {
+    pub blocks: Vec<EncodedBlock>,
+    /// The certificate of the archive's certified data.  Only present in
+    /// non-replicated query responses.
+    pub certificate: Option<ByteBuf>,
+    /// The CBOR-encoded hash tree revealing the hashes of the returned blocks.
+    /// The digest of the tree is the archive's certified data.
+    pub hash_tree: ByteBuf,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ArchivedBlocksRange {
+    pub start: Nat,
+    pub length: Nat,
+    pub callback: QueryBlockArchiveFn,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GetBlocksResponse {
+    pub first_index: Nat,
+    pub chain_length: u64,
+    /// The certificate of the ledger's certified data.  Only present in
+    /// non-replicated query responses.
+    pub certificate: Option<ByteBuf>,
+    /// The CBOR-encoded hash tree revealing the hashes of the returned blocks.
+    /// The digest of the tree is the ledger's certified data.
+    pub hash_tree: ByteBuf,
+    pub blocks: Vec<EncodedBlock>,
+    pub archived_blocks: Vec<ArchivedBlocksRange>,
+}
+
+/// A reference to a query method of an archive canister.  The type parameters
+/// determine the candid type of the method.
 #[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
 #[serde(try_from = "candid::types::reference::Func")]
-pub struct QueryArchiveFn {
+pub struct QueryArchiveFn<Input = GetTransactionsRequest, Output = TransactionRange> {
     pub canister_id: CanisterId,
     pub method: String,
+    #[serde(skip)]
+    _marker: PhantomData<(Input, Output)>,
 }
 
-impl From<QueryArchiveFn> for candid::types::reference::Func {
-    fn from(archive_fn: QueryArchiveFn) -> Self {
+pub type QueryBlockArchiveFn = QueryArchiveFn<GetBlocksRequest, BlockRange>;
+
+impl<Input, Output> QueryArchiveFn<Input, Output> {
+    pub fn new(canister_id: CanisterId, method: impl Into<String>) -> Self {
+        Self {
+            canister_id,
+            method: method.into(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<Input, Output> From<QueryArchiveFn<Input, Output>> for candid::types::reference::Func {
+    fn from(archive_fn: QueryArchiveFn<Input, Output>) -> Self {
         let p: &ic_base_types::PrincipalId = archive_fn.canister_id.as_ref();
         Self {
             principal: p.0,
@@ -238,24 +296,25 @@ impl From<QueryArchiveFn> for candid::types::reference::Func {
     }
 }
 
-impl TryFrom<candid::types::reference::Func> for QueryArchiveFn {
+impl<Input, Output> TryFrom<candid::types::reference::Func> for QueryArchiveFn<Input, Output> {
     type Error = String;
     fn try_from(func: candid::types::reference::Func) -> Result<Self, Self::Error> {
         let canister_id = CanisterId::try_from(func.principal.as_slice())
             .map_err(|e| format!("principal is not a canister id: {}", e))?;
-        Ok(QueryArchiveFn {
-            canister_id,
-            method: func.method,
-        })
+        Ok(QueryArchiveFn::new(canister_id, func.method))
     }
 }
 
-impl CandidType for QueryArchiveFn {
+impl<Input, Output> CandidType for QueryArchiveFn<Input, Output>
+where
+    Input: CandidType + Clone,
+    Output: CandidType + Clone,
+{
     fn _ty() -> candid::types::Type {
         candid::types::Type::Func(candid::types::Function {
             modes: vec![candid::parser::types::FuncMode::Query],
-            args: vec![GetTransactionsRequest::_ty()],
-            rets: vec![TransactionRange::_ty()],
+            args: vec![Input::_ty()],
+            rets: vec![Output::_ty()],
         })
     }
 
@@ -277,6 +336,8 @@ impl From<Block> for Transaction {
             burn: None,
             transfer: None,
             timestamp: b.timestamp,
+            fee_collector: b.fee_collector,
+            fee_collector_block: b.fee_collector_block_index,
         };
         let created_at_time = b.transaction.created_at_time;
         let memo = b.transaction.memo;
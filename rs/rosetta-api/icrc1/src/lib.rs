@@ -5,9 +5,10 @@ use candid::CandidType;
 use ciborium::tag::Required;
 use ic_base_types::PrincipalId;
 use ic_ledger_canister_core::ledger::LedgerTransaction;
+use ic_ledger_canister_core::stable_balances::StorableAccount;
 use ic_ledger_core::{
     balances::{BalanceError, Balances, BalancesStore},
-    block::{BlockType, EncodedBlock, HashOf},
+    block::{BlockIndex, BlockType, EncodedBlock, FeeCollector, HashOf},
     timestamp::TimeStamp,
     tokens::Tokens,
 };
@@ -65,6 +66,35 @@ impl std::hash::Hash for Account {
     }
 }
 
+/// Encodes accounts as the length of the owner principal followed by the
+/// principal bytes and, for non-default subaccounts, the subaccount bytes.
+impl StorableAccount for Account {
+    fn to_storable_bytes(&self) -> Vec<u8> {
+        let owner = self.owner.as_slice();
+        let mut buf = Vec::with_capacity(1 + owner.len() + 32);
+        buf.push(owner.len() as u8);
+        buf.extend_from_slice(owner);
+        if self.effective_subaccount() != DEFAULT_SUBACCOUNT {
+            buf.extend_from_slice(self.effective_subaccount());
+        }
+        buf
+    }
+
+    fn from_storable_bytes(bytes: &[u8]) -> Self {
+        let owner_len = bytes[0] as usize;
+        let owner = PrincipalId::try_from(&bytes[1..1 + owner_len])
+            .expect("BUG: stored account owner must be a valid principal");
+        let subaccount = match &bytes[1 + owner_len..] {
+            [] => None,
+            rest => Some(
+                rest.try_into()
+                    .expect("BUG: stored subaccount must be 32 bytes long"),
+            ),
+        };
+        Self { owner, subaccount }
+    }
+}
+
 impl fmt::Display for Account {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.subaccount {
@@ -99,6 +129,24 @@ where
     Account::try_from(compact_account).map_err(D::Error::custom)
 }
 
+fn ser_opt_compact_account<S>(acc: &Option<Account>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::ser::Serializer,
+{
+    acc.clone().map(CompactAccount::from).serialize(s)
+}
+
+fn de_opt_compact_account<'de, D>(d: D) -> Result<Option<Account>, D::Error>
+where
+    D: serde::de::Deserializer<'de>,
+{
+    use serde::de::Error;
+    Option::<CompactAccount>::deserialize(d)?
+        .map(Account::try_from)
+        .transpose()
+        .map_err(D::Error::custom)
+}
+
 /// A compact representation of an Account.
 ///
 /// Instead of encoding accounts as structs with named fields,
@@ -308,7 +356,15 @@ impl LedgerTransaction for Transaction {
             })
     }
 
-    fn apply<S>(&self, balances: &mut Balances<Self::AccountId, S>) -> Result<(), BalanceError>
+    fn is_transfer(&self) -> bool {
+        matches!(self.operation, Operation::Transfer { .. })
+    }
+
+    fn apply<S>(
+        &self,
+        balances: &mut Balances<Self::AccountId, S>,
+        fee_collector: Option<&Self::AccountId>,
+    ) -> Result<(), BalanceError>
     where
         S: Default + BalancesStore<Self::AccountId>,
     {
@@ -318,7 +374,13 @@ impl LedgerTransaction for Transaction {
                 to,
                 amount,
                 fee,
-            } => balances.transfer(from, to, Tokens::from_e8s(*amount), Tokens::from_e8s(*fee)),
+            } => balances.transfer(
+                from,
+                to,
+                Tokens::from_e8s(*amount),
+                Tokens::from_e8s(*fee),
+                fee_collector,
+            ),
             Operation::Burn { from, amount } => balances.burn(from, Tokens::from_e8s(*amount)),
             Operation::Mint { to, amount } => balances.mint(to, Tokens::from_e8s(*amount)),
         }
@@ -372,12 +434,28 @@ pub struct Block {
     pub transaction: Transaction,
     #[serde(rename = "ts")]
     pub timestamp: u64,
+    /// The account that received the fee of this block's transaction.  Only
+    /// the first block after the ledger starts using a fee collector
+    /// specifies the account, later blocks set
+    /// [Block::fee_collector_block_index] instead.
+    #[serde(rename = "fee_col")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "ser_opt_compact_account")]
+    #[serde(deserialize_with = "de_opt_compact_account")]
+    pub fee_collector: Option<Account>,
+    /// The index of the block that specifies the fee collector of this block.
+    #[serde(rename = "fee_col_block")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_collector_block_index: Option<BlockIndex>,
 }
 
 type TaggedBlock = Required<Block, 55799>;
 
 impl BlockType for Block {
     type Transaction = Transaction;
+    type AccountId = Account;
 
     fn encode(self) -> EncodedBlock {
         let mut bytes = vec![];
@@ -417,11 +495,25 @@ impl BlockType for Block {
         parent_hash: Option<HashOf<EncodedBlock>>,
         transaction: Self::Transaction,
         timestamp: TimeStamp,
+        fee_collector: Option<FeeCollector<Self::AccountId>>,
     ) -> Self {
+        let (fee_collector, fee_collector_block_index) = match fee_collector {
+            None => (None, None),
+            Some(FeeCollector {
+                fee_collector,
+                block_index: None,
+            }) => (Some(fee_collector), None),
+            Some(FeeCollector {
+                block_index: Some(block_index),
+                ..
+            }) => (None, Some(block_index)),
+        };
         Self {
             parent_hash,
             transaction,
             timestamp: timestamp.as_nanos_since_unix_epoch(),
+            fee_collector,
+            fee_collector_block_index,
         }
     }
 }
@@ -180,8 +180,10 @@ impl ClientBalancesStore {
 }
 
 impl BalancesStore<AccountIdentifier> for ClientBalancesStore {
-    fn get_balance(&self, k: &AccountIdentifier) -> Option<&Tokens> {
-        self.acc_to_hist.get(k).and_then(|hist| hist.get_last_ref())
+    fn get_balance(&self, k: &AccountIdentifier) -> Option<Tokens> {
+        self.acc_to_hist
+            .get(k)
+            .and_then(|hist| hist.get_last_ref().cloned())
     }
 
     // In here, ledger removes zero amount accounts from it's map,
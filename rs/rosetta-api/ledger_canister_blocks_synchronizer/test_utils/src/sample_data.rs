@@ -110,7 +110,7 @@ impl Scribe {
         let parent_hash = self.blockchain.back().map(|hb| hb.hash);
         let index = self.next_index();
 
-        let block = Block::from_transaction(parent_hash, transaction, self.time().into());
+        let block = Block::from_transaction(parent_hash, transaction, self.time().into(), None);
 
         self.blockchain
             .push_back(HashedBlock::hash_block(block.encode(), parent_hash, index));
@@ -124,6 +124,7 @@ impl Scribe {
             operation: Operation::Mint { to: uid, amount },
             memo: self.next_message(),
             created_at_time: Some(self.time().into()),
+            icrc1_memo: None,
         };
         self.balance_history.push_back(self.balance_book.clone());
         self.add_block(transaction);
@@ -137,6 +138,7 @@ impl Scribe {
             operation: Operation::Burn { from: uid, amount },
             memo: self.next_message(),
             created_at_time: Some(self.time().into()),
+            icrc1_memo: None,
         };
         self.balance_history.push_back(self.balance_book.clone());
         self.add_block(transaction);
@@ -158,6 +160,7 @@ impl Scribe {
             },
             memo: self.next_message(),
             created_at_time: Some(self.time().into()),
+            icrc1_memo: None,
         };
         self.balance_history.push_back(self.balance_book.clone());
         self.add_block(transaction);
@@ -7,8 +7,9 @@ use ic_ledger_canister_blocks_synchronizer_test_utils::{
 };
 use ic_ledger_canister_core::ledger::LedgerTransaction;
 use ic_ledger_core::{balances::BalancesStore, block::BlockType, Tokens};
-use icp_ledger::{apply_operation, AccountIdentifier, Block, Operation};
+use icp_ledger::{apply_operation, AccountIdentifier, Block, Memo, Operation, Transaction};
 use rusqlite::params;
+use serde_bytes::ByteBuf;
 use std::path::Path;
 pub(crate) fn sqlite_on_disk_store(path: &Path) -> Blocks {
     Blocks::new_persistent(path).unwrap()
@@ -46,6 +47,37 @@ async fn store_smoke_test() {
     );
 }
 
+#[actix_rt::test]
+async fn store_icrc1_memo_test() {
+    init_test_logger();
+    let tmpdir = create_tmp_dir();
+    let mut store = sqlite_on_disk_store(tmpdir.path());
+    let mut scribe = Scribe::new_with_sample_data(10, 10);
+
+    // Blocks created via `icrc1_transfer` carry a byte memo and might lack
+    // `created_at_time`.
+    let icrc1_memo = ByteBuf::from(b"icrc1 memo".to_vec());
+    let to = scribe.accounts[0];
+    scribe.add_block(Transaction {
+        operation: Operation::Mint {
+            to,
+            amount: Tokens::from_e8s(1),
+        },
+        memo: Memo::default(),
+        created_at_time: None,
+        icrc1_memo: Some(icrc1_memo.clone()),
+    });
+
+    for hb in &scribe.blockchain {
+        store.push(hb).unwrap();
+    }
+
+    let last_idx = scribe.blockchain.back().unwrap().index;
+    let transaction = store.get_transaction(&last_idx).unwrap();
+    assert_eq!(transaction.icrc1_memo, Some(icrc1_memo));
+    assert_eq!(transaction.created_at_time, None);
+}
+
 #[actix_rt::test]
 async fn store_coherance_test() {
     init_test_logger();
@@ -123,13 +155,13 @@ async fn store_account_balances_test() {
         if let Some(acc_str) = from_account {
             let id = AccountIdentifier::from_hex(acc_str.as_str()).unwrap();
             let amount_from = store.get_account_balance(&id, &hb.index).unwrap();
-            let amount_local = *account_balances.store.get_balance(&id).unwrap();
+            let amount_local = account_balances.store.get_balance(&id).unwrap();
             assert_eq!(amount_from, amount_local);
         }
         if let Some(acc_str) = to_account {
             let id = AccountIdentifier::from_hex(acc_str.as_str()).unwrap();
             let amount_to = store.get_account_balance(&id, &hb.index).unwrap();
-            let amount_local = *account_balances.store.get_balance(&id).unwrap();
+            let amount_local = account_balances.store.get_balance(&id).unwrap();
             assert_eq!(amount_to, amount_local);
         }
     }
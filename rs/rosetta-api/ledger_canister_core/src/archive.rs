@@ -169,6 +169,24 @@ impl<Rt: Runtime, Wasm: ArchiveCanisterWasm> Archive<Rt, Wasm> {
     pub fn nodes(&self) -> &[CanisterId] {
         &self.nodes
     }
+
+    /// Replaces the archiving options.  The new node capacity and controller
+    /// only apply to archive nodes created after the change.
+    pub fn update_options(&mut self, options: ArchiveOptions) {
+        self.controller_id = options.controller_id;
+        if let Some(node_max_memory_size_bytes) = options.node_max_memory_size_bytes {
+            self.node_max_memory_size_bytes = node_max_memory_size_bytes;
+        }
+        if let Some(max_message_size_bytes) = options.max_message_size_bytes {
+            self.max_message_size_bytes = max_message_size_bytes;
+        }
+        self.trigger_threshold = options.trigger_threshold;
+        self.num_blocks_to_archive = options.num_blocks_to_archive;
+        if let Some(cycles_for_archive_creation) = options.cycles_for_archive_creation {
+            self.cycles_for_archive_creation = cycles_for_archive_creation;
+        }
+        self.max_transactions_per_response = options.max_transactions_per_response;
+    }
 }
 
 /// Grabs a write lock on the archive and executes a synchronous function under the lock.
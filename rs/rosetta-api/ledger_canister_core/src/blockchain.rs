@@ -1,15 +1,28 @@
 use crate::{
     archive::{Archive, ArchiveCanisterWasm, ArchiveOptions},
+    memory::{get_memory, VMem, BLOCK_HASH_TREE_MEMORY_ID},
+    mmr::MerkleMountainRange,
     runtime::Runtime,
 };
+use ic_crypto_tree_hash::MixedHashTree;
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::convert::TryFrom;
+use std::ops::Range;
 use std::sync::{Arc, RwLock};
 
 use ic_ledger_core::block::{BlockIndex, BlockType, EncodedBlock, HashOf};
 use ic_ledger_core::timestamp::TimeStamp;
 
+thread_local! {
+    /// The hashes of the blocks appended since the ledger started maintaining
+    /// the tree.  Ledgers that existed before the tree was introduced cannot
+    /// certify their older blocks because these blocks might be archived.
+    static BLOCK_HASH_TREE: RefCell<MerkleMountainRange<VMem>> =
+        RefCell::new(MerkleMountainRange::init(get_memory(BLOCK_HASH_TREE_MEMORY_ID)));
+}
+
 /// Stores a chain of transactions with their metadata
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(bound = "")]
@@ -29,6 +42,12 @@ pub struct Blockchain<Rt: Runtime, Wasm: ArchiveCanisterWasm> {
 
     /// How many blocks have been sent to the archive
     pub num_archived_blocks: u64,
+
+    /// Whether the blockchain maintains a Merkle mountain range over block
+    /// hashes.  Ledgers that certify block ranges opt in with
+    /// [Self::enable_block_hash_tree].
+    #[serde(default)]
+    pub block_hash_tree_enabled: bool,
 }
 
 impl<Rt: Runtime, Wasm: ArchiveCanisterWasm> Default for Blockchain<Rt, Wasm> {
@@ -39,6 +58,7 @@ impl<Rt: Runtime, Wasm: ArchiveCanisterWasm> Default for Blockchain<Rt, Wasm> {
             last_timestamp: TimeStamp::from_nanos_since_unix_epoch(0),
             archive: Arc::new(RwLock::new(None)),
             num_archived_blocks: 0,
+            block_hash_tree_enabled: false,
         }
     }
 }
@@ -66,11 +86,55 @@ impl<Rt: Runtime, Wasm: ArchiveCanisterWasm> Blockchain<Rt, Wasm> {
         }
         self.last_timestamp = block.timestamp();
         let encoded_block = block.encode();
-        self.last_hash = Some(B::block_hash(&encoded_block));
+        let hash = B::block_hash(&encoded_block);
+        if self.block_hash_tree_enabled {
+            BLOCK_HASH_TREE.with(|tree| {
+                tree.borrow_mut()
+                    .append(self.chain_length(), hash.into_bytes())
+            });
+        }
+        self.last_hash = Some(hash);
         self.blocks.push(encoded_block);
         Ok(self.chain_length().checked_sub(1).unwrap())
     }
 
+    /// Starts maintaining the block hash tree.  The tree covers the blocks
+    /// appended after the first call to this function.
+    pub fn enable_block_hash_tree(&mut self) {
+        self.block_hash_tree_enabled = true;
+    }
+
+    /// The range of blocks whose hashes are in the block hash tree.
+    pub fn certified_block_range(&self) -> Range<BlockIndex> {
+        if !self.block_hash_tree_enabled {
+            return 0..0;
+        }
+        BLOCK_HASH_TREE.with(|tree| tree.borrow().block_range())
+    }
+
+    /// Returns the block hash tree with all the nodes pruned except the
+    /// hashes of the specified blocks.  Without blocks, the result is the
+    /// smallest tree with the same root hash.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the block hash tree is not enabled or the specified range is
+    /// not a subrange of [Self::certified_block_range].
+    pub fn block_hash_tree(&self, blocks: Range<BlockIndex>) -> MixedHashTree {
+        assert!(
+            self.block_hash_tree_enabled,
+            "the block hash tree is not enabled"
+        );
+        BLOCK_HASH_TREE.with(|tree| {
+            let tree = tree.borrow();
+            if blocks.is_empty() {
+                tree.root()
+            } else {
+                tree.witness(blocks)
+            }
+        })
+    }
+
     pub fn get(&self, height: BlockIndex) -> Option<&EncodedBlock> {
         if height < self.num_archived_blocks() {
             None
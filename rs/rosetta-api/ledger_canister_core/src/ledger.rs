@@ -1,18 +1,15 @@
 use crate::{archive::ArchiveCanisterWasm, blockchain::Blockchain, range_utils, runtime::Runtime};
 use ic_base_types::CanisterId;
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::collections::{BTreeMap, VecDeque};
 use std::ops::Range;
 use std::time::Duration;
 
-use ic_ledger_core::balances::{BalanceError, Balances, BalancesStore};
-use ic_ledger_core::block::{BlockIndex, BlockType, EncodedBlock, HashOf};
+use ic_ledger_core::balances::{BalanceError, Balances, BalancesStore, InspectableBalancesStore};
+use ic_ledger_core::block::{BlockIndex, BlockType, EncodedBlock, FeeCollector, HashOf};
 use ic_ledger_core::timestamp::TimeStamp;
 use ic_ledger_core::tokens::Tokens;
 
-/// The memo to use for balances burned during trimming
-const TRIMMED_MEMO: u64 = u64::MAX;
-
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TransactionInfo<TransactionType> {
     pub block_timestamp: TimeStamp,
@@ -37,8 +34,17 @@ pub trait LedgerTransaction: Sized {
     /// Returns the hash of this transaction.
     fn hash(&self) -> HashOf<Self>;
 
-    /// Applies this transaction to the balance book.
-    fn apply<S>(&self, balances: &mut Balances<Self::AccountId, S>) -> Result<(), BalanceError>
+    /// Returns true if this transaction is a transfer.  Only transfers pay
+    /// fees, so only their blocks refer to the fee collector.
+    fn is_transfer(&self) -> bool;
+
+    /// Applies this transaction to the balance book.  Transaction fees go to
+    /// the `fee_collector` account if specified and are burned otherwise.
+    fn apply<S>(
+        &self,
+        balances: &mut Balances<Self::AccountId, S>,
+        fee_collector: Option<&Self::AccountId>,
+    ) -> Result<(), BalanceError>
     where
         S: Default + BalancesStore<Self::AccountId>;
 }
@@ -65,8 +71,9 @@ pub trait LedgerData {
     type AccountId: std::hash::Hash + Ord + Eq + Clone;
     type ArchiveWasm: ArchiveCanisterWasm;
     type Runtime: Runtime;
-    type Block: BlockType<Transaction = Self::Transaction>;
+    type Block: BlockType<Transaction = Self::Transaction, AccountId = Self::AccountId>;
     type Transaction: LedgerTransaction<AccountId = Self::AccountId> + Ord + Clone;
+    type BalancesStore: InspectableBalancesStore<Self::AccountId> + Default;
 
     // Purge configuration
 
@@ -80,13 +87,6 @@ pub trait LedgerData {
     /// The maximum number of transactions that we attempt to purge in one go.
     fn max_transactions_to_purge(&self) -> usize;
 
-    /// The maximum size of the balances map.
-    fn max_number_of_accounts(&self) -> usize;
-
-    /// How many accounts with lowest balances to purge when the number of accounts exceeds
-    /// [LedgerData::max_number_of_accounts].
-    fn accounts_overflow_trim_quantity(&self) -> usize;
-
     // Token configuration
 
     /// Token name (e.g., Bitcoin).
@@ -97,8 +97,8 @@ pub trait LedgerData {
 
     // Ledger data structures
 
-    fn balances(&self) -> &Balances<Self::AccountId, HashMap<Self::AccountId, Tokens>>;
-    fn balances_mut(&mut self) -> &mut Balances<Self::AccountId, HashMap<Self::AccountId, Tokens>>;
+    fn balances(&self) -> &Balances<Self::AccountId, Self::BalancesStore>;
+    fn balances_mut(&mut self) -> &mut Balances<Self::AccountId, Self::BalancesStore>;
 
     fn blockchain(&self) -> &Blockchain<Self::Runtime, Self::ArchiveWasm>;
     fn blockchain_mut(&mut self) -> &mut Blockchain<Self::Runtime, Self::ArchiveWasm>;
@@ -109,6 +109,10 @@ pub trait LedgerData {
     fn transactions_by_height(&self) -> &VecDeque<TransactionInfo<Self::Transaction>>;
     fn transactions_by_height_mut(&mut self) -> &mut VecDeque<TransactionInfo<Self::Transaction>>;
 
+    /// The account receiving transfer fees, if the ledger does not burn them.
+    fn fee_collector(&self) -> Option<&FeeCollector<Self::AccountId>>;
+    fn fee_collector_mut(&mut self) -> Option<&mut FeeCollector<Self::AccountId>>;
+
     /// The callback that the ledger framework calls when it purges a transaction.
     fn on_purged_transaction(&mut self, height: BlockIndex);
 }
@@ -160,15 +164,29 @@ pub fn apply_transaction<L: LedgerData>(
         }
     }
 
+    let fee_collector = if transaction.is_transfer() {
+        ledger.fee_collector().cloned()
+    } else {
+        None
+    };
     transaction
-        .apply(ledger.balances_mut())
+        .apply(
+            ledger.balances_mut(),
+            fee_collector.as_ref().map(|fc| &fc.fee_collector),
+        )
         .map_err(|e| match e {
             BalanceError::InsufficientFunds { balance } => {
                 TransferError::InsufficientFunds { balance }
             }
         })?;
 
-    let block = L::Block::from_transaction(ledger.blockchain().last_hash, transaction, now);
+    let records_fee_collector = fee_collector.is_some();
+    let block = L::Block::from_transaction(
+        ledger.blockchain().last_hash,
+        transaction,
+        now,
+        fee_collector,
+    );
     let block_timestamp = block.timestamp();
 
     let height = ledger
@@ -176,6 +194,14 @@ pub fn apply_transaction<L: LedgerData>(
         .add_block(block)
         .expect("failed to add block");
 
+    if let Some(fee_collector) = ledger.fee_collector_mut().filter(|_| records_fee_collector) {
+        if fee_collector.block_index.is_none() {
+            // Subsequent blocks can refer to the fee collector by the index
+            // of this block.
+            fee_collector.block_index = Some(height);
+        }
+    }
+
     if let Some((_, tx_hash)) = maybe_time_and_hash {
         // The caller requested deduplication, so we have to remember this
         // transaction within the dedup window.
@@ -189,29 +215,6 @@ pub fn apply_transaction<L: LedgerData>(
             });
     }
 
-    let to_trim = if ledger.balances().store.len()
-        >= ledger.max_number_of_accounts() + ledger.accounts_overflow_trim_quantity()
-    {
-        select_accounts_to_trim(ledger)
-    } else {
-        vec![]
-    };
-
-    for (balance, account) in to_trim {
-        let burn_tx = L::Transaction::burn(account, balance, Some(now), Some(TRIMMED_MEMO));
-
-        burn_tx
-            .apply(ledger.balances_mut())
-            .expect("failed to burn funds that must have existed");
-
-        let parent_hash = ledger.blockchain().last_hash;
-
-        ledger
-            .blockchain_mut()
-            .add_block(L::Block::from_transaction(parent_hash, burn_tx, now))
-            .unwrap();
-    }
-
     Ok((height, ledger.blockchain().last_hash.unwrap()))
 }
 
@@ -309,34 +312,6 @@ pub fn purge_old_transactions<L: LedgerData>(ledger: &mut L, now: TimeStamp) ->
     num_tx_purged
 }
 
-// Find the specified number of accounts with lowest balances so that their
-// balances can be reclaimed.
-fn select_accounts_to_trim<L: LedgerData>(ledger: &L) -> Vec<(Tokens, L::AccountId)> {
-    let mut to_trim: std::collections::BinaryHeap<(Tokens, L::AccountId)> =
-        std::collections::BinaryHeap::new();
-
-    let num_accounts = ledger.accounts_overflow_trim_quantity();
-    let mut iter = ledger.balances().store.iter();
-
-    // Accumulate up to `trim_quantity` accounts
-    for (account, balance) in iter.by_ref().take(num_accounts) {
-        to_trim.push((*balance, account.clone()));
-    }
-
-    for (account, balance) in iter {
-        // If any account's balance is lower than the maximum in our set,
-        // include that account, and remove the current maximum
-        if let Some((greatest_balance, _)) = to_trim.peek() {
-            if balance < greatest_balance {
-                to_trim.push((*balance, account.clone()));
-                to_trim.pop();
-            }
-        }
-    }
-
-    to_trim.into_vec()
-}
-
 /// Asynchronously archives a suffix of the locally available blockchain.
 ///
 /// NOTE: only one archiving task can run at each point in time.
@@ -1,6 +1,9 @@
 pub mod archive;
 pub mod blockchain;
 pub mod ledger;
+pub mod memory;
+pub mod mmr;
 pub mod range_utils;
 pub mod runtime;
 mod spawn;
+pub mod stable_balances;
@@ -0,0 +1,134 @@
+//! The layout of the ledger stable memory.
+//!
+//! The stable memory is split into virtual memories:
+//!
+//!   * [UPGRADES_MEMORY_ID] holds the heap state the ledger serializes on
+//!     upgrades.
+//!   * [BALANCES_MEMORY_ID] holds the balances B-tree (see
+//!     [crate::stable_balances]).
+//!   * [BLOCK_HASH_TREE_MEMORY_ID] holds the Merkle mountain range over block
+//!     hashes of ledgers that certify block ranges (see [crate::mmr]).
+//!
+//! The ledger state that lives on the heap is small compared to the data in
+//! stable memory, which makes the cost of an upgrade independent of the number
+//! of accounts and blocks.
+use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
+use ic_stable_structures::{DefaultMemoryImpl, Memory};
+use std::cell::RefCell;
+use std::io;
+
+pub(crate) const WASM_PAGE_SIZE: u64 = 65536;
+
+/// The magic bytes the memory manager writes at the beginning of the stable
+/// memory.
+const MEMORY_MANAGER_MAGIC: &[u8; 3] = b"MGR";
+
+pub(crate) const UPGRADES_MEMORY_ID: MemoryId = MemoryId::new(0);
+pub(crate) const BALANCES_MEMORY_ID: MemoryId = MemoryId::new(1);
+pub(crate) const BLOCK_HASH_TREE_MEMORY_ID: MemoryId = MemoryId::new(2);
+
+pub(crate) type VMem = VirtualMemory<DefaultMemoryImpl>;
+
+thread_local! {
+    /// The stable memory of the canister.  Outside of Wasm, this is an
+    /// in-memory vector shared with the memory manager, which lets tests
+    /// take a snapshot of the ledger stable memory.
+    static STABLE_MEMORY: DefaultMemoryImpl = DefaultMemoryImpl::default();
+
+    static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
+        RefCell::new(MemoryManager::init(STABLE_MEMORY.with(|m| m.clone())));
+}
+
+pub(crate) fn get_memory(id: MemoryId) -> VMem {
+    MEMORY_MANAGER.with(|m| m.borrow().get(id))
+}
+
+/// Returns true if the stable memory holds a ledger state written by a ledger
+/// version that did not use the memory manager.
+pub fn is_legacy_stable_memory() -> bool {
+    STABLE_MEMORY.with(|memory| {
+        if memory.size() == 0 {
+            return false;
+        }
+        let mut magic = [0u8; 3];
+        memory.read(0, &mut magic);
+        &magic != MEMORY_MANAGER_MAGIC
+    })
+}
+
+/// Copies the whole stable memory to the heap.  Ledgers use this function to
+/// read their legacy state before the memory manager takes over the stable
+/// memory.  Tests use it to capture the stable memory of a ledger built
+/// outside of a canister.
+pub fn read_stable_memory() -> Vec<u8> {
+    STABLE_MEMORY.with(|memory| {
+        let mut buf = vec![0u8; (memory.size() * WASM_PAGE_SIZE) as usize];
+        memory.read(0, &mut buf);
+        buf
+    })
+}
+
+/// Returns a writer for the heap state the ledger saves in `pre_upgrade`.
+pub fn upgrades_writer() -> impl io::Write {
+    MemoryWriter {
+        memory: get_memory(UPGRADES_MEMORY_ID),
+        offset: 0,
+    }
+}
+
+/// Returns a reader for the heap state the ledger saved in `pre_upgrade`.
+pub fn upgrades_reader() -> impl io::Read {
+    MemoryReader {
+        memory: get_memory(UPGRADES_MEMORY_ID),
+        offset: 0,
+    }
+}
+
+/// Grows the memory so that it has at least `size` bytes.  Returns false if
+/// the memory cannot grow.
+pub(crate) fn ensure_capacity<M: Memory>(memory: &M, size: u64) -> bool {
+    let capacity = memory.size() * WASM_PAGE_SIZE;
+    if size <= capacity {
+        return true;
+    }
+    let missing_pages = (size - capacity + WASM_PAGE_SIZE - 1) / WASM_PAGE_SIZE;
+    memory.grow(missing_pages) >= 0
+}
+
+struct MemoryWriter<M> {
+    memory: M,
+    offset: u64,
+}
+
+impl<M: Memory> io::Write for MemoryWriter<M> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !ensure_capacity(&self.memory, self.offset + buf.len() as u64) {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "failed to grow the upgrades memory",
+            ));
+        }
+        self.memory.write(self.offset, buf);
+        self.offset += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct MemoryReader<M> {
+    memory: M,
+    offset: u64,
+}
+
+impl<M: Memory> io::Read for MemoryReader<M> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let capacity = self.memory.size() * WASM_PAGE_SIZE;
+        let n = capacity.saturating_sub(self.offset).min(buf.len() as u64) as usize;
+        self.memory.read(self.offset, &mut buf[..n]);
+        self.offset += n as u64;
+        Ok(n)
+    }
+}
@@ -0,0 +1,343 @@
+//! A Merkle mountain range over block hashes.
+//!
+//! The mountain range is a list of perfect binary trees ("mountains") of
+//! strictly decreasing heights.  Each leaf of a mountain is a block hash, so
+//! appending a block takes a logarithmic number of hash computations and the
+//! range never needs to be rebuilt.
+//!
+//! All nodes are [MixedHashTree] nodes: a leaf holding the hash of block `i`
+//! is `Labeled(i, Leaf(hash))` with `i` encoded as a big-endian u64, inner
+//! nodes are forks, and the root is the right fold of the mountain peaks:
+//!
+//! ```text
+//! Fork(peak_0, Fork(peak_1, ... Fork(peak_{k-1}, peak_k)))
+//! ```
+//!
+//! Since the labels grow from left to right, a witness produced by
+//! [MerkleMountainRange::witness] supports the usual label lookups, e.g.,
+//! `witness.lookup(&[i.to_be_bytes()])` returns the hash of block `i`.
+//!
+//! The nodes are stored in post-order in a [Memory] after a header holding the
+//! index of the first block and the number of leaves:
+//!
+//! ```text
+//! [first_index: u64 LE][num_leaves: u64 LE][node_0: 32 bytes][node_1: 32 bytes]...
+//! ```
+//!
+//! Leaf nodes contain the raw block hash, inner nodes contain the digest of
+//! the fork.
+use crate::memory::ensure_capacity;
+use ic_crypto_tree_hash::{Digest, Label, MixedHashTree as T};
+use ic_ledger_core::block::BlockIndex;
+use ic_stable_structures::Memory;
+use std::ops::Range;
+
+const HEADER_SIZE: u64 = 16;
+const NODE_SIZE: u64 = 32;
+
+/// The number of leaves in a mountain of the specified height.
+fn num_mountain_leaves(height: u32) -> u64 {
+    1 << height
+}
+
+/// The number of nodes in a mountain of the specified height.
+fn num_mountain_nodes(height: u32) -> u64 {
+    (1 << (height + 1)) - 1
+}
+
+/// The number of nodes in a mountain range with the specified number of
+/// leaves.
+fn num_nodes(num_leaves: u64) -> u64 {
+    2 * num_leaves - num_leaves.count_ones() as u64
+}
+
+fn leaf_label(index: BlockIndex) -> Label {
+    Label::from(index.to_be_bytes())
+}
+
+fn leaf_digest(index: BlockIndex, hash: &[u8; 32]) -> Digest {
+    T::Labeled(leaf_label(index), Box::new(T::Leaf(hash.to_vec()))).digest()
+}
+
+fn fork_digest(left: Digest, right: Digest) -> Digest {
+    T::Fork(Box::new((T::Pruned(left), T::Pruned(right)))).digest()
+}
+
+/// A mountain of the mountain range.
+struct Mountain {
+    height: u32,
+    /// The position of the first leaf relative to the first leaf of the range.
+    leaf_offset: u64,
+    /// The position of the root node.
+    root_pos: u64,
+}
+
+pub struct MerkleMountainRange<M: Memory> {
+    memory: M,
+    first_index: BlockIndex,
+    num_leaves: u64,
+}
+
+impl<M: Memory> MerkleMountainRange<M> {
+    /// Loads the mountain range from the memory or creates an empty range if
+    /// the memory is empty.
+    pub fn init(memory: M) -> Self {
+        if memory.size() == 0 {
+            return Self {
+                memory,
+                first_index: 0,
+                num_leaves: 0,
+            };
+        }
+        let mut header = [0u8; HEADER_SIZE as usize];
+        memory.read(0, &mut header);
+        Self {
+            memory,
+            first_index: u64::from_le_bytes(header[0..8].try_into().unwrap()),
+            num_leaves: u64::from_le_bytes(header[8..16].try_into().unwrap()),
+        }
+    }
+
+    /// The range of blocks covered by the mountain range.
+    pub fn block_range(&self) -> Range<BlockIndex> {
+        self.first_index..self.first_index + self.num_leaves
+    }
+
+    /// Appends the hash of the block with the specified index.
+    ///
+    /// The first appended block determines the start of the range, all
+    /// subsequent blocks must have consecutive indices.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index does not follow the last block in the range or if
+    /// the memory cannot grow.
+    pub fn append(&mut self, index: BlockIndex, hash: [u8; 32]) {
+        if self.num_leaves == 0 {
+            self.first_index = index;
+        }
+        assert_eq!(
+            index,
+            self.block_range().end,
+            "bug: cannot append block {} to a hash tree covering blocks {:?}",
+            index,
+            self.block_range()
+        );
+
+        let n = self.num_leaves;
+        let mut pos = num_nodes(n);
+        self.write_node(pos, &hash);
+
+        let mut digest = leaf_digest(index, &hash);
+        let mut height = 0;
+        // Every trailing one bit of the old number of leaves corresponds to a
+        // mountain that has the same height as the newly completed one.
+        while (n >> height) & 1 == 1 {
+            let left_pos = pos - num_mountain_nodes(height);
+            let left_digest = self.node_digest(left_pos, height, n - 1);
+            digest = fork_digest(left_digest, digest);
+            pos += 1;
+            self.write_node(pos, &digest.0);
+            height += 1;
+        }
+
+        self.num_leaves += 1;
+        self.write_header();
+    }
+
+    /// Returns the tree with all the mountain peaks pruned.  The digest of this
+    /// tree is the root hash of the mountain range.
+    pub fn root(&self) -> T {
+        self.build_tree(&(0..0))
+    }
+
+    /// Returns a witness revealing the hashes of the specified blocks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mountain range does not cover the specified blocks.
+    pub fn witness(&self, blocks: Range<BlockIndex>) -> T {
+        let range = self.block_range();
+        assert!(
+            range.start <= blocks.start && blocks.end <= range.end,
+            "requested blocks {:?} are not a subrange of blocks {:?}",
+            blocks,
+            range
+        );
+        self.build_tree(&(blocks.start - self.first_index..blocks.end - self.first_index))
+    }
+
+    fn mountains(&self) -> Vec<Mountain> {
+        let mut mountains = vec![];
+        let mut leaf_offset = 0;
+        let mut node_offset = 0;
+        for height in (0..u64::BITS).rev() {
+            if (self.num_leaves >> height) & 1 == 1 {
+                mountains.push(Mountain {
+                    height,
+                    leaf_offset,
+                    root_pos: node_offset + num_mountain_nodes(height) - 1,
+                });
+                leaf_offset += num_mountain_leaves(height);
+                node_offset += num_mountain_nodes(height);
+            }
+        }
+        mountains
+    }
+
+    /// Builds a tree revealing the leaves in the specified range of leaf
+    /// positions and pruning everything else.
+    fn build_tree(&self, leaves: &Range<u64>) -> T {
+        self.mountains()
+            .into_iter()
+            .rev()
+            .map(|m| self.subtree(m.root_pos, m.height, m.leaf_offset, leaves))
+            .reduce(|right, left| T::Fork(Box::new((left, right))))
+            .unwrap_or(T::Empty)
+    }
+
+    fn subtree(&self, pos: u64, height: u32, leaf_offset: u64, leaves: &Range<u64>) -> T {
+        let leaf_end = leaf_offset + num_mountain_leaves(height);
+        if leaves.end <= leaf_offset || leaf_end <= leaves.start {
+            return T::Pruned(self.node_digest(pos, height, leaf_offset));
+        }
+        if height == 0 {
+            return T::Labeled(
+                leaf_label(self.first_index + leaf_offset),
+                Box::new(T::Leaf(self.read_node(pos).to_vec())),
+            );
+        }
+        // The right child immediately precedes the parent, the left child
+        // precedes the right subtree.
+        let right_pos = pos - 1;
+        let left_pos = right_pos - num_mountain_nodes(height - 1);
+        let left = self.subtree(left_pos, height - 1, leaf_offset, leaves);
+        let right = self.subtree(
+            right_pos,
+            height - 1,
+            leaf_offset + num_mountain_leaves(height - 1),
+            leaves,
+        );
+        T::Fork(Box::new((left, right)))
+    }
+
+    /// Returns the digest of the node at the specified position.  The leaf
+    /// offset is only used if the node is a leaf, i.e., the height is zero.
+    fn node_digest(&self, pos: u64, height: u32, leaf_offset: u64) -> Digest {
+        let bytes = self.read_node(pos);
+        if height == 0 {
+            leaf_digest(self.first_index + leaf_offset, &bytes)
+        } else {
+            Digest(bytes)
+        }
+    }
+
+    fn read_node(&self, pos: u64) -> [u8; 32] {
+        let mut buf = [0u8; NODE_SIZE as usize];
+        self.memory.read(HEADER_SIZE + pos * NODE_SIZE, &mut buf);
+        buf
+    }
+
+    fn write_node(&self, pos: u64, bytes: &[u8; 32]) {
+        let offset = HEADER_SIZE + pos * NODE_SIZE;
+        assert!(
+            ensure_capacity(&self.memory, offset + NODE_SIZE),
+            "failed to grow the block hash tree memory"
+        );
+        self.memory.write(offset, bytes);
+    }
+
+    fn write_header(&self) {
+        assert!(
+            ensure_capacity(&self.memory, HEADER_SIZE),
+            "failed to grow the block hash tree memory"
+        );
+        let mut header = [0u8; HEADER_SIZE as usize];
+        header[0..8].copy_from_slice(&self.first_index.to_le_bytes());
+        header[8..16].copy_from_slice(&self.num_leaves.to_le_bytes());
+        self.memory.write(0, &header);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_crypto_tree_hash::LookupStatus;
+    use ic_stable_structures::VectorMemory;
+
+    fn block_hash(i: u64) -> [u8; 32] {
+        let mut hash = [0u8; 32];
+        hash[..8].copy_from_slice(&i.to_le_bytes());
+        hash
+    }
+
+    fn build(first_index: u64, n: u64) -> MerkleMountainRange<VectorMemory> {
+        let mut mmr = MerkleMountainRange::init(VectorMemory::default());
+        for i in first_index..first_index + n {
+            mmr.append(i, block_hash(i));
+        }
+        mmr
+    }
+
+    #[test]
+    fn root_matches_witnesses() {
+        for n in 1..40 {
+            let mmr = build(5, n);
+            let root = mmr.root().digest();
+            for start in 5..5 + n {
+                for end in start..=5 + n {
+                    let witness = mmr.witness(start..end);
+                    assert_eq!(
+                        witness.digest(),
+                        root,
+                        "n = {}, range {}..{}",
+                        n,
+                        start,
+                        end
+                    );
+                    for i in start..end {
+                        assert_eq!(
+                            witness.lookup(&[i.to_be_bytes()]),
+                            LookupStatus::Found(&T::Leaf(block_hash(i).to_vec()))
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn root_depends_on_every_block() {
+        let mmr = build(0, 13);
+        let mut other = MerkleMountainRange::init(VectorMemory::default());
+        for i in 0..13 {
+            let mut hash = block_hash(i);
+            if i == 6 {
+                hash[31] = 1;
+            }
+            other.append(i, hash);
+        }
+        assert_ne!(mmr.root().digest(), other.root().digest());
+    }
+
+    #[test]
+    fn mountain_range_survives_reload() {
+        let memory = VectorMemory::default();
+        let mut mmr = MerkleMountainRange::init(memory.clone());
+        for i in 0..10 {
+            mmr.append(i, block_hash(i));
+        }
+        let mut reloaded = MerkleMountainRange::init(memory);
+        assert_eq!(reloaded.block_range(), 0..10);
+        assert_eq!(reloaded.root().digest(), mmr.root().digest());
+        reloaded.append(10, block_hash(10));
+        assert_eq!(reloaded.root().digest(), build(0, 11).root().digest());
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot append block")]
+    fn rejects_gaps() {
+        let mut mmr = build(0, 3);
+        mmr.append(4, block_hash(4));
+    }
+}
@@ -20,6 +20,7 @@ where
         compute_allocation: None,
         memory_allocation: Some(candid::Nat::from(8 * 1024 * 1024 * 1024u64)),
         query_allocation: None,
+        wasm_module_hash: None,
     };
 
     Rt::call(IC_00, "install_code", /*cycles=*/ 0, (install_code,)).await?;
@@ -0,0 +1,218 @@
+//! A balances store that keeps account balances in a B-tree in stable memory.
+//!
+//! The balances never leave the stable memory, so the ledger does not need to
+//! serialize them on upgrades.
+use crate::memory::{get_memory, VMem, BALANCES_MEMORY_ID};
+use ic_ledger_core::balances::{BalancesStore, InspectableBalancesStore};
+use ic_ledger_core::Tokens;
+use ic_stable_structures::StableBTreeMap;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::cell::RefCell;
+use std::convert::Infallible;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// The maximum size of an encoded account identifier.
+pub const MAX_ACCOUNT_SIZE: u32 = 64;
+
+/// The size of an encoded balance.
+const BALANCE_SIZE: u32 = 8;
+
+/// The number of entries [StableBalancesStore::iter_balances] loads at once.
+const ITER_BATCH_SIZE: usize = 1_000;
+
+thread_local! {
+    /// Encoded account identifiers mapped to little-endian encoded e8s.
+    static BALANCES: RefCell<StableBTreeMap<VMem, Vec<u8>, Vec<u8>>> =
+        RefCell::new(StableBTreeMap::init(
+            get_memory(BALANCES_MEMORY_ID),
+            MAX_ACCOUNT_SIZE,
+            BALANCE_SIZE,
+        ));
+}
+
+/// An account identifier that can be used as a key in stable memory.
+pub trait StorableAccount: Sized {
+    /// Encodes the account as at most [MAX_ACCOUNT_SIZE] bytes.  The encoding
+    /// must be injective on equivalence classes of accounts: equal accounts
+    /// have the same encoding.
+    fn to_storable_bytes(&self) -> Vec<u8>;
+
+    /// Decodes an account previously encoded with [to_storable_bytes].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bytes do not represent a valid account.
+    fn from_storable_bytes(bytes: &[u8]) -> Self;
+}
+
+fn encode_balance(tokens: Tokens) -> Vec<u8> {
+    tokens.get_e8s().to_le_bytes().to_vec()
+}
+
+fn decode_balance(bytes: &[u8]) -> Tokens {
+    let mut buf = [0u8; BALANCE_SIZE as usize];
+    buf.copy_from_slice(bytes);
+    Tokens::from_e8s(u64::from_le_bytes(buf))
+}
+
+/// A handle to the balances B-tree in stable memory.
+///
+/// All handles refer to the same B-tree, so there must be at most one ledger
+/// per canister.  The handle itself is zero-sized and carries no state, which
+/// makes it trivial to serialize: all the data is already in stable memory.
+pub struct StableBalancesStore<AccountId> {
+    _marker: PhantomData<AccountId>,
+}
+
+impl<AccountId> Default for StableBalancesStore<AccountId> {
+    fn default() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<AccountId> fmt::Debug for StableBalancesStore<AccountId> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let num_accounts = BALANCES.with(|b| b.borrow().len());
+        f.debug_struct("StableBalancesStore")
+            .field("num_accounts", &num_accounts)
+            .finish()
+    }
+}
+
+impl<AccountId: StorableAccount> BalancesStore<AccountId> for StableBalancesStore<AccountId> {
+    fn get_balance(&self, k: &AccountId) -> Option<Tokens> {
+        BALANCES.with(|b| {
+            b.borrow()
+                .get(&k.to_storable_bytes())
+                .map(|v| decode_balance(&v))
+        })
+    }
+
+    fn update<F, E>(&mut self, k: AccountId, mut f: F) -> Result<Tokens, E>
+    where
+        F: FnMut(Option<&Tokens>) -> Result<Tokens, E>,
+    {
+        let key = k.to_storable_bytes();
+        BALANCES.with(|b| {
+            let mut balances = b.borrow_mut();
+            let old_v = balances.get(&key).map(|v| decode_balance(&v));
+            let new_v = f(old_v.as_ref())?;
+            if new_v != Tokens::ZERO {
+                balances
+                    .insert(key, encode_balance(new_v))
+                    .expect("failed to insert a balance into stable memory");
+            } else if old_v.is_some() {
+                balances.remove(&key);
+            }
+            Ok(new_v)
+        })
+    }
+}
+
+impl<AccountId: StorableAccount> InspectableBalancesStore<AccountId>
+    for StableBalancesStore<AccountId>
+{
+    fn num_accounts(&self) -> usize {
+        BALANCES.with(|b| b.borrow().len() as usize)
+    }
+
+    fn iter_balances(&self) -> Box<dyn Iterator<Item = (AccountId, Tokens)> + '_> {
+        Box::new(StableBalancesIter {
+            next_key: None,
+            batch: Vec::new().into_iter(),
+            done: false,
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// Iterates over the balances B-tree in batches so that the iterator does not
+/// hold a borrow of the tree between calls to `next`.
+struct StableBalancesIter<AccountId> {
+    /// The first key of the next batch.
+    next_key: Option<Vec<u8>>,
+    batch: std::vec::IntoIter<(Vec<u8>, Vec<u8>)>,
+    done: bool,
+    _marker: PhantomData<AccountId>,
+}
+
+impl<AccountId: StorableAccount> Iterator for StableBalancesIter<AccountId> {
+    type Item = (AccountId, Tokens);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((k, v)) = self.batch.next() {
+                return Some((AccountId::from_storable_bytes(&k), decode_balance(&v)));
+            }
+            if self.done {
+                return None;
+            }
+            let mut entries: Vec<_> = BALANCES.with(|b| {
+                b.borrow()
+                    .range(vec![], self.next_key.take())
+                    .take(ITER_BATCH_SIZE + 1)
+                    .collect()
+            });
+            if entries.len() > ITER_BATCH_SIZE {
+                self.next_key = entries.pop().map(|(k, _)| k);
+            } else {
+                self.done = true;
+            }
+            self.batch = entries.into_iter();
+        }
+    }
+}
+
+/// The balances are already in stable memory, so the store serializes to a
+/// unit value.
+impl<AccountId> Serialize for StableBalancesStore<AccountId> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_unit()
+    }
+}
+
+/// Accepts either the unit value written by [Serialize] or a map from accounts
+/// to balances.  The latter is the format of heap-based stores, which allows
+/// ledgers to migrate their balances to stable memory on upgrade.
+impl<'de, AccountId> Deserialize<'de> for StableBalancesStore<AccountId>
+where
+    AccountId: StorableAccount + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct StoreVisitor<AccountId>(PhantomData<AccountId>);
+
+        impl<'de, AccountId> de::Visitor<'de> for StoreVisitor<AccountId>
+        where
+            AccountId: StorableAccount + Deserialize<'de>,
+        {
+            type Value = StableBalancesStore<AccountId>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a unit value or a map from accounts to balances")
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+                Ok(StableBalancesStore::default())
+            }
+
+            fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+                Ok(StableBalancesStore::default())
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut store = StableBalancesStore::default();
+                while let Some((account, balance)) = map.next_entry::<AccountId, Tokens>()? {
+                    store
+                        .update(account, |_| Ok::<_, Infallible>(balance))
+                        .unwrap();
+                }
+                Ok(store)
+            }
+        }
+
+        deserializer.deserialize_any(StoreVisitor(PhantomData))
+    }
+}
@@ -8,7 +8,7 @@ use std::marker::PhantomData;
 
 pub trait BalancesStore<AccountId> {
     /// Returns the balance on the specified account.
-    fn get_balance(&self, k: &AccountId) -> Option<&Tokens>;
+    fn get_balance(&self, k: &AccountId) -> Option<Tokens>;
 
     /// Update balance for an account using function f.
     /// Its arg is previous balance or None if not found and
@@ -18,9 +18,19 @@ pub trait BalancesStore<AccountId> {
         F: FnMut(Option<&Tokens>) -> Result<Tokens, E>;
 }
 
+/// A balances store that can enumerate the accounts it holds.
+pub trait InspectableBalancesStore<AccountId>: BalancesStore<AccountId> {
+    /// Returns the number of accounts with a non-zero balance.
+    fn num_accounts(&self) -> usize;
+
+    /// Returns an iterator over all accounts with a non-zero balance in no
+    /// particular order.
+    fn iter_balances(&self) -> Box<dyn Iterator<Item = (AccountId, Tokens)> + '_>;
+}
+
 impl<AccountId: std::hash::Hash + Eq> BalancesStore<AccountId> for HashMap<AccountId, Tokens> {
-    fn get_balance(&self, k: &AccountId) -> Option<&Tokens> {
-        self.get(k)
+    fn get_balance(&self, k: &AccountId) -> Option<Tokens> {
+        self.get(k).cloned()
     }
 
     fn update<F, E>(&mut self, k: AccountId, mut f: F) -> Result<Tokens, E>
@@ -48,6 +58,22 @@ impl<AccountId: std::hash::Hash + Eq> BalancesStore<AccountId> for HashMap<Accou
     }
 }
 
+impl<AccountId> InspectableBalancesStore<AccountId> for HashMap<AccountId, Tokens>
+where
+    AccountId: std::hash::Hash + Eq + Clone,
+{
+    fn num_accounts(&self) -> usize {
+        self.len()
+    }
+
+    fn iter_balances(&self) -> Box<dyn Iterator<Item = (AccountId, Tokens)> + '_> {
+        Box::new(
+            self.iter()
+                .map(|(account, balance)| (account.clone(), *balance)),
+        )
+    }
+}
+
 /// An error returned by `Balances` if the debit operation fails.
 #[derive(Debug)]
 pub enum BalanceError {
@@ -91,12 +117,15 @@ where
         }
     }
 
+    /// Moves `amount` tokens from `from` to `to`.  The `fee` goes to the
+    /// `fee_collector` account if specified and is burned otherwise.
     pub fn transfer(
         &mut self,
         from: &AccountId,
         to: &AccountId,
         amount: Tokens,
         fee: Tokens,
+        fee_collector: Option<&AccountId>,
     ) -> Result<(), BalanceError> {
         let debit_amount = (amount + fee).map_err(|_| {
             // No account can hold more than u64::MAX.
@@ -105,10 +134,13 @@ where
         })?;
         self.debit(from, debit_amount)?;
         self.credit(to, amount);
-        // NB. integer overflow is not possible here unless there is a
-        // severe bug in the system: total amount of tokens in the
-        // circulation cannot exceed u64::MAX.
-        self.token_pool += fee;
+        match fee_collector {
+            Some(fee_collector) => self.credit(fee_collector, fee),
+            // NB. integer overflow is not possible here unless there is a
+            // severe bug in the system: total amount of tokens in the
+            // circulation cannot exceed u64::MAX.
+            None => self.token_pool += fee,
+        }
         Ok(())
     }
 
@@ -163,10 +195,7 @@ where
     }
 
     pub fn account_balance(&self, account: &AccountId) -> Tokens {
-        self.store
-            .get_balance(account)
-            .cloned()
-            .unwrap_or(Tokens::ZERO)
+        self.store.get_balance(account).unwrap_or(Tokens::ZERO)
     }
 
     /// Returns the total quantity of Tokens that are "in existence" -- that
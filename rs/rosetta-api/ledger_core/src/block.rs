@@ -169,22 +169,45 @@ impl EncodedBlock {
     }
 }
 
+/// The account that receives the transfer fees instead of burning them.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FeeCollector<AccountId> {
+    pub fee_collector: AccountId,
+    /// The index of the first block that mentions the fee collector.  Later
+    /// blocks refer to the fee collector by this index to save space.  `None`
+    /// until the ledger creates such a block.
+    pub block_index: Option<BlockIndex>,
+}
+
+impl<AccountId> From<AccountId> for FeeCollector<AccountId> {
+    fn from(fee_collector: AccountId) -> Self {
+        Self {
+            fee_collector,
+            block_index: None,
+        }
+    }
+}
+
 pub trait BlockType: Sized {
     type Transaction;
+    type AccountId;
 
-    /// Constructs a new block containing the given transaction.
+    /// Constructs a new block containing the given transaction.  The
+    /// `fee_collector` is the account that received the transaction fee, if
+    /// any.
     ///
     /// Law:
     ///
     /// ```text
-    /// forall PH, TX, TS:
-    ///     from_transaction(PH, TX, TS).parent_hash() = PH
-    ///   ∧ from_transaction(PH, TX, TS).timestamp() = TS
+    /// forall PH, TX, TS, FC:
+    ///     from_transaction(PH, TX, TS, FC).parent_hash() = PH
+    ///   ∧ from_transaction(PH, TX, TS, FC).timestamp() = TS
     /// ```
     fn from_transaction(
         parent_hash: Option<HashOf<EncodedBlock>>,
         tx: Self::Transaction,
         block_timestamp: TimeStamp,
+        fee_collector: Option<FeeCollector<Self::AccountId>>,
     ) -> Self;
 
     /// Encodes this block into a binary representation.
@@ -53,6 +53,17 @@ pub fn block_to_transaction(
         "memo".to_string(),
         Value::Number(Number::from(transaction.memo.0)),
     );
+    if let Some(icrc1_memo) = transaction.icrc1_memo {
+        metadata.insert(
+            "icrc1_memo".to_string(),
+            Value::Array(
+                icrc1_memo
+                    .iter()
+                    .map(|b| Value::Number(Number::from(*b)))
+                    .collect(),
+            ),
+        );
+    }
     metadata.insert(
         "block_height".to_string(),
         Value::Number(Number::from(hb.index)),
@@ -673,6 +673,9 @@ impl<'a> Canister<'a> {
                         compute_allocation: None,
                         memory_allocation: None,
                         freezing_threshold: None,
+                        ingress_priority_fee: None,
+                        wasm_memory_limit: None,
+                        reserved_cycles_limit: None,
                     },
                 },),
             )
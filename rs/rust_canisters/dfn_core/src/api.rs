@@ -80,6 +80,8 @@ pub mod ic0 {
         pub fn stable64_grow(additional_pages: u64) -> i64;
         pub fn stable64_read(dst: u64, offset: u64, size: u64);
         pub fn stable64_write(offset: u64, src: u64, size: u64);
+        pub fn stable64_checkpoint(offset: u64, src: u64, size: u64) -> i64;
+        pub fn stable64_restore(dst: u64, offset: u64, size: u64) -> i64;
         pub fn time() -> u64;
         pub fn performance_counter(counter_type: u32) -> u64;
         pub fn canister_cycle_balance() -> u64;
@@ -245,6 +247,14 @@ pub mod ic0 {
         wrong_arch("stable64_write")
     }
 
+    pub unsafe fn stable64_checkpoint(_offset: u64, _src: u64, _size: u64) -> i64 {
+        wrong_arch("stable64_checkpoint")
+    }
+
+    pub unsafe fn stable64_restore(_dst: u64, _offset: u64, _size: u64) -> i64 {
+        wrong_arch("stable64_restore")
+    }
+
     pub unsafe fn time() -> u64 {
         std::time::SystemTime::now()
             .duration_since(std::time::SystemTime::UNIX_EPOCH)
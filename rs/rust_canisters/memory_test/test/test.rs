@@ -19,6 +19,9 @@ fn test_memory_test_canisters() {
                 None,
                 Some(8 * 1024 * 1024 * 1024), // 8GiB
                 None,
+                None,
+                None,
+                None,
             )),
             Cycles::from(u128::MAX),
         )
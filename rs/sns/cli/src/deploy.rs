@@ -8,6 +8,7 @@ use candid::Decode;
 use candid::Encode;
 use candid::IDLArgs;
 use ic_base_types::{CanisterId, PrincipalId};
+use ic_icrc1_ledger::LedgerArgument;
 use ic_nns_constants::ROOT_CANISTER_ID as NNS_ROOT_CANISTER_ID;
 use ic_nns_constants::SNS_WASM_CANISTER_ID;
 use ic_sns_init::pb::v1::SnsInitPayload;
@@ -575,7 +576,9 @@ impl DirectSnsDeployerForTests {
 
     /// Install and initialize Ledger
     fn install_ledger(&self) {
-        let init_args = hex_encode_candid(&self.sns_canister_payloads.ledger);
+        let init_args = hex_encode_candid(LedgerArgument::Init(
+            self.sns_canister_payloads.ledger.clone(),
+        ));
         self.install_canister("sns_ledger", &init_args);
     }
 
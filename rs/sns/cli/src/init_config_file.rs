@@ -480,6 +480,11 @@ impl TryFrom<SnsCliInitConfig> for SnsInitPayload {
             wait_for_quiet_deadline_increase_seconds: sns_cli_init_config
                 .sns_governance
                 .wait_for_quiet_deadline_increase_seconds,
+            // Participation in the swap cannot be restricted through the init
+            // config file.
+            restricted_countries: vec![],
+            confirmation_text_sha256: None,
+            allowed_participant_principal_ids: vec![],
         })
     }
 }
@@ -1170,6 +1175,9 @@ wait_for_quiet_deadline_increase_seconds: 1000
             initial_voting_period_seconds,
             wait_for_quiet_deadline_increase_seconds,
             initial_token_distribution,
+            restricted_countries,
+            confirmation_text_sha256,
+            allowed_participant_principal_ids,
         } = sns_init_payload;
 
         assert_eq!(
@@ -1269,6 +1277,9 @@ wait_for_quiet_deadline_increase_seconds: 1000
                 .wait_for_quiet_deadline_increase_seconds,
             wait_for_quiet_deadline_increase_seconds
         );
+        assert!(restricted_countries.is_empty());
+        assert_eq!(confirmation_text_sha256, None);
+        assert!(allowed_participant_principal_ids.is_empty());
 
         // Read the test.png file into memory
         let logo_path = sns_cli_init_config.sns_governance.logo.unwrap();
@@ -43,7 +43,8 @@ use ic_sns_governance::{
         GetRunningSnsVersionRequest, GetRunningSnsVersionResponse,
         GetSnsInitializationParametersRequest, GetSnsInitializationParametersResponse,
         Governance as GovernanceProto, ListNervousSystemFunctionsResponse, ListNeurons,
-        ListNeuronsResponse, ListProposals, ListProposalsResponse, ManageNeuron,
+        ListNeuronsResponse, ListPaymentStreamsRequest, ListPaymentStreamsResponse, ListProposals,
+        ListProposalsResponse, ListSaleRoundsRequest, ListSaleRoundsResponse, ManageNeuron,
         ManageNeuronResponse, NervousSystemParameters, RewardEvent, SetMode, SetModeResponse,
     },
     types::{Environment, HeapGrowthPotential},
@@ -430,6 +431,34 @@ fn list_proposals_(list_proposals: ListProposals) -> ListProposalsResponse {
     governance().list_proposals(&list_proposals)
 }
 
+/// Returns all payment streams created by CreatePaymentStream proposals,
+/// including the cancelled and fully paid ones.
+#[export_name = "canister_query list_payment_streams"]
+fn list_payment_streams() {
+    println!("{}list_payment_streams", log_prefix());
+    over(candid_one, list_payment_streams_)
+}
+
+/// Internal method for calling list_payment_streams.
+#[candid_method(query, rename = "list_payment_streams")]
+fn list_payment_streams_(request: ListPaymentStreamsRequest) -> ListPaymentStreamsResponse {
+    governance().list_payment_streams(&request)
+}
+
+/// Returns all follow-up sale rounds opened by OpenSaleRound proposals,
+/// including past ones.
+#[export_name = "canister_query list_sale_rounds"]
+fn list_sale_rounds() {
+    println!("{}list_sale_rounds", log_prefix());
+    over(candid_one, list_sale_rounds_)
+}
+
+/// Internal method for calling list_sale_rounds.
+#[candid_method(query, rename = "list_sale_rounds")]
+fn list_sale_rounds_(request: ListSaleRoundsRequest) -> ListSaleRoundsResponse {
+    governance().list_sale_rounds(&request)
+}
+
 /// Returns the current list of available NervousSystemFunctions.
 #[export_name = "canister_query list_nervous_system_functions"]
 fn list_nervous_system_functions() {
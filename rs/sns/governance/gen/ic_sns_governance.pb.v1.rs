@@ -325,6 +325,10 @@ pub struct UpgradeSnsControlledCanister {
     /// The new wasm module that the canister is upgraded to.
     #[prost(bytes = "vec", tag = "2")]
     pub new_canister_wasm: ::prost::alloc::vec::Vec<u8>,
+    /// The argument passed to the canister's post_upgrade hook.  If not set, the
+    /// canister receives an empty argument.
+    #[prost(bytes = "vec", optional, tag = "3")]
+    pub canister_upgrade_arg: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
 }
 /// A proposal to transfer SNS treasury funds to (optionally a Subaccount of) the
 /// target principal.
@@ -377,6 +381,287 @@ pub mod transfer_sns_treasury_funds {
         }
     }
 }
+/// A proposal to create a payment stream that periodically transfers SNS
+/// treasury funds to (optionally a Subaccount of) the target principal.
+///
+/// Payments are made by the governance canister's heartbeat. Once a period
+/// has elapsed, `amount_per_period_e8s` becomes due. No payment is made
+/// before `cliff_periods` periods have elapsed; at that point, all the
+/// periods accrued so far are paid at once.
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct CreatePaymentStream {
+    /// Whether to make the payments from the NNS ledger (in ICP) or from the
+    /// SNS ledger (in SNS tokens).
+    #[prost(enumeration = "transfer_sns_treasury_funds::TransferFrom", tag = "1")]
+    pub from_treasury: i32,
+    /// The principal to transfer the funds to.
+    #[prost(message, optional, tag = "2")]
+    pub to_principal: ::core::option::Option<::ic_base_types::PrincipalId>,
+    /// An (optional) Subaccount of the principal to transfer the funds to.
+    #[prost(message, optional, tag = "3")]
+    pub to_subaccount: ::core::option::Option<Subaccount>,
+    /// The amount paid for each period, in e8s.
+    #[prost(uint64, tag = "4")]
+    pub amount_per_period_e8s: u64,
+    /// The length of a period, in seconds.
+    #[prost(uint64, tag = "5")]
+    pub period_seconds: u64,
+    /// The total number of periods that are paid by the stream.
+    #[prost(uint64, tag = "6")]
+    pub num_periods: u64,
+    /// The number of periods that must elapse before the first payment.
+    #[prost(uint64, tag = "7")]
+    pub cliff_periods: u64,
+    /// An optional memo to use for the transfers.
+    #[prost(uint64, optional, tag = "8")]
+    pub memo: ::core::option::Option<u64>,
+}
+/// A proposal to cancel an active payment stream. Periods that have not been
+/// paid at the time the proposal is executed are never paid.
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct CancelPaymentStream {
+    /// The id of the payment stream, i.e., the id of the proposal that
+    /// created it.
+    #[prost(uint64, tag = "1")]
+    pub payment_stream_id: u64,
+}
+/// A payment stream created by a CreatePaymentStream proposal.
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct PaymentStream {
+    /// The id of the payment stream, which is the id of the proposal that
+    /// created it.
+    #[prost(uint64, tag = "1")]
+    pub id: u64,
+    /// The terms of the payment stream, as given in the proposal.
+    #[prost(message, optional, tag = "2")]
+    pub terms: ::core::option::Option<CreatePaymentStream>,
+    /// The time at which the first period started, i.e., the time when
+    /// the proposal was executed, in seconds since the Unix epoch.
+    #[prost(uint64, tag = "3")]
+    pub start_timestamp_seconds: u64,
+    /// The number of periods that have been paid so far.
+    #[prost(uint64, tag = "4")]
+    pub paid_periods: u64,
+    /// If set, the time at which the stream was cancelled, in seconds since
+    /// the Unix epoch. No further payments are made by a cancelled stream.
+    #[prost(uint64, optional, tag = "5")]
+    pub cancellation_timestamp_seconds: ::core::option::Option<u64>,
+    /// The error of the last failed payment, if any. It is cleared once a
+    /// payment succeeds.
+    #[prost(string, optional, tag = "6")]
+    pub last_payment_error: ::core::option::Option<::prost::alloc::string::String>,
+    /// The time of the last failed payment, in seconds since the Unix epoch.
+    /// Failed payments are retried once PAYMENT_STREAM_RETRY_INTERVAL_SECONDS
+    /// have passed since then.
+    #[prost(uint64, tag = "7")]
+    pub last_payment_failure_timestamp_seconds: u64,
+    /// The payment in flight, if any. It is retried until the ledger either
+    /// executes or definitely rejects it; in the latter case, its periods are
+    /// removed from paid_periods again.
+    #[prost(message, optional, tag = "8")]
+    pub pending_payment: ::core::option::Option<payment_stream::PendingPayment>,
+}
+/// Nested message and enum types in `PaymentStream`.
+pub mod payment_stream {
+    /// A payment whose transfer has been attempted, but whose outcome is not
+    /// known yet. The periods it covers are already counted in paid_periods.
+    #[derive(
+        candid::CandidType,
+        candid::Deserialize,
+        comparable::Comparable,
+        Clone,
+        PartialEq,
+        ::prost::Message,
+    )]
+    pub struct PendingPayment {
+        /// The number of periods paid by this payment.
+        #[prost(uint64, tag = "1")]
+        pub num_periods: u64,
+        /// The created_at_time of the transfer, in nanoseconds since the Unix
+        /// epoch. Retries reuse it, so that the ledger deduplicates them.
+        #[prost(uint64, tag = "2")]
+        pub created_at_time_nanos: u64,
+        /// The time of the last attempt to make the transfer, in seconds since
+        /// the Unix epoch.
+        #[prost(uint64, tag = "3")]
+        pub last_attempt_timestamp_seconds: u64,
+        /// The fee of the transfer, in e8s. Retries reuse it, as the ledger does
+        /// not deduplicate transfers with different fees.
+        #[prost(uint64, tag = "4")]
+        pub fee_e8s: u64,
+        /// The subaccount of the treasury that the transfer is made from, if any.
+        #[prost(message, optional, tag = "5")]
+        pub from_subaccount: ::core::option::Option<super::Subaccount>,
+    }
+}
+/// A proposal to mint SNS tokens to (optionally a Subaccount of) the
+/// target principal. The tokens are minted by a transfer from the minting
+/// account of the SNS ledger, which is the governance canister's main account.
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct MintSnsTokens {
+    /// The amount to mint, in e8s. This is at most
+    /// NervousSystemParameters.max_mint_sns_tokens_e8s_per_window.
+    #[prost(uint64, tag = "1")]
+    pub amount_e8s: u64,
+    /// The principal to mint the tokens to.
+    #[prost(message, optional, tag = "2")]
+    pub to_principal: ::core::option::Option<::ic_base_types::PrincipalId>,
+    /// An (optional) Subaccount of the principal to mint the tokens to.
+    #[prost(message, optional, tag = "3")]
+    pub to_subaccount: ::core::option::Option<Subaccount>,
+    /// An optional memo to use for the minting transfer.
+    #[prost(uint64, optional, tag = "4")]
+    pub memo: ::core::option::Option<u64>,
+}
+/// The tokens minted by a MintSnsTokens proposal. Recent mints are kept to cap
+/// the amount minted within a rolling window.
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct MintedSnsTokens {
+    /// The amount minted, in e8s.
+    #[prost(uint64, tag = "1")]
+    pub amount_e8s: u64,
+    /// The time of the mint, in seconds since the Unix epoch.
+    #[prost(uint64, tag = "2")]
+    pub timestamp_seconds: u64,
+}
+/// A proposal to change the parameters of the SNS ledger. The ledger is
+/// upgraded to its currently deployed Wasm with the new parameters as upgrade
+/// arguments. Fields with None values will remain unchanged.
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct ManageLedgerParameters {
+    /// The new transfer fee of the ledger, in e8s. On success, this also becomes
+    /// NervousSystemParameters.transaction_fee_e8s.
+    #[prost(uint64, optional, tag = "1")]
+    pub transfer_fee: ::core::option::Option<u64>,
+    /// The new name of the token.
+    #[prost(string, optional, tag = "2")]
+    pub token_name: ::core::option::Option<::prost::alloc::string::String>,
+    /// The new symbol of the token.
+    #[prost(string, optional, tag = "3")]
+    pub token_symbol: ::core::option::Option<::prost::alloc::string::String>,
+    /// The new logo of the token, as a base64-encoded image. It is stored in the
+    /// ledger metadata under the "icrc1:logo" key.
+    #[prost(string, optional, tag = "4")]
+    pub token_logo: ::core::option::Option<::prost::alloc::string::String>,
+}
+/// A proposal to open a follow-up sale round, in which SNS tokens from the SNS
+/// token treasury are sold for ICP. Executing the proposal asks SNS root to
+/// create a new swap canister, transfers `sns_token_e8s` SNS tokens from the
+/// treasury to it, and opens it. The fields have the same meaning as the
+/// corresponding fields of the swap's Params.
+///
+/// A round cannot be opened while another one is still in progress, i.e.,
+/// before the due date of the last round has passed.
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct OpenSaleRound {
+    /// The minimum number of participants for the round to succeed.
+    #[prost(uint32, tag = "1")]
+    pub min_participants: u32,
+    /// The minimum amount of ICP that must be raised for the round to succeed.
+    #[prost(uint64, tag = "2")]
+    pub min_icp_e8s: u64,
+    /// The amount of ICP at which the round ends immediately.
+    #[prost(uint64, tag = "3")]
+    pub max_icp_e8s: u64,
+    /// The minimum amount of ICP that each participant must contribute.
+    #[prost(uint64, tag = "4")]
+    pub min_participant_icp_e8s: u64,
+    /// The maximum amount of ICP that each participant can contribute.
+    #[prost(uint64, tag = "5")]
+    pub max_participant_icp_e8s: u64,
+    /// The time at which the round ends, in seconds since the Unix epoch.
+    #[prost(uint64, tag = "6")]
+    pub swap_due_timestamp_seconds: u64,
+    /// The amount of SNS tokens offered, taken from the SNS token treasury.
+    #[prost(uint64, tag = "7")]
+    pub sns_token_e8s: u64,
+    /// The number of neurons that each participant receives.
+    #[prost(uint64, tag = "8")]
+    pub neuron_basket_count: u64,
+    /// The interval by which the dissolve delays of the neurons in a basket
+    /// increase.
+    #[prost(uint64, tag = "9")]
+    pub neuron_basket_dissolve_delay_interval_seconds: u64,
+}
+/// A follow-up sale round opened by an OpenSaleRound proposal.
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct SaleRound {
+    /// The id of the proposal that opened the round.
+    #[prost(uint64, tag = "1")]
+    pub proposal_id: u64,
+    /// The swap canister of the round. Not set while SNS root is creating it.
+    #[prost(message, optional, tag = "2")]
+    pub swap_canister_id: ::core::option::Option<::ic_base_types::PrincipalId>,
+    /// The parameters of the round, as given in the proposal.
+    #[prost(message, optional, tag = "3")]
+    pub parameters: ::core::option::Option<OpenSaleRound>,
+    /// The time at which the proposal was executed, in seconds since the Unix
+    /// epoch.
+    #[prost(uint64, tag = "4")]
+    pub creation_timestamp_seconds: u64,
+    /// Set if the swap canister of the round could not be opened, and the SNS
+    /// tokens that were transferred to it have not been returned to the treasury
+    /// yet, to the time of the latest attempt to get them back. Such a round is
+    /// not in progress, and is forgotten once the tokens are back.
+    #[prost(uint64, tag = "5")]
+    pub abort_failure_timestamp_seconds: u64,
+}
 /// A proposal function to change the values of SNS metadata.
 /// Fields with None values will remain unchanged.
 #[derive(
@@ -439,7 +724,10 @@ pub struct Proposal {
     ///
     /// See `impl From<&Action> for u64` in src/types.rs for the implementation
     /// of this mapping.
-    #[prost(oneof = "proposal::Action", tags = "4, 5, 6, 7, 8, 9, 10, 11, 12, 13")]
+    #[prost(
+        oneof = "proposal::Action",
+        tags = "4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18"
+    )]
     pub action: ::core::option::Option<proposal::Action>,
 }
 /// Nested message and enum types in `Proposal`.
@@ -516,6 +804,26 @@ pub mod proposal {
         /// Id = 9.
         #[prost(message, tag = "13")]
         TransferSnsTreasuryFunds(super::TransferSnsTreasuryFunds),
+        /// Create a stream of periodic payments from an SNS treasury.
+        /// Id = 10.
+        #[prost(message, tag = "14")]
+        CreatePaymentStream(super::CreatePaymentStream),
+        /// Cancel an active payment stream.
+        /// Id = 11.
+        #[prost(message, tag = "15")]
+        CancelPaymentStream(super::CancelPaymentStream),
+        /// Mint SNS tokens to an account.
+        /// Id = 12.
+        #[prost(message, tag = "16")]
+        MintSnsTokens(super::MintSnsTokens),
+        /// Change the parameters of the SNS ledger.
+        /// Id = 13.
+        #[prost(message, tag = "17")]
+        ManageLedgerParameters(super::ManageLedgerParameters),
+        /// Open a follow-up sale round of SNS tokens from the SNS token treasury.
+        /// Id = 14.
+        #[prost(message, tag = "18")]
+        OpenSaleRound(super::OpenSaleRound),
     }
 }
 #[derive(candid::CandidType, candid::Deserialize, comparable::Comparable)]
@@ -693,6 +1001,11 @@ pub struct ProposalData {
     /// Id 7 - UpgradeSnsToNextVersion proposals.
     /// Id 8 - ManageSnsMetadata proposals.
     /// Id 9 - TransferSnsTreasuryFunds proposals.
+    /// Id 10 - CreatePaymentStream proposals.
+    /// Id 11 - CancelPaymentStream proposals.
+    /// Id 12 - MintSnsTokens proposals.
+    /// Id 13 - ManageLedgerParameters proposals.
+    /// Id 14 - OpenSaleRound proposals.
     #[prost(uint64, tag = "1")]
     pub action: u64,
     /// This is stored here temporarily. It is also stored on the map
@@ -928,6 +1241,14 @@ pub struct NervousSystemParameters {
     /// To achieve functionality equivalent to NNS, this should be set to 25.
     #[prost(uint64, optional, tag = "21")]
     pub max_age_bonus_percentage: ::core::option::Option<u64>,
+    /// The maximum amount of SNS tokens, in e8s, that MintSnsTokens proposals
+    /// can mint in total within any rolling window of
+    /// MINT_SNS_TOKENS_WINDOW_SECONDS (7 days). A single proposal can thus
+    /// mint at most this amount, minus what other proposals minted within the
+    /// window. When this is not set or set to zero, no tokens can be minted by
+    /// proposal.
+    #[prost(uint64, optional, tag = "22")]
+    pub max_mint_sns_tokens_e8s_per_window: ::core::option::Option<u64>,
 }
 #[derive(
     candid::CandidType,
@@ -1130,6 +1451,25 @@ pub struct Governance {
     /// Version SNS is in process of upgrading to.
     #[prost(message, optional, tag = "24")]
     pub pending_version: ::core::option::Option<governance::UpgradeInProgress>,
+    /// The payment streams created by CreatePaymentStream proposals, as a map
+    /// from payment stream ids to payment streams. Cancelled and fully paid
+    /// streams are kept for record keeping.
+    #[prost(btree_map = "uint64, message", tag = "25")]
+    pub payment_streams: ::prost::alloc::collections::BTreeMap<u64, PaymentStream>,
+    /// The follow-up sale rounds opened by OpenSaleRound proposals, as a map
+    /// from the ids of those proposals to sale rounds. Past rounds are kept for
+    /// record keeping.
+    #[prost(btree_map = "uint64, message", tag = "26")]
+    pub sale_rounds: ::prost::alloc::collections::BTreeMap<u64, SaleRound>,
+    /// The mints made by MintSnsTokens proposals within the last
+    /// MINT_SNS_TOKENS_WINDOW_SECONDS, as a map from the ids of those proposals
+    /// to the minted tokens. Older mints are removed by the next mint.
+    #[prost(btree_map = "uint64, message", tag = "27")]
+    pub recent_sns_token_mints: ::prost::alloc::collections::BTreeMap<u64, MintedSnsTokens>,
+    /// The transfer fee change that NervousSystemParameters.transaction_fee_e8s
+    /// is waiting to be synced with, if any.
+    #[prost(message, optional, tag = "28")]
+    pub pending_ledger_fee_sync: ::core::option::Option<governance::PendingLedgerFeeSync>,
 }
 /// Nested message and enum types in `Governance`.
 pub mod governance {
@@ -1358,6 +1698,31 @@ pub mod governance {
         #[prost(uint64, tag = "4")]
         pub proposal_id: u64,
     }
+    /// A change of the SNS ledger's transfer fee by a ManageLedgerParameters
+    /// proposal. The ledger is upgraded asynchronously by root, so the fee is
+    /// read back from the ledger until it reports the new fee.
+    #[derive(
+        candid::CandidType,
+        candid::Deserialize,
+        comparable::Comparable,
+        Clone,
+        PartialEq,
+        ::prost::Message,
+    )]
+    pub struct PendingLedgerFeeSync {
+        /// The proposal that changed the fee.
+        #[prost(uint64, tag = "1")]
+        pub proposal_id: u64,
+        /// The new transfer fee, in e8s.
+        #[prost(uint64, tag = "2")]
+        pub transfer_fee_e8s: u64,
+        /// Seconds since UNIX epoch after which the fee is no longer read back.
+        #[prost(uint64, tag = "3")]
+        pub mark_failed_at_seconds: u64,
+        /// Seconds since UNIX epoch of the last time the fee was read from the ledger.
+        #[prost(uint64, tag = "4")]
+        pub last_check_timestamp_seconds: u64,
+    }
     #[derive(
         strum_macros::EnumIter,
         Clone,
@@ -1464,6 +1829,55 @@ pub struct GetRunningSnsVersionResponse {
     #[prost(message, optional, tag = "2")]
     pub pending_version: ::core::option::Option<governance::UpgradeInProgress>,
 }
+/// Request message for 'list_payment_streams'.
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct ListPaymentStreamsRequest {}
+/// Response message for 'list_payment_streams'.
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct ListPaymentStreamsResponse {
+    /// All payment streams, ordered by id.
+    #[prost(message, repeated, tag = "1")]
+    pub payment_streams: ::prost::alloc::vec::Vec<PaymentStream>,
+}
+/// Request message for 'list_sale_rounds'.
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct ListSaleRoundsRequest {}
+/// Response message for 'list_sale_rounds'.
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct ListSaleRoundsResponse {
+    /// All follow-up sale rounds, ordered by the id of the proposal that
+    /// opened them.
+    #[prost(message, repeated, tag = "1")]
+    pub sale_rounds: ::prost::alloc::vec::Vec<SaleRound>,
+}
 /// Empty message to use in oneof fields that represent empty
 /// enums.
 #[derive(
@@ -85,6 +85,7 @@ pub async fn install_code(
         compute_allocation: None,
         memory_allocation: Some(candid::Nat::from(MEMORY_ALLOCATION_BYTES)),
         query_allocation: None,
+        wasm_module_hash: None,
     };
 
     env.call_canister(
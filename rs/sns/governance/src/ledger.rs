@@ -8,6 +8,7 @@ use ic_icrc1::{endpoints::TransferArg, Account, Memo, Subaccount};
 use ic_icrc1_client::{ICRC1Client, Runtime};
 use ic_ledger_core::{block::BlockIndex, Tokens};
 pub use ic_nervous_system_common::ledger::ICRC1Ledger;
+use ic_nervous_system_common::ledger::{icrc1_transfer_result_to_block_index, TransferFundsError};
 use ic_nervous_system_common::NervousSystemError;
 
 // A ICRC1 client runtime that uses dfn_* functionalities
@@ -85,6 +86,33 @@ impl ICRC1Ledger for LedgerCanister {
         })
     }
 
+    async fn transfer_funds_idempotently(
+        &self,
+        amount_e8s: u64,
+        fee_e8s: u64,
+        from_subaccount: Option<Subaccount>,
+        to: Account,
+        memo: u64,
+        created_at_time_nanos: u64,
+    ) -> Result<BlockIndex, TransferFundsError> {
+        let args = TransferArg {
+            from_subaccount,
+            to,
+            fee: Some(Nat::from(fee_e8s)),
+            created_at_time: Some(created_at_time_nanos),
+            amount: Nat::from(amount_e8s),
+            memo: Some(Memo::from(memo)),
+        };
+        let res = self
+            .client
+            .transfer(args)
+            .await
+            .map(|res| res.map(Nat::from))
+            // DfnRuntime reports a missing reject code as 0.
+            .map_err(|(code, msg)| (Some(code).filter(|code| *code != 0), msg));
+        icrc1_transfer_result_to_block_index(res)
+    }
+
     async fn total_supply(&self) -> Result<Tokens, NervousSystemError> {
         self.client.total_supply().await
             .map(Tokens::from_e8s)
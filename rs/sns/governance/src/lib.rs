@@ -6,6 +6,7 @@ pub mod neuron;
 pub mod pb;
 pub mod proposal;
 pub mod reward;
+pub mod sale_round;
 pub mod sns_upgrade;
 pub mod types;
 
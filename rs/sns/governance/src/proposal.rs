@@ -5,18 +5,22 @@ use crate::pb::v1::nervous_system_function::{FunctionType, GenericNervousSystemF
 use crate::pb::v1::proposal::Action;
 use crate::pb::v1::transfer_sns_treasury_funds::TransferFrom;
 use crate::pb::v1::{
-    proposal, ExecuteGenericNervousSystemFunction, Governance, ManageSnsMetadata, Motion,
-    NervousSystemFunction, NervousSystemParameters, Proposal, ProposalData, ProposalDecisionStatus,
-    ProposalRewardStatus, Tally, TransferSnsTreasuryFunds, UpgradeSnsControlledCanister,
-    UpgradeSnsToNextVersion, Vote,
+    proposal, CancelPaymentStream, CreatePaymentStream, ExecuteGenericNervousSystemFunction,
+    Governance, ManageLedgerParameters, ManageSnsMetadata, MintSnsTokens, Motion,
+    NervousSystemFunction, NervousSystemParameters, OpenSaleRound, PaymentStream, Proposal,
+    ProposalData, ProposalDecisionStatus, ProposalRewardStatus, SaleRound, Subaccount, Tally,
+    TransferSnsTreasuryFunds, UpgradeSnsControlledCanister, UpgradeSnsToNextVersion, Vote,
 };
+use crate::sale_round::sale_round_in_progress;
 use crate::sns_upgrade::{get_upgrade_params, UpgradeSnsParams};
-use crate::types::{Environment, DEFAULT_TRANSFER_FEE};
+use crate::types::{Environment, DEFAULT_TRANSFER_FEE, ONE_DAY_SECONDS};
 use crate::{validate_chars_count, validate_len, validate_required_field};
+use candid::Decode;
 use dfn_core::api::CanisterId;
 use ic_base_types::PrincipalId;
 use ic_crypto_sha::Sha256;
 use ic_icrc1::Account;
+use ic_icrc1_ledger::{LedgerArgument, UpgradeArgs};
 use icp_ledger::DEFAULT_TRANSFER_FEE as NNS_DEFAULT_TRANSFER_FEE;
 use std::collections::{BTreeMap, HashSet};
 use std::convert::TryFrom;
@@ -51,6 +55,12 @@ pub const MAX_NUMBER_OF_PROPOSALS_WITH_BALLOTS: usize = 700;
 /// The maximum number of GenericNervousSystemFunctions the system allows.
 pub const MAX_NUMBER_OF_GENERIC_NERVOUS_SYSTEM_FUNCTIONS: usize = 200_000;
 
+/// The minimum length of a payment stream's period.
+pub const PAYMENT_STREAM_PERIOD_SECONDS_MIN: u64 = ONE_DAY_SECONDS;
+
+/// The maximum number of periods of a payment stream.
+pub const PAYMENT_STREAM_NUM_PERIODS_MAX: u64 = 1_000;
+
 impl Proposal {
     /// Returns whether a proposal is allowed to be submitted when
     /// the heap growth potential is low.
@@ -162,7 +172,10 @@ pub async fn validate_and_render_action(
             validate_and_render_manage_nervous_system_parameters(manage, current_parameters)
         }
         proposal::Action::UpgradeSnsControlledCanister(upgrade) => {
-            validate_and_render_upgrade_sns_controlled_canister(upgrade)
+            validate_and_render_upgrade_sns_controlled_canister(
+                upgrade,
+                governance_proto.ledger_canister_id,
+            )
         }
         Action::UpgradeSnsToNextVersion(upgrade_sns) => {
             let current_version = governance_proto.deployed_version_or_panic();
@@ -206,6 +219,32 @@ pub async fn validate_and_render_action(
                 .unwrap_or(DEFAULT_TRANSFER_FEE.get_e8s());
             validate_and_render_transfer_sns_treasury_funds(transfer, sns_transfer_fee_e8s)
         }
+        proposal::Action::CreatePaymentStream(create) => {
+            let sns_transfer_fee_e8s = governance_proto
+                .parameters
+                .as_ref()
+                .and_then(|params| params.transaction_fee_e8s)
+                .unwrap_or(DEFAULT_TRANSFER_FEE.get_e8s());
+            validate_and_render_create_payment_stream(create, sns_transfer_fee_e8s)
+        }
+        proposal::Action::CancelPaymentStream(cancel) => {
+            validate_and_render_cancel_payment_stream(cancel, &governance_proto.payment_streams)
+        }
+        proposal::Action::MintSnsTokens(mint) => {
+            let max_mint_e8s = current_parameters
+                .max_mint_sns_tokens_e8s_per_window
+                .unwrap_or_default();
+            validate_and_render_mint_sns_tokens(mint, max_mint_e8s)
+        }
+        proposal::Action::ManageLedgerParameters(manage_ledger_parameters) => {
+            validate_and_render_manage_ledger_parameters(
+                manage_ledger_parameters,
+                current_parameters,
+            )
+        }
+        proposal::Action::OpenSaleRound(open) => {
+            validate_and_render_open_sale_round(open, &governance_proto.sale_rounds, env.now())
+        }
     }
 }
 
@@ -253,7 +292,50 @@ fn validate_and_render_transfer_sns_treasury_funds(
     sns_transfer_fee_e8s: u64,
 ) -> Result<String, String> {
     let mut defects: Vec<String> = vec![];
-    let from = match transfer.from_treasury() {
+    let from = validate_and_render_treasury(
+        transfer.from_treasury(),
+        transfer.amount_e8s,
+        sns_transfer_fee_e8s,
+        &mut defects,
+    );
+    let (to_principal, to_account) = validate_and_render_target_account(
+        transfer.to_principal,
+        &transfer.to_subaccount,
+        &mut defects,
+    );
+
+    // Generate final report.
+    if !defects.is_empty() {
+        return Err(format!(
+            "TransferSnsTreasuryFunds proposal was invalid for the following reason(s):\n{}",
+            defects.join("\n"),
+        ));
+    }
+
+    Ok(format!(
+        r"# Proposal to transfer SNS Treasury funds:
+## Source treasury: {}
+## Amount (e8s): {}
+## Target principal: {}
+## Target account: {}
+## Memo: {}",
+        from,
+        transfer.amount_e8s,
+        to_principal,
+        to_account,
+        transfer.memo.unwrap_or(0)
+    ))
+}
+
+/// Validates the treasury from which a transfer of `amount_e8s` is made, pushing
+/// any problem onto `defects`, and returns a displayable name for the treasury.
+fn validate_and_render_treasury(
+    from_treasury: TransferFrom,
+    amount_e8s: u64,
+    sns_transfer_fee_e8s: u64,
+    defects: &mut Vec<String>,
+) -> &'static str {
+    let from = match from_treasury {
         TransferFrom::IcpTreasury => "ICP Treasury (NNS Ledger)",
         TransferFrom::SnsTokenTreasury => "SNS Token Treasury (SNS Ledger)",
         TransferFrom::Unspecified => {
@@ -265,20 +347,31 @@ fn validate_and_render_transfer_sns_treasury_funds(
         }
     };
 
-    let minimum_transaction = match transfer.from_treasury() {
+    let minimum_transaction = match from_treasury {
         TransferFrom::IcpTreasury => NNS_DEFAULT_TRANSFER_FEE.get_e8s(),
         TransferFrom::SnsTokenTreasury => sns_transfer_fee_e8s,
         TransferFrom::Unspecified => 0,
     };
 
-    if transfer.amount_e8s < minimum_transaction {
+    if amount_e8s < minimum_transaction {
         defects.push(format!(
             "For transactions from {}, the fee and minimum transaction is {} e8s",
             from, minimum_transaction
         ))
     }
 
-    let to_principal = if let Some(to_principal) = transfer.to_principal {
+    from
+}
+
+/// Validates the target of a transfer from a treasury, pushing any problem
+/// onto `defects`, and returns the target principal and a displayable
+/// rendering of the target account.
+fn validate_and_render_target_account(
+    to_principal: Option<PrincipalId>,
+    to_subaccount: &Option<Subaccount>,
+    defects: &mut Vec<String>,
+) -> (PrincipalId, String) {
+    let to_principal = if let Some(to_principal) = to_principal {
         if to_principal == PrincipalId::new_anonymous() {
             defects.push("Principal must not be anonymous.".to_string());
         }
@@ -288,7 +381,7 @@ fn validate_and_render_transfer_sns_treasury_funds(
         PrincipalId::new_anonymous()
     };
 
-    let to_account = match &transfer.to_subaccount {
+    let to_account = match to_subaccount {
         None => Account {
             owner: to_principal,
             subaccount: None,
@@ -307,32 +400,331 @@ fn validate_and_render_transfer_sns_treasury_funds(
         },
     };
 
+    (to_principal, to_account)
+}
+
+/// Validates and renders a proposal with action CreatePaymentStream.
+fn validate_and_render_create_payment_stream(
+    create: &CreatePaymentStream,
+    sns_transfer_fee_e8s: u64,
+) -> Result<String, String> {
+    let mut defects: Vec<String> = vec![];
+    let from = validate_and_render_treasury(
+        create.from_treasury(),
+        create.amount_per_period_e8s,
+        sns_transfer_fee_e8s,
+        &mut defects,
+    );
+    let (to_principal, to_account) = validate_and_render_target_account(
+        create.to_principal,
+        &create.to_subaccount,
+        &mut defects,
+    );
+
+    if create.period_seconds < PAYMENT_STREAM_PERIOD_SECONDS_MIN {
+        defects.push(format!(
+            "The period must be at least {} seconds.",
+            PAYMENT_STREAM_PERIOD_SECONDS_MIN
+        ));
+    }
+
+    if create.num_periods == 0 || create.num_periods > PAYMENT_STREAM_NUM_PERIODS_MAX {
+        defects.push(format!(
+            "The number of periods must be between 1 and {}.",
+            PAYMENT_STREAM_NUM_PERIODS_MAX
+        ));
+    }
+
+    if create.cliff_periods > create.num_periods {
+        defects.push("The cliff must not exceed the number of periods.".to_string());
+    }
+
+    let total_amount_e8s = create
+        .amount_per_period_e8s
+        .checked_mul(create.num_periods)
+        .unwrap_or_else(|| {
+            defects.push("The total amount of the payment stream overflows.".to_string());
+            u64::MAX
+        });
+
     // Generate final report.
     if !defects.is_empty() {
         return Err(format!(
-            "TransferSnsTreasuryFunds proposal was invalid for the following reason(s):\n{}",
+            "CreatePaymentStream proposal was invalid for the following reason(s):\n{}",
             defects.join("\n"),
         ));
     }
 
     Ok(format!(
-        r"# Proposal to transfer SNS Treasury funds:
+        r"# Proposal to create a payment stream from an SNS Treasury:
 ## Source treasury: {}
-## Amount (e8s): {}
+## Amount per period (e8s): {}
+## Period (seconds): {}
+## Number of periods: {}
+## Cliff (periods): {}
+## Total amount (e8s): {}
 ## Target principal: {}
 ## Target account: {}
 ## Memo: {}",
         from,
-        transfer.amount_e8s,
+        create.amount_per_period_e8s,
+        create.period_seconds,
+        create.num_periods,
+        create.cliff_periods,
+        total_amount_e8s,
         to_principal,
         to_account,
-        transfer.memo.unwrap_or(0)
+        create.memo.unwrap_or(0)
+    ))
+}
+
+/// Validates and renders a proposal with action MintSnsTokens.
+fn validate_and_render_mint_sns_tokens(
+    mint: &MintSnsTokens,
+    max_mint_e8s: u64,
+) -> Result<String, String> {
+    let mut defects: Vec<String> = vec![];
+
+    if mint.amount_e8s == 0 {
+        defects.push("The amount to mint must be positive.".to_string());
+    }
+    if mint.amount_e8s > max_mint_e8s {
+        defects.push(format!(
+            "The amount to mint must be at most {} e8s, the value of \
+             NervousSystemParameters.max_mint_sns_tokens_e8s_per_window, which caps \
+             the total amount minted within MINT_SNS_TOKENS_WINDOW_SECONDS.",
+            max_mint_e8s
+        ));
+    }
+
+    let (to_principal, to_account) =
+        validate_and_render_target_account(mint.to_principal, &mint.to_subaccount, &mut defects);
+
+    // Generate final report.
+    if !defects.is_empty() {
+        return Err(format!(
+            "MintSnsTokens proposal was invalid for the following reason(s):\n{}",
+            defects.join("\n"),
+        ));
+    }
+
+    Ok(format!(
+        r"# Proposal to mint SNS tokens:
+## Amount (e8s): {}
+## Target principal: {}
+## Target account: {}
+## Memo: {}",
+        mint.amount_e8s,
+        to_principal,
+        to_account,
+        mint.memo.unwrap_or(0)
+    ))
+}
+
+/// Validates and renders a proposal with action ManageLedgerParameters.
+fn validate_and_render_manage_ledger_parameters(
+    manage_ledger_parameters: &ManageLedgerParameters,
+    current_parameters: &NervousSystemParameters,
+) -> Result<String, String> {
+    let ManageLedgerParameters {
+        transfer_fee,
+        token_name,
+        token_symbol,
+        token_logo,
+    } = manage_ledger_parameters;
+
+    if transfer_fee.is_none()
+        && token_name.is_none()
+        && token_symbol.is_none()
+        && token_logo.is_none()
+    {
+        return Err(
+            "Error: ManageLedgerParameters must change at least one value, all values are None"
+                .to_string(),
+        );
+    }
+
+    let mut defects: Vec<String> = vec![];
+
+    if let Err(err) = UpgradeArgs::from(manage_ledger_parameters).validate() {
+        defects.push(format!("The ledger would reject the change: {}", err));
+    }
+    // Governance takes the new fee over as its transaction_fee_e8s, which has
+    // to stay below the minimum stake of a neuron.
+    if let Some(transfer_fee) = transfer_fee {
+        let neuron_minimum_stake_e8s = current_parameters
+            .neuron_minimum_stake_e8s
+            .unwrap_or_default();
+        if *transfer_fee >= neuron_minimum_stake_e8s {
+            defects.push(format!(
+                "The transfer fee must be less than {} e8s, the value of \
+                 NervousSystemParameters.neuron_minimum_stake_e8s.",
+                neuron_minimum_stake_e8s
+            ));
+        }
+    }
+    if let Some(token_logo) = token_logo {
+        if let Err(err) = SnsMetadata::validate_logo(token_logo) {
+            defects.push(err);
+        }
+    }
+
+    // Generate final report.
+    if !defects.is_empty() {
+        return Err(format!(
+            "ManageLedgerParameters proposal was invalid for the following reason(s):\n{}",
+            defects.join("\n"),
+        ));
+    }
+
+    let render_change = |value: Option<String>| value.unwrap_or_else(|| "unchanged".to_string());
+    Ok(format!(
+        r"# Proposal to change the parameters of the SNS ledger:
+## Transfer fee (e8s): {}
+## Token name: {}
+## Token symbol: {}
+## Token logo (base64 encoding): {}",
+        render_change(transfer_fee.map(|fee| fee.to_string())),
+        render_change(token_name.clone()),
+        render_change(token_symbol.clone()),
+        render_change(token_logo.clone()),
+    ))
+}
+
+/// Validates and renders a proposal with action OpenSaleRound.
+///
+/// The parameters are checked the same way as the swap canister checks them
+/// when it is opened, except for the checks that depend on the SNS ledger
+/// and governance parameters, which are left to the swap canister.
+fn validate_and_render_open_sale_round(
+    open: &OpenSaleRound,
+    sale_rounds: &BTreeMap<u64, SaleRound>,
+    now_seconds: u64,
+) -> Result<String, String> {
+    let mut defects: Vec<String> = vec![];
+
+    if let Some(sale_round) = sale_round_in_progress(sale_rounds, now_seconds) {
+        defects.push(format!(
+            "The sale round opened by proposal {} is still in progress.",
+            sale_round.proposal_id
+        ));
+    }
+
+    if open.min_participants == 0 || open.min_participants > 100 {
+        defects.push("The minimum number of participants must be between 1 and 100.".to_string());
+    }
+    if open.min_icp_e8s == 0 {
+        defects.push("The minimum amount of ICP to raise must be positive.".to_string());
+    }
+    if open.min_icp_e8s > open.max_icp_e8s {
+        defects.push(
+            "The minimum amount of ICP to raise must not exceed the maximum amount.".to_string(),
+        );
+    }
+    if open.min_participant_icp_e8s == 0 {
+        defects.push("The minimum amount of ICP per participant must be positive.".to_string());
+    }
+    if open.min_participant_icp_e8s > open.max_participant_icp_e8s {
+        defects.push(
+            "The minimum amount of ICP per participant must not exceed the maximum amount."
+                .to_string(),
+        );
+    }
+    if open.max_participant_icp_e8s > open.max_icp_e8s {
+        defects.push(
+            "The maximum amount of ICP per participant must not exceed the maximum amount \
+             of ICP to raise."
+                .to_string(),
+        );
+    }
+    if open.max_icp_e8s
+        < (open.min_participants as u64).saturating_mul(open.min_participant_icp_e8s)
+    {
+        defects.push(
+            "The maximum amount of ICP to raise must be at least the minimum number of \
+             participants times the minimum amount of ICP per participant."
+                .to_string(),
+        );
+    }
+    if open.sns_token_e8s == 0 {
+        defects.push("The amount of SNS tokens offered must be positive.".to_string());
+    }
+    if open.neuron_basket_count == 0 {
+        defects.push("The number of neurons per participant must be positive.".to_string());
+    }
+    if open.neuron_basket_dissolve_delay_interval_seconds == 0 {
+        defects.push("The dissolve delay interval of the neurons must be positive.".to_string());
+    }
+    if open.swap_due_timestamp_seconds <= now_seconds {
+        defects.push("The due date of the sale round must be in the future.".to_string());
+    }
+
+    // Generate final report.
+    if !defects.is_empty() {
+        return Err(format!(
+            "OpenSaleRound proposal was invalid for the following reason(s):\n{}",
+            defects.join("\n"),
+        ));
+    }
+
+    Ok(format!(
+        r"# Proposal to open a follow-up sale round of SNS tokens from the SNS Token Treasury:
+## SNS tokens offered (e8s): {}
+## Minimum number of participants: {}
+## Minimum ICP to raise (e8s): {}
+## Maximum ICP to raise (e8s): {}
+## Minimum ICP per participant (e8s): {}
+## Maximum ICP per participant (e8s): {}
+## Due date (seconds since the Unix epoch): {}
+## Neurons per participant: {}
+## Dissolve delay interval of the neurons (seconds): {}",
+        open.sns_token_e8s,
+        open.min_participants,
+        open.min_icp_e8s,
+        open.max_icp_e8s,
+        open.min_participant_icp_e8s,
+        open.max_participant_icp_e8s,
+        open.swap_due_timestamp_seconds,
+        open.neuron_basket_count,
+        open.neuron_basket_dissolve_delay_interval_seconds,
+    ))
+}
+
+/// Validates and renders a proposal with action CancelPaymentStream.
+fn validate_and_render_cancel_payment_stream(
+    cancel: &CancelPaymentStream,
+    payment_streams: &BTreeMap<u64, PaymentStream>,
+) -> Result<String, String> {
+    let id = cancel.payment_stream_id;
+    let stream = payment_streams
+        .get(&id)
+        .ok_or_else(|| format!("There is no payment stream with id {}.", id))?;
+
+    if !stream.is_active() {
+        return Err(format!(
+            "Payment stream {} is already cancelled or fully paid.",
+            id
+        ));
+    }
+
+    let num_periods = stream
+        .terms
+        .as_ref()
+        .map(|terms| terms.num_periods)
+        .unwrap_or_default();
+
+    Ok(format!(
+        r"# Proposal to cancel a payment stream:
+## Payment stream id: {}
+## Paid periods: {} of {}",
+        id, stream.paid_periods, num_periods
     ))
 }
 
 /// Validates and renders a proposal with action UpgradeSnsControlledCanister.
 fn validate_and_render_upgrade_sns_controlled_canister(
     upgrade: &UpgradeSnsControlledCanister,
+    ledger_canister_id: Option<PrincipalId>,
 ) -> Result<String, String> {
     let mut defects = vec![];
 
@@ -365,6 +757,16 @@ fn validate_and_render_upgrade_sns_controlled_canister(
         defects.push("new_canister_wasm lacks the magic value in its header.".into());
     }
 
+    // Inspect the upgrade argument of the SNS ledger.
+    let is_ledger_upgrade = ledger_canister_id == Some(canister_id);
+    let mut ledger_upgrade_args = None;
+    if let (true, Some(arg)) = (is_ledger_upgrade, &upgrade.canister_upgrade_arg) {
+        match decode_ledger_upgrade_arg(arg) {
+            Ok(args) => ledger_upgrade_args = args,
+            Err(err) => defects.push(err),
+        }
+    }
+
     // Generate final report.
     if !defects.is_empty() {
         return Err(format!(
@@ -377,17 +779,71 @@ fn validate_and_render_upgrade_sns_controlled_canister(
     state.write(&upgrade.new_canister_wasm);
     let sha = state.finish();
 
+    let upgrade_arg = match (&upgrade.canister_upgrade_arg, ledger_upgrade_args) {
+        (None, _) => "none".to_string(),
+        (Some(arg), None) if is_ledger_upgrade => format!(
+            "sha256 {} (keeps the ledger configuration)",
+            hex::encode(Sha256::hash(arg))
+        ),
+        (Some(arg), None) => format!("sha256 {}", hex::encode(Sha256::hash(arg))),
+        (Some(arg), Some(args)) => format!(
+            "sha256 {}\n\n```\n{:#?}\n```",
+            hex::encode(Sha256::hash(arg)),
+            args
+        ),
+    };
+
     Ok(format!(
         r"# Proposal to upgrade SNS controlled canister:
 
 ## Canister id: {:?}
 
-## Canister wasm sha256: {}",
+## Canister wasm sha256: {}
+
+## Upgrade argument: {}",
         canister_id,
-        hex::encode(sha)
+        hex::encode(sha),
+        upgrade_arg
     ))
 }
 
+/// Decodes the argument of an upgrade of the SNS ledger the same way the
+/// ledger does and checks that the ledger would accept it.
+///
+/// The argument must not change the transfer fee: governance keeps a copy of
+/// the fee in its parameters, so the fee only changes through
+/// ManageLedgerParameters proposals.
+pub(crate) fn decode_ledger_upgrade_arg(arg: &[u8]) -> Result<Option<UpgradeArgs>, String> {
+    // The ledger treats an empty argument as no argument.
+    if arg.is_empty() {
+        return Ok(None);
+    }
+    let upgrade_args = match Decode!(arg, Option<LedgerArgument>) {
+        Err(err) => {
+            return Err(format!(
+                "The ledger upgrade argument is not a valid LedgerArgument: {}",
+                err
+            ))
+        }
+        Ok(None) | Ok(Some(LedgerArgument::Upgrade(None))) => return Ok(None),
+        Ok(Some(LedgerArgument::Init(_))) => {
+            return Err("The ledger upgrade argument must not be LedgerArgument::Init.".to_string())
+        }
+        Ok(Some(LedgerArgument::Upgrade(Some(upgrade_args)))) => upgrade_args,
+    };
+    if upgrade_args.transfer_fee.is_some() {
+        return Err(
+            "The ledger upgrade argument must not change the transfer fee: \
+            use a ManageLedgerParameters proposal instead."
+                .to_string(),
+        );
+    }
+    upgrade_args
+        .validate()
+        .map_err(|err| format!("The ledger would reject the upgrade argument: {}", err))?;
+    Ok(Some(upgrade_args))
+}
+
 pub(crate) fn render_version(version: &Version) -> String {
     format!(
         r"Version {{
@@ -1002,7 +1458,6 @@ impl ProposalRewardStatus {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::pb::v1::Subaccount;
     use crate::{
         pb::v1::{governance, governance::Version, Empty, Governance as GovernanceProto},
         sns_upgrade::{
@@ -1020,10 +1475,11 @@ mod tests {
     use ic_crypto_sha::Sha256;
     use ic_ic00_types::CanisterStatusResultV2;
     use ic_ic00_types::CanisterStatusType;
+    use ic_nervous_system_common::E8;
     use ic_nns_constants::SNS_WASM_CANISTER_ID;
     use ic_test_utilities::types::ids::canister_test_id;
     use lazy_static::lazy_static;
-    use maplit::hashset;
+    use maplit::{btreemap, hashset};
     use std::convert::TryFrom;
 
     pub const FORBIDDEN_CANISTER: CanisterId = CanisterId::ic_00();
@@ -1054,6 +1510,10 @@ mod tests {
             deployed_version,
             pending_version: None,
             sns_initialization_parameters: "".to_string(),
+            payment_streams: Default::default(),
+            sale_rounds: Default::default(),
+            recent_sns_token_mints: Default::default(),
+            pending_ledger_fee_sync: None,
         }
     }
 
@@ -1202,9 +1662,10 @@ mod tests {
         let upgrade = UpgradeSnsControlledCanister {
             canister_id: Some(basic_principal_id()),
             new_canister_wasm: vec![0, 0x61, 0x73, 0x6D, 1, 0, 0, 0],
+            canister_upgrade_arg: None,
         };
         assert_is_ok(validate_and_render_upgrade_sns_controlled_canister(
-            &upgrade,
+            &upgrade, None,
         ));
 
         let mut result = basic_motion_proposal();
@@ -1221,9 +1682,9 @@ mod tests {
         assert_is_err(validate_default_action(&proposal.action));
 
         match proposal.action.as_ref().unwrap() {
-            proposal::Action::UpgradeSnsControlledCanister(upgrade) => {
-                assert_is_err(validate_and_render_upgrade_sns_controlled_canister(upgrade))
-            }
+            proposal::Action::UpgradeSnsControlledCanister(upgrade) => assert_is_err(
+                validate_and_render_upgrade_sns_controlled_canister(upgrade, None),
+            ),
             _ => panic!("Proposal.action is not an UpgradeSnsControlledCanister."),
         }
     }
@@ -1236,7 +1697,9 @@ mod tests {
         match proposal.action.as_mut().unwrap() {
             proposal::Action::UpgradeSnsControlledCanister(upgrade) => {
                 upgrade.canister_id = None;
-                assert_is_err(validate_and_render_upgrade_sns_controlled_canister(upgrade));
+                assert_is_err(validate_and_render_upgrade_sns_controlled_canister(
+                    upgrade, None,
+                ));
             }
             _ => panic!("Proposal.action is not an UpgradeSnsControlledCanister."),
         }
@@ -1254,7 +1717,9 @@ mod tests {
         match proposal.action.as_mut().unwrap() {
             proposal::Action::UpgradeSnsControlledCanister(upgrade) => {
                 upgrade.new_canister_wasm = vec![];
-                assert_is_err(validate_and_render_upgrade_sns_controlled_canister(upgrade));
+                assert_is_err(validate_and_render_upgrade_sns_controlled_canister(
+                    upgrade, None,
+                ));
             }
             _ => panic!("Proposal.action is not an UpgradeSnsControlledCanister."),
         }
@@ -1274,7 +1739,9 @@ mod tests {
                 // first four bytes of this Vec are 0xDeadBeef.)
                 upgrade.new_canister_wasm = vec![0xde, 0xad, 0xbe, 0xef, 1, 0, 0, 0];
                 assert!(upgrade.new_canister_wasm.len() == 8); // The minimum wasm len.
-                assert_is_err(validate_and_render_upgrade_sns_controlled_canister(upgrade));
+                assert_is_err(validate_and_render_upgrade_sns_controlled_canister(
+                    upgrade, None,
+                ));
             }
             _ => panic!("Proposal.action is not an UpgradeSnsControlledCanister."),
         }
@@ -1282,6 +1749,102 @@ mod tests {
         assert_validate_upgrade_sns_controlled_canister_is_err(&proposal);
     }
 
+    #[test]
+    fn upgrade_rendering_includes_the_upgrade_arg_hash() {
+        let mut upgrade = match basic_upgrade_sns_controlled_canister_proposal().action {
+            Some(proposal::Action::UpgradeSnsControlledCanister(upgrade)) => upgrade,
+            _ => panic!("Proposal.action is not an UpgradeSnsControlledCanister."),
+        };
+
+        let rendered = validate_and_render_upgrade_sns_controlled_canister(&upgrade, None).unwrap();
+        assert!(
+            rendered.contains("## Upgrade argument: none"),
+            "{}",
+            rendered
+        );
+
+        upgrade.canister_upgrade_arg = Some(vec![1, 2, 3]);
+        let rendered = validate_and_render_upgrade_sns_controlled_canister(&upgrade, None).unwrap();
+        assert!(
+            rendered.contains(&format!(
+                "## Upgrade argument: sha256 {}",
+                hex::encode(Sha256::hash(&[1, 2, 3]))
+            )),
+            "{}",
+            rendered
+        );
+    }
+
+    #[test]
+    fn ledger_upgrade_arg_is_decoded_and_rendered() {
+        let ledger_canister_id = Some(basic_principal_id());
+        let mut upgrade = match basic_upgrade_sns_controlled_canister_proposal().action {
+            Some(proposal::Action::UpgradeSnsControlledCanister(upgrade)) => upgrade,
+            _ => panic!("Proposal.action is not an UpgradeSnsControlledCanister."),
+        };
+        let with_arg = |upgrade: &UpgradeSnsControlledCanister, arg: LedgerArgument| {
+            UpgradeSnsControlledCanister {
+                canister_upgrade_arg: Some(Encode!(&Some(arg)).unwrap()),
+                ..upgrade.clone()
+            }
+        };
+
+        upgrade = with_arg(
+            &upgrade,
+            LedgerArgument::Upgrade(Some(UpgradeArgs {
+                token_name: Some("New Token".to_string()),
+                ..UpgradeArgs::default()
+            })),
+        );
+        let rendered =
+            validate_and_render_upgrade_sns_controlled_canister(&upgrade, ledger_canister_id)
+                .unwrap();
+        assert!(rendered.contains("token_name: Some(\n"), "{}", rendered);
+        assert!(rendered.contains("\"New Token\""), "{}", rendered);
+
+        // Dapp canisters receive their argument as is.
+        let rendered = validate_and_render_upgrade_sns_controlled_canister(&upgrade, None).unwrap();
+        assert!(!rendered.contains("New Token"), "{}", rendered);
+
+        // Governance caches the transfer fee, so the fee only changes through
+        // ManageLedgerParameters.
+        let changes_fee = with_arg(
+            &upgrade,
+            LedgerArgument::Upgrade(Some(UpgradeArgs {
+                transfer_fee: Some(1),
+                ..UpgradeArgs::default()
+            })),
+        );
+        assert_is_err(validate_and_render_upgrade_sns_controlled_canister(
+            &changes_fee,
+            ledger_canister_id,
+        ));
+
+        let invalid = with_arg(
+            &upgrade,
+            LedgerArgument::Upgrade(Some(UpgradeArgs {
+                token_symbol: Some("".to_string()),
+                ..UpgradeArgs::default()
+            })),
+        );
+        assert_is_err(validate_and_render_upgrade_sns_controlled_canister(
+            &invalid,
+            ledger_canister_id,
+        ));
+
+        let garbage = UpgradeSnsControlledCanister {
+            canister_upgrade_arg: Some(vec![1, 2, 3]),
+            ..upgrade.clone()
+        };
+        assert_is_err(validate_and_render_upgrade_sns_controlled_canister(
+            &garbage,
+            ledger_canister_id,
+        ));
+        assert_is_ok(validate_and_render_upgrade_sns_controlled_canister(
+            &garbage, None,
+        ));
+    }
+
     fn basic_add_nervous_system_function_proposal() -> Proposal {
         let nervous_system_function = NervousSystemFunction {
             id: 1000,
@@ -1572,6 +2135,10 @@ mod tests {
             Some(0),
             0,
             0,
+            None,
+            0,
+            None,
+            Default::default(),
         )
     }
 
@@ -1680,6 +2247,7 @@ mod tests {
                     )),
                     canister_id: Some(index_canister_id.get())
                 }),
+                sale_round_swaps: vec![],
             })
             .unwrap()),
         );
@@ -1880,6 +2448,7 @@ Version {
             dapps: vec![],
             archives: vec![],
             index: None,
+            sale_round_swaps: vec![],
         };
 
         env.set_call_canister_response(
@@ -2134,4 +2703,339 @@ Version {
             "TransferSnsTreasuryFunds proposal was invalid for the following reason(s):\nFor transactions from SNS Token Treasury (SNS Ledger), the fee and minimum transaction is 1000 e8s"
         );
     }
+
+    fn valid_create_payment_stream() -> CreatePaymentStream {
+        CreatePaymentStream {
+            from_treasury: TransferFrom::SnsTokenTreasury.into(),
+            to_principal: Some(basic_principal_id()),
+            to_subaccount: None,
+            amount_per_period_e8s: 100_000,
+            period_seconds: 30 * ONE_DAY_SECONDS,
+            num_periods: 12,
+            cliff_periods: 3,
+            memo: Some(7),
+        }
+    }
+
+    #[test]
+    fn validate_and_render_create_payment_stream_renders_for_valid_inputs() {
+        assert_eq!(
+            validate_and_render_create_payment_stream(&valid_create_payment_stream(), 1000)
+                .unwrap(),
+            r"# Proposal to create a payment stream from an SNS Treasury:
+## Source treasury: SNS Token Treasury (SNS Ledger)
+## Amount per period (e8s): 100000
+## Period (seconds): 2592000
+## Number of periods: 12
+## Cliff (periods): 3
+## Total amount (e8s): 1200000
+## Target principal: bg4sm-wzk
+## Target account: bg4sm-wzk
+## Memo: 7"
+        );
+    }
+
+    #[test]
+    fn validate_and_render_create_payment_stream_rejects_invalid_schedules() {
+        let err = validate_and_render_create_payment_stream(
+            &CreatePaymentStream {
+                period_seconds: ONE_DAY_SECONDS - 1,
+                ..valid_create_payment_stream()
+            },
+            1000,
+        )
+        .unwrap_err();
+        assert!(err.contains("The period must be at least"), "{}", err);
+
+        for num_periods in [0, PAYMENT_STREAM_NUM_PERIODS_MAX + 1] {
+            let err = validate_and_render_create_payment_stream(
+                &CreatePaymentStream {
+                    num_periods,
+                    cliff_periods: 0,
+                    ..valid_create_payment_stream()
+                },
+                1000,
+            )
+            .unwrap_err();
+            assert!(err.contains("The number of periods must be"), "{}", err);
+        }
+
+        let err = validate_and_render_create_payment_stream(
+            &CreatePaymentStream {
+                cliff_periods: 13,
+                ..valid_create_payment_stream()
+            },
+            1000,
+        )
+        .unwrap_err();
+        assert!(err.contains("The cliff must not exceed"), "{}", err);
+
+        let err = validate_and_render_create_payment_stream(
+            &CreatePaymentStream {
+                amount_per_period_e8s: u64::MAX,
+                ..valid_create_payment_stream()
+            },
+            1000,
+        )
+        .unwrap_err();
+        assert!(err.contains("overflows"), "{}", err);
+
+        let err = validate_and_render_create_payment_stream(
+            &CreatePaymentStream {
+                amount_per_period_e8s: 999,
+                ..valid_create_payment_stream()
+            },
+            1000,
+        )
+        .unwrap_err();
+        assert!(
+            err.contains("the fee and minimum transaction is 1000 e8s"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn validate_and_render_cancel_payment_stream_requires_an_active_stream() {
+        let active = PaymentStream {
+            id: 42,
+            terms: Some(valid_create_payment_stream()),
+            paid_periods: 5,
+            ..Default::default()
+        };
+        let cancelled = PaymentStream {
+            id: 43,
+            cancellation_timestamp_seconds: Some(1),
+            ..active.clone()
+        };
+        let payment_streams = btreemap! { 42 => active, 43 => cancelled };
+
+        assert_eq!(
+            validate_and_render_cancel_payment_stream(
+                &CancelPaymentStream {
+                    payment_stream_id: 42
+                },
+                &payment_streams
+            )
+            .unwrap(),
+            r"# Proposal to cancel a payment stream:
+## Payment stream id: 42
+## Paid periods: 5 of 12"
+        );
+        validate_and_render_cancel_payment_stream(
+            &CancelPaymentStream {
+                payment_stream_id: 43,
+            },
+            &payment_streams,
+        )
+        .unwrap_err();
+        validate_and_render_cancel_payment_stream(
+            &CancelPaymentStream {
+                payment_stream_id: 44,
+            },
+            &payment_streams,
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn validate_and_render_mint_sns_tokens_enforces_the_cap() {
+        let mint = MintSnsTokens {
+            amount_e8s: 1_000,
+            to_principal: Some(basic_principal_id()),
+            to_subaccount: None,
+            memo: Some(5),
+        };
+
+        assert_eq!(
+            validate_and_render_mint_sns_tokens(&mint, 1_000).unwrap(),
+            r"# Proposal to mint SNS tokens:
+## Amount (e8s): 1000
+## Target principal: bg4sm-wzk
+## Target account: bg4sm-wzk
+## Memo: 5"
+        );
+
+        let err = validate_and_render_mint_sns_tokens(&mint, 999).unwrap_err();
+        assert!(err.contains("must be at most 999 e8s"), "{}", err);
+
+        // Minting is disabled while the cap is zero.
+        validate_and_render_mint_sns_tokens(&mint, 0).unwrap_err();
+
+        let err = validate_and_render_mint_sns_tokens(
+            &MintSnsTokens {
+                amount_e8s: 0,
+                ..mint
+            },
+            1_000,
+        )
+        .unwrap_err();
+        assert!(err.contains("must be positive"), "{}", err);
+    }
+
+    #[test]
+    fn validate_and_render_manage_ledger_parameters_renders_the_changes() {
+        let manage_ledger_parameters = ManageLedgerParameters {
+            transfer_fee: Some(20_000),
+            token_name: Some("Foo".to_string()),
+            token_symbol: None,
+            token_logo: None,
+        };
+
+        assert_eq!(
+            validate_and_render_manage_ledger_parameters(
+                &manage_ledger_parameters,
+                &DEFAULT_PARAMS
+            )
+            .unwrap(),
+            r"# Proposal to change the parameters of the SNS ledger:
+## Transfer fee (e8s): 20000
+## Token name: Foo
+## Token symbol: unchanged
+## Token logo (base64 encoding): unchanged"
+        );
+
+        let err = validate_and_render_manage_ledger_parameters(
+            &ManageLedgerParameters::default(),
+            &DEFAULT_PARAMS,
+        )
+        .unwrap_err();
+        assert!(err.contains("must change at least one value"), "{}", err);
+
+        let err = validate_and_render_manage_ledger_parameters(
+            &ManageLedgerParameters {
+                token_symbol: Some("".to_string()),
+                ..manage_ledger_parameters.clone()
+            },
+            &DEFAULT_PARAMS,
+        )
+        .unwrap_err();
+        assert!(err.contains("token symbol must not be empty"), "{}", err);
+
+        let err = validate_and_render_manage_ledger_parameters(
+            &ManageLedgerParameters {
+                token_logo: Some("not a logo".to_string()),
+                ..manage_ledger_parameters.clone()
+            },
+            &DEFAULT_PARAMS,
+        )
+        .unwrap_err();
+        assert!(err.contains("SnsMetadata.logo"), "{}", err);
+
+        let err = validate_and_render_manage_ledger_parameters(
+            &ManageLedgerParameters {
+                transfer_fee: DEFAULT_PARAMS.neuron_minimum_stake_e8s,
+                ..manage_ledger_parameters
+            },
+            &DEFAULT_PARAMS,
+        )
+        .unwrap_err();
+        assert!(err.contains("neuron_minimum_stake_e8s"), "{}", err);
+    }
+
+    fn valid_open_sale_round() -> OpenSaleRound {
+        OpenSaleRound {
+            min_participants: 2,
+            min_icp_e8s: 10 * E8,
+            max_icp_e8s: 100 * E8,
+            min_participant_icp_e8s: E8,
+            max_participant_icp_e8s: 50 * E8,
+            swap_due_timestamp_seconds: 2_000_000,
+            sns_token_e8s: 1_000 * E8,
+            neuron_basket_count: 3,
+            neuron_basket_dissolve_delay_interval_seconds: ONE_DAY_SECONDS,
+        }
+    }
+
+    #[test]
+    fn validate_and_render_open_sale_round_renders_for_valid_inputs() {
+        assert_eq!(
+            validate_and_render_open_sale_round(
+                &valid_open_sale_round(),
+                &BTreeMap::new(),
+                1_000_000
+            )
+            .unwrap(),
+            r"# Proposal to open a follow-up sale round of SNS tokens from the SNS Token Treasury:
+## SNS tokens offered (e8s): 100000000000
+## Minimum number of participants: 2
+## Minimum ICP to raise (e8s): 1000000000
+## Maximum ICP to raise (e8s): 10000000000
+## Minimum ICP per participant (e8s): 100000000
+## Maximum ICP per participant (e8s): 5000000000
+## Due date (seconds since the Unix epoch): 2000000
+## Neurons per participant: 3
+## Dissolve delay interval of the neurons (seconds): 86400"
+        );
+    }
+
+    #[test]
+    fn validate_and_render_open_sale_round_rejects_invalid_parameters() {
+        let err = validate_and_render_open_sale_round(
+            &OpenSaleRound::default(),
+            &BTreeMap::new(),
+            1_000_000,
+        )
+        .unwrap_err();
+        for expected in [
+            "minimum number of participants",
+            "minimum amount of ICP to raise must be positive",
+            "minimum amount of ICP per participant must be positive",
+            "SNS tokens offered must be positive",
+            "number of neurons per participant",
+            "dissolve delay interval",
+            "due date",
+        ] {
+            assert!(err.contains(expected), "{}: {}", expected, err);
+        }
+
+        let err = validate_and_render_open_sale_round(
+            &OpenSaleRound {
+                min_icp_e8s: 200 * E8,
+                max_participant_icp_e8s: 500 * E8,
+                ..valid_open_sale_round()
+            },
+            &BTreeMap::new(),
+            1_000_000,
+        )
+        .unwrap_err();
+        assert!(
+            err.contains("minimum amount of ICP to raise must not exceed"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("maximum amount of ICP per participant must not exceed"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn validate_and_render_open_sale_round_rejects_while_another_round_is_in_progress() {
+        let sale_rounds = btreemap! {
+            7 => SaleRound {
+                proposal_id: 7,
+                swap_canister_id: Some(canister_test_id(600).get()),
+                parameters: Some(OpenSaleRound {
+                    swap_due_timestamp_seconds: 1_500_000,
+                    ..valid_open_sale_round()
+                }),
+                creation_timestamp_seconds: 900_000,
+            },
+        };
+
+        let err =
+            validate_and_render_open_sale_round(&valid_open_sale_round(), &sale_rounds, 1_000_000)
+                .unwrap_err();
+        assert!(
+            err.contains("The sale round opened by proposal 7 is still in progress."),
+            "{}",
+            err
+        );
+
+        // Once the due date of the previous round has passed, a new round can be opened.
+        validate_and_render_open_sale_round(&valid_open_sale_round(), &sale_rounds, 1_500_000)
+            .unwrap();
+    }
 }
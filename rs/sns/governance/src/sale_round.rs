@@ -0,0 +1,219 @@
+use crate::pb::v1::{OpenSaleRound, SaleRound};
+use crate::types::Environment;
+use candid::{Decode, Encode};
+use ic_base_types::{CanisterId, PrincipalId};
+use std::collections::BTreeMap;
+
+/// Returns the sale round that is still in progress, if any. A round is in
+/// progress until its due date has passed, or while its swap canister is
+/// being created, unless its swap canister could not be opened.
+pub(crate) fn sale_round_in_progress(
+    sale_rounds: &BTreeMap<u64, SaleRound>,
+    now_seconds: u64,
+) -> Option<&SaleRound> {
+    sale_rounds.values().find(|sale_round| {
+        let swap_due_timestamp_seconds = sale_round
+            .parameters
+            .as_ref()
+            .map(|parameters| parameters.swap_due_timestamp_seconds)
+            .unwrap_or_default();
+        sale_round.abort_failure_timestamp_seconds == 0
+            && (sale_round.swap_canister_id.is_none() || now_seconds < swap_due_timestamp_seconds)
+    })
+}
+
+impl OpenSaleRound {
+    /// The parameters with which the swap canister of the round is opened.
+    pub(crate) fn swap_params(&self) -> Params {
+        Params {
+            min_participants: self.min_participants,
+            min_icp_e8s: self.min_icp_e8s,
+            max_icp_e8s: self.max_icp_e8s,
+            min_participant_icp_e8s: self.min_participant_icp_e8s,
+            max_participant_icp_e8s: self.max_participant_icp_e8s,
+            swap_due_timestamp_seconds: self.swap_due_timestamp_seconds,
+            sns_token_e8s: self.sns_token_e8s,
+            neuron_basket_construction_parameters: Some(NeuronBasketConstructionParameters {
+                count: self.neuron_basket_count,
+                dissolve_delay_interval_seconds: self.neuron_basket_dissolve_delay_interval_seconds,
+            }),
+        }
+    }
+}
+
+/// Asks SNS root to create a new swap canister running `wasm_module`, and
+/// returns its ID.
+pub(crate) async fn create_swap_canister(
+    env: &dyn Environment,
+    root_canister_id: CanisterId,
+    wasm_module: Vec<u8>,
+    init: &Init,
+) -> Result<PrincipalId, String> {
+    let arg = Encode!(&CreateSwapCanisterRequest {
+        wasm_module,
+        arg: Encode!(init).expect("Could not encode"),
+    })
+    .expect("Could not encode");
+
+    let response = env
+        .call_canister(root_canister_id, "create_swap_canister", arg)
+        .await
+        .map_err(|(code, message)| {
+            format!(
+                "Call to create_swap_canister failed: {} {}",
+                code.unwrap_or_default(),
+                message
+            )
+        })?;
+
+    Decode!(&response, CreateSwapCanisterResponse)
+        .map_err(|e| format!("Failed to decode response: {:?}", e))?
+        .canister_id
+        .ok_or_else(|| "Root did not return the ID of the new swap canister.".to_string())
+}
+
+/// Opens the swap canister of a sale round. The swap canister must already
+/// hold the SNS tokens that it offers.
+pub(crate) async fn open_swap(
+    env: &dyn Environment,
+    swap_canister_id: CanisterId,
+    request: &OpenRequest,
+) -> Result<(), String> {
+    env.call_canister(
+        swap_canister_id,
+        "open",
+        Encode!(request).expect("Could not encode"),
+    )
+    .await
+    .map(|_| ())
+    .map_err(|(code, message)| {
+        format!(
+            "Call to open failed: {} {}",
+            code.unwrap_or_default(),
+            message
+        )
+    })
+}
+
+/// Asks the swap canister of a sale round that could not be opened to return
+/// the SNS tokens that it holds to the treasury.
+pub(crate) async fn abort_unopened_swap(
+    env: &dyn Environment,
+    swap_canister_id: CanisterId,
+) -> Result<(), String> {
+    let response = env
+        .call_canister(
+            swap_canister_id,
+            "abort_unopened_sale_round",
+            Encode!(&AbortUnopenedSaleRoundRequest {}).expect("Could not encode"),
+        )
+        .await
+        .map_err(|(code, message)| {
+            format!(
+                "Call to abort_unopened_sale_round failed: {} {}",
+                code.unwrap_or_default(),
+                message
+            )
+        })?;
+
+    let sweep_sns = Decode!(&response, AbortUnopenedSaleRoundResponse)
+        .map_err(|e| format!("Failed to decode response: {:?}", e))?
+        .sweep_sns
+        .unwrap_or_default();
+    if sweep_sns.failure > 0 {
+        return Err("The swap canister could not return the SNS tokens.".to_string());
+    }
+    Ok(())
+}
+
+// TODO(NNS1-1590) Remove following duplicate definitions and split the types into their own crates
+
+/// Copied from ic-sns-root.
+#[derive(candid::CandidType, candid::Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct CreateSwapCanisterRequest {
+    pub wasm_module: Vec<u8>,
+    pub arg: Vec<u8>,
+}
+
+/// Copied from ic-sns-root.
+#[derive(candid::CandidType, candid::Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct CreateSwapCanisterResponse {
+    pub canister_id: Option<PrincipalId>,
+}
+
+/// Copied from ic-sns-swap to avoid circular dependency. Only contains the
+/// fields that follow-up sale rounds need; the others are optional.
+#[derive(candid::CandidType, candid::Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Init {
+    pub nns_governance_canister_id: String,
+    pub sns_governance_canister_id: String,
+    pub sns_ledger_canister_id: String,
+    pub icp_ledger_canister_id: String,
+    pub sns_root_canister_id: String,
+    pub fallback_controller_principal_ids: Vec<String>,
+    pub transaction_fee_e8s: Option<u64>,
+    pub neuron_minimum_stake_e8s: Option<u64>,
+    pub allowed_participant_principal_ids: Vec<String>,
+    pub follow_up_sale_round: Option<bool>,
+}
+
+/// Copied from ic-sns-swap to avoid circular dependency.
+#[derive(candid::CandidType, candid::Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct OpenRequest {
+    pub params: Option<Params>,
+    pub cf_participants: Vec<CfParticipant>,
+    pub open_sns_token_swap_proposal_id: Option<u64>,
+}
+
+/// Copied from ic-sns-swap to avoid circular dependency.
+#[derive(candid::CandidType, candid::Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Params {
+    pub min_participants: u32,
+    pub min_icp_e8s: u64,
+    pub max_icp_e8s: u64,
+    pub min_participant_icp_e8s: u64,
+    pub max_participant_icp_e8s: u64,
+    pub swap_due_timestamp_seconds: u64,
+    pub sns_token_e8s: u64,
+    pub neuron_basket_construction_parameters: Option<NeuronBasketConstructionParameters>,
+}
+
+/// Copied from ic-sns-swap to avoid circular dependency.
+#[derive(candid::CandidType, candid::Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct NeuronBasketConstructionParameters {
+    pub count: u64,
+    pub dissolve_delay_interval_seconds: u64,
+}
+
+/// Copied from ic-sns-swap to avoid circular dependency. Sale rounds never
+/// have community fund participants.
+#[derive(candid::CandidType, candid::Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct CfParticipant {
+    pub hotkey_principal: String,
+    pub cf_neurons: Vec<CfNeuron>,
+}
+
+/// Copied from ic-sns-swap to avoid circular dependency.
+#[derive(candid::CandidType, candid::Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct CfNeuron {
+    pub nns_neuron_id: u64,
+    pub amount_icp_e8s: u64,
+}
+
+/// Copied from ic-sns-swap to avoid circular dependency.
+#[derive(candid::CandidType, candid::Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct AbortUnopenedSaleRoundRequest {}
+
+/// Copied from ic-sns-swap to avoid circular dependency.
+#[derive(candid::CandidType, candid::Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct AbortUnopenedSaleRoundResponse {
+    pub sweep_sns: Option<SweepResult>,
+}
+
+/// Copied from ic-sns-swap to avoid circular dependency.
+#[derive(candid::CandidType, candid::Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct SweepResult {
+    pub success: u32,
+    pub failure: u32,
+    pub skipped: u32,
+}
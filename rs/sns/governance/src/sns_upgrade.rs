@@ -15,6 +15,7 @@ pub struct RunningSnsCanisters {
     pub dapps: Vec<PrincipalId>,
     pub archives: Vec<PrincipalId>,
     pub index: Option<PrincipalId>,
+    pub sale_round_swaps: Vec<PrincipalId>,
 }
 
 /// Upgrade parameters.
@@ -111,7 +112,14 @@ async fn get_canisters_to_upgrade(
         SnsCanisterType::Root => (vec![running_canisters.root], "Root"),
         SnsCanisterType::Governance => (vec![running_canisters.governance], "Governance"),
         SnsCanisterType::Ledger => (vec![running_canisters.ledger], "Ledger"),
-        SnsCanisterType::Swap => (vec![running_canisters.swap], "Swap"),
+        // The swap canisters of follow-up sale rounds run the same Wasm as
+        // the swap canister of the initial swap.
+        SnsCanisterType::Swap => (
+            std::iter::once(running_canisters.swap)
+                .chain(running_canisters.sale_round_swaps.iter().map(|p| Some(*p)))
+                .collect(),
+            "Swap",
+        ),
         SnsCanisterType::Archive => (
             running_canisters
                 .archives
@@ -284,6 +292,11 @@ pub(crate) async fn get_all_sns_canisters(
         .index
         .map(|summary| summary.canister_id)
         .unwrap_or_default();
+    let sale_round_swaps = response
+        .sale_round_swaps
+        .iter()
+        .map(|response| response.canister_id.unwrap())
+        .collect();
 
     Ok(RunningSnsCanisters {
         root,
@@ -293,6 +306,7 @@ pub(crate) async fn get_all_sns_canisters(
         dapps,
         archives,
         index,
+        sale_round_swaps,
     })
 }
 
@@ -424,6 +438,7 @@ pub(crate) struct GetSnsCanistersSummaryResponse {
     pub dapps: Vec<CanisterSummary>,
     pub archives: Vec<CanisterSummary>,
     pub index: Option<CanisterSummary>,
+    pub sale_round_swaps: Vec<CanisterSummary>,
 }
 
 /// Copied from ic-sns-root
@@ -9,7 +9,8 @@ use crate::{
         nervous_system_function::FunctionType,
         proposal::Action,
         DefaultFollowees, Empty, ExecuteGenericNervousSystemFunction, GovernanceError,
-        ManageNeuronResponse, NervousSystemFunction, NervousSystemParameters, NeuronId,
+        ManageLedgerParameters, ManageNeuronResponse, NervousSystemFunction,
+        NervousSystemParameters, NeuronId, PaymentStream,
     },
     proposal::ValidGenericNervousSystemFunction,
 };
@@ -17,6 +18,8 @@ use crate::{
 use async_trait::async_trait;
 
 use ic_base_types::{CanisterId, PrincipalId};
+use ic_icrc1::endpoints::Value;
+use ic_icrc1_ledger::UpgradeArgs;
 use ic_ledger_core::{tokens::Tokens, tokens::TOKEN_SUBDIVIDABLE_BY};
 use ic_nervous_system_common::{validate_proposal_url, NervousSystemError};
 
@@ -34,6 +37,9 @@ use std::{
 
 pub const DEFAULT_TRANSFER_FEE: Tokens = Tokens::from_e8s(10_000);
 
+/// The key under which the SNS ledger stores the token logo in its metadata.
+pub const LEDGER_LOGO_METADATA_KEY: &str = "icrc1:logo";
+
 pub const ONE_DAY_SECONDS: u64 = 24 * 60 * 60;
 pub const ONE_YEAR_SECONDS: u64 = (4 * 365 + 1) * ONE_DAY_SECONDS / 4;
 pub const ONE_MONTH_SECONDS: u64 = ONE_YEAR_SECONDS / 12;
@@ -78,6 +84,21 @@ pub mod native_action_ids {
 
     /// TransferSnsTreasuryFunds
     pub const TRANSFER_SNS_TREASURY_FUNDS: u64 = 9;
+
+    /// CreatePaymentStream Action.
+    pub const CREATE_PAYMENT_STREAM: u64 = 10;
+
+    /// CancelPaymentStream Action.
+    pub const CANCEL_PAYMENT_STREAM: u64 = 11;
+
+    /// MintSnsTokens Action.
+    pub const MINT_SNS_TOKENS: u64 = 12;
+
+    /// ManageLedgerParameters Action.
+    pub const MANAGE_LEDGER_PARAMETERS: u64 = 13;
+
+    /// OpenSaleRound Action.
+    pub const OPEN_SALE_ROUND: u64 = 14;
 }
 
 impl governance::Mode {
@@ -203,6 +224,42 @@ impl governance::Mode {
                 )
             )),
 
+            Action::CreatePaymentStream(_) => Err(GovernanceError::new_with_message(
+                ErrorType::PreconditionFailed,
+                format!(
+                    "CreatePaymentStream proposals are not allowed while \
+                        governance is in PreInitializationSwap mode: {:#?}",
+                    action
+                )
+            )),
+
+            Action::MintSnsTokens(_) => Err(GovernanceError::new_with_message(
+                ErrorType::PreconditionFailed,
+                format!(
+                    "MintSnsTokens proposals are not allowed while \
+                        governance is in PreInitializationSwap mode: {:#?}",
+                    action
+                )
+            )),
+
+            Action::ManageLedgerParameters(_) => Err(GovernanceError::new_with_message(
+                ErrorType::PreconditionFailed,
+                format!(
+                    "ManageLedgerParameters proposals are not allowed while \
+                        governance is in PreInitializationSwap mode: {:#?}",
+                    action
+                )
+            )),
+
+            Action::OpenSaleRound(_) => Err(GovernanceError::new_with_message(
+                ErrorType::PreconditionFailed,
+                format!(
+                    "OpenSaleRound proposals are not allowed while \
+                        governance is in PreInitializationSwap mode: {:#?}",
+                    action
+                )
+            )),
+
             _ => Ok(()),
         }
     }
@@ -358,6 +415,7 @@ impl NervousSystemParameters {
             voting_rewards_parameters: Some(VotingRewardsParameters::with_default_values()),
             max_dissolve_delay_bonus_percentage: Some(100),
             max_age_bonus_percentage: Some(25),
+            max_mint_sns_tokens_e8s_per_window: Some(0),
         }
     }
 
@@ -415,6 +473,9 @@ impl NervousSystemParameters {
             max_age_bonus_percentage: self
                 .max_age_bonus_percentage
                 .or(base.max_age_bonus_percentage),
+            max_mint_sns_tokens_e8s_per_window: self
+                .max_mint_sns_tokens_e8s_per_window
+                .or(base.max_mint_sns_tokens_e8s_per_window),
             voting_rewards_parameters: self
                 .voting_rewards_parameters
                 .clone()
@@ -1269,6 +1330,63 @@ impl From<&Action> for u64 {
             Action::ExecuteGenericNervousSystemFunction(proposal) => proposal.function_id,
             Action::ManageSnsMetadata(_) => native_action_ids::MANAGE_SNS_METADATA,
             Action::TransferSnsTreasuryFunds(_) => native_action_ids::TRANSFER_SNS_TREASURY_FUNDS,
+            Action::CreatePaymentStream(_) => native_action_ids::CREATE_PAYMENT_STREAM,
+            Action::CancelPaymentStream(_) => native_action_ids::CANCEL_PAYMENT_STREAM,
+            Action::MintSnsTokens(_) => native_action_ids::MINT_SNS_TOKENS,
+            Action::ManageLedgerParameters(_) => native_action_ids::MANAGE_LEDGER_PARAMETERS,
+            Action::OpenSaleRound(_) => native_action_ids::OPEN_SALE_ROUND,
+        }
+    }
+}
+
+impl From<&ManageLedgerParameters> for UpgradeArgs {
+    /// Converts the proposal into the arguments to upgrade the SNS ledger
+    /// with. The logo is the only custom metadata entry that governance sets.
+    fn from(manage_ledger_parameters: &ManageLedgerParameters) -> Self {
+        let ManageLedgerParameters {
+            transfer_fee,
+            token_name,
+            token_symbol,
+            token_logo,
+        } = manage_ledger_parameters.clone();
+
+        UpgradeArgs {
+            metadata: token_logo.map(|logo| vec![Value::entry(LEDGER_LOGO_METADATA_KEY, logo)]),
+            token_name,
+            token_symbol,
+            transfer_fee,
+            ..Default::default()
+        }
+    }
+}
+
+impl PaymentStream {
+    /// Returns whether the stream may still make payments, i.e., whether it
+    /// has neither been cancelled nor paid all of its periods.
+    pub fn is_active(&self) -> bool {
+        let num_periods = self
+            .terms
+            .as_ref()
+            .map(|terms| terms.num_periods)
+            .unwrap_or_default();
+        self.cancellation_timestamp_seconds.is_none() && self.paid_periods < num_periods
+    }
+
+    /// Returns the number of periods that are due at `now_seconds`, including
+    /// the periods that have already been paid. This is zero until the cliff
+    /// has been reached and never exceeds the stream's number of periods.
+    pub fn due_periods(&self, now_seconds: u64) -> u64 {
+        let terms = match &self.terms {
+            Some(terms) if terms.period_seconds > 0 => terms,
+            _ => return 0,
+        };
+        let elapsed_periods = (now_seconds.saturating_sub(self.start_timestamp_seconds)
+            / terms.period_seconds)
+            .min(terms.num_periods);
+        if elapsed_periods < terms.cliff_periods {
+            0
+        } else {
+            elapsed_periods
         }
     }
 }
@@ -1743,7 +1861,7 @@ pub(crate) mod tests {
         governance::Mode::PreInitializationSwap,
         nervous_system_function::{FunctionType, GenericNervousSystemFunction},
         neuron::Followees,
-        ExecuteGenericNervousSystemFunction, VotingRewardsParameters,
+        CreatePaymentStream, ExecuteGenericNervousSystemFunction, VotingRewardsParameters,
     };
     use ic_base_types::PrincipalId;
     use ic_nervous_system_common_test_keys::{TEST_USER1_PRINCIPAL, TEST_USER2_PRINCIPAL};
@@ -2077,11 +2195,16 @@ pub(crate) mod tests {
                 Action::UpgradeSnsControlledCanister       (Default::default()),
                 Action::AddGenericNervousSystemFunction    (Default::default()),
                 Action::RemoveGenericNervousSystemFunction (Default::default()),
+                Action::CancelPaymentStream                (Default::default()),
             ];
 
             let disallowed_in_pre_initialization_swap = vec! [
                 Action::ManageNervousSystemParameters(Default::default()),
-                Action::TransferSnsTreasuryFunds(Default::default())
+                Action::TransferSnsTreasuryFunds(Default::default()),
+                Action::CreatePaymentStream(Default::default()),
+                Action::MintSnsTokens(Default::default()),
+                Action::ManageLedgerParameters(Default::default()),
+                Action::OpenSaleRound(Default::default()),
             ];
 
             // Conditionally allow: No targetting SNS canisters.
@@ -2540,4 +2663,54 @@ pub(crate) mod tests {
         // so we should panic here.
         SnsMetadata::validate_logo("data:image/png;base64,aGVsbG8gZnJvbSBkZmluaXR5IQ==_").unwrap();
     }
+
+    #[test]
+    fn test_payment_stream_due_periods_respects_cliff_and_num_periods() {
+        let start = 1_000_000;
+        let period = ONE_DAY_SECONDS;
+        let stream = PaymentStream {
+            id: 1,
+            terms: Some(CreatePaymentStream {
+                period_seconds: period,
+                num_periods: 12,
+                cliff_periods: 3,
+                ..Default::default()
+            }),
+            start_timestamp_seconds: start,
+            ..Default::default()
+        };
+
+        assert_eq!(stream.due_periods(start - 1), 0);
+        assert_eq!(stream.due_periods(start), 0);
+        assert_eq!(stream.due_periods(start + 3 * period - 1), 0);
+        assert_eq!(stream.due_periods(start + 3 * period), 3);
+        assert_eq!(stream.due_periods(start + 5 * period + 1), 5);
+        assert_eq!(stream.due_periods(start + 100 * period), 12);
+    }
+
+    #[test]
+    fn test_payment_stream_is_active() {
+        let stream = PaymentStream {
+            id: 1,
+            terms: Some(CreatePaymentStream {
+                period_seconds: ONE_DAY_SECONDS,
+                num_periods: 2,
+                ..Default::default()
+            }),
+            paid_periods: 1,
+            ..Default::default()
+        };
+        assert!(stream.is_active());
+
+        assert!(!PaymentStream {
+            paid_periods: 2,
+            ..stream.clone()
+        }
+        .is_active());
+        assert!(!PaymentStream {
+            cancellation_timestamp_seconds: Some(42),
+            ..stream
+        }
+        .is_active());
+    }
 }
@@ -3,7 +3,7 @@ use futures::future::FutureExt;
 use ic_base_types::{CanisterId, PrincipalId};
 use ic_icrc1::{Account, Subaccount};
 use ic_ledger_core::Tokens;
-use ic_nervous_system_common::NervousSystemError;
+use ic_nervous_system_common::{ledger::TransferFundsError, NervousSystemError};
 use ic_sns_governance::{
     governance::{Governance, ValidGovernanceProto},
     ledger::ICRC1Ledger,
@@ -17,6 +17,7 @@ use ic_sns_governance::{
         neuron::{DissolveState, Followees},
         GetNeuron, Governance as GovernanceProto, GovernanceError, ManageNeuron,
         ManageNeuronResponse, NervousSystemParameters, Neuron, NeuronId, NeuronPermission,
+        PaymentStream,
     },
     types::{Environment, HeapGrowthPotential},
 };
@@ -109,6 +110,21 @@ impl ICRC1Ledger for LedgerFixture {
         Ok(ledger_fixture_state.block_height)
     }
 
+    async fn transfer_funds_idempotently(
+        &self,
+        amount_e8s: u64,
+        fee_e8s: u64,
+        from_subaccount: Option<Subaccount>,
+        to: Account,
+        memo: u64,
+        _created_at_time_nanos: u64,
+    ) -> Result<u64, TransferFundsError> {
+        // The fixture doesn't change any state when a transfer fails.
+        self.transfer_funds(amount_e8s, fee_e8s, from_subaccount, to, memo)
+            .await
+            .map_err(TransferFundsError::Rejected)
+    }
+
     async fn total_supply(&self) -> Result<Tokens, NervousSystemError> {
         let accounts = &mut self.ledger_fixture_state.try_lock().unwrap().accounts;
 
@@ -589,6 +605,14 @@ impl GovernanceCanisterFixture {
     pub fn get_nervous_system_parameters(&self) -> &NervousSystemParameters {
         self.governance.proto.parameters.as_ref().unwrap()
     }
+
+    pub fn get_payment_stream(&self, id: u64) -> &PaymentStream {
+        self.governance
+            .proto
+            .payment_streams
+            .get(&id)
+            .expect("Expected the payment stream to exist")
+    }
 }
 
 pub type LedgerTransform = Box<dyn FnOnce(Box<dyn ICRC1Ledger>) -> Box<dyn ICRC1Ledger>>;
@@ -771,6 +795,13 @@ impl GovernanceCanisterFixtureBuilder {
         self.governance.neurons.get(&neuron_id.to_string())
     }
 
+    pub fn add_payment_stream(mut self, payment_stream: PaymentStream) -> Self {
+        self.governance
+            .payment_streams
+            .insert(payment_stream.id, payment_stream);
+        self
+    }
+
     /// Transform the ledger just before it's built, e.g., to allow blocking on
     /// its calls for interleaving tests. Multiple transformations can be
     /// chained; they are applied in the order in which they were added.
@@ -1,5 +1,5 @@
 use crate::fixtures::{neuron_id, GovernanceCanisterFixtureBuilder, NeuronBuilder, TargetLedger};
-use ic_base_types::PrincipalId;
+use ic_base_types::{CanisterId, PrincipalId};
 use ic_nervous_system_common::E8;
 use ic_sns_governance::neuron::NeuronState;
 use ic_sns_governance::{
@@ -8,7 +8,8 @@ use ic_sns_governance::{
         manage_neuron,
         manage_neuron::{configure::Operation, Configure, Disburse, IncreaseDissolveDelay},
         manage_neuron_response::Command as CommandResponse,
-        Account, NeuronPermission,
+        transfer_sns_treasury_funds::TransferFrom,
+        Account, CreatePaymentStream, NeuronPermission, PaymentStream,
     },
     types::ONE_MONTH_SECONDS,
 };
@@ -143,3 +144,117 @@ async fn test_disburse_fails_when_state_is_not_dissolving() {
     };
     assert_eq!(error.error_type, ErrorType::PreconditionFailed as i32);
 }
+
+/// Tests that a payment stream pays nothing before its cliff, then pays all the
+/// periods that are due, and stops once all of its periods have been paid.
+#[test]
+fn test_payment_stream_pays_due_periods_from_the_treasury() {
+    let start_timestamp_seconds = 1_000_000_000;
+    let governance_principal = CanisterId::from_u64(0).get();
+    let recipient = PrincipalId::new_user_test_id(1000);
+    let recipient_account = ic_icrc1::Account {
+        owner: recipient,
+        subaccount: None,
+    };
+
+    let mut canister_fixture = GovernanceCanisterFixtureBuilder::new()
+        .set_start_time(start_timestamp_seconds)
+        .add_account_for(governance_principal, 100 * E8, TargetLedger::Icp)
+        .add_account_for(recipient, 0, TargetLedger::Icp)
+        .add_payment_stream(PaymentStream {
+            id: 1,
+            terms: Some(CreatePaymentStream {
+                from_treasury: TransferFrom::IcpTreasury.into(),
+                to_principal: Some(recipient),
+                to_subaccount: None,
+                amount_per_period_e8s: 10 * E8,
+                period_seconds: ONE_MONTH_SECONDS,
+                num_periods: 3,
+                cliff_periods: 2,
+                memo: None,
+            }),
+            start_timestamp_seconds,
+            ..Default::default()
+        })
+        .create();
+
+    // Before the cliff, nothing is paid.
+    canister_fixture
+        .advance_time_by(ONE_MONTH_SECONDS)
+        .run_periodic_tasks();
+    assert_eq!(
+        canister_fixture.get_account_balance(&recipient_account, TargetLedger::Icp),
+        0
+    );
+    assert_eq!(canister_fixture.get_payment_stream(1).paid_periods, 0);
+
+    // Once the cliff is reached, the periods accrued so far are paid at once.
+    canister_fixture
+        .advance_time_by(ONE_MONTH_SECONDS)
+        .run_periodic_tasks();
+    assert_eq!(
+        canister_fixture.get_account_balance(&recipient_account, TargetLedger::Icp),
+        20 * E8
+    );
+    assert_eq!(canister_fixture.get_payment_stream(1).paid_periods, 2);
+
+    // Running the periodic tasks again in the same period pays nothing more.
+    canister_fixture.run_periodic_tasks();
+    assert_eq!(
+        canister_fixture.get_account_balance(&recipient_account, TargetLedger::Icp),
+        20 * E8
+    );
+
+    // The stream stops after its last period.
+    canister_fixture
+        .advance_time_by(10 * ONE_MONTH_SECONDS)
+        .run_periodic_tasks();
+    assert_eq!(
+        canister_fixture.get_account_balance(&recipient_account, TargetLedger::Icp),
+        30 * E8
+    );
+    let payment_stream = canister_fixture.get_payment_stream(1);
+    assert_eq!(payment_stream.paid_periods, 3);
+    assert_eq!(payment_stream.last_payment_error, None);
+    assert!(!payment_stream.is_active());
+}
+
+/// Tests that a failed payment is recorded on the stream and that its periods
+/// remain unpaid, so that they can be paid by a later attempt.
+#[test]
+fn test_payment_stream_records_failed_payments() {
+    let start_timestamp_seconds = 1_000_000_000;
+    let recipient = PrincipalId::new_user_test_id(1000);
+
+    // The treasury has no account on the ICP ledger, so transfers fail.
+    let mut canister_fixture = GovernanceCanisterFixtureBuilder::new()
+        .set_start_time(start_timestamp_seconds)
+        .add_account_for(recipient, 0, TargetLedger::Icp)
+        .add_payment_stream(PaymentStream {
+            id: 1,
+            terms: Some(CreatePaymentStream {
+                from_treasury: TransferFrom::IcpTreasury.into(),
+                to_principal: Some(recipient),
+                to_subaccount: None,
+                amount_per_period_e8s: 10 * E8,
+                period_seconds: ONE_MONTH_SECONDS,
+                num_periods: 3,
+                cliff_periods: 0,
+                memo: None,
+            }),
+            start_timestamp_seconds,
+            ..Default::default()
+        })
+        .create();
+
+    canister_fixture
+        .advance_time_by(ONE_MONTH_SECONDS)
+        .run_periodic_tasks();
+
+    let now = canister_fixture.now();
+    let payment_stream = canister_fixture.get_payment_stream(1);
+    assert_eq!(payment_stream.paid_periods, 0);
+    assert!(payment_stream.last_payment_error.is_some());
+    assert_eq!(payment_stream.last_payment_failure_timestamp_seconds, now);
+    assert!(payment_stream.is_active());
+}
@@ -117,6 +117,18 @@ pub struct SnsInitPayload {
     /// <https://wiki.internetcomputer.org/wiki/Network_Nervous_System#Proposal_decision_and_wait-for-quiet>
     #[prost(uint64, optional, tag = "22")]
     pub wait_for_quiet_deadline_increase_seconds: ::core::option::Option<u64>,
+    /// The ISO 3166-1 alpha-2 codes of the countries whose residents cannot
+    /// participate in the swap. If empty, the swap does not restrict countries.
+    #[prost(string, repeated, tag = "23")]
+    pub restricted_countries: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// The SHA-256 hash of a text that participants in the swap must confirm,
+    /// e.g., the terms of the swap. If not set, participants need not confirm
+    /// any text.
+    #[prost(bytes = "vec", optional, tag = "24")]
+    pub confirmation_text_sha256: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    /// If not empty, only these principals can participate in the swap.
+    #[prost(string, repeated, tag = "25")]
+    pub allowed_participant_principal_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
     /// The initial tokens and neurons available at genesis will be distributed according
     /// to the strategy and configuration picked via the initial_token_distribution
     /// parameter.
@@ -24,7 +24,7 @@ use ic_sns_governance::pb::v1::{
 };
 use ic_sns_governance::types::DEFAULT_TRANSFER_FEE;
 use ic_sns_root::pb::v1::SnsRootCanister;
-use ic_sns_swap::pb::v1::Init as SwapInit;
+use ic_sns_swap::pb::v1::{Countries, Init as SwapInit};
 use lazy_static::lazy_static;
 use maplit::{btreemap, hashset};
 use std::collections::{BTreeMap, HashSet};
@@ -120,6 +120,9 @@ impl SnsInitPayload {
                 .initial_voting_period_seconds,
             wait_for_quiet_deadline_increase_seconds: nervous_system_parameters_default
                 .wait_for_quiet_deadline_increase_seconds,
+            restricted_countries: vec![],
+            confirmation_text_sha256: None,
+            allowed_participant_principal_ids: vec![],
         }
     }
 
@@ -256,6 +259,7 @@ impl SnsInitPayload {
                 cycles_for_archive_creation: Some(10_000_000_000_000),
                 max_transactions_per_response: None,
             },
+            fee_collector_account: None,
         };
 
         Ok(payload)
@@ -278,6 +282,7 @@ impl SnsInitPayload {
             archive_canister_ids: vec![],
             latest_ledger_archive_poll_timestamp_seconds: None,
             index_canister_id: Some(sns_canister_ids.index),
+            sale_round_swap_canister_ids: vec![],
         }
     }
 
@@ -297,6 +302,25 @@ impl SnsInitPayload {
 
             transaction_fee_e8s: self.transaction_fee_e8s,
             neuron_minimum_stake_e8s: self.neuron_minimum_stake_e8s,
+
+            restricted_countries: self.swap_restricted_countries(),
+            confirmation_text_sha256: self.confirmation_text_sha256.clone(),
+            allowed_participant_principal_ids: self.allowed_participant_principal_ids.clone(),
+
+            // The initial decentralization swap is never a follow-up sale round.
+            follow_up_sale_round: None,
+        }
+    }
+
+    /// Returns the countries whose residents cannot participate in the swap,
+    /// or None if the swap does not restrict countries.
+    fn swap_restricted_countries(&self) -> Option<Countries> {
+        if self.restricted_countries.is_empty() {
+            None
+        } else {
+            Some(Countries {
+                iso_codes: self.restricted_countries.clone(),
+            })
         }
     }
 
@@ -358,6 +382,9 @@ impl SnsInitPayload {
             max_age_bonus_percentage,
             initial_voting_period_seconds,
             wait_for_quiet_deadline_increase_seconds,
+            restricted_countries: _,
+            confirmation_text_sha256: _,
+            allowed_participant_principal_ids: _,
         } = self.clone();
 
         let voting_rewards_parameters = Some(VotingRewardsParameters {
@@ -420,6 +447,7 @@ impl SnsInitPayload {
             self.validate_max_age_bonus_percentage(),
             self.validate_initial_voting_period_seconds(),
             self.validate_wait_for_quiet_deadline_increase_seconds(),
+            self.validate_swap_participation_restrictions(),
         ];
 
         let defect_msg = validation_fns
@@ -655,6 +683,17 @@ impl SnsInitPayload {
         Ok(())
     }
 
+    fn validate_swap_participation_restrictions(&self) -> Result<(), String> {
+        SwapInit {
+            restricted_countries: self.swap_restricted_countries(),
+            confirmation_text_sha256: self.confirmation_text_sha256.clone(),
+            allowed_participant_principal_ids: self.allowed_participant_principal_ids.clone(),
+            ..Default::default()
+        }
+        .validate_participation_restrictions()
+        .map_err(|e| format!("Error: {}", e))
+    }
+
     fn validate_logo(&self) -> Result<(), String> {
         if let Some(logo) = &self.logo {
             SnsMetadata::validate_logo(logo)?;
@@ -837,10 +876,11 @@ mod test {
         FractionalDeveloperVotingPower, SnsCanisterIds, SnsInitPayload, MAX_TOKEN_NAME_LENGTH,
         MAX_TOKEN_SYMBOL_LENGTH,
     };
-    use ic_base_types::CanisterId;
+    use ic_base_types::{CanisterId, PrincipalId};
     use ic_icrc1::Account;
     use ic_sns_governance::governance::ValidGovernanceProto;
     use ic_sns_governance::pb::v1::governance::SnsMetadata;
+    use ic_sns_swap::pb::v1::Countries;
 
     fn create_canister_ids() -> SnsCanisterIds {
         SnsCanisterIds {
@@ -1068,6 +1108,52 @@ mod test {
         assert!(swap.validate().is_ok());
     }
 
+    #[test]
+    fn test_swap_init_args_restrict_participation() {
+        let sns_init_payload = SnsInitPayload {
+            restricted_countries: vec!["CH".to_string()],
+            confirmation_text_sha256: Some(vec![42; 32]),
+            allowed_participant_principal_ids: vec![PrincipalId::new_user_test_id(1).to_string()],
+            ..SnsInitPayload::with_valid_values_for_testing()
+        };
+        assert!(sns_init_payload.validate().is_ok());
+
+        let swap = sns_init_payload
+            .build_canister_payloads(&create_canister_ids(), None)
+            .expect("Expected SnsInitPayload to be a valid payload")
+            .swap;
+        assert!(swap.validate().is_ok());
+        assert_eq!(
+            swap.restricted_countries,
+            Some(Countries {
+                iso_codes: vec!["CH".to_string()]
+            })
+        );
+        assert_eq!(swap.confirmation_text_sha256, Some(vec![42; 32]));
+        assert_eq!(
+            swap.allowed_participant_principal_ids,
+            vec![PrincipalId::new_user_test_id(1).to_string()]
+        );
+
+        // The swap restrictions are validated along with the rest of the payload.
+        for invalid_sns_init_payload in [
+            SnsInitPayload {
+                restricted_countries: vec!["Switzerland".to_string()],
+                ..sns_init_payload.clone()
+            },
+            SnsInitPayload {
+                confirmation_text_sha256: Some(vec![]),
+                ..sns_init_payload.clone()
+            },
+            SnsInitPayload {
+                allowed_participant_principal_ids: vec!["not a principal".to_string()],
+                ..sns_init_payload
+            },
+        ] {
+            assert!(invalid_sns_init_payload.validate().is_err());
+        }
+    }
+
     #[test]
     fn test_ledger_init_args_is_valid() {
         // Build an sns_init_payload with defaults for non-ledger related configuration.
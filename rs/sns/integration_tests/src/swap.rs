@@ -1,5 +1,6 @@
 use candid::{types::number::Nat, Decode, Encode};
 use ic_base_types::{CanisterId, PrincipalId};
+use ic_crypto_sha::Sha256;
 use ic_ic00_types::{CanisterSettingsArgs, UpdateSettingsArgs};
 use ic_icrc1::Account;
 use ic_ledger_core::Tokens;
@@ -94,7 +95,16 @@ fn set_controllers(
 ) {
     let request = Encode!(&UpdateSettingsArgs {
         canister_id: target.into(),
-        settings: CanisterSettingsArgs::new(None, Some(controllers), None, None, None,),
+        settings: CanisterSettingsArgs::new(
+            None,
+            Some(controllers),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
     })
     .unwrap();
 
@@ -172,6 +182,33 @@ fn begin_swap(
     /* community_fund_nns_neurons */ Vec<nns_governance_pb::Neuron>,
     FractionalDeveloperVotingPower,
     /* dapp_canister_id */ CanisterId,
+) {
+    begin_swap_with_customized_sns_init_payload(
+        state_machine,
+        accounts,
+        additional_nns_neurons,
+        planned_participation_amount_per_account,
+        planned_community_fund_participation_amount,
+        neuron_basket_count,
+        |_sns_init_payload| {},
+    )
+}
+
+/// Like `begin_swap`, but `customize_sns_init_payload` gets to modify the
+/// SnsInitPayload before it is used to deploy the SNS.
+fn begin_swap_with_customized_sns_init_payload(
+    state_machine: &mut StateMachine,
+    accounts: &[ic_base_types::PrincipalId],
+    additional_nns_neurons: &[nns_governance_pb::Neuron],
+    planned_participation_amount_per_account: ExplosiveTokens,
+    planned_community_fund_participation_amount: ExplosiveTokens,
+    neuron_basket_count: u64,
+    customize_sns_init_payload: impl FnOnce(&mut SnsInitPayload),
+) -> (
+    SnsCanisterIds,
+    /* community_fund_nns_neurons */ Vec<nns_governance_pb::Neuron>,
+    FractionalDeveloperVotingPower,
+    /* dapp_canister_id */ CanisterId,
 ) {
     let num_accounts = accounts.len().max(1) as u64;
     // Give TEST_USER2 and everyone in `accounts` some ICP so that they can buy into the swap.
@@ -288,6 +325,7 @@ fn begin_swap(
         Some(InitialTokenDistribution::FractionalDeveloperVotingPower(
             fractional_developer_voting_power.clone(),
         ));
+    customize_sns_init_payload(&mut sns_init_payload);
     let cycle_count = EXPECTED_SNS_CREATION_FEE;
     let wallet_canister = set_up_universal_canister(state_machine, Some(Cycles::new(cycle_count)));
     let deploy_new_sns_response = deploy_new_sns(
@@ -1211,6 +1249,90 @@ fn swap_lifecycle_sad() {
     assert_community_fund_neuron_maturities(&mut state_machine, &[zero, zero]);
 }
 
+#[test]
+fn swap_participation_restrictions() {
+    // Step 1: Prepare the world.
+    let confirmation_text = "I am not a resident of a restricted country.";
+    let mut state_machine = StateMachine::new();
+    let (sns_canister_ids, _community_fund_neurons, _fractional_developer_voting_power, _dapp) =
+        begin_swap_with_customized_sns_init_payload(
+            &mut state_machine,
+            &[], // accounts
+            &[], // additional_nns_neurons
+            ExplosiveTokens::from_e8s(70 * E8),
+            ExplosiveTokens::from_e8s(30 * E8),
+            3, // neuron_basket_count
+            |sns_init_payload| {
+                sns_init_payload.restricted_countries = vec!["CH".to_string()];
+                sns_init_payload.confirmation_text_sha256 =
+                    Some(Sha256::hash(confirmation_text.as_bytes()).to_vec());
+                sns_init_payload.allowed_participant_principal_ids =
+                    vec![TEST_USER2_PRINCIPAL.to_string()];
+            },
+        );
+    let swap_canister_id: CanisterId = sns_canister_ids.swap.unwrap().try_into().unwrap();
+    let amount = ExplosiveTokens::from_e8s(E8 * 5 / 4);
+    send_participation_funds(
+        &mut state_machine,
+        swap_canister_id,
+        *TEST_USER2_PRINCIPAL,
+        amount,
+    );
+
+    // Step 2: Run code under test.
+    let mut refresh_buyer_tokens =
+        |buyer: PrincipalId, country_code: Option<&str>, confirmation_text: Option<&str>| {
+            swap_refresh_buyer_tokens(
+                &mut state_machine,
+                swap_canister_id,
+                &swap_pb::RefreshBuyerTokensRequest {
+                    buyer: buyer.to_string(),
+                    country_code: country_code.map(String::from),
+                    confirmation_text: confirmation_text.map(String::from),
+                },
+            )
+        };
+
+    // Step 3: Inspect results.
+
+    // Principals that are not on the allowlist are rejected.
+    let err = refresh_buyer_tokens(*TEST_USER1_PRINCIPAL, Some("US"), Some(confirmation_text))
+        .unwrap_err();
+    assert!(err.contains("is not allowed to participate"), "{}", err);
+
+    // The country of residence must be declared, and must not be restricted.
+    let err =
+        refresh_buyer_tokens(*TEST_USER2_PRINCIPAL, None, Some(confirmation_text)).unwrap_err();
+    assert!(err.contains("must declare the country"), "{}", err);
+    let err = refresh_buyer_tokens(*TEST_USER2_PRINCIPAL, Some("CH"), Some(confirmation_text))
+        .unwrap_err();
+    assert!(err.contains("cannot participate"), "{}", err);
+
+    // The confirmation text must match.
+    let err =
+        refresh_buyer_tokens(*TEST_USER2_PRINCIPAL, Some("US"), Some("I agree.")).unwrap_err();
+    assert!(err.contains("confirmation text does not match"), "{}", err);
+
+    // Once all restrictions are satisfied, participation is accepted.
+    let response =
+        refresh_buyer_tokens(*TEST_USER2_PRINCIPAL, Some("US"), Some(confirmation_text)).unwrap();
+    assert_eq!(response.icp_accepted_participation_e8s, amount.into_e8s());
+
+    let swap = swap_get_state(
+        &mut state_machine,
+        swap_canister_id,
+        &swap_pb::GetStateRequest {},
+    )
+    .swap
+    .unwrap();
+    assert_eq!(swap.buyers.len(), 1, "{:#?}", swap.buyers);
+    assert!(
+        swap.buyers.contains_key(&TEST_USER2_PRINCIPAL.to_string()),
+        "{:#?}",
+        swap.buyers
+    );
+}
+
 fn participate_in_swap(
     state_machine: &mut StateMachine,
     swap_canister_id: CanisterId,
@@ -1226,16 +1348,16 @@ fn participate_in_swap(
     );
 
     // ... then, swap must be notified about that transfer.
-    state_machine
-        .execute_ingress(
-            swap_canister_id,
-            "refresh_buyer_tokens",
-            Encode!(&swap_pb::RefreshBuyerTokensRequest {
-                buyer: participant_principal_id.to_string(),
-            })
-            .unwrap(),
-        )
-        .unwrap();
+    swap_refresh_buyer_tokens(
+        state_machine,
+        swap_canister_id,
+        &swap_pb::RefreshBuyerTokensRequest {
+            buyer: participant_principal_id.to_string(),
+            country_code: None,
+            confirmation_text: None,
+        },
+    )
+    .unwrap();
 }
 
 fn send_participation_funds(
@@ -1398,6 +1520,25 @@ fn ledger_account_balance(
     Decode!(&result, Tokens).unwrap()
 }
 
+fn swap_refresh_buyer_tokens(
+    state_machine: &mut StateMachine,
+    swap_canister_id: CanisterId,
+    request: &swap_pb::RefreshBuyerTokensRequest,
+) -> Result<swap_pb::RefreshBuyerTokensResponse, String> {
+    let result = state_machine
+        .execute_ingress(
+            swap_canister_id,
+            "refresh_buyer_tokens",
+            Encode!(request).unwrap(),
+        )
+        .map_err(|err| format!("{:?}", err))?;
+    let result = match result {
+        WasmResult::Reply(reply) => reply,
+        WasmResult::Reject(reject) => return Err(reject),
+    };
+    Ok(Decode!(&result, swap_pb::RefreshBuyerTokensResponse).unwrap())
+}
+
 fn swap_get_state(
     state_machine: &mut StateMachine,
     swap_canister_id: CanisterId,
@@ -113,6 +113,7 @@ fn test_upgrade_canister_proposal_is_successful() {
                 UpgradeSnsControlledCanister {
                     canister_id: Some(dapp_canister.canister_id().get()),
                     new_canister_wasm: new_dapp_wasm,
+                    canister_upgrade_arg: None,
                 },
             )),
             ..Default::default()
@@ -254,6 +255,7 @@ fn test_upgrade_canister_proposal_execution_fail() {
                 UpgradeSnsControlledCanister {
                     canister_id: Some(dapp_canister.canister_id().get()),
                     new_canister_wasm: new_dapp_wasm,
+                    canister_upgrade_arg: None,
                 },
             )),
             ..Default::default()
@@ -441,6 +443,7 @@ fn test_upgrade_after_state_shrink() {
                 UpgradeSnsControlledCanister {
                     canister_id: Some(sns_canisters.governance.canister_id().into()),
                     new_canister_wasm: governance_wasm,
+                    canister_upgrade_arg: None,
                 },
             )),
             ..Default::default()
@@ -12,16 +12,17 @@ use ic_nervous_system_common::stable_mem_utils::{
 use ic_nervous_system_root::{ChangeCanisterProposal, LOG_PREFIX};
 use ic_sns_root::{
     pb::v1::{
-        CanisterCallError, ListSnsCanistersRequest, ListSnsCanistersResponse,
-        RegisterDappCanisterRequest, RegisterDappCanisterResponse, SetDappControllersRequest,
-        SetDappControllersResponse, SnsRootCanister,
+        CanisterCallError, CreateSwapCanisterRequest, CreateSwapCanisterResponse,
+        ListSnsCanistersRequest, ListSnsCanistersResponse, RegisterDappCanisterRequest,
+        RegisterDappCanisterResponse, SetDappControllersRequest, SetDappControllersResponse,
+        SnsRootCanister,
     },
-    CanisterIdRecord, CanisterStatusResultV2, EmptyBlob, GetSnsCanistersSummaryRequest,
-    GetSnsCanistersSummaryResponse, LedgerCanisterClient, ManagementCanisterClient,
-    UpdateSettingsArgs,
+    CanisterIdRecord, CanisterStatusResultV2, CreateCanisterArgs, EmptyBlob,
+    GetSnsCanistersSummaryRequest, GetSnsCanistersSummaryResponse, InstallCodeArgs,
+    LedgerCanisterClient, ManagementCanisterClient, UpdateSettingsArgs,
 };
 
-use dfn_core::api::{call_bytes_with_cleanup, Funds};
+use dfn_core::api::{call_bytes_with_cleanup, call_with_funds_and_cleanup, Funds};
 use prost::Message;
 
 #[cfg(target_arch = "wasm32")]
@@ -86,6 +87,31 @@ impl ManagementCanisterClient for RealManagementCanisterClient {
             .await
             .map_err(CanisterCallError::from)
     }
+
+    async fn create_canister(
+        &mut self,
+        args: &CreateCanisterArgs,
+        cycles: u64,
+    ) -> Result<CanisterIdRecord, CanisterCallError> {
+        call_with_funds_and_cleanup(
+            IC_00,
+            "create_canister",
+            candid_one,
+            args,
+            Funds::new(cycles),
+        )
+        .await
+        .map_err(CanisterCallError::from)
+    }
+
+    async fn install_code(
+        &mut self,
+        args: &InstallCodeArgs,
+    ) -> Result<EmptyBlob, CanisterCallError> {
+        call(IC_00, "install_code", candid_one, args)
+            .await
+            .map_err(CanisterCallError::from)
+    }
 }
 
 /// An implementation of the LedgerCanisterClient trait that is suitable for
@@ -321,6 +347,31 @@ async fn set_dapp_controllers_(request: SetDappControllersRequest) -> SetDappCon
     .await
 }
 
+/// Creates the swap canister of a follow-up sale round.
+///
+/// Caller must be the governance canister. Otherwise, the request will be
+/// rejected.
+///
+/// The new canister is controlled by this canister (SNS root), and runs the
+/// Wasm module given in the request.
+#[export_name = "canister_update create_swap_canister"]
+fn create_swap_canister() {
+    println!("{}create_swap_canister", LOG_PREFIX);
+    over_async(candid_one, create_swap_canister_);
+}
+
+#[candid_method(update, rename = "create_swap_canister")]
+async fn create_swap_canister_(request: CreateSwapCanisterRequest) -> CreateSwapCanisterResponse {
+    SnsRootCanister::create_swap_canister(
+        &STATE,
+        &mut RealManagementCanisterClient::new(),
+        dfn_core::api::id(),
+        dfn_core::api::caller(),
+        request,
+    )
+    .await
+}
+
 fn assert_state_is_valid(state: &SnsRootCanister) {
     assert!(state.governance_canister_id.is_some());
     assert!(state.ledger_canister_id.is_some());
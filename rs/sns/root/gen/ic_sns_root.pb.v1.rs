@@ -44,6 +44,11 @@ pub struct SnsRootCanister {
     /// The SNS Index canister ID
     #[prost(message, optional, tag = "7")]
     pub index_canister_id: ::core::option::Option<::ic_base_types::PrincipalId>,
+    /// The swap canisters of follow-up sale rounds. Unlike the swap canister of
+    /// the initial decentralization swap, these are created and controlled by
+    /// this canister (see create_swap_canister).
+    #[prost(message, repeated, tag = "8")]
+    pub sale_round_swap_canister_ids: ::prost::alloc::vec::Vec<::ic_base_types::PrincipalId>,
 }
 #[derive(
     candid::CandidType,
@@ -161,4 +166,40 @@ pub struct ListSnsCanistersResponse {
     pub archives: ::prost::alloc::vec::Vec<::ic_base_types::PrincipalId>,
     #[prost(message, optional, tag = "7")]
     pub index: ::core::option::Option<::ic_base_types::PrincipalId>,
+    #[prost(message, repeated, tag = "8")]
+    pub sale_round_swaps: ::prost::alloc::vec::Vec<::ic_base_types::PrincipalId>,
+}
+/// Request struct for the CreateSwapCanister API on the SNS Root canister.
+/// Only SNS governance may call this method, which it does when executing an
+/// OpenSaleRound proposal. Root creates a new canister on its own subnet,
+/// controlled by root, and installs the given swap Wasm module in it.
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct CreateSwapCanisterRequest {
+    /// The Wasm module of the swap canister.
+    #[prost(bytes = "vec", tag = "1")]
+    pub wasm_module: ::prost::alloc::vec::Vec<u8>,
+    /// The candid-encoded init argument of the swap canister.
+    #[prost(bytes = "vec", tag = "2")]
+    pub arg: ::prost::alloc::vec::Vec<u8>,
+}
+/// Response struct for the CreateSwapCanister API on the SNS Root canister.
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct CreateSwapCanisterResponse {
+    /// The ID of the newly created swap canister.
+    #[prost(message, optional, tag = "1")]
+    pub canister_id: ::core::option::Option<::ic_base_types::PrincipalId>,
 }
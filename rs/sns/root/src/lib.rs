@@ -2,14 +2,16 @@ pub mod pb;
 pub mod types;
 
 use crate::pb::v1::{
-    set_dapp_controllers_response, CanisterCallError, ListSnsCanistersResponse,
-    RegisterDappCanisterRequest, RegisterDappCanisterResponse, SetDappControllersRequest,
-    SetDappControllersResponse, SnsRootCanister,
+    set_dapp_controllers_response, CanisterCallError, CreateSwapCanisterRequest,
+    CreateSwapCanisterResponse, ListSnsCanistersResponse, RegisterDappCanisterRequest,
+    RegisterDappCanisterResponse, SetDappControllersRequest, SetDappControllersResponse,
+    SnsRootCanister,
 };
 use async_trait::async_trait;
 use candid::{CandidType, Decode, Deserialize, Encode};
 use dfn_core::CanisterId;
 use ic_base_types::{NumBytes, PrincipalId};
+use ic_ic00_types::CanisterInstallMode;
 use ic_icrc1::endpoints::ArchiveInfo;
 use ic_nervous_system_root::LOG_PREFIX;
 use ic_sns_swap::pb::v1::GetCanisterStatusRequest;
@@ -22,6 +24,10 @@ use dfn_core::println;
 
 const ONE_DAY_SECONDS: u64 = 24 * 60 * 60;
 
+/// The number of cycles that SNS root attaches when creating the swap canister
+/// of a follow-up sale round (see `SnsRootCanister::create_swap_canister`).
+pub const SWAP_CANISTER_CREATION_CYCLES: u64 = 3_000_000_000_000;
+
 /// Begin Local Copy of Various Candid Type definitions from ic00_types
 ///
 /// This is the standard practice; this allows the Candid interface to evolve
@@ -105,6 +111,8 @@ impl CanisterStatusResultV2 {
                 compute_allocation,
                 memory_allocation,
                 freezing_threshold,
+                None,
+                None,
             ),
             freezing_threshold: candid::Nat::from(freezing_threshold),
             idle_cycles_burned_per_day: candid::Nat::from(idle_cycles_burned_per_day),
@@ -216,6 +224,28 @@ pub struct CanisterSettingsArgs {
     pub freezing_threshold: Option<candid::Nat>,
 }
 
+/// Struct used for encoding/decoding
+/// `(record {
+///     settings : opt canister_settings;
+/// })`
+#[derive(PartialEq, Eq, Default, Clone, CandidType, Deserialize, Debug)]
+pub struct CreateCanisterArgs {
+    pub settings: Option<CanisterSettingsArgs>,
+}
+
+/// Struct used for encoding/decoding the argument of `install_code`. The
+/// optional `wasm_module_hash` field is left out, as it is not needed here.
+#[derive(PartialEq, Eq, Clone, CandidType, Deserialize, Debug)]
+pub struct InstallCodeArgs {
+    pub mode: CanisterInstallMode,
+    pub canister_id: PrincipalId,
+    pub wasm_module: Vec<u8>,
+    pub arg: Vec<u8>,
+    pub compute_allocation: Option<candid::Nat>,
+    pub memory_allocation: Option<candid::Nat>,
+    pub query_allocation: Option<candid::Nat>,
+}
+
 /// End ic00_type copies
 
 impl From<(Option<i32>, String)> for CanisterCallError {
@@ -239,6 +269,19 @@ pub trait ManagementCanisterClient {
         &mut self,
         settings: &UpdateSettingsArgs,
     ) -> Result<EmptyBlob, CanisterCallError>;
+
+    /// Creates a new canister on the same subnet as the caller, paying for it
+    /// with `cycles` from the caller's balance.
+    async fn create_canister(
+        &mut self,
+        args: &CreateCanisterArgs,
+        cycles: u64,
+    ) -> Result<CanisterIdRecord, CanisterCallError>;
+
+    async fn install_code(
+        &mut self,
+        args: &InstallCodeArgs,
+    ) -> Result<EmptyBlob, CanisterCallError>;
 }
 
 // TODO NNS1-1593: Use a common icrc1 trait
@@ -282,6 +325,7 @@ pub struct GetSnsCanistersSummaryResponse {
     pub dapps: Vec<CanisterSummary>,
     pub archives: Vec<CanisterSummary>,
     pub index: Option<CanisterSummary>,
+    pub sale_round_swaps: Vec<CanisterSummary>,
 }
 
 impl GetSnsCanistersSummaryResponse {
@@ -312,6 +356,10 @@ impl GetSnsCanistersSummaryResponse {
     pub fn index_canister_summary(&self) -> &CanisterSummary {
         self.index.as_ref().unwrap()
     }
+
+    pub fn sale_round_swap_canister_summaries(&self) -> &Vec<CanisterSummary> {
+        &self.sale_round_swaps
+    }
 }
 
 #[derive(PartialEq, Eq, Debug, candid::CandidType, candid::Deserialize)]
@@ -389,6 +437,7 @@ impl SnsRootCanister {
             dapp_canister_ids,
             archive_canister_ids,
             index_canister_id,
+            sale_round_swap_canister_ids,
         ) = self_ref.with(|self_ref| {
             let self_ref = self_ref.borrow();
             (
@@ -398,6 +447,7 @@ impl SnsRootCanister {
                 self_ref.dapp_canister_ids.clone(),
                 self_ref.archive_canister_ids.clone(),
                 self_ref.index_canister_id(),
+                self_ref.sale_round_swap_canister_ids.clone(),
             )
         });
 
@@ -444,6 +494,16 @@ impl SnsRootCanister {
             archive_canister_summaries.push(archive_summary);
         }
 
+        // Get status of the swap canister(s) of follow-up sale rounds. Unlike
+        // the swap canister of the initial swap, these are owned by root.
+        let mut sale_round_swap_canister_summaries = vec![];
+        for sale_round_swap_canister_id in sale_round_swap_canister_ids {
+            let sale_round_swap_summary =
+                get_owned_canister_summary(management_canister_client, sale_round_swap_canister_id)
+                    .await;
+            sale_round_swap_canister_summaries.push(sale_round_swap_summary);
+        }
+
         GetSnsCanistersSummaryResponse {
             root: root_canister_summary,
             governance: governance_canister_summary,
@@ -452,6 +512,7 @@ impl SnsRootCanister {
             dapps: dapp_canister_summaries,
             archives: archive_canister_summaries,
             index: index_canister_summary,
+            sale_round_swaps: sale_round_swap_canister_summaries,
         }
     }
 
@@ -467,6 +528,7 @@ impl SnsRootCanister {
             dapps: self.dapp_canister_ids.clone(),
             archives: self.archive_canister_ids.clone(),
             index: self.index_canister_id,
+            sale_round_swaps: self.sale_round_swap_canister_ids.clone(),
         }
     }
 
@@ -501,6 +563,7 @@ impl SnsRootCanister {
             ]
             .into_iter()
             .chain(s.archive_canister_ids.clone())
+            .chain(s.sale_round_swap_canister_ids.clone())
             .collect()
         });
         if sns_canister_ids.contains(&query_canister_id) {
@@ -653,6 +716,77 @@ impl SnsRootCanister {
         SetDappControllersResponse { failed_updates }
     }
 
+    /// Creates the swap canister of a follow-up sale round.
+    ///
+    /// Caller must be the governance canister. Otherwise, the request will be
+    /// rejected.
+    ///
+    /// The new canister lives on the same subnet as this canister (SNS root),
+    /// is exclusively controlled by it, and runs the Wasm module given in the
+    /// request. Unlike the swap canister of the initial decentralization swap,
+    /// it is owned by this canister, and recorded in
+    /// `sale_round_swap_canister_ids`, so that it shows up in the canister
+    /// summary and is upgraded along with the other SNS canisters.
+    pub async fn create_swap_canister(
+        self_ref: &'static LocalKey<RefCell<Self>>,
+        management_canister_client: &mut impl ManagementCanisterClient,
+        own_canister_id: CanisterId,
+        caller: PrincipalId,
+        request: CreateSwapCanisterRequest,
+    ) -> CreateSwapCanisterResponse {
+        let is_authorized =
+            self_ref.with(|self_ref| caller == self_ref.borrow().governance_canister_id());
+        assert!(is_authorized, "Caller ({caller}) is not authorized.");
+
+        let CreateSwapCanisterRequest { wasm_module, arg } = request;
+        assert!(
+            !wasm_module.is_empty(),
+            "Invalid CreateSwapCanisterRequest: wasm_module must not be empty."
+        );
+
+        let create_canister_args = CreateCanisterArgs {
+            settings: Some(CanisterSettingsArgs {
+                controllers: Some(vec![own_canister_id.into()]),
+                ..Default::default()
+            }),
+        };
+        let swap_canister_id = management_canister_client
+            .create_canister(&create_canister_args, SWAP_CANISTER_CREATION_CYCLES)
+            .await
+            .unwrap_or_else(|err| panic!("Unable to create a new swap canister: {err:#?}"))
+            .get_canister_id();
+
+        // From now on, a canister exists, so make sure that its ID shows up
+        // in the logs and in our state, even if we are unable to install code
+        // in it.
+        println!("{LOG_PREFIX}Created swap canister {swap_canister_id} for a sale round.");
+        self_ref.with(|self_ref| {
+            self_ref
+                .borrow_mut()
+                .sale_round_swap_canister_ids
+                .push(swap_canister_id.into())
+        });
+        let install_code_args = InstallCodeArgs {
+            mode: CanisterInstallMode::Install,
+            canister_id: swap_canister_id.into(),
+            wasm_module,
+            arg,
+            compute_allocation: None,
+            memory_allocation: None,
+            query_allocation: None,
+        };
+        management_canister_client
+            .install_code(&install_code_args)
+            .await
+            .unwrap_or_else(|err| {
+                panic!("Unable to install code in the new swap canister ({swap_canister_id}): {err:#?}")
+            });
+
+        CreateSwapCanisterResponse {
+            canister_id: Some(swap_canister_id.into()),
+        }
+    }
+
     /// Runs periodic tasks that are not directly triggered by user input.
     pub async fn run_periodic_tasks(
         self_ref: &'static LocalKey<RefCell<Self>>,
@@ -883,6 +1017,15 @@ mod tests {
             update_settings_args: UpdateSettingsArgs,
             result: Result<EmptyBlob, CanisterCallError>,
         },
+        CreateCanister {
+            create_canister_args: CreateCanisterArgs,
+            expected_cycles: u64,
+            result: Result<CanisterIdRecord, CanisterCallError>,
+        },
+        InstallCode {
+            install_code_args: InstallCodeArgs,
+            result: Result<EmptyBlob, CanisterCallError>,
+        },
     }
 
     #[derive(Debug)]
@@ -940,6 +1083,55 @@ mod tests {
 
             result
         }
+
+        async fn create_canister(
+            &mut self,
+            observed_create_canister_args: &CreateCanisterArgs,
+            observed_cycles: u64,
+        ) -> Result<CanisterIdRecord, CanisterCallError> {
+            let (expected_create_canister_args, expected_cycles, result) =
+                match self.calls.pop_front().unwrap() {
+                    ManagementCanisterClientCall::CreateCanister {
+                        create_canister_args,
+                        expected_cycles,
+                        result,
+                    } => (create_canister_args, expected_cycles, result),
+
+                    call => panic!(
+                        "An unexpected create_canister call was made. \
+                         Should have been {call:#?} instead: {observed_create_canister_args:#?}"
+                    ),
+                };
+
+            assert_eq!(
+                *observed_create_canister_args,
+                expected_create_canister_args,
+            );
+            assert_eq!(observed_cycles, expected_cycles);
+
+            result
+        }
+
+        async fn install_code(
+            &mut self,
+            observed_install_code_args: &InstallCodeArgs,
+        ) -> Result<EmptyBlob, CanisterCallError> {
+            let (expected_install_code_args, result) = match self.calls.pop_front().unwrap() {
+                ManagementCanisterClientCall::InstallCode {
+                    install_code_args,
+                    result,
+                } => (install_code_args, result),
+
+                call => panic!(
+                    "An unexpected install_code call was made. \
+                     Should have been {call:#?} instead: {observed_install_code_args:#?}"
+                ),
+            };
+
+            assert_eq!(*observed_install_code_args, expected_install_code_args);
+
+            result
+        }
     }
 
     #[derive(Debug, Clone)]
@@ -1051,6 +1243,7 @@ mod tests {
             archive_canister_ids: vec![],
             latest_ledger_archive_poll_timestamp_seconds: None,
             index_canister_id: Some(PrincipalId::new_user_test_id(4)),
+            sale_round_swap_canister_ids: vec![],
         }
     }
 
@@ -1382,6 +1575,108 @@ mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn test_create_swap_canister() {
+        // Step 1: Prepare the world.
+        thread_local! {
+            static STATE: RefCell<SnsRootCanister> = RefCell::new(build_test_sns_root_canister());
+        }
+        let sns_root_canister_id = CanisterId::try_from(PrincipalId::new_user_test_id(5)).unwrap();
+        let new_swap_canister_id = CanisterId::try_from(PrincipalId::new_user_test_id(6)).unwrap();
+        let wasm_module = vec![0, 0x61, 0x73, 0x6d, 1, 0, 0, 0];
+        let arg = vec![42, 43];
+
+        // Step 1.1: Prepare helpers.
+        let mut management_canister_client = MockManagementCanisterClient {
+            calls: vec![
+                ManagementCanisterClientCall::CreateCanister {
+                    create_canister_args: CreateCanisterArgs {
+                        settings: Some(CanisterSettingsArgs {
+                            controllers: Some(vec![sns_root_canister_id.get()]),
+                            ..Default::default()
+                        }),
+                    },
+                    expected_cycles: SWAP_CANISTER_CREATION_CYCLES,
+                    result: Ok(new_swap_canister_id.into()),
+                },
+                ManagementCanisterClientCall::InstallCode {
+                    install_code_args: InstallCodeArgs {
+                        mode: CanisterInstallMode::Install,
+                        canister_id: new_swap_canister_id.get(),
+                        wasm_module: wasm_module.clone(),
+                        arg: arg.clone(),
+                        compute_allocation: None,
+                        memory_allocation: None,
+                        query_allocation: None,
+                    },
+                    result: Ok(EmptyBlob {}),
+                },
+            ]
+            .into(),
+        };
+
+        // Step 2: Run code under test.
+        let response = SnsRootCanister::create_swap_canister(
+            &STATE,
+            &mut management_canister_client,
+            sns_root_canister_id,
+            STATE.with(|state| state.borrow().governance_canister_id()),
+            CreateSwapCanisterRequest { wasm_module, arg },
+        )
+        .await;
+
+        // Step 3: Inspect results.
+        assert_eq!(
+            response,
+            CreateSwapCanisterResponse {
+                canister_id: Some(new_swap_canister_id.get()),
+            }
+        );
+        assert!(
+            management_canister_client.calls.is_empty(),
+            "{management_canister_client:#?}",
+        );
+        // The new swap canister is recorded in the state of root.
+        let state = STATE.with(|state| state.borrow().clone());
+        assert_eq!(
+            state,
+            SnsRootCanister {
+                sale_round_swap_canister_ids: vec![new_swap_canister_id.get()],
+                ..build_test_sns_root_canister()
+            }
+        );
+    }
+
+    #[should_panic(expected = "authorize")]
+    #[tokio::test]
+    async fn test_create_swap_canister_rejects_non_governance_caller() {
+        // Step 1: Prepare the world.
+        thread_local! {
+            static STATE: RefCell<SnsRootCanister> = RefCell::new(build_test_sns_root_canister());
+        }
+        let sns_root_canister_id = CanisterId::try_from(PrincipalId::new_user_test_id(5)).unwrap();
+        let not_governance = PrincipalId::new_user_test_id(9001);
+        assert!(not_governance != STATE.with(|state| state.borrow().governance_canister_id()));
+
+        // Step 1.1: Prepare helpers.
+        let mut management_canister_client = MockManagementCanisterClient {
+            calls: vec![].into(),
+        };
+
+        // Step 2: Run code under test.
+        SnsRootCanister::create_swap_canister(
+            &STATE,
+            &mut management_canister_client,
+            sns_root_canister_id,
+            not_governance,
+            CreateSwapCanisterRequest {
+                wasm_module: vec![1, 2, 3],
+                arg: vec![],
+            },
+        )
+        .await;
+    }
+
     #[tokio::test]
     async fn test_set_dapp_controllers_state_not_changed_if_sns_root_still_controls() {
         // Step 1: Prepare the world.
@@ -1488,6 +1783,7 @@ mod tests {
                 dapps: state.dapp_canister_ids,
                 archives: state.archive_canister_ids,
                 index: state.index_canister_id,
+                sale_round_swaps: state.sale_round_swap_canister_ids,
             }
         )
     }
@@ -2191,6 +2487,7 @@ mod tests {
                 archive_canister_ids: vec![],
                 latest_ledger_archive_poll_timestamp_seconds: None,
                 index_canister_id: Some(PrincipalId::new_user_test_id(4)),
+                sale_round_swap_canister_ids: vec![],
             });
         }
 
@@ -2388,6 +2685,7 @@ mod tests {
                 archive_canister_ids: EXPECTED_ARCHIVE_CANISTERS_PRINCIPAL_IDS.with(|i| i.clone()),
                 latest_ledger_archive_poll_timestamp_seconds: None,
                 index_canister_id: Some(PrincipalId::new_user_test_id(4)),
+                sale_round_swap_canister_ids: vec![],
             });
         }
 
@@ -25,10 +25,11 @@ use ic_sns_swap::pb::v1::{
 };
 
 use ic_sns_swap::pb::v1::{
-    CanisterCallError, ErrorRefundIcpRequest, ErrorRefundIcpResponse, FinalizeSwapRequest,
-    FinalizeSwapResponse, GetBuyerStateRequest, GetBuyerStateResponse, GetBuyersTotalRequest,
-    GetBuyersTotalResponse, GetCanisterStatusRequest, GetStateRequest, GetStateResponse, Init,
-    OpenRequest, OpenResponse, RefreshBuyerTokensRequest, RefreshBuyerTokensResponse, Swap,
+    AbortUnopenedSaleRoundRequest, AbortUnopenedSaleRoundResponse, CanisterCallError,
+    ErrorRefundIcpRequest, ErrorRefundIcpResponse, FinalizeSwapRequest, FinalizeSwapResponse,
+    GetBuyerStateRequest, GetBuyerStateResponse, GetBuyersTotalRequest, GetBuyersTotalResponse,
+    GetCanisterStatusRequest, GetStateRequest, GetStateResponse, Init, OpenRequest, OpenResponse,
+    RefreshBuyerTokensRequest, RefreshBuyerTokensResponse, Swap,
 };
 use ic_sns_swap::swap::{NnsGovernanceClient, SnsGovernanceClient, SnsRootClient, LOG_PREFIX};
 use prost::Message;
@@ -110,7 +111,7 @@ fn open() {
 async fn open_(req: OpenRequest) -> OpenResponse {
     println!("{}open", LOG_PREFIX);
     // Require authorization.
-    let allowed_canister = swap().init().nns_governance();
+    let allowed_canister = swap().init().swap_opener();
     if caller() != PrincipalId::from(allowed_canister) {
         panic!(
             "This method can only be called by canister {}",
@@ -141,7 +142,13 @@ async fn refresh_buyer_tokens_(arg: RefreshBuyerTokensRequest) -> RefreshBuyerTo
     };
     let icp_ledger = create_real_icp_ledger(swap().init().icp_ledger());
     match swap_mut()
-        .refresh_buyer_token_e8s(p, id(), &icp_ledger)
+        .refresh_buyer_token_e8s(
+            p,
+            arg.country_code.as_deref(),
+            arg.confirmation_text.as_deref(),
+            id(),
+            &icp_ledger,
+        )
         .await
     {
         Ok(r) => r,
@@ -289,6 +296,27 @@ async fn finalize_swap_(_arg: FinalizeSwapRequest) -> FinalizeSwapResponse {
         .await
 }
 
+/// See Swap.abort_unopened_sale_round.
+#[export_name = "canister_update abort_unopened_sale_round"]
+fn abort_unopened_sale_round() {
+    over_async(candid_one, abort_unopened_sale_round_)
+}
+
+/// See Swap.abort_unopened_sale_round.
+#[candid_method(update, rename = "abort_unopened_sale_round")]
+async fn abort_unopened_sale_round_(
+    _arg: AbortUnopenedSaleRoundRequest,
+) -> AbortUnopenedSaleRoundResponse {
+    let sns_ledger = create_real_icrc1_ledger(swap().init().sns_ledger());
+    match swap_mut()
+        .abort_unopened_sale_round(caller(), id(), now_fn, &sns_ledger)
+        .await
+    {
+        Ok(response) => response,
+        Err(msg) => panic!("{}", msg),
+    }
+}
+
 #[export_name = "canister_update error_refund_icp"]
 fn error_refund_icp() {
     over_async(candid_one, error_refund_icp_)
@@ -538,6 +566,10 @@ mod tests {
             None,
             0,
             0,
+            None,
+            0,
+            None,
+            Default::default(),
         )
     }
 
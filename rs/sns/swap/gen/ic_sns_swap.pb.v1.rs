@@ -119,6 +119,20 @@
 ///        - Send the information about CF participants (`CfParticipant`) back to NNS governance which will return it to the corresponding neurons. Assign the control of the dapp (now under the SNS control) back to the specified principals.
 /// - On reject of proposal to open decentralization sale:
 ///    - Assign the control of the dapp (now under the SNS control) back to the specified principals.
+///
+/// Follow-up sale rounds.
+///
+/// - After the initial decentralization swap, SNS governance can sell more
+///    SNS tokens from its treasury in follow-up sale rounds (see the
+///    OpenSaleRound proposal of SNS governance).
+/// - Each round is a fresh swap canister, created by SNS root, whose
+///    `Init.follow_up_sale_round` is set.
+/// - SNS governance transfers the offered SNS tokens to the new swap canister
+///    and opens the swap. There is no community fund participation.
+/// - On commit, participants get their neurons via `claim_swap_neurons` and
+///    the ICP goes to SNS governance, as in the initial swap.
+/// - On abort, participants get their ICP refunded and the SNS tokens go back
+///    to the SNS governance treasury.
 #[derive(
     candid::CandidType,
     candid::Deserialize,
@@ -160,6 +174,11 @@ pub struct Swap {
     /// (that field is required at the application level).
     #[prost(uint64, optional, tag = "9")]
     pub open_sns_token_swap_proposal_id: ::core::option::Option<u64>,
+    /// Only used by follow-up sale rounds: when such a round is aborted, the SNS
+    /// tokens that were offered are returned to the SNS governance treasury
+    /// during finalization. This records the progress of that transfer.
+    #[prost(message, optional, tag = "10")]
+    pub unsold_sns_tokens: ::core::option::Option<TransferableAmount>,
 }
 /// The initialisation data of the canister. Always specified on
 /// canister creation, and cannot be modified afterwards.
@@ -177,7 +196,7 @@ pub struct Swap {
 )]
 pub struct Init {
     /// The canister ID of the NNS governance canister. This is the only
-    /// principal that can open the swap.
+    /// principal that can open the swap, unless this is a follow-up sale round.
     #[prost(string, tag = "1")]
     pub nns_governance_canister_id: ::prost::alloc::string::String,
     /// The canister ID of the governance canister of the SNS that this
@@ -208,6 +227,44 @@ pub struct Init {
     /// the values match is not checked. If they don't match things will break.
     #[prost(uint64, optional, tag = "14")]
     pub neuron_minimum_stake_e8s: ::core::option::Option<u64>,
+    /// Participants who declare to reside in one of these countries cannot
+    /// participate. If set, participants must declare a country when calling
+    /// refresh_buyer_tokens. If set, must not be empty.
+    #[prost(message, optional, tag = "15")]
+    pub restricted_countries: ::core::option::Option<Countries>,
+    /// The SHA-256 hash of a text that participants must confirm, e.g., the
+    /// terms of the swap. If set, participants must pass the confirmation text
+    /// when calling refresh_buyer_tokens, and its hash must match this value.
+    #[prost(bytes = "vec", optional, tag = "16")]
+    pub confirmation_text_sha256: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    /// If not empty, only these principals can participate in the swap.
+    #[prost(string, repeated, tag = "17")]
+    pub allowed_participant_principal_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Whether this swap is a follow-up sale round, i.e., a swap that SNS
+    /// governance opened after the initial decentralization swap. Such a swap
+    /// is opened by SNS governance instead of NNS governance, and has no
+    /// community fund participation. When it is finalized, SNS governance stays
+    /// in its current mode and the controllers of dapp canisters are not
+    /// changed. If it is aborted, the SNS tokens that were offered are returned
+    /// to the SNS governance treasury.
+    #[prost(bool, optional, tag = "18")]
+    pub follow_up_sale_round: ::core::option::Option<bool>,
+}
+/// A list of countries.
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    serde::Serialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct Countries {
+    /// ISO 3166-1 alpha-2 codes, e.g., "CH". Must consist of two upper case
+    /// letters each.
+    #[prost(string, repeated, tag = "1")]
+    pub iso_codes: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 /// Represents one NNS neuron from the community fund participating in this swap.
 #[derive(
@@ -524,6 +581,7 @@ pub struct OpenRequest {
     #[prost(message, repeated, tag = "2")]
     pub cf_participants: ::prost::alloc::vec::Vec<CfParticipant>,
     /// The ID of the proposal whose execution consists of calling this method.
+    /// For follow-up sale rounds, this is the ID of the SNS governance proposal.
     #[prost(uint64, optional, tag = "3")]
     pub open_sns_token_swap_proposal_id: ::core::option::Option<u64>,
 }
@@ -684,6 +742,14 @@ pub struct RefreshBuyerTokensRequest {
     /// If not specified, the caller is used.
     #[prost(string, tag = "1")]
     pub buyer: ::prost::alloc::string::String,
+    /// The country that the buyer declares to reside in, as an ISO 3166-1
+    /// alpha-2 code. Required if the swap restricts countries.
+    #[prost(string, optional, tag = "2")]
+    pub country_code: ::core::option::Option<::prost::alloc::string::String>,
+    /// The text that the buyer confirms. Required if the swap has a
+    /// confirmation text.
+    #[prost(string, optional, tag = "3")]
+    pub confirmation_text: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[derive(
     candid::CandidType,
@@ -724,6 +790,8 @@ pub struct FinalizeSwapRequest {}
 pub struct FinalizeSwapResponse {
     #[prost(message, optional, tag = "1")]
     pub sweep_icp: ::core::option::Option<SweepResult>,
+    /// In an aborted follow-up sale round, this reports returning the unsold SNS
+    /// tokens to the SNS governance treasury.
     #[prost(message, optional, tag = "2")]
     pub sweep_sns: ::core::option::Option<SweepResult>,
     #[prost(message, optional, tag = "3")]
@@ -736,6 +804,32 @@ pub struct FinalizeSwapResponse {
     pub settle_community_fund_participation_result:
         ::core::option::Option<SettleCommunityFundParticipationResult>,
 }
+/// SNS governance calls this when it could not open a follow-up sale round,
+/// to get back the SNS tokens that it already transferred to the swap canister.
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    serde::Serialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct AbortUnopenedSaleRoundRequest {}
+#[derive(
+    candid::CandidType,
+    candid::Deserialize,
+    serde::Serialize,
+    comparable::Comparable,
+    Clone,
+    PartialEq,
+    ::prost::Message,
+)]
+pub struct AbortUnopenedSaleRoundResponse {
+    /// Reports returning the SNS tokens to the SNS governance treasury.
+    #[prost(message, optional, tag = "1")]
+    pub sweep_sns: ::core::option::Option<SweepResult>,
+}
 #[derive(
     candid::CandidType,
     candid::Deserialize,
@@ -1,12 +1,13 @@
 use crate::pb::v1::{
     error_refund_icp_response, restore_dapp_controllers_response, set_dapp_controllers_call_result,
     set_mode_call_result, settle_community_fund_participation_result, sns_neuron_recipe::Investor,
-    BuyerState, CanisterCallError, CfInvestment, CfNeuron, CfParticipant, DerivedState,
-    DirectInvestment, ErrorRefundIcpRequest, ErrorRefundIcpResponse, FinalizeSwapResponse,
-    GetBuyerStateRequest, GetBuyerStateResponse, GetBuyersTotalResponse, Init, Lifecycle,
-    OpenRequest, OpenResponse, Params, RefreshBuyerTokensResponse, RestoreDappControllersResponse,
-    SetDappControllersCallResult, SetModeCallResult, SettleCommunityFundParticipationResult,
-    SnsNeuronRecipe, Swap, SweepResult, TransferableAmount,
+    AbortUnopenedSaleRoundResponse, BuyerState, CanisterCallError, CfInvestment, CfNeuron,
+    CfParticipant, Countries, DerivedState, DirectInvestment, ErrorRefundIcpRequest,
+    ErrorRefundIcpResponse, FinalizeSwapResponse, GetBuyerStateRequest, GetBuyerStateResponse,
+    GetBuyersTotalResponse, Init, Lifecycle, OpenRequest, OpenResponse, Params,
+    RefreshBuyerTokensResponse, RestoreDappControllersResponse, SetDappControllersCallResult,
+    SetModeCallResult, SettleCommunityFundParticipationResult, SnsNeuronRecipe, Swap, SweepResult,
+    TransferableAmount,
 };
 // TODO(NNS1-1589): Get these from authoritative source.
 use crate::pb::v1::GovernanceError;
@@ -15,11 +16,15 @@ use async_trait::async_trait;
 use dfn_core::println;
 use dfn_core::CanisterId;
 use ic_base_types::PrincipalId;
+use ic_crypto_sha::Sha256;
 use ic_icrc1::{Account, Subaccount};
 use ic_ledger_core::Tokens;
 use ic_nervous_system_common::i2d;
-use ic_nervous_system_common::ledger::compute_neuron_staking_subaccount_bytes;
+use ic_nervous_system_common::ledger::{
+    compute_distribution_subaccount_bytes, compute_neuron_staking_subaccount_bytes,
+};
 use ic_sns_governance::{
+    governance::TREASURY_SUBACCOUNT_NONCE,
     ledger::ICRC1Ledger,
     pb::v1::{
         claim_swap_neurons_request::NeuronParameters, governance, ClaimSwapNeuronsRequest,
@@ -30,6 +35,7 @@ use icp_ledger::DEFAULT_TRANSFER_FEE;
 use rand::SeedableRng;
 use rand_chacha::ChaCha20Rng;
 use rust_decimal::prelude::ToPrimitive;
+use std::collections::BTreeSet;
 use std::ops::Div;
 
 use std::num::NonZeroU128;
@@ -48,6 +54,15 @@ pub const START_OF_2022_TIMESTAMP_SECONDS: u64 = 1640995200;
 pub const LOG_PREFIX: &str = "[Swap] ";
 pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
 
+/// The memos of the neurons of direct investors in a follow-up sale round
+/// start at the ID of the proposal that opened the round times this value.
+/// Hence, a neuron basket can have at most this many neurons.
+pub const FOLLOW_UP_SALE_ROUND_MEMO_STRIDE: u64 = 1 << 32;
+
+/// The maximum number of principals that `Init.allowed_participant_principal_ids`
+/// can contain. This bounds the work of checking a participant against it.
+pub const MAX_ALLOWED_PARTICIPANT_PRINCIPAL_IDS: usize = 1_000;
+
 /// Result of a token transfer (commit or abort) on a ledger (ICP or
 /// SNS) for a single buyer.
 pub enum TransferResult {
@@ -160,6 +175,7 @@ impl Swap {
             buyers: Default::default(), // Btree map
             neuron_recipes: vec![],
             open_sns_token_swap_proposal_id: None,
+            unsold_sns_tokens: None,
         }
     }
 
@@ -368,6 +384,10 @@ impl Swap {
         let mut total_sns_tokens_sold: u64 = 0;
         // Vector of neuron recipes.
         let mut neurons = Vec::new();
+        // Direct investors may have participated in earlier rounds of the
+        // same SNS, so the memos of each follow-up sale round start at an
+        // offset derived from the (unique) ID of the proposal that opened it.
+        let direct_investor_memo_offset = self.direct_investor_memo_offset();
         // =====================================================================
         // ===            This is where the actual swap happens              ===
         // =====================================================================
@@ -382,7 +402,8 @@ impl Swap {
             // identifier for an SNS Neuron is the SNS Ledger Subaccount, which
             // is a hash of PrincipalId and some unique memo. Since direct
             // investors in the swap use their own principal_id, there are no
-            // neuron id collisions, and each basket can use memos starting at 0.
+            // neuron id collisions within a swap, and each basket can use
+            // memos starting at `direct_investor_memo_offset`.
             for (memo, scheduled_vesting_event) in self
                 .generate_vesting_schedule(amount_sns_e8s, &mut rng)
                 .into_iter()
@@ -398,7 +419,7 @@ impl Swap {
                         buyer_principal: buyer_principal.clone(),
                     })),
                     neuron_attributes: Some(NeuronAttributes {
-                        memo: memo as u64,
+                        memo: direct_investor_memo_offset + memo as u64,
                         dissolve_delay_seconds: scheduled_vesting_event.dissolve_delay_seconds,
                     }),
                 });
@@ -461,6 +482,22 @@ impl Swap {
         self.set_lifecycle(Lifecycle::Committed);
     }
 
+    /// The first memo of the neuron basket of each direct investor.
+    ///
+    /// This is 0 for the initial swap. For a follow-up sale round, it is the
+    /// ID of the proposal that opened the round times
+    /// `FOLLOW_UP_SALE_ROUND_MEMO_STRIDE`, so that the neurons of a buyer
+    /// never reuse the staking subaccount of a neuron from another round.
+    fn direct_investor_memo_offset(&self) -> u64 {
+        if !self.init().is_follow_up_sale_round() {
+            return 0;
+        }
+        self.open_sns_token_swap_proposal_id
+            .expect("Follow-up sale rounds are opened by a proposal.")
+            .checked_mul(FOLLOW_UP_SALE_ROUND_MEMO_STRIDE)
+            .expect("The memo offset of the sale round overflows.")
+    }
+
     /// Precondition: lifecycle = OPEN && swap_due && not sufficient_participation
     ///
     /// Postcondition: lifecycle == ABORTED
@@ -520,10 +557,16 @@ impl Swap {
     /// to the subaccount can be reclaimed using `error_refund_icp`
     /// once this swap is closed (committed or aborted).
     ///
+    /// If the swap restricts who can participate (see `Init`), the buyer
+    /// must be allowed to participate, and must declare their country and
+    /// the confirmation text as required.
+    ///
     /// TODO(NNS1-1682): attempt to refund ICP that cannot be accepted.
     pub async fn refresh_buyer_token_e8s(
         &mut self,
         buyer: PrincipalId,
+        country_code: Option<&str>,
+        confirmation_text: Option<&str>,
         this_canister: CanisterId,
         icp_ledger: &dyn ICRC1Ledger,
     ) -> Result<RefreshBuyerTokensResponse, String> {
@@ -533,6 +576,8 @@ impl Swap {
                     .to_string(),
             );
         }
+        self.init()
+            .validate_participant(&buyer, country_code, confirmation_text)?;
         if self.icp_target_reached() {
             return Err("The ICP target for this token swap has already been reached.".to_string());
         }
@@ -676,6 +721,14 @@ impl Swap {
 
         let sweep_icp = self.sweep_icp(now_fn, icp_ledger).await;
 
+        // Follow-up sale rounds neither have community fund participation,
+        // nor control over the dapp or the mode of SNS governance.
+        if self.init().is_follow_up_sale_round() {
+            return self
+                .finalize_follow_up_sale_round(now_fn, sweep_icp, sns_governance_client, sns_ledger)
+                .await;
+        }
+
         let settle_community_fund_participation_result = Some({
             use settle_community_fund_participation::{Aborted, Committed, Result};
 
@@ -733,6 +786,159 @@ impl Swap {
         }
     }
 
+    /// The part of `finalize` that is specific to follow-up sale rounds, i.e.,
+    /// everything after ICP has been swept.
+    ///
+    /// If the round was committed, SNS tokens are swept and neurons are
+    /// claimed, as in the initial swap. Otherwise, the unsold SNS tokens are
+    /// returned to the SNS governance treasury.
+    async fn finalize_follow_up_sale_round(
+        &mut self,
+        now_fn: fn(bool) -> u64,
+        sweep_icp: SweepResult,
+        sns_governance_client: &mut impl SnsGovernanceClient,
+        sns_ledger: &dyn ICRC1Ledger,
+    ) -> FinalizeSwapResponse {
+        if self.lifecycle() != Lifecycle::Committed {
+            let sweep_sns = self.return_unsold_sns_tokens(now_fn, sns_ledger).await;
+            return FinalizeSwapResponse {
+                sweep_icp: Some(sweep_icp),
+                sweep_sns: Some(sweep_sns),
+                create_neuron: None,
+                sns_governance_normal_mode_enabled: None,
+                set_dapp_controllers_result: None,
+                settle_community_fund_participation_result: None,
+            };
+        }
+
+        let sweep_sns = self.sweep_sns(now_fn, sns_ledger).await;
+
+        let create_neuron = self
+            .claim_neurons(sns_governance_client, DEFAULT_TRANSFER_FEE)
+            .await;
+
+        FinalizeSwapResponse {
+            sweep_icp: Some(sweep_icp),
+            sweep_sns: Some(sweep_sns),
+            create_neuron: Some(create_neuron),
+            sns_governance_normal_mode_enabled: None,
+            set_dapp_controllers_result: None,
+            settle_community_fund_participation_result: None,
+        }
+    }
+
+    /// In state ABORTED of a follow-up sale round. Transfer the SNS
+    /// tokens that were offered back to the SNS governance treasury. (If the
+    /// round was never opened, these are the tokens that
+    /// `abort_unopened_sale_round` found in this canister's account.)
+    ///
+    /// Like the other sweeps, this is idempotent: the transfer is
+    /// tracked in `unsold_sns_tokens` and made at most once.
+    pub async fn return_unsold_sns_tokens(
+        &mut self,
+        now_fn: fn(bool) -> u64,
+        sns_ledger: &dyn ICRC1Ledger,
+    ) -> SweepResult {
+        assert!(self.lifecycle() == Lifecycle::Aborted);
+        let sns_governance = self.init().sns_governance();
+        let sns_transaction_fee_e8s = Tokens::from_e8s(
+            self.init()
+                .transaction_fee_e8s
+                .expect("Transfer fee not known."),
+        );
+        let dst = Account {
+            owner: sns_governance.get(),
+            subaccount: Some(compute_distribution_subaccount_bytes(
+                sns_governance.get(),
+                TREASURY_SUBACCOUNT_NONCE,
+            )),
+        };
+        let sns_token_e8s = self.sns_token_e8s();
+        let result = self
+            .unsold_sns_tokens
+            .get_or_insert_with(|| TransferableAmount {
+                amount_e8s: sns_token_e8s,
+                transfer_start_timestamp_seconds: 0,
+                transfer_success_timestamp_seconds: 0,
+            })
+            .transfer_helper(
+                now_fn,
+                sns_transaction_fee_e8s,
+                /* src_subaccount= */ None,
+                &dst,
+                sns_ledger,
+            )
+            .await;
+        let mut sweep_result = SweepResult {
+            success: 0,
+            failure: 0,
+            skipped: 0,
+        };
+        match result {
+            TransferResult::AmountTooSmall | TransferResult::AlreadyStarted => {
+                sweep_result.skipped += 1;
+            }
+            TransferResult::Success(_) => {
+                sweep_result.success += 1;
+            }
+            TransferResult::Failure(_) => {
+                sweep_result.failure += 1;
+            }
+        }
+        sweep_result
+    }
+
+    /// Aborts a follow-up sale round that SNS governance could not open, and
+    /// returns the SNS tokens that SNS governance already transferred to this
+    /// canister to the SNS governance treasury.
+    ///
+    /// Only SNS governance may call this, while the round is PENDING, or
+    /// after a previous call aborted it (to retry the transfer).
+    pub async fn abort_unopened_sale_round(
+        &mut self,
+        caller: PrincipalId,
+        this_canister: CanisterId,
+        now_fn: fn(bool) -> u64,
+        sns_ledger: &dyn ICRC1Ledger,
+    ) -> Result<AbortUnopenedSaleRoundResponse, String> {
+        if !self.init().is_follow_up_sale_round() {
+            return Err("Only follow-up sale rounds can be aborted before they open.".to_string());
+        }
+        let sns_governance = self.init().sns_governance();
+        if caller != sns_governance.get() {
+            return Err(format!(
+                "This method can only be called by canister {}",
+                sns_governance
+            ));
+        }
+        match self.lifecycle() {
+            Lifecycle::Pending => {
+                let sns_token_amount = Self::get_sns_tokens(this_canister, sns_ledger).await?;
+                // The round may have been opened while we were waiting.
+                if self.lifecycle() != Lifecycle::Pending {
+                    return Err("The sale round is no longer PENDING.".to_string());
+                }
+                self.unsold_sns_tokens = Some(TransferableAmount {
+                    amount_e8s: sns_token_amount.get_e8s(),
+                    transfer_start_timestamp_seconds: 0,
+                    transfer_success_timestamp_seconds: 0,
+                });
+                self.set_lifecycle(Lifecycle::Aborted);
+            }
+            Lifecycle::Aborted if self.params.is_none() => (),
+            lifecycle => {
+                return Err(format!(
+                    "Cannot abort a sale round that was opened (lifecycle {:?}).",
+                    lifecycle
+                ));
+            }
+        }
+        let sweep_sns = self.return_unsold_sns_tokens(now_fn, sns_ledger).await;
+        Ok(AbortUnopenedSaleRoundResponse {
+            sweep_sns: Some(sweep_sns),
+        })
+    }
+
     /// Restore control over the dapp to the fallback controllers.
     pub async fn restore_dapp_controllers(
         &mut self,
@@ -1396,6 +1602,21 @@ impl Init {
     pub fn icp_ledger(&self) -> CanisterId {
         CanisterId::new(PrincipalId::from_str(&self.icp_ledger_canister_id).unwrap()).unwrap()
     }
+
+    /// Whether this swap is a follow-up sale round opened by SNS governance.
+    pub fn is_follow_up_sale_round(&self) -> bool {
+        self.follow_up_sale_round.unwrap_or(false)
+    }
+
+    /// The only canister that can open the swap: SNS governance for
+    /// follow-up sale rounds, and NNS governance otherwise.
+    pub fn swap_opener(&self) -> CanisterId {
+        if self.is_follow_up_sale_round() {
+            self.sns_governance()
+        } else {
+            self.nns_governance()
+        }
+    }
     pub fn validate(&self) -> Result<(), String> {
         validate_canister_id(&self.nns_governance_canister_id)?;
         validate_canister_id(&self.sns_governance_canister_id)?;
@@ -1423,8 +1644,142 @@ impl Init {
         // that it is supplied. Needs to match the value in SNS governance
         // though.
 
+        self.validate_participation_restrictions()?;
+
+        Ok(())
+    }
+
+    /// Validates the (optional) restrictions on who can participate in the
+    /// swap.
+    pub fn validate_participation_restrictions(&self) -> Result<(), String> {
+        if let Some(restricted_countries) = &self.restricted_countries {
+            restricted_countries.validate()?;
+        }
+
+        if let Some(confirmation_text_sha256) = &self.confirmation_text_sha256 {
+            if confirmation_text_sha256.len() != 32 {
+                return Err(format!(
+                    "confirmation_text_sha256 must be 32 bytes long, but it is {} bytes long.",
+                    confirmation_text_sha256.len()
+                ));
+            }
+        }
+
+        if self.allowed_participant_principal_ids.len() > MAX_ALLOWED_PARTICIPANT_PRINCIPAL_IDS {
+            return Err(format!(
+                "allowed_participant_principal_ids must contain at most {} principals, \
+                 but it contains {}.",
+                MAX_ALLOWED_PARTICIPANT_PRINCIPAL_IDS,
+                self.allowed_participant_principal_ids.len()
+            ));
+        }
+        let allowed_participants = self.allowed_participants()?;
+        if allowed_participants.len() != self.allowed_participant_principal_ids.len() {
+            return Err(
+                "allowed_participant_principal_ids contains a principal more than once."
+                    .to_string(),
+            );
+        }
+
         Ok(())
     }
+
+    /// Parses `allowed_participant_principal_ids` into a set.
+    pub fn allowed_participants(&self) -> Result<BTreeSet<PrincipalId>, String> {
+        self.allowed_participant_principal_ids
+            .iter()
+            .map(|p| {
+                PrincipalId::from_str(p).map_err(|err| {
+                    format!(
+                        "allowed_participant_principal_ids contains \"{}\", which is not a \
+                         valid PrincipalId: {}",
+                        p, err
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Checks that `buyer` may participate in the swap, given the country
+    /// and the confirmation text that they declared.
+    pub fn validate_participant(
+        &self,
+        buyer: &PrincipalId,
+        country_code: Option<&str>,
+        confirmation_text: Option<&str>,
+    ) -> Result<(), String> {
+        if !self.allowed_participant_principal_ids.is_empty()
+            && !self.allowed_participants()?.contains(buyer)
+        {
+            return Err(format!(
+                "Principal {} is not allowed to participate in this swap.",
+                buyer
+            ));
+        }
+
+        if let Some(restricted_countries) = &self.restricted_countries {
+            let country_code = country_code.ok_or_else(|| {
+                "Participants in this swap must declare the country they reside in.".to_string()
+            })?;
+            validate_country_code(country_code)?;
+            if restricted_countries.contains(country_code) {
+                return Err(format!(
+                    "Participants from country {} cannot participate in this swap.",
+                    country_code
+                ));
+            }
+        }
+
+        if let Some(confirmation_text_sha256) = &self.confirmation_text_sha256 {
+            let confirmation_text = confirmation_text.ok_or_else(|| {
+                "Participants in this swap must confirm the confirmation text.".to_string()
+            })?;
+            if Sha256::hash(confirmation_text.as_bytes())[..] != confirmation_text_sha256[..] {
+                return Err(
+                    "The confirmation text does not match the one of this swap.".to_string()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Countries {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.iso_codes.is_empty() {
+            return Err("restricted_countries must not be empty if set.".to_string());
+        }
+        let mut iso_codes = BTreeSet::new();
+        for iso_code in &self.iso_codes {
+            validate_country_code(iso_code)?;
+            if !iso_codes.insert(iso_code) {
+                return Err(format!(
+                    "restricted_countries contains {} more than once.",
+                    iso_code
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, country_code: &str) -> bool {
+        self.iso_codes
+            .iter()
+            .any(|iso_code| iso_code == country_code)
+    }
+}
+
+/// Checks that `country_code` looks like an ISO 3166-1 alpha-2 code, i.e.,
+/// that it consists of two upper case letters.
+pub fn validate_country_code(country_code: &str) -> Result<(), String> {
+    if country_code.len() != 2 || !country_code.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(format!(
+            "Country code \"{}\" is not an ISO 3166-1 alpha-2 code.",
+            country_code
+        ));
+    }
+    Ok(())
 }
 
 impl Params {
@@ -1533,6 +1888,14 @@ impl Params {
             ));
         }
 
+        if init.is_follow_up_sale_round() && neuron_basket.count >= FOLLOW_UP_SALE_ROUND_MEMO_STRIDE
+        {
+            return Err(format!(
+                "neuron_basket_construction_parameters.count ({}) must be < {} in a follow-up sale round",
+                neuron_basket.count, FOLLOW_UP_SALE_ROUND_MEMO_STRIDE,
+            ));
+        }
+
         if neuron_basket.dissolve_delay_interval_seconds == 0 {
             return Err(format!(
                 "neuron_basket_construction_parameters.dissolve_delay_interval_seconds ({}) must be > 0",
@@ -1689,9 +2052,32 @@ impl OpenRequest {
             }
         }
 
+        // Follow-up sale rounds are funded by SNS governance only.
+        if init.is_follow_up_sale_round() && !self.cf_participants.is_empty() {
+            defects.push(
+                "Follow-up sale rounds cannot have community fund participation.".to_string(),
+            );
+        }
+
         // Inspect open_sns_token_swap_proposal_id.
-        if self.open_sns_token_swap_proposal_id.is_none() {
-            defects.push("The open_sns_token_swap_proposal_id field has no value.".to_string());
+        match self.open_sns_token_swap_proposal_id {
+            None => {
+                defects.push("The open_sns_token_swap_proposal_id field has no value.".to_string());
+            }
+            // The neuron memos of a follow-up sale round are derived from it
+            // (see `FOLLOW_UP_SALE_ROUND_MEMO_STRIDE`).
+            Some(proposal_id)
+                if init.is_follow_up_sale_round()
+                    && proposal_id
+                        .checked_mul(FOLLOW_UP_SALE_ROUND_MEMO_STRIDE)
+                        .is_none() =>
+            {
+                defects.push(format!(
+                    "The open_sns_token_swap_proposal_id ({}) is too large for a follow-up sale round.",
+                    proposal_id
+                ));
+            }
+            Some(_) => {}
         }
 
         // Return result.
@@ -1919,6 +2305,21 @@ mod tests {
         assert_is_ok!(OPEN_REQUEST.validate(START_OF_2022_TIMESTAMP_SECONDS, &INIT));
     }
 
+    #[test]
+    fn open_request_validate_follow_up_sale_round_without_cf_participants() {
+        let init = Init {
+            follow_up_sale_round: Some(true),
+            ..INIT.clone()
+        };
+        assert_is_err!(OPEN_REQUEST.validate(START_OF_2022_TIMESTAMP_SECONDS, &init));
+
+        let request = OpenRequest {
+            cf_participants: vec![],
+            ..OPEN_REQUEST.clone()
+        };
+        assert_is_ok!(request.validate(START_OF_2022_TIMESTAMP_SECONDS, &init));
+    }
+
     #[test]
     fn open_request_validate_invalid_params() {
         let request = OpenRequest {
@@ -2,16 +2,19 @@ use async_trait::async_trait;
 use candid::Principal;
 use futures::future::FutureExt;
 use ic_base_types::{CanisterId, PrincipalId};
+use ic_crypto_sha::Sha256;
 use ic_icrc1::{Account, Subaccount};
 use ic_ledger_core::Tokens;
 use ic_nervous_system_common::{
-    assert_is_err, assert_is_ok, ledger::compute_neuron_staking_subaccount_bytes,
+    assert_is_err, assert_is_ok,
+    ledger::{compute_distribution_subaccount_bytes, compute_neuron_staking_subaccount_bytes},
     NervousSystemError, E8,
 };
 use ic_nervous_system_common_test_keys::{
     TEST_USER1_PRINCIPAL, TEST_USER2_PRINCIPAL, TEST_USER3_PRINCIPAL,
 };
 use ic_sns_governance::{
+    governance::TREASURY_SUBACCOUNT_NONCE,
     ledger::ICRC1Ledger,
     pb::v1::{
         governance,
@@ -31,7 +34,10 @@ use ic_sns_swap::{
         Lifecycle::{Committed, Open},
         *,
     },
-    swap::{principal_to_subaccount, NnsGovernanceClient, SnsGovernanceClient, SECONDS_PER_DAY},
+    swap::{
+        principal_to_subaccount, NnsGovernanceClient, SnsGovernanceClient,
+        FOLLOW_UP_SALE_ROUND_MEMO_STRIDE, MAX_ALLOWED_PARTICIPANT_PRINCIPAL_IDS, SECONDS_PER_DAY,
+    },
 };
 use icp_ledger::DEFAULT_TRANSFER_FEE;
 use maplit::{btreemap, hashset};
@@ -71,6 +77,10 @@ fn init() -> Init {
         // Similar to, but different from values used in NNS.
         transaction_fee_e8s: Some(12_345),
         neuron_minimum_stake_e8s: Some(123_456_789),
+        restricted_countries: None,
+        confirmation_text_sha256: None,
+        allowed_participant_principal_ids: vec![],
+        follow_up_sale_round: None,
     };
     assert_is_ok!(result.validate());
     result
@@ -174,6 +184,64 @@ fn transaction_fee_e8s_is_required() {
     assert_is_err!(init.validate());
 }
 
+#[test]
+fn participation_restrictions_are_validated() {
+    let countries = |iso_codes: &[&str]| {
+        Some(Countries {
+            iso_codes: iso_codes.iter().map(|code| code.to_string()).collect(),
+        })
+    };
+    let valid_init = Init {
+        restricted_countries: countries(&["CH", "US"]),
+        confirmation_text_sha256: Some(Sha256::hash(b"I agree.").to_vec()),
+        allowed_participant_principal_ids: vec![i2principal_id_string(1), i2principal_id_string(2)],
+        ..init()
+    };
+    assert_is_ok!(valid_init.validate());
+
+    for invalid_init in [
+        Init {
+            restricted_countries: countries(&[]),
+            ..valid_init.clone()
+        },
+        Init {
+            restricted_countries: countries(&["ch"]),
+            ..valid_init.clone()
+        },
+        Init {
+            restricted_countries: countries(&["CHE"]),
+            ..valid_init.clone()
+        },
+        Init {
+            restricted_countries: countries(&["CH", "CH"]),
+            ..valid_init.clone()
+        },
+        Init {
+            confirmation_text_sha256: Some(vec![0; 31]),
+            ..valid_init.clone()
+        },
+        Init {
+            allowed_participant_principal_ids: vec!["not a principal".to_string()],
+            ..valid_init.clone()
+        },
+        Init {
+            allowed_participant_principal_ids: vec![
+                i2principal_id_string(1),
+                i2principal_id_string(1),
+            ],
+            ..valid_init.clone()
+        },
+        Init {
+            allowed_participant_principal_ids: (0..=MAX_ALLOWED_PARTICIPANT_PRINCIPAL_IDS as u64)
+                .map(i2principal_id_string)
+                .collect(),
+            ..valid_init.clone()
+        },
+    ] {
+        assert!(invalid_init.validate().is_err(), "{:#?}", invalid_init);
+    }
+}
+
 /// Expectation of one call on the mock Ledger.
 #[derive(Debug, Clone)]
 enum LedgerExpect {
@@ -387,6 +455,8 @@ fn test_min_icp() {
     assert!(swap
         .refresh_buyer_token_e8s(
             *TEST_USER1_PRINCIPAL,
+            None, // country_code
+            None, // confirmation_text
             SWAP_CANISTER_ID,
             &mock_stub(vec![LedgerExpect::AccountBalance(
                 Account {
@@ -410,6 +480,8 @@ fn test_min_icp() {
     assert!(swap
         .refresh_buyer_token_e8s(
             *TEST_USER2_PRINCIPAL,
+            None, // country_code
+            None, // confirmation_text
             SWAP_CANISTER_ID,
             &mock_stub(vec![LedgerExpect::AccountBalance(
                 Account {
@@ -522,6 +594,8 @@ fn test_min_max_icp_per_buyer() {
         let e = swap
             .refresh_buyer_token_e8s(
                 *TEST_USER1_PRINCIPAL,
+                None, // country_code
+                None, // confirmation_text
                 SWAP_CANISTER_ID,
                 &mock_stub(vec![LedgerExpect::AccountBalance(
                     Account {
@@ -541,6 +615,8 @@ fn test_min_max_icp_per_buyer() {
         let e = swap
             .refresh_buyer_token_e8s(
                 *TEST_USER1_PRINCIPAL,
+                None, // country_code
+                None, // confirmation_text
                 SWAP_CANISTER_ID,
                 &mock_stub(vec![LedgerExpect::AccountBalance(
                     Account {
@@ -565,6 +641,8 @@ fn test_min_max_icp_per_buyer() {
         let e = swap
             .refresh_buyer_token_e8s(
                 *TEST_USER1_PRINCIPAL,
+                None, // country_code
+                None, // confirmation_text
                 SWAP_CANISTER_ID,
                 &mock_stub(vec![LedgerExpect::AccountBalance(
                     Account {
@@ -631,6 +709,8 @@ fn test_max_icp() {
     assert!(swap
         .refresh_buyer_token_e8s(
             *TEST_USER1_PRINCIPAL,
+            None, // country_code
+            None, // confirmation_text
             SWAP_CANISTER_ID,
             &mock_stub(vec![LedgerExpect::AccountBalance(
                 Account {
@@ -654,6 +734,8 @@ fn test_max_icp() {
     assert!(swap
         .refresh_buyer_token_e8s(
             *TEST_USER2_PRINCIPAL,
+            None, // country_code
+            None, // confirmation_text
             SWAP_CANISTER_ID,
             &mock_stub(vec![LedgerExpect::AccountBalance(
                 Account {
@@ -751,6 +833,8 @@ fn test_scenario_happy() {
     assert!(swap
         .refresh_buyer_token_e8s(
             *TEST_USER1_PRINCIPAL,
+            None, // country_code
+            None, // confirmation_text
             SWAP_CANISTER_ID,
             &mock_stub(vec![LedgerExpect::AccountBalance(
                 Account {
@@ -776,6 +860,8 @@ fn test_scenario_happy() {
     assert!(swap
         .refresh_buyer_token_e8s(
             *TEST_USER2_PRINCIPAL,
+            None, // country_code
+            None, // confirmation_text
             SWAP_CANISTER_ID,
             &mock_stub(vec![LedgerExpect::AccountBalance(
                 Account {
@@ -806,6 +892,8 @@ fn test_scenario_happy() {
     assert!(swap
         .refresh_buyer_token_e8s(
             *TEST_USER3_PRINCIPAL,
+            None, // country_code
+            None, // confirmation_text
             SWAP_CANISTER_ID,
             &mock_stub(vec![LedgerExpect::AccountBalance(
                 Account {
@@ -1230,6 +1318,7 @@ async fn test_finalize_swap_ok() {
         cf_participants: vec![],
         neuron_recipes: vec![],
         open_sns_token_swap_proposal_id: Some(OPEN_SNS_TOKEN_SWAP_PROPOSAL_ID),
+        unsold_sns_tokens: None,
     };
     assert!(swap.try_commit_or_abort(END_TIMESTAMP_SECONDS));
     assert_eq!(swap.lifecycle(), Committed);
@@ -1500,6 +1589,7 @@ async fn test_finalize_swap_abort() {
         },
         neuron_recipes: vec![],
         open_sns_token_swap_proposal_id: Some(OPEN_SNS_TOKEN_SWAP_PROPOSAL_ID),
+        unsold_sns_tokens: None,
     };
 
     assert!(swap.try_commit_or_abort(/* now_seconds: */ END_TIMESTAMP_SECONDS + 1));
@@ -1611,6 +1701,378 @@ async fn test_finalize_swap_abort() {
     }
 }
 
+#[tokio::test]
+async fn test_finalize_follow_up_sale_round_commit() {
+    // Step 1: Prepare the world.
+    let icp_ledger_calls = Arc::new(Mutex::new(Vec::<LedgerCall>::new()));
+    let icp_ledger: SpyLedger = SpyLedger::new(Arc::clone(&icp_ledger_calls));
+    let sns_ledger_calls = Arc::new(Mutex::new(Vec::<LedgerCall>::new()));
+    let sns_ledger: SpyLedger = SpyLedger::new(Arc::clone(&sns_ledger_calls));
+
+    let init = Init {
+        follow_up_sale_round: Some(true),
+        ..init()
+    };
+    let params = Params {
+        max_icp_e8s: 100,
+        min_icp_e8s: 0,
+        min_participant_icp_e8s: 1,
+        max_participant_icp_e8s: 100,
+        min_participants: 1,
+        sns_token_e8s: 10 * E8,
+        swap_due_timestamp_seconds: END_TIMESTAMP_SECONDS,
+        neuron_basket_construction_parameters: Some(NeuronBasketConstructionParameters {
+            count: 3,
+            dissolve_delay_interval_seconds: 7890000, // 3 months
+        }),
+    };
+    let mut swap = Swap {
+        lifecycle: Open as i32,
+        init: Some(init),
+        params: Some(params),
+        buyers: btreemap! {
+            i2principal_id_string(1001) => BuyerState::new(50 * E8),
+            i2principal_id_string(1002) => BuyerState::new(50 * E8),
+        },
+        cf_participants: vec![],
+        neuron_recipes: vec![],
+        open_sns_token_swap_proposal_id: Some(OPEN_SNS_TOKEN_SWAP_PROPOSAL_ID),
+        unsold_sns_tokens: None,
+    };
+    assert!(swap.try_commit_or_abort(END_TIMESTAMP_SECONDS));
+    assert_eq!(swap.lifecycle(), Committed);
+
+    // The memos of the neuron basket of each buyer start at an offset derived
+    // from the proposal that opened the round, so that the neurons don't reuse
+    // the staking subaccounts of neurons of the buyer from earlier rounds.
+    let first_memo = OPEN_SNS_TOKEN_SWAP_PROPOSAL_ID * FOLLOW_UP_SALE_ROUND_MEMO_STRIDE;
+    let mut memos = swap
+        .neuron_recipes
+        .iter()
+        .map(|recipe| recipe.neuron_attributes.as_ref().unwrap().memo)
+        .collect::<Vec<_>>();
+    memos.sort_unstable();
+    assert_eq!(
+        memos,
+        vec![
+            first_memo,
+            first_memo,
+            first_memo + 1,
+            first_memo + 1,
+            first_memo + 2,
+            first_memo + 2
+        ]
+    );
+
+    // Dapp controllers are not touched by follow-up sale rounds.
+    let mut sns_root_client = ExplodingSnsRootClient::default();
+    let mut sns_governance_client = SpySnsGovernanceClient::default();
+    sns_governance_client
+        .replies
+        .push(SnsGovernanceClientReply::ClaimSwapNeurons(
+            ClaimSwapNeuronsResponse {
+                successful_claims: 6,
+                skipped_claims: 0,
+                failed_claims: 0,
+            },
+        ));
+    let mut nns_governance_client = SpyNnsGovernanceClient::default();
+
+    // Step 2: Run the code under test. To wit, finalize_swap.
+    let result = swap
+        .finalize(
+            now_fn,
+            &mut sns_root_client,
+            &mut sns_governance_client,
+            &icp_ledger,
+            &sns_ledger,
+            &mut nns_governance_client,
+        )
+        .await;
+
+    // Step 3: Inspect the results.
+    assert_eq!(
+        result,
+        FinalizeSwapResponse {
+            sweep_icp: Some(SweepResult {
+                success: 2,
+                failure: 0,
+                skipped: 0,
+            }),
+            sweep_sns: Some(SweepResult {
+                success: 6,
+                failure: 0,
+                skipped: 0,
+            }),
+            create_neuron: Some(SweepResult {
+                success: 6,
+                failure: 0,
+                skipped: 0,
+            }),
+            sns_governance_normal_mode_enabled: None,
+            set_dapp_controllers_result: None,
+            settle_community_fund_participation_result: None,
+        },
+    );
+
+    // Neurons were claimed, but the mode of SNS governance was left alone.
+    assert_eq!(
+        sns_governance_client.calls.len(),
+        1,
+        "{:#?}",
+        sns_governance_client.calls
+    );
+    assert!(matches!(
+        sns_governance_client.calls[0],
+        SnsGovernanceClientCall::ClaimSwapNeurons(_)
+    ));
+
+    // NNS governance is not involved in follow-up sale rounds.
+    assert_eq!(nns_governance_client.calls, vec![]);
+}
+
+#[tokio::test]
+async fn test_finalize_follow_up_sale_round_abort_returns_unsold_sns_tokens() {
+    // Step 1: Prepare the world.
+    let icp_ledger_calls = Arc::new(Mutex::new(Vec::<LedgerCall>::new()));
+    let icp_ledger: SpyLedger = SpyLedger::new(Arc::clone(&icp_ledger_calls));
+    let sns_ledger_calls = Arc::new(Mutex::new(Vec::<LedgerCall>::new()));
+    let sns_ledger: SpyLedger = SpyLedger::new(Arc::clone(&sns_ledger_calls));
+
+    let init = Init {
+        follow_up_sale_round: Some(true),
+        ..init()
+    };
+    let params = Params {
+        // This absurdly large number ensures that the swap reaches the Aborted state.
+        max_icp_e8s: E8 * E8,
+        min_icp_e8s: E8 * E8,
+        min_participant_icp_e8s: 1,
+        max_participant_icp_e8s: E8 * E8,
+        min_participants: 2,
+        sns_token_e8s: 10 * E8,
+        swap_due_timestamp_seconds: END_TIMESTAMP_SECONDS,
+        neuron_basket_construction_parameters: Some(NeuronBasketConstructionParameters {
+            count: 12,
+            dissolve_delay_interval_seconds: 7890000, // 3 months
+        }),
+    };
+    let mut swap = Swap {
+        lifecycle: Open as i32,
+        init: Some(init.clone()),
+        params: Some(params),
+        cf_participants: vec![],
+        buyers: btreemap! {
+                i2principal_id_string(8502) => BuyerState::new(77 * E8),
+        },
+        neuron_recipes: vec![],
+        open_sns_token_swap_proposal_id: Some(OPEN_SNS_TOKEN_SWAP_PROPOSAL_ID),
+        unsold_sns_tokens: None,
+    };
+    assert!(swap.try_commit_or_abort(/* now_seconds: */ END_TIMESTAMP_SECONDS + 1));
+    assert_eq!(swap.lifecycle(), Lifecycle::Aborted);
+
+    // Dapp controllers are not touched by follow-up sale rounds.
+    let mut sns_root_client = ExplodingSnsRootClient::default();
+    let mut sns_governance_client = SpySnsGovernanceClient::default();
+    let mut nns_governance_client = SpyNnsGovernanceClient::default();
+
+    // Step 2: Run the code under test. To wit, finalize_swap.
+    let result = swap
+        .finalize(
+            now_fn,
+            &mut sns_root_client,
+            &mut sns_governance_client,
+            &icp_ledger,
+            &sns_ledger,
+            &mut nns_governance_client,
+        )
+        .await;
+
+    // Step 3: Inspect the results.
+    assert_eq!(
+        result,
+        FinalizeSwapResponse {
+            sweep_icp: Some(SweepResult {
+                success: 1,
+                failure: 0,
+                skipped: 0,
+            }),
+            // This is the main assertion.
+            sweep_sns: Some(SweepResult {
+                success: 1,
+                failure: 0,
+                skipped: 0,
+            }),
+            create_neuron: None,
+            sns_governance_normal_mode_enabled: None,
+            set_dapp_controllers_result: None,
+            settle_community_fund_participation_result: None,
+        },
+    );
+
+    // The SNS tokens were returned to the SNS governance treasury.
+    let sns_governance = PrincipalId::from_str(&init.sns_governance_canister_id).unwrap();
+    let sns_transaction_fee_e8s = init.transaction_fee_e8s.unwrap();
+    let expected_sns_ledger_calls = vec![LedgerCall::TransferFunds {
+        amount_e8s: 10 * E8 - sns_transaction_fee_e8s,
+        fee_e8s: sns_transaction_fee_e8s,
+        from_subaccount: None,
+        to: Account {
+            owner: sns_governance,
+            subaccount: Some(compute_distribution_subaccount_bytes(
+                sns_governance,
+                TREASURY_SUBACCOUNT_NONCE,
+            )),
+        },
+        memo: 0,
+    }];
+    assert_eq!(*sns_ledger_calls.lock().unwrap(), expected_sns_ledger_calls);
+    assert_eq!(sns_governance_client.calls, vec![]);
+    assert_eq!(nns_governance_client.calls, vec![]);
+
+    // Step 4: Finalizing again does not return the SNS tokens twice.
+    let result = swap
+        .finalize(
+            now_fn,
+            &mut sns_root_client,
+            &mut sns_governance_client,
+            &icp_ledger,
+            &sns_ledger,
+            &mut nns_governance_client,
+        )
+        .await;
+    assert_eq!(
+        result.sweep_sns,
+        Some(SweepResult {
+            success: 0,
+            failure: 0,
+            skipped: 1,
+        })
+    );
+    assert_eq!(*sns_ledger_calls.lock().unwrap(), expected_sns_ledger_calls);
+}
+
+#[tokio::test]
+async fn test_abort_unopened_sale_round_returns_sns_tokens() {
+    // Step 1: Prepare the world.
+    let sns_ledger_calls = Arc::new(Mutex::new(Vec::<LedgerCall>::new()));
+    let sns_ledger: SpyLedger = SpyLedger::new(Arc::clone(&sns_ledger_calls));
+    let init = Init {
+        follow_up_sale_round: Some(true),
+        ..init()
+    };
+    let mut swap = Swap::new(init.clone());
+    assert_eq!(swap.lifecycle(), Lifecycle::Pending);
+    let sns_governance = SNS_GOVERNANCE_CANISTER_ID.get();
+
+    // Step 2: Run the code under test. Only SNS governance may abort the round.
+    assert!(swap
+        .abort_unopened_sale_round(
+            PrincipalId::new_user_test_id(1),
+            SWAP_CANISTER_ID,
+            now_fn,
+            &sns_ledger
+        )
+        .await
+        .is_err());
+    assert_eq!(swap.lifecycle(), Lifecycle::Pending);
+    let response = swap
+        .abort_unopened_sale_round(sns_governance, SWAP_CANISTER_ID, now_fn, &sns_ledger)
+        .await
+        .unwrap();
+
+    // Step 3: Inspect the results.
+    assert_eq!(
+        response.sweep_sns,
+        Some(SweepResult {
+            success: 1,
+            failure: 0,
+            skipped: 0,
+        })
+    );
+    assert_eq!(swap.lifecycle(), Lifecycle::Aborted);
+    // The whole balance of the swap canister went back to the treasury.
+    let sns_transaction_fee_e8s = init.transaction_fee_e8s.unwrap();
+    let expected_sns_ledger_calls = vec![
+        LedgerCall::AccountBalance {
+            account_id: Account {
+                owner: SWAP_CANISTER_ID.get(),
+                subaccount: None,
+            },
+        },
+        LedgerCall::TransferFunds {
+            amount_e8s: 10 * E8 - sns_transaction_fee_e8s,
+            fee_e8s: sns_transaction_fee_e8s,
+            from_subaccount: None,
+            to: Account {
+                owner: sns_governance,
+                subaccount: Some(compute_distribution_subaccount_bytes(
+                    sns_governance,
+                    TREASURY_SUBACCOUNT_NONCE,
+                )),
+            },
+            memo: 0,
+        },
+    ];
+    assert_eq!(*sns_ledger_calls.lock().unwrap(), expected_sns_ledger_calls);
+
+    // Step 4: Retrying does not return the SNS tokens twice, and the round
+    // can no longer be opened.
+    let response = swap
+        .abort_unopened_sale_round(sns_governance, SWAP_CANISTER_ID, now_fn, &sns_ledger)
+        .await
+        .unwrap();
+    assert_eq!(
+        response.sweep_sns,
+        Some(SweepResult {
+            success: 0,
+            failure: 0,
+            skipped: 1,
+        })
+    );
+    assert_eq!(*sns_ledger_calls.lock().unwrap(), expected_sns_ledger_calls);
+    assert!(swap
+        .open(
+            SWAP_CANISTER_ID,
+            &sns_ledger,
+            START_TIMESTAMP_SECONDS,
+            OpenRequest {
+                params: Some(params()),
+                cf_participants: vec![],
+                open_sns_token_swap_proposal_id: Some(OPEN_SNS_TOKEN_SWAP_PROPOSAL_ID),
+            },
+        )
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_abort_unopened_sale_round_rejects_opened_rounds() {
+    let sns_ledger_calls = Arc::new(Mutex::new(Vec::<LedgerCall>::new()));
+    let sns_ledger: SpyLedger = SpyLedger::new(Arc::clone(&sns_ledger_calls));
+    let mut swap = Swap {
+        lifecycle: Open as i32,
+        params: Some(params()),
+        ..Swap::new(Init {
+            follow_up_sale_round: Some(true),
+            ..init()
+        })
+    };
+
+    assert!(swap
+        .abort_unopened_sale_round(
+            SNS_GOVERNANCE_CANISTER_ID.get(),
+            SWAP_CANISTER_ID,
+            now_fn,
+            &sns_ledger
+        )
+        .await
+        .is_err());
+    assert_eq!(swap.lifecycle(), Open);
+    assert_eq!(*sns_ledger_calls.lock().unwrap(), vec![]);
+}
+
 /// Test the error refund method.
 #[test]
 fn test_error_refund() {
@@ -1655,6 +2117,8 @@ fn test_error_refund() {
     assert!(swap
         .refresh_buyer_token_e8s(
             *TEST_USER1_PRINCIPAL,
+            None, // country_code
+            None, // confirmation_text
             SWAP_CANISTER_ID,
             &mock_stub(vec![LedgerExpect::AccountBalance(
                 Account {
@@ -1945,6 +2409,8 @@ fn test_get_buyer_state() {
     assert!(swap
         .refresh_buyer_token_e8s(
             *TEST_USER1_PRINCIPAL,
+            None, // country_code
+            None, // confirmation_text
             SWAP_CANISTER_ID,
             &mock_stub(vec![LedgerExpect::AccountBalance(
                 Account {
@@ -1981,6 +2447,8 @@ fn test_get_buyer_state() {
     assert!(swap
         .refresh_buyer_token_e8s(
             *TEST_USER2_PRINCIPAL,
+            None, // country_code
+            None, // confirmation_text
             SWAP_CANISTER_ID,
             &mock_stub(vec![LedgerExpect::AccountBalance(
                 Account {
@@ -2022,3 +2490,91 @@ fn test_get_buyer_state() {
         .buyer_state
         .is_none());
 }
+
+#[test]
+fn test_refresh_buyer_tokens_enforces_participation_restrictions() {
+    let init = Init {
+        restricted_countries: Some(Countries {
+            iso_codes: vec!["US".to_string()],
+        }),
+        confirmation_text_sha256: Some(Sha256::hash(b"I agree.").to_vec()),
+        allowed_participant_principal_ids: vec![TEST_USER1_PRINCIPAL.to_string()],
+        ..init()
+    };
+    let params = params();
+    let mut swap = Swap::new(init);
+    // Open swap.
+    {
+        let r = swap
+            .open(
+                SWAP_CANISTER_ID,
+                &mock_stub(vec![LedgerExpect::AccountBalance(
+                    Account {
+                        owner: SWAP_CANISTER_ID.get(),
+                        subaccount: None,
+                    },
+                    Ok(Tokens::from_e8s(params.sns_token_e8s)),
+                )]),
+                START_TIMESTAMP_SECONDS,
+                OpenRequest {
+                    params: Some(params.clone()),
+                    cf_participants: vec![],
+                    open_sns_token_swap_proposal_id: Some(OPEN_SNS_TOKEN_SWAP_PROPOSAL_ID),
+                },
+            )
+            .now_or_never()
+            .unwrap();
+        assert!(r.is_ok());
+    }
+
+    // Participants who are rejected never get to the ledger.
+    let mut refresh_rejected =
+        |buyer: PrincipalId, country_code: Option<&str>, confirmation_text: Option<&str>| {
+            let result = swap
+                .refresh_buyer_token_e8s(
+                    buyer,
+                    country_code,
+                    confirmation_text,
+                    SWAP_CANISTER_ID,
+                    &mock_stub(vec![]),
+                )
+                .now_or_never()
+                .unwrap();
+            assert!(result.is_err(), "{:#?}", result);
+        };
+    // Not on the allowlist.
+    refresh_rejected(*TEST_USER2_PRINCIPAL, Some("CH"), Some("I agree."));
+    // No or a restricted country.
+    refresh_rejected(*TEST_USER1_PRINCIPAL, None, Some("I agree."));
+    refresh_rejected(*TEST_USER1_PRINCIPAL, Some("US"), Some("I agree."));
+    refresh_rejected(*TEST_USER1_PRINCIPAL, Some("Switzerland"), Some("I agree."));
+    // No or the wrong confirmation text.
+    refresh_rejected(*TEST_USER1_PRINCIPAL, Some("CH"), None);
+    refresh_rejected(*TEST_USER1_PRINCIPAL, Some("CH"), Some("I disagree."));
+    assert!(swap.buyers.is_empty());
+
+    let result = swap
+        .refresh_buyer_token_e8s(
+            *TEST_USER1_PRINCIPAL,
+            Some("CH"),
+            Some("I agree."),
+            SWAP_CANISTER_ID,
+            &mock_stub(vec![LedgerExpect::AccountBalance(
+                Account {
+                    owner: SWAP_CANISTER_ID.get(),
+                    subaccount: Some(principal_to_subaccount(&TEST_USER1_PRINCIPAL)),
+                },
+                Ok(Tokens::from_e8s(params.min_participant_icp_e8s)),
+            )]),
+        )
+        .now_or_never()
+        .unwrap();
+    assert!(result.is_ok(), "{:#?}", result);
+    assert_eq!(
+        swap.buyers
+            .get(&TEST_USER1_PRINCIPAL.to_string())
+            .unwrap()
+            .amount_icp_e8s(),
+        params.min_participant_icp_e8s
+    );
+}
@@ -8,7 +8,7 @@ use ic_config::Config;
 use ic_crypto_sha::Sha256;
 use ic_icrc1::{endpoints::TransferArg, Account, Subaccount};
 use ic_icrc1_index::InitArgs as IndexInitArgs;
-use ic_icrc1_ledger::InitArgs as LedgerInitArgs;
+use ic_icrc1_ledger::{InitArgs as LedgerInitArgs, LedgerArgument};
 use ic_ledger_canister_core::archive::ArchiveOptions;
 use ic_ledger_core::Tokens;
 use ic_nervous_system_root::{CanisterStatusResult, CanisterStatusType};
@@ -140,6 +140,7 @@ impl SnsTestsInitPayloadBuilder {
             token_symbol: "TKX".to_string(),
             token_name: "Token Example".to_string(),
             metadata: vec![],
+            fee_collector_account: None,
         };
 
         let swap = SwapInit {
@@ -1284,7 +1285,7 @@ pub async fn install_ledger_canister<'runtime, 'a>(
         canister,
         "ic-icrc1-ledger",
         &[],
-        Some(CandidOne(args).into_bytes().unwrap()),
+        Some(CandidOne(LedgerArgument::Init(args)).into_bytes().unwrap()),
         SNS_MAX_CANISTER_MEMORY_ALLOCATION_IN_BYTES,
     )
     .await
@@ -2,6 +2,7 @@ use crate::itest_helpers::populate_canister_ids;
 use candid::Encode;
 use ic_base_types::{CanisterId, PrincipalId};
 use ic_ic00_types::CanisterInstallMode;
+use ic_icrc1_ledger::LedgerArgument;
 use ic_nns_constants::ROOT_CANISTER_ID as NNS_ROOT_CANISTER_ID;
 use ic_nns_test_utils::sns_wasm::{
     build_governance_sns_wasm, build_index_sns_wasm, build_ledger_sns_wasm, build_root_sns_wasm,
@@ -98,7 +99,7 @@ pub fn setup_sns_canisters(
     install_canister(
         ledger_canister_id,
         build_ledger_sns_wasm().wasm,
-        Encode!(&ledger).unwrap(),
+        Encode!(&LedgerArgument::Init(ledger)).unwrap(),
     );
     install_canister(
         swap_canister_id,
@@ -14,7 +14,8 @@ use ic_protobuf::{
 };
 use ic_replicated_state::{
     bitcoin_state, canister_state::execution_state::WasmMetadata, CallContextManager,
-    CanisterStatus, ExecutionTask, ExportedFunctions, Global, NumWasmPages,
+    CanisterHistory, CanisterStatus, ExecutionTask, ExportedFunctions, Global, NumWasmPages,
+    TotalQueryStats,
 };
 use ic_sys::mmap::ScopedMmap;
 use ic_types::{
@@ -131,6 +132,12 @@ pub struct CanisterStateBits {
     pub time_of_last_allocation_charge_nanos: u64,
     pub global_timer_nanos: Option<u64>,
     pub canister_version: u64,
+    pub ingress_priority_fee: Cycles,
+    pub canister_history: CanisterHistory,
+    pub wasm_memory_limit: Option<NumBytes>,
+    pub reserved_balance: Cycles,
+    pub reserved_balance_limit: Option<Cycles>,
+    pub total_query_stats: TotalQueryStats,
 }
 
 /// This struct contains bits of the `BitcoinState` that are not already
@@ -183,6 +190,7 @@ impl Default for BitcoinStateBits {
 /// │           ├── queues.pbuf
 /// │           ├── vmemory_0.bin
 /// │           ├── canister.pbuf
+/// │           ├── wasm_chunk_store.pbuf
 /// │           ├── stable_memory.(pbuf|bin)
 /// │           └── software.wasm
 /// │
@@ -201,6 +209,7 @@ impl Default for BitcoinStateBits {
 /// │              ├── queues.pbuf
 /// │              ├── vmemory_0.bin
 /// │              ├── canister.pbuf
+/// │              ├── wasm_chunk_store.pbuf
 /// │              ├── stable_memory.(pbuf|bin)
 /// │              └── software.wasm
 /// │
@@ -1038,6 +1047,12 @@ impl<Permissions: AccessPolicy> CanisterLayout<Permissions> {
         self.canister_root.join("canister.pbuf").into()
     }
 
+    pub fn wasm_chunk_store(
+        &self,
+    ) -> ProtoFileWith<pb_canister_state_bits::WasmChunkStore, Permissions> {
+        self.canister_root.join("wasm_chunk_store.pbuf").into()
+    }
+
     pub fn vmemory_0(&self) -> PathBuf {
         self.canister_root.join("vmemory_0.bin")
     }
@@ -1309,6 +1324,12 @@ impl From<CanisterStateBits> for pb_canister_state_bits::CanisterStateBits {
             task_queue: item.task_queue.iter().map(|v| v.into()).collect(),
             global_timer_nanos: item.global_timer_nanos,
             canister_version: item.canister_version,
+            ingress_priority_fee: Some(item.ingress_priority_fee.into()),
+            canister_history: Some((&item.canister_history).into()),
+            wasm_memory_limit: item.wasm_memory_limit.map(|limit| limit.get()),
+            reserved_balance: Some(item.reserved_balance.into()),
+            reserved_balance_limit: item.reserved_balance_limit.map(|limit| limit.into()),
+            total_query_stats: Some((&item.total_query_stats).into()),
         }
     }
 }
@@ -1348,6 +1369,23 @@ impl TryFrom<pb_canister_state_bits::CanisterStateBits> for CanisterStateBits {
             .transpose()?
             .unwrap_or_else(Cycles::zero);
 
+        let ingress_priority_fee = value
+            .ingress_priority_fee
+            .map(|c| c.try_into())
+            .transpose()?
+            .unwrap_or_else(Cycles::zero);
+
+        let reserved_balance = value
+            .reserved_balance
+            .map(|c| c.try_into())
+            .transpose()?
+            .unwrap_or_else(Cycles::zero);
+
+        let reserved_balance_limit = value
+            .reserved_balance_limit
+            .map(|c| c.try_into())
+            .transpose()?;
+
         let task_queue = value
             .task_queue
             .into_iter()
@@ -1394,6 +1432,20 @@ impl TryFrom<pb_canister_state_bits::CanisterStateBits> for CanisterStateBits {
             task_queue,
             global_timer_nanos: value.global_timer_nanos,
             canister_version: value.canister_version,
+            ingress_priority_fee,
+            canister_history: value
+                .canister_history
+                .map(CanisterHistory::try_from)
+                .transpose()?
+                .unwrap_or_default(),
+            wasm_memory_limit: value.wasm_memory_limit.map(NumBytes::from),
+            reserved_balance,
+            reserved_balance_limit,
+            total_query_stats: value
+                .total_query_stats
+                .map(TotalQueryStats::try_from)
+                .transpose()?
+                .unwrap_or_default(),
         })
     }
 }
@@ -1824,6 +1876,12 @@ mod test {
             task_queue: vec![],
             global_timer_nanos: None,
             canister_version: 0,
+            ingress_priority_fee: Cycles::zero(),
+            canister_history: CanisterHistory::default(),
+            wasm_memory_limit: None,
+            reserved_balance: Cycles::zero(),
+            reserved_balance_limit: None,
+            total_query_stats: TotalQueryStats::default(),
         }
     }
 
@@ -1,5 +1,6 @@
 use ic_config::flag_status::FlagStatus;
 use ic_config::{
+    embedders::Config as EmbeddersConfig,
     execution_environment::Config as HypervisorConfig,
     subnet_config::{SubnetConfig, SubnetConfigs},
 };
@@ -12,6 +13,16 @@ use ic_crypto_internal_threshold_sig_bls12381::types::SecretKeyBytes;
 use ic_crypto_internal_types::sign::threshold_sig::public_key::CspThresholdSigPublicKey;
 use ic_crypto_tree_hash::{flatmap, Label, LabeledTree, LabeledTree::SubTree};
 use ic_cycles_account_manager::CyclesAccountManager;
+use ic_embedders::{
+    wasm_utils::{
+        decoding::decode_wasm,
+        new_instrumentation::{
+            exported_global_names, profiled_function_names, PROFILE_INSTRUCTIONS_PREFIX,
+        },
+        validate_and_instrument_for_testing,
+    },
+    WasmtimeEmbedder,
+};
 pub use ic_error_types::{ErrorCode, UserError};
 use ic_execution_environment::ExecutionServices;
 use ic_ic00_types::{self as ic00, CanisterIdRecord, InstallCodeArgs, Method, Payload};
@@ -26,7 +37,7 @@ use ic_interfaces::{
 };
 use ic_interfaces_registry::RegistryClient;
 use ic_interfaces_state_manager::{CertificationScope, StateHashError, StateManager, StateReader};
-use ic_logger::ReplicaLogger;
+use ic_logger::{replica_logger::no_op_logger, ReplicaLogger};
 use ic_messaging::MessageRoutingImpl;
 use ic_metrics::MetricsRegistry;
 use ic_protobuf::registry::{
@@ -55,7 +66,7 @@ use ic_replicated_state::metadata_state::subnet_call_context_manager::SignWithEc
 use ic_replicated_state::page_map::Buffer;
 use ic_replicated_state::{
     canister_state::{NumWasmPages, WASM_PAGE_SIZE_IN_BYTES},
-    Memory, PageMap, ReplicatedState,
+    Global, Memory, PageMap, ReplicatedState,
 };
 use ic_state_manager::StateManagerImpl;
 use ic_test_utilities_metrics::{fetch_histogram_stats, fetch_int_counter};
@@ -79,7 +90,7 @@ use ic_types::{
         Blob, HttpCallContent, HttpCanisterUpdate, HttpRequestEnvelope, SignedIngress, UserQuery,
     },
     time::current_time_and_expiry_time,
-    CryptoHashOfPartialState, Height, NodeId, NumberOfNodes, Randomness, RegistryVersion,
+    CryptoHashOfPartialState, Height, NodeId, NumBytes, NumberOfNodes, Randomness, RegistryVersion,
 };
 pub use ic_types::{
     ingress::{IngressState, IngressStatus, WasmResult},
@@ -90,6 +101,7 @@ pub use ic_types::{
 use serde::Serialize;
 pub use slog::Level;
 use std::fmt;
+use std::path::Path;
 use std::str::FromStr;
 use std::string::ToString;
 use std::sync::Arc;
@@ -247,6 +259,48 @@ impl StateMachineConfig {
     }
 }
 
+/// Instructions executed by every Wasm function of a canister while processing a
+/// single message, as returned by [StateMachine::profile_ingress].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstructionProfile {
+    /// The name of the method that was called.
+    pub method: String,
+    /// The instructions executed in the body of each function, excluding the
+    /// functions it called. Functions that executed no instructions are omitted.
+    pub instructions_by_function: BTreeMap<String, u64>,
+    /// The memory usage of the canister before the message was executed.
+    pub memory_usage_before: NumBytes,
+    /// The memory usage of the canister after the message was executed.
+    pub memory_usage_after: NumBytes,
+}
+
+impl InstructionProfile {
+    /// Returns the total number of instructions executed by all functions.
+    pub fn total_instructions(&self) -> u64 {
+        self.instructions_by_function.values().sum()
+    }
+
+    /// Renders the profile as one `<function> <instructions>` line per
+    /// function.
+    ///
+    /// The profile is flat: it records no call stacks, so every function is
+    /// reported on its own. Flamegraph tools accept the lines as single-frame
+    /// stacks.
+    pub fn to_flat_profile(&self) -> String {
+        self.instructions_by_function
+            .iter()
+            .map(|(function, instructions)| {
+                format!("{} {}\n", function.replace([';', ' '], "_"), instructions)
+            })
+            .collect()
+    }
+
+    /// Writes the flat profile to the specified file.
+    pub fn write_flat_profile(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_flat_profile())
+    }
+}
+
 /// Represents a replicated state machine detached from the network layer that
 /// can be used to test this part of the stack in isolation.
 pub struct StateMachine {
@@ -1165,6 +1219,111 @@ impl StateMachine {
         self.execute_ingress_as(PrincipalId::new_anonymous(), canister_id, method, payload)
     }
 
+    /// Executes an ingress message like [execute_ingress_as] and additionally
+    /// returns the number of instructions every Wasm function of the canister
+    /// executed while processing it.
+    ///
+    /// The state machine must be created with the `instruction_profiling` flag
+    /// enabled in the hypervisor config and the canister must be installed
+    /// afterwards. Function names are taken from the `name` section of the
+    /// canister module, if present.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the specified canister does not exist or if its
+    /// module was not instrumented for instruction profiling.
+    pub fn profile_ingress(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        method: impl ToString,
+        payload: Vec<u8>,
+    ) -> Result<(WasmResult, InstructionProfile), UserError> {
+        let method = method.to_string();
+        let (function_names, counters_before, memory_usage_before) =
+            self.instruction_profiling_counters(canister_id);
+        let result = self.execute_ingress_as(sender, canister_id, method.clone(), payload)?;
+        let (_, counters_after, memory_usage_after) =
+            self.instruction_profiling_counters(canister_id);
+
+        let mut instructions_by_function = BTreeMap::new();
+        for (function_ix, after) in counters_after {
+            let before = counters_before.get(&function_ix).copied().unwrap_or(0);
+            let instructions = after.saturating_sub(before);
+            if instructions > 0 {
+                *instructions_by_function
+                    .entry(function_names[function_ix].clone())
+                    .or_insert(0) += instructions;
+            }
+        }
+        Ok((
+            result,
+            InstructionProfile {
+                method,
+                instructions_by_function,
+                memory_usage_before,
+                memory_usage_after,
+            },
+        ))
+    }
+
+    /// Returns the names of the profiled functions of the specified canister,
+    /// the values of their instruction counters by function index and the
+    /// canister memory usage.
+    fn instruction_profiling_counters(
+        &self,
+        canister_id: CanisterId,
+    ) -> (Vec<String>, BTreeMap<usize, u64>, NumBytes) {
+        let state = self.state_manager.get_latest_state().take();
+        let canister = state
+            .canister_state(&canister_id)
+            .unwrap_or_else(|| panic!("Canister {} not found", canister_id));
+        let memory_usage = canister.memory_usage(state.metadata.own_subnet_type);
+        let execution_state = canister
+            .execution_state
+            .as_ref()
+            .unwrap_or_else(|| panic!("Canister {} has no code installed", canister_id));
+        let wasm = decode_wasm(execution_state.wasm_binary.binary.to_shared_vec())
+            .expect("Failed to decode the canister module");
+        let function_names =
+            profiled_function_names(&wasm).expect("Failed to read the canister function names");
+
+        // The exported globals of the instance do not carry their names, so
+        // instrument the module again to learn the export name of each one.
+        let mut config = EmbeddersConfig::new();
+        config.feature_flags.instruction_profiling = FlagStatus::Enabled;
+        let (_, instrumentation_output) = validate_and_instrument_for_testing(
+            &WasmtimeEmbedder::new(config, no_op_logger()),
+            &wasm,
+        )
+        .expect("Failed to instrument the canister module");
+        let global_names = exported_global_names(&instrumentation_output.binary)
+            .expect("Failed to read the exported globals of the canister module");
+        let globals = &execution_state.exported_globals;
+        assert_eq!(
+            globals.len(),
+            global_names.len(),
+            "Canister {} was not instrumented for instruction profiling",
+            canister_id
+        );
+
+        let counters = global_names
+            .iter()
+            .zip(globals.iter())
+            .filter_map(|(name, global)| {
+                let function_ix = name
+                    .strip_prefix(PROFILE_INSTRUCTIONS_PREFIX)?
+                    .parse::<usize>()
+                    .expect("Invalid profiling counter name");
+                match global {
+                    Global::I64(value) => Some((function_ix, *value as u64)),
+                    _ => panic!("Profiling counter {} is not an i64 global", name),
+                }
+            })
+            .collect();
+        (function_names, counters, memory_usage)
+    }
+
     /// Sends an ingress message to the canister with the specified ID.
     ///
     /// This function is asynchronous. It returns the ID of the ingress message
@@ -11,6 +11,7 @@ use ic_replicated_state::{
     canister_state::execution_state::WasmBinary,
     page_map::PageMap,
     CanisterMetrics, CanisterState, ExecutionState, ReplicatedState, SchedulerState, SystemState,
+    WasmChunkStore,
 };
 use ic_state_layout::{
     BitcoinStateBits, CanisterLayout, CanisterStateBits, CheckpointLayout, ReadOnly, ReadPolicy,
@@ -314,6 +315,16 @@ pub fn load_canister_state<P: ReadPolicy>(
             })?;
     durations.insert("canister_queues", starting_time.elapsed());
 
+    // Checkpoints written before the chunk store was introduced do not
+    // contain the file, which is equivalent to an empty store.
+    let starting_time = Instant::now();
+    let wasm_chunk_store = canister_layout
+        .wasm_chunk_store()
+        .deserialize_opt()?
+        .map(WasmChunkStore::from)
+        .unwrap_or_default();
+    durations.insert("wasm_chunk_store", starting_time.elapsed());
+
     let canister_metrics = CanisterMetrics {
         scheduled_as_first: canister_state_bits.scheduled_as_first,
         skipped_round_due_to_no_messages: canister_state_bits.skipped_round_due_to_no_messages,
@@ -336,6 +347,13 @@ pub fn load_canister_state<P: ReadPolicy>(
         canister_state_bits.task_queue.into_iter().collect(),
         CanisterTimer::from_nanos_since_unix_epoch(canister_state_bits.global_timer_nanos),
         canister_state_bits.canister_version,
+        canister_state_bits.ingress_priority_fee,
+        wasm_chunk_store,
+        canister_state_bits.canister_history,
+        canister_state_bits.wasm_memory_limit,
+        canister_state_bits.reserved_balance,
+        canister_state_bits.reserved_balance_limit,
+        canister_state_bits.total_query_stats,
     );
 
     let canister_state = CanisterState {
@@ -536,6 +554,7 @@ mod tests {
                 checkpoint_path.join("system_metadata.pbuf"),
                 canister_path.join("queues.pbuf"),
                 canister_path.join("canister.pbuf"),
+                canister_path.join("wasm_chunk_store.pbuf"),
             ];
 
             for path in expected_paths {
@@ -1,13 +1,23 @@
 use super::*;
 use ic_base_types::NumSeconds;
 use ic_canonical_state::MAX_SUPPORTED_CERTIFICATION_VERSION;
+use ic_certification_version::{CertificationVersion, CURRENT_CERTIFICATION_VERSION};
+use ic_config::{execution_environment::Config as HypervisorConfig, flag_status::FlagStatus};
 use ic_registry_subnet_type::SubnetType;
-use ic_replicated_state::{testing::ReplicatedStateTesting, ReplicatedState};
+use ic_replicated_state::{testing::ReplicatedStateTesting, ReplicatedState, Stream};
 use ic_test_utilities::{
     state::{arb_stream, new_canister_state},
-    types::ids::{canister_test_id, subnet_test_id, user_test_id},
+    types::{
+        ids::{canister_test_id, subnet_test_id, user_test_id},
+        messages::RequestBuilder,
+    },
+};
+use ic_types::{
+    messages::{Request, RequestOrResponse, NO_DEADLINE},
+    time::CoarseTime,
+    xnet::{StreamIndex, StreamIndexedQueue, StreamSlice},
+    Cycles,
 };
-use ic_types::{xnet::StreamSlice, Cycles};
 use proptest::prelude::*;
 
 const INITIAL_CYCLES: Cycles = Cycles::new(1 << 36);
@@ -70,3 +80,64 @@ proptest! {
         }
     }
 }
+
+/// Round-trips a stream at the certification version that replicas currently
+/// use. Before version 12, the deadline of a best-effort request is not
+/// encoded, so the request would arrive at the remote subnet as a
+/// guaranteed-response request. Best-effort calls must therefore be disabled
+/// by default until version 12 is current.
+#[test]
+fn stream_encode_decode_roundtrip_at_current_certification_version() {
+    let guaranteed: RequestOrResponse = RequestBuilder::new().build().into();
+    let best_effort = RequestBuilder::new()
+        .deadline(CoarseTime::from_secs_since_unix_epoch(1000))
+        .build();
+
+    let mut messages = StreamIndexedQueue::with_begin(StreamIndex::new(0));
+    messages.push(guaranteed.clone());
+    messages.push(best_effort.clone().into());
+    let stream = Stream::new(messages, StreamIndex::new(0));
+
+    let mut state = ReplicatedState::new(subnet_test_id(1), SubnetType::Application);
+    let subnet = subnet_test_id(42);
+    state.modify_streams(|streams| {
+        streams.insert(subnet, stream);
+    });
+    state.metadata.certification_version = CURRENT_CERTIFICATION_VERSION;
+
+    let tree_encoding = encode_stream_slice(
+        &state,
+        subnet,
+        StreamIndex::new(0),
+        StreamIndex::new(2),
+        None,
+    )
+    .0;
+    let bytes = encode_tree(tree_encoding);
+    let (actual_subnet, slice) = decode_stream_slice(&bytes[..]).unwrap();
+    assert_eq!(subnet, actual_subnet);
+    let decoded: Vec<_> = slice
+        .messages()
+        .unwrap()
+        .iter()
+        .map(|(_, msg)| msg.clone())
+        .collect();
+    assert_eq!(decoded[0], guaranteed);
+
+    if CURRENT_CERTIFICATION_VERSION >= CertificationVersion::V12 {
+        assert_eq!(decoded[1], best_effort.into());
+    } else {
+        assert_eq!(
+            decoded[1],
+            Request {
+                deadline: NO_DEADLINE,
+                ..best_effort
+            }
+            .into()
+        );
+        assert_eq!(
+            HypervisorConfig::default().best_effort_responses,
+            FlagStatus::Disabled
+        );
+    }
+}
@@ -301,6 +301,9 @@ fn serialize_canister_to_tip(
     canister_layout
         .queues()
         .serialize(canister_state.system_state.queues().into())?;
+    canister_layout
+        .wasm_chunk_store()
+        .serialize((&canister_state.system_state.wasm_chunk_store).into())?;
 
     let execution_state_bits = match &canister_state.execution_state {
         Some(execution_state) => {
@@ -406,6 +409,12 @@ fn serialize_canister_to_tip(
                     .global_timer
                     .to_nanos_since_unix_epoch(),
                 canister_version: canister_state.system_state.canister_version,
+                ingress_priority_fee: canister_state.system_state.ingress_priority_fee,
+                canister_history: canister_state.system_state.canister_history.clone(),
+                wasm_memory_limit: canister_state.system_state.wasm_memory_limit,
+                reserved_balance: canister_state.system_state.reserved_balance(),
+                reserved_balance_limit: canister_state.system_state.reserved_balance_limit,
+                total_query_stats: canister_state.system_state.total_query_stats.clone(),
             }
             .into(),
         )
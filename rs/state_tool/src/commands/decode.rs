@@ -4,7 +4,7 @@ use ic_protobuf::state::{
     canister_state_bits::v1 as pb_canister, queues::v1 as pb_queues,
     system_metadata::v1 as pb_metadata,
 };
-use ic_replicated_state::{canister_state::CanisterQueues, SystemMetadata};
+use ic_replicated_state::{canister_state::CanisterQueues, SystemMetadata, WasmChunkStore};
 use ic_state_layout::{CanisterStateBits, ProtoFileWith, ReadOnly};
 use std::convert::TryFrom;
 use std::path::PathBuf;
@@ -27,6 +27,9 @@ pub fn do_decode(path: PathBuf) -> Result<(), String> {
         "canister.pbuf" => {
             display_proto::<pb_canister::CanisterStateBits, CanisterStateBits>(path.clone())
         }
+        "wasm_chunk_store.pbuf" => {
+            display_proto::<pb_canister::WasmChunkStore, WasmChunkStore>(path.clone())
+        }
         _ => Err(format!("don't know how to decode {}", fname)),
     }
 }
@@ -20,8 +20,11 @@ use ic_replicated_state::{memory_required_to_push_request, Memory, NumWasmPages,
 use ic_sys::PageBytes;
 use ic_types::{
     ingress::WasmResult,
-    messages::{CallContextId, RejectContext, Request, MAX_INTER_CANISTER_PAYLOAD_IN_BYTES},
+    messages::{
+        CallContextId, RejectContext, Request, MAX_INTER_CANISTER_PAYLOAD_IN_BYTES, NO_DEADLINE,
+    },
     methods::{Callback, SystemMethod, WasmClosure},
+    time::CoarseTime,
     CanisterId, CanisterTimer, ComputeAllocation, Cycles, NumBytes, NumInstructions, NumPages,
     PrincipalId, SubnetId, Time,
 };
@@ -32,6 +35,7 @@ use serde::{Deserialize, Serialize};
 use stable_memory::StableMemory;
 use std::{
     convert::{From, TryFrom},
+    ops::Range,
     sync::Arc,
 };
 
@@ -39,6 +43,10 @@ const MULTIPLIER_MAX_SIZE_LOCAL_SUBNET: u64 = 5;
 const MAX_NON_REPLICATED_QUERY_REPLY_SIZE: NumBytes = NumBytes::new(3 << 20);
 const CERTIFIED_DATA_MAX_LENGTH: u32 = 32;
 
+/// The maximum timeout of a best-effort call, in seconds. Larger timeouts
+/// passed to `ic0.call_with_best_effort_response` are silently capped.
+pub const MAX_CALL_TIMEOUT_SECONDS: u32 = 300;
+
 // Enables tracing of system calls for local debugging.
 const TRACE_SYSCALLS: bool = false;
 
@@ -144,6 +152,10 @@ impl InstructionLimits {
 pub struct ExecutionParameters {
     pub instruction_limits: InstructionLimits,
     pub canister_memory_limit: NumBytes,
+    // Limit on the size of the Wasm heap enforced on `memory.grow`. It is
+    // `None` if the canister has no such limit or if the executed method is
+    // an upgrade hook.
+    pub wasm_memory_limit: Option<NumBytes>,
     pub compute_allocation: ComputeAllocation,
     pub subnet_type: SubnetType,
     pub execution_mode: ExecutionMode,
@@ -1796,6 +1808,7 @@ impl SystemApi for SystemApiImpl {
                             on_reply,
                             on_reject,
                             None,
+                            NO_DEADLINE,
                         ))?;
 
                 let msg = Request {
@@ -1805,6 +1818,7 @@ impl SystemApi for SystemApiImpl {
                     method_payload: payload,
                     sender_reply_callback: callback_id,
                     payment: Cycles::zero(),
+                    deadline: NO_DEADLINE,
                 };
                 self.push_output_request(
                     msg,
@@ -2001,6 +2015,62 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
+    fn ic0_call_with_best_effort_response(&mut self, timeout_seconds: u32) -> HypervisorResult<()> {
+        let result = match &mut self.api_type {
+            ApiType::Start { .. }
+            | ApiType::Init { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery {
+                query_kind: NonReplicatedQueryKind::Pure,
+                ..
+            }
+            | ApiType::Cleanup { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => {
+                Err(self.error_for("ic0_call_with_best_effort_response"))
+            }
+            ApiType::Update {
+                time,
+                outgoing_request,
+                ..
+            }
+            | ApiType::NonReplicatedQuery {
+                time,
+                query_kind:
+                    NonReplicatedQueryKind::Stateful {
+                        outgoing_request, ..
+                    },
+                ..
+            }
+            | ApiType::SystemTask {
+                time,
+                outgoing_request,
+                ..
+            }
+            | ApiType::ReplyCallback {
+                time,
+                outgoing_request,
+                ..
+            }
+            | ApiType::RejectCallback {
+                time,
+                outgoing_request,
+                ..
+            } => match outgoing_request {
+                None => Err(HypervisorError::ContractViolation(
+                    "ic0.call_with_best_effort_response called when no call is under construction."
+                        .to_string(),
+                )),
+                Some(request) => request.set_deadline(
+                    CoarseTime::floor(*time)
+                        .saturating_add_secs(timeout_seconds.min(MAX_CALL_TIMEOUT_SECONDS)),
+                ),
+            },
+        };
+        trace_syscall!(self, ic0_call_with_best_effort_response, timeout_seconds);
+        result
+    }
+
     fn ic0_call_cycles_add(&mut self, amount: u64) -> HypervisorResult<()> {
         let result = self.ic0_call_cycles_add_helper("ic0_call_cycles_add", Cycles::from(amount));
         trace_syscall!(self, ic0_call_cycles_add, result, amount);
@@ -2322,6 +2392,63 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
+    fn ic0_stable64_checkpoint(
+        &mut self,
+        offset: u64,
+        src: u64,
+        size: u64,
+        modified_heap_ranges: &[Range<u64>],
+        heap: &[u8],
+    ) -> HypervisorResult<u64> {
+        let result = match &self.api_type {
+            ApiType::Start {} => Err(self.error_for("ic0_stable64_checkpoint")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Update { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::InspectMessage { .. } => self.stable_memory.stable64_checkpoint(
+                offset,
+                src,
+                size,
+                modified_heap_ranges,
+                heap,
+            ),
+        };
+        trace_syscall!(self, ic0_stable64_checkpoint, result, offset, src, size);
+        result
+    }
+
+    fn ic0_stable64_restore(
+        &mut self,
+        dst: u64,
+        offset: u64,
+        size: u64,
+        heap: &mut [u8],
+    ) -> HypervisorResult<u64> {
+        let result = match &self.api_type {
+            ApiType::Start {} => Err(self.error_for("ic0_stable64_restore")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Update { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::InspectMessage { .. } => {
+                self.stable_memory.stable64_restore(dst, offset, size, heap)
+            }
+        };
+        trace_syscall!(self, ic0_stable64_restore, result, dst, offset, size);
+        result
+    }
+
     fn dirty_pages_from_stable_write(
         &self,
         offset: u64,
@@ -2334,6 +2461,17 @@ impl SystemApi for SystemApiImpl {
         Ok((dirty_pages, cost))
     }
 
+    fn stable64_differing_ranges(
+        &self,
+        offset: u64,
+        src: u64,
+        size: u64,
+        heap: &[u8],
+    ) -> HypervisorResult<Vec<Range<u64>>> {
+        self.stable_memory
+            .stable64_differing_ranges(offset, src, size, heap)
+    }
+
     fn ic0_time(&self) -> HypervisorResult<Time> {
         let result = match &self.api_type {
             ApiType::Start { .. } => Err(self.error_for("ic0_time")),
@@ -2434,6 +2572,16 @@ impl SystemApi for SystemApiImpl {
             if native_memory_grow_res == -1 {
                 return Ok(-1);
             }
+            if let Some(limit) = self.execution_parameters.wasm_memory_limit {
+                // `native_memory_grow_res` is the size of the Wasm memory in
+                // pages before the grow.
+                let new_pages = native_memory_grow_res as usize + additional_pages as usize;
+                let bytes = ic_replicated_state::num_bytes_try_from(NumWasmPages::from(new_pages))
+                    .map_err(|_| HypervisorError::OutOfMemory)?;
+                if bytes > limit {
+                    return Err(HypervisorError::WasmMemoryLimitExceeded { bytes, limit });
+                }
+            }
             match self.memory_usage.allocate_pages(additional_pages as usize) {
                 Ok(()) => Ok(native_memory_grow_res),
                 Err(_err) => Err(HypervisorError::OutOfMemory),
@@ -2,8 +2,9 @@ use crate::{sandbox_safe_system_state::SandboxSafeSystemState, valid_subslice};
 use ic_interfaces::execution_environment::{HypervisorError, HypervisorResult};
 use ic_logger::ReplicaLogger;
 use ic_types::{
-    messages::{CallContextId, Request},
+    messages::{CallContextId, Request, NO_DEADLINE},
     methods::{Callback, WasmClosure},
+    time::CoarseTime,
     CanisterId, Cycles, NumBytes, PrincipalId,
 };
 use serde::{Deserialize, Serialize};
@@ -37,6 +38,9 @@ pub struct RequestInPrep {
     cycles: Cycles,
     method_name: String,
     method_payload: Vec<u8>,
+    /// The deadline of a best-effort call; `NO_DEADLINE` for guaranteed
+    /// response calls.
+    deadline: CoarseTime,
     /// The maximum size of a message that will go to a canister on another
     /// subnet.
     max_size_remote_subnet: NumBytes,
@@ -94,6 +98,7 @@ impl RequestInPrep {
             cycles: Cycles::zero(),
             method_name,
             method_payload: Vec::new(),
+            deadline: NO_DEADLINE,
             max_size_remote_subnet,
             multiplier_max_size_local_subnet,
         })
@@ -111,6 +116,18 @@ impl RequestInPrep {
         }
     }
 
+    pub(crate) fn set_deadline(&mut self, deadline: CoarseTime) -> HypervisorResult<()> {
+        if self.deadline != NO_DEADLINE {
+            Err(HypervisorError::ContractViolation(
+                "ic0.call_with_best_effort_response can be called at most once between `ic0.call_new` and `ic0.call_perform`"
+                    .to_string(),
+            ))
+        } else {
+            self.deadline = deadline;
+            Ok(())
+        }
+    }
+
     pub(crate) fn take_cycles(self) -> Cycles {
         self.cycles
     }
@@ -159,6 +176,7 @@ pub(crate) fn into_request(
         cycles,
         method_name,
         method_payload,
+        deadline,
         max_size_remote_subnet,
         multiplier_max_size_local_subnet,
     }: RequestInPrep,
@@ -195,6 +213,7 @@ pub(crate) fn into_request(
         on_reply,
         on_reject,
         on_cleanup,
+        deadline,
     ))?;
 
     let req = Request {
@@ -204,6 +223,7 @@ pub(crate) fn into_request(
         method_payload,
         sender_reply_callback: callback_id,
         payment: cycles,
+        deadline,
     };
     // We cannot call `Request::payload_size_bytes()` before constructing the
     // request, so ensure our separate calculation matches the actual size.
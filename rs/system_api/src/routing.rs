@@ -6,9 +6,10 @@ use ic_base_types::{CanisterId, PrincipalId, SubnetId};
 use ic_btc_types::NetworkInRequest as BitcoinNetwork;
 use ic_ic00_types::{
     BitcoinGetBalanceArgs, BitcoinGetCurrentFeePercentilesArgs, BitcoinGetUtxosArgs,
-    BitcoinSendTransactionArgs, CanisterIdRecord, ComputeInitialEcdsaDealingsArgs,
-    ECDSAPublicKeyArgs, EcdsaKeyId, InstallCodeArgs, Method as Ic00Method, Payload,
-    ProvisionalTopUpCanisterArgs, SetControllerArgs, SignWithECDSAArgs, UpdateSettingsArgs,
+    BitcoinSendTransactionArgs, CanisterIdRecord, CanisterInfoRequest,
+    ComputeInitialEcdsaDealingsArgs, ECDSAPublicKeyArgs, EcdsaKeyId, InstallChunkedCodeArgs,
+    InstallCodeArgs, Method as Ic00Method, Payload, ProvisionalTopUpCanisterArgs,
+    SetControllerArgs, SignWithECDSAArgs, UpdateSettingsArgs, UploadChunkArgs,
 };
 use ic_replicated_state::NetworkTopology;
 
@@ -75,6 +76,43 @@ pub(super) fn resolve_destination(
                     ResolveDestinationError::SubnetNotFound(canister_id, Ic00Method::InstallCode)
                 })
         }
+        Ok(Ic00Method::InstallChunkedCode) => {
+            // Find the destination canister from the payload.
+            let args = Decode!(payload, InstallChunkedCodeArgs)?;
+            let canister_id = args.get_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .map(|subnet_id| subnet_id.get())
+                .ok_or({
+                    ResolveDestinationError::SubnetNotFound(
+                        canister_id,
+                        Ic00Method::InstallChunkedCode,
+                    )
+                })
+        }
+        Ok(Ic00Method::UploadChunk) => {
+            let args = Decode!(payload, UploadChunkArgs)?;
+            let canister_id = args.get_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .map(|subnet_id| subnet_id.get())
+                .ok_or({
+                    ResolveDestinationError::SubnetNotFound(canister_id, Ic00Method::UploadChunk)
+                })
+        }
+        Ok(Ic00Method::CanisterInfo) => {
+            let args = Decode!(payload, CanisterInfoRequest)?;
+            let canister_id = args.canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .map(|subnet_id| subnet_id.get())
+                .ok_or({
+                    ResolveDestinationError::SubnetNotFound(canister_id, Ic00Method::CanisterInfo)
+                })
+        }
         Ok(Ic00Method::SetController) => {
             let args = Decode!(payload, SetControllerArgs)?;
             let canister_id = args.get_canister_id();
@@ -91,6 +129,7 @@ pub(super) fn resolve_destination(
         | Ok(Ic00Method::StopCanister)
         | Ok(Ic00Method::DeleteCanister)
         | Ok(Ic00Method::UninstallCode)
+        | Ok(Ic00Method::ClearChunkStore)
         | Ok(Ic00Method::DepositCycles) => {
             let args = Decode!(payload, CanisterIdRecord)?;
             let canister_id = args.get_canister_id();
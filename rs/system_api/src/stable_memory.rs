@@ -1,10 +1,11 @@
-use std::convert::TryInto;
+use std::{convert::TryInto, ops::Range};
 
 use ic_interfaces::execution_environment::{
     HypervisorError, HypervisorResult,
     TrapCode::{HeapOutOfBounds, StableMemoryOutOfBounds, StableMemoryTooBigFor32Bit},
 };
 use ic_replicated_state::{canister_state::WASM_PAGE_SIZE_IN_BYTES, page_map, NumWasmPages};
+use ic_sys::PAGE_SIZE;
 use ic_types::{NumPages, MAX_STABLE_MEMORY_IN_BYTES};
 
 const MAX_64_BIT_STABLE_MEMORY_IN_PAGES: usize =
@@ -170,7 +171,125 @@ impl StableMemory {
         size: u64,
         heap: &[u8],
     ) -> HypervisorResult<()> {
-        let (src, offset, size) = (src as usize, offset as usize, size as usize);
+        let heap_end = self.check_stable64_bounds(offset, src, size, heap)?;
+        let (src, offset) = (src as usize, offset as usize);
+
+        self.stable_memory_buffer
+            .write(&heap[src..heap_end], offset);
+        Ok(())
+    }
+
+    /// Returns the ranges of `[src, src+size)` of the heap whose contents
+    /// differ from the corresponding locations in `[offset, offset+size)` of
+    /// the stable memory. The ranges are computed at OS page granularity.
+    ///
+    /// Traps under the same conditions as `stable64_write`.
+    pub(super) fn stable64_differing_ranges(
+        &self,
+        offset: u64,
+        src: u64,
+        size: u64,
+        heap: &[u8],
+    ) -> HypervisorResult<Vec<Range<u64>>> {
+        let heap_end = self.check_stable64_bounds(offset, src, size, heap)? as u64;
+        Ok(self.differing_ranges(offset, src, heap_end, heap))
+    }
+
+    /// Copies the given ranges of the heap, restricted to `[src, src+size)`,
+    /// to the corresponding locations in `[offset, offset+size)` of the
+    /// stable memory.
+    ///
+    /// Traps under the same conditions as `stable64_write`, regardless of
+    /// which ranges are given.
+    /// Returns the number of bytes copied.
+    pub(super) fn stable64_checkpoint(
+        &mut self,
+        offset: u64,
+        src: u64,
+        size: u64,
+        modified_heap_ranges: &[Range<u64>],
+        heap: &[u8],
+    ) -> HypervisorResult<u64> {
+        let heap_end = self.check_stable64_bounds(offset, src, size, heap)? as u64;
+
+        let mut copied_bytes = 0;
+        for range in modified_heap_ranges {
+            let start = range.start.max(src);
+            let end = range.end.min(heap_end);
+            if start >= end {
+                continue;
+            }
+            self.stable_memory_buffer.write(
+                &heap[start as usize..end as usize],
+                (offset + (start - src)) as usize,
+            );
+            copied_bytes += end - start;
+        }
+        Ok(copied_bytes)
+    }
+
+    /// Copies the parts of `[offset, offset+size)` of the stable memory that
+    /// differ from the corresponding locations in `[dst, dst+size)` of the
+    /// heap back to the heap. Heap pages that already hold the same contents
+    /// are not written, so they do not become dirty.
+    ///
+    /// Traps under the same conditions as `stable64_read`.
+    /// Returns the number of bytes copied.
+    pub(super) fn stable64_restore(
+        &self,
+        dst: u64,
+        offset: u64,
+        size: u64,
+        heap: &mut [u8],
+    ) -> HypervisorResult<u64> {
+        let heap_end = self.check_stable64_bounds(offset, dst, size, heap)? as u64;
+
+        let mut copied_bytes = 0;
+        for range in self.differing_ranges(offset, dst, heap_end, heap) {
+            self.stable_memory_buffer.read(
+                &mut heap[range.start as usize..range.end as usize],
+                (offset + (range.start - dst)) as usize,
+            );
+            copied_bytes += range.end - range.start;
+        }
+        Ok(copied_bytes)
+    }
+
+    /// Compares `[start, end)` of the heap with the stable memory starting at
+    /// `offset` one OS page of the heap at a time and returns the differing
+    /// ranges. Adjacent differing pages are merged into a single range.
+    fn differing_ranges(&self, offset: u64, start: u64, end: u64, heap: &[u8]) -> Vec<Range<u64>> {
+        let page_size = PAGE_SIZE as u64;
+        let mut stable_page = vec![0; PAGE_SIZE];
+        let mut ranges: Vec<Range<u64>> = vec![];
+        let mut chunk_start = start;
+        while chunk_start < end {
+            let chunk_end = ((chunk_start / page_size + 1) * page_size).min(end);
+            let stable_chunk = &mut stable_page[..(chunk_end - chunk_start) as usize];
+            self.stable_memory_buffer
+                .read(stable_chunk, (offset + (chunk_start - start)) as usize);
+            if *stable_chunk != heap[chunk_start as usize..chunk_end as usize] {
+                match ranges.last_mut() {
+                    Some(last) if last.end == chunk_start => last.end = chunk_end,
+                    _ => ranges.push(chunk_start..chunk_end),
+                }
+            }
+            chunk_start = chunk_end;
+        }
+        ranges
+    }
+
+    /// Checks that `[offset, offset+size)` is within the stable memory and
+    /// `[heap_start, heap_start+size)` is within the heap.
+    /// Returns the end of the heap range.
+    fn check_stable64_bounds(
+        &self,
+        offset: u64,
+        heap_start: u64,
+        size: u64,
+        heap: &[u8],
+    ) -> HypervisorResult<usize> {
+        let (src, offset, size) = (heap_start as usize, offset as usize, size as usize);
 
         let (stable_memory_size_in_bytes, overflow) = self
             .stable64_size()?
@@ -188,10 +307,7 @@ impl StableMemory {
         if overflow || heap_end > heap.len() {
             return Err(HypervisorError::Trapped(HeapOutOfBounds));
         }
-
-        self.stable_memory_buffer
-            .write(&heap[src..heap_end], offset);
-        Ok(())
+        Ok(heap_end)
     }
 
     /// Calculates the number of new dirty pages that a given write would
@@ -7,6 +7,7 @@ use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::PageIndex;
 use ic_sys::PageBytes;
 use ic_types::{Cycles, NumBytes, NumInstructions, NumPages, Time};
+use std::ops::Range;
 
 const MESSAGE_UNIMPLEMENTED: &str =
     "Empty System API should not be called. Only used by the embedder to create an ExecutionState instance";
@@ -169,6 +170,9 @@ impl SystemApi for SystemApiEmpty {
     fn ic0_call_on_cleanup(&mut self, _: u32, _: u32) -> HypervisorResult<()> {
         unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
     }
+    fn ic0_call_with_best_effort_response(&mut self, _: u32) -> HypervisorResult<()> {
+        unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
+    }
     fn ic0_call_cycles_add(&mut self, _: u64) -> HypervisorResult<()> {
         unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
     }
@@ -202,6 +206,25 @@ impl SystemApi for SystemApiEmpty {
     fn ic0_stable64_write(&mut self, _: u64, _: u64, _: u64, _: &[u8]) -> HypervisorResult<()> {
         unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
     }
+    fn ic0_stable64_checkpoint(
+        &mut self,
+        _: u64,
+        _: u64,
+        _: u64,
+        _: &[Range<u64>],
+        _: &[u8],
+    ) -> HypervisorResult<u64> {
+        unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
+    }
+    fn ic0_stable64_restore(
+        &mut self,
+        _: u64,
+        _: u64,
+        _: u64,
+        _: &mut [u8],
+    ) -> HypervisorResult<u64> {
+        unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
+    }
     fn ic0_time(&self) -> HypervisorResult<Time> {
         unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
     }
@@ -283,4 +306,13 @@ impl SystemApi for SystemApiEmpty {
     ) -> HypervisorResult<(NumPages, NumInstructions)> {
         unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
     }
+    fn stable64_differing_ranges(
+        &self,
+        _: u64,
+        _: u64,
+        _: u64,
+        _: &[u8],
+    ) -> HypervisorResult<Vec<Range<u64>>> {
+        unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
+    }
 }
@@ -1,4 +1,4 @@
-use ic_base_types::NumSeconds;
+use ic_base_types::{NumSeconds, PrincipalId};
 use ic_config::subnet_config::SchedulerConfig;
 use ic_constants::SMALL_APP_SUBNET_MAX_SIZE;
 use ic_error_types::RejectCode;
@@ -9,12 +9,12 @@ use ic_interfaces::execution_environment::{
 use ic_logger::replica_logger::no_op_logger;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    testing::CanisterQueuesTesting, CallOrigin, Memory, NetworkTopology, NumWasmPages, PageMap,
-    SystemState,
+    testing::{CanisterQueuesTesting, SystemStateTesting},
+    CallOrigin, Memory, NetworkTopology, NumWasmPages, PageMap, SystemState,
 };
 use ic_system_api::{
     sandbox_safe_system_state::SandboxSafeSystemState, ApiType, DefaultOutOfInstructionsHandler,
-    NonReplicatedQueryKind, SystemApiImpl,
+    NonReplicatedQueryKind, SystemApiImpl, MAX_CALL_TIMEOUT_SECONDS,
 };
 use ic_test_utilities::{
     cycles_account_manager::CyclesAccountManagerBuilder,
@@ -26,9 +26,13 @@ use ic_test_utilities::{
     },
 };
 use ic_types::{
-    messages::{CallContextId, CallbackId, RejectContext, MAX_RESPONSE_COUNT_BYTES},
+    messages::{
+        CallContextId, CallbackId, RejectContext, RequestOrResponse, MAX_RESPONSE_COUNT_BYTES,
+        NO_DEADLINE,
+    },
     methods::{Callback, WasmClosure},
-    time, CanisterTimer, CountBytes, Cycles, NumBytes, NumInstructions, Time,
+    time::{self, CoarseTime},
+    CanisterId, CanisterTimer, CountBytes, Cycles, NumBytes, NumInstructions, Time,
 };
 use std::{
     convert::{From, TryInto},
@@ -96,6 +100,7 @@ fn test_canister_init_support() {
     assert_api_not_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_not_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_not_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_not_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_not_supported(api.ic0_call_cycles_add(0));
     assert_api_not_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_not_supported(api.ic0_call_perform());
@@ -107,6 +112,8 @@ fn test_canister_init_support() {
     assert_api_supported(api.ic0_stable64_grow(1));
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
+    assert_api_supported(api.ic0_stable64_checkpoint(0, 0, 0, &[], &[]));
+    assert_api_supported(api.ic0_stable64_restore(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_time());
     assert_api_supported(api.ic0_canister_version());
     assert_api_supported(api.ic0_global_timer_set(time::UNIX_EPOCH));
@@ -161,6 +168,7 @@ fn test_canister_update_support() {
     assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_supported(api.ic0_call_cycles_add(0));
     assert_api_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_supported(api.ic0_call_perform());
@@ -172,6 +180,8 @@ fn test_canister_update_support() {
     assert_api_supported(api.ic0_stable64_grow(1));
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
+    assert_api_supported(api.ic0_stable64_checkpoint(0, 0, 0, &[], &[]));
+    assert_api_supported(api.ic0_stable64_restore(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_time());
     assert_api_supported(api.ic0_canister_version());
     assert_api_supported(api.ic0_global_timer_set(time::UNIX_EPOCH));
@@ -226,6 +236,7 @@ fn test_canister_replicated_query_support() {
     assert_api_not_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_not_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_not_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_not_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_not_supported(api.ic0_call_cycles_add(0));
     assert_api_not_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_not_supported(api.ic0_call_perform());
@@ -237,6 +248,8 @@ fn test_canister_replicated_query_support() {
     assert_api_supported(api.ic0_stable64_grow(1));
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
+    assert_api_supported(api.ic0_stable64_checkpoint(0, 0, 0, &[], &[]));
+    assert_api_supported(api.ic0_stable64_restore(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_time());
     assert_api_supported(api.ic0_canister_version());
     assert_api_not_supported(api.ic0_global_timer_set(time::UNIX_EPOCH));
@@ -291,6 +304,7 @@ fn test_canister_pure_query_support() {
     assert_api_not_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_not_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_not_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_not_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_not_supported(api.ic0_call_cycles_add(0));
     assert_api_not_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_not_supported(api.ic0_call_perform());
@@ -302,6 +316,8 @@ fn test_canister_pure_query_support() {
     assert_api_supported(api.ic0_stable64_grow(1));
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
+    assert_api_supported(api.ic0_stable64_checkpoint(0, 0, 0, &[], &[]));
+    assert_api_supported(api.ic0_stable64_restore(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_time());
     assert_api_supported(api.ic0_canister_version());
     assert_api_not_supported(api.ic0_global_timer_set(time::UNIX_EPOCH));
@@ -366,6 +382,7 @@ fn test_canister_stateful_query_support() {
     assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_not_supported(api.ic0_call_cycles_add(0));
     assert_api_not_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_supported(api.ic0_call_perform());
@@ -377,6 +394,8 @@ fn test_canister_stateful_query_support() {
     assert_api_supported(api.ic0_stable64_grow(1));
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
+    assert_api_supported(api.ic0_stable64_checkpoint(0, 0, 0, &[], &[]));
+    assert_api_supported(api.ic0_stable64_restore(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_time());
     assert_api_supported(api.ic0_canister_version());
     assert_api_not_supported(api.ic0_global_timer_set(time::UNIX_EPOCH));
@@ -431,6 +450,7 @@ fn test_reply_api_support_on_nns() {
     assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_supported(api.ic0_call_cycles_add(0));
     assert_api_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_supported(api.ic0_call_perform());
@@ -442,6 +462,8 @@ fn test_reply_api_support_on_nns() {
     assert_api_supported(api.ic0_stable64_grow(1));
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
+    assert_api_supported(api.ic0_stable64_checkpoint(0, 0, 0, &[], &[]));
+    assert_api_supported(api.ic0_stable64_restore(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_time());
     assert_api_supported(api.ic0_canister_version());
     assert_api_supported(api.ic0_global_timer_set(time::UNIX_EPOCH));
@@ -496,6 +518,7 @@ fn test_reply_api_support_non_nns() {
     assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_supported(api.ic0_call_cycles_add(0));
     assert_api_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_supported(api.ic0_call_perform());
@@ -506,6 +529,8 @@ fn test_reply_api_support_non_nns() {
     assert_api_supported(api.ic0_stable64_size());
     assert_api_supported(api.ic0_stable64_grow(1));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
+    assert_api_supported(api.ic0_stable64_checkpoint(0, 0, 0, &[], &[]));
+    assert_api_supported(api.ic0_stable64_restore(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_time());
     assert_api_supported(api.ic0_canister_version());
@@ -564,6 +589,7 @@ fn test_reject_api_support_on_nns() {
     assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_supported(api.ic0_call_cycles_add(0));
     assert_api_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_supported(api.ic0_call_perform());
@@ -575,6 +601,8 @@ fn test_reject_api_support_on_nns() {
     assert_api_supported(api.ic0_stable64_grow(1));
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
+    assert_api_supported(api.ic0_stable64_checkpoint(0, 0, 0, &[], &[]));
+    assert_api_supported(api.ic0_stable64_restore(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_time());
     assert_api_supported(api.ic0_canister_version());
     assert_api_supported(api.ic0_global_timer_set(time::UNIX_EPOCH));
@@ -632,6 +660,7 @@ fn test_reject_api_support_non_nns() {
     assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_supported(api.ic0_call_cycles_add(0));
     assert_api_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_supported(api.ic0_call_perform());
@@ -643,6 +672,8 @@ fn test_reject_api_support_non_nns() {
     assert_api_supported(api.ic0_stable64_grow(1));
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
+    assert_api_supported(api.ic0_stable64_checkpoint(0, 0, 0, &[], &[]));
+    assert_api_supported(api.ic0_stable64_restore(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_time());
     assert_api_supported(api.ic0_canister_version());
     assert_api_supported(api.ic0_global_timer_set(time::UNIX_EPOCH));
@@ -697,6 +728,7 @@ fn test_pre_upgrade_support() {
     assert_api_not_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_not_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_not_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_not_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_not_supported(api.ic0_call_cycles_add(0));
     assert_api_not_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_not_supported(api.ic0_call_perform());
@@ -708,6 +740,8 @@ fn test_pre_upgrade_support() {
     assert_api_supported(api.ic0_stable64_grow(1));
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
+    assert_api_supported(api.ic0_stable64_checkpoint(0, 0, 0, &[], &[]));
+    assert_api_supported(api.ic0_stable64_restore(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_time());
     assert_api_supported(api.ic0_canister_version());
     assert_api_not_supported(api.ic0_global_timer_set(time::UNIX_EPOCH));
@@ -762,6 +796,7 @@ fn test_start_support() {
     assert_api_not_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_not_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_not_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_not_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_not_supported(api.ic0_call_cycles_add(0));
     assert_api_not_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_not_supported(api.ic0_call_perform());
@@ -773,6 +808,8 @@ fn test_start_support() {
     assert_api_not_supported(api.ic0_stable64_grow(1));
     assert_api_not_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_not_supported(api.ic0_stable64_write(0, 0, 0, &[]));
+    assert_api_not_supported(api.ic0_stable64_checkpoint(0, 0, 0, &[], &[]));
+    assert_api_not_supported(api.ic0_stable64_restore(0, 0, 0, &mut []));
     assert_api_not_supported(api.ic0_time());
     assert_api_not_supported(api.ic0_canister_version());
     assert_api_not_supported(api.ic0_global_timer_set(time::UNIX_EPOCH));
@@ -827,6 +864,7 @@ fn test_cleanup_support() {
     assert_api_not_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_not_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_not_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_not_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_not_supported(api.ic0_call_cycles_add(0));
     assert_api_not_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_not_supported(api.ic0_call_perform());
@@ -838,6 +876,8 @@ fn test_cleanup_support() {
     assert_api_supported(api.ic0_stable64_grow(1));
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
+    assert_api_supported(api.ic0_stable64_checkpoint(0, 0, 0, &[], &[]));
+    assert_api_supported(api.ic0_stable64_restore(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_time());
     assert_api_supported(api.ic0_canister_version());
     assert_api_supported(api.ic0_global_timer_set(time::UNIX_EPOCH));
@@ -897,6 +937,7 @@ fn test_inspect_message_support() {
     assert_api_not_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_not_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_not_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_not_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_not_supported(api.ic0_call_cycles_add(0));
     assert_api_not_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_not_supported(api.ic0_call_perform());
@@ -908,6 +949,8 @@ fn test_inspect_message_support() {
     assert_api_supported(api.ic0_stable64_grow(1));
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
+    assert_api_supported(api.ic0_stable64_checkpoint(0, 0, 0, &[], &[]));
+    assert_api_supported(api.ic0_stable64_restore(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_time());
     assert_api_supported(api.ic0_canister_version());
     assert_api_not_supported(api.ic0_global_timer_set(time::UNIX_EPOCH));
@@ -963,6 +1006,7 @@ fn test_canister_heartbeat_support() {
     assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_supported(api.ic0_call_cycles_add(0));
     assert_api_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_supported(api.ic0_call_perform());
@@ -974,6 +1018,8 @@ fn test_canister_heartbeat_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_grow(1));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
+    assert_api_supported(api.ic0_stable64_checkpoint(0, 0, 0, &[], &[]));
+    assert_api_supported(api.ic0_stable64_restore(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_time());
     assert_api_supported(api.ic0_canister_version());
     assert_api_supported(api.ic0_global_timer_set(time::UNIX_EPOCH));
@@ -1028,6 +1074,7 @@ fn test_canister_heartbeat_support_nns() {
     assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_supported(api.ic0_call_cycles_add(0));
     assert_api_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_supported(api.ic0_call_perform());
@@ -1039,6 +1086,8 @@ fn test_canister_heartbeat_support_nns() {
     assert_api_supported(api.ic0_stable64_grow(1));
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
+    assert_api_supported(api.ic0_stable64_checkpoint(0, 0, 0, &[], &[]));
+    assert_api_supported(api.ic0_stable64_restore(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_time());
     assert_api_supported(api.ic0_canister_version());
     assert_api_supported(api.ic0_global_timer_set(time::UNIX_EPOCH));
@@ -1208,7 +1257,7 @@ fn test_canister_balance() {
         .call_context_manager_mut()
         .unwrap()
         .new_call_context(
-            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5)),
+            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5), NO_DEADLINE),
             Cycles::new(50),
             Time::from_nanos_since_unix_epoch(0),
         );
@@ -1236,7 +1285,7 @@ fn test_canister_cycle_balance() {
         .call_context_manager_mut()
         .unwrap()
         .new_call_context(
-            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5)),
+            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5), NO_DEADLINE),
             Cycles::new(50),
             Time::from_nanos_since_unix_epoch(0),
         );
@@ -1270,7 +1319,7 @@ fn test_msg_cycles_available_traps() {
         .call_context_manager_mut()
         .unwrap()
         .new_call_context(
-            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5)),
+            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5), NO_DEADLINE),
             available_cycles,
             Time::from_nanos_since_unix_epoch(0),
         );
@@ -1431,7 +1480,7 @@ fn msg_cycles_accept_all_cycles_in_call_context() {
         .call_context_manager_mut()
         .unwrap()
         .new_call_context(
-            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5)),
+            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5), NO_DEADLINE),
             Cycles::from(amount),
             Time::from_nanos_since_unix_epoch(0),
         );
@@ -1454,7 +1503,7 @@ fn msg_cycles_accept_all_cycles_in_call_context_when_more_asked() {
         .call_context_manager_mut()
         .unwrap()
         .new_call_context(
-            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5)),
+            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5), NO_DEADLINE),
             Cycles::new(40),
             Time::from_nanos_since_unix_epoch(0),
         );
@@ -1485,7 +1534,7 @@ fn call_perform_not_enough_cycles_resets_state() {
         .call_context_manager_mut()
         .unwrap()
         .new_call_context(
-            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5)),
+            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5), NO_DEADLINE),
             Cycles::new(40),
             Time::from_nanos_since_unix_epoch(0),
         );
@@ -1514,6 +1563,66 @@ fn call_perform_not_enough_cycles_resets_state() {
     assert_eq!(call_context_manager.callbacks().len(), 0);
 }
 
+#[test]
+fn call_with_best_effort_response_sets_capped_deadline() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let mut system_state = SystemStateBuilder::new().build();
+    system_state
+        .call_context_manager_mut()
+        .unwrap()
+        .new_call_context(
+            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5), NO_DEADLINE),
+            Cycles::zero(),
+            Time::from_nanos_since_unix_epoch(0),
+        );
+    let mut api = get_system_api(
+        ApiTypeBuilder::build_update_api(),
+        &system_state,
+        cycles_account_manager,
+    );
+
+    // Not allowed outside of a call under construction.
+    assert!(api.ic0_call_with_best_effort_response(10).is_err());
+
+    api.ic0_call_new(0, 10, 0, 10, 0, 0, 0, 0, &[0; 1024])
+        .unwrap();
+    api.ic0_call_with_best_effort_response(1_000).unwrap();
+    // Can only be called once per call.
+    assert!(api.ic0_call_with_best_effort_response(10).is_err());
+    assert_eq!(api.ic0_call_perform().unwrap(), 0);
+
+    let system_state_changes = api.into_system_state_changes();
+    system_state_changes
+        .apply_changes(
+            mock_time(),
+            &mut system_state,
+            &default_network_topology(),
+            subnet_test_id(1),
+            &no_op_logger(),
+        )
+        .unwrap();
+
+    // The timeout is capped at `MAX_CALL_TIMEOUT_SECONDS`.
+    let expected_deadline =
+        CoarseTime::floor(mock_time()).saturating_add_secs(MAX_CALL_TIMEOUT_SECONDS);
+    let callback = system_state
+        .call_context_manager()
+        .unwrap()
+        .callbacks()
+        .values()
+        .next()
+        .unwrap()
+        .clone();
+    assert_eq!(expected_deadline, callback.deadline);
+    let request = match system_state.queues_mut().pop_canister_output(
+        &CanisterId::new(PrincipalId::try_from(&[0_u8; 10][..]).unwrap()).unwrap(),
+    ) {
+        Some(RequestOrResponse::Request(request)) => request,
+        other => panic!("Expected a request, got {:?}", other),
+    };
+    assert_eq!(expected_deadline, request.deadline);
+}
+
 #[test]
 fn stable_grow_updates_subnet_available_memory() {
     let wasm_page_size = 64 << 10;
@@ -1645,6 +1754,7 @@ fn take_execution_result_properly_frees_memory() {
             WasmClosure::new(0, 0),
             WasmClosure::new(0, 0),
             None,
+            NO_DEADLINE,
         ))
         .unwrap();
     let mut api = SystemApiImpl::new(
@@ -1709,6 +1819,7 @@ fn push_output_request_respects_memory_limits() {
                 WasmClosure::new(0, 0),
                 WasmClosure::new(0, 0),
                 None,
+                NO_DEADLINE,
             ))
             .unwrap();
         let mut api = SystemApiImpl::new(
@@ -1812,6 +1923,7 @@ fn push_output_request_oversized_request_memory_limits() {
             WasmClosure::new(0, 0),
             WasmClosure::new(0, 0),
             None,
+            NO_DEADLINE,
         ))
         .unwrap();
     let mut api = SystemApiImpl::new(
@@ -58,4 +58,12 @@ impl IngressPoolSelect for TestIngressPool {
     ) -> Vec<SignedIngress> {
         self.pool.select_validated(range, f)
     }
+
+    fn select_validated_from_refs<'a>(
+        &self,
+        range: std::ops::RangeInclusive<Time>,
+        f: Box<dyn FnOnce(Vec<&IngressPoolObject>) -> Vec<SignedIngress> + 'a>,
+    ) -> Vec<SignedIngress> {
+        self.pool.select_validated_from_refs(range, f)
+    }
 }
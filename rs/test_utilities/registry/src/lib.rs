@@ -1,8 +1,8 @@
 use ic_interfaces::time_source::TimeSource;
 use ic_interfaces_registry::{LocalStoreCertifiedTimeReader, RegistryClient};
 use ic_protobuf::registry::subnet::v1::{
-    CatchUpPackageContents, InitialNiDkgTranscriptRecord, SubnetFeatures, SubnetListRecord,
-    SubnetRecord,
+    AdaptiveNotaryDelayConfig, CatchUpPackageContents, InitialNiDkgTranscriptRecord,
+    SubnetFeatures, SubnetListRecord, SubnetRecord,
 };
 use ic_registry_client_fake::FakeRegistryClient;
 use ic_registry_keys::{
@@ -172,6 +172,9 @@ pub fn test_subnet_record() -> SubnetRecord {
         ssh_readonly_access: vec![],
         ssh_backup_access: vec![],
         ecdsa_config: None,
+        adaptive_notary_delay: None,
+        max_ingress_messages_per_canister_per_block: 0,
+        max_ingress_messages_per_sender_per_block: 0,
     }
 }
 
@@ -236,6 +239,18 @@ impl SubnetRecordBuilder {
         self
     }
 
+    pub fn with_adaptive_notary_delay(
+        mut self,
+        min_initial_notary_delay_millis: u64,
+        max_initial_notary_delay_millis: u64,
+    ) -> Self {
+        self.record.adaptive_notary_delay = Some(AdaptiveNotaryDelayConfig {
+            min_initial_notary_delay_millis,
+            max_initial_notary_delay_millis,
+        });
+        self
+    }
+
     pub fn build(self) -> SubnetRecord {
         self.record
     }
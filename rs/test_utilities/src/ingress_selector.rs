@@ -20,6 +20,7 @@ pub type FakeIngressSelector = FakeQueue<Vec<SignedIngress>>;
 impl IngressSelector for FakeIngressSelector {
     fn get_ingress_payload(
         &self,
+        _height: Height,
         _past_payloads: &dyn IngressSetQuery,
         _context: &ValidationContext,
         byte_limit: NumBytes,
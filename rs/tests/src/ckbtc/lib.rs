@@ -150,6 +150,8 @@ fn empty_subnet_update() -> UpdateSubnetPayload {
         subnet_id: subnet_test_id(0),
         max_ingress_bytes_per_message: None,
         max_ingress_messages_per_block: None,
+        max_ingress_messages_per_canister_per_block: None,
+        max_ingress_messages_per_sender_per_block: None,
         max_block_payload_size: None,
         unit_delay_millis: None,
         initial_notary_delay_millis: None,
@@ -177,6 +179,8 @@ fn empty_subnet_update() -> UpdateSubnetPayload {
         max_number_of_canisters: None,
         ssh_readonly_access: None,
         ssh_backup_access: None,
+        adaptive_notary_delay: None,
+        disable_adaptive_notary_delay: None,
     }
 }
 
@@ -243,6 +247,7 @@ pub(crate) async fn install_ledger(
             cycles_for_archive_creation: None,
             max_transactions_per_response: None,
         },
+        fee_collector_account: None,
     };
     install_icrc1_ledger(env, canister, &init_args).await;
     canister.canister_id()
@@ -5,7 +5,7 @@ use canister_test::{Canister, PrincipalId, RemoteTestRuntime, Runtime};
 use ic_canister_client::{Agent, Sender};
 use ic_icrc1::Account;
 use ic_icrc1_agent::{CallMode, Icrc1Agent, TransferArg, Value};
-use ic_icrc1_ledger::InitArgs;
+use ic_icrc1_ledger::{InitArgs, LedgerArgument};
 use ic_nns_test_utils::itest_helpers::install_rust_canister_from_path;
 use ic_registry_subnet_type::SubnetType;
 use icp_ledger::ArchiveOptions;
@@ -90,6 +90,7 @@ pub fn test(env: TestEnv) {
                 cycles_for_archive_creation: None,
                 max_transactions_per_response: None,
             },
+            fee_collector_account: None,
         };
         install_icrc1_ledger(&env, &mut ledger, &init_args).await;
 
@@ -237,7 +238,7 @@ pub async fn install_icrc1_ledger<'a>(env: &TestEnv, canister: &mut Canister<'a>
     install_rust_canister_from_path(
         canister,
         env.get_dependency_path("rs/rosetta-api/icrc1/ledger/ledger_canister.wasm"),
-        Some(Encode!(&args).unwrap()),
+        Some(Encode!(&LedgerArgument::Init(args.clone())).unwrap()),
     )
     .await
 }
@@ -919,6 +919,8 @@ pub async fn submit_create_application_subnet_proposal(
         ingress_bytes_per_block_soft_cap: config.ingress_bytes_per_block_soft_cap,
         max_ingress_bytes_per_message: config.max_ingress_bytes_per_message,
         max_ingress_messages_per_block: config.max_ingress_messages_per_block,
+        max_ingress_messages_per_canister_per_block: 0,
+        max_ingress_messages_per_sender_per_block: 0,
         max_block_payload_size: config.max_block_payload_size,
         replica_version_id: replica_version.to_string(),
         unit_delay_millis: duration_to_millis(config.unit_delay),
@@ -943,6 +945,7 @@ pub async fn submit_create_application_subnet_proposal(
         max_number_of_canisters: 4,
         ssh_readonly_access: vec![],
         ssh_backup_access: vec![],
+        adaptive_notary_delay: None,
         ecdsa_config: None,
     };
 
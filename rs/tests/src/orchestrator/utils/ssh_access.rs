@@ -150,6 +150,8 @@ pub(crate) fn get_updatesubnetpayload_with_keys(
         subnet_id,
         max_ingress_bytes_per_message: None,
         max_ingress_messages_per_block: None,
+        max_ingress_messages_per_canister_per_block: None,
+        max_ingress_messages_per_sender_per_block: None,
         max_block_payload_size: None,
         unit_delay_millis: None,
         initial_notary_delay_millis: None,
@@ -177,6 +179,8 @@ pub(crate) fn get_updatesubnetpayload_with_keys(
         max_number_of_canisters: None,
         ssh_readonly_access: readonly_keys,
         ssh_backup_access: backup_keys,
+        adaptive_notary_delay: None,
+        disable_adaptive_notary_delay: None,
     }
 }
 
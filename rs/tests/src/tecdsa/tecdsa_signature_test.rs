@@ -78,6 +78,8 @@ pub(crate) fn empty_subnet_update() -> UpdateSubnetPayload {
         subnet_id: subnet_test_id(0),
         max_ingress_bytes_per_message: None,
         max_ingress_messages_per_block: None,
+        max_ingress_messages_per_canister_per_block: None,
+        max_ingress_messages_per_sender_per_block: None,
         max_block_payload_size: None,
         unit_delay_millis: None,
         initial_notary_delay_millis: None,
@@ -105,6 +107,8 @@ pub(crate) fn empty_subnet_update() -> UpdateSubnetPayload {
         max_number_of_canisters: None,
         ssh_readonly_access: None,
         ssh_backup_access: None,
+        adaptive_notary_delay: None,
+        disable_adaptive_notary_delay: None,
     }
 }
 
@@ -426,6 +430,8 @@ pub(crate) async fn create_new_subnet_with_keys(
         ingress_bytes_per_block_soft_cap: config.ingress_bytes_per_block_soft_cap,
         max_ingress_bytes_per_message: config.max_ingress_bytes_per_message,
         max_ingress_messages_per_block: config.max_ingress_messages_per_block,
+        max_ingress_messages_per_canister_per_block: 0,
+        max_ingress_messages_per_sender_per_block: 0,
         max_block_payload_size: config.max_block_payload_size,
         replica_version_id: replica_version.to_string(),
         unit_delay_millis: ic_prep_lib::subnet_configuration::duration_to_millis(config.unit_delay),
@@ -452,6 +458,7 @@ pub(crate) async fn create_new_subnet_with_keys(
         max_number_of_canisters: 4,
         ssh_readonly_access: vec![],
         ssh_backup_access: vec![],
+        adaptive_notary_delay: None,
         ecdsa_config: Some(EcdsaInitialConfig {
             quadruples_to_create_in_advance: 4,
             keys,
@@ -5,9 +5,11 @@ mod provisional;
 use candid::{CandidType, Decode, Deserialize, Encode};
 use ic_base_types::{CanisterId, NodeId, NumBytes, PrincipalId, RegistryVersion, SubnetId};
 use ic_error_types::{ErrorCode, UserError};
+use ic_protobuf::proxy::{try_from_option_field, ProxyDecodeError};
+use ic_protobuf::registry::crypto::v1 as pb_registry_crypto;
 use ic_protobuf::registry::crypto::v1::PublicKey;
 use ic_protobuf::registry::subnet::v1::{InitialIDkgDealings, InitialNiDkgTranscriptRecord};
-use ic_protobuf::{proxy::ProxyDecodeError, registry::crypto::v1 as pb_registry_crypto};
+use ic_protobuf::state::canister_state_bits::v1 as pb_canister_state_bits;
 use num_traits::cast::ToPrimitive;
 use serde::Serialize;
 use std::{collections::BTreeSet, convert::TryFrom, fmt, slice::Iter, str::FromStr};
@@ -43,6 +45,14 @@ pub enum Method {
     UpdateSettings,
     ComputeInitialEcdsaDealings,
 
+    // Installation of Wasm modules larger than the maximum message size.
+    UploadChunk,
+    ClearChunkStore,
+    InstallChunkedCode,
+
+    // Canister history.
+    CanisterInfo,
+
     // Bitcoin Interface.
     BitcoinGetBalance,
     BitcoinGetUtxos,
@@ -99,6 +109,9 @@ impl From<CanisterId> for CanisterIdRecord {
 ///     controller : principal;
 ///     compute_allocation: nat;
 ///     memory_allocation: opt nat;
+///     freezing_threshold: nat;
+///     wasm_memory_limit: opt nat;
+///     reserved_cycles_limit: opt nat;
 /// })`
 #[derive(CandidType, Deserialize, Debug, Eq, PartialEq)]
 pub struct DefiniteCanisterSettingsArgs {
@@ -107,6 +120,8 @@ pub struct DefiniteCanisterSettingsArgs {
     compute_allocation: candid::Nat,
     memory_allocation: candid::Nat,
     freezing_threshold: candid::Nat,
+    wasm_memory_limit: Option<candid::Nat>,
+    reserved_cycles_limit: Option<candid::Nat>,
 }
 
 impl DefiniteCanisterSettingsArgs {
@@ -116,6 +131,8 @@ impl DefiniteCanisterSettingsArgs {
         compute_allocation: u64,
         memory_allocation: Option<u64>,
         freezing_threshold: u64,
+        wasm_memory_limit: Option<u64>,
+        reserved_cycles_limit: Option<u128>,
     ) -> Self {
         let memory_allocation = match memory_allocation {
             None => candid::Nat::from(0),
@@ -127,12 +144,26 @@ impl DefiniteCanisterSettingsArgs {
             compute_allocation: candid::Nat::from(compute_allocation),
             memory_allocation,
             freezing_threshold: candid::Nat::from(freezing_threshold),
+            wasm_memory_limit: wasm_memory_limit.map(candid::Nat::from),
+            reserved_cycles_limit: reserved_cycles_limit.map(candid::Nat::from),
         }
     }
 
     pub fn controllers(&self) -> Vec<PrincipalId> {
         self.controllers.clone()
     }
+
+    pub fn wasm_memory_limit(&self) -> Option<u64> {
+        self.wasm_memory_limit
+            .as_ref()
+            .map(|limit| limit.0.to_u64().unwrap())
+    }
+
+    pub fn reserved_cycles_limit(&self) -> Option<u128> {
+        self.reserved_cycles_limit
+            .as_ref()
+            .map(|limit| limit.0.to_u128().unwrap())
+    }
 }
 
 impl Payload<'_> for DefiniteCanisterSettingsArgs {}
@@ -202,6 +233,8 @@ impl Payload<'_> for CanisterStatusResult {}
 ///     memory_size: nat;
 ///     cycles: nat;
 ///     idle_cycles_burned_per_day: nat;
+///     reserved_cycles: nat;
+///     query_stats: query_stats;
 /// })`
 #[derive(CandidType, Debug, Deserialize, Eq, PartialEq)]
 pub struct CanisterStatusResultV2 {
@@ -215,6 +248,27 @@ pub struct CanisterStatusResultV2 {
     balance: Vec<(Vec<u8>, candid::Nat)>,
     freezing_threshold: candid::Nat,
     idle_cycles_burned_per_day: candid::Nat,
+    reserved_cycles: candid::Nat,
+    query_stats: QueryStatsResponse,
+}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     num_calls_total: nat;
+///     num_instructions_total: nat;
+///     request_payload_bytes_total: nat;
+///     response_payload_bytes_total: nat;
+/// })`
+///
+/// The totals of the query calls executed on a canister, as aggregated
+/// through consensus. They lag behind the actual query executions by up to
+/// a few query stats epochs.
+#[derive(Clone, CandidType, Debug, Default, Deserialize, Eq, PartialEq)]
+pub struct QueryStatsResponse {
+    pub num_calls_total: candid::Nat,
+    pub num_instructions_total: candid::Nat,
+    pub request_payload_bytes_total: candid::Nat,
+    pub response_payload_bytes_total: candid::Nat,
 }
 
 impl CanisterStatusResultV2 {
@@ -230,6 +284,10 @@ impl CanisterStatusResultV2 {
         memory_allocation: Option<u64>,
         freezing_threshold: u64,
         idle_cycles_burned_per_day: u128,
+        wasm_memory_limit: Option<u64>,
+        reserved_cycles: u128,
+        reserved_cycles_limit: Option<u128>,
+        query_stats: QueryStatsResponse,
     ) -> Self {
         Self {
             status,
@@ -246,9 +304,13 @@ impl CanisterStatusResultV2 {
                 compute_allocation,
                 memory_allocation,
                 freezing_threshold,
+                wasm_memory_limit,
+                reserved_cycles_limit,
             ),
             freezing_threshold: candid::Nat::from(freezing_threshold),
             idle_cycles_burned_per_day: candid::Nat::from(idle_cycles_burned_per_day),
+            reserved_cycles: candid::Nat::from(reserved_cycles),
+            query_stats,
         }
     }
 
@@ -283,6 +345,22 @@ impl CanisterStatusResultV2 {
     pub fn idle_cycles_burned_per_day(&self) -> u128 {
         self.idle_cycles_burned_per_day.0.to_u128().unwrap()
     }
+
+    pub fn wasm_memory_limit(&self) -> Option<u64> {
+        self.settings.wasm_memory_limit()
+    }
+
+    pub fn reserved_cycles(&self) -> u128 {
+        self.reserved_cycles.0.to_u128().unwrap()
+    }
+
+    pub fn reserved_cycles_limit(&self) -> Option<u128> {
+        self.settings.reserved_cycles_limit()
+    }
+
+    pub fn query_stats(&self) -> QueryStatsResponse {
+        self.query_stats.clone()
+    }
 }
 
 /// Indicates whether the canister is running, stopping, or stopped.
@@ -386,7 +464,13 @@ impl Payload<'_> for CanisterStatusResultV2 {}
 ///     compute_allocation: opt nat;
 ///     memory_allocation: opt nat;
 ///     query_allocation: opt nat;
+///     wasm_module_hash: opt blob;
 /// })`
+///
+/// If `wasm_module_hash` is set and `wasm_module` is empty, the Wasm module is
+/// taken from a canister on the same subnet that has a module with this
+/// SHA-256 hash installed. If both are set, the hash of `wasm_module` must
+/// match `wasm_module_hash`.
 #[derive(Clone, CandidType, Deserialize, Debug)]
 pub struct InstallCodeArgs {
     pub mode: CanisterInstallMode,
@@ -397,6 +481,7 @@ pub struct InstallCodeArgs {
     pub compute_allocation: Option<candid::Nat>,
     pub memory_allocation: Option<candid::Nat>,
     pub query_allocation: Option<candid::Nat>,
+    pub wasm_module_hash: Option<Vec<u8>>,
 }
 
 impl std::fmt::Display for InstallCodeArgs {
@@ -430,6 +515,14 @@ impl std::fmt::Display for InstallCodeArgs {
                 .as_ref()
                 .map(|value| format!("{}", value))
         )?;
+        writeln!(
+            f,
+            "  wasm_module_hash: {:?}",
+            &self.wasm_module_hash.as_ref().map(|hash| hash
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>())
+        )?;
         writeln!(f, "}}")
     }
 }
@@ -454,6 +547,32 @@ impl InstallCodeArgs {
             compute_allocation: compute_allocation.map(candid::Nat::from),
             memory_allocation: memory_allocation.map(candid::Nat::from),
             query_allocation: query_allocation.map(candid::Nat::from),
+            wasm_module_hash: None,
+        }
+    }
+
+    /// Creates arguments that install the Wasm module with the given SHA-256
+    /// hash, which must already be installed on a canister of the same subnet.
+    pub fn new_from_module_hash(
+        mode: CanisterInstallMode,
+        canister_id: CanisterId,
+        wasm_module_hash: Vec<u8>,
+        arg: Vec<u8>,
+        compute_allocation: Option<u64>,
+        memory_allocation: Option<u64>,
+        query_allocation: Option<u64>,
+    ) -> Self {
+        Self {
+            wasm_module_hash: Some(wasm_module_hash),
+            ..Self::new(
+                mode,
+                canister_id,
+                vec![],
+                arg,
+                compute_allocation,
+                memory_allocation,
+                query_allocation,
+            )
         }
     }
 
@@ -463,6 +582,585 @@ impl InstallCodeArgs {
     }
 }
 
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id: principal;
+///     chunk: blob;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug)]
+pub struct UploadChunkArgs {
+    pub canister_id: PrincipalId,
+    #[serde(with = "serde_bytes")]
+    pub chunk: Vec<u8>,
+}
+
+impl Payload<'_> for UploadChunkArgs {}
+
+impl UploadChunkArgs {
+    pub fn new(canister_id: CanisterId, chunk: Vec<u8>) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+            chunk,
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     hash: blob;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct UploadChunkReply {
+    #[serde(with = "serde_bytes")]
+    pub hash: Vec<u8>,
+}
+
+impl Payload<'_> for UploadChunkReply {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     mode : variant { install; reinstall; upgrade };
+///     canister_id: principal;
+///     chunk_hashes_list: vec blob;
+///     wasm_module_hash: blob;
+///     arg: blob;
+/// })`
+///
+/// The Wasm module is the concatenation of the chunks in `chunk_hashes_list`,
+/// which must have been uploaded to the canister via `upload_chunk`, and must
+/// have the SHA-256 hash `wasm_module_hash`.
+#[derive(Clone, CandidType, Deserialize, Debug)]
+pub struct InstallChunkedCodeArgs {
+    pub mode: CanisterInstallMode,
+    pub canister_id: PrincipalId,
+    pub chunk_hashes_list: Vec<Vec<u8>>,
+    #[serde(with = "serde_bytes")]
+    pub wasm_module_hash: Vec<u8>,
+    pub arg: Vec<u8>,
+}
+
+impl Payload<'_> for InstallChunkedCodeArgs {}
+
+impl InstallChunkedCodeArgs {
+    pub fn new(
+        mode: CanisterInstallMode,
+        canister_id: CanisterId,
+        chunk_hashes_list: Vec<Vec<u8>>,
+        wasm_module_hash: Vec<u8>,
+        arg: Vec<u8>,
+    ) -> Self {
+        Self {
+            mode,
+            canister_id: canister_id.into(),
+            chunk_hashes_list,
+            wasm_module_hash,
+            arg,
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+}
+
+/// `CandidType` for `CanisterChangeFromUserRecord`
+/// `(record {
+///     user_id: principal;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct CanisterChangeFromUserRecord {
+    user_id: PrincipalId,
+}
+
+/// `CandidType` for `CanisterChangeFromCanisterRecord`
+/// `(record {
+///     canister_id: principal;
+///     canister_version: opt nat64;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct CanisterChangeFromCanisterRecord {
+    canister_id: PrincipalId,
+    canister_version: Option<u64>,
+}
+
+/// `CandidType` for `CanisterChangeOrigin`
+/// `(variant {
+///     from_user: CanisterChangeFromUserRecord;
+///     from_canister: CanisterChangeFromCanisterRecord;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub enum CanisterChangeOrigin {
+    #[serde(rename = "from_user")]
+    CanisterChangeFromUser(CanisterChangeFromUserRecord),
+    #[serde(rename = "from_canister")]
+    CanisterChangeFromCanister(CanisterChangeFromCanisterRecord),
+}
+
+impl CanisterChangeOrigin {
+    pub fn from_user(user_id: PrincipalId) -> CanisterChangeOrigin {
+        CanisterChangeOrigin::CanisterChangeFromUser(CanisterChangeFromUserRecord { user_id })
+    }
+
+    pub fn from_canister(
+        canister_id: PrincipalId,
+        canister_version: Option<u64>,
+    ) -> CanisterChangeOrigin {
+        CanisterChangeOrigin::CanisterChangeFromCanister(CanisterChangeFromCanisterRecord {
+            canister_id,
+            canister_version,
+        })
+    }
+
+    /// The principal (user or canister) initiating the change.
+    pub fn origin(&self) -> PrincipalId {
+        match self {
+            CanisterChangeOrigin::CanisterChangeFromUser(record) => record.user_id,
+            CanisterChangeOrigin::CanisterChangeFromCanister(record) => record.canister_id,
+        }
+    }
+}
+
+/// `CandidType` for `CanisterCreationRecord`
+/// `(record {
+///     controllers: vec principal;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct CanisterCreationRecord {
+    controllers: Vec<PrincipalId>,
+}
+
+impl CanisterCreationRecord {
+    pub fn controllers(&self) -> &[PrincipalId] {
+        &self.controllers
+    }
+}
+
+/// `CandidType` for `CanisterCodeDeploymentRecord`
+/// `(record {
+///     mode: variant { install; reinstall; upgrade };
+///     module_hash: blob;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct CanisterCodeDeploymentRecord {
+    mode: CanisterInstallMode,
+    #[serde(with = "serde_bytes")]
+    module_hash: Vec<u8>,
+}
+
+impl CanisterCodeDeploymentRecord {
+    pub fn mode(&self) -> CanisterInstallMode {
+        self.mode
+    }
+
+    pub fn module_hash(&self) -> &[u8] {
+        &self.module_hash
+    }
+}
+
+/// `CandidType` for `CanisterControllersChangeRecord`
+/// `(record {
+///     controllers: vec principal;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct CanisterControllersChangeRecord {
+    controllers: Vec<PrincipalId>,
+}
+
+impl CanisterControllersChangeRecord {
+    pub fn controllers(&self) -> &[PrincipalId] {
+        &self.controllers
+    }
+}
+
+/// `CandidType` for `CanisterChangeDetails`
+/// `(variant {
+///     creation: CanisterCreationRecord;
+///     code_uninstall;
+///     code_deployment: CanisterCodeDeploymentRecord;
+///     controllers_change: CanisterControllersChangeRecord;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub enum CanisterChangeDetails {
+    #[serde(rename = "creation")]
+    CanisterCreation(CanisterCreationRecord),
+    #[serde(rename = "code_uninstall")]
+    CanisterCodeUninstall,
+    #[serde(rename = "code_deployment")]
+    CanisterCodeDeployment(CanisterCodeDeploymentRecord),
+    #[serde(rename = "controllers_change")]
+    CanisterControllersChange(CanisterControllersChangeRecord),
+}
+
+impl CanisterChangeDetails {
+    pub fn canister_creation(controllers: Vec<PrincipalId>) -> CanisterChangeDetails {
+        CanisterChangeDetails::CanisterCreation(CanisterCreationRecord { controllers })
+    }
+
+    pub fn code_uninstall() -> CanisterChangeDetails {
+        CanisterChangeDetails::CanisterCodeUninstall
+    }
+
+    pub fn code_deployment(
+        mode: CanisterInstallMode,
+        module_hash: [u8; 32],
+    ) -> CanisterChangeDetails {
+        CanisterChangeDetails::CanisterCodeDeployment(CanisterCodeDeploymentRecord {
+            mode,
+            module_hash: module_hash.to_vec(),
+        })
+    }
+
+    pub fn controllers_change(controllers: Vec<PrincipalId>) -> CanisterChangeDetails {
+        CanisterChangeDetails::CanisterControllersChange(CanisterControllersChangeRecord {
+            controllers,
+        })
+    }
+}
+
+/// `CandidType` for `CanisterChange`
+/// `(record {
+///     timestamp_nanos: nat64;
+///     canister_version: nat64;
+///     origin: CanisterChangeOrigin;
+///     details: CanisterChangeDetails;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct CanisterChange {
+    timestamp_nanos: u64,
+    canister_version: u64,
+    origin: CanisterChangeOrigin,
+    details: CanisterChangeDetails,
+}
+
+impl CanisterChange {
+    pub fn new(
+        timestamp_nanos: u64,
+        canister_version: u64,
+        origin: CanisterChangeOrigin,
+        details: CanisterChangeDetails,
+    ) -> CanisterChange {
+        CanisterChange {
+            timestamp_nanos,
+            canister_version,
+            origin,
+            details,
+        }
+    }
+
+    pub fn timestamp_nanos(&self) -> u64 {
+        self.timestamp_nanos
+    }
+
+    pub fn canister_version(&self) -> u64 {
+        self.canister_version
+    }
+
+    pub fn origin(&self) -> &CanisterChangeOrigin {
+        &self.origin
+    }
+
+    pub fn details(&self) -> &CanisterChangeDetails {
+        &self.details
+    }
+}
+
+impl From<&CanisterChangeOrigin> for pb_canister_state_bits::canister_change::ChangeOrigin {
+    fn from(item: &CanisterChangeOrigin) -> Self {
+        match item {
+            CanisterChangeOrigin::CanisterChangeFromUser(change_from_user) => {
+                pb_canister_state_bits::canister_change::ChangeOrigin::CanisterChangeFromUser(
+                    pb_canister_state_bits::CanisterChangeFromUser {
+                        user_id: Some(change_from_user.user_id.into()),
+                    },
+                )
+            }
+            CanisterChangeOrigin::CanisterChangeFromCanister(change_from_canister) => {
+                pb_canister_state_bits::canister_change::ChangeOrigin::CanisterChangeFromCanister(
+                    pb_canister_state_bits::CanisterChangeFromCanister {
+                        canister_id: Some(change_from_canister.canister_id.into()),
+                        canister_version: change_from_canister.canister_version,
+                    },
+                )
+            }
+        }
+    }
+}
+
+impl TryFrom<pb_canister_state_bits::canister_change::ChangeOrigin> for CanisterChangeOrigin {
+    type Error = ProxyDecodeError;
+
+    fn try_from(
+        value: pb_canister_state_bits::canister_change::ChangeOrigin,
+    ) -> Result<Self, Self::Error> {
+        match value {
+            pb_canister_state_bits::canister_change::ChangeOrigin::CanisterChangeFromUser(
+                change_from_user,
+            ) => Ok(CanisterChangeOrigin::from_user(try_from_option_field(
+                change_from_user.user_id,
+                "user_id",
+            )?)),
+            pb_canister_state_bits::canister_change::ChangeOrigin::CanisterChangeFromCanister(
+                change_from_canister,
+            ) => Ok(CanisterChangeOrigin::from_canister(
+                try_from_option_field(change_from_canister.canister_id, "canister_id")?,
+                change_from_canister.canister_version,
+            )),
+        }
+    }
+}
+
+impl From<CanisterInstallMode> for pb_canister_state_bits::CanisterInstallMode {
+    fn from(item: CanisterInstallMode) -> Self {
+        match item {
+            CanisterInstallMode::Install => pb_canister_state_bits::CanisterInstallMode::Install,
+            CanisterInstallMode::Reinstall => {
+                pb_canister_state_bits::CanisterInstallMode::Reinstall
+            }
+            CanisterInstallMode::Upgrade => pb_canister_state_bits::CanisterInstallMode::Upgrade,
+        }
+    }
+}
+
+impl TryFrom<pb_canister_state_bits::CanisterInstallMode> for CanisterInstallMode {
+    type Error = CanisterInstallModeError;
+
+    fn try_from(item: pb_canister_state_bits::CanisterInstallMode) -> Result<Self, Self::Error> {
+        match item {
+            pb_canister_state_bits::CanisterInstallMode::Install => {
+                Ok(CanisterInstallMode::Install)
+            }
+            pb_canister_state_bits::CanisterInstallMode::Reinstall => {
+                Ok(CanisterInstallMode::Reinstall)
+            }
+            pb_canister_state_bits::CanisterInstallMode::Upgrade => {
+                Ok(CanisterInstallMode::Upgrade)
+            }
+            pb_canister_state_bits::CanisterInstallMode::Unspecified => Err(
+                CanisterInstallModeError(format!("Unexpected install mode: {:?}", item)),
+            ),
+        }
+    }
+}
+
+impl From<&CanisterChangeDetails> for pb_canister_state_bits::canister_change::ChangeDetails {
+    fn from(item: &CanisterChangeDetails) -> Self {
+        match item {
+            CanisterChangeDetails::CanisterCreation(canister_creation) => {
+                pb_canister_state_bits::canister_change::ChangeDetails::CanisterCreation(
+                    pb_canister_state_bits::CanisterCreation {
+                        controllers: canister_creation
+                            .controllers
+                            .iter()
+                            .map(|c| (*c).into())
+                            .collect(),
+                    },
+                )
+            }
+            CanisterChangeDetails::CanisterCodeUninstall => {
+                pb_canister_state_bits::canister_change::ChangeDetails::CanisterCodeUninstall(
+                    pb_canister_state_bits::CanisterCodeUninstall {},
+                )
+            }
+            CanisterChangeDetails::CanisterCodeDeployment(canister_code_deployment) => {
+                pb_canister_state_bits::canister_change::ChangeDetails::CanisterCodeDeployment(
+                    pb_canister_state_bits::CanisterCodeDeployment {
+                        mode: pb_canister_state_bits::CanisterInstallMode::from(
+                            canister_code_deployment.mode,
+                        )
+                        .into(),
+                        module_hash: canister_code_deployment.module_hash.clone(),
+                    },
+                )
+            }
+            CanisterChangeDetails::CanisterControllersChange(canister_controllers_change) => {
+                pb_canister_state_bits::canister_change::ChangeDetails::CanisterControllersChange(
+                    pb_canister_state_bits::CanisterControllersChange {
+                        controllers: canister_controllers_change
+                            .controllers
+                            .iter()
+                            .map(|c| (*c).into())
+                            .collect(),
+                    },
+                )
+            }
+        }
+    }
+}
+
+impl TryFrom<pb_canister_state_bits::canister_change::ChangeDetails> for CanisterChangeDetails {
+    type Error = ProxyDecodeError;
+
+    fn try_from(
+        item: pb_canister_state_bits::canister_change::ChangeDetails,
+    ) -> Result<Self, Self::Error> {
+        match item {
+            pb_canister_state_bits::canister_change::ChangeDetails::CanisterCreation(
+                canister_creation,
+            ) => {
+                let controllers = canister_creation
+                    .controllers
+                    .into_iter()
+                    .map(PrincipalId::try_from)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(CanisterChangeDetails::canister_creation(controllers))
+            }
+            pb_canister_state_bits::canister_change::ChangeDetails::CanisterCodeUninstall(_) => {
+                Ok(CanisterChangeDetails::code_uninstall())
+            }
+            pb_canister_state_bits::canister_change::ChangeDetails::CanisterCodeDeployment(
+                canister_code_deployment,
+            ) => {
+                let mode = pb_canister_state_bits::CanisterInstallMode::from_i32(
+                    canister_code_deployment.mode,
+                )
+                .ok_or(ProxyDecodeError::ValueOutOfRange {
+                    typ: "CanisterInstallMode",
+                    err: format!(
+                        "Unexpected value for canister install mode {}",
+                        canister_code_deployment.mode
+                    ),
+                })?;
+                let mode = CanisterInstallMode::try_from(mode).map_err(|err| {
+                    ProxyDecodeError::ValueOutOfRange {
+                        typ: "CanisterInstallMode",
+                        err: err.0,
+                    }
+                })?;
+                let module_hash: [u8; 32] = canister_code_deployment
+                    .module_hash
+                    .try_into()
+                    .map_err(|hash: Vec<u8>| ProxyDecodeError::InvalidDigestLength {
+                        expected: 32,
+                        actual: hash.len(),
+                    })?;
+                Ok(CanisterChangeDetails::code_deployment(mode, module_hash))
+            }
+            pb_canister_state_bits::canister_change::ChangeDetails::CanisterControllersChange(
+                canister_controllers_change,
+            ) => {
+                let controllers = canister_controllers_change
+                    .controllers
+                    .into_iter()
+                    .map(PrincipalId::try_from)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(CanisterChangeDetails::controllers_change(controllers))
+            }
+        }
+    }
+}
+
+impl From<&CanisterChange> for pb_canister_state_bits::CanisterChange {
+    fn from(item: &CanisterChange) -> Self {
+        Self {
+            timestamp_nanos: item.timestamp_nanos,
+            canister_version: item.canister_version,
+            change_origin: Some((&item.origin).into()),
+            change_details: Some((&item.details).into()),
+        }
+    }
+}
+
+impl TryFrom<pb_canister_state_bits::CanisterChange> for CanisterChange {
+    type Error = ProxyDecodeError;
+
+    fn try_from(value: pb_canister_state_bits::CanisterChange) -> Result<Self, Self::Error> {
+        let origin = try_from_option_field(value.change_origin, "origin")?;
+        let details = try_from_option_field(value.change_details, "details")?;
+        Ok(Self {
+            timestamp_nanos: value.timestamp_nanos,
+            canister_version: value.canister_version,
+            origin,
+            details,
+        })
+    }
+}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id: principal;
+///     num_requested_changes: opt nat64;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug)]
+pub struct CanisterInfoRequest {
+    canister_id: PrincipalId,
+    num_requested_changes: Option<u64>,
+}
+
+impl CanisterInfoRequest {
+    pub fn new(canister_id: CanisterId, num_requested_changes: Option<u64>) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+            num_requested_changes,
+        }
+    }
+
+    pub fn canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+
+    pub fn num_requested_changes(&self) -> Option<u64> {
+        self.num_requested_changes
+    }
+}
+
+impl Payload<'_> for CanisterInfoRequest {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     total_num_changes: nat64;
+///     recent_changes: vec CanisterChange;
+///     module_hash: opt blob;
+///     controllers: vec principal;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct CanisterInfoResponse {
+    total_num_changes: u64,
+    recent_changes: Vec<CanisterChange>,
+    module_hash: Option<Vec<u8>>,
+    controllers: Vec<PrincipalId>,
+}
+
+impl CanisterInfoResponse {
+    pub fn new(
+        total_num_changes: u64,
+        recent_changes: Vec<CanisterChange>,
+        module_hash: Option<Vec<u8>>,
+        controllers: Vec<PrincipalId>,
+    ) -> Self {
+        Self {
+            total_num_changes,
+            recent_changes,
+            module_hash,
+            controllers,
+        }
+    }
+
+    pub fn total_num_changes(&self) -> u64 {
+        self.total_num_changes
+    }
+
+    pub fn changes(&self) -> Vec<CanisterChange> {
+        self.recent_changes.clone()
+    }
+
+    pub fn module_hash(&self) -> Option<Vec<u8>> {
+        self.module_hash.clone()
+    }
+
+    pub fn controllers(&self) -> Vec<PrincipalId> {
+        self.controllers.clone()
+    }
+}
+
+impl Payload<'_> for CanisterInfoResponse {}
+
 /// Represents the empty blob.
 #[derive(CandidType, Deserialize)]
 pub struct EmptyBlob;
@@ -503,6 +1201,10 @@ impl Payload<'_> for UpdateSettingsArgs {}
 ///     controllers: opt vec principal;
 ///     compute_allocation: opt nat;
 ///     memory_allocation: opt nat;
+///     freezing_threshold: opt nat;
+///     ingress_priority_fee: opt nat;
+///     wasm_memory_limit: opt nat;
+///     reserved_cycles_limit: opt nat;
 /// })`
 #[derive(Default, Clone, CandidType, Deserialize, Debug)]
 pub struct CanisterSettingsArgs {
@@ -511,6 +1213,18 @@ pub struct CanisterSettingsArgs {
     pub compute_allocation: Option<candid::Nat>,
     pub memory_allocation: Option<candid::Nat>,
     pub freezing_threshold: Option<candid::Nat>,
+    /// Cycles the canister is willing to pay on top of the induction cost of
+    /// each ingress message addressed to it, in exchange for prioritized
+    /// inclusion into blocks.
+    pub ingress_priority_fee: Option<candid::Nat>,
+    /// Upper bound on the size of the canister's Wasm heap in bytes. A
+    /// `memory.grow` that would exceed it traps, except in upgrade hooks.
+    /// Setting it to zero removes the limit.
+    pub wasm_memory_limit: Option<candid::Nat>,
+    /// Upper bound on the cycles the canister may have reserved for storage
+    /// allocated while the subnet memory usage is high. Memory allocations
+    /// that would exceed it fail.
+    pub reserved_cycles_limit: Option<candid::Nat>,
 }
 
 impl Payload<'_> for CanisterSettingsArgs {}
@@ -522,6 +1236,9 @@ impl CanisterSettingsArgs {
         compute_allocation: Option<u64>,
         memory_allocation: Option<u64>,
         freezing_threshold: Option<u64>,
+        ingress_priority_fee: Option<u128>,
+        wasm_memory_limit: Option<u64>,
+        reserved_cycles_limit: Option<u128>,
     ) -> Self {
         Self {
             controller,
@@ -529,6 +1246,9 @@ impl CanisterSettingsArgs {
             compute_allocation: compute_allocation.map(candid::Nat::from),
             memory_allocation: memory_allocation.map(candid::Nat::from),
             freezing_threshold: freezing_threshold.map(candid::Nat::from),
+            ingress_priority_fee: ingress_priority_fee.map(candid::Nat::from),
+            wasm_memory_limit: wasm_memory_limit.map(candid::Nat::from),
+            reserved_cycles_limit: reserved_cycles_limit.map(candid::Nat::from),
         }
     }
 }
@@ -3,11 +3,15 @@
 
 mod canister_http;
 mod ingress;
+mod query_stats;
 mod self_validating;
 mod xnet;
 
 pub use self::canister_http::{CanisterHttpPayload, MAX_CANISTER_HTTP_PAYLOAD_SIZE};
 pub use self::ingress::{IngressPayload, IngressPayloadError, InvalidIngressPayload};
+pub use self::query_stats::{
+    epoch_from_height, CanisterQueryStats, QueryStats, QueryStatsEpoch, QueryStatsPayload,
+};
 pub use self::self_validating::{SelfValidatingPayload, MAX_BITCOIN_PAYLOAD_IN_BYTES};
 pub use self::xnet::XNetPayload;
 
@@ -70,13 +74,15 @@ impl ValidationContext {
 
 /// The payload of a batch.
 ///
-/// Contains ingress messages, XNet messages and self-validating messages.
+/// Contains ingress messages, XNet messages, self-validating messages and
+/// the query statistics of the block maker.
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct BatchPayload {
     pub ingress: IngressPayload,
     pub xnet: XNetPayload,
     pub self_validating: SelfValidatingPayload,
     pub canister_http: CanisterHttpPayload,
+    pub query_stats: Option<QueryStatsPayload>,
 }
 
 /// Return ingress messages, xnet messages, and responses from the bitcoin adapter.
@@ -92,12 +98,14 @@ impl BatchPayload {
         xnet: XNetPayload,
         self_validating: SelfValidatingPayload,
         canister_http: CanisterHttpPayload,
+        query_stats: Option<QueryStatsPayload>,
     ) -> Self {
         BatchPayload {
             ingress,
             xnet,
             self_validating,
             canister_http,
+            query_stats,
         }
     }
 
@@ -118,6 +126,7 @@ impl BatchPayload {
             && self.xnet.stream_slices.is_empty()
             && self.self_validating.is_empty()
             && self.canister_http.is_empty()
+            && self.query_stats.is_none()
     }
 }
 #[cfg(test)]
@@ -0,0 +1,207 @@
+use crate::{node_id_into_protobuf, node_id_try_from_protobuf, CanisterId, CountBytes, NodeId};
+use ic_protobuf::types::v1 as pb;
+use phantom_newtype::AmountOf;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+pub struct QueryStatsEpochTag {}
+/// The index of a query stats epoch.
+///
+/// Query statistics are collected by each replica over a fixed number of
+/// certified heights (an epoch) and only become eligible for inclusion in a
+/// block once the epoch has ended.
+pub type QueryStatsEpoch = AmountOf<QueryStatsEpochTag, u64>;
+
+/// Returns the query stats epoch the given certified height belongs to.
+pub fn epoch_from_height(height: crate::Height, epoch_length: u64) -> QueryStatsEpoch {
+    QueryStatsEpoch::from(height.get() / epoch_length.max(1))
+}
+
+/// Statistics about the query calls a single replica executed on a single
+/// canister during one epoch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct QueryStats {
+    pub num_calls: u32,
+    pub num_instructions: u64,
+    pub ingress_payload_size: u64,
+    pub egress_payload_size: u64,
+}
+
+impl QueryStats {
+    /// Adds the statistics of `other` to `self`, saturating on overflow.
+    pub fn saturating_accumulate(&mut self, other: &QueryStats) {
+        self.num_calls = self.num_calls.saturating_add(other.num_calls);
+        self.num_instructions = self.num_instructions.saturating_add(other.num_instructions);
+        self.ingress_payload_size = self
+            .ingress_payload_size
+            .saturating_add(other.ingress_payload_size);
+        self.egress_payload_size = self
+            .egress_payload_size
+            .saturating_add(other.egress_payload_size);
+    }
+}
+
+/// The query statistics of a single canister.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanisterQueryStats {
+    pub canister_id: CanisterId,
+    pub stats: QueryStats,
+}
+
+/// Payload that contains the query statistics a replica collected during a
+/// completed epoch.
+///
+/// Every replica includes its own statistics when it is the block maker, so
+/// the statistics of each replica and epoch are delivered at most once.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct QueryStatsPayload {
+    /// The node that collected the statistics.
+    pub proposer: NodeId,
+    /// The epoch during which the statistics were collected.
+    pub epoch: QueryStatsEpoch,
+    /// The per-canister statistics, sorted by canister id.
+    pub stats: Vec<CanisterQueryStats>,
+}
+
+impl From<&QueryStatsPayload> for pb::QueryStatsPayload {
+    fn from(payload: &QueryStatsPayload) -> Self {
+        Self {
+            proposer: Some(node_id_into_protobuf(payload.proposer)),
+            epoch: payload.epoch.get(),
+            canister_stats: payload
+                .stats
+                .iter()
+                .map(|stats| pb::CanisterQueryStats {
+                    canister_id: Some(pb::CanisterId::from(stats.canister_id)),
+                    num_calls: stats.stats.num_calls,
+                    num_instructions: stats.stats.num_instructions,
+                    ingress_payload_size: stats.stats.ingress_payload_size,
+                    egress_payload_size: stats.stats.egress_payload_size,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<pb::QueryStatsPayload> for QueryStatsPayload {
+    type Error = String;
+
+    fn try_from(payload: pb::QueryStatsPayload) -> Result<Self, Self::Error> {
+        let proposer = node_id_try_from_protobuf(
+            payload
+                .proposer
+                .ok_or("Error: query_stats_payload does not contain a proposer")?,
+        )
+        .map_err(|err| format!("{:?}", err))?;
+        let stats = payload
+            .canister_stats
+            .into_iter()
+            .map(|stats| -> Result<CanisterQueryStats, String> {
+                let canister_id = stats
+                    .canister_id
+                    .ok_or_else(|| "No canister id on canister query stats".to_string())
+                    .and_then(|canister_id| {
+                        CanisterId::try_from(canister_id)
+                            .map_err(|e| format!("Proxy decode error {:?}", e))
+                    })?;
+                Ok(CanisterQueryStats {
+                    canister_id,
+                    stats: QueryStats {
+                        num_calls: stats.num_calls,
+                        num_instructions: stats.num_instructions,
+                        ingress_payload_size: stats.ingress_payload_size,
+                        egress_payload_size: stats.egress_payload_size,
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(QueryStatsPayload {
+            proposer,
+            epoch: QueryStatsEpoch::from(payload.epoch),
+            stats,
+        })
+    }
+}
+
+impl CountBytes for CanisterQueryStats {
+    fn count_bytes(&self) -> usize {
+        self.canister_id.get_ref().as_slice().len() + std::mem::size_of::<QueryStats>()
+    }
+}
+
+impl CountBytes for QueryStatsPayload {
+    fn count_bytes(&self) -> usize {
+        self.proposer.get_ref().as_slice().len()
+            + std::mem::size_of::<QueryStatsEpoch>()
+            + self
+                .stats
+                .iter()
+                .map(CountBytes::count_bytes)
+                .sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_base_types::PrincipalId;
+
+    /// Tests, whether a roundtrip of protobuf conversions generates the same
+    /// `QueryStatsPayload`
+    #[test]
+    fn into_query_stats_payload_and_back() {
+        let payload = QueryStatsPayload {
+            proposer: NodeId::from(PrincipalId::new_node_test_id(7)),
+            epoch: QueryStatsEpoch::from(3),
+            stats: vec![
+                CanisterQueryStats {
+                    canister_id: CanisterId::from_u64(1),
+                    stats: QueryStats {
+                        num_calls: 10,
+                        num_instructions: 1_000_000,
+                        ingress_payload_size: 512,
+                        egress_payload_size: 2048,
+                    },
+                },
+                CanisterQueryStats {
+                    canister_id: CanisterId::from_u64(42),
+                    stats: QueryStats {
+                        num_calls: 1,
+                        num_instructions: 5,
+                        ingress_payload_size: 0,
+                        egress_payload_size: 7,
+                    },
+                },
+            ],
+        };
+
+        let pb_payload = pb::QueryStatsPayload::from(&payload);
+        let new_payload = QueryStatsPayload::try_from(pb_payload).unwrap();
+        assert_eq!(payload, new_payload);
+    }
+
+    #[test]
+    fn saturating_accumulate_adds_all_fields() {
+        let mut stats = QueryStats {
+            num_calls: u32::MAX - 1,
+            num_instructions: 10,
+            ingress_payload_size: 20,
+            egress_payload_size: 30,
+        };
+        stats.saturating_accumulate(&QueryStats {
+            num_calls: 5,
+            num_instructions: 1,
+            ingress_payload_size: 2,
+            egress_payload_size: 3,
+        });
+        assert_eq!(
+            stats,
+            QueryStats {
+                num_calls: u32::MAX,
+                num_instructions: 11,
+                ingress_payload_size: 22,
+                egress_payload_size: 33,
+            }
+        );
+    }
+}
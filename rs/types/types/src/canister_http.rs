@@ -458,7 +458,7 @@ pub enum CanisterHttpResponseAttribute {
 
 #[cfg(test)]
 mod tests {
-    use crate::{time::UNIX_EPOCH, Cycles};
+    use crate::{messages::NO_DEADLINE, time::UNIX_EPOCH, Cycles};
 
     use super::*;
 
@@ -484,6 +484,7 @@ mod tests {
                 payment: Cycles::new(10),
                 method_name: "tansform".to_string(),
                 method_payload: Vec::new(),
+                deadline: NO_DEADLINE,
             },
             time: UNIX_EPOCH,
         };
@@ -524,6 +525,7 @@ mod tests {
                 payment: Cycles::new(10),
                 method_name: "tansform".to_string(),
                 method_payload: Vec::new(),
+                deadline: NO_DEADLINE,
             },
             time: UNIX_EPOCH,
         };
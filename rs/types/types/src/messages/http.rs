@@ -572,6 +572,24 @@ pub struct HttpQueryResponseReply {
     pub arg: Blob,
 }
 
+/// The response to a synchronous `/api/v3/canister/_/call` request whose
+/// message reached a terminal status before the request timed out.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[serde(tag = "status")]
+pub enum HttpCallResponse {
+    Replied {
+        /// The CBOR-encoded `Certificate` containing the `request_status` of
+        /// the message.
+        certificate: Blob,
+    },
+    Rejected {
+        /// The CBOR-encoded `Certificate` containing the `request_status` of
+        /// the message.
+        certificate: Blob,
+    },
+}
+
 /// The response to a `read_state` request.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct HttpReadStateResponse {
@@ -1,8 +1,11 @@
-use crate::{ingress::WasmResult, CanisterId, CountBytes, Cycles, Funds, NumBytes};
+use crate::{
+    ingress::WasmResult, time::CoarseTime, CanisterId, CountBytes, Cycles, Funds, NumBytes,
+};
 use ic_error_types::{RejectCode, TryFromError, UserError};
 use ic_ic00_types::{
-    CanisterIdRecord, InstallCodeArgs, Method, Payload as _, ProvisionalTopUpCanisterArgs,
-    SetControllerArgs, UpdateSettingsArgs,
+    CanisterIdRecord, CanisterInfoRequest, InstallChunkedCodeArgs, InstallCodeArgs, Method,
+    Payload as _, ProvisionalTopUpCanisterArgs, SetControllerArgs, UpdateSettingsArgs,
+    UploadChunkArgs,
 };
 use ic_protobuf::{
     proxy::{try_from_option_field, ProxyDecodeError},
@@ -34,6 +37,10 @@ pub enum CallContextIdTag {}
 /// Identifies an incoming call.
 pub type CallContextId = Id<CallContextIdTag, u64>;
 
+/// The deadline of a guaranteed response call, i.e. of a call that is not
+/// best-effort.
+pub const NO_DEADLINE: CoarseTime = CoarseTime::from_secs_since_unix_epoch(0);
+
 /// Canister-to-canister request message.
 #[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Request {
@@ -44,6 +51,14 @@ pub struct Request {
     pub method_name: String,
     #[serde(with = "serde_bytes")]
     pub method_payload: Vec<u8>,
+    /// The deadline of a best-effort call; `NO_DEADLINE` for guaranteed
+    /// response calls.
+    ///
+    /// Best-effort requests and their responses may be dropped by the system,
+    /// in which case the caller receives a `SYS_UNKNOWN` reject once the
+    /// deadline has expired.
+    #[serde(default)]
+    pub deadline: CoarseTime,
 }
 
 impl Request {
@@ -62,6 +77,11 @@ impl Request {
         &self.method_payload
     }
 
+    /// Returns `true` if this is the request of a best-effort call.
+    pub fn is_best_effort(&self) -> bool {
+        self.deadline != NO_DEADLINE
+    }
+
     /// Returns the size of the user-controlled part of this `Request`,
     /// in bytes.
     pub fn payload_size_bytes(&self) -> NumBytes {
@@ -78,6 +98,7 @@ impl Request {
             | Ok(Method::DeleteCanister)
             | Ok(Method::UninstallCode)
             | Ok(Method::DepositCycles)
+            | Ok(Method::ClearChunkStore)
             | Ok(Method::StopCanister) => match CanisterIdRecord::decode(&self.method_payload) {
                 Ok(record) => Some(record.get_canister_id()),
                 Err(_) => None,
@@ -94,6 +115,20 @@ impl Request {
                 Ok(record) => Some(record.get_canister_id()),
                 Err(_) => None,
             },
+            Ok(Method::InstallChunkedCode) => {
+                match InstallChunkedCodeArgs::decode(&self.method_payload) {
+                    Ok(record) => Some(record.get_canister_id()),
+                    Err(_) => None,
+                }
+            }
+            Ok(Method::CanisterInfo) => match CanisterInfoRequest::decode(&self.method_payload) {
+                Ok(record) => Some(record.canister_id()),
+                Err(_) => None,
+            },
+            Ok(Method::UploadChunk) => match UploadChunkArgs::decode(&self.method_payload) {
+                Ok(record) => Some(record.get_canister_id()),
+                Err(_) => None,
+            },
             Ok(Method::ProvisionalTopUpCanister) => {
                 match ProvisionalTopUpCanisterArgs::decode(&self.method_payload) {
                     Ok(record) => Some(record.get_canister_id()),
@@ -131,6 +166,9 @@ impl std::fmt::Debug for Request {
             self.sender_reply_callback
         )?;
         write!(f, "payment: {:?}, ", self.payment)?;
+        if self.is_best_effort() {
+            write!(f, "deadline: {:?}, ", self.deadline)?;
+        }
         if self.method_name.len() <= 103 {
             write!(f, "method_name: {:?}, ", self.method_name)?;
         } else {
@@ -270,6 +308,10 @@ pub struct Response {
     pub originator_reply_callback: CallbackId,
     pub refund: Cycles,
     pub response_payload: Payload,
+    /// The deadline of the originating `Request`; `NO_DEADLINE` for guaranteed
+    /// responses.
+    #[serde(default)]
+    pub deadline: CoarseTime,
 }
 
 impl Response {
@@ -277,6 +319,11 @@ impl Response {
     pub fn payload_size_bytes(&self) -> NumBytes {
         self.response_payload.size_bytes()
     }
+
+    /// Returns `true` if this is the response to a best-effort call.
+    pub fn is_best_effort(&self) -> bool {
+        self.deadline != NO_DEADLINE
+    }
 }
 
 /// Canister-to-canister message.
@@ -323,6 +370,14 @@ impl RequestOrResponse {
             RequestOrResponse::Response(resp) => resp.refund,
         }
     }
+
+    /// Returns `true` if this message belongs to a best-effort call.
+    pub fn is_best_effort(&self) -> bool {
+        match self {
+            RequestOrResponse::Request(req) => req.is_best_effort(),
+            RequestOrResponse::Response(resp) => resp.is_best_effort(),
+        }
+    }
 }
 
 /// Convenience `CountBytes` implementation that returns the same value as
@@ -381,6 +436,7 @@ impl From<&Request> for pb_queues::Request {
             method_name: req.method_name.clone(),
             method_payload: req.method_payload.clone(),
             cycles_payment: Some((req.payment).into()),
+            deadline_seconds: req.deadline.as_secs_since_unix_epoch(),
         }
     }
 }
@@ -404,6 +460,7 @@ impl TryFrom<pb_queues::Request> for Request {
             payment,
             method_name: req.method_name,
             method_payload: req.method_payload,
+            deadline: CoarseTime::from_secs_since_unix_epoch(req.deadline_seconds),
         })
     }
 }
@@ -446,6 +503,7 @@ impl From<&Response> for pb_queues::Response {
             refund: Some((&Funds::new(rep.refund)).into()),
             response_payload: Some(p),
             cycles_refund: Some((rep.refund).into()),
+            deadline_seconds: rep.deadline.as_secs_since_unix_epoch(),
         }
     }
 }
@@ -476,6 +534,7 @@ impl TryFrom<pb_queues::Response> for Response {
             originator_reply_callback: rep.originator_reply_callback.into(),
             refund,
             response_payload,
+            deadline: CoarseTime::from_secs_since_unix_epoch(rep.deadline_seconds),
         })
     }
 }
@@ -104,6 +104,45 @@ impl fmt::Display for Time {
     }
 }
 
+/// Time since UNIX_EPOCH, in whole seconds.
+///
+/// Used where a coarse resolution is sufficient and a compact encoding is
+/// desirable, e.g. for the deadlines of best-effort inter-canister messages.
+#[derive(
+    Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize,
+)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct CoarseTime(u32);
+
+impl CoarseTime {
+    pub const fn from_secs_since_unix_epoch(secs: u32) -> Self {
+        Self(secs)
+    }
+
+    pub fn as_secs_since_unix_epoch(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns the `CoarseTime` that `time` falls into, i.e. `time` rounded
+    /// down to whole seconds. Saturates at `u32::MAX` seconds.
+    pub fn floor(time: Time) -> Self {
+        let secs = time.as_nanos_since_unix_epoch() / 1_000_000_000;
+        Self(secs.min(u32::MAX as u64) as u32)
+    }
+
+    /// Returns the `CoarseTime` `secs` seconds after `self`, saturating at
+    /// `u32::MAX` seconds.
+    pub fn saturating_add_secs(&self, secs: u32) -> Self {
+        Self(self.0.saturating_add(secs))
+    }
+}
+
+impl From<CoarseTime> for Time {
+    fn from(coarse: CoarseTime) -> Self {
+        Time::from_nanos_since_unix_epoch(coarse.0 as u64 * 1_000_000_000)
+    }
+}
+
 /// Returns the current time.
 ///
 /// WARNING: this function should not be used in any deterministic part of the